@@ -93,6 +93,9 @@ fn criterion_benchmark(c: &mut Criterion) {
                 runtime_context
                     .execute_pipeline(source, operators, sink)
                     .await
+                    .unwrap()
+                    .await_completion()
+                    .await
                     .unwrap();
             })
         });
@@ -38,7 +38,7 @@ impl<T> CollectionSink<T> {
 #[async_trait]
 impl<T> Sink<T> for CollectionSink<T>
 where
-    T: Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
 {
     async fn init(&mut self) -> StreamResult<()> {
         Ok(())
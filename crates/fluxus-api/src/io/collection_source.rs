@@ -1,18 +1,33 @@
 use async_trait::async_trait;
-use fluxus_sources::Source;
+use fluxus_sources::{Progress, Source, SourceProgress};
 use fluxus_utils::models::{Record, StreamResult};
 use fluxus_utils::time::current_time;
 use std::collections::VecDeque;
 
 /// A source that produces elements from a collection
 pub struct CollectionSource<T> {
-    data: VecDeque<T>,
+    data: VecDeque<(Option<i64>, T)>,
+    total: usize,
 }
 
 impl<T> CollectionSource<T> {
     pub fn new(data: impl IntoIterator<Item = T>) -> Self {
+        let data: VecDeque<_> = data.into_iter().map(|data| (None, data)).collect();
         Self {
-            data: data.into_iter().collect(),
+            total: data.len(),
+            data,
+        }
+    }
+
+    /// Create a source whose records carry the given timestamps (in milliseconds)
+    pub fn with_timestamps(data: impl IntoIterator<Item = (i64, T)>) -> Self {
+        let data: VecDeque<_> = data
+            .into_iter()
+            .map(|(ts, data)| (Some(ts), data))
+            .collect();
+        Self {
+            total: data.len(),
+            data,
         }
     }
 }
@@ -20,7 +35,7 @@ impl<T> CollectionSource<T> {
 #[async_trait]
 impl<T> Source<T> for CollectionSource<T>
 where
-    T: Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
 {
     async fn init(&mut self) -> StreamResult<()> {
         Ok(())
@@ -28,9 +43,9 @@ where
 
     async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
         let value = self.data.pop_front();
-        Ok(value.map(|data| Record {
+        Ok(value.map(|(timestamp, data)| Record {
             data,
-            timestamp: current_time() as i64,
+            timestamp: timestamp.unwrap_or_else(|| current_time() as i64),
         }))
     }
 
@@ -38,3 +53,10 @@ where
         Ok(())
     }
 }
+
+impl<T> SourceProgress for CollectionSource<T> {
+    fn progress(&self) -> Progress {
+        let total = self.total as u64;
+        Progress::new(total - self.data.len() as u64, Some(total))
+    }
+}
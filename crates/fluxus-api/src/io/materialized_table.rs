@@ -0,0 +1,69 @@
+use fluxus_runtime::state::KeyedStateBackend;
+use std::hash::Hash;
+
+/// The latest element per key of a stream, queryable while the stream runs.
+///
+/// Clones share the same table, so one clone can be passed to
+/// [`DataStream::materialize`](crate::DataStream::materialize) and another
+/// used to serve lookups.
+pub struct MaterializedTable<K, V> {
+    state: KeyedStateBackend<K, V>,
+}
+
+impl<K, V> Clone for MaterializedTable<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V> Default for MaterializedTable<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> MaterializedTable<K, V>
+where
+    K: Eq + Hash,
+{
+    pub fn new() -> Self {
+        Self {
+            state: KeyedStateBackend::new(),
+        }
+    }
+
+    /// The latest value of a key
+    pub fn get(&self, key: &K) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.state.get(key)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.state.contains_key(key)
+    }
+
+    /// All keys with their latest values, in no particular order
+    pub fn scan(&self) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.state.entries()
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.is_empty()
+    }
+
+    pub(crate) fn upsert(&self, key: K, value: V) {
+        self.state.set(key, value);
+    }
+}
@@ -1,5 +1,7 @@
 mod collection_sink;
 mod collection_source;
+mod materialized_table;
 
 pub use collection_sink::CollectionSink;
 pub use collection_source::CollectionSource;
+pub use materialized_table::MaterializedTable;
@@ -5,6 +5,10 @@
 pub mod io;
 pub mod operators;
 pub mod stream;
+pub mod testing;
 
-pub use io::{CollectionSink, CollectionSource};
-pub use stream::{DataStream, WindowedStream};
+pub use io::{CollectionSink, CollectionSource, MaterializedTable};
+pub use stream::{
+    BroadcastConnectedStream, BroadcastStream, DataStream, GroupedWindowedStream, KeyedStream,
+    KeyedWindowedStream, WindowedStream,
+};
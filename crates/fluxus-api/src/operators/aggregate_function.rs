@@ -0,0 +1,20 @@
+/// An aggregation of the elements of a window that can run partially on
+/// several tasks, whose accumulators are merged when the window fires, see
+/// [`WindowedStream::aggregate_function`](crate::WindowedStream::aggregate_function).
+///
+/// `add` and `merge` must agree: merging the accumulators of any split of
+/// the elements must give the accumulator of all elements, so that the
+/// result does not depend on the parallelism.
+pub trait AggregateFunction<In, Acc, Out>: Send + Sync {
+    /// The accumulator of a window without elements
+    fn create_accumulator(&self) -> Acc;
+
+    /// Add an element to an accumulator
+    fn add(&self, acc: Acc, value: In) -> Acc;
+
+    /// Combine the accumulators of the same window from two tasks
+    fn merge(&self, a: Acc, b: Acc) -> Acc;
+
+    /// The result of a window from its accumulator
+    fn get_result(&self, acc: Acc) -> Out;
+}
@@ -0,0 +1,201 @@
+use async_trait::async_trait;
+use fluxus_core::Gauge;
+use fluxus_transformers::Operator;
+use fluxus_utils::{
+    models::{Record, StreamResult},
+    window::{Window, WindowAssigner, WindowConfig, WindowedValue},
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Folds the records of each window into an aggregate, with the windows of
+/// each record given by a [`WindowAssigner`].
+///
+/// The aggregate of a window is emitted once the watermark, the latest
+/// timestamp minus the watermark delay, passes the end of the window, or
+/// when the input ends. Until the watermark also passes the allowed
+/// lateness, late records still update the window and its aggregate is
+/// emitted again; records later than that are dropped. Windows without an
+/// end are emitted when the input ends.
+pub struct AssignedWindowAggregator<T, A, F> {
+    window_config: WindowConfig,
+    assigner: Arc<dyn WindowAssigner<T>>,
+    init: A,
+    f: F,
+    open: HashMap<Window, A>,
+    ended: HashMap<Window, A>,
+    max_timestamp: Option<i64>,
+    live_windows: Option<Arc<Gauge>>,
+}
+
+impl<T, A, F> AssignedWindowAggregator<T, A, F>
+where
+    A: Clone,
+    F: Fn(A, T) -> A,
+{
+    pub fn new(
+        window_config: WindowConfig,
+        assigner: Arc<dyn WindowAssigner<T>>,
+        init: A,
+        f: F,
+    ) -> Self {
+        Self {
+            window_config,
+            assigner,
+            init,
+            f,
+            open: HashMap::new(),
+            ended: HashMap::new(),
+            max_timestamp: None,
+            live_windows: None,
+        }
+    }
+
+    /// Publish the number of windows that hold state to a gauge
+    pub fn live_windows(mut self, gauge: Arc<Gauge>) -> Self {
+        self.live_windows = Some(gauge);
+        self
+    }
+
+    fn publish_live_windows(&self) {
+        if let Some(gauge) = &self.live_windows {
+            gauge.set((self.open.len() + self.ended.len()) as i64);
+        }
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        self.max_timestamp
+            .map(|max| max - self.window_config.watermark_delay.as_millis() as i64)
+    }
+
+    /// Whether a window can no longer change at the given time
+    fn is_closed(&self, window: &Window, time: i64) -> bool {
+        let lateness = self.window_config.allow_lateness.as_millis() as i64;
+        window.end.is_some_and(|end| end + lateness <= time)
+    }
+
+    fn result(&self, window: Window, aggregate: A) -> Record<WindowedValue<A>> {
+        let timestamp = window
+            .end
+            .map(|end| end - 1)
+            .or(self.max_timestamp)
+            .unwrap_or_default();
+        Record::with_timestamp(
+            WindowedValue {
+                start: window.start,
+                end: window.end,
+                value: aggregate,
+                is_final: true,
+            },
+            timestamp,
+        )
+    }
+
+    /// Fold a record into its windows and return the aggregates of the
+    /// windows that ended or were updated after they ended
+    fn on_record(&mut self, record: Record<T>) -> Vec<Record<WindowedValue<A>>>
+    where
+        T: Clone,
+    {
+        let timestamp = record.timestamp;
+        let windows: Vec<Window> = self
+            .assigner
+            .assign(&record.data, timestamp)
+            .into_iter()
+            .filter(|window| {
+                self.watermark()
+                    .is_none_or(|watermark| !self.is_closed(window, watermark))
+            })
+            .collect();
+        if windows.is_empty() {
+            tracing::debug!("Record at {} is too late for its windows", timestamp);
+        }
+
+        let mut results = Vec::new();
+        for window in windows {
+            let (windows, ended) = match self.ended.contains_key(&window) {
+                true => (&mut self.ended, true),
+                false => (&mut self.open, false),
+            };
+            // The accumulator is moved through `f` instead of being cloned
+            let current = windows.remove(&window).unwrap_or_else(|| self.init.clone());
+            let aggregate = (self.f)(current, record.data.clone());
+            windows.insert(window, aggregate.clone());
+            if ended {
+                results.push(self.result(window, aggregate));
+            }
+        }
+
+        self.max_timestamp = Some(
+            self.max_timestamp
+                .map_or(timestamp, |max| max.max(timestamp)),
+        );
+        if let Some(watermark) = self.watermark() {
+            results.extend(self.advance(watermark));
+        }
+        results
+    }
+
+    /// End the windows the watermark passed, in order of their end, and drop
+    /// the windows past their allowed lateness
+    fn advance(&mut self, watermark: i64) -> Vec<Record<WindowedValue<A>>> {
+        let mut ended: Vec<Window> = self
+            .open
+            .keys()
+            .filter(|window| window.end.is_some_and(|end| end <= watermark))
+            .copied()
+            .collect();
+        ended.sort_by_key(|window| (window.end, window.start));
+
+        let mut results = Vec::new();
+        for window in ended {
+            if let Some(aggregate) = self.open.remove(&window) {
+                results.push(self.result(window, aggregate.clone()));
+                self.ended.insert(window, aggregate);
+            }
+        }
+        let lateness = self.window_config.allow_lateness.as_millis() as i64;
+        self.ended
+            .retain(|window, _| window.end.is_none_or(|end| end + lateness > watermark));
+        results
+    }
+
+    /// Emit the windows that are still open and drop all state
+    fn end_of_input(&mut self) -> Vec<Record<WindowedValue<A>>> {
+        let mut open: Vec<(Window, A)> = self.open.drain().collect();
+        open.sort_by_key(|(window, _)| (window.end.is_none(), window.end, window.start));
+        self.ended.clear();
+        open.into_iter()
+            .map(|(window, aggregate)| self.result(window, aggregate))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<T, A, F> Operator<T, WindowedValue<A>> for AssignedWindowAggregator<T, A, F>
+where
+    T: Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<WindowedValue<A>>>> {
+        let results = self.on_record(record);
+        self.publish_live_windows();
+        Ok(results)
+    }
+
+    async fn on_watermark(
+        &mut self,
+        watermark: i64,
+    ) -> StreamResult<Vec<Record<WindowedValue<A>>>> {
+        let results = self.advance(watermark);
+        self.publish_live_windows();
+        Ok(results)
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<WindowedValue<A>>>> {
+        let results = self.end_of_input();
+        self.publish_live_windows();
+        Ok(results)
+    }
+}
@@ -0,0 +1,184 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::{
+    models::{Record, StreamResult},
+    window::WindowedValue,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::keyed_window_aggregator::{KeyedWindows, without_windows};
+
+type KeyFn<T, K> = Arc<dyn Fn(&T) -> K + Send + Sync>;
+
+/// The open window of a key
+struct Batch<A> {
+    id: u64,
+    start: i64,
+    last: i64,
+    size: usize,
+    aggregate: Option<A>,
+}
+
+/// Folds the records of each key into windows of up to `count` records that
+/// also close once `duration` passed since their first record, whichever
+/// comes first, emitting a `(key, aggregate)` record when a window closes.
+///
+/// Time is event time: a window closes once the watermark of the stream, the
+/// latest timestamp minus `watermark_delay`, reaches `duration` after its
+/// first record, before the record that moved the watermark is added. The
+/// windows that are still open are closed when the input ends.
+pub struct CountOrTimeAggregator<T, K, A, F> {
+    count: usize,
+    duration: i64,
+    watermark_delay: i64,
+    key: KeyFn<T, K>,
+    init: A,
+    f: F,
+    batches: HashMap<K, Batch<A>>,
+    /// Keys of the open windows by the time they close at and their id
+    deadlines: BTreeMap<(i64, u64), K>,
+    next_id: u64,
+    max_timestamp: Option<i64>,
+}
+
+impl<T, K, A, F> CountOrTimeAggregator<T, K, A, F>
+where
+    K: Eq + Hash + Clone,
+    A: Clone,
+    F: Fn(A, T) -> A,
+{
+    pub fn new(
+        count: usize,
+        duration: Duration,
+        watermark_delay: Duration,
+        key: KeyFn<T, K>,
+        init: A,
+        f: F,
+    ) -> Self {
+        Self {
+            count: count.max(1),
+            duration: duration.as_millis() as i64,
+            watermark_delay: watermark_delay.as_millis() as i64,
+            key,
+            init,
+            f,
+            batches: HashMap::new(),
+            deadlines: BTreeMap::new(),
+            next_id: 0,
+            max_timestamp: None,
+        }
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        self.max_timestamp.map(|max| max - self.watermark_delay)
+    }
+
+    fn close(&mut self, key: &K) -> Option<Record<WindowedValue<(K, A)>>> {
+        let batch = self.batches.remove(key)?;
+        self.deadlines
+            .remove(&(batch.start + self.duration, batch.id));
+        Some(Record::with_timestamp(
+            WindowedValue {
+                start: Some(batch.start),
+                end: Some(batch.start + self.duration),
+                value: (
+                    key.clone(),
+                    batch.aggregate.unwrap_or_else(|| self.init.clone()),
+                ),
+                is_final: true,
+            },
+            batch.last,
+        ))
+    }
+
+    fn on_record(&mut self, record: Record<T>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        let timestamp = record.timestamp;
+        let key = (self.key)(&record.data);
+
+        // Close the windows whose time is up before adding the record
+        self.max_timestamp = Some(
+            self.max_timestamp
+                .map_or(timestamp, |max| max.max(timestamp)),
+        );
+        let mut results = match self.watermark() {
+            Some(watermark) => self.fire(Some(watermark)),
+            None => Vec::new(),
+        };
+
+        let id = self.next_id + 1;
+        let batch = self.batches.entry(key.clone()).or_insert_with(|| Batch {
+            id,
+            start: timestamp,
+            last: timestamp,
+            size: 0,
+            aggregate: None,
+        });
+        if batch.id == id {
+            self.next_id = id;
+            self.deadlines
+                .insert((timestamp + self.duration, id), key.clone());
+        }
+        batch.last = batch.last.max(timestamp);
+        batch.size += 1;
+        // The accumulator is moved through `f` instead of being cloned
+        let current = batch.aggregate.take().unwrap_or_else(|| self.init.clone());
+        batch.aggregate = Some((self.f)(current, record.data));
+
+        if batch.size >= self.count {
+            results.extend(self.close(&key));
+        }
+        results
+    }
+
+    /// Close the windows whose time is up at `time`, or all windows if it is
+    /// `None`, in the order they close
+    fn fire(&mut self, time: Option<i64>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        let expired: Vec<K> = self
+            .deadlines
+            .iter()
+            .take_while(|((deadline, _), _)| time.is_none_or(|time| *deadline <= time))
+            .map(|(_, key)| key.clone())
+            .collect();
+        expired.iter().filter_map(|key| self.close(key)).collect()
+    }
+}
+
+impl<T, K, A, F> KeyedWindows<T, K, A> for CountOrTimeAggregator<T, K, A, F>
+where
+    T: Send + Sync,
+    K: Eq + Hash + Clone + Send + Sync,
+    A: Clone + Send + Sync,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    fn on_record(&mut self, record: Record<T>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        CountOrTimeAggregator::on_record(self, record)
+    }
+
+    fn fire(&mut self, time: Option<i64>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        CountOrTimeAggregator::fire(self, time)
+    }
+}
+
+#[async_trait]
+impl<T, K, A, F> Operator<T, (K, A)> for CountOrTimeAggregator<T, K, A, F>
+where
+    T: Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(self.on_record(record)))
+    }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(self.fire(Some(watermark))))
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(self.fire(None)))
+    }
+}
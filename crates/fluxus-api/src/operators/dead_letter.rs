@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use fluxus_sinks::Sink;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use std::fmt::Display;
+use std::marker::PhantomData;
+
+use super::side_output::SideOutput;
+
+/// A failed record written to a dead-letter sink
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadLetter<I> {
+    /// The input record that failed
+    pub payload: I,
+    /// The error rendered as a string
+    pub error: String,
+}
+
+/// Passes `Ok` values downstream and writes the failed input of `Err` values
+/// to a dead-letter sink
+pub struct DeadLetterRouter<T, I, E, K> {
+    dlq: SideOutput<DeadLetter<I>, K>,
+    _phantom: PhantomData<(T, E)>,
+}
+
+impl<T, I, E, K> DeadLetterRouter<T, I, E, K>
+where
+    I: Send,
+    K: Sink<DeadLetter<I>> + Send,
+{
+    pub fn new(dlq: K) -> Self {
+        Self {
+            dlq: SideOutput::new(dlq),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, I, E, K> Operator<Result<T, (I, E)>, T> for DeadLetterRouter<T, I, E, K>
+where
+    T: Clone + Send + Sync + 'static,
+    I: Clone + Send + Sync + 'static,
+    E: Display + Clone + Send + Sync + 'static,
+    K: Sink<DeadLetter<I>> + Send + Sync,
+{
+    async fn process(&mut self, record: Record<Result<T, (I, E)>>) -> StreamResult<Vec<Record<T>>> {
+        match record.data {
+            Ok(data) => Ok(vec![Record::with_timestamp(data, record.timestamp)]),
+            Err((payload, error)) => {
+                let error = error.to_string();
+                tracing::warn!("Routing record to dead-letter sink: {}", error);
+                self.dlq
+                    .emit(Record::with_timestamp(
+                        DeadLetter { payload, error },
+                        record.timestamp,
+                    ))
+                    .await?;
+                Ok(vec![])
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.dlq.close().await
+    }
+}
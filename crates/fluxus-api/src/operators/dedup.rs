@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Drops records equal to the previous record of the same key
+pub struct DedupOperator<T, K, F> {
+    key_fn: F,
+    previous: HashMap<K, T>,
+}
+
+impl<T, K, F> DedupOperator<T, K, F>
+where
+    K: Eq + Hash,
+    F: Fn(&T) -> K,
+{
+    pub fn new(key_fn: F) -> Self {
+        Self {
+            key_fn,
+            previous: HashMap::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl<T, K, F> Operator<T, T> for DedupOperator<T, K, F>
+where
+    T: PartialEq + Clone + Send + Sync + 'static,
+    K: Eq + Hash + Send + Sync + 'static,
+    F: Fn(&T) -> K + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<T>>> {
+        let key = (self.key_fn)(&record.data);
+        match self.previous.get_mut(&key) {
+            Some(previous) if *previous == record.data => Ok(vec![]),
+            Some(previous) => {
+                *previous = record.data.clone();
+                Ok(vec![record])
+            }
+            None => {
+                self.previous.insert(key, record.data.clone());
+                Ok(vec![record])
+            }
+        }
+    }
+}
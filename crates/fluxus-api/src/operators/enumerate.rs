@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+/// Pairs every record with a sequence number starting at zero.
+///
+/// The counter is shared between clones, so parallel instances of the
+/// operator never hand out the same number twice.
+#[derive(Clone, Default)]
+pub struct EnumerateOperator {
+    next: Arc<AtomicU64>,
+}
+
+impl EnumerateOperator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl<T> Operator<T, (u64, T)> for EnumerateOperator
+where
+    T: Send + Sync + 'static,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<(u64, T)>>> {
+        let index = self.next.fetch_add(1, Ordering::SeqCst);
+        Ok(vec![Record::with_timestamp(
+            (index, record.data),
+            record.timestamp,
+        )])
+    }
+}
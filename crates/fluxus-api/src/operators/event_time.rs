@@ -0,0 +1,137 @@
+use async_trait::async_trait;
+use fluxus_runtime::watermark::{WatermarkGenerator, WatermarkPolicy, WatermarkStrategy};
+use fluxus_sources::Source;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use std::marker::PhantomData;
+
+/// Replaces the timestamp of every record with one extracted from its payload
+pub struct TimestampAssigner<T, F> {
+    f: F,
+    _phantom: PhantomData<T>,
+}
+
+impl<T, F> TimestampAssigner<T, F>
+where
+    F: Fn(&T) -> i64,
+{
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, F> Operator<T, T> for TimestampAssigner<T, F>
+where
+    T: Send + Sync + 'static,
+    F: Fn(&T) -> i64 + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<T>>> {
+        let timestamp = (self.f)(&record.data);
+        Ok(vec![Record::with_timestamp(record.data, timestamp)])
+    }
+}
+
+/// Tracks the event-time watermark and drops records that arrive behind it
+pub struct WatermarkOperator<T> {
+    generator: WatermarkGenerator,
+    _phantom: PhantomData<T>,
+}
+
+impl<T> WatermarkOperator<T> {
+    pub fn new(strategy: WatermarkStrategy) -> Self {
+        Self {
+            generator: WatermarkGenerator::new(strategy),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T> Operator<T, T> for WatermarkOperator<T>
+where
+    T: Send + Sync + 'static,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<T>>> {
+        if self.generator.is_late(record.timestamp) {
+            tracing::debug!(
+                "Dropping record at {} behind watermark {:?}",
+                record.timestamp,
+                self.generator.current()
+            );
+            return Ok(Vec::new());
+        }
+        self.generator.on_event(record.timestamp);
+        Ok(vec![record])
+    }
+}
+
+/// Tracks the event-time watermark of a source with a [`WatermarkPolicy`],
+/// dropping records that arrive behind it. The watermark is passed to the
+/// [`on_watermark`](Operator::on_watermark) of the operators downstream as
+/// it advances.
+pub struct WatermarkSource<S, P> {
+    inner: S,
+    policy: P,
+}
+
+impl<S, P> WatermarkSource<S, P> {
+    pub fn new(inner: S, policy: P) -> Self {
+        Self { inner, policy }
+    }
+}
+
+#[async_trait]
+impl<T, S, P> Source<T> for WatermarkSource<S, P>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + Sync,
+    P: WatermarkPolicy<T>,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        self.inner.init().await
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        while let Some(record) = self.inner.next().await? {
+            let watermark = self.policy.current();
+            if watermark.is_some_and(|watermark| record.timestamp < watermark) {
+                tracing::debug!(
+                    "Dropping record at {} behind watermark {:?}",
+                    record.timestamp,
+                    watermark
+                );
+                continue;
+            }
+            self.policy.on_event(&record.data, record.timestamp);
+            return Ok(Some(record));
+        }
+        Ok(None)
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.inner.close().await
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        self.policy.current()
+    }
+}
+
+/// Wraps every record in a record with the same timestamp, so that later
+/// operators see the timestamps of the records they collect
+pub(crate) struct RecordsOperator;
+
+#[async_trait]
+impl<T> Operator<T, Record<T>> for RecordsOperator
+where
+    T: Send + Sync + 'static,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<Record<T>>>> {
+        let timestamp = record.timestamp;
+        Ok(vec![Record::with_timestamp(record, timestamp)])
+    }
+}
@@ -23,7 +23,7 @@ where
 #[async_trait]
 impl<T, F> Operator<T, T> for FilterOperator<T, F>
 where
-    T: Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
     F: Fn(&T) -> bool + Send + Sync,
 {
     async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<T>>> {
@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use fluxus_transformers::Operator;
 use fluxus_utils::models::{Record, StreamResult};
+use std::future::Future;
 use std::marker::PhantomData;
 
 pub struct FlatMapOperator<T, R, F, I>
@@ -28,8 +29,8 @@ where
 #[async_trait]
 impl<T, R, F, I> Operator<T, R> for FlatMapOperator<T, R, F, I>
 where
-    T: Clone + Send + Sync + 'static,
-    R: Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
     F: Fn(T) -> I + Send + Sync,
     I: IntoIterator<Item = R>,
 {
@@ -42,3 +43,43 @@ where
             .collect())
     }
 }
+
+/// Expands every record into zero or more records computed asynchronously,
+/// e.g. the entries of an archive downloaded from a URL
+pub struct FlatMapAsyncOperator<T, R, F> {
+    f: F,
+    _phantom: PhantomData<(T, R)>,
+}
+
+impl<T, R, F, Fut, I> FlatMapAsyncOperator<T, R, F>
+where
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = I>,
+    I: IntoIterator<Item = R>,
+{
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, R, F, Fut, I> Operator<T, R> for FlatMapAsyncOperator<T, R, F>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+    F: Fn(T) -> Fut + Send + Sync,
+    Fut: Future<Output = I> + Send,
+    I: IntoIterator<Item = R> + Send,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<R>>> {
+        let Record { data, timestamp } = record;
+        let result = (self.f)(data).await;
+        Ok(result
+            .into_iter()
+            .map(|r| Record::with_timestamp(r, timestamp))
+            .collect())
+    }
+}
@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::marker::PhantomData;
+
+/// Fails the pipeline with a configuration error when it starts, for
+/// combinations of settings that are only known to be invalid once the
+/// stream is built
+pub(crate) struct InvalidConfig<Out> {
+    message: String,
+    _marker: PhantomData<fn() -> Out>,
+}
+
+impl<Out> InvalidConfig<Out> {
+    pub(crate) fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<In, Out> Operator<In, Out> for InvalidConfig<Out>
+where
+    In: Send + Sync + 'static,
+    Out: Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        Err(StreamError::Config(self.message.clone()))
+    }
+
+    async fn process(&mut self, _record: Record<In>) -> StreamResult<Vec<Record<Out>>> {
+        Err(StreamError::Config(self.message.clone()))
+    }
+}
@@ -0,0 +1,333 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::{
+    models::{Record, StreamResult},
+    time::current_time,
+    window::{WindowConfig, WindowedValue},
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::Hash;
+use std::sync::Arc;
+
+use super::window_aggregator::by_start;
+
+type KeyFn<T, K> = Arc<dyn Fn(&T) -> K + Send + Sync>;
+
+/// Folds the records of each key and window into an aggregate, emitting
+/// `(key, aggregate)` records.
+///
+/// Every key has its own windows, which end together at the watermark of
+/// the stream, the latest timestamp minus the watermark delay, at a
+/// processing-time window trigger, or when the input ends. Until the
+/// watermark also passes the allowed lateness, late records update their
+/// key's window and its aggregate is emitted again; later records are dropped.
+///
+/// With a [`state_ttl`](WindowConfig::state_ttl), the windows of keys that
+/// received no records for the TTL are dropped as the watermark passes it.
+pub struct KeyedWindowAggregator<T, K, A, F> {
+    window_config: WindowConfig,
+    key: KeyFn<T, K>,
+    init: A,
+    f: F,
+    state: HashMap<(K, u64), A>,
+    /// Keys with state in each window that has not ended, in order of arrival
+    open: BTreeMap<u64, Vec<K>>,
+    /// Keys with state in each window that ended but accepts late records
+    ended: BTreeMap<u64, Vec<K>>,
+    max_timestamp: Option<i64>,
+    /// Latest timestamp of each key, when the state has a TTL
+    last_seen: HashMap<K, i64>,
+    /// Earliest watermark at which a key may expire
+    next_expiry: Option<i64>,
+}
+
+impl<T, K, A, F> KeyedWindowAggregator<T, K, A, F>
+where
+    K: Eq + Hash + Clone,
+    A: Clone,
+    F: Fn(A, T) -> A,
+{
+    pub fn new(window_config: WindowConfig, key: KeyFn<T, K>, init: A, f: F) -> Self {
+        Self {
+            window_config,
+            key,
+            init,
+            f,
+            state: HashMap::new(),
+            open: BTreeMap::new(),
+            ended: BTreeMap::new(),
+            max_timestamp: None,
+            last_seen: HashMap::new(),
+            next_expiry: None,
+        }
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        self.max_timestamp
+            .map(|max| max - self.window_config.watermark_delay.as_millis() as i64)
+    }
+
+    fn window_end(&self, window: u64) -> Option<i64> {
+        self.window_config.window_type.window_end(window as i64)
+    }
+
+    fn is_closed(&self, window: u64, time: i64) -> bool {
+        let lateness = self.window_config.allow_lateness.as_millis() as i64;
+        self.window_end(window)
+            .is_some_and(|end| end + lateness <= time)
+    }
+
+    /// Timestamp of the aggregate of a window, the last millisecond it covers
+    fn window_timestamp(&self, window: u64) -> i64 {
+        self.window_end(window)
+            .map(|end| end - 1)
+            .or(self.max_timestamp)
+            .unwrap_or_default()
+    }
+
+    fn emit(&self, key: &K, window: u64) -> Option<Record<WindowedValue<(K, A)>>> {
+        let aggregate = self.state.get(&(key.clone(), window))?.clone();
+        Some(Record::with_timestamp(
+            WindowedValue::new(
+                &self.window_config.window_type,
+                window,
+                (key.clone(), aggregate),
+            ),
+            self.window_timestamp(window),
+        ))
+    }
+
+    fn on_record(&mut self, record: Record<T>) -> Vec<Record<WindowedValue<(K, A)>>>
+    where
+        T: Clone,
+    {
+        let timestamp = record.timestamp;
+        let key = (self.key)(&record.data);
+        let watermark = self.watermark();
+        let windows: Vec<u64> = self
+            .window_config
+            .get_window_keys(timestamp)
+            .into_iter()
+            .filter(|window| watermark.is_none_or(|watermark| !self.is_closed(*window, watermark)))
+            .collect();
+        if windows.is_empty() {
+            tracing::debug!("Record at {} is too late for its windows", timestamp);
+            return Vec::new();
+        }
+
+        let mut results = Vec::new();
+        for window in windows {
+            let state_key = (key.clone(), window);
+            let current = match self.state.remove(&state_key) {
+                Some(aggregate) => aggregate,
+                None => {
+                    let keys = match self.ended.get_mut(&window) {
+                        Some(keys) => keys,
+                        None => self.open.entry(window).or_default(),
+                    };
+                    keys.push(key.clone());
+                    self.init.clone()
+                }
+            };
+            self.state
+                .insert(state_key, (self.f)(current, record.data.clone()));
+            // A late update of a window that was already emitted
+            if self.ended.contains_key(&window) {
+                results.extend(self.emit(&key, window));
+            }
+        }
+
+        if let Some(ttl) = self.ttl() {
+            let seen = self.last_seen.entry(key).or_insert(timestamp);
+            *seen = (*seen).max(timestamp);
+            let expiry = *seen + ttl;
+            self.next_expiry = Some(self.next_expiry.map_or(expiry, |next| next.min(expiry)));
+        }
+
+        self.max_timestamp = Some(
+            self.max_timestamp
+                .map_or(timestamp, |max| max.max(timestamp)),
+        );
+        if let Some(watermark) = self.watermark() {
+            results.extend(self.fire(Some(watermark)));
+            self.expire_keys(watermark);
+        }
+        results
+    }
+
+    fn ttl(&self) -> Option<i64> {
+        self.window_config
+            .state_ttl
+            .map(|ttl| ttl.as_millis() as i64)
+    }
+
+    /// Drop the windows of the keys whose TTL passed at `watermark`
+    fn expire_keys(&mut self, watermark: i64) {
+        let Some(ttl) = self.ttl() else {
+            return;
+        };
+        if self.next_expiry.is_none_or(|next| next > watermark) {
+            return;
+        }
+        let mut expired = HashSet::new();
+        self.last_seen.retain(|key, seen| {
+            let alive = *seen + ttl > watermark;
+            if !alive {
+                expired.insert(key.clone());
+            }
+            alive
+        });
+        self.next_expiry = self.last_seen.values().map(|seen| seen + ttl).min();
+        if expired.is_empty() {
+            return;
+        }
+
+        tracing::debug!("Dropping the window state of {} idle keys", expired.len());
+        self.state.retain(|(key, _), _| !expired.contains(key));
+        for windows in [&mut self.open, &mut self.ended] {
+            windows.retain(|_, keys| {
+                keys.retain(|key| !expired.contains(key));
+                !keys.is_empty()
+            });
+        }
+    }
+
+    /// Emit the windows of all keys that ended by `time`, or all open windows
+    /// if it is `None`, and drop the state of windows past their allowed lateness
+    fn fire(&mut self, time: Option<i64>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        let ended: Vec<u64> = by_start(self.open.keys())
+            .into_iter()
+            .filter(|window| {
+                time.is_none_or(|time| self.window_end(*window).is_some_and(|end| end <= time))
+            })
+            .collect();
+
+        let mut results = Vec::new();
+        for window in ended {
+            if let Some(keys) = self.open.remove(&window) {
+                results.extend(keys.iter().filter_map(|key| self.emit(key, window)));
+                self.ended.insert(window, keys);
+            }
+        }
+
+        let expired: Vec<u64> = self
+            .ended
+            .keys()
+            .copied()
+            .filter(|window| time.is_none_or(|time| self.is_closed(*window, time)))
+            .collect();
+        for window in expired {
+            for key in self.ended.remove(&window).unwrap_or_default() {
+                self.state.remove(&(key, window));
+            }
+        }
+        results
+    }
+}
+
+impl<T, K, A, F> KeyedWindows<T, K, A> for KeyedWindowAggregator<T, K, A, F>
+where
+    T: Clone + Send + Sync,
+    K: Eq + Hash + Clone + Send + Sync,
+    A: Clone + Send + Sync,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    fn on_record(&mut self, record: Record<T>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        KeyedWindowAggregator::on_record(self, record)
+    }
+
+    fn fire(&mut self, time: Option<i64>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        KeyedWindowAggregator::fire(self, time)
+    }
+
+    fn on_watermark(&mut self, watermark: i64) -> Vec<Record<WindowedValue<(K, A)>>> {
+        let results = KeyedWindowAggregator::fire(self, Some(watermark));
+        self.expire_keys(watermark);
+        results
+    }
+}
+
+#[async_trait]
+impl<T, K, A, F> Operator<T, (K, A)> for KeyedWindowAggregator<T, K, A, F>
+where
+    T: Clone + Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(self.on_record(record)))
+    }
+
+    async fn on_window_trigger(&mut self) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(self.fire(Some(current_time() as i64))))
+    }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(KeyedWindows::on_watermark(self, watermark)))
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(self.fire(None)))
+    }
+}
+
+/// The per-key windows of a keyed aggregator, whose results carry the
+/// bounds of their window
+pub(crate) trait KeyedWindows<T, K, A>: Send + Sync {
+    fn on_record(&mut self, record: Record<T>) -> Vec<Record<WindowedValue<(K, A)>>>;
+
+    /// Emit the windows that ended by `time`, or all windows if it is `None`
+    fn fire(&mut self, time: Option<i64>) -> Vec<Record<WindowedValue<(K, A)>>>;
+
+    /// Emit the windows that ended by the watermark
+    fn on_watermark(&mut self, watermark: i64) -> Vec<Record<WindowedValue<(K, A)>>> {
+        self.fire(Some(watermark))
+    }
+}
+
+pub(crate) fn without_windows<K, A>(
+    results: Vec<Record<WindowedValue<(K, A)>>>,
+) -> Vec<Record<(K, A)>> {
+    results
+        .into_iter()
+        .map(|record| Record {
+            data: record.data.value,
+            timestamp: record.timestamp,
+        })
+        .collect()
+}
+
+/// A keyed aggregator whose results carry the bounds of their window
+pub(crate) struct WithWindows<O>(pub(crate) O);
+
+#[async_trait]
+impl<T, K, A, O> Operator<T, WindowedValue<(K, A)>> for WithWindows<O>
+where
+    T: Send + Sync + 'static,
+    K: Send + Sync + 'static,
+    A: Send + Sync + 'static,
+    O: KeyedWindows<T, K, A>,
+{
+    async fn process(
+        &mut self,
+        record: Record<T>,
+    ) -> StreamResult<Vec<Record<WindowedValue<(K, A)>>>> {
+        Ok(self.0.on_record(record))
+    }
+
+    async fn on_window_trigger(&mut self) -> StreamResult<Vec<Record<WindowedValue<(K, A)>>>> {
+        Ok(self.0.fire(Some(current_time() as i64)))
+    }
+
+    async fn on_watermark(
+        &mut self,
+        watermark: i64,
+    ) -> StreamResult<Vec<Record<WindowedValue<(K, A)>>>> {
+        Ok(self.0.on_watermark(watermark))
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<WindowedValue<(K, A)>>>> {
+        Ok(self.0.fire(None))
+    }
+}
@@ -23,8 +23,8 @@ where
 #[async_trait]
 impl<T, R, F> Operator<T, R> for MapOperator<T, R, F>
 where
-    T: Clone + Send + Sync + 'static,
-    R: Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
     F: Fn(T) -> R + Send + Sync,
 {
     async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<R>>> {
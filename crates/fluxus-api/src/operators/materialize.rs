@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use std::hash::Hash;
+
+use crate::io::MaterializedTable;
+
+/// Stores the latest record per key in a table and passes records on unchanged
+pub struct MaterializeOperator<T, K, F> {
+    key_fn: F,
+    table: MaterializedTable<K, T>,
+}
+
+impl<T, K, F> MaterializeOperator<T, K, F>
+where
+    K: Eq + Hash,
+    F: Fn(&T) -> K,
+{
+    pub fn new(key_fn: F, table: MaterializedTable<K, T>) -> Self {
+        Self { key_fn, table }
+    }
+}
+
+#[async_trait]
+impl<T, K, F> Operator<T, T> for MaterializeOperator<T, K, F>
+where
+    T: Clone + Send + Sync + 'static,
+    K: Eq + Hash + Send + Sync + 'static,
+    F: Fn(&T) -> K + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<T>>> {
+        let key = (self.key_fn)(&record.data);
+        self.table.upsert(key, record.data.clone());
+        Ok(vec![record])
+    }
+}
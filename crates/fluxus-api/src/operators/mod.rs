@@ -1,14 +1,68 @@
+mod aggregate_function;
+mod assigned_window_aggregator;
+mod count_window_aggregator;
+mod dead_letter;
+mod dedup;
+mod enumerate;
+mod event_time;
 mod filter;
 mod flat_map;
+mod invalid_config;
+mod keyed_window_aggregator;
 mod map;
+mod materialize;
+mod named;
+mod pattern;
+mod rich_map;
+mod scan;
+mod session_aggregator;
+mod side_output;
+mod stats;
+mod tee;
+mod timeout_router;
+mod trigger;
+mod try_map;
+mod validate;
 mod window_aggregator;
+mod window_changelog;
+mod window_expiry;
+mod window_memory;
 mod window_skipper;
 mod window_sorter;
 
+pub use aggregate_function::AggregateFunction;
+pub use assigned_window_aggregator::AssignedWindowAggregator;
+pub use count_window_aggregator::CountOrTimeAggregator;
+pub use dead_letter::{DeadLetter, DeadLetterRouter};
+pub use dedup::DedupOperator;
+pub use enumerate::EnumerateOperator;
+pub(crate) use event_time::RecordsOperator;
+pub use event_time::{TimestampAssigner, WatermarkOperator, WatermarkSource};
 pub use filter::FilterOperator;
-pub use flat_map::FlatMapOperator;
+pub use flat_map::{FlatMapAsyncOperator, FlatMapOperator};
+pub(crate) use invalid_config::InvalidConfig;
+pub use keyed_window_aggregator::KeyedWindowAggregator;
+pub(crate) use keyed_window_aggregator::WithWindows;
 pub use map::MapOperator;
+pub use materialize::MaterializeOperator;
+pub use named::{NamedOperator, NamedSource};
+pub use pattern::{Pattern, PatternMatch, PatternOperator};
+pub use rich_map::{FunctionContext, RichMapFunction, RichMapOperator};
+pub use scan::ScanOperator;
+pub use session_aggregator::KeyedSessionAggregator;
+pub use stats::{Anomaly, Ewma, RollingMean, ZScoreAnomaly};
+pub use tee::TeeOperator;
+pub use timeout_router::TimeoutRouter;
+pub use trigger::{
+    CountTrigger, DeltaTrigger, EventTimeTrigger, FlushSignal, OrTrigger, PunctuationTrigger,
+    PurgingTrigger, SignalTrigger, Trigger, TriggerResult, TriggerWindow,
+};
+pub use try_map::TryMapOperator;
+pub use validate::{QuarantineOperator, Rule, RuleSet, ValidateOperator, Validated};
 pub use window_aggregator::WindowAggregator;
+pub(crate) use window_aggregator::{WindowEarlyAggregator, WindowKeyedAggregator, by_start};
+pub use window_changelog::WindowChangelogAggregator;
+pub(crate) use window_memory::WindowMemory;
 pub use window_skipper::WindowSkipper;
 pub use window_sorter::SortOrder;
 pub use window_sorter::WindowSorter;
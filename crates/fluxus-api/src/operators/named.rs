@@ -0,0 +1,136 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::stream::OperatorInfo;
+
+/// Counts the records passing through an operator and logs its failures
+/// under the name of the stage
+pub struct NamedOperator<T, R, O> {
+    inner: O,
+    info: Arc<OperatorInfo>,
+    _phantom: PhantomData<(T, R)>,
+}
+
+impl<T, R, O> NamedOperator<T, R, O>
+where
+    O: Operator<T, R>,
+{
+    pub fn new(inner: O, info: Arc<OperatorInfo>) -> Self {
+        Self {
+            inner,
+            info,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, R, O> Operator<T, R> for NamedOperator<T, R, O>
+where
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
+    O: Operator<T, R> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn init(&mut self) -> StreamResult<()> {
+        tracing::debug!(operator = %self.info.name(), "initializing operator");
+        self.inner.init().await
+    }
+
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<R>>> {
+        self.info.records_in.increment();
+        let timestamp = record.timestamp;
+        match self.inner.process(record).await {
+            Ok(records) => {
+                self.info.records_out.add(records.len() as u64);
+                Ok(records)
+            }
+            Err(e) => {
+                self.info.errors.increment();
+                let e = e.or_context(&self.info.name(), Some(timestamp));
+                tracing::error!(operator = %self.info.name(), "operator failed: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn on_window_trigger(&mut self) -> StreamResult<Vec<Record<R>>> {
+        let records = self.inner.on_window_trigger().await?;
+        self.info.records_out.add(records.len() as u64);
+        Ok(records)
+    }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<R>>> {
+        let records = self.inner.on_watermark(watermark).await?;
+        self.info.records_out.add(records.len() as u64);
+        Ok(records)
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<R>>> {
+        let records = self.inner.on_end_of_input().await?;
+        self.info.records_out.add(records.len() as u64);
+        Ok(records)
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        tracing::debug!(operator = %self.info.name(), "closing operator");
+        self.inner.close().await
+    }
+}
+
+/// Counts the records emitted by a source stage and logs its failures under
+/// the name of the stage
+pub struct NamedSource<S> {
+    inner: S,
+    info: Arc<OperatorInfo>,
+}
+
+impl<S> NamedSource<S> {
+    pub fn new(inner: S, info: Arc<OperatorInfo>) -> Self {
+        Self { inner, info }
+    }
+}
+
+#[async_trait]
+impl<T, S> Source<T> for NamedSource<S>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + Sync,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        tracing::debug!(operator = %self.info.name(), "initializing source");
+        self.inner.init().await
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        match self.inner.next().await {
+            Ok(Some(record)) => {
+                self.info.records_out.increment();
+                Ok(Some(record))
+            }
+            Err(e) if !matches!(e, StreamError::EOF | StreamError::Wait(_)) => {
+                self.info.errors.increment();
+                let e = e.or_context(&self.info.name(), None);
+                tracing::error!(operator = %self.info.name(), "source failed: {}", e);
+                Err(e)
+            }
+            result => result,
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        tracing::debug!(operator = %self.info.name(), "closing source");
+        self.inner.close().await
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        self.inner.watermark()
+    }
+}
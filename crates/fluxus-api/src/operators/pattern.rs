@@ -0,0 +1,250 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
+
+type ConditionFn<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+type KeyFn<T, K> = Arc<dyn Fn(&T) -> K + Send + Sync>;
+
+/// One named step of a [`Pattern`]
+#[derive(Clone)]
+struct Step<T> {
+    name: String,
+    condition: Option<ConditionFn<T>>,
+    /// Whether the step must directly follow the previous one
+    strict: bool,
+}
+
+impl<T> Step<T> {
+    fn matches(&self, value: &T) -> bool {
+        self.condition
+            .as_ref()
+            .is_none_or(|condition| condition(value))
+    }
+}
+
+/// A sequence of events to detect in each key of a keyed stream, e.g. a
+/// login followed by a failed payment and a retry within a minute:
+///
+/// ```
+/// # use fluxus_api::operators::Pattern;
+/// # use std::time::Duration;
+/// let _pattern = Pattern::<&str>::begin("login")
+///     .r#where(|event| *event == "login")
+///     .followed_by("failed")
+///     .r#where(|event| *event == "payment_failed")
+///     .next("retry")
+///     .r#where(|event| *event == "payment")
+///     .within(Duration::from_secs(60));
+/// ```
+#[derive(Clone)]
+pub struct Pattern<T> {
+    steps: Vec<Step<T>>,
+    within: Option<Duration>,
+}
+
+impl<T> Pattern<T> {
+    /// Start a pattern with a step matching any event until
+    /// [`where`](Self::r#where) is given
+    pub fn begin(name: impl Into<String>) -> Self {
+        Self {
+            steps: vec![Step {
+                name: name.into(),
+                condition: None,
+                strict: true,
+            }],
+            within: None,
+        }
+    }
+
+    /// The condition of the last step. Conditions of the same step are all
+    /// required to hold.
+    pub fn r#where<F>(mut self, condition: F) -> Self
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+        T: 'static,
+    {
+        let step = self.steps.last_mut().expect("a pattern has a first step");
+        step.condition = Some(match step.condition.take() {
+            Some(previous) => Arc::new(move |value: &T| previous(value) && condition(value)),
+            None => Arc::new(condition),
+        });
+        self
+    }
+
+    /// Add a step that must match the event directly after the previous step
+    pub fn next(self, name: impl Into<String>) -> Self {
+        self.step(name, true)
+    }
+
+    /// Add a step that matches the first matching event after the previous
+    /// step, skipping events in between
+    pub fn followed_by(self, name: impl Into<String>) -> Self {
+        self.step(name, false)
+    }
+
+    /// Only match sequences whose events are at most `duration` apart from
+    /// the first event
+    pub fn within(mut self, duration: Duration) -> Self {
+        self.within = Some(duration);
+        self
+    }
+
+    fn step(mut self, name: impl Into<String>, strict: bool) -> Self {
+        self.steps.push(Step {
+            name: name.into(),
+            condition: None,
+            strict,
+        });
+        self
+    }
+}
+
+/// The events of a detected [`Pattern`] with the names of their steps, in
+/// the order of the steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternMatch<T> {
+    pub events: Vec<(String, T)>,
+}
+
+impl<T> PatternMatch<T> {
+    /// The event of the step with the given name
+    pub fn get(&self, name: &str) -> Option<&T> {
+        self.events
+            .iter()
+            .find(|(step, _)| step == name)
+            .map(|(_, event)| event)
+    }
+}
+
+/// A sequence that matched the first steps of the pattern
+struct Partial<T> {
+    start: i64,
+    events: Vec<Record<T>>,
+}
+
+/// Detects a [`Pattern`] in the records of each key in order of arrival,
+/// emitting a `(key, match)` record timestamped with its last event for
+/// every sequence that matches all steps.
+///
+/// Every event that matches the first step starts a new sequence. Sequences
+/// that can't complete within the time of the pattern are dropped as the
+/// records of their key or the watermark pass it.
+pub struct PatternOperator<T, K> {
+    pattern: Pattern<T>,
+    key: KeyFn<T, K>,
+    partials: HashMap<K, Vec<Partial<T>>>,
+}
+
+impl<T, K> PatternOperator<T, K>
+where
+    T: Clone,
+    K: Eq + Hash + Clone,
+{
+    pub fn new(pattern: Pattern<T>, key: KeyFn<T, K>) -> Self {
+        Self {
+            pattern,
+            key,
+            partials: HashMap::new(),
+        }
+    }
+
+    fn is_expired(&self, partial: &Partial<T>, time: i64) -> bool {
+        self.pattern
+            .within
+            .is_some_and(|within| time - partial.start > within.as_millis() as i64)
+    }
+
+    fn complete(&self, key: &K, events: Vec<Record<T>>) -> Record<(K, PatternMatch<T>)> {
+        let timestamp = events.last().map_or(0, |record| record.timestamp);
+        let events = self
+            .pattern
+            .steps
+            .iter()
+            .zip(events)
+            .map(|(step, record)| (step.name.clone(), record.data))
+            .collect();
+        Record::with_timestamp((key.clone(), PatternMatch { events }), timestamp)
+    }
+
+    fn on_record(&mut self, record: Record<T>) -> Vec<Record<(K, PatternMatch<T>)>> {
+        let key = (self.key)(&record.data);
+        let mut partials = self.partials.remove(&key).unwrap_or_default();
+        partials.retain(|partial| !self.is_expired(partial, record.timestamp));
+
+        let steps = &self.pattern.steps;
+        let mut results = Vec::new();
+        let mut open = Vec::with_capacity(partials.len() + 1);
+        for mut partial in partials {
+            let step = &steps[partial.events.len()];
+            if step.matches(&record.data) {
+                partial.events.push(record.clone());
+                if partial.events.len() == steps.len() {
+                    results.push(self.complete(&key, partial.events));
+                } else {
+                    open.push(partial);
+                }
+            } else if !step.strict {
+                open.push(partial);
+            }
+        }
+        if steps[0].matches(&record.data) {
+            let partial = Partial {
+                start: record.timestamp,
+                events: vec![record],
+            };
+            if steps.len() == 1 {
+                results.push(self.complete(&key, partial.events));
+            } else {
+                open.push(partial);
+            }
+        }
+
+        if !open.is_empty() {
+            self.partials.insert(key, open);
+        }
+        results
+    }
+
+    /// Drop the sequences that can no longer complete at `watermark`
+    fn expire(&mut self, watermark: i64) {
+        let Some(within) = self.pattern.within else {
+            return;
+        };
+        let within = within.as_millis() as i64;
+        self.partials.retain(|_, partials| {
+            partials.retain(|partial| watermark - partial.start <= within);
+            !partials.is_empty()
+        });
+    }
+}
+
+#[async_trait]
+impl<T, K> Operator<T, (K, PatternMatch<T>)> for PatternOperator<T, K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    async fn process(
+        &mut self,
+        record: Record<T>,
+    ) -> StreamResult<Vec<Record<(K, PatternMatch<T>)>>> {
+        Ok(self.on_record(record))
+    }
+
+    async fn on_watermark(
+        &mut self,
+        watermark: i64,
+    ) -> StreamResult<Vec<Record<(K, PatternMatch<T>)>>> {
+        self.expire(watermark);
+        Ok(Vec::new())
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<(K, PatternMatch<T>)>>> {
+        self.partials.clear();
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::stream::OperatorInfo;
+
+/// Information handed to a rich function when it is opened
+#[derive(Debug, Clone)]
+pub struct FunctionContext {
+    /// Name of the operator running the function
+    pub operator_name: String,
+    /// Parallelism configured on the stream
+    pub parallelism: usize,
+}
+
+/// A map function with a lifecycle, e.g. to open a connection pool before the
+/// first record and release it once the stream ends
+#[async_trait]
+pub trait RichMapFunction<T, R>: Send + Sync {
+    /// Acquire resources before the first record
+    async fn open(&mut self, _ctx: &FunctionContext) -> StreamResult<()> {
+        Ok(())
+    }
+
+    /// Transform a single value
+    async fn map(&mut self, value: T) -> StreamResult<R>;
+
+    /// Release resources after the last record
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+/// Runs a [`RichMapFunction`] as an operator
+pub struct RichMapOperator<T, R, F> {
+    f: F,
+    parallelism: usize,
+    info: Option<Arc<OperatorInfo>>,
+    _phantom: PhantomData<(T, R)>,
+}
+
+impl<T, R, F> RichMapOperator<T, R, F>
+where
+    F: RichMapFunction<T, R>,
+{
+    pub fn new(f: F, parallelism: usize) -> Self {
+        Self {
+            f,
+            parallelism,
+            info: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Take the operator name passed to the function from the given stage
+    pub(crate) fn with_info(mut self, info: Arc<OperatorInfo>) -> Self {
+        self.info = Some(info);
+        self
+    }
+}
+
+#[async_trait]
+impl<T, R, F> Operator<T, R> for RichMapOperator<T, R, F>
+where
+    T: Clone + Send + Sync + 'static,
+    R: Clone + Send + Sync + 'static,
+    F: RichMapFunction<T, R>,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        let operator_name = match &self.info {
+            Some(info) => info.name(),
+            None => OperatorInfo::for_type::<F>().name(),
+        };
+        let ctx = FunctionContext {
+            operator_name,
+            parallelism: self.parallelism,
+        };
+        self.f.open(&ctx).await
+    }
+
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<R>>> {
+        let result = self.f.map(record.data).await?;
+        Ok(vec![Record::with_timestamp(result, record.timestamp)])
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.f.close().await
+    }
+}
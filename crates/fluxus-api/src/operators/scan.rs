@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use std::marker::PhantomData;
+
+/// Folds every record into a running accumulator and emits it after each record
+pub struct ScanOperator<T, A, F> {
+    acc: A,
+    f: F,
+    _phantom: PhantomData<T>,
+}
+
+impl<T, A, F> ScanOperator<T, A, F>
+where
+    F: Fn(A, T) -> A,
+{
+    pub fn new(init: A, f: F) -> Self {
+        Self {
+            acc: init,
+            f,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, A, F> Operator<T, A> for ScanOperator<T, A, F>
+where
+    T: Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<A>>> {
+        self.acc = (self.f)(self.acc.clone(), record.data);
+        Ok(vec![Record::with_timestamp(
+            self.acc.clone(),
+            record.timestamp,
+        )])
+    }
+}
@@ -0,0 +1,234 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::{
+    models::{Record, StreamResult},
+    time::current_time,
+    window::WindowedValue,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::keyed_window_aggregator::{KeyedWindows, without_windows};
+
+type KeyFn<T, K> = Arc<dyn Fn(&T) -> K + Send + Sync>;
+
+/// The open session of a key
+struct Session<A> {
+    id: u64,
+    start: i64,
+    last: i64,
+    size: usize,
+    aggregate: Option<A>,
+}
+
+/// Folds the records of each key into sessions, emitting a
+/// `(key, aggregate)` record when a session closes.
+///
+/// A session of a key closes once the watermark of the stream, the latest
+/// timestamp minus `watermark_delay`, or processing time at a window trigger
+/// reaches `gap` after its last record, and its state is dropped right away. With [`max_duration`](Self::max_duration) and
+/// [`max_size`](Self::max_size) sessions are also closed when they span too
+/// long or hold too many records, so that keys which never go quiet, such
+/// as bots, can't keep growing their session. Records of a key that are
+/// too late for its last session start a new one unless the watermark
+/// already reached `gap` after them, in which case they are dropped.
+pub struct KeyedSessionAggregator<T, K, A, F> {
+    gap: i64,
+    watermark_delay: i64,
+    max_duration: Option<i64>,
+    max_size: Option<usize>,
+    key: KeyFn<T, K>,
+    init: A,
+    f: F,
+    sessions: HashMap<K, Session<A>>,
+    /// Keys of the open sessions by the time they close at and their id
+    expiry: BTreeMap<(i64, u64), K>,
+    next_id: u64,
+    max_timestamp: Option<i64>,
+}
+
+impl<T, K, A, F> KeyedSessionAggregator<T, K, A, F>
+where
+    K: Eq + Hash + Clone,
+    A: Clone,
+    F: Fn(A, T) -> A,
+{
+    pub fn new(gap: Duration, watermark_delay: Duration, key: KeyFn<T, K>, init: A, f: F) -> Self {
+        Self {
+            gap: gap.as_millis() as i64,
+            watermark_delay: watermark_delay.as_millis() as i64,
+            max_duration: None,
+            max_size: None,
+            key,
+            init,
+            f,
+            sessions: HashMap::new(),
+            expiry: BTreeMap::new(),
+            next_id: 0,
+            max_timestamp: None,
+        }
+    }
+
+    /// Close sessions once they span `duration` from their first record
+    pub fn max_duration(mut self, duration: Duration) -> Self {
+        self.max_duration = Some(duration.as_millis() as i64);
+        self
+    }
+
+    /// Close sessions once they hold `size` records
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.max_size = Some(size.max(1));
+        self
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        self.max_timestamp.map(|max| max - self.watermark_delay)
+    }
+
+    /// Whether a record at `timestamp` belongs to the open session of its key
+    fn extends(&self, session: &Session<A>, timestamp: i64) -> bool {
+        let within_gap =
+            timestamp < session.last + self.gap && timestamp > session.start - self.gap;
+        let within_duration = self
+            .max_duration
+            .is_none_or(|max| timestamp.max(session.last) - timestamp.min(session.start) < max);
+        within_gap && within_duration
+    }
+
+    /// Close the session of a key, whose window spans from its first record
+    /// to `gap` after its last record
+    fn close(&mut self, key: &K) -> Option<Record<WindowedValue<(K, A)>>> {
+        let session = self.sessions.remove(key)?;
+        self.expiry.remove(&(session.last + self.gap, session.id));
+        Some(Record::with_timestamp(
+            WindowedValue {
+                start: Some(session.start),
+                end: Some(session.last + self.gap),
+                value: (
+                    key.clone(),
+                    session.aggregate.unwrap_or_else(|| self.init.clone()),
+                ),
+                is_final: true,
+            },
+            session.last,
+        ))
+    }
+
+    fn on_record(&mut self, record: Record<T>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        let timestamp = record.timestamp;
+        let key = (self.key)(&record.data);
+
+        // Close the sessions the record moves the watermark past first, so
+        // that sessions are emitted in the order they went inactive
+        self.max_timestamp = Some(
+            self.max_timestamp
+                .map_or(timestamp, |max| max.max(timestamp)),
+        );
+        let mut results = match self.watermark() {
+            Some(watermark) => self.fire(Some(watermark)),
+            None => Vec::new(),
+        };
+
+        let extends = self
+            .sessions
+            .get(&key)
+            .is_some_and(|session| self.extends(session, timestamp));
+        if !extends {
+            if self
+                .watermark()
+                .is_some_and(|watermark| timestamp + self.gap <= watermark)
+            {
+                tracing::debug!("Record at {} is too late for its session", timestamp);
+                return results;
+            }
+            results.extend(self.close(&key));
+            self.next_id += 1;
+            self.sessions.insert(
+                key.clone(),
+                Session {
+                    id: self.next_id,
+                    start: timestamp,
+                    last: timestamp,
+                    size: 0,
+                    aggregate: None,
+                },
+            );
+        }
+
+        if let Some(session) = self.sessions.get_mut(&key) {
+            self.expiry.remove(&(session.last + self.gap, session.id));
+            session.start = session.start.min(timestamp);
+            session.last = session.last.max(timestamp);
+            session.size += 1;
+            // The accumulator is moved through `f` instead of being cloned
+            let current = session
+                .aggregate
+                .take()
+                .unwrap_or_else(|| self.init.clone());
+            session.aggregate = Some((self.f)(current, record.data));
+            let full = self.max_size.is_some_and(|max| session.size >= max);
+            self.expiry
+                .insert((session.last + self.gap, session.id), key.clone());
+            if full {
+                results.extend(self.close(&key));
+            }
+        }
+
+        results
+    }
+
+    /// Close the sessions that are inactive at `time`, or all sessions if it
+    /// is `None`, in the order they went inactive
+    fn fire(&mut self, time: Option<i64>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        let inactive: Vec<K> = self
+            .expiry
+            .iter()
+            .take_while(|((end, _), _)| time.is_none_or(|time| *end <= time))
+            .map(|(_, key)| key.clone())
+            .collect();
+        inactive.iter().filter_map(|key| self.close(key)).collect()
+    }
+}
+
+impl<T, K, A, F> KeyedWindows<T, K, A> for KeyedSessionAggregator<T, K, A, F>
+where
+    T: Send + Sync,
+    K: Eq + Hash + Clone + Send + Sync,
+    A: Clone + Send + Sync,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    fn on_record(&mut self, record: Record<T>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        KeyedSessionAggregator::on_record(self, record)
+    }
+
+    fn fire(&mut self, time: Option<i64>) -> Vec<Record<WindowedValue<(K, A)>>> {
+        KeyedSessionAggregator::fire(self, time)
+    }
+}
+
+#[async_trait]
+impl<T, K, A, F> Operator<T, (K, A)> for KeyedSessionAggregator<T, K, A, F>
+where
+    T: Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(self.on_record(record)))
+    }
+
+    async fn on_window_trigger(&mut self) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(self.fire(Some(current_time() as i64))))
+    }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(self.fire(Some(watermark))))
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<(K, A)>>> {
+        Ok(without_windows(self.fire(None)))
+    }
+}
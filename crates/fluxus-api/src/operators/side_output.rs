@@ -0,0 +1,44 @@
+use fluxus_sinks::Sink;
+use fluxus_utils::models::{Record, StreamResult};
+use std::marker::PhantomData;
+
+/// A secondary sink that operators route records to, initialized on first use.
+///
+/// Side outputs carry exceptional records (rejects, timeouts, late data), so
+/// every write is flushed right away to keep them visible.
+pub(crate) struct SideOutput<T, K> {
+    sink: K,
+    initialized: bool,
+    _phantom: PhantomData<T>,
+}
+
+impl<T, K> SideOutput<T, K>
+where
+    T: Send,
+    K: Sink<T> + Send,
+{
+    pub(crate) fn new(sink: K) -> Self {
+        Self {
+            sink,
+            initialized: false,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub(crate) async fn emit(&mut self, record: Record<T>) -> StreamResult<()> {
+        if !self.initialized {
+            self.sink.init().await?;
+            self.initialized = true;
+        }
+        self.sink.write(record).await?;
+        self.sink.flush().await
+    }
+
+    pub(crate) async fn close(&mut self) -> StreamResult<()> {
+        if self.initialized {
+            self.initialized = false;
+            self.sink.close().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use fluxus_utils::stats::Stats;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+/// Emits the mean of the values of the last `n` records after each record
+pub struct RollingMean<T, F> {
+    n: usize,
+    f: F,
+    values: VecDeque<f64>,
+    sum: f64,
+    _phantom: PhantomData<T>,
+}
+
+impl<T, F> RollingMean<T, F>
+where
+    F: Fn(&T) -> f64,
+{
+    pub fn new(n: usize, f: F) -> Self {
+        Self {
+            n: n.max(1),
+            f,
+            values: VecDeque::new(),
+            sum: 0.0,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, F> Operator<T, f64> for RollingMean<T, F>
+where
+    T: Send + Sync + 'static,
+    F: Fn(&T) -> f64 + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<f64>>> {
+        let value = (self.f)(&record.data);
+        self.values.push_back(value);
+        self.sum += value;
+        if self.values.len() > self.n {
+            self.sum -= self.values.pop_front().unwrap_or_default();
+        }
+        let mean = self.sum / self.values.len() as f64;
+        Ok(vec![Record::with_timestamp(mean, record.timestamp)])
+    }
+}
+
+/// Emits the exponentially weighted moving average of the values of the
+/// records after each record, weighting the latest value by `alpha`
+pub struct Ewma<T, F> {
+    alpha: f64,
+    f: F,
+    average: Option<f64>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T, F> Ewma<T, F>
+where
+    F: Fn(&T) -> f64,
+{
+    /// `alpha` is clamped to `[0, 1]`
+    pub fn new(alpha: f64, f: F) -> Self {
+        Self {
+            alpha: alpha.clamp(0.0, 1.0),
+            f,
+            average: None,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, F> Operator<T, f64> for Ewma<T, F>
+where
+    T: Send + Sync + 'static,
+    F: Fn(&T) -> f64 + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<f64>>> {
+        let value = (self.f)(&record.data);
+        let average = self
+            .average
+            .map_or(value, |average| average + self.alpha * (value - average));
+        self.average = Some(average);
+        Ok(vec![Record::with_timestamp(average, record.timestamp)])
+    }
+}
+
+/// A record whose value deviates from the values before it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly<T> {
+    pub data: T,
+    /// Number of standard deviations the value is from the mean of the
+    /// values before it
+    pub zscore: f64,
+}
+
+/// Emits the records whose value is more than `threshold` standard
+/// deviations from the mean of the values of all records before it.
+///
+/// Records are only checked once two values were seen, and not while all
+/// values so far are equal.
+pub struct ZScoreAnomaly<T, F> {
+    threshold: f64,
+    f: F,
+    stats: Stats,
+    _phantom: PhantomData<T>,
+}
+
+impl<T, F> ZScoreAnomaly<T, F>
+where
+    F: Fn(&T) -> f64,
+{
+    pub fn new(threshold: f64, f: F) -> Self {
+        Self {
+            threshold,
+            f,
+            stats: Stats::new(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, F> Operator<T, Anomaly<T>> for ZScoreAnomaly<T, F>
+where
+    T: Send + Sync + 'static,
+    F: Fn(&T) -> f64 + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<Anomaly<T>>>> {
+        let value = (self.f)(&record.data);
+        let zscore = match (self.stats.mean(), self.stats.stddev()) {
+            (Some(mean), Some(stddev)) if self.stats.count() >= 2 && stddev > 0.0 => {
+                Some((value - mean) / stddev)
+            }
+            _ => None,
+        };
+        self.stats.add(value);
+        Ok(zscore
+            .filter(|zscore| zscore.abs() > self.threshold)
+            .map(|zscore| {
+                Record::with_timestamp(
+                    Anomaly {
+                        data: record.data,
+                        zscore,
+                    },
+                    record.timestamp,
+                )
+            })
+            .into_iter()
+            .collect())
+    }
+}
@@ -0,0 +1,39 @@
+use async_trait::async_trait;
+use fluxus_sinks::Sink;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+
+use super::side_output::SideOutput;
+
+/// Writes a copy of every record to a side sink and passes it on unchanged
+pub struct TeeOperator<T, K> {
+    output: SideOutput<T, K>,
+}
+
+impl<T, K> TeeOperator<T, K>
+where
+    T: Send,
+    K: Sink<T> + Send,
+{
+    pub fn new(sink: K) -> Self {
+        Self {
+            output: SideOutput::new(sink),
+        }
+    }
+}
+
+#[async_trait]
+impl<T, K> Operator<T, T> for TeeOperator<T, K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: Sink<T> + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<T>>> {
+        self.output.emit(record.clone()).await?;
+        Ok(vec![record])
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.output.close().await
+    }
+}
@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use fluxus_sinks::Sink;
+use fluxus_transformers::{Operator, TimeoutEvent};
+use fluxus_utils::models::{Record, StreamResult};
+use std::time::Duration;
+
+use super::side_output::SideOutput;
+
+/// Unwraps timeout events, routing the timeout markers to a side output sink
+pub struct TimeoutRouter<T, K> {
+    timeouts: SideOutput<Duration, K>,
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T, K> TimeoutRouter<T, K>
+where
+    K: Sink<Duration> + Send,
+{
+    pub fn new(timeouts: K) -> Self {
+        Self {
+            timeouts: SideOutput::new(timeouts),
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, K> Operator<TimeoutEvent<T>, T> for TimeoutRouter<T, K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: Sink<Duration> + Send + Sync,
+{
+    async fn process(&mut self, record: Record<TimeoutEvent<T>>) -> StreamResult<Vec<Record<T>>> {
+        match record.data {
+            TimeoutEvent::Record(data) => Ok(vec![Record::with_timestamp(data, record.timestamp)]),
+            TimeoutEvent::Timeout { idle } => {
+                self.timeouts
+                    .emit(Record::with_timestamp(idle, record.timestamp))
+                    .await?;
+                Ok(vec![])
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.timeouts.close().await
+    }
+}
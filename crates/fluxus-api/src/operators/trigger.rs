@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// What a window does after a [`Trigger`] was consulted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerResult {
+    /// Keep collecting elements
+    Continue,
+    /// Emit the current aggregate of the window and keep its state
+    Fire,
+    /// Emit the current aggregate of the window and start it over
+    FireAndPurge,
+}
+
+impl TriggerResult {
+    /// Whether the aggregate of the window is emitted
+    pub fn is_fire(self) -> bool {
+        self != TriggerResult::Continue
+    }
+
+    /// Whether the state of the window is cleared
+    pub fn is_purge(self) -> bool {
+        self == TriggerResult::FireAndPurge
+    }
+
+    /// Fire if either result fires, and purge if either purges
+    pub fn or(self, other: TriggerResult) -> TriggerResult {
+        match (self, other) {
+            (TriggerResult::FireAndPurge, _) | (_, TriggerResult::FireAndPurge) => {
+                TriggerResult::FireAndPurge
+            }
+            (TriggerResult::Fire, _) | (_, TriggerResult::Fire) => TriggerResult::Fire,
+            _ => TriggerResult::Continue,
+        }
+    }
+}
+
+/// The window a [`Trigger`] is consulted for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriggerWindow {
+    /// Key of the window, its start for time windows
+    pub key: u64,
+    /// Exclusive end of the window, `None` for the global window
+    pub end: Option<i64>,
+    /// Whether the watermark or processing time already passed the end
+    pub ended: bool,
+}
+
+/// Decides when a window emits its aggregate.
+///
+/// A trigger is consulted for every element added to a window, whenever the
+/// watermark advances and at every processing-time window trigger, for the
+/// windows that have not ended yet. At the end of the input, windows are
+/// consulted once more with an event time of `i64::MAX`. Triggers that keep
+/// state per window should drop it in [`clear`](Self::clear).
+pub trait Trigger<T>: Send + Sync {
+    /// Called after an element was added to a window
+    fn on_element(&mut self, element: &T, timestamp: i64, window: &TriggerWindow) -> TriggerResult;
+
+    /// Called when the watermark advanced to `time`
+    fn on_event_time(&mut self, time: i64, window: &TriggerWindow) -> TriggerResult;
+
+    /// Called at a processing-time window trigger
+    fn on_processing_time(&mut self, _time: i64, _window: &TriggerWindow) -> TriggerResult {
+        TriggerResult::Continue
+    }
+
+    /// Called when a window is purged or discarded
+    fn clear(&mut self, _window: &TriggerWindow) {}
+}
+
+impl<T, R> Trigger<T> for Box<R>
+where
+    R: Trigger<T> + ?Sized,
+{
+    fn on_element(&mut self, element: &T, timestamp: i64, window: &TriggerWindow) -> TriggerResult {
+        (**self).on_element(element, timestamp, window)
+    }
+
+    fn on_event_time(&mut self, time: i64, window: &TriggerWindow) -> TriggerResult {
+        (**self).on_event_time(time, window)
+    }
+
+    fn on_processing_time(&mut self, time: i64, window: &TriggerWindow) -> TriggerResult {
+        (**self).on_processing_time(time, window)
+    }
+
+    fn clear(&mut self, window: &TriggerWindow) {
+        (**self).clear(window)
+    }
+}
+
+/// Fires once the watermark or processing time passes the end of the window,
+/// and again for every element that is added later within the allowed
+/// lateness. This is the default trigger of windows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EventTimeTrigger;
+
+impl<T> Trigger<T> for EventTimeTrigger {
+    fn on_element(&mut self, _: &T, _: i64, window: &TriggerWindow) -> TriggerResult {
+        if window.ended {
+            TriggerResult::Fire
+        } else {
+            TriggerResult::Continue
+        }
+    }
+
+    fn on_event_time(&mut self, time: i64, window: &TriggerWindow) -> TriggerResult {
+        if window.end.unwrap_or(i64::MAX) <= time {
+            TriggerResult::Fire
+        } else {
+            TriggerResult::Continue
+        }
+    }
+
+    fn on_processing_time(&mut self, time: i64, window: &TriggerWindow) -> TriggerResult {
+        Trigger::<T>::on_event_time(self, time, window)
+    }
+}
+
+/// Fires every `count` elements of a window
+#[derive(Debug, Clone, Default)]
+pub struct CountTrigger {
+    count: usize,
+    seen: HashMap<u64, usize>,
+}
+
+impl CountTrigger {
+    pub fn of(count: usize) -> Self {
+        Self {
+            count: count.max(1),
+            seen: HashMap::new(),
+        }
+    }
+}
+
+impl<T> Trigger<T> for CountTrigger {
+    fn on_element(&mut self, _: &T, _: i64, window: &TriggerWindow) -> TriggerResult {
+        let seen = self.seen.entry(window.key).or_default();
+        *seen += 1;
+        if *seen >= self.count {
+            *seen = 0;
+            TriggerResult::Fire
+        } else {
+            TriggerResult::Continue
+        }
+    }
+
+    fn on_event_time(&mut self, _: i64, _: &TriggerWindow) -> TriggerResult {
+        TriggerResult::Continue
+    }
+
+    fn clear(&mut self, window: &TriggerWindow) {
+        self.seen.remove(&window.key);
+    }
+}
+
+/// Fires when the value extracted from an element differs from the value
+/// at the last firing of the window by more than a threshold, e.g. when a
+/// price moved by more than a percent
+pub struct DeltaTrigger<F> {
+    threshold: f64,
+    value: F,
+    last: HashMap<u64, f64>,
+}
+
+impl<F> DeltaTrigger<F> {
+    /// The first element of a window only sets the value to compare against
+    pub fn new(threshold: f64, value: F) -> Self {
+        Self {
+            threshold,
+            value,
+            last: HashMap::new(),
+        }
+    }
+}
+
+impl<T, F> Trigger<T> for DeltaTrigger<F>
+where
+    F: Fn(&T) -> f64 + Send + Sync,
+{
+    fn on_element(&mut self, element: &T, _: i64, window: &TriggerWindow) -> TriggerResult {
+        let value = (self.value)(element);
+        match self.last.get(&window.key) {
+            Some(last) if (value - last).abs() <= self.threshold => TriggerResult::Continue,
+            Some(_) => {
+                self.last.insert(window.key, value);
+                TriggerResult::Fire
+            }
+            None => {
+                self.last.insert(window.key, value);
+                TriggerResult::Continue
+            }
+        }
+    }
+
+    fn on_event_time(&mut self, _: i64, _: &TriggerWindow) -> TriggerResult {
+        TriggerResult::Continue
+    }
+
+    fn clear(&mut self, window: &TriggerWindow) {
+        self.last.remove(&window.key);
+    }
+}
+
+/// Fires when an element is a punctuation, such as a flush marker, e.g. to
+/// aggregate the elements of a global window up to each marker with
+/// `PurgingTrigger::new(PunctuationTrigger::new(|e: &Event| e.is_flush()))`.
+///
+/// The marker itself is added to the window before it fires.
+pub struct PunctuationTrigger<F> {
+    is_punctuation: F,
+}
+
+impl<F> PunctuationTrigger<F> {
+    pub fn new(is_punctuation: F) -> Self {
+        Self { is_punctuation }
+    }
+}
+
+impl<T, F> Trigger<T> for PunctuationTrigger<F>
+where
+    F: Fn(&T) -> bool + Send + Sync,
+{
+    fn on_element(&mut self, element: &T, _: i64, _: &TriggerWindow) -> TriggerResult {
+        if (self.is_punctuation)(element) {
+            TriggerResult::Fire
+        } else {
+            TriggerResult::Continue
+        }
+    }
+
+    fn on_event_time(&mut self, _: i64, _: &TriggerWindow) -> TriggerResult {
+        TriggerResult::Continue
+    }
+}
+
+/// A handle to fire a [`SignalTrigger`] from outside of the pipeline, e.g.
+/// from an admin endpoint. Clones fire the same trigger.
+#[derive(Debug, Clone, Default)]
+pub struct FlushSignal {
+    raised: Arc<AtomicU64>,
+}
+
+impl FlushSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fire the windows of the trigger the next time it is consulted
+    pub fn flush(&self) {
+        self.raised.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A trigger that fires when this signal is flushed
+    pub fn trigger(&self) -> SignalTrigger {
+        SignalTrigger {
+            raised: self.raised.clone(),
+            fired: HashMap::new(),
+        }
+    }
+}
+
+/// Fires every window once after each flush of its [`FlushSignal`], the
+/// next time the trigger is consulted for the window: when an element is
+/// added, the watermark advances or at a processing-time window trigger.
+/// Windows only fire for flushes after they received their first element.
+#[derive(Debug)]
+pub struct SignalTrigger {
+    raised: Arc<AtomicU64>,
+    /// Flushes at the last firing of each window
+    fired: HashMap<u64, u64>,
+}
+
+impl SignalTrigger {
+    fn check(&mut self, window: &TriggerWindow) -> TriggerResult {
+        let raised = self.raised.load(Ordering::Relaxed);
+        let fired = self.fired.entry(window.key).or_insert(raised);
+        if raised > *fired {
+            *fired = raised;
+            TriggerResult::Fire
+        } else {
+            TriggerResult::Continue
+        }
+    }
+}
+
+impl<T> Trigger<T> for SignalTrigger {
+    fn on_element(&mut self, _: &T, _: i64, window: &TriggerWindow) -> TriggerResult {
+        self.check(window)
+    }
+
+    fn on_event_time(&mut self, _: i64, window: &TriggerWindow) -> TriggerResult {
+        self.check(window)
+    }
+
+    fn on_processing_time(&mut self, _: i64, window: &TriggerWindow) -> TriggerResult {
+        self.check(window)
+    }
+
+    fn clear(&mut self, window: &TriggerWindow) {
+        self.fired.remove(&window.key);
+    }
+}
+
+/// Fires when either of two triggers fires, e.g. to emit early results of
+/// an event-time window
+pub struct OrTrigger<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> OrTrigger<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<T, A, B> Trigger<T> for OrTrigger<A, B>
+where
+    A: Trigger<T>,
+    B: Trigger<T>,
+{
+    fn on_element(&mut self, element: &T, timestamp: i64, window: &TriggerWindow) -> TriggerResult {
+        let first = self.first.on_element(element, timestamp, window);
+        first.or(self.second.on_element(element, timestamp, window))
+    }
+
+    fn on_event_time(&mut self, time: i64, window: &TriggerWindow) -> TriggerResult {
+        let first = self.first.on_event_time(time, window);
+        first.or(self.second.on_event_time(time, window))
+    }
+
+    fn on_processing_time(&mut self, time: i64, window: &TriggerWindow) -> TriggerResult {
+        let first = self.first.on_processing_time(time, window);
+        first.or(self.second.on_processing_time(time, window))
+    }
+
+    fn clear(&mut self, window: &TriggerWindow) {
+        self.first.clear(window);
+        self.second.clear(window);
+    }
+}
+
+/// Turns every firing of a trigger into a [`TriggerResult::FireAndPurge`],
+/// so the window starts over every time it fires
+pub struct PurgingTrigger<A> {
+    inner: A,
+}
+
+impl<A> PurgingTrigger<A> {
+    pub fn new(inner: A) -> Self {
+        Self { inner }
+    }
+
+    fn purge(result: TriggerResult) -> TriggerResult {
+        if result.is_fire() {
+            TriggerResult::FireAndPurge
+        } else {
+            result
+        }
+    }
+}
+
+impl<T, A> Trigger<T> for PurgingTrigger<A>
+where
+    A: Trigger<T>,
+{
+    fn on_element(&mut self, element: &T, timestamp: i64, window: &TriggerWindow) -> TriggerResult {
+        Self::purge(self.inner.on_element(element, timestamp, window))
+    }
+
+    fn on_event_time(&mut self, time: i64, window: &TriggerWindow) -> TriggerResult {
+        Self::purge(self.inner.on_event_time(time, window))
+    }
+
+    fn on_processing_time(&mut self, time: i64, window: &TriggerWindow) -> TriggerResult {
+        Self::purge(self.inner.on_processing_time(time, window))
+    }
+
+    fn clear(&mut self, window: &TriggerWindow) {
+        self.inner.clear(window);
+    }
+}
@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use fluxus_core::RetryStrategy;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use std::marker::PhantomData;
+
+/// Fallible map operator that retries failed records according to a retry
+/// strategy, returning the input of records that still fail with their error
+pub struct TryMapOperator<T, R, E, F> {
+    f: F,
+    strategy: RetryStrategy,
+    _phantom: PhantomData<(T, R, E)>,
+}
+
+impl<T, R, E, F> TryMapOperator<T, R, E, F>
+where
+    F: Fn(T) -> Result<R, E>,
+{
+    pub fn new(f: F, strategy: RetryStrategy) -> Self {
+        Self {
+            f,
+            strategy,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, R, E, F> Operator<T, Result<R, (T, E)>> for TryMapOperator<T, R, E, F>
+where
+    T: Clone + Send + Sync + 'static,
+    R: Clone + Send + Sync + 'static,
+    E: Clone + Send + Sync + 'static,
+    F: Fn(T) -> Result<R, E> + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<Result<R, (T, E)>>>> {
+        let mut attempt = 0;
+        let result = loop {
+            match (self.f)(record.data.clone()) {
+                Ok(value) => break Ok(value),
+                Err(error) => match self.strategy.get_delay(attempt) {
+                    Some(delay) => {
+                        tracing::debug!(
+                            "try_map failed (attempt {}), retrying after {:?}",
+                            attempt + 1,
+                            delay
+                        );
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => break Err((record.data.clone(), error)),
+                },
+            }
+        };
+        Ok(vec![Record::with_timestamp(result, record.timestamp)])
+    }
+}
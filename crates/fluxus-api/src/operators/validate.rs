@@ -0,0 +1,273 @@
+use async_trait::async_trait;
+use fluxus_core::{Counter, MetricValue};
+use fluxus_sinks::Sink;
+use fluxus_transformers::Operator;
+use fluxus_utils::{
+    models::{Record, StreamError, StreamResult},
+    row::{Row, Value},
+};
+use regex::Regex;
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    sync::Arc,
+};
+
+use super::side_output::SideOutput;
+
+type CheckFn<T> = Arc<dyn Fn(&T) -> bool + Send + Sync>;
+
+/// A named data-quality check
+#[derive(Clone)]
+pub struct Rule<T> {
+    name: String,
+    check: CheckFn<T>,
+    violations: Arc<Counter>,
+}
+
+impl<T> Rule<T> {
+    /// Create a rule from a predicate that returns true for valid records
+    pub fn new<S, F>(name: S, check: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        Self {
+            name: name.into(),
+            check: Arc::new(check),
+            violations: Arc::new(Counter::new()),
+        }
+    }
+
+    /// The extracted value must be present
+    pub fn not_null<S, V, F>(name: S, f: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&T) -> Option<V> + Send + Sync + 'static,
+    {
+        Self::new(name, move |t| f(t).is_some())
+    }
+
+    /// The extracted value must fall within `[min, max]`
+    pub fn range<S, V, F>(name: S, f: F, min: V, max: V) -> Self
+    where
+        S: Into<String>,
+        V: PartialOrd + Send + Sync + 'static,
+        F: Fn(&T) -> V + Send + Sync + 'static,
+    {
+        Self::new(name, move |t| {
+            let v = f(t);
+            v >= min && v <= max
+        })
+    }
+
+    /// The extracted text must match the regular expression
+    pub fn regex<S, F>(name: S, f: F, pattern: &str) -> StreamResult<Self>
+    where
+        S: Into<String>,
+        F: Fn(&T) -> &str + Send + Sync + 'static,
+    {
+        let re = Regex::new(pattern).map_err(|e| StreamError::Config(e.to_string()))?;
+        Ok(Self::new(name, move |t| re.is_match(f(t))))
+    }
+
+    /// The extracted value must be a member of the reference set
+    pub fn one_of<S, V, F>(name: S, f: F, allowed: impl IntoIterator<Item = V>) -> Self
+    where
+        S: Into<String>,
+        V: Eq + Hash + Send + Sync + 'static,
+        F: Fn(&T) -> V + Send + Sync + 'static,
+    {
+        let allowed: HashSet<V> = allowed.into_iter().collect();
+        Self::new(name, move |t| allowed.contains(&f(t)))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of records that failed this rule so far
+    pub fn violations(&self) -> u64 {
+        self.violations.value()
+    }
+}
+
+impl Rule<Row> {
+    /// The named field must not be null
+    pub fn field_not_null(field: &str) -> Self {
+        let f = field.to_string();
+        Self::new(format!("{}_not_null", field), move |row: &Row| {
+            !row.is_null(&f)
+        })
+    }
+
+    /// The named numeric field must fall within `[min, max]`
+    pub fn field_range(field: &str, min: f64, max: f64) -> Self {
+        let f = field.to_string();
+        Self::new(format!("{}_range", field), move |row: &Row| {
+            row.get_f64(&f).is_some_and(|v| v >= min && v <= max)
+        })
+    }
+
+    /// The named string field must match the regular expression
+    pub fn field_regex(field: &str, pattern: &str) -> StreamResult<Self> {
+        let re = Regex::new(pattern).map_err(|e| StreamError::Config(e.to_string()))?;
+        let f = field.to_string();
+        Ok(Self::new(format!("{}_regex", field), move |row: &Row| {
+            row.get_str(&f).is_some_and(|s| re.is_match(s))
+        }))
+    }
+
+    /// The named field must be one of the allowed values
+    pub fn field_in(field: &str, allowed: impl IntoIterator<Item = Value>) -> Self {
+        let allowed: Vec<Value> = allowed.into_iter().collect();
+        let f = field.to_string();
+        Self::new(format!("{}_in", field), move |row: &Row| {
+            row.get_by_name(&f).is_some_and(|v| allowed.contains(v))
+        })
+    }
+}
+
+/// A set of rules applied together by the validate operators
+#[derive(Clone)]
+pub struct RuleSet<T> {
+    rules: Vec<Rule<T>>,
+}
+
+impl<T> Default for RuleSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> RuleSet<T> {
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Add a rule to the set
+    pub fn rule(mut self, rule: Rule<T>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Check a value against all rules, returning the names of the failed ones
+    pub fn check(&self, value: &T) -> Vec<String> {
+        self.rules
+            .iter()
+            .filter(|rule| !(rule.check)(value))
+            .map(|rule| {
+                rule.violations.increment();
+                rule.name.clone()
+            })
+            .collect()
+    }
+
+    /// Per-rule violation counters, keyed by `violations.<rule name>`
+    pub fn metrics(&self) -> HashMap<String, MetricValue> {
+        self.rules
+            .iter()
+            .map(|rule| {
+                (
+                    format!("violations.{}", rule.name),
+                    MetricValue::Counter(rule.violations()),
+                )
+            })
+            .collect()
+    }
+}
+
+impl<T> From<Vec<Rule<T>>> for RuleSet<T> {
+    fn from(rules: Vec<Rule<T>>) -> Self {
+        Self { rules }
+    }
+}
+
+/// A record tagged with the rules it violated
+#[derive(Debug, Clone, PartialEq)]
+pub struct Validated<T> {
+    pub data: T,
+    pub violations: Vec<String>,
+}
+
+impl<T> Validated<T> {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Tags every record with its rule violations
+pub struct ValidateOperator<T> {
+    rules: RuleSet<T>,
+}
+
+impl<T> ValidateOperator<T> {
+    pub fn new(rules: RuleSet<T>) -> Self {
+        Self { rules }
+    }
+}
+
+#[async_trait]
+impl<T> Operator<T, Validated<T>> for ValidateOperator<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<Validated<T>>>> {
+        let violations = self.rules.check(&record.data);
+        Ok(vec![Record::with_timestamp(
+            Validated {
+                data: record.data,
+                violations,
+            },
+            record.timestamp,
+        )])
+    }
+}
+
+/// Passes valid records downstream and writes violating ones to a quarantine sink
+pub struct QuarantineOperator<T, K> {
+    rules: RuleSet<T>,
+    quarantine: SideOutput<Validated<T>, K>,
+}
+
+impl<T, K> QuarantineOperator<T, K>
+where
+    T: Send,
+    K: Sink<Validated<T>> + Send,
+{
+    pub fn new(rules: RuleSet<T>, quarantine: K) -> Self {
+        Self {
+            rules,
+            quarantine: SideOutput::new(quarantine),
+        }
+    }
+}
+
+#[async_trait]
+impl<T, K> Operator<T, T> for QuarantineOperator<T, K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: Sink<Validated<T>> + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<T>>> {
+        let violations = self.rules.check(&record.data);
+        if violations.is_empty() {
+            return Ok(vec![record]);
+        }
+
+        self.quarantine
+            .emit(Record::with_timestamp(
+                Validated {
+                    data: record.data,
+                    violations,
+                },
+                record.timestamp,
+            ))
+            .await?;
+        Ok(vec![])
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.quarantine.close().await
+    }
+}
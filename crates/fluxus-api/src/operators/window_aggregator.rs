@@ -1,18 +1,52 @@
 use async_trait::async_trait;
+use fluxus_core::Gauge;
 use fluxus_runtime::state::KeyedStateBackend;
+use fluxus_sinks::Sink;
 use fluxus_transformers::Operator;
 use fluxus_utils::{
+    memory::{MemoryAccount, MemorySize},
     models::{Record, StreamResult},
-    window::WindowConfig,
+    time::current_time,
+    window::{WindowConfig, WindowedValue},
 };
-use std::marker::PhantomData;
+use std::collections::BTreeSet;
+use std::mem::size_of;
+use std::sync::Arc;
 
+use super::side_output::SideOutput;
+use super::trigger::{EventTimeTrigger, Trigger, TriggerResult, TriggerWindow};
+
+type LateSink<T> = Box<dyn Sink<T> + Send + Sync>;
+
+/// Folds the records of each window into an aggregate.
+///
+/// The aggregate of a window is emitted once the window ends: when the
+/// watermark, the latest timestamp minus the watermark delay, passes the end
+/// of the window, when processing time does at a window trigger, or when the
+/// input ends. Until the watermark also passes the allowed lateness, late
+/// records still update the window and its aggregate is emitted again.
+/// Records later than that are dropped, or written to the sink set with
+/// [`late_records_to`](Self::late_records_to). A different [`Trigger`] set
+/// with [`trigger`](Self::trigger) decides when windows emit instead. With
+/// [`emit_partial`](Self::emit_partial) the running aggregate is emitted
+/// after every record, also for late records, and the state of windows is
+/// only dropped when the watermark is advanced with
+/// [`on_watermark`](Operator::on_watermark).
 pub struct WindowAggregator<T, A, F> {
     window_config: WindowConfig,
     init: A,
     f: F,
-    state: KeyedStateBackend<u64, A>,
-    _phantom: PhantomData<T>,
+    state: KeyedStateBackend<u64, Option<A>>,
+    emit_partial: bool,
+    open: BTreeSet<u64>,
+    ended: BTreeSet<u64>,
+    max_timestamp: Option<i64>,
+    late: Option<SideOutput<T, LateSink<T>>>,
+    late_records: Vec<Record<T>>,
+    trigger: Box<dyn Trigger<T>>,
+    /// Keys of the windows updated by records, if tracked
+    updated: Option<Vec<u64>>,
+    live_windows: Option<Arc<Gauge>>,
 }
 
 impl<T, A, F> WindowAggregator<T, A, F>
@@ -26,12 +60,333 @@ where
             init,
             f,
             state: KeyedStateBackend::new(),
-            _phantom: PhantomData,
+            emit_partial: false,
+            open: BTreeSet::new(),
+            ended: BTreeSet::new(),
+            max_timestamp: None,
+            late: None,
+            late_records: Vec::new(),
+            trigger: Box::new(EventTimeTrigger),
+            updated: None,
+            live_windows: None,
         }
     }
 
+    /// Publish the number of windows that hold state to a gauge
+    pub fn live_windows(mut self, gauge: Arc<Gauge>) -> Self {
+        self.live_windows = Some(gauge);
+        self
+    }
+
+    /// Account for the estimated memory of the aggregates of the windows
+    pub fn memory_account(self, account: MemoryAccount) -> Self
+    where
+        A: MemorySize + 'static,
+    {
+        self.memory_estimate(account, A::heap_size)
+    }
+
+    /// Account for the memory of the aggregates of the windows, estimating
+    /// the heap memory an aggregate owns with `heap_size`
+    pub(crate) fn memory_estimate<H>(mut self, account: MemoryAccount, heap_size: H) -> Self
+    where
+        A: 'static,
+        H: Fn(&A) -> usize + Send + Sync + 'static,
+    {
+        self.state = self
+            .state
+            .with_memory_estimate(account, u64::memory_size, move |acc| {
+                size_of::<Option<A>>() + acc.as_ref().map_or(0, &heap_size)
+            });
+        self
+    }
+
+    fn publish_live_windows(&self) {
+        if let Some(gauge) = &self.live_windows {
+            gauge.set(self.state.len() as i64);
+        }
+    }
+
+    /// Decide with the given trigger when windows emit their aggregate
+    pub fn trigger<R>(mut self, trigger: R) -> Self
+    where
+        R: Trigger<T> + 'static,
+    {
+        self.trigger = Box::new(trigger);
+        self
+    }
+
+    /// Emit the running aggregate of every window a record updates
+    pub fn emit_partial(mut self) -> Self {
+        self.emit_partial = true;
+        self
+    }
+
+    /// Keep the keys of the windows updated by records, see
+    /// [`WindowEarlyAggregator`]
+    pub(crate) fn track_updates(mut self) -> Self {
+        self.updated = Some(Vec::new());
+        self
+    }
+
     fn get_window_keys(&self, timestamp: i64) -> Vec<u64> {
-        self.window_config.window_type.get_window_keys(timestamp)
+        self.window_config.get_window_keys(timestamp)
+    }
+
+    pub(crate) fn watermark(&self) -> Option<i64> {
+        self.max_timestamp
+            .map(|max| max - self.window_config.watermark_delay.as_millis() as i64)
+    }
+
+    /// Write records too late for all of their windows to a sink
+    pub fn late_records_to<K>(mut self, sink: K) -> Self
+    where
+        T: Send + 'static,
+        K: Sink<T> + Send + Sync + 'static,
+    {
+        self.late = Some(SideOutput::new(Box::new(sink)));
+        self
+    }
+
+    /// Whether a window has ended at the given time
+    fn has_ended(&self, window_key: u64, time: i64) -> bool {
+        self.window_config
+            .window_type
+            .window_end(window_key as i64)
+            .is_some_and(|end| end <= time)
+    }
+
+    /// Whether a window can no longer change at the given time
+    pub(crate) fn is_closed(&self, window_key: u64, time: i64) -> bool {
+        let lateness = self.window_config.allow_lateness.as_millis() as i64;
+        self.window_config
+            .window_type
+            .window_end(window_key as i64)
+            .is_some_and(|end| end + lateness <= time)
+    }
+
+    /// Fold a record into each of its windows and return the updated
+    /// aggregates with the key of their window
+    pub(crate) fn update(&mut self, record: Record<T>) -> Vec<(u64, Record<A>)>
+    where
+        T: Clone,
+    {
+        let window_keys = self.get_window_keys(record.timestamp);
+        self.update_windows(window_keys, record)
+    }
+
+    fn update_windows(&mut self, window_keys: Vec<u64>, record: Record<T>) -> Vec<(u64, Record<A>)>
+    where
+        T: Clone,
+    {
+        let mut results = Vec::new();
+
+        if let Some(updated) = self.updated.as_mut() {
+            updated.extend(&window_keys);
+        }
+        for window_key in window_keys {
+            // The accumulator is moved through `f` instead of being cloned out of the state
+            let new_value = self.state.update_with(
+                window_key,
+                || None,
+                |acc| {
+                    let current = acc.take().unwrap_or_else(|| self.init.clone());
+                    acc.insert((self.f)(current, record.data.clone())).clone()
+                },
+            );
+
+            results.push((
+                window_key,
+                Record {
+                    data: new_value,
+                    timestamp: record.timestamp,
+                },
+            ));
+        }
+
+        results
+    }
+
+    /// Fold a record into its windows and return the aggregates of the
+    /// windows that ended or were updated after they ended, or of every
+    /// updated window when emitting partial results
+    fn on_record(&mut self, record: Record<T>) -> Vec<(u64, Record<A>)>
+    where
+        T: Clone,
+    {
+        if self.emit_partial {
+            return self.update(record);
+        }
+
+        let timestamp = record.timestamp;
+        let window_keys = self.get_window_keys(timestamp);
+        let window_keys: Vec<_> = match self.watermark() {
+            Some(watermark) => window_keys
+                .into_iter()
+                .filter(|key| !self.is_closed(*key, watermark))
+                .collect(),
+            None => window_keys,
+        };
+        if window_keys.is_empty() {
+            tracing::debug!("Record at {} is too late for its windows", timestamp);
+            if self.late.is_some() {
+                self.late_records.push(record);
+            }
+            return Vec::new();
+        }
+
+        let windows: Vec<_> = window_keys
+            .iter()
+            .map(|key| self.trigger_window(*key))
+            .collect();
+        let decisions: Vec<_> = windows
+            .iter()
+            .map(|window| self.trigger.on_element(&record.data, timestamp, window))
+            .collect();
+        self.update_windows(window_keys, record);
+
+        let mut results = Vec::new();
+        for (window, decision) in windows.iter().zip(decisions) {
+            if !window.ended {
+                self.open.insert(window.key);
+            }
+            results.extend(self.apply(window, decision));
+        }
+
+        self.max_timestamp = Some(
+            self.max_timestamp
+                .map_or(timestamp, |max| max.max(timestamp)),
+        );
+        if let Some(watermark) = self.watermark() {
+            results.extend(self.advance(watermark, true));
+        }
+        results
+    }
+
+    /// Drop the state of the windows past their allowed lateness at
+    /// `watermark`, for records folded in with [`update`](Self::update)
+    pub(crate) fn purge(&mut self, watermark: i64) {
+        let window_type = &self.window_config.window_type;
+        let lateness = self.window_config.allow_lateness.as_millis() as i64;
+        self.state.retain(|key, _| {
+            window_type
+                .window_end(*key as i64)
+                .is_none_or(|end| end + lateness > watermark)
+        });
+        self.publish_live_windows();
+    }
+
+    /// End the windows the watermark passed and drop the state of windows
+    /// past their allowed lateness
+    fn advance_watermark(&mut self, watermark: i64) -> Vec<(u64, Record<A>)> {
+        if self.emit_partial {
+            self.purge(watermark);
+            return Vec::new();
+        }
+        self.advance(watermark, true)
+    }
+
+    fn trigger_window(&self, key: u64) -> TriggerWindow {
+        TriggerWindow {
+            key,
+            end: self.window_config.window_type.window_end(key as i64),
+            ended: self.ended.contains(&key),
+        }
+    }
+
+    /// Emit the aggregate of a window if the trigger fired, clearing the
+    /// window if it purged
+    fn apply(
+        &mut self,
+        window: &TriggerWindow,
+        decision: TriggerResult,
+    ) -> Option<(u64, Record<A>)> {
+        if !decision.is_fire() {
+            return None;
+        }
+        let aggregate = if decision.is_purge() {
+            self.trigger.clear(window);
+            self.state.remove(&window.key).flatten()
+        } else {
+            self.state.get(&window.key).flatten()
+        }?;
+        let timestamp = self.window_timestamp(window.key);
+        Some((window.key, Record::with_timestamp(aggregate, timestamp)))
+    }
+
+    /// Timestamp of the aggregate of a window, the last millisecond it covers
+    fn window_timestamp(&self, key: u64) -> i64 {
+        self.window_config
+            .window_type
+            .window_end(key as i64)
+            .map(|end| end - 1)
+            .or(self.max_timestamp)
+            .unwrap_or_default()
+    }
+
+    /// Consult the trigger for the open windows as event or processing time
+    /// advances to `time`, end the windows it passed and drop the state of
+    /// windows past their allowed lateness
+    fn advance(&mut self, time: i64, event_time: bool) -> Vec<(u64, Record<A>)> {
+        let mut results = Vec::new();
+        for key in by_start(&self.open) {
+            let window = self.trigger_window(key);
+            let decision = if event_time {
+                self.trigger.on_event_time(time, &window)
+            } else {
+                self.trigger.on_processing_time(time, &window)
+            };
+            results.extend(self.apply(&window, decision));
+            if self.has_ended(key, time) {
+                self.open.remove(&key);
+                self.ended.insert(key);
+            }
+        }
+
+        let expired: Vec<u64> = self
+            .ended
+            .iter()
+            .copied()
+            .filter(|key| self.is_closed(*key, time))
+            .collect();
+        for key in expired {
+            self.discard(key);
+        }
+        self.publish_live_windows();
+        results
+    }
+
+    /// Consult the trigger one last time for the open windows and drop all state
+    fn end_of_input(&mut self) -> Vec<(u64, Record<A>)> {
+        let results = self.advance(i64::MAX, true);
+        let keys: Vec<u64> = self.open.iter().chain(&self.ended).copied().collect();
+        for key in keys {
+            self.discard(key);
+        }
+        self.state.retain(|_, _| false);
+        self.publish_live_windows();
+        results
+    }
+
+    fn discard(&mut self, key: u64) {
+        let window = self.trigger_window(key);
+        self.trigger.clear(&window);
+        self.open.remove(&key);
+        self.ended.remove(&key);
+        self.state.remove(&key);
+    }
+
+    /// Write the records that were too late to the late sink
+    async fn emit_late(&mut self) -> StreamResult<()>
+    where
+        T: Send + 'static,
+    {
+        if let Some(late) = self.late.as_mut() {
+            for record in self.late_records.drain(..) {
+                late.emit(record).await?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -43,22 +398,163 @@ where
     F: Fn(A, T) -> A + Send + Sync,
 {
     async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<A>>> {
-        let mut results = Vec::new();
+        let results = self.on_record(record);
+        self.emit_late().await?;
+        Ok(without_keys(results))
+    }
 
-        for window_key in self.get_window_keys(record.timestamp) {
-            let current = self
-                .state
-                .get(&window_key)
-                .unwrap_or_else(|| self.init.clone());
-            let new_value = (self.f)(current, record.data.clone());
-            self.state.set(window_key, new_value.clone());
+    async fn on_window_trigger(&mut self) -> StreamResult<Vec<Record<A>>> {
+        Ok(without_keys(self.advance(current_time() as i64, false)))
+    }
 
-            results.push(Record {
-                data: new_value,
-                timestamp: record.timestamp,
-            });
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<A>>> {
+        Ok(without_keys(self.advance_watermark(watermark)))
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<A>>> {
+        Ok(without_keys(self.end_of_input()))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        match self.late.as_mut() {
+            Some(late) => late.close().await,
+            None => Ok(()),
         }
+    }
+}
+
+/// Window keys in order of the start of their windows, since the keys of
+/// windows starting before the epoch wrap around
+pub(crate) fn by_start<'a>(keys: impl IntoIterator<Item = &'a u64>) -> Vec<u64> {
+    let mut keys: Vec<u64> = keys.into_iter().copied().collect();
+    keys.sort_by_key(|key| *key as i64);
+    keys
+}
+
+fn without_keys<A>(results: Vec<(u64, Record<A>)>) -> Vec<Record<A>> {
+    results.into_iter().map(|(_, record)| record).collect()
+}
+
+/// A [`WindowAggregator`] whose results carry the key of their window
+pub(crate) struct WindowKeyedAggregator<T, A, F>(pub(crate) WindowAggregator<T, A, F>);
+
+fn with_keys<A>(results: Vec<(u64, Record<A>)>) -> Vec<Record<(u64, A)>> {
+    results
+        .into_iter()
+        .map(|(window_key, record)| Record {
+            data: (window_key, record.data),
+            timestamp: record.timestamp,
+        })
+        .collect()
+}
+
+#[async_trait]
+impl<T, A, F> Operator<T, (u64, A)> for WindowKeyedAggregator<T, A, F>
+where
+    T: Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<(u64, A)>>> {
+        let results = self.0.on_record(record);
+        self.0.emit_late().await?;
+        Ok(with_keys(results))
+    }
+
+    async fn on_window_trigger(&mut self) -> StreamResult<Vec<Record<(u64, A)>>> {
+        Ok(with_keys(self.0.advance(current_time() as i64, false)))
+    }
 
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<(u64, A)>>> {
+        Ok(with_keys(self.0.advance_watermark(watermark)))
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<(u64, A)>>> {
+        Ok(with_keys(self.0.end_of_input()))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Operator::<T, A>::close(&mut self.0).await
+    }
+}
+
+/// A [`WindowAggregator`] that also emits the running aggregate of the open
+/// windows updated by each record as an early result, for
+/// [`WindowedStream::emit_every`](crate::WindowedStream::emit_every)
+pub(crate) struct WindowEarlyAggregator<T, A, F>(pub(crate) WindowAggregator<T, A, F>);
+
+impl<T, A, F> WindowEarlyAggregator<T, A, F>
+where
+    A: Clone,
+    F: Fn(A, T) -> A,
+{
+    pub(crate) fn new(aggregator: WindowAggregator<T, A, F>) -> Self {
+        Self(aggregator.track_updates())
+    }
+
+    fn finals(&self, results: Vec<(u64, Record<A>)>) -> Vec<Record<WindowedValue<A>>> {
+        let window_type = &self.0.window_config.window_type;
+        results
+            .into_iter()
+            .map(|(key, record)| Record {
+                data: WindowedValue::new(window_type, key, record.data),
+                timestamp: record.timestamp,
+            })
+            .collect()
+    }
+
+    /// The running aggregates of the open windows updated since the last call
+    fn early(&mut self) -> Vec<Record<WindowedValue<A>>> {
+        let updated = self.0.updated.replace(Vec::new()).unwrap_or_default();
+        let window_type = &self.0.window_config.window_type;
+        updated
+            .into_iter()
+            .filter(|key| self.0.open.contains(key))
+            .filter_map(|key| {
+                let aggregate = self.0.state.get(&key).flatten()?;
+                Some(Record::with_timestamp(
+                    WindowedValue::early(window_type, key, aggregate),
+                    self.0.window_timestamp(key),
+                ))
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<T, A, F> Operator<T, WindowedValue<A>> for WindowEarlyAggregator<T, A, F>
+where
+    T: Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<WindowedValue<A>>>> {
+        let fired = self.0.on_record(record);
+        self.0.emit_late().await?;
+        let mut results = self.finals(fired);
+        results.extend(self.early());
         Ok(results)
     }
+
+    async fn on_window_trigger(&mut self) -> StreamResult<Vec<Record<WindowedValue<A>>>> {
+        let fired = self.0.advance(current_time() as i64, false);
+        Ok(self.finals(fired))
+    }
+
+    async fn on_watermark(
+        &mut self,
+        watermark: i64,
+    ) -> StreamResult<Vec<Record<WindowedValue<A>>>> {
+        let fired = self.0.advance_watermark(watermark);
+        Ok(self.finals(fired))
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<WindowedValue<A>>>> {
+        let fired = self.0.end_of_input();
+        Ok(self.finals(fired))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Operator::<T, A>::close(&mut self.0).await
+    }
 }
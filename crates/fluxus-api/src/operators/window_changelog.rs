@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use fluxus_core::Gauge;
+use fluxus_transformers::Operator;
+use fluxus_utils::{
+    models::{Change, ChangeKind, Record, StreamResult},
+    window::WindowConfig,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use super::WindowAggregator;
+
+/// Emits window aggregates as a changelog keyed by window, retracting the
+/// previous result of a window whenever it is updated.
+///
+/// Records older than the latest timestamp minus the watermark delay and the
+/// allowed lateness of the window configuration are dropped, and so is the
+/// state of the windows they would belong to.
+pub struct WindowChangelogAggregator<T, A, F> {
+    aggregator: WindowAggregator<T, A, F>,
+    watermark_delay: i64,
+    lateness: i64,
+    max_timestamp: Option<i64>,
+    emitted: HashMap<u64, A>,
+}
+
+impl<T, A, F> WindowChangelogAggregator<T, A, F>
+where
+    A: Clone,
+    F: Fn(A, T) -> A,
+{
+    pub fn new(window_config: WindowConfig, init: A, f: F) -> Self {
+        let lateness = (window_config.allow_lateness + window_config.watermark_delay).as_millis();
+        Self {
+            watermark_delay: window_config.watermark_delay.as_millis() as i64,
+            aggregator: WindowAggregator::new(window_config, init, f),
+            lateness: lateness as i64,
+            max_timestamp: None,
+            emitted: HashMap::new(),
+        }
+    }
+
+    /// Publish the number of windows that hold state to a gauge
+    pub fn live_windows(mut self, gauge: Arc<Gauge>) -> Self {
+        self.aggregator = self.aggregator.live_windows(gauge);
+        self
+    }
+
+    /// Drop the state and the last result of the windows past their allowed
+    /// lateness
+    fn purge(&mut self, watermark: i64) {
+        self.aggregator.purge(watermark);
+        let aggregator = &self.aggregator;
+        self.emitted
+            .retain(|window_key, _| !aggregator.is_closed(*window_key, watermark));
+    }
+}
+
+#[async_trait]
+impl<T, A, F> Operator<T, Change<u64, A>> for WindowChangelogAggregator<T, A, F>
+where
+    T: Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+    F: Fn(A, T) -> A + Send + Sync,
+{
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<Change<u64, A>>>> {
+        let timestamp = record.timestamp;
+        if let Some(max) = self.max_timestamp
+            && timestamp < max - self.lateness
+        {
+            tracing::debug!(
+                "Dropping record at {} after the allowed lateness",
+                timestamp
+            );
+            return Ok(vec![]);
+        }
+        self.max_timestamp = Some(
+            self.max_timestamp
+                .map_or(timestamp, |max| max.max(timestamp)),
+        );
+
+        let mut changes = Vec::new();
+        for (window_key, result) in self.aggregator.update(record) {
+            let change = |kind, value| {
+                Record::with_timestamp(Change::new(kind, window_key, value), timestamp)
+            };
+            match self.emitted.insert(window_key, result.data.clone()) {
+                None => changes.push(change(ChangeKind::Insert, result.data)),
+                Some(previous) => {
+                    changes.push(change(ChangeKind::UpdateBefore, previous));
+                    changes.push(change(ChangeKind::UpdateAfter, result.data));
+                }
+            }
+        }
+        if let Some(max) = self.max_timestamp {
+            self.purge(max - self.watermark_delay);
+        }
+        Ok(changes)
+    }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<Change<u64, A>>>> {
+        self.purge(watermark);
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,69 @@
+use fluxus_core::Gauge;
+use fluxus_utils::window::WindowConfig;
+use std::sync::Arc;
+
+/// Tracks the watermark of a window operator, the latest timestamp minus the
+/// watermark delay, to tell which windows can no longer change and should
+/// drop their state
+pub(crate) struct WindowExpiry {
+    window_config: WindowConfig,
+    max_timestamp: Option<i64>,
+    live_windows: Option<Arc<Gauge>>,
+}
+
+impl WindowExpiry {
+    pub(crate) fn new(window_config: &WindowConfig) -> Self {
+        Self {
+            window_config: window_config.clone(),
+            max_timestamp: None,
+            live_windows: None,
+        }
+    }
+
+    /// Publish the number of live windows to a gauge
+    pub(crate) fn report_to(&mut self, gauge: Arc<Gauge>) {
+        self.live_windows = Some(gauge);
+    }
+
+    pub(crate) fn watermark(&self) -> Option<i64> {
+        self.max_timestamp
+            .map(|max| max - self.window_config.watermark_delay.as_millis() as i64)
+    }
+
+    /// Advance the watermark with the timestamp of a record
+    pub(crate) fn observe(&mut self, timestamp: i64) -> i64 {
+        let max = self
+            .max_timestamp
+            .map_or(timestamp, |max| max.max(timestamp));
+        self.max_timestamp = Some(max);
+        max - self.window_config.watermark_delay.as_millis() as i64
+    }
+
+    /// Whether the window with the given key is past its allowed lateness at
+    /// `watermark`. Windows without an end never expire.
+    pub(crate) fn is_expired(&self, key: u64, watermark: i64) -> bool {
+        let lateness = self.window_config.allow_lateness.as_millis() as i64;
+        self.window_config
+            .window_type
+            .window_end(key as i64)
+            .is_some_and(|end| end + lateness <= watermark)
+    }
+
+    /// The keys of the windows of a record that have not expired
+    pub(crate) fn live_keys(&self, timestamp: i64) -> Vec<u64> {
+        let keys = self.window_config.get_window_keys(timestamp);
+        match self.watermark() {
+            Some(watermark) => keys
+                .into_iter()
+                .filter(|key| !self.is_expired(*key, watermark))
+                .collect(),
+            None => keys,
+        }
+    }
+
+    pub(crate) fn publish(&self, live: usize) {
+        if let Some(gauge) = &self.live_windows {
+            gauge.set(live as i64);
+        }
+    }
+}
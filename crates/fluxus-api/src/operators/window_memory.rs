@@ -0,0 +1,45 @@
+use fluxus_utils::memory::{MemoryAccount, MemorySize};
+use fluxus_utils::models::Record;
+use std::mem::size_of;
+
+/// The account of the memory a window operator buffers, with the estimate
+/// of the heap memory of its elements
+pub(crate) struct WindowMemory<T> {
+    pub(crate) account: MemoryAccount,
+    heap_size: fn(&T) -> usize,
+}
+
+impl<T> Clone for WindowMemory<T> {
+    fn clone(&self) -> Self {
+        Self {
+            account: self.account.clone(),
+            heap_size: self.heap_size,
+        }
+    }
+}
+
+impl<T> WindowMemory<T> {
+    pub(crate) fn new(account: MemoryAccount) -> Self
+    where
+        T: MemorySize,
+    {
+        Self {
+            account,
+            heap_size: T::heap_size,
+        }
+    }
+
+    /// Heap bytes of a buffer of elements, as [`MemorySize`] for a `Vec`
+    pub(crate) fn vec_heap_size(&self, values: &Vec<T>) -> usize {
+        values.capacity() * size_of::<T>() + values.iter().map(self.heap_size).sum::<usize>()
+    }
+
+    /// Heap bytes of a buffer of records, as [`MemorySize`] for a `Vec`
+    pub(crate) fn records_heap_size(&self, records: &Vec<Record<T>>) -> usize {
+        records.capacity() * size_of::<Record<T>>()
+            + records
+                .iter()
+                .map(|record| (self.heap_size)(&record.data))
+                .sum::<usize>()
+    }
+}
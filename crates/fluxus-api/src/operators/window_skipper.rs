@@ -1,15 +1,25 @@
 use async_trait::async_trait;
+use fluxus_core::Gauge;
 use fluxus_transformers::Operator;
 use fluxus_utils::{
+    memory::{MemoryAccount, MemorySize},
     models::{Record, StreamResult},
     window::WindowConfig,
 };
-use std::{collections::HashMap, marker::PhantomData};
+use std::{collections::HashMap, marker::PhantomData, mem::size_of, sync::Arc};
 
+use super::window_expiry::WindowExpiry;
+use super::window_memory::WindowMemory;
+
+/// Emits the elements of each window after skipping its first `n`.
+///
+/// The elements of a window are dropped once the watermark passes its
+/// allowed lateness, and later elements of the window are ignored.
 pub struct WindowSkipper<T> {
-    window_config: WindowConfig,
+    expiry: WindowExpiry,
     n: usize,
     buffer: HashMap<u64, Vec<T>>,
+    memory: Option<WindowMemory<T>>,
     _phantom: PhantomData<T>,
 }
 
@@ -19,15 +29,60 @@ where
 {
     pub fn new(window_config: WindowConfig, n: usize) -> Self {
         Self {
-            window_config,
+            expiry: WindowExpiry::new(&window_config),
             n,
             buffer: HashMap::new(),
+            memory: None,
             _phantom: PhantomData,
         }
     }
 
-    fn get_window_keys(&self, timestamp: i64) -> Vec<u64> {
-        self.window_config.window_type.get_window_keys(timestamp)
+    /// Publish the number of windows that hold elements to a gauge
+    pub fn live_windows(mut self, gauge: Arc<Gauge>) -> Self {
+        self.expiry.report_to(gauge);
+        self
+    }
+
+    /// Account for the estimated memory of the elements of the windows
+    pub fn memory_account(self, account: MemoryAccount) -> Self
+    where
+        T: MemorySize,
+    {
+        self.memory(WindowMemory::new(account))
+    }
+
+    pub(crate) fn memory(mut self, memory: WindowMemory<T>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Estimated bytes of the buffer of a window
+    fn window_size(memory: &WindowMemory<T>, records: &Vec<T>) -> usize {
+        size_of::<u64>() + size_of::<Vec<T>>() + memory.vec_heap_size(records)
+    }
+
+    /// Drop the elements of the windows past their allowed lateness
+    fn purge(&mut self, watermark: i64) {
+        let expiry = &self.expiry;
+        let memory = &self.memory;
+        self.buffer.retain(|key, records| {
+            let expired = expiry.is_expired(*key, watermark);
+            if let (true, Some(memory)) = (expired, memory) {
+                memory.account.release(Self::window_size(memory, records));
+            }
+            !expired
+        });
+        self.expiry.publish(self.buffer.len());
+    }
+
+    /// Drop the elements of all windows
+    fn clear(&mut self) {
+        if let Some(memory) = &self.memory {
+            for records in self.buffer.values() {
+                memory.account.release(Self::window_size(memory, records));
+            }
+        }
+        self.buffer.clear();
     }
 }
 
@@ -39,9 +94,18 @@ where
     async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<Vec<T>>>> {
         let mut results = Vec::new();
 
-        for window_key in self.get_window_keys(record.timestamp) {
+        for window_key in self.expiry.live_keys(record.timestamp) {
             let records = self.buffer.entry(window_key).or_default();
+            let before = match &self.memory {
+                Some(memory) if !records.is_empty() => Self::window_size(memory, records),
+                _ => 0,
+            };
             records.push(record.data.clone());
+            if let Some(memory) = &self.memory {
+                memory
+                    .account
+                    .resize(before, Self::window_size(memory, records));
+            }
             let new_records = records.iter().skip(self.n).cloned().collect::<Vec<_>>();
             results.push(Record {
                 data: new_records,
@@ -49,6 +113,18 @@ where
             });
         }
 
+        let watermark = self.expiry.observe(record.timestamp);
+        self.purge(watermark);
         Ok(results)
     }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<Vec<T>>>> {
+        self.purge(watermark);
+        Ok(Vec::new())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.clear();
+        Ok(())
+    }
 }
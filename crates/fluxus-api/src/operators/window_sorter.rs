@@ -1,15 +1,23 @@
 use async_trait::async_trait;
+use fluxus_core::Gauge;
 use fluxus_runtime::state::KeyedStateBackend;
 use fluxus_transformers::Operator;
 use fluxus_utils::{
+    memory::{MemoryAccount, MemorySize},
     models::{Record, StreamResult},
     window::WindowConfig,
 };
-use std::{cmp::Ordering, marker::PhantomData};
+use std::{cmp::Ordering, marker::PhantomData, mem::size_of, sync::Arc};
+
+use super::window_expiry::WindowExpiry;
+use super::window_memory::WindowMemory;
 
 /// sort_by operator for windowed stream.
+///
+/// The elements of a window are dropped once the watermark passes its
+/// allowed lateness, and later elements of the window are ignored.
 pub struct WindowSorter<T, F> {
-    window_config: WindowConfig,
+    expiry: WindowExpiry,
     f: F,
     state: KeyedStateBackend<u64, Vec<T>>,
     _phantom: PhantomData<T>,
@@ -21,15 +29,46 @@ where
 {
     pub fn new(window_config: WindowConfig, f: F) -> Self {
         Self {
-            window_config,
+            expiry: WindowExpiry::new(&window_config),
             f,
             state: KeyedStateBackend::new(),
             _phantom: PhantomData,
         }
     }
 
-    fn get_window_keys(&self, timestamp: i64) -> Vec<u64> {
-        self.window_config.window_type.get_window_keys(timestamp)
+    /// Publish the number of windows that hold elements to a gauge
+    pub fn live_windows(mut self, gauge: Arc<Gauge>) -> Self {
+        self.expiry.report_to(gauge);
+        self
+    }
+
+    /// Account for the estimated memory of the elements of the windows
+    pub fn memory_account(self, account: MemoryAccount) -> Self
+    where
+        T: MemorySize + 'static,
+    {
+        self.memory(WindowMemory::new(account))
+    }
+
+    pub(crate) fn memory(mut self, memory: WindowMemory<T>) -> Self
+    where
+        T: 'static,
+    {
+        let account = memory.account.clone();
+        self.state = self
+            .state
+            .with_memory_estimate(account, u64::memory_size, move |values| {
+                size_of::<Vec<T>>() + memory.vec_heap_size(values)
+            });
+        self
+    }
+
+    /// Drop the elements of the windows past their allowed lateness
+    fn purge(&mut self, watermark: i64) {
+        let expiry = &self.expiry;
+        self.state
+            .retain(|key, _| !expiry.is_expired(*key, watermark));
+        self.expiry.publish(self.state.len());
     }
 }
 
@@ -42,22 +81,35 @@ where
     async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<Vec<T>>>> {
         let mut results = Vec::new();
 
-        for window_key in self.get_window_keys(record.timestamp) {
-            let mut current = self.state.get(&window_key).unwrap_or_default();
-            let index = current
-                .binary_search_by(|prob| (self.f)(prob, &record.data))
-                .unwrap_or_else(|i| i);
-            current.insert(index, record.data.clone());
-
-            self.state.set(window_key, current.clone());
+        for window_key in self.expiry.live_keys(record.timestamp) {
+            let f = &mut self.f;
+            let current = self.state.update_with(window_key, Vec::new, |current| {
+                let index = current
+                    .binary_search_by(|prob| f(prob, &record.data))
+                    .unwrap_or_else(|i| i);
+                current.insert(index, record.data.clone());
+                current.clone()
+            });
             results.push(Record {
                 data: current,
                 timestamp: record.timestamp,
             });
         }
 
+        let watermark = self.expiry.observe(record.timestamp);
+        self.purge(watermark);
         Ok(results)
     }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<Vec<T>>>> {
+        self.purge(watermark);
+        Ok(Vec::new())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.state.retain(|_, _| false);
+        Ok(())
+    }
 }
 
 /// Specify sorting method of sort_by_ts
@@ -68,8 +120,11 @@ pub enum SortOrder {
 }
 
 /// sort_by_ts operator for windowed stream.
+///
+/// The elements of a window are dropped once the watermark passes its
+/// allowed lateness, and later elements of the window are ignored.
 pub struct WindowTimestampSorter<T> {
-    window_config: WindowConfig,
+    expiry: WindowExpiry,
     method: SortOrder,
     state: KeyedStateBackend<u64, Vec<Record<T>>>,
     _phantom: PhantomData<T>,
@@ -78,15 +133,46 @@ pub struct WindowTimestampSorter<T> {
 impl<T> WindowTimestampSorter<T> {
     pub fn new(window_config: WindowConfig, method: SortOrder) -> Self {
         Self {
-            window_config,
+            expiry: WindowExpiry::new(&window_config),
             method,
             state: KeyedStateBackend::new(),
             _phantom: PhantomData,
         }
     }
 
-    fn get_window_keys(&self, timestamp: i64) -> Vec<u64> {
-        self.window_config.window_type.get_window_keys(timestamp)
+    /// Publish the number of windows that hold elements to a gauge
+    pub fn live_windows(mut self, gauge: Arc<Gauge>) -> Self {
+        self.expiry.report_to(gauge);
+        self
+    }
+
+    /// Account for the estimated memory of the elements of the windows
+    pub fn memory_account(self, account: MemoryAccount) -> Self
+    where
+        T: MemorySize + 'static,
+    {
+        self.memory(WindowMemory::new(account))
+    }
+
+    pub(crate) fn memory(mut self, memory: WindowMemory<T>) -> Self
+    where
+        T: 'static,
+    {
+        let account = memory.account.clone();
+        self.state = self
+            .state
+            .with_memory_estimate(account, u64::memory_size, move |records| {
+                size_of::<Vec<Record<T>>>() + memory.records_heap_size(records)
+            });
+        self
+    }
+
+    /// Drop the elements of the windows past their allowed lateness
+    fn purge(&mut self, watermark: i64) {
+        let expiry = &self.expiry;
+        self.state
+            .retain(|key, _| !expiry.is_expired(*key, watermark));
+        self.expiry.publish(self.state.len());
     }
 }
 
@@ -97,22 +183,25 @@ where
 {
     async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<Vec<T>>>> {
         let mut raw_results = Vec::new();
-        for window_key in self.get_window_keys(record.timestamp) {
-            let mut current = self.state.get(&window_key).unwrap_or_default();
-            let index = current
-                .binary_search_by(|prob| match self.method {
-                    SortOrder::Asc => prob.timestamp.cmp(&record.timestamp),
-                    SortOrder::Desc => record.timestamp.cmp(&prob.timestamp),
-                })
-                .unwrap_or_else(|i| i);
-            current.insert(index, record.clone());
-
-            self.state.set(window_key, current.clone());
+        for window_key in self.expiry.live_keys(record.timestamp) {
+            let method = self.method;
+            let current = self.state.update_with(window_key, Vec::new, |current| {
+                let index = current
+                    .binary_search_by(|prob| match method {
+                        SortOrder::Asc => prob.timestamp.cmp(&record.timestamp),
+                        SortOrder::Desc => record.timestamp.cmp(&prob.timestamp),
+                    })
+                    .unwrap_or_else(|i| i);
+                current.insert(index, record.clone());
+                current.clone()
+            });
             raw_results.push(Record {
                 data: current,
                 timestamp: record.timestamp,
             });
         }
+        let watermark = self.expiry.observe(record.timestamp);
+        self.purge(watermark);
         let results = raw_results
             .into_iter()
             .map(|Record { data, timestamp }| {
@@ -122,4 +211,14 @@ where
             .collect();
         Ok(results)
     }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<Vec<T>>>> {
+        self.purge(watermark);
+        Ok(Vec::new())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.state.retain(|_, _| false);
+        Ok(())
+    }
 }
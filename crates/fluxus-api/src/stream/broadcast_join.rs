@@ -0,0 +1,187 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_transformers::{TransformSource, spawn_reader};
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use super::broadcast_stream::BroadcastInner;
+
+const READ_AHEAD: usize = 64;
+
+pub(crate) type BroadcastSender<C> = mpsc::UnboundedSender<StreamResult<Record<C>>>;
+pub(crate) type BroadcastReceiver<C> = mpsc::UnboundedReceiver<StreamResult<Record<C>>>;
+
+/// Read the broadcast input on a separate task and send every element to all
+/// subscribers, unless another consumer already started it
+fn start_broadcast<C>(inner: &Mutex<BroadcastInner<C>>)
+where
+    C: Clone + Send + Sync + 'static,
+{
+    let (source, subscribers) = {
+        let mut inner = inner.lock().unwrap();
+        match inner.source.take() {
+            Some(source) => (source, std::mem::take(&mut inner.subscribers)),
+            None => return,
+        }
+    };
+
+    tokio::spawn(async move {
+        let send = |record: &Record<C>| {
+            for subscriber in &subscribers {
+                // A consumer that has finished no longer needs updates
+                let _ = subscriber.send(Ok(record.clone()));
+            }
+        };
+        let fail = |e: StreamError| {
+            tracing::error!("Broadcast source failed: {}", e);
+            for subscriber in &subscribers {
+                let message = format!("broadcast source failed: {}", e);
+                let _ = subscriber.send(Err(StreamError::Runtime(message)));
+            }
+        };
+        let mut source = source;
+        if let Err(e) = source.init().await {
+            fail(e);
+            return;
+        }
+        loop {
+            match source.next().await {
+                Ok(Some(record)) => send(&record),
+                Ok(None) | Err(StreamError::EOF) => break,
+                Err(StreamError::Wait(ms)) => {
+                    tokio::time::sleep(Duration::from_millis(ms)).await;
+                }
+                Err(e) => {
+                    fail(e);
+                    break;
+                }
+            }
+        }
+        if let Err(e) = source.close().await {
+            tracing::warn!("Failed to close broadcast source: {}", e);
+        }
+    });
+}
+
+/// Processes a stream against state built from a broadcast stream
+pub(crate) struct BroadcastJoinSource<T: Clone, C: Clone, S, B, E, R> {
+    source: Option<TransformSource<T>>,
+    broadcast: Arc<Mutex<BroadcastInner<C>>>,
+    updates: Option<BroadcastReceiver<C>>,
+    rx: Option<mpsc::Receiver<StreamResult<Record<T>>>>,
+    state: S,
+    on_broadcast: B,
+    on_element: E,
+    output: VecDeque<Record<R>>,
+    _phantom: PhantomData<R>,
+}
+
+impl<T, C, S, B, E, I, R> BroadcastJoinSource<T, C, S, B, E, R>
+where
+    T: Clone + Send + Sync + 'static,
+    C: Clone + Send + Sync + 'static,
+    B: Fn(&mut S, C),
+    E: Fn(&S, T) -> I,
+    I: IntoIterator<Item = R>,
+{
+    pub(crate) fn new(
+        source: TransformSource<T>,
+        broadcast: Arc<Mutex<BroadcastInner<C>>>,
+        updates: BroadcastReceiver<C>,
+        state: S,
+        on_broadcast: B,
+        on_element: E,
+    ) -> Self {
+        Self {
+            source: Some(source),
+            broadcast,
+            updates: Some(updates),
+            rx: None,
+            state,
+            on_broadcast,
+            on_element,
+            output: VecDeque::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    fn on_record(&mut self, record: Record<T>) {
+        let timestamp = record.timestamp;
+        let outputs = (self.on_element)(&self.state, record.data);
+        self.output.extend(
+            outputs
+                .into_iter()
+                .map(|data| Record::with_timestamp(data, timestamp)),
+        );
+    }
+}
+
+async fn recv_update<C>(
+    updates: &mut Option<BroadcastReceiver<C>>,
+) -> Option<StreamResult<Record<C>>> {
+    match updates {
+        Some(updates) => updates.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[async_trait]
+impl<T, C, S, B, E, I, R> Source<R> for BroadcastJoinSource<T, C, S, B, E, R>
+where
+    T: Clone + Send + Sync + 'static,
+    C: Clone + Send + Sync + 'static,
+    S: Send + Sync + 'static,
+    B: Fn(&mut S, C) + Send + Sync + 'static,
+    E: Fn(&S, T) -> I + Send + Sync + 'static,
+    I: IntoIterator<Item = R>,
+    R: Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match self.source.as_mut() {
+            Some(source) => source.init().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<R>>> {
+        if let Some(source) = self.source.take() {
+            start_broadcast(&self.broadcast);
+            self.rx = Some(spawn_reader(source, READ_AHEAD));
+        }
+
+        loop {
+            if let Some(record) = self.output.pop_front() {
+                return Ok(Some(record));
+            }
+            let Some(rx) = self.rx.as_mut() else {
+                return Ok(None);
+            };
+
+            tokio::select! {
+                biased;
+                update = recv_update(&mut self.updates) => match update {
+                    Some(update) => (self.on_broadcast)(&mut self.state, update?.data),
+                    None => self.updates = None,
+                },
+                item = rx.recv() => match item {
+                    Some(item) => self.on_record(item?),
+                    None => self.rx = None,
+                },
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.rx = None;
+        self.updates = None;
+        self.output.clear();
+        match self.source.take() {
+            Some(mut source) => source.close().await,
+            None => Ok(()),
+        }
+    }
+}
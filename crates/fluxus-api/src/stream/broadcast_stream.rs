@@ -0,0 +1,103 @@
+use fluxus_transformers::TransformSource;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+use super::DataStream;
+use super::broadcast_join::{BroadcastJoinSource, BroadcastReceiver, BroadcastSender};
+
+/// The broadcast input and the consumers it is delivered to
+pub(crate) struct BroadcastInner<C: Clone> {
+    pub(crate) source: Option<TransformSource<C>>,
+    pub(crate) subscribers: Vec<BroadcastSender<C>>,
+}
+
+/// A usually small stream, such as rules or a dimension table, whose elements
+/// are delivered to every stream connected to it.
+///
+/// The broadcast input is read once, starting when the first connected
+/// stream runs, so all streams must be connected before any of them runs.
+pub struct BroadcastStream<C: Clone> {
+    inner: Arc<Mutex<BroadcastInner<C>>>,
+}
+
+impl<C: Clone> Clone for BroadcastStream<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<C> BroadcastStream<C>
+where
+    C: Clone + Send + Sync + 'static,
+{
+    pub(crate) fn new(stream: DataStream<C>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(BroadcastInner {
+                source: Some(stream.into_source()),
+                subscribers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Register a consumer that receives every broadcast element
+    fn subscribe(&self) -> BroadcastReceiver<C> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.inner.lock().unwrap().subscribers.push(tx);
+        rx
+    }
+}
+
+/// A stream connected to a [`BroadcastStream`], see [`DataStream::connect`]
+pub struct BroadcastConnectedStream<T, C: Clone> {
+    stream: DataStream<T>,
+    broadcast: BroadcastStream<C>,
+    updates: BroadcastReceiver<C>,
+}
+
+impl<T, C> BroadcastConnectedStream<T, C>
+where
+    T: Clone + Send + Sync + 'static,
+    C: Clone + Send + Sync + 'static,
+{
+    pub(crate) fn new(stream: DataStream<T>, broadcast: BroadcastStream<C>) -> Self {
+        let updates = broadcast.subscribe();
+        Self {
+            stream,
+            broadcast,
+            updates,
+        }
+    }
+
+    /// Process the stream against its own copy of the broadcast state.
+    ///
+    /// `on_broadcast` folds each broadcast element into the state, starting
+    /// from `state`, and `on_element` maps each element of the stream to any
+    /// number of outputs using the current state. Broadcast elements that are
+    /// ready are applied before the next element of the stream.
+    pub fn process<S, B, E, I, R>(self, state: S, on_broadcast: B, on_element: E) -> DataStream<R>
+    where
+        S: Send + Sync + 'static,
+        B: Fn(&mut S, C) + Send + Sync + 'static,
+        E: Fn(&S, T) -> I + Send + Sync + 'static,
+        I: IntoIterator<Item = R>,
+        R: Clone + Send + Sync + 'static,
+    {
+        let Self {
+            stream,
+            broadcast,
+            updates,
+        } = self;
+        stream.wrap_source(|source| {
+            BroadcastJoinSource::new(
+                source,
+                broadcast.inner,
+                updates,
+                state,
+                on_broadcast,
+                on_element,
+            )
+        })
+    }
+}
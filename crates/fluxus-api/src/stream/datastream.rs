@@ -1,41 +1,81 @@
-use crate::operators::{FilterOperator, FlatMapOperator, MapOperator};
-use fluxus_core::ParallelConfig;
-use fluxus_sinks::Sink;
+use crate::io::MaterializedTable;
+use crate::operators::{
+    Anomaly, DeadLetter, DeadLetterRouter, DedupOperator, EnumerateOperator, Ewma, FilterOperator,
+    FlatMapAsyncOperator, FlatMapOperator, MapOperator, MaterializeOperator, NamedOperator,
+    NamedSource, QuarantineOperator, RecordsOperator, RichMapFunction, RichMapOperator,
+    RollingMean, RuleSet, ScanOperator, TeeOperator, TimeoutRouter, TimestampAssigner,
+    TryMapOperator, ValidateOperator, Validated, WatermarkSource, ZScoreAnomaly,
+};
+use fluxus_core::{DryRun, ParallelConfig, RetryStrategy};
+use fluxus_runtime::partition::Partitioning;
+use fluxus_runtime::tenancy::{TenantQuotas, Tenanted};
+use fluxus_runtime::watermark::{WatermarkGenerator, WatermarkPolicy, WatermarkStrategy};
+use fluxus_sinks::{BatchSink, BatchingSink, FanOutSink, Sink, WindowPartitionedSink};
 use fluxus_sources::Source;
 use fluxus_transformers::{
-    InnerOperator, InnerSource, Operator, TransformSource, TransformSourceWithOperator,
+    AsyncFilterSource, BatchSource, BufferedSource, CachePolicy, CoalesceSource, DispatchSource,
+    EnrichSource, InnerOperator, InnerSource, MergeOrder, MergeSorted, Operator, ParallelMapSource,
+    ReplaySource, ReplaySpeed, Route, TimeoutEvent, TimeoutSource, TransformSource,
+    TransformSourceWithOperator,
 };
 use fluxus_utils::{
-    models::{StreamError, StreamResult},
-    window::WindowConfig,
+    models::{Record, StreamError, StreamResult},
+    window::{Session, Window, WindowConfig, WindowedValue},
 };
+use std::fmt::Display;
+use std::future::Future;
+use std::hash::Hash;
+use std::ops::Add;
+use std::path::{Path, PathBuf};
 use std::sync::{
     Arc,
     atomic::{AtomicUsize, Ordering},
 };
+use std::time::Duration;
 
-use super::WindowedStream;
+use super::union::UnionSource;
+use super::{
+    BroadcastConnectedStream, BroadcastStream, ExecutionPlan, KeyedStream, OperatorInfo,
+    WindowedStream,
+};
 
 /// DataStream represents a stream of data elements
+///
+/// Linear chains such as `map`, `filter` and `sink` move elements through
+/// and accept payloads that are not `Clone`.
 pub struct DataStream<T> {
-    pub(crate) source: Arc<InnerSource<T>>,
-    pub(crate) operators: Vec<Arc<InnerOperator<T, T>>>,
+    pub(crate) source: Box<InnerSource<T>>,
+    pub(crate) operators: Vec<Box<InnerOperator<T, T>>>,
     pub(crate) parallel_config: Option<ParallelConfig>,
+    pub(crate) retry_strategy: Option<RetryStrategy>,
+    /// How long the stream may emit nothing before it no longer holds back
+    /// the watermark of a union, see [`with_idleness`](Self::with_idleness)
+    pub(crate) idleness: Option<Duration>,
+    /// How the next stage running on parallel tasks distributes the records
+    /// to them, see [`rebalance`](Self::rebalance)
+    pub(crate) partitioning: Option<Partitioning<T>>,
+    /// Stages from the source to the most recently added operator
+    pub(crate) plan: Vec<Arc<OperatorInfo>>,
 }
 
 impl<T> DataStream<T>
 where
-    T: Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
 {
     /// Create a new DataStream from a source
     pub fn new<S>(source: S) -> Self
     where
         S: Source<T> + Send + Sync + 'static,
     {
+        let info = Arc::new(OperatorInfo::for_type::<S>());
         Self {
-            source: Arc::new(source),
+            source: Box::new(NamedSource::new(source, info.clone())),
             operators: Vec::new(),
             parallel_config: None,
+            retry_strategy: None,
+            idleness: None,
+            partitioning: None,
+            plan: vec![info],
         }
     }
 
@@ -49,36 +89,101 @@ where
         self
     }
 
+    /// Hand the records out round-robin to the tasks of the next stage that
+    /// runs on parallel tasks, such as
+    /// [`WindowedStream::aggregate_with_merge`], evening out their load
+    pub fn rebalance(mut self) -> Self {
+        self.partitioning = Some(Partitioning::Rebalance);
+        self
+    }
+
+    /// Hand the records out round-robin to the local subset of the tasks of
+    /// the next stage that runs on parallel tasks. The stream is read by a
+    /// single task, whose subset is all tasks, so this distributes records
+    /// like [`rebalance`](Self::rebalance).
+    pub fn rescale(mut self) -> Self {
+        self.partitioning = Some(Partitioning::Rescale);
+        self
+    }
+
+    /// Send the records with the same hash to the same task of the next
+    /// stage that runs on parallel tasks, so that each task sees all records
+    /// of its keys
+    pub fn partition_by<F>(mut self, hash_fn: F) -> Self
+    where
+        F: Fn(&T) -> u64 + Send + Sync + 'static,
+    {
+        self.partitioning = Some(Partitioning::hash(hash_fn));
+        self
+    }
+
+    /// Set the retry strategy used by fallible operators added after this call
+    pub fn retry(mut self, strategy: RetryStrategy) -> Self {
+        self.retry_strategy = Some(strategy);
+        self
+    }
+
+    /// Name the most recently added operator in metrics, logs and the execution plan
+    pub fn name<S: Into<String>>(self, name: S) -> Self {
+        if let Some(stage) = self.plan.last() {
+            stage.set_name(name);
+        }
+        self
+    }
+
+    /// Assign a stable identifier to the most recently added operator
+    pub fn uid<S: Into<String>>(self, uid: S) -> Self {
+        if let Some(stage) = self.plan.last() {
+            stage.set_uid(uid);
+        }
+        self
+    }
+
+    /// The stages of the stream, sharing their counters with the running stream
+    pub fn plan(&self) -> ExecutionPlan {
+        ExecutionPlan::new(self.plan.clone())
+    }
+
     /// Apply a map transformation
     pub fn map<F, R>(self, f: F) -> DataStream<R>
     where
         F: Fn(T) -> R + Send + Sync + 'static,
-        R: Clone + Send + Sync + 'static,
+        R: Send + Sync + 'static,
     {
         let mapper = MapOperator::new(f);
         self.transform(mapper)
     }
 
     /// Apply a filter transformation
-    pub fn filter<F>(mut self, f: F) -> Self
+    pub fn filter<F>(self, f: F) -> Self
     where
         F: Fn(&T) -> bool + Send + Sync + 'static,
     {
-        let filter = FilterOperator::new(f);
-        self.operators.push(Arc::new(filter));
-        self
+        self.push_operator(FilterOperator::new(f))
     }
 
     /// Apply a flat map transformation
     pub fn flat_map<F, R, I>(self, f: F) -> DataStream<R>
     where
         F: Fn(T) -> I + Send + Sync + 'static,
-        R: Clone + Send + Sync + 'static,
+        R: Send + Sync + 'static,
         I: IntoIterator<Item = R> + Send + Sync + 'static,
     {
         self.transform(FlatMapOperator::new(f))
     }
 
+    /// Apply a flat map transformation whose outputs are computed asynchronously,
+    /// e.g. fetching a file and expanding it into its entries
+    pub fn flat_map_async<F, Fut, R, I>(self, f: F) -> DataStream<R>
+    where
+        F: Fn(T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = I> + Send + 'static,
+        R: Send + Sync + 'static,
+        I: IntoIterator<Item = R> + Send + 'static,
+    {
+        self.transform(FlatMapAsyncOperator::new(f))
+    }
+
     /// Apply a limit transformation that keeps the first n elements
     pub fn limit(self, n: usize) -> Self {
         let n = AtomicUsize::new(n);
@@ -92,26 +197,95 @@ where
         })
     }
 
+    /// Use the event time extracted from each element, in milliseconds, as its timestamp
+    pub fn assign_timestamps<F>(self, f: F) -> Self
+    where
+        F: Fn(&T) -> i64 + Send + Sync + 'static,
+    {
+        self.push_operator(TimestampAssigner::new(f))
+    }
+
+    /// Track the event-time watermark with the given strategy, dropping
+    /// elements whose timestamp is already behind the watermark. Operators
+    /// downstream see the watermark as it advances, so that windows fire
+    /// once it passes their end.
+    pub fn with_watermark(self, strategy: WatermarkStrategy) -> Self {
+        self.with_watermark_policy(WatermarkGenerator::new(strategy))
+    }
+
+    /// Stop holding back the watermark of a [`union`](Self::union) while
+    /// the stream emitted nothing for `idleness`, so that an input that
+    /// stalls doesn't keep windows downstream from firing. The stream counts
+    /// again once it emits.
+    pub fn with_idleness(mut self, idleness: Duration) -> Self {
+        self.idleness = Some(idleness);
+        self
+    }
+
+    /// Like [`with_watermark`](Self::with_watermark), with a watermark
+    /// decided by a policy such as [`Punctuated`](fluxus_runtime::watermark::Punctuated)
+    pub fn with_watermark_policy<P>(self, policy: P) -> Self
+    where
+        P: WatermarkPolicy<T> + 'static,
+    {
+        self.wrap_source(|source| WatermarkSource::new(source, policy))
+    }
+
+    /// Pair each element with a monotonically increasing sequence number
+    pub fn enumerate(self) -> DataStream<(u64, T)> {
+        self.transform(EnumerateOperator::new())
+    }
+
+    /// Compute up to `capacity` elements ahead on a separate task, overlapping
+    /// upstream reading and parsing with downstream processing
+    pub fn prefetch(self, capacity: usize) -> Self {
+        self.wrap_source(|source| BufferedSource::new(source, capacity))
+    }
+
     /// Transform the stream using a custom operator
     pub fn transform<O, R>(self, operator: O) -> DataStream<R>
     where
         O: Operator<T, R> + Send + Sync + 'static,
-        R: Clone + Send + Sync + 'static,
+        R: Send + Sync + 'static,
     {
+        let info = Arc::new(OperatorInfo::for_type::<O>());
+        self.transform_with_info(operator, info)
+    }
+
+    /// Transform the stream, recording the operator under the given stage
+    pub(crate) fn transform_with_info<O, R>(
+        mut self,
+        operator: O,
+        info: Arc<OperatorInfo>,
+    ) -> DataStream<R>
+    where
+        O: Operator<T, R> + Send + Sync + 'static,
+        R: Send + Sync + 'static,
+    {
+        let operator = NamedOperator::new(operator, info.clone());
+        self.plan.push(info);
         let source = TransformSourceWithOperator::new(self.source, operator, self.operators);
         DataStream {
-            source: Arc::new(source),
+            source: Box::new(source),
             operators: Vec::new(),
             parallel_config: self.parallel_config,
+            retry_strategy: self.retry_strategy,
+            idleness: self.idleness,
+            partitioning: None,
+            plan: self.plan,
         }
     }
 
-    /// Apply windowing to the stream
-    pub fn window(self, config: WindowConfig) -> WindowedStream<T> {
-        WindowedStream {
-            stream: self,
-            window_config: config,
-        }
+    /// Add an operator that keeps the element type to the pending operators
+    fn push_operator<O>(mut self, operator: O) -> Self
+    where
+        O: Operator<T, T> + Send + Sync + 'static,
+    {
+        let info = Arc::new(OperatorInfo::for_type::<O>());
+        self.operators
+            .push(Box::new(NamedOperator::new(operator, info.clone())));
+        self.plan.push(info);
+        self
     }
 
     /// Write the stream to a sink
@@ -119,25 +293,568 @@ where
     where
         K: Sink<T> + Send + Sync + 'static,
     {
-        let mut source = TransformSource::new(self.source);
-        source.set_operators(self.operators);
+        let mut source = self.into_source();
+        source.init().await?;
 
-        loop {
-            match source.next().await {
-                Ok(Some(record)) => sink.write(record).await?,
-                Ok(None) => break,
-                Err(e) => match e {
-                    StreamError::EOF => break,
-                    StreamError::Wait(ms) => {
+        let result = async {
+            sink.init().await?;
+            loop {
+                match source.next().await {
+                    Ok(Some(record)) => sink.write(record).await?,
+                    Ok(None) => break,
+                    Err(e) => match e {
+                        StreamError::EOF => break,
+                        StreamError::Wait(ms) => {
+                            tokio::time::sleep(std::time::Duration::from_millis(ms)).await
+                        }
+                        _ => return Err(e),
+                    },
+                }
+            }
+            sink.flush().await?;
+            sink.close().await
+        }
+        .await;
+
+        // Operators release their resources even if the pipeline failed
+        let closed = source.close().await;
+        result.and(closed)
+    }
+
+    /// Check the stream before running it.
+    ///
+    /// Initializes the source, operators and sink, then processes records until
+    /// `sample` records are produced or the source ends. Nothing is written to
+    /// the sink, and every component is closed again.
+    pub async fn dry_run<K>(self, mut sink: K, sample: usize) -> StreamResult<DryRun<T>>
+    where
+        K: Sink<T> + Send + Sync + 'static,
+    {
+        let source_stage = self.plan.first().cloned();
+        let mut source = self.into_source();
+        source.init().await?;
+
+        let result = async {
+            sink.init().await?;
+            let mut output = Vec::new();
+            while output.len() < sample {
+                match source.next().await {
+                    Ok(Some(record)) => output.push(record),
+                    Ok(None) | Err(StreamError::EOF) => break,
+                    Err(StreamError::Wait(ms)) => {
                         tokio::time::sleep(std::time::Duration::from_millis(ms)).await
                     }
-                    _ => return Err(e),
-                },
+                    Err(e) => return Err(e),
+                }
             }
+            sink.close().await?;
+            Ok(output)
+        }
+        .await;
+
+        let closed = source.close().await;
+        let output = result.and_then(|output| closed.map(|_| output))?;
+        Ok(DryRun {
+            records_read: source_stage.map_or(0, |stage| stage.records_out() as usize),
+            output,
+        })
+    }
+
+    /// Collapse the source and its pending operators into a single source
+    pub(crate) fn into_source(self) -> TransformSource<T> {
+        let mut source = TransformSource::new(self.source);
+        source.set_operators(self.operators);
+        source
+    }
+
+    /// Wrap the collapsed source into a new source, keeping the stream settings
+    pub(crate) fn wrap_source<R, S, W>(self, wrap: W) -> DataStream<R>
+    where
+        R: Send + 'static,
+        S: Source<R> + Send + Sync + 'static,
+        W: FnOnce(TransformSource<T>) -> S,
+    {
+        let parallel_config = self.parallel_config.clone();
+        let retry_strategy = self.retry_strategy.clone();
+        let idleness = self.idleness;
+        let mut plan = self.plan.clone();
+        let info = Arc::new(OperatorInfo::for_type::<S>());
+        plan.push(info.clone());
+        DataStream {
+            source: Box::new(NamedSource::new(wrap(self.into_source()), info)),
+            operators: Vec::new(),
+            parallel_config,
+            retry_strategy,
+            idleness,
+            partitioning: None,
+            plan,
+        }
+    }
+}
+
+// Operations that may hand the same element to several consumers
+impl<T> DataStream<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Apply a map function that is opened before the first record and
+    /// closed once the stream ends
+    pub fn map_rich<F, R>(self, f: F) -> DataStream<R>
+    where
+        F: RichMapFunction<T, R> + 'static,
+        R: Clone + Send + Sync + 'static,
+    {
+        let parallelism = self
+            .parallel_config
+            .as_ref()
+            .map_or(1, |config| config.parallelism);
+        let info = Arc::new(OperatorInfo::for_type::<F>());
+        let operator = RichMapOperator::new(f, parallelism).with_info(info.clone());
+        self.transform_with_info(operator, info)
+    }
+
+    /// Apply a fallible map transformation.
+    ///
+    /// Failed records are retried with the strategy set by [`DataStream::retry`].
+    /// If all attempts fail, the input record is emitted with the last error.
+    pub fn try_map<F, R, E>(self, f: F) -> DataStream<Result<R, (T, E)>>
+    where
+        F: Fn(T) -> Result<R, E> + Send + Sync + 'static,
+        R: Clone + Send + Sync + 'static,
+        E: Clone + Send + Sync + 'static,
+    {
+        let strategy = self
+            .retry_strategy
+            .clone()
+            .unwrap_or(RetryStrategy::NoRetry);
+        self.transform(TryMapOperator::new(f, strategy))
+    }
+
+    /// Split the stream in two: matching elements go to the first stream, the rest to the second.
+    /// Both streams share the upstream source and should be consumed concurrently.
+    pub fn split<F>(self, f: F) -> (DataStream<T>, DataStream<T>)
+    where
+        F: Fn(&T) -> bool + Send + Sync + 'static,
+    {
+        let parallel_config = self.parallel_config.clone();
+        let retry_strategy = self.retry_strategy.clone();
+        let idleness = self.idleness;
+        let partitioning = self.partitioning.clone();
+        let plan = self.plan.clone();
+        let route = move |data: &T| if f(data) { Route::To(0) } else { Route::To(1) };
+        let mut outputs = DispatchSource::new(self.into_source(), 2, route).into_iter();
+        let mut next_stream = || DataStream {
+            source: Box::new(outputs.next().expect("two outputs")),
+            operators: Vec::new(),
+            parallel_config: parallel_config.clone(),
+            retry_strategy: retry_strategy.clone(),
+            idleness,
+            partitioning: partitioning.clone(),
+            plan: plan.clone(),
+        };
+        (next_stream(), next_stream())
+    }
+
+    /// Merge streams into one stream, emitting the elements of all streams
+    /// in the order they arrive.
+    ///
+    /// The watermark of the union is the smallest watermark of the streams
+    /// that neither ended nor are idle, see [`with_idleness`](Self::with_idleness).
+    pub fn union(self, others: Vec<DataStream<T>>) -> Self {
+        let others: Vec<_> = others
+            .into_iter()
+            .map(|other| {
+                let idleness = other.idleness;
+                (other.into_source(), idleness)
+            })
+            .collect();
+        let idleness = self.idleness;
+        let mut union = self.wrap_source(|source| {
+            UnionSource::new(std::iter::once((source, idleness)).chain(others).collect())
+        });
+        union.idleness = None;
+        union
+    }
+
+    /// Merge timestamp-ordered streams into one stream ordered by timestamp
+    pub fn merge_sorted(self, others: Vec<DataStream<T>>) -> Self {
+        let others: Vec<_> = others.into_iter().map(DataStream::into_source).collect();
+        self.wrap_source(|source| {
+            others.into_iter().fold(
+                MergeSorted::default().with_source(source),
+                MergeSorted::with_source,
+            )
+        })
+    }
+
+    /// Write a copy of every element to a sink while the stream continues
+    pub fn tee<K>(self, sink: K) -> Self
+    where
+        K: Sink<T> + Send + Sync + 'static,
+    {
+        self.push_operator(TeeOperator::new(sink))
+    }
+
+    /// Keep the latest element per key in a table that can be queried while
+    /// the stream runs, passing every element on unchanged
+    pub fn materialize<K, F>(self, key_fn: F, table: MaterializedTable<K, T>) -> Self
+    where
+        K: Eq + Hash + Send + Sync + 'static,
+        F: Fn(&T) -> K + Send + Sync + 'static,
+    {
+        self.push_operator(MaterializeOperator::new(key_fn, table))
+    }
+
+    /// Emit the running accumulator after every element
+    pub fn scan<A, F>(self, init: A, f: F) -> DataStream<A>
+    where
+        A: Clone + Send + Sync + 'static,
+        F: Fn(A, T) -> A + Send + Sync + 'static,
+    {
+        self.transform(ScanOperator::new(init, f))
+    }
+
+    /// Running count of elements
+    pub fn count(self) -> DataStream<u64> {
+        self.scan(0, |count, _| count + 1)
+    }
+
+    /// Mean of the values of the last `n` elements after every element
+    pub fn rolling_mean<F>(self, n: usize, f: F) -> DataStream<f64>
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        self.transform(RollingMean::new(n, f))
+    }
+
+    /// Exponentially weighted moving average of the values of the elements
+    /// after every element, with the latest value weighted by `alpha`
+    pub fn ewma<F>(self, alpha: f64, f: F) -> DataStream<f64>
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        self.transform(Ewma::new(alpha, f))
+    }
+
+    /// Keep the elements whose value is more than `threshold` standard
+    /// deviations from the mean of the values before it, with their z-score
+    pub fn zscore_anomaly<F>(self, threshold: f64, f: F) -> DataStream<Anomaly<T>>
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        self.transform(ZScoreAnomaly::new(threshold, f))
+    }
+
+    /// Running sum of the values extracted from each element
+    pub fn sum_by<F, N>(self, f: F) -> DataStream<N>
+    where
+        F: Fn(&T) -> N + Send + Sync + 'static,
+        N: Add<Output = N> + Default + Clone + Send + Sync + 'static,
+    {
+        self.scan(N::default(), move |sum, t| sum + f(&t))
+    }
+
+    /// Running minimum element by the extracted key
+    pub fn min_by_key<F, K>(self, f: F) -> Self
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Ord,
+    {
+        self.scan(None, move |min: Option<T>, t| match min {
+            Some(min) if f(&min) <= f(&t) => Some(min),
+            _ => Some(t),
+        })
+        .flatten_options()
+    }
+
+    /// Running maximum element by the extracted key
+    pub fn max_by_key<F, K>(self, f: F) -> Self
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Ord,
+    {
+        self.scan(None, move |max: Option<T>, t| match max {
+            Some(max) if f(&max) >= f(&t) => Some(max),
+            _ => Some(t),
+        })
+        .flatten_options()
+    }
+
+    /// Running mean of the values extracted from each element
+    pub fn mean_by<F>(self, f: F) -> DataStream<f64>
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        self.scan((0.0, 0u64), move |(sum, count), t| (sum + f(&t), count + 1))
+            .map(|(sum, count)| sum / count as f64)
+    }
+
+    /// Drop consecutive duplicate elements
+    pub fn dedup(self) -> Self
+    where
+        T: PartialEq,
+    {
+        self.dedup_by_key(|_| ())
+    }
+
+    /// Drop elements equal to the previous element with the same key
+    pub fn dedup_by_key<F, K>(self, f: F) -> Self
+    where
+        T: PartialEq,
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Eq + Hash + Send + Sync + 'static,
+    {
+        self.transform(DedupOperator::new(f))
+    }
+
+    /// Tag each element with the tenant returned by the key function
+    pub fn with_tenant<F>(self, f: F) -> DataStream<Tenanted<T>>
+    where
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        self.map(move |data| Tenanted::new(f(&data), data))
+    }
+
+    /// Keep each record with the given probability (0.0 to 1.0)
+    pub fn sample(self, ratio: f64) -> Self {
+        let ratio = ratio.clamp(0.0, 1.0);
+        self.filter(move |_| rand::random::<f64>() < ratio)
+    }
+
+    /// Keep every n-th record, starting with the first one
+    pub fn sample_every(self, n: usize) -> Self {
+        let n = n.max(1);
+        let seen = AtomicUsize::new(0);
+        self.filter(move |_| seen.fetch_add(1, Ordering::SeqCst).is_multiple_of(n))
+    }
+
+    /// Filter with an async predicate, e.g. one that performs I/O
+    pub fn filter_async<F, Fut>(self, f: F) -> Self
+    where
+        F: Fn(&T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.filter_async_with(1, MergeOrder::Ordered, f)
+    }
+
+    /// Filter with an async predicate, evaluating up to `concurrency` predicates at once
+    pub fn filter_async_with<F, Fut>(self, concurrency: usize, order: MergeOrder, f: F) -> Self
+    where
+        F: Fn(&T) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = bool> + Send + 'static,
+    {
+        self.wrap_source(|source| AsyncFilterSource::new(source, concurrency, order, f))
+    }
+
+    /// Pair each element with the result of an async lookup in an external
+    /// store, cached by the key returned by `key_fn`.
+    ///
+    /// Elements the lookup finds nothing for are handled by the miss policy
+    /// of `policy`.
+    pub fn enrich<K, V, KF, L, Fut>(
+        self,
+        key_fn: KF,
+        lookup: L,
+        policy: CachePolicy<V>,
+    ) -> DataStream<(T, Option<V>)>
+    where
+        K: Eq + Hash + Clone + Send + 'static,
+        V: Clone + Send + Sync + 'static,
+        KF: Fn(&T) -> K + Send + Sync + 'static,
+        L: Fn(K) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = StreamResult<Option<V>>> + Send + 'static,
+    {
+        self.wrap_source(|source| EnrichSource::new(source, key_fn, lookup, policy))
+    }
+
+    /// Apply a CPU-heavy function, such as parsing raw lines, on `workers` tasks in parallel
+    pub fn map_parallel<F, R>(self, workers: usize, order: MergeOrder, f: F) -> DataStream<R>
+    where
+        F: Fn(T) -> R + Send + Sync + 'static,
+        R: Clone + Send + Sync + 'static,
+    {
+        self.wrap_source(|source| ParallelMapSource::new(source, workers, order, f))
+    }
+
+    /// Keep only the latest element per key and emit the retained elements
+    /// once every `flush_interval`, e.g. to reduce the writes of fast-updating
+    /// results to a sink
+    pub fn coalesce_by<F, K>(self, key_fn: F, flush_interval: Duration) -> Self
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        self.wrap_source(|source| CoalesceSource::new(source, key_fn, flush_interval))
+    }
+
+    /// Group records into batches of at most `max_size` elements, emitting a
+    /// partial batch once `max_wait` has passed since its first record
+    pub fn batch(self, max_size: usize, max_wait: Duration) -> DataStream<Vec<T>> {
+        self.wrap_source(|source| BatchSource::new(source, max_size, max_wait))
+    }
+
+    /// Replay the elements at the pace of their timestamps, e.g. to run a
+    /// historical dataset through time windows as it arrived live
+    pub fn replay(self, speed: ReplaySpeed) -> Self {
+        self.wrap_source(|source| ReplaySource::new(source, speed))
+    }
+
+    /// Emit a timeout marker whenever no record arrives within `duration`
+    pub fn timeout(self, duration: Duration) -> DataStream<TimeoutEvent<T>> {
+        self.wrap_source(|source| TimeoutSource::new(source, duration))
+    }
+
+    /// Detect silence longer than `duration`, writing the idle time to a side output sink
+    pub fn timeout_to<K>(self, duration: Duration, timeouts: K) -> Self
+    where
+        K: Sink<Duration> + Send + Sync + 'static,
+    {
+        self.timeout(duration)
+            .transform(TimeoutRouter::new(timeouts))
+    }
+
+    /// Check every record against the rules and tag it with the violated rule names
+    pub fn validate(self, rules: impl Into<RuleSet<T>>) -> DataStream<Validated<T>> {
+        self.transform(ValidateOperator::new(rules.into()))
+    }
+
+    /// Check every record against the rules, routing violating records to a quarantine sink
+    pub fn validate_or_quarantine<K>(self, rules: impl Into<RuleSet<T>>, quarantine: K) -> Self
+    where
+        K: Sink<Validated<T>> + Send + Sync + 'static,
+    {
+        self.push_operator(QuarantineOperator::new(rules.into(), quarantine))
+    }
+
+    /// Deliver every element to all streams connected to the returned
+    /// broadcast stream, e.g. to share rules or a dimension table
+    pub fn broadcast(self) -> BroadcastStream<T> {
+        BroadcastStream::new(self)
+    }
+
+    /// Connect to a broadcast stream to process elements against state built
+    /// from its elements
+    pub fn connect<C>(self, broadcast: &BroadcastStream<C>) -> BroadcastConnectedStream<T, C>
+    where
+        C: Clone + Send + Sync + 'static,
+    {
+        BroadcastConnectedStream::new(self, broadcast.clone())
+    }
+
+    /// Partition the stream by the key extracted from each element
+    pub fn key_by<K, F>(self, f: F) -> KeyedStream<T, K>
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        KeyedStream {
+            stream: self,
+            key: Arc::new(f),
+        }
+    }
+
+    /// Collect the elements of each key into sessions, emitting a
+    /// `(key, session)` record once the key was inactive for `gap`
+    pub fn sessionize<K, F>(self, key_fn: F, gap: Duration) -> DataStream<(K, Session<T>)>
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        self.transform(RecordsOperator)
+            .key_by(move |record: &Record<T>| key_fn(&record.data))
+            .window(WindowConfig::session(gap))
+            .aggregate(Vec::new(), |mut records: Vec<Record<T>>, record| {
+                records.push(record);
+                records
+            })
+            .map(|(key, mut records)| {
+                records.sort_by_key(|record| record.timestamp);
+                let start = records.first().map_or(0, |record| record.timestamp);
+                let end = records.last().map_or(start, |record| record.timestamp);
+                let events = records.into_iter().map(|record| record.data).collect();
+                (key, Session { start, end, events })
+            })
+    }
+
+    /// Apply windowing to the stream
+    pub fn window(self, config: WindowConfig) -> WindowedStream<T> {
+        WindowedStream {
+            stream: self,
+            window_config: config,
+            coalesce: None,
+            emit_partial: false,
+            emit_every: None,
+            assigner: None,
+            live_windows: None,
+            memory: None,
+            late: None,
+            trigger: None,
         }
+    }
+
+    /// Write the stream to several sinks, consuming the source once
+    pub async fn sink_all(self, sinks: Vec<Box<dyn Sink<T> + Send + Sync>>) -> StreamResult<()> {
+        self.sink(FanOutSink::new(sinks)).await
+    }
+
+    /// Write the stream to a [`BatchSink`] in batches of up to `batch_size`
+    /// records, or fewer once `max_wait` passed since the previous batch.
+    /// Use a [`BatchingSink`] with [`sink`](Self::sink) to be called back
+    /// when batches are flushed.
+    pub async fn sink_batch<K>(
+        self,
+        sink: K,
+        batch_size: usize,
+        max_wait: Duration,
+    ) -> StreamResult<()>
+    where
+        K: BatchSink<T> + Send + Sync + 'static,
+    {
+        self.sink(BatchingSink::new(sink, batch_size, max_wait))
+            .await
+    }
+}
+
+impl<T, I, E> DataStream<Result<T, (I, E)>>
+where
+    T: Clone + Send + Sync + 'static,
+    I: Clone + Send + Sync + 'static,
+    E: Display + Clone + Send + Sync + 'static,
+{
+    /// Continue with the `Ok` values, writing the failed input and the error
+    /// of `Err` values, as returned by [`DataStream::try_map`], to a
+    /// dead-letter sink
+    pub fn ok_or_dead_letter<K>(self, dlq: K) -> DataStream<T>
+    where
+        K: Sink<DeadLetter<I>> + Send + Sync + 'static,
+    {
+        self.transform(DeadLetterRouter::new(dlq))
+    }
+}
+
+impl<T> DataStream<Tenanted<T>>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Drop records of tenants that exceeded their quota
+    pub fn enforce_quotas(self, quotas: TenantQuotas) -> Self {
+        self.filter(move |record| {
+            let admitted = quotas.admit(&record.tenant);
+            if !admitted {
+                tracing::debug!("Tenant {} exceeded its quota", record.tenant);
+            }
+            admitted
+        })
+    }
+}
 
-        sink.flush().await?;
-        sink.close().await
+impl<T> DataStream<Option<T>>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Unwrap the present values, dropping `None`
+    pub(crate) fn flatten_options(self) -> DataStream<T> {
+        self.flat_map(|value| value)
     }
 }
 
@@ -150,3 +867,21 @@ where
         self.transform(FlatMapOperator::new(|v| v))
     }
 }
+
+impl<K, A> DataStream<WindowedValue<(K, A)>>
+where
+    K: Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+{
+    /// Write the results of each window and key to a sink of its own at the
+    /// path returned by `path`, e.g. one file per hour and sensor, creating
+    /// the sinks with `factory`. See [`WindowPartitionedSink`].
+    pub async fn sink_partitioned<S, F, P>(self, factory: F, path: P) -> StreamResult<()>
+    where
+        S: Sink<WindowedValue<(K, A)>> + Send + Sync + 'static,
+        F: Fn(&Path) -> S + Send + Sync + 'static,
+        P: Fn(Window, &K) -> PathBuf + Send + Sync + 'static,
+    {
+        self.sink(WindowPartitionedSink::new(factory, path)).await
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Add;
+
+use super::keyed_stream::KeyFn;
+use super::{DataStream, WindowedStream};
+
+/// A windowed stream whose elements are aggregated separately per key, see
+/// [`WindowedStream::group_by`]
+pub struct GroupedWindowedStream<T, K> {
+    pub(crate) windowed: WindowedStream<T>,
+    pub(crate) key: KeyFn<T, K>,
+}
+
+/// The aggregates of the keys of a window, in the order the keys first appeared
+#[derive(Clone)]
+struct Groups<K, A> {
+    keys: Vec<K>,
+    aggregates: HashMap<K, A>,
+}
+
+impl<K, A> Groups<K, A>
+where
+    K: Eq + Hash + Clone,
+{
+    fn new() -> Self {
+        Self {
+            keys: Vec::new(),
+            aggregates: HashMap::new(),
+        }
+    }
+
+    fn update<F>(&mut self, key: K, init: &A, f: F)
+    where
+        A: Clone,
+        F: FnOnce(A) -> A,
+    {
+        let aggregate = match self.aggregates.remove(&key) {
+            Some(aggregate) => aggregate,
+            None => {
+                self.keys.push(key.clone());
+                init.clone()
+            }
+        };
+        self.aggregates.insert(key, f(aggregate));
+    }
+
+    fn into_entries(mut self) -> Vec<(K, A)> {
+        self.keys
+            .into_iter()
+            .filter_map(|key| self.aggregates.remove(&key).map(|a| (key, a)))
+            .collect()
+    }
+}
+
+impl<T, K> GroupedWindowedStream<T, K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    /// Aggregate the values of each key in the window, emitting a
+    /// `(key, aggregate)` record per key whenever the window emits
+    pub fn aggregate<A, F>(self, init: A, f: F) -> DataStream<(K, A)>
+    where
+        A: Clone + Send + Sync + 'static,
+        F: Fn(A, T) -> A + Send + Sync + 'static,
+    {
+        let key = self.key;
+        self.windowed
+            .aggregate(Groups::new(), move |mut groups, t| {
+                groups.update(key(&t), &init, |aggregate| f(aggregate, t));
+                groups
+            })
+            .flat_map(Groups::into_entries)
+    }
+
+    /// Count the values of each key in the window
+    pub fn count(self) -> DataStream<(K, u64)> {
+        self.aggregate(0, |count, _| count + 1)
+    }
+
+    /// Sum the values extracted from the elements of each key in the window
+    pub fn sum_by<F, N>(self, f: F) -> DataStream<(K, N)>
+    where
+        F: Fn(&T) -> N + Send + Sync + 'static,
+        N: Add<Output = N> + Default + Clone + Send + Sync + 'static,
+    {
+        self.aggregate(N::default(), move |sum, t| sum + f(&t))
+    }
+}
@@ -0,0 +1,219 @@
+use async_trait::async_trait;
+use fluxus_runtime::watermark::{WatermarkGenerator, WatermarkStrategy};
+use fluxus_sources::Source;
+use fluxus_transformers::{TransformSource, spawn_reader};
+use fluxus_utils::models::{Record, StreamResult};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use tokio::sync::mpsc;
+
+use super::keyed_stream::KeyFn;
+
+const READ_AHEAD: usize = 64;
+
+type Receiver<X> = mpsc::Receiver<StreamResult<Record<X>>>;
+
+/// One input of the join with the elements that may still find a match
+struct Side<X: Clone, K> {
+    source: Option<TransformSource<X>>,
+    key: KeyFn<X, K>,
+    rx: Option<Receiver<X>>,
+    buffer: HashMap<K, Vec<Record<X>>>,
+    watermark: WatermarkGenerator,
+}
+
+impl<X, K> Side<X, K>
+where
+    X: Clone + Send + Sync + 'static,
+    K: Eq + Hash,
+{
+    fn new(source: TransformSource<X>, key: KeyFn<X, K>, strategy: WatermarkStrategy) -> Self {
+        Self {
+            source: Some(source),
+            key,
+            rx: None,
+            buffer: HashMap::new(),
+            watermark: WatermarkGenerator::new(strategy),
+        }
+    }
+
+    /// Drop buffered elements for which `expired` holds
+    fn prune<P: Fn(i64) -> bool>(&mut self, expired: P) {
+        self.buffer.retain(|_, records| {
+            records.retain(|record| !expired(record.timestamp));
+            !records.is_empty()
+        });
+    }
+}
+
+async fn recv<X>(rx: &mut Option<Receiver<X>>) -> Option<StreamResult<Record<X>>> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Joins two keyed inputs on elements with the same key whose timestamps
+/// differ by an amount within `[lower, upper]`
+pub(crate) struct IntervalJoinSource<T: Clone, U: Clone, K, R, F> {
+    left: Side<T, K>,
+    right: Side<U, K>,
+    lower: i64,
+    upper: i64,
+    f: F,
+    output: VecDeque<Record<R>>,
+}
+
+impl<T, U, K, R, F> IntervalJoinSource<T, U, K, R, F>
+where
+    T: Clone + Send + Sync + 'static,
+    U: Clone + Send + Sync + 'static,
+    K: Eq + Hash,
+    F: Fn(&T, &U) -> R,
+{
+    pub(crate) fn new(
+        left: (TransformSource<T>, KeyFn<T, K>),
+        right: (TransformSource<U>, KeyFn<U, K>),
+        (lower, upper): (i64, i64),
+        watermark: WatermarkStrategy,
+        f: F,
+    ) -> Self {
+        Self {
+            left: Side::new(left.0, left.1, watermark),
+            right: Side::new(right.0, right.1, watermark),
+            lower,
+            upper,
+            f,
+            output: VecDeque::new(),
+        }
+    }
+
+    fn on_left(&mut self, record: Record<T>) {
+        let ts = record.timestamp;
+        if self.left.watermark.is_late(ts) {
+            tracing::debug!("Dropping late left element at {}", ts);
+            return;
+        }
+        let before = self.left.watermark.current();
+        let watermark = self.left.watermark.on_event(ts);
+
+        let key = (self.left.key)(&record.data);
+        if let Some(rights) = self.right.buffer.get(&key) {
+            for right in rights {
+                let offset = right.timestamp - ts;
+                if (self.lower..=self.upper).contains(&offset) {
+                    let data = (self.f)(&record.data, &right.data);
+                    let timestamp = ts.max(right.timestamp);
+                    self.output
+                        .push_back(Record::with_timestamp(data, timestamp));
+                }
+            }
+        }
+        // Keep the element only while future right elements can still match it
+        let right_watermark = self.right.watermark.current();
+        if right_watermark.is_none_or(|wm| ts + self.upper >= wm) {
+            self.left.buffer.entry(key).or_default().push(record);
+        }
+
+        if before != Some(watermark) {
+            let lower = self.lower;
+            self.right.prune(|rts| rts - lower < watermark);
+        }
+    }
+
+    fn on_right(&mut self, record: Record<U>) {
+        let ts = record.timestamp;
+        if self.right.watermark.is_late(ts) {
+            tracing::debug!("Dropping late right element at {}", ts);
+            return;
+        }
+        let before = self.right.watermark.current();
+        let watermark = self.right.watermark.on_event(ts);
+
+        let key = (self.right.key)(&record.data);
+        if let Some(lefts) = self.left.buffer.get(&key) {
+            for left in lefts {
+                let offset = ts - left.timestamp;
+                if (self.lower..=self.upper).contains(&offset) {
+                    let data = (self.f)(&left.data, &record.data);
+                    let timestamp = ts.max(left.timestamp);
+                    self.output
+                        .push_back(Record::with_timestamp(data, timestamp));
+                }
+            }
+        }
+        // Keep the element only while future left elements can still match it
+        let left_watermark = self.left.watermark.current();
+        if left_watermark.is_none_or(|wm| ts - self.lower >= wm) {
+            self.right.buffer.entry(key).or_default().push(record);
+        }
+
+        if before != Some(watermark) {
+            let upper = self.upper;
+            self.left.prune(|lts| lts + upper < watermark);
+        }
+    }
+}
+
+#[async_trait]
+impl<T, U, K, R, F> Source<R> for IntervalJoinSource<T, U, K, R, F>
+where
+    T: Clone + Send + Sync + 'static,
+    U: Clone + Send + Sync + 'static,
+    K: Eq + Hash + Send + Sync + 'static,
+    R: Send + Sync + 'static,
+    F: Fn(&T, &U) -> R + Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        if let Some(source) = self.left.source.as_mut() {
+            source.init().await?;
+        }
+        if let Some(source) = self.right.source.as_mut() {
+            source.init().await?;
+        }
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<R>>> {
+        if let Some(source) = self.left.source.take() {
+            self.left.rx = Some(spawn_reader(source, READ_AHEAD));
+        }
+        if let Some(source) = self.right.source.take() {
+            self.right.rx = Some(spawn_reader(source, READ_AHEAD));
+        }
+
+        loop {
+            if let Some(record) = self.output.pop_front() {
+                return Ok(Some(record));
+            }
+            if self.left.rx.is_none() && self.right.rx.is_none() {
+                return Ok(None);
+            }
+
+            tokio::select! {
+                item = recv(&mut self.left.rx) => match item {
+                    Some(item) => self.on_left(item?),
+                    None => self.left.rx = None,
+                },
+                item = recv(&mut self.right.rx) => match item {
+                    Some(item) => self.on_right(item?),
+                    None => self.right.rx = None,
+                },
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.left.rx = None;
+        self.right.rx = None;
+        self.left.buffer.clear();
+        self.right.buffer.clear();
+        if let Some(mut source) = self.left.source.take() {
+            source.close().await?;
+        }
+        if let Some(mut source) = self.right.source.take() {
+            source.close().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,117 @@
+use fluxus_runtime::watermark::WatermarkStrategy;
+use fluxus_utils::window::WindowConfig;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::operators::{Pattern, PatternMatch, PatternOperator};
+
+use super::DataStream;
+use super::KeyedWindowedStream;
+use super::interval_join::IntervalJoinSource;
+
+pub(crate) type KeyFn<T, K> = Arc<dyn Fn(&T) -> K + Send + Sync>;
+
+/// A stream whose elements are partitioned by a key
+pub struct KeyedStream<T, K> {
+    pub(crate) stream: DataStream<T>,
+    pub(crate) key: KeyFn<T, K>,
+}
+
+impl<T, K> KeyedStream<T, K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    /// Join with another keyed stream on elements whose timestamps are close to each other
+    pub fn interval_join<U>(self, other: KeyedStream<U, K>) -> IntervalJoin<T, U, K>
+    where
+        U: Clone + Send + Sync + 'static,
+    {
+        IntervalJoin {
+            left: self,
+            right: other,
+            lower: 0,
+            upper: 0,
+            watermark: WatermarkStrategy::Monotonous,
+        }
+    }
+
+    /// Apply windows to each key separately, so that every key has its own
+    /// window state and results
+    pub fn window(self, config: WindowConfig) -> KeyedWindowedStream<T, K> {
+        KeyedWindowedStream {
+            keyed: self,
+            window_config: config,
+            max_session_duration: None,
+            max_session_size: None,
+        }
+    }
+
+    /// Detect a sequence of events in each key, emitting a `(key, match)`
+    /// record with the events of every sequence that matches the pattern
+    pub fn detect(self, pattern: Pattern<T>) -> DataStream<(K, PatternMatch<T>)> {
+        self.stream
+            .transform(PatternOperator::new(pattern, self.key))
+    }
+
+    /// Drop the key and continue with the underlying stream
+    pub fn into_stream(self) -> DataStream<T> {
+        self.stream
+    }
+}
+
+/// An interval join between two keyed streams, see [`KeyedStream::interval_join`]
+pub struct IntervalJoin<T, U, K> {
+    left: KeyedStream<T, K>,
+    right: KeyedStream<U, K>,
+    lower: i64,
+    upper: i64,
+    watermark: WatermarkStrategy,
+}
+
+impl<T, U, K> IntervalJoin<T, U, K>
+where
+    T: Clone + Send + Sync + 'static,
+    U: Clone + Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    /// Match a left element at time `t` with right elements in `[t + lower, t + upper]`,
+    /// with both bounds in milliseconds and inclusive
+    pub fn between(mut self, lower: i64, upper: i64) -> Self {
+        self.lower = lower;
+        self.upper = upper;
+        self
+    }
+
+    /// How out of order the inputs may be. Buffered elements are dropped once
+    /// the watermark of the other input shows they can no longer match.
+    pub fn with_watermark(mut self, strategy: WatermarkStrategy) -> Self {
+        self.watermark = strategy;
+        self
+    }
+
+    /// Combine every matching pair, timestamped with the later of the two elements
+    pub fn process<F, R>(self, f: F) -> DataStream<R>
+    where
+        F: Fn(&T, &U) -> R + Send + Sync + 'static,
+        R: Clone + Send + Sync + 'static,
+    {
+        let Self {
+            left,
+            right,
+            lower,
+            upper,
+            watermark,
+        } = self;
+        let right_source = right.stream.into_source();
+        left.stream.wrap_source(|left_source| {
+            IntervalJoinSource::new(
+                (left_source, left.key),
+                (right_source, right.key),
+                (lower, upper),
+                watermark,
+                f,
+            )
+        })
+    }
+}
@@ -0,0 +1,192 @@
+use std::hash::Hash;
+use std::ops::Add;
+use std::sync::Arc;
+use std::time::Duration;
+
+use fluxus_utils::window::{WindowConfig, WindowType, WindowedValue};
+
+use super::DataStream;
+use super::keyed_stream::{KeyFn, KeyedStream};
+use crate::operators::{
+    AggregateFunction, CountOrTimeAggregator, KeyedSessionAggregator, KeyedWindowAggregator,
+    WithWindows,
+};
+
+/// A keyed stream with windows per key, see [`KeyedStream::window`]
+pub struct KeyedWindowedStream<T, K> {
+    pub(crate) keyed: KeyedStream<T, K>,
+    pub(crate) window_config: WindowConfig,
+    pub(crate) max_session_duration: Option<Duration>,
+    pub(crate) max_session_size: Option<usize>,
+}
+
+impl<T, K> KeyedWindowedStream<T, K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+{
+    /// Keep windows open for elements up to `lateness` behind the watermark,
+    /// emitting the updated aggregate of the key for each of them
+    pub fn allowed_lateness(mut self, lateness: Duration) -> Self {
+        self.window_config.allow_lateness = lateness;
+        self
+    }
+
+    /// Close the session of a key once it spans `duration`, so that keys
+    /// that never go quiet can't hold on to their session. Only applies to
+    /// session windows.
+    pub fn max_session_duration(mut self, duration: Duration) -> Self {
+        self.max_session_duration = Some(duration);
+        self
+    }
+
+    /// Close the session of a key once it holds `size` elements. Only
+    /// applies to session windows.
+    pub fn max_session_size(mut self, size: usize) -> Self {
+        self.max_session_size = Some(size);
+        self
+    }
+
+    /// Aggregate the values of each key and window, emitting a
+    /// `(key, aggregate)` record per key when the window ends.
+    ///
+    /// With session windows every key has its own sessions, which end once
+    /// the key was inactive for the gap and drop their state when they do.
+    /// Likewise every key has its own count-or-time windows.
+    pub fn aggregate<A, F>(self, init: A, f: F) -> DataStream<(K, A)>
+    where
+        A: Clone + Send + Sync + 'static,
+        F: Fn(A, T) -> A + Send + Sync + 'static,
+    {
+        let KeyedStream { stream, key } = self.keyed;
+        match self.window_config.window_type {
+            WindowType::Session(gap) => stream.transform(Self::sessions(
+                gap,
+                &self.window_config,
+                self.max_session_duration,
+                self.max_session_size,
+                key,
+                init,
+                f,
+            )),
+            WindowType::CountOrTime(count, duration) => {
+                stream.transform(CountOrTimeAggregator::new(
+                    count,
+                    duration,
+                    self.window_config.watermark_delay,
+                    key,
+                    init,
+                    f,
+                ))
+            }
+            _ => stream.transform(KeyedWindowAggregator::new(self.window_config, key, init, f)),
+        }
+    }
+
+    /// Like [`aggregate`](Self::aggregate), but the results carry the bounds
+    /// of their window, so that they can be told apart by window, e.g. to
+    /// [`sink_partitioned`](DataStream::sink_partitioned) them. The window
+    /// of a session spans from its first element to the gap after its last,
+    /// and a count-or-time window from its first element to the duration
+    /// after it.
+    pub fn aggregate_windowed<A, F>(self, init: A, f: F) -> DataStream<WindowedValue<(K, A)>>
+    where
+        A: Clone + Send + Sync + 'static,
+        F: Fn(A, T) -> A + Send + Sync + 'static,
+    {
+        let KeyedStream { stream, key } = self.keyed;
+        match self.window_config.window_type {
+            WindowType::Session(gap) => stream.transform(WithWindows(Self::sessions(
+                gap,
+                &self.window_config,
+                self.max_session_duration,
+                self.max_session_size,
+                key,
+                init,
+                f,
+            ))),
+            WindowType::CountOrTime(count, duration) => {
+                stream.transform(WithWindows(CountOrTimeAggregator::new(
+                    count,
+                    duration,
+                    self.window_config.watermark_delay,
+                    key,
+                    init,
+                    f,
+                )))
+            }
+            _ => stream.transform(WithWindows(KeyedWindowAggregator::new(
+                self.window_config,
+                key,
+                init,
+                f,
+            ))),
+        }
+    }
+
+    fn sessions<A, F>(
+        gap: Duration,
+        window_config: &WindowConfig,
+        max_duration: Option<Duration>,
+        max_size: Option<usize>,
+        key: KeyFn<T, K>,
+        init: A,
+        f: F,
+    ) -> KeyedSessionAggregator<T, K, A, F>
+    where
+        A: Clone,
+        F: Fn(A, T) -> A,
+    {
+        let mut aggregator =
+            KeyedSessionAggregator::new(gap, window_config.watermark_delay, key, init, f);
+        if let Some(duration) = max_duration {
+            aggregator = aggregator.max_duration(duration);
+        }
+        if let Some(size) = max_size {
+            aggregator = aggregator.max_size(size);
+        }
+        aggregator
+    }
+
+    /// Aggregate the values of each key and window with an
+    /// [`AggregateFunction`], emitting `(key, result)` records
+    pub fn aggregate_function<F, Acc, Out>(self, function: F) -> DataStream<(K, Out)>
+    where
+        F: AggregateFunction<T, Acc, Out> + 'static,
+        Acc: Clone + Send + Sync + 'static,
+        Out: Clone + Send + Sync + 'static,
+    {
+        let function = Arc::new(function);
+        let add = function.clone();
+        self.aggregate(function.create_accumulator(), move |acc, value| {
+            add.add(acc, value)
+        })
+        .map(move |(key, acc)| (key, function.get_result(acc)))
+    }
+
+    /// Count the values of each key and window
+    pub fn count(self) -> DataStream<(K, u64)> {
+        self.aggregate(0, |count, _| count + 1)
+    }
+
+    /// Sum the values extracted from the elements of each key and window
+    pub fn sum_by<F, N>(self, f: F) -> DataStream<(K, N)>
+    where
+        F: Fn(&T) -> N + Send + Sync + 'static,
+        N: Add<Output = N> + Default + Clone + Send + Sync + 'static,
+    {
+        self.aggregate(N::default(), move |sum, t| sum + f(&t))
+    }
+
+    /// Combine the elements of each key and window with `f`
+    pub fn reduce<F>(self, f: F) -> DataStream<(K, T)>
+    where
+        F: Fn(T, T) -> T + Send + Sync + 'static,
+    {
+        self.aggregate(None, move |acc: Option<T>, t| match acc {
+            Some(acc) => Some(f(acc, t)),
+            None => Some(t),
+        })
+        .flat_map(|(key, acc)| acc.map(|acc| (key, acc)))
+    }
+}
@@ -1,5 +1,19 @@
+mod broadcast_join;
+mod broadcast_stream;
 mod datastream;
+mod grouped_windowed_stream;
+mod interval_join;
+mod keyed_stream;
+mod keyed_windowed_stream;
+mod parallel_window;
+mod plan;
+mod union;
 mod windowed_stream;
 
+pub use broadcast_stream::{BroadcastConnectedStream, BroadcastStream};
 pub use datastream::DataStream;
+pub use grouped_windowed_stream::GroupedWindowedStream;
+pub use keyed_stream::{IntervalJoin, KeyedStream};
+pub use keyed_windowed_stream::KeyedWindowedStream;
+pub use plan::{ExecutionPlan, OperatorInfo};
 pub use windowed_stream::WindowedStream;
@@ -0,0 +1,253 @@
+use async_trait::async_trait;
+use fluxus_runtime::partition::{Partitioner, Partitioning};
+use fluxus_sources::Source;
+use fluxus_transformers::{Operator, TransformSource, spawn_reader};
+use fluxus_utils::models::{Record, StreamResult};
+use fluxus_utils::window::WindowConfig;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+use crate::operators::{WindowAggregator, WindowKeyedAggregator, by_start};
+
+/// The partial aggregates a worker emitted for a record, with the watermark
+/// of the worker after the record
+struct Progress<A> {
+    worker: usize,
+    watermark: Option<i64>,
+    partials: Vec<Record<(u64, A)>>,
+}
+
+/// The partial aggregates of a window, one slot per worker
+struct PartialWindow<A> {
+    partials: Vec<Option<A>>,
+    timestamp: i64,
+}
+
+/// Aggregates windows on several worker tasks and merges the partial
+/// aggregates of each window into its final aggregate.
+///
+/// Records are handed out to the workers with the partitioning of the
+/// stream, round-robin by default, and each worker folds its share into its
+/// own windows. A window is emitted once the watermarks
+/// of all workers passed its end, and again whenever a worker updates it
+/// within the allowed lateness.
+pub(crate) struct ParallelWindowSource<T, A, F, M> {
+    source: Option<TransformSource<T>>,
+    window_config: WindowConfig,
+    init: A,
+    f: Arc<F>,
+    merge: M,
+    workers: usize,
+    capacity: usize,
+    partitioning: Partitioning<T>,
+    rx: Option<mpsc::Receiver<StreamResult<Progress<A>>>>,
+    watermarks: Vec<Option<i64>>,
+    windows: BTreeMap<u64, PartialWindow<A>>,
+    emitted: BTreeSet<u64>,
+    ready: VecDeque<Record<A>>,
+}
+
+impl<T, A, F, M> ParallelWindowSource<T, A, F, M>
+where
+    T: Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+    F: Fn(A, T) -> A + Send + Sync + 'static,
+    M: Fn(A, A) -> A,
+{
+    pub(crate) fn new(
+        source: TransformSource<T>,
+        workers: usize,
+        capacity: usize,
+        window_config: WindowConfig,
+        init: A,
+        f: F,
+        merge: M,
+    ) -> Self {
+        let workers = workers.max(1);
+        Self {
+            source: Some(source),
+            window_config,
+            init,
+            f: Arc::new(f),
+            merge,
+            workers,
+            capacity: capacity.max(1),
+            partitioning: Partitioning::Rebalance,
+            rx: None,
+            watermarks: vec![None; workers],
+            windows: BTreeMap::new(),
+            emitted: BTreeSet::new(),
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Distribute the records to the workers with the given partitioning
+    pub(crate) fn with_partitioning(mut self, partitioning: Partitioning<T>) -> Self {
+        self.partitioning = partitioning;
+        self
+    }
+
+    fn start(&mut self, source: TransformSource<T>) -> mpsc::Receiver<StreamResult<Progress<A>>> {
+        let mut input = spawn_reader(source, self.capacity);
+        let (out_tx, out_rx) = mpsc::channel(self.capacity);
+        let mut work_txs = Vec::with_capacity(self.workers);
+
+        for worker in 0..self.workers {
+            let (work_tx, mut work_rx) = mpsc::channel::<Record<T>>(self.capacity);
+            work_txs.push(work_tx);
+            let out_tx = out_tx.clone();
+            let f = self.f.clone();
+            let aggregator = WindowAggregator::new(
+                self.window_config.clone(),
+                self.init.clone(),
+                move |a, t| f(a, t),
+            );
+            let mut aggregator = WindowKeyedAggregator(aggregator);
+            tokio::spawn(async move {
+                while let Some(record) = work_rx.recv().await {
+                    let progress = aggregator.process(record).await.map(|partials| Progress {
+                        worker,
+                        watermark: aggregator.0.watermark(),
+                        partials,
+                    });
+                    if out_tx.send(progress).await.is_err() {
+                        return;
+                    }
+                }
+                // The input ended, so the worker has seen all of its windows
+                let progress = aggregator.on_end_of_input().await.map(|partials| Progress {
+                    worker,
+                    watermark: Some(i64::MAX),
+                    partials,
+                });
+                let _ = out_tx.send(progress).await;
+            });
+        }
+
+        let mut partitioner = Partitioner::new(self.partitioning.clone(), self.workers, 0, 1);
+        tokio::spawn(async move {
+            while let Some(item) = input.recv().await {
+                match item {
+                    Ok(record) => {
+                        let worker = partitioner.partition(&record.data);
+                        if work_txs[worker].send(record).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = out_tx.send(Err(e)).await;
+                        break;
+                    }
+                }
+            }
+        });
+
+        out_rx
+    }
+
+    fn merged(&self, key: u64) -> Option<Record<A>> {
+        let window = self.windows.get(&key)?;
+        let aggregate = window
+            .partials
+            .iter()
+            .flatten()
+            .cloned()
+            .reduce(|a, b| (self.merge)(a, b))?;
+        Some(Record::with_timestamp(aggregate, window.timestamp))
+    }
+
+    fn on_progress(&mut self, progress: Progress<A>) {
+        self.watermarks[progress.worker] = progress.watermark;
+        for record in progress.partials {
+            let (key, partial) = record.data;
+            let window = self.windows.entry(key).or_insert_with(|| PartialWindow {
+                partials: vec![None; self.workers],
+                timestamp: record.timestamp,
+            });
+            window.partials[progress.worker] = Some(partial);
+            window.timestamp = record.timestamp;
+            // A worker updated a window that was already emitted
+            if self.emitted.contains(&key) {
+                self.ready.extend(self.merged(key));
+            }
+        }
+
+        // The watermark of the slowest worker bounds the windows that are complete
+        let Some(watermark) = self.watermarks.iter().copied().min().flatten() else {
+            return;
+        };
+        let window_type = &self.window_config.window_type;
+        let lateness = self.window_config.allow_lateness.as_millis() as i64;
+        let ended: Vec<u64> = by_start(self.windows.keys())
+            .into_iter()
+            .filter(|key| !self.emitted.contains(key))
+            .filter(|key| window_type.window_end(*key as i64).unwrap_or(i64::MAX) <= watermark)
+            .collect();
+        for key in ended {
+            self.ready.extend(self.merged(key));
+            self.emitted.insert(key);
+        }
+
+        let expired: Vec<u64> = self
+            .emitted
+            .iter()
+            .copied()
+            .filter(|key| {
+                window_type
+                    .window_end(*key as i64)
+                    .is_none_or(|end| end.saturating_add(lateness) <= watermark)
+            })
+            .collect();
+        for key in expired {
+            self.emitted.remove(&key);
+            self.windows.remove(&key);
+        }
+    }
+}
+
+#[async_trait]
+impl<T, A, F, M> Source<A> for ParallelWindowSource<T, A, F, M>
+where
+    T: Clone + Send + Sync + 'static,
+    A: Clone + Send + Sync + 'static,
+    F: Fn(A, T) -> A + Send + Sync + 'static,
+    M: Fn(A, A) -> A + Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match self.source.as_mut() {
+            Some(source) => source.init().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<A>>> {
+        if let Some(source) = self.source.take() {
+            self.rx = Some(self.start(source));
+        }
+
+        loop {
+            if let Some(record) = self.ready.pop_front() {
+                return Ok(Some(record));
+            }
+            let Some(rx) = self.rx.as_mut() else {
+                return Ok(None);
+            };
+            match rx.recv().await {
+                Some(progress) => self.on_progress(progress?),
+                None => self.rx = None,
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.rx = None;
+        self.windows.clear();
+        self.emitted.clear();
+        self.ready.clear();
+        match self.source.take() {
+            Some(mut source) => source.close().await,
+            None => Ok(()),
+        }
+    }
+}
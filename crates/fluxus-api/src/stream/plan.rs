@@ -0,0 +1,120 @@
+use fluxus_core::{Counter, MetricValue};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Identity and record counters of one stage of a stream
+#[derive(Debug)]
+pub struct OperatorInfo {
+    name: RwLock<String>,
+    uid: RwLock<Option<String>>,
+    pub(crate) records_in: Counter,
+    pub(crate) records_out: Counter,
+    pub(crate) errors: Counter,
+}
+
+impl OperatorInfo {
+    pub(crate) fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: RwLock::new(name.into()),
+            uid: RwLock::new(None),
+            records_in: Counter::new(),
+            records_out: Counter::new(),
+            errors: Counter::new(),
+        }
+    }
+
+    /// Default name of a stage, the type name without its path and generics
+    pub(crate) fn for_type<O>() -> Self {
+        let name = std::any::type_name::<O>();
+        let name = name.split('<').next().unwrap_or(name);
+        Self::new(name.rsplit("::").next().unwrap_or(name))
+    }
+
+    /// Name shown in metrics, logs and the execution plan
+    pub fn name(&self) -> String {
+        self.name.read().unwrap().clone()
+    }
+
+    pub(crate) fn set_name<S: Into<String>>(&self, name: S) {
+        *self.name.write().unwrap() = name.into();
+    }
+
+    /// Stable identifier of the stage, if one was assigned
+    pub fn uid(&self) -> Option<String> {
+        self.uid.read().unwrap().clone()
+    }
+
+    pub(crate) fn set_uid<S: Into<String>>(&self, uid: S) {
+        *self.uid.write().unwrap() = Some(uid.into());
+    }
+
+    /// Number of records the stage received
+    pub fn records_in(&self) -> u64 {
+        self.records_in.value()
+    }
+
+    /// Number of records the stage emitted
+    pub fn records_out(&self) -> u64 {
+        self.records_out.value()
+    }
+
+    /// Number of errors the stage returned
+    pub fn errors(&self) -> u64 {
+        self.errors.value()
+    }
+}
+
+/// The stages of a stream from its source to the most recently added operator.
+///
+/// The plan shares its counters with the running stream, so a plan taken
+/// before the stream is sunk can be used to read the metrics afterwards.
+#[derive(Debug, Clone)]
+pub struct ExecutionPlan {
+    stages: Vec<Arc<OperatorInfo>>,
+}
+
+impl ExecutionPlan {
+    pub(crate) fn new(stages: Vec<Arc<OperatorInfo>>) -> Self {
+        Self { stages }
+    }
+
+    pub fn stages(&self) -> &[Arc<OperatorInfo>] {
+        &self.stages
+    }
+
+    /// Counters of every stage, keyed by `operator.<name>.records_in`,
+    /// `operator.<name>.records_out` and `operator.<name>.errors`
+    pub fn metrics(&self) -> HashMap<String, MetricValue> {
+        self.stages
+            .iter()
+            .flat_map(|stage| {
+                let name = stage.name();
+                [
+                    ("records_in", stage.records_in()),
+                    ("records_out", stage.records_out()),
+                    ("errors", stage.errors()),
+                ]
+                .map(|(metric, value)| {
+                    (
+                        format!("operator.{}.{}", name, metric),
+                        MetricValue::Counter(value),
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for ExecutionPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, stage) in self.stages.iter().enumerate() {
+            write!(f, "{}: {}", index, stage.name())?;
+            if let Some(uid) = stage.uid() {
+                write!(f, " (uid: {})", uid)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
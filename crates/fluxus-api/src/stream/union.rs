@@ -0,0 +1,171 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_transformers::TransformSource;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+const READ_AHEAD: usize = 64;
+
+/// A record of an input with the watermark of the input after it, or the
+/// end of the input
+type Item<T> = (usize, Option<StreamResult<(Record<T>, Option<i64>)>>);
+
+/// One input of the union
+struct Input<T> {
+    source: Option<TransformSource<T>>,
+    idleness: Option<Duration>,
+    watermark: Option<i64>,
+    last_seen: Instant,
+    ended: bool,
+}
+
+impl<T> Input<T> {
+    /// Whether the input counts for the watermark of the union
+    fn is_active(&self, now: Instant) -> bool {
+        !self.ended
+            && self
+                .idleness
+                .is_none_or(|idleness| now.duration_since(self.last_seen) < idleness)
+    }
+}
+
+/// Reads several inputs at once, emitting their records in the order they
+/// arrive.
+///
+/// The watermark of the union is the smallest watermark of the inputs that
+/// neither ended nor are idle, and never moves back. An input without a
+/// watermark holds it back, unless the input is idle. Inputs are idle once
+/// they emitted no records for their idleness, until they emit again.
+pub(crate) struct UnionSource<T> {
+    inputs: Vec<Input<T>>,
+    rx: Option<mpsc::Receiver<Item<T>>>,
+    watermark: Option<i64>,
+}
+
+impl<T> UnionSource<T>
+where
+    T: Send + Sync + 'static,
+{
+    pub(crate) fn new(inputs: Vec<(TransformSource<T>, Option<Duration>)>) -> Self {
+        let now = Instant::now();
+        Self {
+            inputs: inputs
+                .into_iter()
+                .map(|(source, idleness)| Input {
+                    source: Some(source),
+                    idleness,
+                    watermark: None,
+                    last_seen: now,
+                    ended: false,
+                })
+                .collect(),
+            rx: None,
+            watermark: None,
+        }
+    }
+
+    fn start(&mut self) -> mpsc::Receiver<Item<T>> {
+        let (tx, rx) = mpsc::channel(READ_AHEAD);
+        let now = Instant::now();
+        for (index, input) in self.inputs.iter_mut().enumerate() {
+            input.last_seen = now;
+            let Some(mut source) = input.source.take() else {
+                continue;
+            };
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                loop {
+                    let item = match source.next().await {
+                        Ok(Some(record)) => Ok((record, source.watermark())),
+                        Ok(None) | Err(StreamError::EOF) => break,
+                        Err(StreamError::Wait(ms)) => {
+                            tokio::time::sleep(Duration::from_millis(ms)).await;
+                            continue;
+                        }
+                        Err(e) => Err(e),
+                    };
+                    let failed = item.is_err();
+                    if tx.send((index, Some(item))).await.is_err() || failed {
+                        break;
+                    }
+                }
+                let _ = tx.send((index, None)).await;
+                if let Err(e) = source.close().await {
+                    tracing::error!("Error closing source: {:?}", e);
+                }
+            });
+        }
+        rx
+    }
+
+    fn update_watermark(&mut self) {
+        let now = Instant::now();
+        let mut watermark: Option<i64> = None;
+        for input in self.inputs.iter().filter(|input| input.is_active(now)) {
+            match input.watermark {
+                Some(wm) => watermark = Some(watermark.map_or(wm, |min| min.min(wm))),
+                None => return,
+            }
+        }
+        if let Some(watermark) = watermark {
+            self.watermark = Some(self.watermark.map_or(watermark, |wm| wm.max(watermark)));
+        }
+    }
+}
+
+#[async_trait]
+impl<T> Source<T> for UnionSource<T>
+where
+    T: Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        for input in self.inputs.iter_mut() {
+            if let Some(source) = input.source.as_mut() {
+                source.init().await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        if self.rx.is_none() {
+            self.rx = Some(self.start());
+        }
+
+        loop {
+            let Some(rx) = self.rx.as_mut() else {
+                return Ok(None);
+            };
+            match rx.recv().await {
+                Some((index, Some(item))) => {
+                    let (record, watermark) = item?;
+                    let input = &mut self.inputs[index];
+                    input.last_seen = Instant::now();
+                    input.watermark = watermark.or(input.watermark);
+                    self.update_watermark();
+                    return Ok(Some(record));
+                }
+                Some((index, None)) => {
+                    self.inputs[index].ended = true;
+                    self.update_watermark();
+                }
+                None => self.rx = None,
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.rx = None;
+        for input in self.inputs.iter_mut() {
+            if let Some(mut source) = input.source.take() {
+                source.close().await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        self.watermark
+    }
+}
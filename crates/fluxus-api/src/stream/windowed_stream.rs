@@ -1,39 +1,691 @@
 use std::cmp::{Ordering, Reverse};
 use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
 use std::hash::Hash;
+use std::ops::Add;
+use std::sync::Arc;
+use std::time::Duration;
 
+use fluxus_core::Gauge;
+use fluxus_sinks::Sink;
 use fluxus_transformers::operator::{WindowAllOperator, WindowAnyOperator};
-use fluxus_utils::window::WindowConfig;
+use fluxus_utils::memory::{MemoryAccount, MemorySize};
+use fluxus_utils::models::Change;
+use fluxus_utils::models::Record;
+use fluxus_utils::stats::{HyperLogLog, QuantileSketch, Stats};
+use fluxus_utils::window::{Window, WindowAssigner, WindowConfig, WindowType, WindowedValue};
 
 use crate::operators::{
-    SortOrder, WindowAggregator, WindowSkipper, WindowSorter, WindowTimestampSorter,
+    AggregateFunction, AssignedWindowAggregator, CountOrTimeAggregator, InvalidConfig,
+    RecordsOperator, SortOrder, Trigger, WindowAggregator, WindowChangelogAggregator,
+    WindowEarlyAggregator, WindowKeyedAggregator, WindowMemory, WindowSkipper, WindowSorter,
+    WindowTimestampSorter, WithWindows,
 };
+use crate::stream::GroupedWindowedStream;
 use crate::stream::datastream::DataStream;
+use crate::stream::parallel_window::ParallelWindowSource;
+
+/// A record of either input of [`WindowedStream::cogroup`]
+#[derive(Clone)]
+enum CoGrouped<T, U> {
+    Left(T),
+    Right(U),
+}
 
 /// Represents a windowed stream for aggregation operations
 pub struct WindowedStream<T> {
     pub(crate) stream: DataStream<T>,
     pub(crate) window_config: WindowConfig,
+    pub(crate) coalesce: Option<Duration>,
+    pub(crate) emit_partial: bool,
+    pub(crate) emit_every: Option<Duration>,
+    pub(crate) assigner: Option<Arc<dyn WindowAssigner<T>>>,
+    pub(crate) live_windows: Option<Arc<Gauge>>,
+    pub(crate) memory: Option<WindowMemory<T>>,
+    pub(crate) late: Option<Box<dyn Sink<T> + Send + Sync>>,
+    pub(crate) trigger: Option<Box<dyn Trigger<T>>>,
 }
 
 impl<T> WindowedStream<T>
 where
     T: Clone + Send + Sync + 'static,
 {
+    /// Keep windows open for elements up to `lateness` behind the watermark,
+    /// emitting the updated aggregate for each of them
+    pub fn allowed_lateness(mut self, lateness: Duration) -> Self {
+        self.window_config.allow_lateness = lateness;
+        self
+    }
+
+    /// Write elements that arrive after the allowed lateness of all of their
+    /// windows to a sink instead of dropping them.
+    ///
+    /// Applies to [`aggregate`](Self::aggregate) and the aggregations built
+    /// on it.
+    pub fn late_records_to<K>(mut self, sink: K) -> Self
+    where
+        K: Sink<T> + Send + Sync + 'static,
+    {
+        self.late = Some(Box::new(sink));
+        self
+    }
+
+    /// Decide with a trigger when windows emit their aggregate, e.g. to emit
+    /// early results with `OrTrigger::new(EventTimeTrigger, CountTrigger::of(100))`.
+    ///
+    /// Windows fire when they end by default. Applies to
+    /// [`aggregate`](Self::aggregate) and the aggregations built on it,
+    /// unless [`emit_partial`](Self::emit_partial) is set.
+    pub fn trigger<R>(mut self, trigger: R) -> Self
+    where
+        R: Trigger<T> + 'static,
+    {
+        self.trigger = Some(Box::new(trigger));
+        self
+    }
+
+    /// Emit the running aggregate of a window after every element instead of
+    /// once when the window closes.
+    ///
+    /// Applies to [`aggregate`](Self::aggregate) and the aggregations built
+    /// on it, such as `count` and `sum_by`.
+    pub fn emit_partial(mut self) -> Self {
+        self.emit_partial = true;
+        self
+    }
+
+    /// With [`emit_partial`](Self::emit_partial), keep only the latest
+    /// aggregate of each window and emit the retained aggregates once every
+    /// `flush_interval` instead of on every update.
+    ///
+    /// Applies to [`aggregate`](Self::aggregate) and the aggregations built
+    /// on it, such as `count` and `sum_by`.
+    pub fn coalesce(mut self, flush_interval: Duration) -> Self {
+        self.coalesce = Some(flush_interval);
+        self
+    }
+
+    /// Emit the running aggregate of each open window that changed once
+    /// every `interval`, and its final aggregate when the window ends, so
+    /// that long windows can still feed near-real-time dashboards.
+    ///
+    /// Results are emitted at the next flush after they are produced.
+    /// [`aggregate_windowed`](Self::aggregate_windowed) flags the final
+    /// results with [`WindowedValue::is_final`]. Applies to
+    /// [`aggregate`](Self::aggregate) and the aggregations built on it,
+    /// in place of [`emit_partial`](Self::emit_partial) and
+    /// [`coalesce`](Self::coalesce).
+    pub fn emit_every(mut self, interval: Duration) -> Self {
+        self.emit_every = Some(interval);
+        self
+    }
+
+    /// Derive the windows of each element from its content with an
+    /// assigner instead of from the window type, e.g. per-tenant window
+    /// sizes with `|event: &Event, ts| vec![Window::tumbling(ts, event.window_size())]`.
+    ///
+    /// The watermark delay and allowed lateness of the window configuration
+    /// and [`live_windows`](Self::live_windows) still apply. Applies to
+    /// [`aggregate`](Self::aggregate) and the aggregations built on it.
+    /// The pipeline fails with a configuration error when it starts if
+    /// [`trigger`](Self::trigger), [`emit_partial`](Self::emit_partial),
+    /// [`coalesce`](Self::coalesce), [`emit_every`](Self::emit_every) or
+    /// [`late_records_to`](Self::late_records_to) is also set, or if the
+    /// stream is consumed by a method that buffers or sorts whole windows,
+    /// such as [`apply`](Self::apply) or [`sort_by`](Self::sort_by).
+    pub fn assign_with<W>(mut self, assigner: W) -> Self
+    where
+        W: WindowAssigner<T> + 'static,
+    {
+        self.assigner = Some(Arc::new(assigner));
+        self
+    }
+
+    /// Publish the number of windows that hold state to a gauge, e.g. to
+    /// watch that the state of a long-running pipeline stays bounded.
+    ///
+    /// The state of a window is dropped once the watermark passes its
+    /// allowed lateness. Applies to [`aggregate`](Self::aggregate) and the
+    /// aggregations built on it,
+    /// [`aggregate_changelog`](Self::aggregate_changelog), the sorts and
+    /// [`skip`](Self::skip).
+    pub fn live_windows(mut self, gauge: Arc<Gauge>) -> Self {
+        self.live_windows = Some(gauge);
+        self
+    }
+
+    /// Account for the estimated memory of the state of the windows, e.g.
+    /// with an account of [`Metrics::operator_memory`](fluxus_core::Metrics::operator_memory).
+    ///
+    /// Applies to [`aggregate`](Self::aggregate) and the aggregations built
+    /// on it, whose aggregates are estimated by their inline size,
+    /// [`apply`](Self::apply), the sorts and [`skip`](Self::skip).
+    pub fn memory_account(mut self, account: MemoryAccount) -> Self
+    where
+        T: MemorySize,
+    {
+        self.memory = Some(WindowMemory::new(account));
+        self
+    }
+
+    /// Aggregate the elements of each key separately, emitting a
+    /// `(key, aggregate)` record per key of a window when it emits
+    pub fn group_by<K, F>(self, f: F) -> GroupedWindowedStream<T, K>
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Eq + Hash + Clone + Send + Sync + 'static,
+    {
+        GroupedWindowedStream {
+            windowed: self,
+            key: Arc::new(f),
+        }
+    }
+
+    fn aggregator<A, F>(&mut self, emit_partial: bool, init: A, f: F) -> WindowAggregator<T, A, F>
+    where
+        A: Clone + 'static,
+        F: Fn(A, T) -> A,
+    {
+        let mut aggregator = WindowAggregator::new(self.window_config.clone(), init, f);
+        if emit_partial {
+            aggregator = aggregator.emit_partial();
+        }
+        if let Some(late) = self.late.take() {
+            aggregator = aggregator.late_records_to(late);
+        }
+        if let Some(trigger) = self.trigger.take() {
+            aggregator = aggregator.trigger(trigger);
+        }
+        if let Some(gauge) = self.live_windows.take() {
+            aggregator = aggregator.live_windows(gauge);
+        }
+        if let Some(memory) = &self.memory {
+            // Aggregates are of any type, so only their inline size is known
+            aggregator = aggregator.memory_estimate(memory.account.clone(), |_| 0);
+        }
+        aggregator
+    }
+
+    /// The aggregator of windows given by an assigner, or the name of a
+    /// setting it does not support, which is rejected rather than ignored
+    fn assigned_aggregator<A, F>(
+        &mut self,
+        assigner: Arc<dyn WindowAssigner<T>>,
+        init: A,
+        f: F,
+    ) -> Result<AssignedWindowAggregator<T, A, F>, &'static str>
+    where
+        A: Clone,
+        F: Fn(A, T) -> A,
+    {
+        let unsupported = [
+            ("trigger", self.trigger.is_some()),
+            ("emit_partial", self.emit_partial),
+            ("coalesce", self.coalesce.is_some()),
+            ("emit_every", self.emit_every.is_some()),
+            ("late_records_to", self.late.is_some()),
+        ];
+        if let Some((setting, _)) = unsupported.into_iter().find(|(_, set)| *set) {
+            return Err(setting);
+        }
+
+        let mut aggregator =
+            AssignedWindowAggregator::new(self.window_config.clone(), assigner, init, f);
+        if let Some(gauge) = self.live_windows.take() {
+            aggregator = aggregator.live_windows(gauge);
+        }
+        Ok(aggregator)
+    }
+
+    /// A stream that fails the pipeline with a configuration error when it
+    /// starts, for a setting or method that does not support windows given
+    /// by an assigner
+    fn unsupported_with_assigner<R>(self, setting: &str) -> DataStream<R>
+    where
+        R: Send + Sync + 'static,
+    {
+        let message = format!("{} is not supported with assign_with", setting);
+        self.stream.transform(InvalidConfig::new(message))
+    }
+
     /// Aggregate values in the window
-    pub fn aggregate<A, F>(self, init: A, f: F) -> DataStream<A>
+    pub fn aggregate<A, F>(mut self, init: A, f: F) -> DataStream<A>
+    where
+        A: Clone + Send + Sync + 'static,
+        F: Fn(A, T) -> A + Send + Sync + 'static,
+    {
+        if let Some(assigner) = self.assigner.take() {
+            return match self.assigned_aggregator(assigner, init, f) {
+                Ok(aggregator) => self.stream.transform(aggregator).map(|result| result.value),
+                Err(setting) => self.unsupported_with_assigner(setting),
+            };
+        }
+        if let WindowType::CountOrTime(count, duration) = self.window_config.window_type {
+            let aggregator = Self::count_or_time(&self.window_config, count, duration, init, f);
+            return self
+                .stream
+                .transform(aggregator)
+                .map(|(_, aggregate)| aggregate);
+        }
+        if let Some(interval) = self.emit_every {
+            return self
+                .aggregate_early(interval, init, f)
+                .map(|result| result.value);
+        }
+        let aggregator = self.aggregator(self.emit_partial, init, f);
+        match self.coalesce {
+            None => self.stream.transform(aggregator),
+            Some(flush_interval) => self
+                .stream
+                .transform(WindowKeyedAggregator(aggregator))
+                .coalesce_by(|(window_key, _)| *window_key, flush_interval)
+                .map(|(_, aggregate)| aggregate),
+        }
+    }
+
+    /// Aggregate values in the window like [`aggregate`](Self::aggregate),
+    /// emitting each result with the start and end of its window
+    pub fn aggregate_windowed<A, F>(mut self, init: A, f: F) -> DataStream<WindowedValue<A>>
+    where
+        A: Clone + Send + Sync + 'static,
+        F: Fn(A, T) -> A + Send + Sync + 'static,
+    {
+        if let Some(assigner) = self.assigner.take() {
+            return match self.assigned_aggregator(assigner, init, f) {
+                Ok(aggregator) => self.stream.transform(aggregator),
+                Err(setting) => self.unsupported_with_assigner(setting),
+            };
+        }
+        if let WindowType::CountOrTime(count, duration) = self.window_config.window_type {
+            let aggregator = Self::count_or_time(&self.window_config, count, duration, init, f);
+            return self
+                .stream
+                .transform(WithWindows(aggregator))
+                .map(|result| WindowedValue {
+                    start: result.start,
+                    end: result.end,
+                    value: result.value.1,
+                    is_final: result.is_final,
+                });
+        }
+        if let Some(interval) = self.emit_every {
+            return self.aggregate_early(interval, init, f);
+        }
+        let window_type = self.window_config.window_type.clone();
+        let emit_partial = self.emit_partial;
+        let aggregator = self.aggregator(self.emit_partial, init, f);
+        let stream = self.stream.transform(WindowKeyedAggregator(aggregator));
+        let stream = match self.coalesce {
+            None => stream,
+            Some(flush_interval) => {
+                stream.coalesce_by(|(window_key, _)| *window_key, flush_interval)
+            }
+        };
+        stream.map(move |(window_key, aggregate)| match emit_partial {
+            true => WindowedValue::early(&window_type, window_key, aggregate),
+            false => WindowedValue::new(&window_type, window_key, aggregate),
+        })
+    }
+
+    /// The aggregator of count-or-time windows, which are opened by the
+    /// first element after the previous window closed, so that triggers,
+    /// partial results and the late record sink do not apply to them
+    fn count_or_time<A, F>(
+        window_config: &WindowConfig,
+        count: usize,
+        duration: Duration,
+        init: A,
+        f: F,
+    ) -> CountOrTimeAggregator<T, (), A, F>
+    where
+        A: Clone,
+        F: Fn(A, T) -> A,
+    {
+        CountOrTimeAggregator::new(
+            count,
+            duration,
+            window_config.watermark_delay,
+            Arc::new(|_| ()),
+            init,
+            f,
+        )
+    }
+
+    /// Aggregate with early results of the open windows every `interval`,
+    /// keeping only the latest early result of each window per interval
+    fn aggregate_early<A, F>(
+        mut self,
+        interval: Duration,
+        init: A,
+        f: F,
+    ) -> DataStream<WindowedValue<A>>
+    where
+        A: Clone + Send + Sync + 'static,
+        F: Fn(A, T) -> A + Send + Sync + 'static,
+    {
+        let aggregator = self.aggregator(false, init, f);
+        self.stream
+            .transform(WindowEarlyAggregator::new(aggregator))
+            .coalesce_by(|result| (result.start, result.is_final), interval)
+    }
+
+    /// Compute the results of a window from all of its records, for
+    /// computations such as medians that can't be done incrementally.
+    ///
+    /// The records of each window are buffered until it fires, when `f` is
+    /// called with the bounds of the window and its records in order of
+    /// arrival. A window fires again with all of its records for late
+    /// records within the allowed lateness, and its records are dropped once
+    /// the lateness passed. Triggers and the late record sink do not apply.
+    pub fn apply<F, R>(self, f: F) -> DataStream<R>
+    where
+        F: Fn(Window, Vec<Record<T>>) -> Vec<R> + Send + Sync + 'static,
+        R: Send + Sync + 'static,
+    {
+        if self.assigner.is_some() {
+            return self.unsupported_with_assigner("apply");
+        }
+        let window_type = self.window_config.window_type.clone();
+        let mut aggregator = WindowAggregator::new(
+            self.window_config,
+            Vec::new(),
+            |mut records: Vec<Record<T>>, record| {
+                records.push(record);
+                records
+            },
+        );
+        if self.emit_partial {
+            aggregator = aggregator.emit_partial();
+        }
+        if let Some(memory) = self.memory {
+            let account = memory.account.clone();
+            aggregator = aggregator
+                .memory_estimate(account, move |records| memory.records_heap_size(records));
+        }
+        self.stream
+            .transform(RecordsOperator)
+            .transform(WindowKeyedAggregator(aggregator))
+            .flat_map(move |(window_key, records)| {
+                f(Window::new(&window_type, window_key), records)
+            })
+    }
+
+    /// Group the records of this stream and of `other` by window, calling
+    /// `f` with the bounds of each window and the records of both inputs in
+    /// it, e.g. to attribute the clicks of a window to its impressions.
+    ///
+    /// Both inputs are windowed with the window config of this stream, the
+    /// settings of `other` are ignored, and merged in timestamp order into
+    /// one watermark. Windows fire as with [`apply`](Self::apply), also when
+    /// only one of the inputs has records in them.
+    pub fn cogroup<U, F, R>(self, other: WindowedStream<U>, f: F) -> DataStream<R>
+    where
+        U: Clone + Send + Sync + 'static,
+        F: Fn(Window, Vec<T>, Vec<U>) -> Vec<R> + Send + Sync + 'static,
+        R: Send + Sync + 'static,
+    {
+        if self.assigner.is_some() || other.assigner.is_some() {
+            return self.unsupported_with_assigner("cogroup");
+        }
+        let right = other.stream.map(CoGrouped::Right);
+        let mut merged = self
+            .stream
+            .map(CoGrouped::Left)
+            .merge_sorted(vec![right])
+            .window(self.window_config);
+        merged.emit_partial = self.emit_partial;
+        merged.apply(move |window, records| {
+            let mut lefts = Vec::new();
+            let mut rights = Vec::new();
+            for record in records {
+                match record.data {
+                    CoGrouped::Left(left) => lefts.push(left),
+                    CoGrouped::Right(right) => rights.push(right),
+                }
+            }
+            f(window, lefts, rights)
+        })
+    }
+
+    /// Aggregate values in the window on as many tasks as the parallelism of
+    /// the stream, see [`DataStream::parallel`], merging the partial
+    /// aggregates of each task with `merge` into the result of the window.
+    ///
+    /// Records are distributed to the tasks as set by
+    /// [`DataStream::rebalance`], [`DataStream::rescale`] or
+    /// [`DataStream::partition_by`], round-robin by default.
+    ///
+    /// Each window is emitted once the watermarks of all tasks passed its
+    /// end. Triggers, partial results and the late record sink do not apply.
+    /// Without parallelism this is the same as [`aggregate`](Self::aggregate).
+    pub fn aggregate_with_merge<A, F, M>(self, init: A, f: F, merge: M) -> DataStream<A>
     where
         A: Clone + Send + Sync + 'static,
         F: Fn(A, T) -> A + Send + Sync + 'static,
+        M: Fn(A, A) -> A + Send + Sync + 'static,
     {
-        let aggregator = WindowAggregator::new(self.window_config, init, f);
+        let Some(config) = self.stream.parallel_config.clone() else {
+            return self.aggregate(init, f);
+        };
+        if config.parallelism <= 1 {
+            return self.aggregate(init, f);
+        }
+        if self.assigner.is_some() {
+            return self.unsupported_with_assigner("aggregate_with_merge");
+        }
+        let window_config = self.window_config;
+        let partitioning = self.stream.partitioning.clone();
+        self.stream.wrap_source(|source| {
+            let source = ParallelWindowSource::new(
+                source,
+                config.parallelism,
+                config.buffer_size,
+                window_config,
+                init,
+                f,
+                merge,
+            );
+            match partitioning {
+                Some(partitioning) => source.with_partitioning(partitioning),
+                None => source,
+            }
+        })
+    }
+
+    /// Aggregate values in the window with an [`AggregateFunction`], on as
+    /// many tasks as the parallelism of the stream like
+    /// [`aggregate_with_merge`](Self::aggregate_with_merge), emitting the
+    /// result of the merged accumulators of each window
+    pub fn aggregate_function<F, Acc, Out>(self, function: F) -> DataStream<Out>
+    where
+        F: AggregateFunction<T, Acc, Out> + 'static,
+        Acc: Clone + Send + Sync + 'static,
+        Out: Clone + Send + Sync + 'static,
+    {
+        let function = Arc::new(function);
+        let (add, merge) = (function.clone(), function.clone());
+        self.aggregate_with_merge(
+            function.create_accumulator(),
+            move |acc, value| add.add(acc, value),
+            move |a, b| merge.merge(a, b),
+        )
+        .map(move |acc| function.get_result(acc))
+    }
+
+    /// Aggregate values in the window and emit the results as a changelog
+    /// keyed by window start, so that sinks supporting upserts and deletes
+    /// can correct results updated by late elements.
+    ///
+    /// The first result of a window is an `Insert`, each later one an
+    /// `UpdateBefore` with the previous result followed by an `UpdateAfter`.
+    pub fn aggregate_changelog<A, F>(self, init: A, f: F) -> DataStream<Change<u64, A>>
+    where
+        A: Clone + Send + Sync + 'static,
+        F: Fn(A, T) -> A + Send + Sync + 'static,
+    {
+        if self.assigner.is_some() {
+            return self.unsupported_with_assigner("aggregate_changelog");
+        }
+        let mut aggregator = WindowChangelogAggregator::new(self.window_config, init, f);
+        if let Some(gauge) = self.live_windows {
+            aggregator = aggregator.live_windows(gauge);
+        }
         self.stream.transform(aggregator)
     }
 
+    /// Count the values in the window
+    pub fn count(self) -> DataStream<u64> {
+        self.aggregate(0, |count, _| count + 1)
+    }
+
+    /// Sum the values extracted from the elements of the window
+    pub fn sum_by<F, N>(self, f: F) -> DataStream<N>
+    where
+        F: Fn(&T) -> N + Send + Sync + 'static,
+        N: Add<Output = N> + Default + Clone + Send + Sync + 'static,
+    {
+        self.aggregate(N::default(), move |sum, t| sum + f(&t))
+    }
+
+    /// Minimum element of the window by the extracted key
+    pub fn min_by_key<F, K>(self, f: F) -> DataStream<T>
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Ord,
+    {
+        self.aggregate(None, move |min: Option<T>, t| match min {
+            Some(min) if f(&min) <= f(&t) => Some(min),
+            _ => Some(t),
+        })
+        .flatten_options()
+    }
+
+    /// Maximum element of the window by the extracted key
+    pub fn max_by_key<F, K>(self, f: F) -> DataStream<T>
+    where
+        F: Fn(&T) -> K + Send + Sync + 'static,
+        K: Ord,
+    {
+        self.aggregate(None, move |max: Option<T>, t| match max {
+            Some(max) if f(&max) >= f(&t) => Some(max),
+            _ => Some(t),
+        })
+        .flatten_options()
+    }
+
+    /// Smallest value extracted from the elements of the window
+    pub fn min_by<F, N>(self, f: F) -> DataStream<N>
+    where
+        F: Fn(&T) -> N + Send + Sync + 'static,
+        N: PartialOrd + Clone + Send + Sync + 'static,
+    {
+        self.aggregate(None, move |min: Option<N>, t| {
+            let value = f(&t);
+            match min {
+                Some(min) if min <= value => Some(min),
+                _ => Some(value),
+            }
+        })
+        .flatten_options()
+    }
+
+    /// Largest value extracted from the elements of the window
+    pub fn max_by<F, N>(self, f: F) -> DataStream<N>
+    where
+        F: Fn(&T) -> N + Send + Sync + 'static,
+        N: PartialOrd + Clone + Send + Sync + 'static,
+    {
+        self.aggregate(None, move |max: Option<N>, t| {
+            let value = f(&t);
+            match max {
+                Some(max) if max >= value => Some(max),
+                _ => Some(value),
+            }
+        })
+        .flatten_options()
+    }
+
+    /// Count, sum, extremes, mean and variance of the values extracted from
+    /// the elements of the window
+    pub fn stats_by<F>(self, f: F) -> DataStream<Stats>
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        self.aggregate(Stats::new(), move |mut stats, t| {
+            stats.add(f(&t));
+            stats
+        })
+    }
+
+    /// Mean of the values extracted from the elements of the window
+    pub fn avg_by<F>(self, f: F) -> DataStream<f64>
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        self.stats_by(f).map(|stats| stats.mean()).flatten_options()
+    }
+
+    /// Mean of the values extracted from the elements of the window, the
+    /// same as [`avg_by`](Self::avg_by)
+    pub fn mean_by<F>(self, f: F) -> DataStream<f64>
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        self.avg_by(f)
+    }
+
+    /// Population standard deviation of the values extracted from the
+    /// elements of the window
+    pub fn stddev_by<F>(self, f: F) -> DataStream<f64>
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        self.stats_by(f)
+            .map(|stats| stats.stddev())
+            .flatten_options()
+    }
+
+    /// Approximate quantiles of the values extracted from the elements of
+    /// the window, one for each of `quantiles` between 0 and 1, within 1% of
+    /// the exact values. The values are counted in a [`QuantileSketch`]
+    /// instead of being stored.
+    pub fn quantiles<F>(self, quantiles: &[f64], f: F) -> DataStream<Vec<f64>>
+    where
+        F: Fn(&T) -> f64 + Send + Sync + 'static,
+    {
+        let quantiles = quantiles.to_vec();
+        self.aggregate(QuantileSketch::default(), move |mut sketch, t| {
+            sketch.add(f(&t));
+            sketch
+        })
+        .map(move |sketch| {
+            quantiles
+                .iter()
+                .filter_map(|q| sketch.quantile(*q))
+                .collect()
+        })
+    }
+
+    /// Combine the values in the window with `f`, without an initial value
+    pub fn reduce<F>(self, f: F) -> DataStream<T>
+    where
+        F: Fn(T, T) -> T + Send + Sync + 'static,
+    {
+        self.aggregate(None, move |acc: Option<T>, t| {
+            Some(match acc {
+                Some(acc) => f(acc, t),
+                None => t,
+            })
+        })
+        .flatten_options()
+    }
+
     pub fn any<F>(self, f: F) -> DataStream<bool>
     where
         F: Fn(&T) -> bool + Send + Sync + 'static,
     {
+        if self.assigner.is_some() {
+            return self.unsupported_with_assigner("any");
+        }
         let anyer = WindowAnyOperator::new(f, self.window_config);
         self.stream.transform(anyer)
     }
@@ -42,34 +694,34 @@ where
     where
         F: Fn(&T) -> bool + Send + Sync + 'static,
     {
+        if self.assigner.is_some() {
+            return self.unsupported_with_assigner("all");
+        }
         let aller = WindowAllOperator::new(f, self.window_config);
         self.stream.transform(aller)
     }
 
     /// Limit the number of values in the window
     pub fn limit(self, n: usize) -> DataStream<Vec<T>> {
-        let limiter = WindowAggregator::new(self.window_config, vec![], move |mut acc, value| {
+        self.aggregate(vec![], move |mut acc, value| {
             if acc.len() < n {
                 acc.push(value);
             }
             acc
-        });
-        self.stream.transform(limiter)
+        })
     }
 
     /// Retain last n values in the window
     pub fn tail(self, n: usize) -> DataStream<Vec<T>> {
         let init = VecDeque::with_capacity(n);
-        let limiter = WindowAggregator::new(self.window_config, init, move |mut acc, value| {
+        self.aggregate(init, move |mut acc, value| {
             acc.push_back(value);
             if acc.len() > n {
                 acc.pop_front();
             }
             acc
-        });
-        self.stream
-            .transform(limiter)
-            .map(|d| d.into_iter().collect())
+        })
+        .map(|d| d.into_iter().collect())
     }
 
     /// Sort values in the window
@@ -77,31 +729,56 @@ where
     where
         F: FnMut(&T, &T) -> Ordering + Send + Sync + 'static,
     {
-        let sorter = WindowSorter::new(self.window_config, f);
+        if self.assigner.is_some() {
+            return self.unsupported_with_assigner("sort_by");
+        }
+        let mut sorter = WindowSorter::new(self.window_config, f);
+        if let Some(gauge) = self.live_windows {
+            sorter = sorter.live_windows(gauge);
+        }
+        if let Some(memory) = self.memory {
+            sorter = sorter.memory(memory);
+        }
         self.stream.transform(sorter)
     }
 
     /// Sort values in the window by timestamp
     pub fn sort_by_ts(self, order: SortOrder) -> DataStream<Vec<T>> {
-        let sorter = WindowTimestampSorter::new(self.window_config, order);
+        if self.assigner.is_some() {
+            return self.unsupported_with_assigner("sort_by_ts");
+        }
+        let mut sorter = WindowTimestampSorter::new(self.window_config, order);
+        if let Some(gauge) = self.live_windows {
+            sorter = sorter.live_windows(gauge);
+        }
+        if let Some(memory) = self.memory {
+            sorter = sorter.memory(memory);
+        }
         self.stream.transform(sorter)
     }
 
     /// Sort values in the window by timestamp in ascending order
     pub fn sort_by_ts_asc(self) -> DataStream<Vec<T>> {
-        let sorter = WindowTimestampSorter::new(self.window_config, SortOrder::Asc);
-        self.stream.transform(sorter)
+        self.sort_by_ts(SortOrder::Asc)
     }
 
     /// Sort values in the window by timestamp in descending order
     pub fn sort_by_ts_desc(self) -> DataStream<Vec<T>> {
-        let sorter = WindowTimestampSorter::new(self.window_config, SortOrder::Desc);
-        self.stream.transform(sorter)
+        self.sort_by_ts(SortOrder::Desc)
     }
 
     /// Skip
     pub fn skip(self, n: usize) -> DataStream<Vec<T>> {
-        let skipper = WindowSkipper::new(self.window_config, n);
+        if self.assigner.is_some() {
+            return self.unsupported_with_assigner("skip");
+        }
+        let mut skipper = WindowSkipper::new(self.window_config, n);
+        if let Some(gauge) = self.live_windows {
+            skipper = skipper.live_windows(gauge);
+        }
+        if let Some(memory) = self.memory {
+            skipper = skipper.memory(memory);
+        }
         self.stream.transform(skipper)
     }
 }
@@ -148,6 +825,16 @@ where
             set
         })
     }
+
+    /// Approximate number of distinct values, counted in a [`HyperLogLog`]
+    /// sketch of a few kilobytes instead of a set of all values
+    pub fn distinct_count_approx(self) -> DataStream<u64> {
+        self.aggregate(HyperLogLog::default(), |mut sketch, value| {
+            sketch.add(&value);
+            sketch
+        })
+        .map(|sketch| sketch.count())
+    }
 }
 
 impl<T> WindowedStream<T>
@@ -189,10 +876,10 @@ where
             keys.push(Reverse(k.clone()));
             kvs.entry(k).or_default().push(value);
 
-            if keys.len() > n {
-                if let Some(Reverse(min_k)) = keys.pop() {
-                    kvs.get_mut(&min_k).map(|v| v.pop());
-                }
+            if keys.len() > n
+                && let Some(Reverse(min_k)) = keys.pop()
+            {
+                kvs.get_mut(&min_k).map(|v| v.pop());
             }
             (keys, kvs)
         })
@@ -0,0 +1,134 @@
+//! Utilities for golden (snapshot) tests of pipelines.
+//!
+//! A fixture is a text file with one record per line, `<timestamp_ms> <payload>`;
+//! blank lines and lines starting with `#` are ignored. Records are replayed with
+//! their fixture timestamps, so window assignment does not depend on wall-clock time.
+//!
+//! Snapshots store the complete output of a pipeline, one `<timestamp> <value>`
+//! line per record, sorted by timestamp and value so that the comparison does
+//! not depend on emission order.
+//! Set `FLUXUS_BLESS=1` to create or update snapshot files instead of comparing.
+
+use async_trait::async_trait;
+use fluxus_sinks::Sink;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::fmt::Debug;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::{CollectionSource, DataStream};
+
+/// Environment variable that switches snapshot assertions to update mode
+pub const BLESS_ENV: &str = "FLUXUS_BLESS";
+
+/// Load a fixture file into a source that replays the recorded timestamps
+pub fn fixture_source<P: AsRef<Path>>(path: P) -> StreamResult<CollectionSource<String>> {
+    let text = std::fs::read_to_string(path)?;
+    let records = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (ts, payload) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let ts = ts.parse::<i64>().map_err(|_| {
+                StreamError::Serialization(format!("invalid fixture timestamp: {}", line))
+            })?;
+            Ok((ts, payload.trim().to_string()))
+        })
+        .collect::<StreamResult<Vec<_>>>()?;
+    Ok(CollectionSource::with_timestamps(records))
+}
+
+/// Sink that keeps records together with their timestamps
+#[derive(Clone, Default)]
+struct RecordingSink {
+    lines: Arc<Mutex<Vec<(i64, String)>>>,
+}
+
+#[async_trait]
+impl<T> Sink<T> for RecordingSink
+where
+    T: Debug + Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn write(&mut self, record: Record<T>) -> StreamResult<()> {
+        if let Ok(mut lines) = self.lines.lock() {
+            lines.push((record.timestamp, format!("{:?}", record.data)));
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+/// Run the stream to completion and compare its normalized output with a snapshot file.
+///
+/// Panics with a line diff when the output differs from the snapshot.
+pub async fn assert_snapshot<T, P>(stream: DataStream<T>, snapshot: P) -> StreamResult<()>
+where
+    T: Debug + Clone + Send + Sync + 'static,
+    P: AsRef<Path>,
+{
+    let sink = RecordingSink::default();
+    stream.sink(sink.clone()).await?;
+
+    let mut lines = sink
+        .lines
+        .lock()
+        .map_or_else(|p| p.into_inner().clone(), |l| l.clone());
+    lines.sort();
+    let actual: String = lines
+        .iter()
+        .map(|(ts, value)| format!("{} {}\n", ts, value))
+        .collect();
+
+    let path = snapshot.as_ref();
+    if std::env::var_os(BLESS_ENV).is_some() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, actual)?;
+        return Ok(());
+    }
+
+    let expected = std::fs::read_to_string(path).map_err(|e| {
+        StreamError::Config(format!(
+            "cannot read snapshot {} ({}), run with {}=1 to create it",
+            path.display(),
+            e,
+            BLESS_ENV
+        ))
+    })?;
+    if expected != actual {
+        panic!(
+            "snapshot {} does not match, run with {}=1 to update it\n{}",
+            path.display(),
+            BLESS_ENV,
+            diff(&expected, &actual)
+        );
+    }
+    Ok(())
+}
+
+/// Line-based diff of two snapshots
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut out = String::new();
+    for line in expected.iter().filter(|line| !actual.contains(line)) {
+        out.push_str(&format!("- {}\n", line));
+    }
+    for line in actual.iter().filter(|line| !expected.contains(line)) {
+        out.push_str(&format!("+ {}\n", line));
+    }
+    out
+}
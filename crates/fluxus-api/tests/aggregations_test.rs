@@ -0,0 +1,199 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_utils::stats::Stats;
+use fluxus_utils::window::WindowConfig;
+
+fn trades() -> Vec<(&'static str, u32)> {
+    vec![("a", 30), ("b", 10), ("c", 50), ("d", 20)]
+}
+
+#[test]
+fn test_running_aggregations() {
+    tokio_test::block_on(async {
+        let count = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .count()
+            .sink(count.clone())
+            .await
+            .unwrap();
+        assert_eq!(count.get_data(), vec![1, 2, 3, 4]);
+
+        let sum = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .sum_by(|(_, qty)| *qty)
+            .sink(sum.clone())
+            .await
+            .unwrap();
+        assert_eq!(sum.get_data(), vec![30, 40, 90, 110]);
+
+        let min = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .min_by_key(|(_, qty)| *qty)
+            .map(|(name, _)| name)
+            .sink(min.clone())
+            .await
+            .unwrap();
+        assert_eq!(min.get_data(), vec!["a", "b", "b", "b"]);
+
+        let max = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .max_by_key(|(_, qty)| *qty)
+            .map(|(name, _)| name)
+            .sink(max.clone())
+            .await
+            .unwrap();
+        assert_eq!(max.get_data(), vec!["a", "a", "c", "c"]);
+
+        let mean = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .mean_by(|(_, qty)| *qty as f64)
+            .sink(mean.clone())
+            .await
+            .unwrap();
+        assert_eq!(mean.get_data(), vec![30.0, 20.0, 30.0, 27.5]);
+    })
+}
+
+#[test]
+fn test_windowed_aggregations() {
+    tokio_test::block_on(async {
+        let count = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .window(WindowConfig::global())
+            .count()
+            .sink(count.clone())
+            .await
+            .unwrap();
+        assert_eq!(count.get_last_element(), Some(4));
+
+        let sum = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .window(WindowConfig::global())
+            .sum_by(|(_, qty)| *qty)
+            .sink(sum.clone())
+            .await
+            .unwrap();
+        assert_eq!(sum.get_last_element(), Some(110));
+
+        let max = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .window(WindowConfig::global())
+            .max_by_key(|(_, qty)| *qty)
+            .sink(max.clone())
+            .await
+            .unwrap();
+        assert_eq!(max.get_last_element(), Some(("c", 50)));
+
+        let min = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .window(WindowConfig::global())
+            .min_by_key(|(_, qty)| *qty)
+            .sink(min.clone())
+            .await
+            .unwrap();
+        assert_eq!(min.get_last_element(), Some(("b", 10)));
+
+        let mean = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .window(WindowConfig::global())
+            .mean_by(|(_, qty)| *qty as f64)
+            .sink(mean.clone())
+            .await
+            .unwrap();
+        assert_eq!(mean.get_last_element(), Some(27.5));
+    })
+}
+
+#[test]
+fn test_windowed_numeric_aggregations() {
+    tokio_test::block_on(async {
+        let min = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .window(WindowConfig::global())
+            .min_by(|(_, qty)| *qty)
+            .sink(min.clone())
+            .await
+            .unwrap();
+        assert_eq!(min.get_last_element(), Some(10));
+
+        let max = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .window(WindowConfig::global())
+            .max_by(|(_, qty)| *qty as f64)
+            .sink(max.clone())
+            .await
+            .unwrap();
+        assert_eq!(max.get_last_element(), Some(50.0));
+
+        let avg = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .window(WindowConfig::global())
+            .avg_by(|(_, qty)| *qty as f64)
+            .sink(avg.clone())
+            .await
+            .unwrap();
+        assert_eq!(avg.get_last_element(), Some(27.5));
+
+        let stddev = CollectionSink::new();
+        DataStream::new(CollectionSource::new(trades()))
+            .window(WindowConfig::global())
+            .stddev_by(|(_, qty)| *qty as f64)
+            .sink(stddev.clone())
+            .await
+            .unwrap();
+        let stddev = stddev.get_last_element().unwrap();
+        assert!((stddev - 218.75f64.sqrt()).abs() < 1e-9);
+
+        // Merging the statistics of two halves gives those of the whole series
+        let whole: Stats = [30.0, 10.0, 50.0, 20.0].into_iter().collect();
+        let first: Stats = [30.0, 10.0].into_iter().collect();
+        let second: Stats = [50.0, 20.0].into_iter().collect();
+        let merged = first.merge(second);
+        assert_eq!(merged.count(), whole.count());
+        assert_eq!(merged.min(), Some(10.0));
+        assert_eq!(merged.max(), Some(50.0));
+        assert!((merged.variance().unwrap() - whole.variance().unwrap()).abs() < 1e-9);
+    })
+}
+
+#[test]
+fn test_windowed_quantiles() {
+    tokio_test::block_on(async {
+        // Latencies from 1 to 1000 milliseconds
+        let latencies: Vec<f64> = (1..=1000).map(f64::from).collect();
+        let sink = CollectionSink::new();
+        DataStream::new(CollectionSource::new(latencies))
+            .window(WindowConfig::global())
+            .quantiles(&[0.0, 0.5, 0.95, 0.99, 1.0], |latency| *latency)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        let quantiles = sink.get_last_element().unwrap();
+        let exact = [1.0, 500.0, 950.0, 990.0, 1000.0];
+        assert_eq!(quantiles.len(), exact.len());
+        for (estimate, exact) in quantiles.iter().zip(exact) {
+            assert!(
+                (estimate - exact).abs() <= exact * 0.01,
+                "{estimate} vs {exact}"
+            );
+        }
+    })
+}
+
+#[test]
+fn test_windowed_distinct_count_approx() {
+    tokio_test::block_on(async {
+        // 20000 visits of 5000 users
+        let visits: Vec<u32> = (0..20_000).map(|i| i % 5_000).collect();
+        let sink = CollectionSink::new();
+        DataStream::new(CollectionSource::new(visits))
+            .window(WindowConfig::global())
+            .distinct_count_approx()
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        let count = sink.get_last_element().unwrap();
+        assert!(count.abs_diff(5_000) <= 250, "{count}");
+    })
+}
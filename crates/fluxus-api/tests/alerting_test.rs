@@ -0,0 +1,92 @@
+use fluxus_core::{AlertEngine, AlertRule, AlertSource, AlertState, Metrics};
+use fluxus_sources::Source;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn test_alert_rules_from_json() {
+    let rules = r#"[
+        {"name": "lagging", "metric": "job.watermark_lag", "condition": "above", "threshold": 1000},
+        {"name": "failing", "metric": "job.records_failed", "condition": "rate_above", "per_sec": 5}
+    ]"#;
+    let engine = AlertEngine::from_json(Arc::new(Metrics::new()), rules).unwrap();
+    assert_eq!(
+        engine.rules(),
+        [
+            AlertRule::above("lagging", "job.watermark_lag", 1000.0),
+            AlertRule::rate_above("failing", "job.records_failed", 5.0),
+        ]
+    );
+
+    let invalid = r#"[{"name": "lagging", "metric": "lag", "condition": "sideways"}]"#;
+    assert!(AlertEngine::from_json(Arc::new(Metrics::new()), invalid).is_err());
+}
+
+#[test]
+fn test_alert_engine_fires_and_resolves() {
+    let mut metrics = Metrics::new();
+    let lag = metrics.gauge("job.watermark_lag");
+    let failed = metrics.counter("job.records_failed");
+    let mut engine = AlertEngine::new(
+        Arc::new(metrics),
+        vec![
+            AlertRule::above("lagging", "job.watermark_lag", 1000.0),
+            AlertRule::rate_above("failing", "job.records_failed", 5.0),
+            AlertRule::below("missing", "job.missing", 1.0),
+        ],
+    );
+    assert!(engine.evaluate().is_empty());
+
+    // Rules only alert when they change state
+    lag.set(5000);
+    failed.add(100);
+    std::thread::sleep(Duration::from_millis(20));
+    let alerts = engine.evaluate();
+    assert_eq!(alerts.len(), 2);
+    assert_eq!(alerts[0].rule, "lagging");
+    assert_eq!(alerts[0].state, AlertState::Firing);
+    assert_eq!(alerts[0].value, 5000.0);
+    assert_eq!(
+        alerts[0].to_string(),
+        "[FIRING] lagging: job.watermark_lag = 5000 (threshold 1000)"
+    );
+    assert_eq!(alerts[1].rule, "failing");
+    assert!(alerts[1].value > 5.0);
+
+    // The failure rate drops once no more records fail
+    std::thread::sleep(Duration::from_millis(5));
+    let alerts = engine.evaluate();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule, "failing");
+    assert_eq!(alerts[0].state, AlertState::Resolved);
+
+    lag.set(10);
+    let alerts = engine.evaluate();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].rule, "lagging");
+    assert_eq!(alerts[0].state, AlertState::Resolved);
+}
+
+#[tokio::test]
+async fn test_alert_source() {
+    let mut metrics = Metrics::new();
+    let lag = metrics.gauge("job.watermark_lag");
+    let engine = AlertEngine::new(
+        Arc::new(metrics),
+        vec![AlertRule::above("lagging", "job.watermark_lag", 1000.0)],
+    );
+    let mut source = AlertSource::new(engine, Duration::from_millis(5));
+    source.init().await.unwrap();
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        lag.set(2000);
+    });
+    let alert = tokio::time::timeout(Duration::from_secs(5), source.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(alert.data.rule, "lagging");
+    assert_eq!(alert.data.state, AlertState::Firing);
+}
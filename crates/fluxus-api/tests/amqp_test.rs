@@ -0,0 +1,235 @@
+use fluxus_api::{CollectionSource, DataStream};
+use fluxus_sinks::AmqpSink;
+use fluxus_sources::{AmqpSource, Source};
+use fluxus_utils::amqp::{AmqpOptions, AmqpQueue};
+use fluxus_utils::models::StreamError;
+use fluxus_utils::security::TlsConfig;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Read a frame as its type and payload
+async fn read_frame(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let kind = stream.read_u8().await.unwrap();
+    let _channel = stream.read_u16().await.unwrap();
+    let size = stream.read_u32().await.unwrap() as usize;
+    let mut payload = vec![0; size];
+    stream.read_exact(&mut payload).await.unwrap();
+    assert_eq!(stream.read_u8().await.unwrap(), 0xce);
+    (kind, payload)
+}
+
+/// Read a method frame, checking its class and method ids, and return its
+/// arguments
+async fn read_method(stream: &mut TcpStream, class: u16, method: u16) -> Vec<u8> {
+    let (kind, payload) = read_frame(stream).await;
+    assert_eq!(kind, 1);
+    let ids = [class.to_be_bytes(), method.to_be_bytes()].concat();
+    assert_eq!(payload[..4], ids, "expected method {}.{}", class, method);
+    payload[4..].to_vec()
+}
+
+async fn write_frame(stream: &mut TcpStream, kind: u8, channel: u16, payload: &[u8]) {
+    let mut bytes = vec![kind];
+    bytes.extend_from_slice(&channel.to_be_bytes());
+    bytes.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(payload);
+    bytes.push(0xce);
+    stream.write_all(&bytes).await.unwrap();
+}
+
+async fn write_method(stream: &mut TcpStream, channel: u16, class: u16, method: u16, args: &[u8]) {
+    let payload = [&class.to_be_bytes()[..], &method.to_be_bytes(), args].concat();
+    write_frame(stream, 1, channel, &payload).await;
+}
+
+fn short_str(value: &str) -> Vec<u8> {
+    [&[value.len() as u8][..], value.as_bytes()].concat()
+}
+
+/// Accept a connection and open its channel
+async fn accept(listener: &TcpListener) -> TcpStream {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let mut header = [0; 8];
+    stream.read_exact(&mut header).await.unwrap();
+    assert_eq!(&header, b"AMQP\x00\x00\x09\x01");
+
+    let start = [
+        &[0, 9][..],
+        &0u32.to_be_bytes(),
+        &5u32.to_be_bytes(),
+        b"PLAIN",
+        &5u32.to_be_bytes(),
+        b"en_US",
+    ]
+    .concat();
+    write_method(&mut stream, 0, 10, 10, &start).await;
+    let start_ok = read_method(&mut stream, 10, 11).await;
+    assert!(start_ok.windows(12).any(|w| w == b"\0guest\0guest"));
+    let tune = [
+        &0u16.to_be_bytes()[..],
+        &131_072u32.to_be_bytes(),
+        &0u16.to_be_bytes(),
+    ]
+    .concat();
+    write_method(&mut stream, 0, 10, 30, &tune).await;
+    read_method(&mut stream, 10, 31).await;
+    read_method(&mut stream, 10, 40).await;
+    write_method(&mut stream, 0, 10, 41, &short_str("")).await;
+    read_method(&mut stream, 20, 10).await;
+    write_method(&mut stream, 1, 20, 11, &0u32.to_be_bytes()).await;
+    stream
+}
+
+/// Answer a queue declaration, returning its arguments
+async fn accept_declare(stream: &mut TcpStream, queue: &str) -> Vec<u8> {
+    let declare = read_method(stream, 50, 10).await;
+    let ok = [short_str(queue), vec![0; 8]].concat();
+    write_method(stream, 1, 50, 11, &ok).await;
+    declare
+}
+
+async fn deliver(stream: &mut TcpStream, tag: u64, body: &[u8]) {
+    let args = [
+        short_str("ctag"),
+        tag.to_be_bytes().to_vec(),
+        vec![0],
+        short_str(""),
+        short_str("readings"),
+    ]
+    .concat();
+    write_method(stream, 1, 60, 60, &args).await;
+    let header = [
+        &60u16.to_be_bytes()[..],
+        &[0, 0],
+        &(body.len() as u64).to_be_bytes(),
+        &[0, 0],
+    ]
+    .concat();
+    write_frame(stream, 2, 1, &header).await;
+    write_frame(stream, 3, 1, body).await;
+}
+
+fn options(listener: &TcpListener) -> AmqpOptions {
+    let port = listener.local_addr().unwrap().port();
+    AmqpOptions::new("127.0.0.1", port).with_reconnect_delay(Duration::from_millis(10))
+}
+
+#[tokio::test]
+async fn test_amqp_source_acks_after_flush() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let queue = AmqpQueue::new("readings").with_dead_letter("poison", None);
+    let mut source = AmqpSource::new(options(&listener), queue)
+        .with_prefetch(2)
+        .with_decoder(|delivery| {
+            String::from_utf8_lossy(&delivery.body)
+                .parse::<i32>()
+                .map_err(|e| StreamError::Serialization(e.to_string()))
+        });
+    let acker = source.acker();
+
+    let broker = tokio::spawn(async move {
+        let mut stream = accept(&listener).await;
+        let declare = accept_declare(&mut stream, "readings").await;
+        assert!(declare.windows(22).any(|w| w == b"x-dead-letter-exchange"));
+        let qos = read_method(&mut stream, 60, 10).await;
+        assert_eq!(qos[4..6], 2u16.to_be_bytes());
+        write_method(&mut stream, 1, 60, 11, &[]).await;
+        read_method(&mut stream, 60, 20).await;
+        write_method(&mut stream, 1, 60, 21, &short_str("ctag")).await;
+
+        deliver(&mut stream, 1, b"1").await;
+        deliver(&mut stream, 2, b"oops").await;
+        deliver(&mut stream, 3, b"3").await;
+        // The poison message is rejected without requeueing
+        let nack = read_method(&mut stream, 60, 120).await;
+        assert_eq!(nack, [&2u64.to_be_bytes()[..], &[0]].concat());
+        // Only the first delivery was flushed
+        let ack = read_method(&mut stream, 60, 80).await;
+        assert_eq!(ack, [&1u64.to_be_bytes()[..], &[1]].concat());
+        deliver(&mut stream, 4, b"4").await;
+        stream
+    });
+
+    source.init().await.unwrap();
+    let first = source.next().await.unwrap().unwrap();
+    assert_eq!(first.data, 1);
+    let second = source.next().await.unwrap().unwrap();
+    assert_eq!(second.data, 3);
+    assert!(second.timestamp > first.timestamp);
+
+    acker.ack_until(first.timestamp);
+    assert_eq!(source.next().await.unwrap().unwrap().data, 4);
+    broker.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_amqp_sink_waits_for_confirms() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let sink =
+        AmqpSink::json(options(&listener), "", "numbers").with_queue(AmqpQueue::new("numbers"));
+
+    let broker = tokio::spawn(async move {
+        let mut stream = accept(&listener).await;
+        accept_declare(&mut stream, "numbers").await;
+        read_method(&mut stream, 85, 10).await;
+        write_method(&mut stream, 1, 85, 11, &[]).await;
+
+        let mut bodies = Vec::new();
+        loop {
+            let (_, payload) = read_frame(&mut stream).await;
+            if payload[..4] == [0, 10, 0, 50] {
+                write_method(&mut stream, 0, 10, 51, &[]).await;
+                return bodies;
+            }
+            assert_eq!(payload[..4], [0, 60, 0, 40]);
+            let (_, header) = read_frame(&mut stream).await;
+            // Persistent delivery mode
+            assert_eq!(header[12..], [0x10, 0, 2]);
+            let (_, body) = read_frame(&mut stream).await;
+            bodies.push(String::from_utf8(body).unwrap());
+            let tag = bodies.len() as u64;
+            write_method(
+                &mut stream,
+                1,
+                60,
+                80,
+                &[&tag.to_be_bytes()[..], &[0]].concat(),
+            )
+            .await;
+        }
+    });
+
+    DataStream::new(CollectionSource::new(vec![1, 2, 3]))
+        .sink(sink)
+        .await
+        .unwrap();
+    assert_eq!(broker.await.unwrap(), vec!["1", "2", "3"]);
+}
+
+#[tokio::test]
+async fn test_amqp_tls() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut source = AmqpSource::new(
+        options(&listener).with_tls(TlsConfig::new()),
+        AmqpQueue::new("numbers"),
+    );
+    let broker = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        // A TLS handshake starts with a handshake record instead of the
+        // protocol header
+        assert_eq!(stream.read_u8().await.unwrap(), 0x16);
+    });
+
+    assert!(source.init().await.is_err());
+    broker.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_amqp_tls_rejects_invalid_certs() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let tls = TlsConfig::new().with_accept_invalid_certs(true);
+    let mut source = AmqpSource::new(options(&listener).with_tls(tls), AmqpQueue::new("numbers"));
+
+    assert!(matches!(source.init().await, Err(StreamError::Config(_))));
+}
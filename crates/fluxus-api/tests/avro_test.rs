@@ -0,0 +1,186 @@
+use apache_avro::types::Value;
+use apache_avro::{Codec, DeflateSettings, Writer};
+use fluxus_api::{CollectionSink, DataStream};
+use fluxus_sources::avro::Schema;
+use fluxus_sources::{AvroSource, Source};
+use serde::Deserialize;
+use std::path::Path;
+
+const USER_V1: &str = r#"{
+    "type": "record",
+    "name": "User",
+    "namespace": "com.example",
+    "fields": [
+        {"name": "id", "type": "int"},
+        {"name": "name", "type": "string"},
+        {"name": "email", "type": ["null", "string"]},
+        {"name": "plan", "type": {"type": "enum", "name": "Plan", "symbols": ["FREE", "PRO", "LEGACY"]}},
+        {"name": "tags", "type": {"type": "array", "items": "string"}},
+        {"name": "legacy_score", "type": "double"}
+    ]
+}"#;
+
+const USER_V2: &str = r#"{
+    "type": "record",
+    "name": "User",
+    "namespace": "com.example",
+    "fields": [
+        {"name": "id", "type": "long"},
+        {"name": "name", "type": "string"},
+        {"name": "email", "type": ["null", "string"], "default": null},
+        {"name": "plan", "type": {"type": "enum", "name": "Plan", "symbols": ["FREE", "PRO"], "default": "FREE"}},
+        {"name": "tags", "type": {"type": "array", "items": "string"}},
+        {"name": "country", "type": "string", "default": "unknown"},
+        {"name": "limits", "type": {"type": "map", "values": "long"}, "default": {"requests": 100}}
+    ]
+}"#;
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+struct UserV1 {
+    id: i32,
+    name: String,
+    email: Option<String>,
+    plan: Plan,
+    tags: Vec<String>,
+    legacy_score: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "UPPERCASE")]
+enum Plan {
+    Free,
+    Pro,
+    Legacy,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+struct Account {
+    id: i64,
+    name: String,
+    email: Option<String>,
+    plan: Plan,
+    tags: Vec<String>,
+    country: String,
+    limits: std::collections::HashMap<String, i64>,
+}
+
+fn user(id: i32, name: &str, email: Option<&str>, plan: u32, tags: &[&str]) -> Value {
+    let symbols = ["FREE", "PRO", "LEGACY"];
+    let email = match email {
+        Some(email) => Value::Union(1, Box::new(Value::String(email.to_string()))),
+        None => Value::Union(0, Box::new(Value::Null)),
+    };
+    Value::Record(vec![
+        ("id".to_string(), Value::Int(id)),
+        ("name".to_string(), Value::String(name.to_string())),
+        ("email".to_string(), email),
+        (
+            "plan".to_string(),
+            Value::Enum(plan, symbols[plan as usize].to_string()),
+        ),
+        (
+            "tags".to_string(),
+            Value::Array(tags.iter().map(|t| Value::String(t.to_string())).collect()),
+        ),
+        ("legacy_score".to_string(), Value::Double(id as f64 * 1.5)),
+    ])
+}
+
+/// A container file of users written with the first version of the schema,
+/// in two blocks
+fn users_file(dir: &Path, codec: Codec) -> std::path::PathBuf {
+    let schema = Schema::parse_str(USER_V1).unwrap();
+    let mut writer = Writer::with_codec(&schema, Vec::new(), codec).unwrap();
+    writer
+        .append_value(user(
+            1,
+            "Ada",
+            Some("ada@example.com"),
+            1,
+            &["admin", "beta"],
+        ))
+        .unwrap();
+    writer.append_value(user(2, "Grace", None, 0, &[])).unwrap();
+    writer.flush().unwrap();
+    writer
+        .append_value(user(3, "Linus", None, 2, &["kernel"]))
+        .unwrap();
+
+    let path = dir.join("users.avro");
+    std::fs::write(&path, writer.into_inner().unwrap()).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn test_avro_source_reads_with_writer_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let sink = CollectionSink::new();
+    DataStream::new(AvroSource::<UserV1>::new(users_file(
+        dir.path(),
+        Codec::Null,
+    )))
+    .sink(sink.clone())
+    .await
+    .unwrap();
+
+    let users = sink.get_data();
+    assert_eq!(users.len(), 3);
+    assert_eq!(
+        users[0],
+        UserV1 {
+            id: 1,
+            name: "Ada".to_string(),
+            email: Some("ada@example.com".to_string()),
+            plan: Plan::Pro,
+            tags: vec!["admin".to_string(), "beta".to_string()],
+            legacy_score: 1.5,
+        }
+    );
+    assert_eq!(users[1].email, None);
+    assert_eq!(users[2].plan, Plan::Legacy);
+}
+
+#[tokio::test]
+async fn test_avro_source_resolves_reader_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = AvroSource::<Account>::new(users_file(
+        dir.path(),
+        Codec::Deflate(DeflateSettings::default()),
+    ))
+    .with_reader_schema(Schema::parse_str(USER_V2).unwrap());
+    let sink = CollectionSink::new();
+    DataStream::new(source).sink(sink.clone()).await.unwrap();
+
+    let accounts = sink.get_data();
+    assert_eq!(
+        accounts[0],
+        Account {
+            id: 1,
+            name: "Ada".to_string(),
+            email: Some("ada@example.com".to_string()),
+            plan: Plan::Pro,
+            tags: vec!["admin".to_string(), "beta".to_string()],
+            country: "unknown".to_string(),
+            limits: [("requests".to_string(), 100)].into(),
+        }
+    );
+    assert_eq!(accounts[1].name, "Grace");
+    // A symbol the reader doesn't know takes the enum default
+    assert_eq!(accounts[2].plan, Plan::Free);
+}
+
+#[tokio::test]
+async fn test_avro_source_rejects_incompatible_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let reader = Schema::parse_str(
+        r#"{"type": "record", "name": "User", "fields": [
+            {"name": "id", "type": "int"},
+            {"name": "created_at", "type": "long"}
+        ]}"#,
+    )
+    .unwrap();
+    let mut source = AvroSource::<serde_json::Value>::new(users_file(dir.path(), Codec::Null))
+        .with_reader_schema(reader);
+    let error = source.init().await.unwrap_err();
+    assert!(error.to_string().contains("created_at"), "{}", error);
+}
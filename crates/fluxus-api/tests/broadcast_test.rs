@@ -0,0 +1,63 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+struct Threshold {
+    sensor: &'static str,
+    max: i32,
+}
+
+/// Delays every element so that the broadcast input is read first
+fn delayed<T: Clone + Send + Sync + 'static>(data: Vec<T>) -> DataStream<T> {
+    DataStream::new(CollectionSource::new(data)).filter_async(|_| async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        true
+    })
+}
+
+#[test]
+fn test_broadcast_join() {
+    tokio_test::block_on(async {
+        let thresholds = DataStream::new(CollectionSource::new(vec![
+            Threshold {
+                sensor: "a",
+                max: 10,
+            },
+            Threshold {
+                sensor: "b",
+                max: 20,
+            },
+        ]))
+        .broadcast();
+
+        let alerts = |stream: DataStream<(&'static str, i32)>| {
+            stream.connect(&thresholds).process(
+                HashMap::new(),
+                |state: &mut HashMap<&'static str, i32>, threshold: Threshold| {
+                    state.insert(threshold.sensor, threshold.max);
+                },
+                |state, (sensor, value)| {
+                    state
+                        .get(sensor)
+                        .filter(|max| value > **max)
+                        .map(|max| format!("{} {} > {}", sensor, value, max))
+                },
+            )
+        };
+        let first = alerts(delayed(vec![("a", 5), ("a", 15), ("c", 100)]));
+        let second = alerts(delayed(vec![("b", 25), ("b", 15)]));
+
+        let first_sink = CollectionSink::new();
+        let second_sink = CollectionSink::new();
+        let (first, second) = tokio::join!(
+            first.sink(first_sink.clone()),
+            second.sink(second_sink.clone())
+        );
+        first.unwrap();
+        second.unwrap();
+
+        assert_eq!(first_sink.get_data(), vec!["a 15 > 10".to_string()]);
+        assert_eq!(second_sink.get_data(), vec!["b 25 > 20".to_string()]);
+    })
+}
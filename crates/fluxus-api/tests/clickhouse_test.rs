@@ -0,0 +1,371 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_sinks::ClickHouseSink;
+use fluxus_sources::ClickHouseSource;
+use fluxus_utils::clickhouse::ClickHouseOptions;
+use fluxus_utils::models::StreamError;
+use fluxus_utils::row::{Row, Value};
+use fluxus_utils::security::TlsConfig;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A request received by the fake server: its request line, headers and body
+#[derive(Debug, Clone)]
+struct Request {
+    line: String,
+    headers: Vec<String>,
+    body: Vec<u8>,
+}
+
+/// Serve HTTP requests answering each with the given status and body, the
+/// body split in two writes, and record the requests
+async fn serve(status: &'static str, body: Vec<u8>) -> (String, Arc<Mutex<Vec<Request>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let received = requests.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut stream).await;
+            received.lock().unwrap().push(request);
+
+            let head = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            stream.write_all(head.as_bytes()).await.unwrap();
+            let (first, second) = body.split_at(body.len() / 2);
+            stream.write_all(first).await.unwrap();
+            stream.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            stream.write_all(second).await.unwrap();
+        }
+    });
+    (url, requests)
+}
+
+async fn read_request(stream: &mut TcpStream) -> Request {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    let head_end = loop {
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        let n = stream.read(&mut buf).await.unwrap();
+        data.extend_from_slice(&buf[..n]);
+    };
+    let head = String::from_utf8(data[..head_end].to_vec()).unwrap();
+    let mut lines = head.split("\r\n").map(str::to_string);
+    let line = lines.next().unwrap();
+    let headers: Vec<String> = lines.collect();
+    let length: Option<usize> = headers.iter().find_map(|h| {
+        h.to_ascii_lowercase()
+            .strip_prefix("content-length: ")?
+            .parse()
+            .ok()
+    });
+    let mut body = data[head_end + 4..].to_vec();
+    match length {
+        Some(length) => {
+            while body.len() < length {
+                let n = stream.read(&mut buf).await.unwrap();
+                body.extend_from_slice(&buf[..n]);
+            }
+        }
+        // A chunked body, which ends with an empty chunk
+        None => {
+            while !body.ends_with(b"0\r\n\r\n") {
+                let n = stream.read(&mut buf).await.unwrap();
+                body.extend_from_slice(&buf[..n]);
+            }
+            body = dechunk(&body);
+        }
+    }
+    Request {
+        line,
+        headers,
+        body,
+    }
+}
+
+fn dechunk(mut chunked: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = chunked.windows(2).position(|w| w == b"\r\n").unwrap();
+        let size = std::str::from_utf8(&chunked[..line_end]).unwrap();
+        let size = usize::from_str_radix(size, 16).unwrap();
+        if size == 0 {
+            return body;
+        }
+        let start = line_end + 2;
+        body.extend_from_slice(&chunked[start..start + size]);
+        chunked = &chunked[start + size + 2..];
+    }
+}
+
+fn put_string(out: &mut Vec<u8>, text: &[u8]) {
+    let mut len = text.len();
+    loop {
+        let byte = (len & 0x7f) as u8;
+        len >>= 7;
+        if len == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+    out.extend_from_slice(text);
+}
+
+/// Results of a query in the `RowBinaryWithNamesAndTypes` format
+fn results(columns: &[(&str, &str)], rows: &[Vec<u8>]) -> Vec<u8> {
+    let mut out = vec![columns.len() as u8];
+    for (name, _) in columns {
+        put_string(&mut out, name.as_bytes());
+    }
+    for (_, column_type) in columns {
+        put_string(&mut out, column_type.as_bytes());
+    }
+    for row in rows {
+        out.extend_from_slice(row);
+    }
+    out
+}
+
+#[tokio::test]
+async fn test_clickhouse_source_decodes_row_binary() {
+    let columns = [
+        ("id", "UInt64"),
+        ("page", "LowCardinality(String)"),
+        ("duration", "Nullable(Float64)"),
+        ("at", "DateTime64(6, 'UTC')"),
+        ("day", "Date"),
+        ("status", "Enum8('ok' = 1, 'it\\'s bad' = -2)"),
+        ("tags", "Array(String)"),
+        ("visitor", "UUID"),
+        ("price", "Decimal(9, 2)"),
+    ];
+    let mut row = Vec::new();
+    row.extend(7u64.to_le_bytes());
+    put_string(&mut row, b"/home");
+    row.push(0);
+    row.extend(1.5f64.to_le_bytes());
+    row.extend(1_700_000_000_123_456i64.to_le_bytes());
+    row.extend(19_675u16.to_le_bytes());
+    row.push(-2i8 as u8);
+    row.push(2);
+    put_string(&mut row, b"a");
+    put_string(&mut row, b"b");
+    row.extend(0x0123_4567_89ab_cdefu64.to_le_bytes());
+    row.extend(0xfedc_ba98_7654_3210u64.to_le_bytes());
+    row.extend((-1234i32).to_le_bytes());
+
+    let mut null_row = Vec::new();
+    null_row.extend(8u64.to_le_bytes());
+    put_string(&mut null_row, b"/cart");
+    null_row.push(1);
+    null_row.extend(0i64.to_le_bytes());
+    null_row.extend(0u16.to_le_bytes());
+    null_row.push(1);
+    null_row.push(0);
+    null_row.extend([0; 16]);
+    null_row.extend(0i32.to_le_bytes());
+
+    let (url, requests) = serve("200 OK", results(&columns, &[row, null_row])).await;
+    let options = ClickHouseOptions::new(url)
+        .with_database("analytics")
+        .with_credentials("reader", "secret");
+    let sink = CollectionSink::<Row>::new();
+    DataStream::new(ClickHouseSource::new(options, "SELECT * FROM visits;"))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+
+    let rows = sink.get_data();
+    assert_eq!(rows.len(), 2);
+    let values = |row: &Row| -> Vec<Value> {
+        columns
+            .iter()
+            .map(|(name, _)| row.get_by_name(name).unwrap().clone())
+            .collect()
+    };
+    assert_eq!(
+        values(&rows[0]),
+        vec![
+            Value::Int64(7),
+            Value::String("/home".to_string()),
+            Value::Float64(1.5),
+            Value::Timestamp(1_700_000_000_123),
+            Value::Timestamp(19_675 * 86_400_000),
+            Value::String("it's bad".to_string()),
+            Value::String(r#"["a","b"]"#.to_string()),
+            Value::String("01234567-89ab-cdef-fedc-ba9876543210".to_string()),
+            Value::Float64(-12.34),
+        ]
+    );
+    assert!(rows[1].is_null("duration"));
+    assert_eq!(
+        rows[1].get_by_name("status"),
+        Some(&Value::String("ok".to_string()))
+    );
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(
+        requests[0]
+            .line
+            .starts_with("POST /?default_format=RowBinaryWithNamesAndTypes&database=analytics "),
+        "{}",
+        requests[0].line
+    );
+    assert!(
+        requests[0]
+            .headers
+            .contains(&"x-clickhouse-user: reader".to_string())
+    );
+    assert!(
+        requests[0]
+            .headers
+            .contains(&"x-clickhouse-key: secret".to_string())
+    );
+    assert_eq!(requests[0].body, b"SELECT * FROM visits");
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+struct Visit {
+    id: i64,
+    page: String,
+}
+
+#[tokio::test]
+async fn test_clickhouse_source_deserializes_rows() {
+    let rows: Vec<Vec<u8>> = (1..=3u32)
+        .map(|id| {
+            let mut row = id.to_le_bytes().to_vec();
+            put_string(&mut row, format!("/page/{}", id).as_bytes());
+            row
+        })
+        .collect();
+    let (url, _) = serve(
+        "200 OK",
+        results(&[("id", "UInt32"), ("page", "String")], &rows),
+    )
+    .await;
+    let sink = CollectionSink::new();
+    DataStream::new(
+        ClickHouseSource::new(ClickHouseOptions::new(url), "SELECT id, page FROM visits")
+            .deserialize::<Visit>(),
+    )
+    .sink(sink.clone())
+    .await
+    .unwrap();
+
+    let ids: Vec<i64> = sink.get_data().iter().map(|visit| visit.id).collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert_eq!(sink.get_data()[2].page, "/page/3");
+}
+
+#[tokio::test]
+async fn test_clickhouse_source_reports_query_errors() {
+    let (url, _) = serve(
+        "404 Not Found",
+        b"Code: 60. DB::Exception: Table default.missing does not exist.".to_vec(),
+    )
+    .await;
+    let result = DataStream::new(ClickHouseSource::new(
+        ClickHouseOptions::new(url),
+        "SELECT * FROM missing",
+    ))
+    .sink(CollectionSink::new())
+    .await;
+
+    let error = result.unwrap_err().to_string();
+    assert!(
+        error.contains("Table default.missing does not exist"),
+        "{}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn test_clickhouse_sink_inserts_batches() {
+    let (url, requests) = serve("200 OK", Vec::new()).await;
+    let options = ClickHouseOptions::new(url).with_setting("async_insert", "1");
+    let visits: Vec<Visit> = (1..=5)
+        .map(|id| Visit {
+            id,
+            page: format!("/page/{}", id),
+        })
+        .collect();
+    DataStream::new(CollectionSource::new(visits))
+        .sink(ClickHouseSink::json(options, "visits").batched(2, Duration::from_secs(60)))
+        .await
+        .unwrap();
+
+    let requests = requests.lock().unwrap();
+    // A query checking the connection, then an insert per batch
+    assert_eq!(requests.len(), 4);
+    assert_eq!(requests[0].body, b"SELECT 1");
+    let mut inserted = Vec::new();
+    for request in &requests[1..] {
+        assert!(
+            request
+                .line
+                .contains("query=INSERT+INTO+visits+FORMAT+JSONEachRow"),
+            "{}",
+            request.line
+        );
+        assert!(request.line.contains("async_insert=1"), "{}", request.line);
+        let body = String::from_utf8(request.body.clone()).unwrap();
+        inserted.extend(
+            body.lines()
+                .map(|line| serde_json::from_str::<Visit>(line).unwrap().id),
+        );
+    }
+    assert_eq!(inserted, vec![1, 2, 3, 4, 5]);
+    assert_eq!(
+        requests[1].body,
+        b"{\"id\":1,\"page\":\"/page/1\"}\n{\"id\":2,\"page\":\"/page/2\"}\n"
+    );
+}
+
+#[tokio::test]
+async fn test_clickhouse_tls() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!(
+        "https://localhost:{}",
+        listener.local_addr().unwrap().port()
+    );
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        // A TLS handshake starts with a handshake record instead of a request
+        assert_eq!(stream.read_u8().await.unwrap(), 0x16);
+    });
+
+    let result = DataStream::new(ClickHouseSource::new(
+        ClickHouseOptions::new(url).with_tls(TlsConfig::new()),
+        "SELECT 1",
+    ))
+    .sink(CollectionSink::<Row>::new())
+    .await;
+    assert!(result.is_err());
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_clickhouse_tls_invalid_ca_cert() {
+    let dir = tempfile::tempdir().unwrap();
+    let ca_cert = dir.path().join("ca.pem");
+    std::fs::write(&ca_cert, "not a certificate").unwrap();
+    let options = ClickHouseOptions::new("https://localhost:8443")
+        .with_tls(TlsConfig::new().with_ca_cert(ca_cert));
+
+    assert!(matches!(
+        options.client().await,
+        Err(StreamError::Config(_))
+    ));
+}
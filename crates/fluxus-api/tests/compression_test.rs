@@ -0,0 +1,36 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_sinks::{FileSink, file::FileFormat};
+use fluxus_sources::CsvSource;
+use fluxus_utils::compression::CompressionCodec;
+
+#[test]
+fn test_compressed_file_roundtrip() {
+    tokio_test::block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        for codec in [
+            CompressionCodec::Gzip,
+            CompressionCodec::Zstd,
+            CompressionCodec::Bzip2,
+            CompressionCodec::Xz,
+        ] {
+            let path = dir
+                .path()
+                .join(format!("data.csv.{}", codec.extension().unwrap()));
+            assert_eq!(CompressionCodec::from_extension(&path), codec);
+
+            let source = CollectionSource::new(vec!["a,1", "b,2", "c,3"]);
+            DataStream::new(source)
+                .map(|s| s.to_string())
+                .sink(FileSink::new(&path, FileFormat::Text).with_compression(codec))
+                .await
+                .unwrap();
+
+            let sink = CollectionSink::new();
+            DataStream::new(CsvSource::new(&path).with_compression(CompressionCodec::Auto))
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec!["\"a,1\"", "\"b,2\"", "\"c,3\""]);
+        }
+    })
+}
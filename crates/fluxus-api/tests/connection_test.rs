@@ -0,0 +1,138 @@
+use async_trait::async_trait;
+use fluxus_api::{CollectionSource, DataStream};
+use fluxus_core::{ConnectionPool, Connector, Metrics, RetryStrategy};
+use fluxus_sinks::Sink;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A service that keeps written values and can be restarted, which breaks
+/// the connections opened before the restart
+#[derive(Clone, Default)]
+struct Service {
+    generation: Arc<AtomicUsize>,
+    /// Connects that fail before one succeeds
+    unavailable: Arc<AtomicUsize>,
+    written: Arc<Mutex<Vec<i32>>>,
+}
+
+impl Service {
+    fn restart(&self, unavailable: usize) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.unavailable.store(unavailable, Ordering::SeqCst);
+    }
+}
+
+struct Connection {
+    service: Service,
+    generation: usize,
+}
+
+impl Connection {
+    fn write(&self, value: i32) -> StreamResult<()> {
+        if self.generation != self.service.generation.load(Ordering::SeqCst) {
+            return Err(StreamError::Runtime("connection reset".to_string()));
+        }
+        self.service.written.lock().unwrap().push(value);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Connector for Service {
+    type Connection = Connection;
+
+    async fn connect(&self) -> StreamResult<Connection> {
+        let unavailable = self.unavailable.load(Ordering::SeqCst);
+        if unavailable > 0 {
+            self.unavailable.store(unavailable - 1, Ordering::SeqCst);
+            return Err(StreamError::Runtime("connection refused".to_string()));
+        }
+        Ok(Connection {
+            service: self.clone(),
+            generation: self.generation.load(Ordering::SeqCst),
+        })
+    }
+
+    async fn is_healthy(&self, connection: &mut Connection) -> bool {
+        connection.generation == self.generation.load(Ordering::SeqCst)
+    }
+}
+
+/// Sink writing through a pool, restarting the service after the given value
+struct ServiceSink {
+    pool: ConnectionPool<Service>,
+    service: Service,
+    restart_after: i32,
+}
+
+#[async_trait]
+impl Sink<i32> for ServiceSink {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn write(&mut self, record: Record<i32>) -> StreamResult<()> {
+        let connection = self.pool.get().await?;
+        if let Err(e) = connection.write(record.data) {
+            connection.discard();
+            return Err(e);
+        }
+        drop(connection);
+        if record.data == self.restart_after {
+            self.service.restart(2);
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_pool_reconnects_after_restart() {
+    tokio_test::block_on(async {
+        let service = Service::default();
+        let mut metrics = Metrics::new();
+        let pool = ConnectionPool::new(service.clone(), 2)
+            .with_retry(RetryStrategy::fixed(Duration::from_millis(5), 3))
+            .report_to(&mut metrics, "service");
+        let sink = ServiceSink {
+            pool: pool.clone(),
+            service: service.clone(),
+            restart_after: 2,
+        };
+
+        DataStream::new(CollectionSource::new(vec![1, 2, 3, 4]))
+            .sink(sink)
+            .await
+            .unwrap();
+
+        assert_eq!(*service.written.lock().unwrap(), vec![1, 2, 3, 4]);
+        // The connection broken by the restart was replaced after two refused connects
+        assert_eq!(pool.connects(), 2);
+        assert_eq!(pool.connect_failures(), 2);
+        assert_eq!(pool.open_connections(), 1);
+        assert!(metrics.snapshot().contains_key("service.connections_open"));
+    })
+}
+
+#[test]
+fn test_pool_gives_up_after_retries() {
+    tokio_test::block_on(async {
+        let service = Service::default();
+        service.restart(10);
+        let pool = ConnectionPool::new(service, 1)
+            .with_retry(RetryStrategy::fixed(Duration::from_millis(1), 2));
+
+        assert!(pool.get().await.is_err());
+        assert_eq!(pool.connect_failures(), 3);
+        assert_eq!(pool.open_connections(), 0);
+    })
+}
@@ -0,0 +1,95 @@
+use flate2::{Compression, write::GzEncoder};
+use fluxus_api::{CollectionSink, DataStream};
+use fluxus_sources::{CsvSource, Source};
+use fluxus_utils::compression::CompressionCodec;
+use std::io::Write;
+
+async fn read_all(source: CsvSource) -> Vec<String> {
+    let sink = CollectionSink::new();
+    DataStream::new(source).sink(sink.clone()).await.unwrap();
+    sink.get_data()
+}
+
+#[tokio::test]
+async fn test_csv_source_defaults_emit_lines() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.csv");
+    std::fs::write(&path, "name,city\n\"Ada\",London\n# not a comment\n").unwrap();
+
+    assert_eq!(
+        read_all(CsvSource::new(&path)).await,
+        vec!["name,city", "\"Ada\",London", "# not a comment"]
+    );
+}
+
+#[tokio::test]
+async fn test_csv_source_parsing_options() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.tsv");
+    std::fs::write(
+        &path,
+        "# exported users\nid\tname\tcity\n1\tAda\tLondon\n# skipped\n2\t'Grace\tB.'\tNew York\n3\t'multi\nline'\tParis\n",
+    )
+    .unwrap();
+
+    let source = CsvSource::new(&path)
+        .with_delimiter(b'\t')
+        .with_quote(b'\'')
+        .with_comment(b'#')
+        .with_headers(true)
+        .select_columns(["city", "name"]);
+    assert_eq!(
+        read_all(source).await,
+        vec!["London,Ada", "New York,Grace\tB.", "Paris,\"multi\nline\"",]
+    );
+
+    let mut source = CsvSource::new(&path)
+        .with_delimiter(b'\t')
+        .with_comment(b'#')
+        .with_headers(true);
+    source.init().await.unwrap();
+    assert_eq!(source.headers().unwrap(), ["id", "name", "city"]);
+}
+
+#[tokio::test]
+async fn test_csv_source_column_indices_without_quoting() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.csv");
+    std::fs::write(&path, "a,\"b,c\n1,2\n").unwrap();
+
+    let source = CsvSource::new(&path)
+        .without_quoting()
+        .select_column_indices([1, 0, 5]);
+    assert_eq!(read_all(source).await, vec!["\"\"\"b\",a,", "2,1,"]);
+}
+
+#[tokio::test]
+async fn test_csv_source_rejects_unknown_columns() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.csv");
+    std::fs::write(&path, "id,name\n1,Ada\n").unwrap();
+
+    let mut source = CsvSource::new(&path)
+        .with_headers(true)
+        .select_columns(["email"]);
+    let error = source.init().await.unwrap_err();
+    assert!(error.to_string().contains("email"), "{}", error);
+
+    let mut source = CsvSource::new(&path).select_columns(["name"]);
+    assert!(source.init().await.is_err());
+}
+
+#[tokio::test]
+async fn test_csv_source_options_with_compression() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("data.csv.gz");
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(b"id;name\n1;Ada\n2;Grace\n").unwrap();
+    std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+    let source = CsvSource::new(&path)
+        .with_compression(CompressionCodec::from_extension(&path))
+        .with_delimiter(b';')
+        .with_headers(true);
+    assert_eq!(read_all(source).await, vec!["1,Ada", "2,Grace"]);
+}
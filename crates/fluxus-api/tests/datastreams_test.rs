@@ -1,4 +1,11 @@
+use fluxus_api::operators::EnumerateOperator;
 use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_sources::Source;
+use fluxus_transformers::{
+    BatchSource, CachePolicy, MergeOrder, MissPolicy, Operator, TimeoutEvent, TimeoutSource,
+};
+use fluxus_utils::models::{Record, StreamResult};
+use std::time::Duration;
 
 #[test]
 fn test_limit() {
@@ -18,6 +25,77 @@ fn test_limit() {
     })
 }
 
+struct InitCheckedSource {
+    inner: CollectionSource<i32>,
+    initialized: bool,
+}
+
+#[async_trait::async_trait]
+impl Source<i32> for InitCheckedSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        self.initialized = true;
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<i32>>> {
+        assert!(self.initialized, "source read before init");
+        self.inner.next().await
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+struct InitCheckedSink {
+    inner: CollectionSink<i32>,
+    initialized: bool,
+}
+
+#[async_trait::async_trait]
+impl fluxus_sinks::Sink<i32> for InitCheckedSink {
+    async fn init(&mut self) -> StreamResult<()> {
+        self.initialized = true;
+        Ok(())
+    }
+
+    async fn write(&mut self, record: Record<i32>) -> StreamResult<()> {
+        assert!(self.initialized, "sink written before init");
+        self.inner.write(record).await
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        self.inner.flush().await
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.inner.close().await
+    }
+}
+
+#[test]
+fn test_sink_initializes_source_and_sink() {
+    tokio_test::block_on(async {
+        let source = InitCheckedSource {
+            inner: CollectionSource::new(vec![1, 2, 3]),
+            initialized: false,
+        };
+        let collected = CollectionSink::new();
+        let sink = InitCheckedSink {
+            inner: collected.clone(),
+            initialized: false,
+        };
+
+        DataStream::new(source)
+            .map(|x| x * 10)
+            .sink(sink)
+            .await
+            .unwrap();
+
+        assert_eq!(collected.get_data(), vec![10, 20, 30]);
+    })
+}
+
 #[test]
 fn test_windowed_limit() {
     tokio_test::block_on(async {
@@ -27,6 +105,7 @@ fn test_windowed_limit() {
 
         DataStream::new(source)
             .window(fluxus_utils::window::WindowConfig::global())
+            .emit_partial()
             .limit(3)
             .sink(sink.clone())
             .await
@@ -54,6 +133,7 @@ fn test_tail() {
         let sink = CollectionSink::new();
         DataStream::new(source)
             .window(fluxus_utils::window::WindowConfig::global())
+            .emit_partial()
             .tail(3)
             .sink(sink.clone())
             .await
@@ -109,3 +189,962 @@ fn test_flat_map() {
         assert_eq!(data, vec![1, 2, 2, 3, 3, 3]);
     })
 }
+
+#[test]
+fn test_flat_map_async() {
+    tokio_test::block_on(async {
+        let archives = vec!["a.zip:x,y", "b.zip:", "c.zip:z"];
+        let sink = CollectionSink::new();
+
+        DataStream::new(CollectionSource::new(archives))
+            .flat_map_async(|archive| async move {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                let (name, entries) = archive.split_once(':').unwrap();
+                entries
+                    .split(',')
+                    .filter(|entry| !entry.is_empty())
+                    .map(|entry| format!("{}/{}", name, entry))
+                    .collect::<Vec<_>>()
+            })
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec!["a.zip/x", "a.zip/y", "c.zip/z"]);
+    })
+}
+
+#[test]
+fn test_sample() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::new(1..=10);
+        let sink = CollectionSink::new();
+
+        DataStream::new(source)
+            .sample_every(3)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+        assert_eq!(sink.get_data(), vec![1, 4, 7, 10]);
+
+        let source = CollectionSource::new(1..=100);
+        let all = CollectionSink::new();
+        DataStream::new(source)
+            .sample(1.0)
+            .sink(all.clone())
+            .await
+            .unwrap();
+        assert_eq!(all.get_data().len(), 100);
+
+        let source = CollectionSource::new(1..=100);
+        let none: CollectionSink<i32> = CollectionSink::new();
+        DataStream::new(source)
+            .sample(0.0)
+            .sink(none.clone())
+            .await
+            .unwrap();
+        assert!(none.get_data().is_empty());
+    })
+}
+
+#[test]
+fn test_batch_by_size() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::new(1..=5);
+        let sink = CollectionSink::new();
+
+        DataStream::new(source)
+            .batch(2, Duration::from_secs(10))
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![vec![1, 2], vec![3, 4], vec![5]]);
+    })
+}
+
+struct StallingSource {
+    inner: CollectionSource<i32>,
+    stall_before: i32,
+}
+
+#[async_trait::async_trait]
+impl Source<i32> for StallingSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<i32>>> {
+        let next = self.inner.next().await?;
+        if next.as_ref().is_some_and(|r| r.data == self.stall_before) {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        Ok(next)
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_batch_by_time() {
+    let source = StallingSource {
+        inner: CollectionSource::new(vec![1, 2, 3]),
+        stall_before: 3,
+    };
+    let sink = CollectionSink::new();
+
+    DataStream::new(source)
+        .batch(10, Duration::from_millis(50))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(sink.get_data(), vec![vec![1, 2], vec![3]]);
+}
+
+/// Source that emits one record and then never completes, holding `_alive`
+/// until it is dropped
+struct HangingSource {
+    _alive: std::sync::Arc<()>,
+    emitted: bool,
+}
+
+#[async_trait::async_trait]
+impl Source<i32> for HangingSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<i32>>> {
+        if !self.emitted {
+            self.emitted = true;
+            return Ok(Some(Record::new(1)));
+        }
+        std::future::pending().await
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_batch_reader_aborted_on_drop() {
+    let alive = std::sync::Arc::new(());
+    let source = HangingSource {
+        _alive: alive.clone(),
+        emitted: false,
+    };
+    let mut batches = BatchSource::new(source, 10, Duration::from_millis(50));
+    let batch = batches.next().await.unwrap().unwrap();
+    assert_eq!(batch.data, vec![1]);
+    assert_eq!(std::sync::Arc::strong_count(&alive), 2);
+
+    drop(batches);
+    tokio::task::yield_now().await;
+    assert_eq!(std::sync::Arc::strong_count(&alive), 1);
+}
+
+// Paused time advances straight to the next timer, so the stall of the
+// source and the timeouts fire in a fixed order
+#[tokio::test(start_paused = true)]
+async fn test_timeout() {
+    let source = StallingSource {
+        inner: CollectionSource::new(vec![1, 2, 3]),
+        stall_before: 3,
+    };
+    let sink = CollectionSink::new();
+
+    DataStream::new(source)
+        .timeout(Duration::from_millis(80))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+
+    let timeout = |ms| TimeoutEvent::Timeout {
+        idle: Duration::from_millis(ms),
+    };
+    assert_eq!(
+        sink.get_data(),
+        vec![
+            TimeoutEvent::Record(1),
+            TimeoutEvent::Record(2),
+            timeout(80),
+            timeout(160),
+            TimeoutEvent::Record(3),
+        ]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_timeout_to_side_output() {
+    let source = StallingSource {
+        inner: CollectionSource::new(vec![1, 2, 3]),
+        stall_before: 2,
+    };
+    let sink = CollectionSink::new();
+    let timeouts = CollectionSink::new();
+
+    DataStream::new(source)
+        .timeout_to(Duration::from_millis(150), timeouts.clone())
+        .sink(sink.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(sink.get_data(), vec![1, 2, 3]);
+    assert_eq!(timeouts.get_data(), vec![Duration::from_millis(150)]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_timeout_marker_after_advance() {
+    let source = StallingSource {
+        inner: CollectionSource::new(vec![1, 2]),
+        stall_before: 2,
+    };
+    let mut source = TimeoutSource::new(source, Duration::from_millis(80));
+    source.init().await.unwrap();
+    let first = source.next().await.unwrap().unwrap();
+    assert_eq!(first.data, TimeoutEvent::Record(1));
+
+    // The source stalls for 200ms, so the next read times out after 80ms
+    let start = tokio::time::Instant::now();
+    let next = source.next().await.unwrap().unwrap();
+    assert_eq!(
+        next.data,
+        TimeoutEvent::Timeout {
+            idle: Duration::from_millis(80)
+        }
+    );
+    assert_eq!(start.elapsed(), Duration::from_millis(80));
+
+    tokio::time::advance(Duration::from_millis(120)).await;
+    let next = source.next().await.unwrap().unwrap();
+    assert_eq!(next.data, TimeoutEvent::Record(2));
+    assert!(source.next().await.unwrap().is_none());
+}
+
+#[test]
+fn test_try_map_with_retry() {
+    tokio_test::block_on(async {
+        // Every record fails on its first attempt, "bad" always fails
+        let attempts = std::sync::Mutex::new(std::collections::HashMap::new());
+        let parse = move |s: &'static str| {
+            let mut attempts = attempts.lock().unwrap();
+            let n = attempts.entry(s).or_insert(0);
+            *n += 1;
+            if *n == 1 || s == "bad" {
+                Err(format!("failed to parse {s}"))
+            } else {
+                s.parse::<i32>().map_err(|e| e.to_string())
+            }
+        };
+
+        let source = CollectionSource::new(vec!["1", "bad", "3"]);
+        let sink = CollectionSink::new();
+        DataStream::new(source)
+            .retry(fluxus_core::RetryStrategy::fixed(
+                Duration::from_millis(1),
+                2,
+            ))
+            .try_map(parse)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            sink.get_data(),
+            vec![
+                Ok(1),
+                Err(("bad", "failed to parse bad".to_string())),
+                Ok(3)
+            ]
+        );
+    })
+}
+
+#[test]
+fn test_try_map_without_retry() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::new(vec!["1", "x"]);
+        let sink = CollectionSink::new();
+        DataStream::new(source)
+            .try_map(|s| s.parse::<i32>().map_err(|_| s.to_string()))
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![Ok(1), Err(("x", "x".to_string()))]);
+    })
+}
+
+#[test]
+fn test_ok_or_dead_letter() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::new(vec!["1", "x", "3"]);
+        let sink = CollectionSink::new();
+        let dlq = CollectionSink::new();
+        DataStream::new(source)
+            .try_map(|s| s.parse::<i32>())
+            .ok_or_dead_letter(dlq.clone())
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![1, 3]);
+        let dead = dlq.get_data();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].payload, "x");
+        assert_eq!(dead[0].error, "invalid digit found in string");
+    })
+}
+
+#[test]
+fn test_enumerate() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::new(vec!["a", "b", "c", "d"]);
+        let sink = CollectionSink::new();
+        DataStream::new(source)
+            .filter(|s| *s != "b")
+            .enumerate()
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![(0, "a"), (1, "c"), (2, "d")]);
+    })
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_enumerate_parallel_instances() {
+    // Clones of the operator running on separate tasks share one counter
+    let operator = EnumerateOperator::new();
+    let instances: Vec<_> = (0..4)
+        .map(|instance| {
+            let mut operator = operator.clone();
+            tokio::spawn(async move {
+                let mut indexes = Vec::new();
+                for i in 0..250 {
+                    let record = Record::new(instance * 250 + i);
+                    for result in operator.process(record).await.unwrap() {
+                        indexes.push(result.data.0);
+                    }
+                }
+                indexes
+            })
+        })
+        .collect();
+
+    let mut indexes = Vec::new();
+    for instance in instances {
+        indexes.extend(instance.await.unwrap());
+    }
+    indexes.sort_unstable();
+    assert_eq!(indexes, (0..1000).collect::<Vec<u64>>());
+}
+
+#[test]
+fn test_dedup() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::new(vec![1, 1, 2, 2, 2, 1, 3, 3]);
+        let sink = CollectionSink::new();
+        DataStream::new(source)
+            .dedup()
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![1, 2, 1, 3]);
+    })
+}
+
+#[test]
+fn test_dedup_by_key() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::new(vec![
+            ("a", 1),
+            ("b", 1),
+            ("a", 1),
+            ("b", 2),
+            ("a", 2),
+            ("b", 2),
+        ]);
+        let sink = CollectionSink::new();
+        DataStream::new(source)
+            .dedup_by_key(|(sensor, _)| *sensor)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            sink.get_data(),
+            vec![("a", 1), ("b", 1), ("b", 2), ("a", 2)]
+        );
+    })
+}
+
+#[test]
+fn test_scan() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::new(vec![1, 2, 3, 4]);
+        let sink = CollectionSink::new();
+        DataStream::new(source)
+            .scan(0, |acc, x| acc + x)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![1, 3, 6, 10]);
+    })
+}
+
+#[test]
+fn test_rolling_stats() {
+    tokio_test::block_on(async {
+        let values = vec![2.0, 4.0, 6.0, 8.0];
+        let means = CollectionSink::new();
+        DataStream::new(CollectionSource::new(values.clone()))
+            .rolling_mean(2, |x| *x)
+            .sink(means.clone())
+            .await
+            .unwrap();
+        assert_eq!(means.get_data(), vec![2.0, 3.0, 5.0, 7.0]);
+
+        let averages = CollectionSink::new();
+        DataStream::new(CollectionSource::new(values))
+            .ewma(0.5, |x| *x)
+            .sink(averages.clone())
+            .await
+            .unwrap();
+        assert_eq!(averages.get_data(), vec![2.0, 3.0, 4.5, 6.25]);
+
+        let anomalies = CollectionSink::new();
+        DataStream::new(CollectionSource::new(vec![
+            10, 11, 9, 10, 11, 9, 10, 50, 10,
+        ]))
+        .zscore_anomaly(3.0, |x| *x as f64)
+        .sink(anomalies.clone())
+        .await
+        .unwrap();
+        let anomalies = anomalies.get_data();
+        assert_eq!(anomalies.len(), 1);
+        assert_eq!(anomalies[0].data, 50);
+        assert!(anomalies[0].zscore > 3.0);
+    })
+}
+
+#[test]
+fn test_split() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::new(vec![1, 2, 3, 4, 5, 6]);
+        let evens = CollectionSink::new();
+        let odds = CollectionSink::new();
+        let (even_stream, odd_stream) = DataStream::new(source)
+            .map(|x| x * 10)
+            .split(|x| x % 20 == 0);
+
+        let (even_result, odd_result) = tokio::join!(
+            even_stream.map(|x| x / 10).sink(evens.clone()),
+            odd_stream.sink(odds.clone())
+        );
+        even_result.unwrap();
+        odd_result.unwrap();
+
+        assert_eq!(evens.get_data(), vec![2, 4, 6]);
+        assert_eq!(odds.get_data(), vec![10, 30, 50]);
+    })
+}
+
+#[test]
+fn test_sink_all() {
+    tokio_test::block_on(async {
+        let first = CollectionSink::new();
+        let second = CollectionSink::new();
+        DataStream::new(CollectionSource::new(vec![1, 2, 3]))
+            .map(|x| x * 2)
+            .sink_all(vec![Box::new(first.clone()), Box::new(second.clone())])
+            .await
+            .unwrap();
+
+        assert_eq!(first.get_data(), vec![2, 4, 6]);
+        assert_eq!(second.get_data(), vec![2, 4, 6]);
+    })
+}
+
+#[test]
+fn test_tee() {
+    tokio_test::block_on(async {
+        let raw = CollectionSink::new();
+        let sink = CollectionSink::new();
+        DataStream::new(CollectionSource::new(vec![1, 2, 3]))
+            .tee(raw.clone())
+            .map(|x| x + 100)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(raw.get_data(), vec![1, 2, 3]);
+        assert_eq!(sink.get_data(), vec![101, 102, 103]);
+    })
+}
+
+#[test]
+fn test_prefetch() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::new(vec!["1", "2", "x", "4"]);
+        let sink = CollectionSink::new();
+        DataStream::new(source)
+            .flat_map(|s| s.parse::<i32>().ok())
+            .prefetch(2)
+            .map(|x| x * 2)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![2, 4, 8]);
+    })
+}
+
+#[test]
+fn test_map_parallel() {
+    let lines: Vec<String> = (0..200).map(|i| format!("{{\"id\": {}}}", i)).collect();
+    let parse = |line: String| {
+        serde_json::from_str::<serde_json::Value>(&line).unwrap()["id"]
+            .as_i64()
+            .unwrap()
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(4)
+        .enable_all()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let ordered = CollectionSink::new();
+        DataStream::new(CollectionSource::new(lines.clone()))
+            .map_parallel(4, MergeOrder::Ordered, parse)
+            .sink(ordered.clone())
+            .await
+            .unwrap();
+        assert_eq!(ordered.get_data(), (0..200).collect::<Vec<i64>>());
+
+        let unordered = CollectionSink::new();
+        DataStream::new(CollectionSource::new(lines))
+            .map_parallel(4, MergeOrder::Unordered, parse)
+            .sink(unordered.clone())
+            .await
+            .unwrap();
+        let mut data = unordered.get_data();
+        data.sort();
+        assert_eq!(data, (0..200).collect::<Vec<i64>>());
+    });
+}
+
+#[test]
+fn test_filter_async() {
+    tokio_test::block_on(async {
+        let blocklist = std::sync::Arc::new(vec![2, 5]);
+        let sink = CollectionSink::new();
+        DataStream::new(CollectionSource::new(1..=6))
+            .filter_async(move |x| {
+                let blocklist = blocklist.clone();
+                let x = *x;
+                async move {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                    !blocklist.contains(&x)
+                }
+            })
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![1, 3, 4, 6]);
+    })
+}
+
+#[test]
+fn test_filter_async_concurrent() {
+    tokio_test::block_on(async {
+        let ordered = CollectionSink::new();
+        // Later records finish first, ordering must still be preserved
+        DataStream::new(CollectionSource::new(1..=8))
+            .filter_async_with(4, MergeOrder::Ordered, |x| {
+                let x = *x;
+                async move {
+                    tokio::time::sleep(Duration::from_millis(20 - 2 * x as u64)).await;
+                    x % 2 == 0
+                }
+            })
+            .sink(ordered.clone())
+            .await
+            .unwrap();
+        assert_eq!(ordered.get_data(), vec![2, 4, 6, 8]);
+
+        let unordered = CollectionSink::new();
+        DataStream::new(CollectionSource::new(1..=8))
+            .filter_async_with(4, MergeOrder::Unordered, |x| {
+                let x = *x;
+                async move {
+                    tokio::time::sleep(Duration::from_millis(20 - 2 * x as u64)).await;
+                    x % 2 == 0
+                }
+            })
+            .sink(unordered.clone())
+            .await
+            .unwrap();
+        let mut data = unordered.get_data();
+        data.sort();
+        assert_eq!(data, vec![2, 4, 6, 8]);
+    })
+}
+
+#[test]
+fn test_map_rich_lifecycle() {
+    use async_trait::async_trait;
+    use fluxus_api::operators::{FunctionContext, RichMapFunction};
+    use std::sync::{Arc, Mutex};
+
+    struct Enrich {
+        pool: Option<Vec<String>>,
+        events: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl RichMapFunction<usize, String> for Enrich {
+        async fn open(&mut self, ctx: &FunctionContext) -> StreamResult<()> {
+            self.pool = Some(vec!["a".to_string(), "b".to_string()]);
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("open {}", ctx.parallelism));
+            Ok(())
+        }
+
+        async fn map(&mut self, value: usize) -> StreamResult<String> {
+            let pool = self.pool.as_ref().expect("map called before open");
+            Ok(format!("{}{}", pool[value % pool.len()], value))
+        }
+
+        async fn close(&mut self) -> StreamResult<()> {
+            self.pool = None;
+            self.events.lock().unwrap().push("close".to_string());
+            Ok(())
+        }
+    }
+
+    tokio_test::block_on(async {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = CollectionSink::new();
+        DataStream::new(CollectionSource::new(vec![1, 2, 3]))
+            .parallel(2)
+            .map_rich(Enrich {
+                pool: None,
+                events: events.clone(),
+            })
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec!["b1", "a2", "b3"]);
+        assert_eq!(*events.lock().unwrap(), vec!["open 2", "close"]);
+    })
+}
+
+#[test]
+fn test_named_operators() {
+    use fluxus_core::MetricValue;
+
+    tokio_test::block_on(async {
+        let stream = DataStream::new(CollectionSource::new(vec!["1", "x", "3", "4"]))
+            .name("numbers")
+            .map(|s| s.parse::<i32>().ok())
+            .name("parse")
+            .uid("parse-v1")
+            .filter(|n| n.is_some())
+            .name("drop-invalid")
+            .map(|n| n.unwrap() * 10);
+        let plan = stream.plan();
+
+        assert_eq!(
+            plan.to_string(),
+            "0: numbers\n1: parse (uid: parse-v1)\n2: drop-invalid\n3: MapOperator\n"
+        );
+
+        let sink = CollectionSink::new();
+        stream.sink(sink.clone()).await.unwrap();
+        assert_eq!(sink.get_data(), vec![10, 30, 40]);
+
+        let metrics = plan.metrics();
+        let counter = |key: &str| match metrics.get(key) {
+            Some(MetricValue::Counter(value)) => *value,
+            other => panic!("unexpected metric {}: {:?}", key, other),
+        };
+        assert_eq!(counter("operator.numbers.records_out"), 4);
+        assert_eq!(counter("operator.parse.records_in"), 4);
+        assert_eq!(counter("operator.drop-invalid.records_in"), 4);
+        assert_eq!(counter("operator.drop-invalid.records_out"), 3);
+        assert_eq!(counter("operator.MapOperator.records_out"), 3);
+        assert_eq!(counter("operator.parse.errors"), 0);
+    })
+}
+
+#[test]
+fn test_metrics_json() {
+    tokio_test::block_on(async {
+        let stream = DataStream::new(CollectionSource::new(vec![1, 2, 3])).name("numbers");
+        let plan = stream.plan();
+        stream.sink(CollectionSink::new()).await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metrics.json");
+        fluxus_core::write_metrics_json(&plan.metrics(), &path).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            json["operator.numbers.records_out"],
+            serde_json::json!({"type": "counter", "value": 3})
+        );
+    })
+}
+
+#[test]
+fn test_merge_sorted() {
+    tokio_test::block_on(async {
+        let hour_0 = DataStream::new(CollectionSource::with_timestamps(vec![
+            (1, "a"),
+            (4, "b"),
+            (9, "c"),
+        ]));
+        let hour_1 = DataStream::new(CollectionSource::with_timestamps(vec![(2, "d"), (4, "e")]));
+        let hour_2 = DataStream::new(CollectionSource::with_timestamps(vec![(3, "f"), (10, "g")]));
+
+        let dry_run = hour_0
+            .merge_sorted(vec![hour_1, hour_2])
+            .dry_run(CollectionSink::new(), 100)
+            .await
+            .unwrap();
+
+        let output: Vec<(i64, &str)> = dry_run
+            .output
+            .into_iter()
+            .map(|r| (r.timestamp, r.data))
+            .collect();
+        assert_eq!(
+            output,
+            vec![
+                (1, "a"),
+                (2, "d"),
+                (3, "f"),
+                (4, "b"),
+                (4, "e"),
+                (9, "c"),
+                (10, "g")
+            ]
+        );
+    })
+}
+
+#[test]
+fn test_enrich() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    tokio_test::block_on(async {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let counter = lookups.clone();
+        let lookup = move |user: u32| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                Ok((user != 3).then(|| format!("user-{}", user)))
+            }
+        };
+
+        let sink = CollectionSink::new();
+        DataStream::new(CollectionSource::new(vec![1, 2, 1, 3, 2, 1]))
+            .enrich(
+                |user| *user,
+                lookup.clone(),
+                CachePolicy::new(16, Duration::from_secs(60)).with_concurrency(4),
+            )
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        let users = |users: &[(u32, Option<&str>)]| -> Vec<(u32, Option<String>)> {
+            users
+                .iter()
+                .map(|(user, name)| (*user, name.map(str::to_string)))
+                .collect()
+        };
+        assert_eq!(
+            sink.get_data(),
+            users(&[
+                (1, Some("user-1")),
+                (2, Some("user-2")),
+                (1, Some("user-1")),
+                (3, None),
+                (2, Some("user-2")),
+                (1, Some("user-1")),
+            ])
+        );
+        assert!(lookups.load(Ordering::SeqCst) < 6);
+
+        let sink = CollectionSink::new();
+        DataStream::new(CollectionSource::new(vec![1, 3]))
+            .enrich(
+                |user| *user,
+                lookup.clone(),
+                CachePolicy::no_cache().on_miss(MissPolicy::Drop),
+            )
+            .sink(sink.clone())
+            .await
+            .unwrap();
+        assert_eq!(sink.get_data(), users(&[(1, Some("user-1"))]));
+
+        let sink = CollectionSink::new();
+        DataStream::new(CollectionSource::new(vec![3]))
+            .enrich(
+                |user| *user,
+                lookup,
+                CachePolicy::no_cache().on_miss(MissPolicy::Default("guest".to_string())),
+            )
+            .sink(sink.clone())
+            .await
+            .unwrap();
+        assert_eq!(sink.get_data(), users(&[(3, Some("guest"))]));
+    })
+}
+
+#[test]
+fn test_materialize() {
+    tokio_test::block_on(async {
+        let table = fluxus_api::MaterializedTable::new();
+        let sink = CollectionSink::new();
+        DataStream::new(CollectionSource::new(vec![("a", 1), ("b", 2), ("a", 3)]))
+            .materialize(|(key, _)| *key, table.clone())
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data().len(), 3);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get(&"a"), Some(("a", 3)));
+        assert!(!table.contains_key(&"c"));
+
+        let mut rows = table.scan();
+        rows.sort();
+        assert_eq!(rows, vec![("a", ("a", 3)), ("b", ("b", 2))]);
+    })
+}
+
+/// A payload that can only be moved
+struct Frame(Box<[u8]>);
+
+#[test]
+fn test_move_only_payload() {
+    tokio_test::block_on(async {
+        let frames = (1..=4).map(|n| Frame(vec![0; n].into_boxed_slice()));
+        let sink = CollectionSink::new();
+        DataStream::new(CollectionSource::new(frames))
+            .filter(|frame| frame.0.len() % 2 == 0)
+            .map(|frame| Frame(frame.0.into_iter().chain([1]).collect()))
+            .flat_map(|frame| [frame])
+            .map(|frame| frame.0.len())
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![3, 5]);
+    })
+}
+
+#[test]
+fn test_notify_once() {
+    tokio_test::block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let ledger = dir.path().join("sent.txt");
+        let alerts = |ids: Vec<&'static str>| {
+            CollectionSource::new(ids.into_iter().map(|id| (id, "disk full")))
+        };
+        let notify = |sink: &CollectionSink<(&'static str, &'static str)>| {
+            fluxus_sinks::NotifyOnce::new(sink.clone(), ledger.clone(), |(id, _): &(&str, &str)| {
+                id.to_string()
+            })
+        };
+
+        let sent = CollectionSink::new();
+        DataStream::new(alerts(vec!["a1", "a2", "a1"]))
+            .sink(notify(&sent))
+            .await
+            .unwrap();
+        assert_eq!(
+            sent.get_data(),
+            vec![("a1", "disk full"), ("a2", "disk full")]
+        );
+
+        // Replaying the input after a restart only sends the new alert
+        let sent = CollectionSink::new();
+        DataStream::new(alerts(vec!["a1", "a2", "a3"]))
+            .sink(notify(&sent))
+            .await
+            .unwrap();
+        assert_eq!(sent.get_data(), vec![("a3", "disk full")]);
+    })
+}
+
+/// Batch sink that keeps the batches written to it
+#[derive(Clone, Default)]
+struct BatchCollector {
+    batches: std::sync::Arc<std::sync::Mutex<Vec<Vec<i32>>>>,
+}
+
+#[async_trait::async_trait]
+impl fluxus_sinks::BatchSink<i32> for BatchCollector {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, records: Vec<Record<i32>>) -> StreamResult<()> {
+        let batch = records.into_iter().map(|record| record.data).collect();
+        self.batches.lock().unwrap().push(batch);
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_sink_batch() {
+    tokio_test::block_on(async {
+        let collector = BatchCollector::default();
+        DataStream::new(CollectionSource::new(1..=5))
+            .sink_batch(collector.clone(), 2, Duration::from_secs(60))
+            .await
+            .unwrap();
+        assert_eq!(
+            *collector.batches.lock().unwrap(),
+            vec![vec![1, 2], vec![3, 4], vec![5]]
+        );
+
+        // Every flush acknowledges the latest timestamp of its batch
+        let acks = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let source = CollectionSource::with_timestamps(vec![(10, 1), (30, 2), (20, 3)]);
+        let sink =
+            fluxus_sinks::BatchingSink::new(BatchCollector::default(), 2, Duration::from_secs(60))
+                .on_flush({
+                    let acks = acks.clone();
+                    move |ack| acks.lock().unwrap().push((ack.records, ack.max_timestamp))
+                });
+        DataStream::new(source).sink(sink).await.unwrap();
+        assert_eq!(*acks.lock().unwrap(), vec![(2, 30), (1, 20)]);
+    })
+}
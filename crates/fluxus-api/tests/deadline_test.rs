@@ -0,0 +1,89 @@
+use async_trait::async_trait;
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_core::{DeadlineExt, RetryStrategy};
+use fluxus_sinks::Sink;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::time::Duration;
+
+/// Operator that never completes for the given record
+struct HangOn(i32);
+
+#[async_trait]
+impl Operator<i32, i32> for HangOn {
+    async fn process(&mut self, record: Record<i32>) -> StreamResult<Vec<Record<i32>>> {
+        if record.data == self.0 {
+            std::future::pending::<()>().await;
+        }
+        Ok(vec![record])
+    }
+}
+
+/// Sink whose first write hangs, later writes go to the inner sink
+struct FlakySink {
+    inner: CollectionSink<i32>,
+    hung: bool,
+}
+
+#[async_trait]
+impl Sink<i32> for FlakySink {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn write(&mut self, record: Record<i32>) -> StreamResult<()> {
+        if !self.hung {
+            self.hung = true;
+            std::future::pending::<()>().await;
+        }
+        self.inner.write(record).await
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_operator_timeout() {
+    tokio_test::block_on(async {
+        let sink = CollectionSink::new();
+        let operator = HangOn(2).with_timeout(Duration::from_millis(20));
+        let timeouts = operator.timeouts();
+
+        let result = DataStream::new(CollectionSource::new(vec![1, 2, 3]))
+            .transform(operator)
+            .sink(sink.clone())
+            .await;
+
+        assert!(matches!(result, Err(StreamError::Timeout(_))));
+        assert_eq!(sink.get_data(), vec![1]);
+        assert_eq!(timeouts.value(), 1);
+    })
+}
+
+#[test]
+fn test_sink_timeout_with_retry() {
+    tokio_test::block_on(async {
+        let collected = CollectionSink::new();
+        let sink = FlakySink {
+            inner: collected.clone(),
+            hung: false,
+        }
+        .with_timeout(Duration::from_millis(20))
+        .with_retry(RetryStrategy::fixed(Duration::from_millis(1), 2));
+        let timeouts = sink.timeouts();
+
+        DataStream::new(CollectionSource::new(vec![1, 2, 3]))
+            .sink(sink)
+            .await
+            .unwrap();
+
+        assert_eq!(collected.get_data(), vec![1, 2, 3]);
+        assert_eq!(timeouts.value(), 1);
+    })
+}
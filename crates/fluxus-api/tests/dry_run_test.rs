@@ -0,0 +1,93 @@
+use async_trait::async_trait;
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_core::Pipeline;
+use fluxus_sinks::Sink;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+
+struct UnreachableSink;
+
+#[async_trait]
+impl Sink<i32> for UnreachableSink {
+    async fn init(&mut self) -> StreamResult<()> {
+        Err(StreamError::sink_error("connection refused").with_operator("warehouse"))
+    }
+
+    async fn write(&mut self, _record: Record<i32>) -> StreamResult<()> {
+        unreachable!("dry runs never write")
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+struct Double;
+
+#[async_trait]
+impl Operator<i32, i32> for Double {
+    async fn process(&mut self, record: Record<i32>) -> StreamResult<Vec<Record<i32>>> {
+        Ok(vec![Record::with_timestamp(
+            record.data * 2,
+            record.timestamp,
+        )])
+    }
+}
+
+#[test]
+fn test_dry_run_samples_without_writing() {
+    tokio_test::block_on(async {
+        let sink = CollectionSink::new();
+        let dry_run = DataStream::new(CollectionSource::new(1..=100))
+            .filter(|x| x % 2 == 0)
+            .map(|x| x * 10)
+            .dry_run(sink.clone(), 3)
+            .await
+            .unwrap();
+
+        let output: Vec<i32> = dry_run.output.into_iter().map(|r| r.data).collect();
+        assert_eq!(output, vec![20, 40, 60]);
+        assert_eq!(dry_run.records_read, 6);
+        assert!(sink.get_data().is_empty());
+    })
+}
+
+#[test]
+fn test_dry_run_fails_fast() {
+    tokio_test::block_on(async {
+        let result = DataStream::new(CollectionSource::new(vec![1, 2, 3]))
+            .dry_run(UnreachableSink, 10)
+            .await;
+        assert!(
+            matches!(result, Err(StreamError::Sink { message, .. }) if message == "connection refused")
+        );
+    })
+}
+
+#[test]
+fn test_pipeline_validate() {
+    tokio_test::block_on(async {
+        let sink = CollectionSink::new();
+        let dry_run = Pipeline::source(CollectionSource::new(vec![1, 2, 3, 4]))
+            .add_operator(Double)
+            .sink(sink.clone())
+            .validate(2)
+            .await
+            .unwrap();
+
+        let output: Vec<i32> = dry_run.output.into_iter().map(|r| r.data).collect();
+        assert_eq!(output, vec![2, 4]);
+        assert_eq!(dry_run.records_read, 2);
+        assert!(sink.get_data().is_empty());
+
+        let result = Pipeline::source(CollectionSource::new(vec![1]))
+            .sink(UnreachableSink)
+            .validate(1)
+            .await;
+        assert!(result.is_err());
+    })
+}
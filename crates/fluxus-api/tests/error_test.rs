@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{ErrorContext, Record, StreamError, StreamResult};
+use std::time::Duration;
+
+#[test]
+fn test_error_context_display() {
+    let e = StreamError::operator_error("invalid JSON")
+        .with_operator("parse-json")
+        .with_timestamp(1000);
+    assert_eq!(
+        e.to_string(),
+        "Operator error in parse-json at 1000: invalid JSON"
+    );
+    assert_eq!(
+        e.context(),
+        Some(&ErrorContext {
+            operator: Some("parse-json".to_string()),
+            timestamp: Some(1000),
+            retryable: false,
+        })
+    );
+
+    let e = StreamError::checkpoint("snapshot upload failed");
+    assert_eq!(e.to_string(), "Checkpoint error: snapshot upload failed");
+
+    // Variants without context ignore the builders
+    let e = StreamError::Config("missing url".to_string()).with_operator("source");
+    assert!(e.context().is_none());
+    assert_eq!(e.to_string(), "Configuration error: missing url");
+}
+
+#[test]
+fn test_error_retryability() {
+    assert!(StreamError::backpressure("sink queue full").is_retryable());
+    assert!(
+        !StreamError::backpressure("sink queue full")
+            .with_retryable(false)
+            .is_retryable()
+    );
+    assert!(!StreamError::sink_error("schema mismatch").is_retryable());
+    assert!(
+        StreamError::sink_error("throttled")
+            .with_retryable(true)
+            .is_retryable()
+    );
+    assert!(StreamError::Timeout(Duration::from_secs(1)).is_retryable());
+    assert!(StreamError::Io(std::io::ErrorKind::TimedOut.into()).is_retryable());
+    assert!(!StreamError::Io(std::io::ErrorKind::NotFound.into()).is_retryable());
+    assert!(!StreamError::Serialization("bad".to_string()).is_retryable());
+}
+
+struct RejectOdd;
+
+#[async_trait]
+impl Operator<i32, i32> for RejectOdd {
+    async fn process(&mut self, record: Record<i32>) -> StreamResult<Vec<Record<i32>>> {
+        if record.data % 2 == 1 {
+            return Err(StreamError::operator_error(format!(
+                "odd value {}",
+                record.data
+            )));
+        }
+        Ok(vec![record])
+    }
+}
+
+#[test]
+fn test_operator_error_carries_stage_and_record() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::with_timestamps(vec![(100, 2), (200, 3)]);
+        let e = DataStream::new(source)
+            .transform(RejectOdd)
+            .name("reject-odd")
+            .sink(CollectionSink::new())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(&e, StreamError::Operator { message, .. } if message == "odd value 3"));
+        let context = e.context().unwrap();
+        assert_eq!(context.operator.as_deref(), Some("reject-odd"));
+        assert_eq!(context.timestamp, Some(200));
+    })
+}
@@ -0,0 +1,260 @@
+use async_trait::async_trait;
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_runtime::watermark::{
+    Punctuated, WatermarkGenerator, WatermarkPolicy, WatermarkStrategy,
+};
+use fluxus_sources::Source;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use fluxus_utils::window::WindowConfig;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Emits each timestamp after waiting for its delay in milliseconds
+struct Delayed(VecDeque<(u64, i64)>);
+
+#[async_trait]
+impl Source<i64> for Delayed {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<i64>>> {
+        let Some((delay, timestamp)) = self.0.pop_front() else {
+            return Ok(None);
+        };
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        Ok(Some(Record::with_timestamp(timestamp, timestamp)))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+/// Describes the records and watermarks it sees
+struct Trace;
+
+#[async_trait]
+impl<T: Send + Sync + 'static> Operator<T, String> for Trace {
+    async fn process(&mut self, record: Record<T>) -> StreamResult<Vec<Record<String>>> {
+        Ok(vec![Record::with_timestamp(
+            format!("record {}", record.timestamp),
+            record.timestamp,
+        )])
+    }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<String>>> {
+        Ok(vec![Record::with_timestamp(
+            format!("watermark {}", watermark),
+            watermark,
+        )])
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Click {
+    user: &'static str,
+    at_ms: i64,
+}
+
+#[test]
+fn test_watermark_generator() {
+    let mut generator = WatermarkGenerator::new(WatermarkStrategy::bounded_out_of_orderness(
+        Duration::from_secs(2),
+    ));
+    assert_eq!(generator.current(), None);
+    assert!(!generator.is_late(0));
+
+    assert_eq!(generator.on_event(5000), 3000);
+    assert_eq!(generator.on_event(4000), 3000);
+    assert!(generator.is_late(2999));
+    assert!(!generator.is_late(3000));
+
+    let mut generator = WatermarkGenerator::new(WatermarkStrategy::monotonous());
+    assert_eq!(generator.on_event(7), 7);
+    assert!(generator.is_late(6));
+}
+
+#[test]
+fn test_assign_timestamps() {
+    tokio_test::block_on(async {
+        let clicks = vec![
+            Click {
+                user: "a",
+                at_ms: 42,
+            },
+            Click {
+                user: "b",
+                at_ms: 7,
+            },
+        ];
+        let dry_run = DataStream::new(CollectionSource::new(clicks))
+            .assign_timestamps(|click| click.at_ms)
+            .dry_run(CollectionSink::new(), 10)
+            .await
+            .unwrap();
+
+        let stamped: Vec<(i64, &str)> = dry_run
+            .output
+            .into_iter()
+            .map(|record| (record.timestamp, record.data.user))
+            .collect();
+        assert_eq!(stamped, vec![(42, "a"), (7, "b")]);
+    })
+}
+
+#[test]
+fn test_event_time_windows() {
+    tokio_test::block_on(async {
+        let clicks = vec![
+            Click {
+                user: "a",
+                at_ms: 1_000,
+            },
+            Click {
+                user: "b",
+                at_ms: 3_000,
+            },
+            Click {
+                user: "a",
+                at_ms: 2_000,
+            },
+            Click {
+                user: "c",
+                at_ms: 12_000,
+            },
+            // More than 5s behind the latest click, dropped as late
+            Click {
+                user: "b",
+                at_ms: 4_000,
+            },
+            Click {
+                user: "a",
+                at_ms: 13_000,
+            },
+        ];
+        let sink = CollectionSink::new();
+
+        DataStream::new(CollectionSource::new(clicks))
+            .assign_timestamps(|click| click.at_ms)
+            .with_watermark(WatermarkStrategy::bounded_out_of_orderness(
+                Duration::from_secs(5),
+            ))
+            .window(WindowConfig::tumbling(Duration::from_secs(10)))
+            .aggregate(Vec::new(), |mut users, click| {
+                users.push(click.user);
+                users
+            })
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![vec!["a", "b", "a"], vec!["c", "a"]]);
+    })
+}
+
+#[test]
+fn test_punctuated_watermark() {
+    let mut policy = Punctuated::new(|marker: &&str, ts| (*marker == "end").then_some(ts));
+    assert_eq!(policy.on_event(&"data", 5), None);
+    assert_eq!(policy.on_event(&"end", 10), Some(10));
+    assert_eq!(policy.on_event(&"end", 8), Some(10));
+    assert_eq!(WatermarkPolicy::<&str>::current(&policy), Some(10));
+}
+
+#[test]
+fn test_watermarks_reach_operators() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::with_timestamps(vec![
+            (1, "data"),
+            (2, "end"),
+            (3, "data"),
+            (1, "late"),
+            (5, "end"),
+        ]);
+        let sink = CollectionSink::new();
+        DataStream::new(source)
+            .with_watermark_policy(Punctuated::new(|marker: &&str, ts| {
+                (*marker == "end").then_some(ts)
+            }))
+            .map(|marker| marker)
+            .transform(Trace)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            sink.get_data(),
+            vec![
+                "record 1",
+                "record 2",
+                "watermark 2",
+                "record 3",
+                "record 5",
+                "watermark 5"
+            ]
+        );
+    })
+}
+
+#[test]
+fn test_watermark_fires_windows() {
+    tokio_test::block_on(async {
+        let source = CollectionSource::with_timestamps(vec![(1, 1), (2, 1), (12, 1), (25, 1)]);
+        let sink = CollectionSink::new();
+        // The window config alone would hold every window until the end.
+        // Watermarks move on with the records emitted before them.
+        DataStream::new(source)
+            .with_watermark(WatermarkStrategy::Monotonous)
+            .window(
+                WindowConfig::tumbling(Duration::from_millis(10))
+                    .with_watermark_delay(Duration::from_secs(3600)),
+            )
+            .aggregate(0, |count, x: i32| count + x)
+            .transform(Trace)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            sink.get_data(),
+            vec![
+                "record 9",
+                "watermark 12",
+                "record 19",
+                "watermark 25",
+                "record 29"
+            ]
+        );
+    })
+}
+
+#[tokio::test]
+async fn test_union_skips_idle_inputs() {
+    let run = |idleness: Option<Duration>| async move {
+        // The first input stalls after two records
+        let mut stalled = DataStream::new(Delayed(VecDeque::from([(0, 0), (0, 1), (1_000, 2)])))
+            .with_watermark(WatermarkStrategy::Monotonous);
+        if let Some(idleness) = idleness {
+            stalled = stalled.with_idleness(idleness);
+        }
+        let active = DataStream::new(Delayed(VecDeque::from([(100, 5), (100, 15), (100, 25)])))
+            .with_watermark(WatermarkStrategy::Monotonous);
+        let sink = CollectionSink::new();
+        stalled
+            .union(vec![active])
+            .transform(Trace)
+            .filter(|line| line.starts_with("watermark"))
+            .sink(sink.clone())
+            .await
+            .unwrap();
+        sink.get_data()
+    };
+
+    assert_eq!(
+        run(Some(Duration::from_millis(30))).await,
+        vec!["watermark 5", "watermark 15", "watermark 25"]
+    );
+    assert_eq!(run(None).await, vec!["watermark 1", "watermark 2"]);
+}
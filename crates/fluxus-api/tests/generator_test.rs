@@ -0,0 +1,92 @@
+use fluxus_api::{CollectionSink, DataStream};
+use fluxus_sources::generator::{Normal, Sampler, Uniform, Zipf};
+use fluxus_sources::{GeneratorSource, Source};
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[tokio::test]
+async fn test_generator_max_records() {
+    let sink = CollectionSink::new();
+    let source = GeneratorSource::sampling(Sampler::seeded(Uniform::new(10.0, 20.0), 7))
+        .with_max_records(100);
+    DataStream::new(source).sink(sink.clone()).await.unwrap();
+
+    let values = sink.get_data();
+    assert_eq!(values.len(), 100);
+    assert!(values.iter().all(|v| (10.0..20.0).contains(v)));
+}
+
+#[test]
+fn test_sampler_distributions() {
+    let mut normal = Sampler::seeded(Normal::new(100.0, 15.0), 1);
+    let values: Vec<f64> = (0..20_000).map(|_| normal.sample()).collect();
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    assert!((mean - 100.0).abs() < 0.5, "{}", mean);
+    assert!((variance.sqrt() - 15.0).abs() < 0.5, "{}", variance.sqrt());
+
+    let mut zipf = Sampler::seeded(Zipf::new(100, 1.2), 2);
+    let mut counts = [0usize; 101];
+    for _ in 0..20_000 {
+        let rank = zipf.sample();
+        assert!((1.0..=100.0).contains(&rank));
+        counts[rank as usize] += 1;
+    }
+    assert!(counts[1] > counts[2] && counts[2] > counts[10] && counts[10] > counts[100]);
+    // Rank 1 has 1 / H(100, 1.2) ≈ 27% of the samples
+    assert!((4_500..6_500).contains(&counts[1]), "{}", counts[1]);
+
+    // The same seed draws the same values
+    let mut a = Sampler::seeded(Uniform::new(0.0, 1.0), 3);
+    let mut b = Sampler::seeded(Uniform::new(0.0, 1.0), 3);
+    assert_eq!(a.sample(), b.sample());
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_generator_rate() {
+    let mut n = 0;
+    let mut source = GeneratorSource::new(move || {
+        n += 1;
+        Some(n)
+    })
+    .with_rate(100.0)
+    .with_max_records(50);
+    source.init().await.unwrap();
+
+    let start = Instant::now();
+    let mut count = 0;
+    while source.next().await.unwrap().is_some() {
+        count += 1;
+    }
+    assert_eq!(count, 50);
+    let elapsed = start.elapsed();
+    assert!(
+        elapsed >= Duration::from_millis(480) && elapsed <= Duration::from_millis(510),
+        "{:?}",
+        elapsed
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_generator_bursts() {
+    let mut source = GeneratorSource::new(|| Some(()))
+        .with_rate(10.0)
+        .with_bursts(Duration::from_secs(1), Duration::from_millis(500), 10.0);
+    source.init().await.unwrap();
+
+    // 100 per second for the first half of every second, 10 per second for
+    // the second half
+    let start = Instant::now();
+    let mut per_second = [0usize; 3];
+    loop {
+        source.next().await.unwrap();
+        let second = start.elapsed().as_secs() as usize;
+        if second >= per_second.len() {
+            break;
+        }
+        per_second[second] += 1;
+    }
+    for count in per_second {
+        assert!((52..=58).contains(&count), "{:?}", per_second);
+    }
+}
@@ -0,0 +1,45 @@
+use fluxus_api::DataStream;
+use fluxus_api::testing::{assert_snapshot, fixture_source};
+use fluxus_utils::window::WindowConfig;
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn test_file(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(name)
+}
+
+#[test]
+fn test_tumbling_count_snapshot() {
+    tokio_test::block_on(async {
+        let source = fixture_source(test_file("fixtures/page_views.txt")).unwrap();
+        let stream = DataStream::new(source)
+            .window(WindowConfig::tumbling(Duration::from_secs(1)))
+            .aggregate(0, |count, _| count + 1);
+
+        assert_snapshot(stream, test_file("snapshots/tumbling_count.snap"))
+            .await
+            .unwrap();
+    })
+}
+
+#[test]
+fn test_sliding_pages_snapshot() {
+    tokio_test::block_on(async {
+        let source = fixture_source(test_file("fixtures/page_views.txt")).unwrap();
+        let stream = DataStream::new(source)
+            .window(WindowConfig::sliding(
+                Duration::from_secs(2),
+                Duration::from_secs(1),
+            ))
+            .aggregate(Vec::new(), |mut pages, page| {
+                pages.push(page);
+                pages
+            });
+
+        assert_snapshot(stream, test_file("snapshots/sliding_pages.snap"))
+            .await
+            .unwrap();
+    })
+}
@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_sources::Source;
+use fluxus_transformers::HybridSource;
+use fluxus_utils::models::{Record, StreamResult};
+use std::sync::{Arc, Mutex};
+
+fn archive() -> CollectionSource<&'static str> {
+    CollectionSource::with_timestamps(vec![(1, "a"), (3, "c"), (2, "b")])
+}
+
+/// A live source that reports a watermark behind its records
+struct Live {
+    inner: CollectionSource<&'static str>,
+    watermark: Option<i64>,
+}
+
+#[async_trait]
+impl Source<&'static str> for Live {
+    async fn init(&mut self) -> StreamResult<()> {
+        self.inner.init().await
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<&'static str>>> {
+        let record = self.inner.next().await?;
+        if let Some(record) = &record {
+            self.watermark = Some(record.timestamp - 10);
+        }
+        Ok(record)
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.inner.close().await
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        self.watermark
+    }
+}
+
+#[tokio::test]
+async fn test_hybrid_source_switches_to_live() {
+    let live = CollectionSource::with_timestamps(vec![(4, "d"), (5, "e")]);
+    let sink = CollectionSink::new();
+    DataStream::new(HybridSource::new(archive(), live))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+    assert_eq!(sink.get_data(), vec!["a", "c", "b", "d", "e"]);
+}
+
+#[tokio::test]
+async fn test_hybrid_source_hands_over_position() {
+    let handover = Arc::new(Mutex::new(None));
+    let seen = handover.clone();
+    let source = HybridSource::with_handover(archive(), move |position| {
+        *seen.lock().unwrap() = Some(position);
+        // The live source replays from before the handover position
+        CollectionSource::with_timestamps(vec![(2, "b"), (3, "c"), (4, "d"), (5, "e")])
+    })
+    .skip_overlap();
+
+    let sink = CollectionSink::new();
+    DataStream::new(source).sink(sink.clone()).await.unwrap();
+    assert_eq!(sink.get_data(), vec!["a", "c", "b", "d", "e"]);
+    assert_eq!(*handover.lock().unwrap(), Some(Some(3)));
+}
+
+#[tokio::test]
+async fn test_hybrid_source_watermark_continuity() {
+    let live = Live {
+        inner: CollectionSource::with_timestamps(vec![(4, "d"), (20, "e")]),
+        watermark: None,
+    };
+    let mut source = HybridSource::new(archive(), live);
+    source.init().await.unwrap();
+
+    let mut read = Vec::new();
+    while let Some(record) = source.next().await.unwrap() {
+        read.push((record.data, source.is_live(), source.watermark()));
+    }
+    assert_eq!(
+        read,
+        vec![
+            ("a", false, None),
+            ("c", false, None),
+            ("b", false, None),
+            // The live watermark, 4 - 10, is behind the handover position
+            ("d", true, Some(3)),
+            ("e", true, Some(10)),
+        ]
+    );
+    assert_eq!(source.handover(), Some(3));
+    source.close().await.unwrap();
+}
@@ -0,0 +1,75 @@
+use async_trait::async_trait;
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_core::{ConnectionState, InitRetry, RetryStrategy};
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::time::Duration;
+
+/// Source whose first `failures` calls to `init` fail
+struct Unavailable {
+    inner: CollectionSource<i32>,
+    failures: usize,
+}
+
+#[async_trait]
+impl Source<i32> for Unavailable {
+    async fn init(&mut self) -> StreamResult<()> {
+        if self.failures > 0 {
+            self.failures -= 1;
+            return Err(StreamError::Runtime("503 Service Unavailable".to_string()));
+        }
+        self.inner.init().await
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<i32>>> {
+        self.inner.next().await
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.inner.close().await
+    }
+}
+
+fn unavailable(failures: usize) -> Unavailable {
+    Unavailable {
+        inner: CollectionSource::new(vec![1, 2, 3]),
+        failures,
+    }
+}
+
+#[test]
+fn test_init_retry() {
+    tokio_test::block_on(async {
+        let strategy = RetryStrategy::fixed(Duration::from_millis(5), 3);
+        let source = InitRetry::new(unavailable(2), strategy.clone());
+        let status = source.status();
+        assert_eq!(status.get(), ConnectionState::Connecting);
+
+        let sink = CollectionSink::new();
+        DataStream::new(source).sink(sink.clone()).await.unwrap();
+        assert_eq!(sink.get_data(), vec![1, 2, 3]);
+        assert_eq!(status.get(), ConnectionState::Connected);
+
+        // Giving up after the attempts of the strategy
+        let source = InitRetry::new(unavailable(5), strategy);
+        let status = source.status();
+        let result = DataStream::new(source).sink(CollectionSink::new()).await;
+        assert!(result.is_err());
+        assert_eq!(status.get(), ConnectionState::Failed);
+    })
+}
+
+#[test]
+fn test_lazy_init() {
+    tokio_test::block_on(async {
+        let strategy = RetryStrategy::fixed(Duration::from_millis(5), 3);
+        let mut source = InitRetry::new(unavailable(2), strategy).lazy();
+        let status = source.status();
+
+        // The source connects on the first read
+        source.init().await.unwrap();
+        assert_eq!(status.get(), ConnectionState::Connecting);
+        assert_eq!(source.next().await.unwrap().map(|r| r.data), Some(1));
+        assert_eq!(status.get(), ConnectionState::Connected);
+    })
+}
@@ -0,0 +1,293 @@
+use async_trait::async_trait;
+use fluxus_api::operators::WindowAggregator;
+use fluxus_api::{CollectionSink, CollectionSource};
+use fluxus_core::{Metrics, ParallelConfig};
+use fluxus_runtime::{JobRegistry, RuntimeContext, SharedOperator, TaskExit};
+use fluxus_sinks::Sink;
+use fluxus_sources::Source;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use fluxus_utils::window::WindowConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+struct FailingSource {
+    remaining: usize,
+}
+
+#[async_trait]
+impl Source<i32> for FailingSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<i32>>> {
+        if self.remaining == 0 {
+            return Err(StreamError::Io(std::io::Error::new(
+                std::io::ErrorKind::ConnectionReset,
+                "connection reset",
+            )));
+        }
+        self.remaining -= 1;
+        Ok(Some(Record::new(self.remaining as i32)))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+/// Source that never produces a record
+struct IdleSource;
+
+#[async_trait]
+impl Source<i32> for IdleSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<i32>>> {
+        Err(StreamError::Wait(10))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+struct FailingSink;
+
+#[async_trait]
+impl Sink<i32> for FailingSink {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn write(&mut self, _record: Record<i32>) -> StreamResult<()> {
+        Err(StreamError::Runtime("disk full".to_string()))
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+struct PanickingOperator;
+
+#[async_trait]
+impl Operator<i32, i32> for PanickingOperator {
+    async fn process(&mut self, record: Record<i32>) -> StreamResult<Vec<Record<i32>>> {
+        if record.data == 2 {
+            panic!("bad record {}", record.data);
+        }
+        Ok(vec![record])
+    }
+}
+
+fn runtime() -> RuntimeContext {
+    RuntimeContext::new(ParallelConfig::new(1, 16, true))
+}
+
+#[tokio::test]
+async fn test_await_completion_succeeds() {
+    let sink = CollectionSink::new();
+    let job = runtime()
+        .execute_pipeline(
+            CollectionSource::new(vec![1, 2, 3]),
+            Vec::new(),
+            sink.clone(),
+        )
+        .await
+        .unwrap();
+
+    job.await_completion().await.unwrap();
+    assert_eq!(sink.get_data(), vec![1, 2, 3]);
+}
+
+fn window_sum() -> SharedOperator<i32> {
+    let window = WindowAggregator::new(
+        WindowConfig::tumbling(Duration::from_millis(10)),
+        0,
+        |sum, x| sum + x,
+    );
+    Arc::new(Mutex::new(window))
+}
+
+#[tokio::test]
+async fn test_window_emits_open_windows_at_end_of_input() {
+    let sink = CollectionSink::new();
+    let source = CollectionSource::with_timestamps(vec![(0, 1), (5, 2), (12, 3), (15, 4)]);
+    let job = runtime()
+        .execute_pipeline(source, vec![window_sum()], sink.clone())
+        .await
+        .unwrap();
+
+    job.await_completion().await.unwrap();
+    // The first window closes at record 12, the second one when the input ends
+    assert_eq!(sink.get_data(), vec![3, 7]);
+}
+
+#[tokio::test]
+async fn test_parallel_instances_flush_window_once() {
+    let sink = CollectionSink::new();
+    let source = CollectionSource::with_timestamps((1..=4).map(|i| (i as i64, i)));
+    let job = RuntimeContext::new(ParallelConfig::new(4, 16, true))
+        .execute_pipeline(source, vec![window_sum()], sink.clone())
+        .await
+        .unwrap();
+
+    job.await_completion().await.unwrap();
+    assert_eq!(sink.get_data(), vec![10]);
+}
+
+#[tokio::test]
+async fn test_await_completion_reports_source_error() {
+    let sink = CollectionSink::new();
+    let job = runtime()
+        .execute_pipeline(FailingSource { remaining: 2 }, Vec::new(), sink.clone())
+        .await
+        .unwrap();
+
+    let e = job.await_completion().await.unwrap_err();
+    assert!(
+        matches!(&e, StreamError::Source { message, .. } if message.contains("connection reset"))
+    );
+    assert_eq!(e.context().unwrap().operator.as_deref(), Some("source"));
+    assert!(e.is_retryable());
+    assert_eq!(sink.get_data().len(), 2);
+}
+
+#[tokio::test]
+async fn test_await_completion_reports_sink_error() {
+    let job = runtime()
+        .execute_pipeline(
+            CollectionSource::new(vec![1, 2, 3]),
+            Vec::new(),
+            FailingSink,
+        )
+        .await
+        .unwrap();
+
+    let e = job.await_completion().await.unwrap_err();
+    assert!(matches!(&e, StreamError::Sink { message, .. } if message.contains("disk full")));
+    let context = e.context().unwrap();
+    assert_eq!(context.operator.as_deref(), Some("sink"));
+    assert!(context.timestamp.is_some());
+    assert!(!e.is_retryable());
+}
+
+#[tokio::test]
+async fn test_await_completion_reports_panic() {
+    let operators: Vec<SharedOperator<i32>> = vec![Arc::new(Mutex::new(PanickingOperator))];
+    let job = runtime()
+        .execute_pipeline(
+            CollectionSource::new(vec![1, 2, 3]),
+            operators,
+            CollectionSink::new(),
+        )
+        .await
+        .unwrap();
+
+    let result = job.await_completion().await;
+    assert!(
+        matches!(&result, Err(StreamError::Runtime(msg)) if msg.contains("panicked: bad record 2")),
+        "{:?}",
+        result
+    );
+}
+
+#[tokio::test]
+async fn test_stage_handles_capture_panic() {
+    let operators: Vec<SharedOperator<i32>> = vec![Arc::new(Mutex::new(PanickingOperator))];
+    let job = runtime()
+        .execute_pipeline(
+            CollectionSource::new(vec![1, 2, 3]),
+            operators,
+            CollectionSink::new(),
+        )
+        .await
+        .unwrap();
+
+    let stages = job.stages();
+    let names: Vec<&str> = stages.iter().map(|stage| stage.name()).collect();
+    assert_eq!(names.len(), 3);
+    assert_eq!(names[0], "source");
+    assert!(names[1].starts_with("operator[0]"));
+    assert_eq!(names[2], "sink");
+
+    let operator = &stages[1];
+    assert!(
+        matches!(&operator.join().await[..], [TaskExit::Panicked(msg)] if msg == "bad record 2")
+    );
+    assert!(operator.is_finished());
+    assert_eq!(operator.panic().as_deref(), Some("bad record 2"));
+    assert_eq!(stages[2].join().await, vec![TaskExit::Completed]);
+    assert_eq!(stages[2].panic(), None);
+}
+
+#[tokio::test]
+async fn test_stage_abort() {
+    let sink = CollectionSink::new();
+    let job = runtime()
+        .execute_pipeline(IdleSource, Vec::new(), sink.clone())
+        .await
+        .unwrap();
+
+    let source = job.stage("source").unwrap();
+    assert!(!source.is_finished());
+    source.abort();
+    assert_eq!(source.join().await, vec![TaskExit::Aborted]);
+
+    // The sink finishes once its input is closed by the aborted source
+    let sink_stage = job.stage("sink").unwrap();
+    assert_eq!(sink_stage.join().await, vec![TaskExit::Completed]);
+    let e = job.await_completion().await.unwrap_err();
+    assert!(matches!(&e, StreamError::Runtime(msg) if msg == "source was aborted"));
+}
+
+#[tokio::test]
+async fn test_job_registry() {
+    let runtime = runtime();
+    let job = runtime
+        .execute_pipeline(IdleSource, Vec::new(), CollectionSink::new())
+        .await
+        .unwrap();
+    let mut metrics = Metrics::new();
+    metrics.counter("wordcount.records").add(3);
+
+    let registered = JobRegistry::register("wordcount", &job).unwrap();
+    registered.report_metrics(Arc::new(metrics));
+
+    // Looked up by name and by id
+    let found = JobRegistry::get("wordcount").unwrap();
+    assert_eq!(found.id(), job.id());
+    assert_eq!(JobRegistry::get(job.id()).unwrap().name(), job.id());
+    assert_eq!(found.topology(), vec!["source", "sink"]);
+    assert!(found.metrics().unwrap().contains_key("wordcount.records"));
+    assert!(JobRegistry::names().contains(&"wordcount".to_string()));
+
+    // The name is taken while the job runs
+    let other = runtime
+        .execute_pipeline(
+            CollectionSource::new(vec![1]),
+            Vec::new(),
+            CollectionSink::new(),
+        )
+        .await
+        .unwrap();
+    assert!(JobRegistry::register("wordcount", &other).is_err());
+
+    found.abort();
+    job.await_completion().await.unwrap_err();
+    let replaced = JobRegistry::register("wordcount", &other).unwrap();
+    assert_eq!(replaced.id(), other.id());
+    other.await_completion().await.unwrap();
+    assert!(runtime.is_finished());
+    assert!(JobRegistry::remove("wordcount").is_some());
+    assert!(JobRegistry::get("wordcount").is_none());
+}
@@ -0,0 +1,80 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_runtime::watermark::WatermarkStrategy;
+use std::time::Duration;
+
+#[test]
+fn test_interval_join() {
+    tokio_test::block_on(async {
+        let impressions = DataStream::new(CollectionSource::with_timestamps(vec![
+            (1_000, "u1"),
+            (2_000, "u2"),
+            (30_000, "u1"),
+        ]));
+        let clicks = DataStream::new(CollectionSource::with_timestamps(vec![
+            (2_500, "u2"),
+            (5_000, "u1"),
+            (15_000, "u1"),
+            (35_000, "u1"),
+            (40_000, "u3"),
+        ]));
+
+        let dry_run = impressions
+            .key_by(|user| *user)
+            .interval_join(clicks.key_by(|user| *user))
+            .between(0, 10_000)
+            .process(|impression, click| format!("{}->{}", impression, click))
+            .dry_run(CollectionSink::new(), 100)
+            .await
+            .unwrap();
+
+        let mut joined: Vec<(i64, String)> = dry_run
+            .output
+            .into_iter()
+            .map(|record| (record.timestamp, record.data))
+            .collect();
+        joined.sort();
+        assert_eq!(
+            joined,
+            vec![
+                (2_500, "u2->u2".to_string()),
+                (5_000, "u1->u1".to_string()),
+                (35_000, "u1->u1".to_string()),
+            ]
+        );
+    })
+}
+
+#[test]
+fn test_interval_join_negative_bound() {
+    tokio_test::block_on(async {
+        let orders = DataStream::new(CollectionSource::with_timestamps(vec![
+            (10_000, ("o1", 1)),
+            (20_000, ("o2", 2)),
+        ]));
+        let payments = DataStream::new(CollectionSource::with_timestamps(vec![
+            (6_000, ("o1", 100)),
+            (9_000, ("o2", 200)),
+            (24_000, ("o2", 300)),
+        ]));
+        let sink = CollectionSink::new();
+
+        orders
+            .key_by(|(order, _)| *order)
+            .interval_join(payments.key_by(|(order, _)| *order))
+            .between(-5_000, 5_000)
+            .with_watermark(WatermarkStrategy::bounded_out_of_orderness(
+                Duration::from_secs(1),
+            ))
+            .process(|(order, _), (_, amount)| (order.to_string(), *amount))
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        let mut joined = sink.get_data();
+        joined.sort();
+        assert_eq!(
+            joined,
+            vec![("o1".to_string(), 100), ("o2".to_string(), 300)]
+        );
+    })
+}
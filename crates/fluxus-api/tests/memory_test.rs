@@ -0,0 +1,191 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream, WindowedStream};
+use fluxus_core::{MetricValue, Metrics, ParallelConfig};
+use fluxus_runtime::RuntimeContext;
+use fluxus_runtime::state::KeyedStateBackend;
+use fluxus_sources::Source;
+use fluxus_transformers::BufferedSource;
+use fluxus_utils::memory::{MemoryAccount, MemorySize};
+use fluxus_utils::models::Record;
+use fluxus_utils::window::WindowConfig;
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::time::Duration;
+
+#[test]
+fn test_memory_size_estimates() {
+    assert_eq!(7u64.memory_size(), 8);
+    let text = String::with_capacity(100);
+    assert_eq!(text.memory_size(), size_of::<String>() + 100);
+
+    let mut words: Vec<String> = Vec::with_capacity(4);
+    words.push("a".repeat(10));
+    words.push("b".repeat(20));
+    let heap = 4 * size_of::<String>() + words[0].capacity() + words[1].capacity();
+    assert_eq!(words.heap_size(), heap);
+    assert_eq!(
+        Record::new(words).memory_size(),
+        size_of::<Record<Vec<String>>>() + heap
+    );
+
+    let mut counts = HashMap::new();
+    counts.insert("key".to_string(), 1u64);
+    assert!(counts.heap_size() >= counts.capacity() * size_of::<(String, u64)>() + 3);
+}
+
+#[test]
+fn test_memory_accounts_roll_up_to_jobs() {
+    let mut metrics = Metrics::new();
+    let window = metrics.operator_memory("clicks", "window");
+    let state = metrics.operator_memory("clicks", "dedup");
+    window.add(1000);
+    state.add(300);
+    window.release(400);
+    assert_eq!(metrics.job_memory("clicks").bytes(), 900);
+    assert_eq!(window.peak(), 1000);
+
+    let snapshot = metrics.snapshot();
+    let gauge = |name: &str| match snapshot.get(name) {
+        Some(MetricValue::Gauge(value)) => *value,
+        other => panic!("{}: {:?}", name, other),
+    };
+    assert_eq!(gauge("clicks.memory_bytes"), 900);
+    assert_eq!(gauge("clicks.window.memory_bytes"), 600);
+    assert_eq!(gauge("clicks.dedup.memory_bytes"), 300);
+    // The same operator shares its account
+    metrics.operator_memory("clicks", "window").release(600);
+    assert_eq!(metrics.job_memory("clicks").bytes(), 300);
+}
+
+#[test]
+fn test_keyed_state_memory() {
+    let account = MemoryAccount::new();
+    let state = KeyedStateBackend::<String, Vec<u64>>::new().with_memory_account(account.clone());
+    let entry = |key: &str, value: &Vec<u64>| key.to_string().memory_size() + value.memory_size();
+
+    state.set("a".to_string(), vec![1, 2]);
+    let first = entry("a", &vec![1, 2]);
+    assert_eq!(account.bytes(), first as i64);
+
+    // Replacing and updating in place account for the change in size
+    state.set("a".to_string(), Vec::with_capacity(10));
+    assert_eq!(account.bytes(), entry("a", &Vec::with_capacity(10)) as i64);
+    state.update_with("b".to_string(), Vec::new, |values| {
+        values.reserve_exact(100)
+    });
+    assert_eq!(
+        account.bytes(),
+        (entry("a", &Vec::with_capacity(10)) + entry("b", &Vec::with_capacity(100))) as i64
+    );
+
+    // Dropping entries releases them
+    state.retain(|key, _| key == "b");
+    assert_eq!(account.bytes(), entry("b", &Vec::with_capacity(100)) as i64);
+    state.remove(&"b".to_string());
+    assert_eq!(account.bytes(), 0);
+}
+
+#[tokio::test]
+async fn test_buffered_source_memory() {
+    let account = MemoryAccount::new();
+    let data: Vec<String> = (0..100).map(|i| format!("record-{}", i)).collect();
+    let mut source = BufferedSource::new(CollectionSource::new(data.clone()), 10)
+        .with_memory_account(account.clone());
+    source.init().await.unwrap();
+
+    // The reader task fills the queue ahead of the consumer
+    let first = source.next().await.unwrap().unwrap();
+    assert_eq!(first.data, "record-0");
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    assert!(account.bytes() > 0);
+
+    while source.next().await.unwrap().is_some() {}
+    assert_eq!(account.bytes(), 0);
+    assert!(account.peak() > 0);
+
+    let sink = CollectionSink::new();
+    DataStream::new(
+        BufferedSource::new(CollectionSource::new(data.clone()), 10)
+            .with_memory_account(account.clone()),
+    )
+    .sink(sink.clone())
+    .await
+    .unwrap();
+    assert_eq!(sink.get_data(), data);
+    assert_eq!(account.bytes(), 0);
+}
+
+#[tokio::test]
+async fn test_window_memory() {
+    // Ten words of 4 to 40 bytes in the first window, whose buffers hold
+    // them all before it ends
+    let words: Vec<(i64, String)> = (0..20)
+        .map(|i| (i, "word".repeat(i as usize % 10 + 1)))
+        .collect();
+    let buffered = (1..=10).map(|n| 4 * n).sum::<usize>() as i64;
+    let windowed = |account: &MemoryAccount| {
+        DataStream::new(CollectionSource::with_timestamps(words.clone()))
+            .window(WindowConfig::tumbling(Duration::from_millis(10)))
+            .memory_account(account.clone())
+    };
+    async fn run<R: Clone + Send + Sync + 'static>(stream: DataStream<R>) -> Vec<R> {
+        let sink = CollectionSink::new();
+        stream.sink(sink.clone()).await.unwrap();
+        sink.get_data()
+    }
+
+    let account = MemoryAccount::new();
+    let sorted = run(windowed(&account).sort_by(|a, b| b.len().cmp(&a.len()))).await;
+    assert_eq!(sorted.len(), 20);
+    assert!(account.peak() >= buffered, "{}", account.peak());
+    // The state of the windows is dropped once the input ends
+    assert_eq!(account.bytes(), 0);
+
+    let account = MemoryAccount::new();
+    run(windowed(&account).sort_by_ts_desc()).await;
+    assert!(account.peak() >= buffered, "{}", account.peak());
+    assert_eq!(account.bytes(), 0);
+
+    let account = MemoryAccount::new();
+    run(windowed(&account).skip(5)).await;
+    assert!(account.peak() >= buffered, "{}", account.peak());
+    assert_eq!(account.bytes(), 0);
+
+    let account = MemoryAccount::new();
+    let sizes = run(windowed(&account).apply(|_, records| vec![records.len()])).await;
+    assert_eq!(sizes, vec![10, 10]);
+    assert!(account.peak() >= buffered, "{}", account.peak());
+    assert_eq!(account.bytes(), 0);
+
+    // Aggregates are accounted for by their inline size
+    let account = MemoryAccount::new();
+    let counts = run(windowed(&account).count()).await;
+    assert_eq!(counts, vec![10, 10]);
+    assert!(account.peak() >= size_of::<Option<u64>>() as i64);
+    assert_eq!(account.bytes(), 0);
+
+    // Without an account nothing is accounted for
+    let windowed: WindowedStream<String> =
+        DataStream::new(CollectionSource::with_timestamps(words.clone()))
+            .window(WindowConfig::tumbling(Duration::from_millis(10)));
+    assert_eq!(run(windowed.skip(5)).await.len(), 20);
+}
+
+#[tokio::test]
+async fn test_runtime_channel_memory() {
+    let account = MemoryAccount::new();
+    let sink = CollectionSink::<i32>::new();
+    let job = RuntimeContext::new(ParallelConfig::new(2, 16, true))
+        .with_memory_account(account.clone())
+        .execute_pipeline(
+            CollectionSource::new((0..100).collect::<Vec<i32>>()),
+            Vec::new(),
+            sink.clone(),
+        )
+        .await
+        .unwrap();
+
+    job.await_completion().await.unwrap();
+    assert_eq!(sink.get_data().len(), 100);
+    assert!(account.peak() > 0);
+    assert_eq!(account.bytes(), 0);
+}
@@ -0,0 +1,38 @@
+use fluxus_api::{CollectionSink, DataStream};
+use fluxus_sources::MmapFileSource;
+
+fn read_lines(source: MmapFileSource) -> Vec<String> {
+    tokio_test::block_on(async {
+        let sink = CollectionSink::new();
+        DataStream::new(source).sink(sink.clone()).await.unwrap();
+        sink.get_data()
+    })
+}
+
+#[test]
+fn test_mmap_lines_across_chunks() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("events.log");
+    let expected: Vec<String> = (0..500).map(|i| format!("event-{}", i * 37)).collect();
+    std::fs::write(&path, expected.join("\n") + "\n").unwrap();
+
+    // Chunk sizes smaller than, equal to and larger than a line
+    for chunk_size in [1, 3, 9, 64, 1 << 20] {
+        let source = MmapFileSource::new(&path).with_chunk_size(chunk_size);
+        assert_eq!(read_lines(source), expected, "chunk size {}", chunk_size);
+    }
+}
+
+#[test]
+fn test_mmap_edge_cases() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let empty = dir.path().join("empty.log");
+    std::fs::write(&empty, "").unwrap();
+    assert!(read_lines(MmapFileSource::new(&empty)).is_empty());
+
+    let crlf = dir.path().join("crlf.log");
+    std::fs::write(&crlf, "a\r\n\r\nb").unwrap();
+    let lines = read_lines(MmapFileSource::new(&crlf).with_chunk_size(2));
+    assert_eq!(lines, vec!["a", "", "b"]);
+}
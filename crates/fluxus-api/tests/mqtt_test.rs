@@ -0,0 +1,204 @@
+use fluxus_api::{CollectionSource, DataStream};
+use fluxus_sinks::{MqttSink, Sink};
+use fluxus_sources::{MqttSource, Source};
+use fluxus_utils::models::StreamError;
+use fluxus_utils::mqtt::{MqttOptions, QoS, topic_matches};
+use fluxus_utils::security::TlsConfig;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Read a control packet as its first header byte and its body
+async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let header = stream.read_u8().await.unwrap();
+    let (mut length, mut shift) = (0usize, 0);
+    loop {
+        let byte = stream.read_u8().await.unwrap();
+        length += ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).await.unwrap();
+    (header, body)
+}
+
+async fn write_packet(stream: &mut TcpStream, header: u8, body: &[u8]) {
+    let mut bytes = vec![header, body.len() as u8];
+    bytes.extend_from_slice(body);
+    stream.write_all(&bytes).await.unwrap();
+}
+
+fn publish_body(topic: &str, id: Option<u16>, payload: &[u8]) -> Vec<u8> {
+    let mut body = (topic.len() as u16).to_be_bytes().to_vec();
+    body.extend_from_slice(topic.as_bytes());
+    if let Some(id) = id {
+        body.extend_from_slice(&id.to_be_bytes());
+    }
+    body.extend_from_slice(payload);
+    body
+}
+
+/// Accept a connection and answer its CONNECT
+async fn accept(listener: &TcpListener) -> TcpStream {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let (header, body) = read_packet(&mut stream).await;
+    assert_eq!(header, 0x10);
+    assert_eq!(&body[2..6], b"MQTT");
+    write_packet(&mut stream, 0x20, &[0, 0]).await;
+    stream
+}
+
+/// Answer a SUBSCRIBE, returning its topic filter
+async fn accept_subscription(stream: &mut TcpStream) -> String {
+    let (header, body) = read_packet(stream).await;
+    assert_eq!(header, 0x82);
+    let length = u16::from_be_bytes([body[2], body[3]]) as usize;
+    let filter = String::from_utf8(body[4..4 + length].to_vec()).unwrap();
+    write_packet(stream, 0x90, &[body[0], body[1], body[4 + length]]).await;
+    filter
+}
+
+fn options(listener: &TcpListener) -> MqttOptions {
+    let port = listener.local_addr().unwrap().port();
+    MqttOptions::new("fluxus-test", "127.0.0.1", port)
+        .with_reconnect_delay(Duration::from_millis(10))
+}
+
+#[test]
+fn test_topic_matches() {
+    assert!(topic_matches("devices/+/readings", "devices/1/readings"));
+    assert!(!topic_matches("devices/+/readings", "devices/1/2/readings"));
+    assert!(topic_matches("devices/#", "devices/1/readings"));
+    assert!(topic_matches("devices/#", "devices"));
+    assert!(!topic_matches("devices/1", "devices/1/readings"));
+    assert!(!topic_matches("#", "$SYS/uptime"));
+}
+
+#[tokio::test]
+async fn test_mqtt_source_reconnects() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut source = MqttSource::new(options(&listener))
+        .subscribe("devices/+/readings", QoS::AtLeastOnce)
+        .with_decoder(|message| Ok((message.topic, String::from_utf8(message.payload).unwrap())));
+
+    let broker = tokio::spawn(async move {
+        let mut stream = accept(&listener).await;
+        assert_eq!(accept_subscription(&mut stream).await, "devices/+/readings");
+        let body = publish_body("devices/1/readings", Some(7), b"21.5");
+        write_packet(&mut stream, 0x32, &body).await;
+        assert_eq!(read_packet(&mut stream).await, (0x40, vec![0, 7]));
+        drop(stream);
+
+        // The source connects again and renews its subscription
+        let mut stream = accept(&listener).await;
+        assert_eq!(accept_subscription(&mut stream).await, "devices/+/readings");
+        write_packet(
+            &mut stream,
+            0x30,
+            &publish_body("devices/2/readings", None, b"19"),
+        )
+        .await;
+        stream
+    });
+
+    source.init().await.unwrap();
+    let first = source.next().await.unwrap().unwrap();
+    assert_eq!(
+        first.data,
+        ("devices/1/readings".to_string(), "21.5".to_string())
+    );
+    let second = source.next().await.unwrap().unwrap();
+    assert_eq!(
+        second.data,
+        ("devices/2/readings".to_string(), "19".to_string())
+    );
+    broker.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_mqtt_sink_publishes_exactly_once() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let sink = MqttSink::json(options(&listener), "numbers", QoS::ExactlyOnce).with_retain(true);
+
+    let broker = tokio::spawn(async move {
+        let mut stream = accept(&listener).await;
+        let mut payloads = Vec::new();
+        loop {
+            let (header, body) = read_packet(&mut stream).await;
+            if header == 0xe0 {
+                return payloads;
+            }
+            // QoS 2 with the retain flag
+            assert_eq!(header, 0x35);
+            let id = [body[9], body[10]];
+            assert_eq!(&body[2..9], b"numbers");
+            payloads.push(String::from_utf8(body[11..].to_vec()).unwrap());
+            write_packet(&mut stream, 0x50, &id).await;
+            assert_eq!(read_packet(&mut stream).await, (0x62, id.to_vec()));
+            write_packet(&mut stream, 0x70, &id).await;
+        }
+    });
+
+    DataStream::new(CollectionSource::new(vec![1, 2, 3]))
+        .map(|n| n * 10)
+        .sink(sink)
+        .await
+        .unwrap();
+    assert_eq!(broker.await.unwrap(), vec!["10", "20", "30"]);
+}
+
+#[tokio::test]
+async fn test_mqtt_source_gives_up_reconnecting() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let mut source = MqttSource::new(options(&listener).with_max_reconnect_attempts(2))
+        .subscribe("devices/#", QoS::AtMostOnce);
+
+    let (close, closed) = tokio::sync::oneshot::channel();
+    let broker = tokio::spawn(async move {
+        let mut stream = accept(&listener).await;
+        accept_subscription(&mut stream).await;
+        // Neither the connection nor the broker come back
+        closed.await.unwrap();
+    });
+
+    source.init().await.unwrap();
+    close.send(()).unwrap();
+    broker.await.unwrap();
+    assert!(source.next().await.is_err());
+}
+
+#[tokio::test]
+async fn test_mqtt_tls() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let options = options(&listener).with_tls(TlsConfig::new());
+    let broker = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        // A TLS handshake starts with a handshake record instead of CONNECT
+        assert_eq!(stream.read_u8().await.unwrap(), 0x16);
+    });
+
+    assert!(
+        MqttSink::<i32>::json(options, "numbers", QoS::AtMostOnce)
+            .init()
+            .await
+            .is_err()
+    );
+    broker.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_mqtt_tls_invalid_ca_cert() {
+    let dir = tempfile::tempdir().unwrap();
+    let ca_cert = dir.path().join("ca.pem");
+    std::fs::write(&ca_cert, "not a certificate").unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let options = options(&listener).with_tls(TlsConfig::new().with_ca_cert(ca_cert));
+
+    let result = MqttSink::<i32>::json(options, "numbers", QoS::AtMostOnce)
+        .init()
+        .await;
+    assert!(matches!(result, Err(StreamError::Config(_))));
+}
@@ -0,0 +1,215 @@
+use async_trait::async_trait;
+use fluxus_api::{CollectionSink, CollectionSource};
+use fluxus_core::ParallelConfig;
+use fluxus_runtime::partition::Partitioning;
+use fluxus_runtime::{RuntimeContext, StageOperator};
+use fluxus_sources::Source;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamResult};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+type Stage = (StageOperator<(u64, u32)>, Partitioning<(u64, u32)>);
+
+struct Identity;
+
+#[async_trait]
+impl Operator<(u64, u32), (u64, u32)> for Identity {
+    async fn process(
+        &mut self,
+        record: Record<(u64, u32)>,
+    ) -> StreamResult<Vec<Record<(u64, u32)>>> {
+        tokio::task::yield_now().await;
+        Ok(vec![record])
+    }
+}
+
+/// Replaces the value of each record by the number of records of its key
+/// this instance has seen
+#[derive(Default)]
+struct KeyCount(HashMap<u64, u32>);
+
+#[async_trait]
+impl Operator<(u64, u32), (u64, u32)> for KeyCount {
+    async fn process(
+        &mut self,
+        record: Record<(u64, u32)>,
+    ) -> StreamResult<Vec<Record<(u64, u32)>>> {
+        let key = record.data.0;
+        let count = self.0.entry(key).or_default();
+        *count += 1;
+        Ok(vec![Record::new((key, *count))])
+    }
+}
+
+/// Emits the records of the timestamps `0..count` with a watermark of the
+/// latest timestamp
+struct TimestampSource {
+    next: i64,
+    count: i64,
+}
+
+#[async_trait]
+impl Source<(u64, u32)> for TimestampSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<(u64, u32)>>> {
+        if self.next == self.count {
+            return Ok(None);
+        }
+        let record = Record::with_timestamp((self.next as u64, self.next as u32), self.next);
+        self.next += 1;
+        Ok(Some(record))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        (self.next > 0).then(|| self.next - 1)
+    }
+}
+
+/// Delays every fourth record, so that one instance falls behind the others
+struct Delay;
+
+#[async_trait]
+impl Operator<(u64, u32), (u64, u32)> for Delay {
+    async fn process(
+        &mut self,
+        record: Record<(u64, u32)>,
+    ) -> StreamResult<Vec<Record<(u64, u32)>>> {
+        if record.timestamp % 4 == 0 {
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+        Ok(vec![record])
+    }
+}
+
+/// Counts the records arriving behind a watermark they are older than
+struct LateCount {
+    watermark: i64,
+    late: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Operator<(u64, u32), (u64, u32)> for LateCount {
+    async fn process(
+        &mut self,
+        record: Record<(u64, u32)>,
+    ) -> StreamResult<Vec<Record<(u64, u32)>>> {
+        if record.timestamp < self.watermark {
+            self.late.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(vec![record])
+    }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<(u64, u32)>>> {
+        self.watermark = self.watermark.max(watermark);
+        Ok(Vec::new())
+    }
+}
+
+async fn run_stages(
+    source: impl Source<(u64, u32)> + Send + Sync + 'static,
+    stages: Vec<Stage>,
+) -> Vec<(u64, u32)> {
+    let runtime = RuntimeContext::new(ParallelConfig::new(4, 8, false));
+    let sink = CollectionSink::new();
+    let job = runtime
+        .execute_partitioned_pipeline(source, stages, sink.clone())
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), job.await_completion())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(runtime.is_finished());
+
+    sink.get_data()
+}
+
+async fn run(partitioning: Partitioning<(u64, u32)>) -> Vec<(u64, u32)> {
+    let input: Vec<(u64, u32)> = (0..60).map(|i| (i % 3, i as u32)).collect();
+    let stages = vec![
+        (
+            StageOperator::per_instance(|| Identity),
+            partitioning.clone(),
+        ),
+        (StageOperator::per_instance(|| Identity), partitioning),
+    ];
+    run_stages(CollectionSource::new(input), stages).await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_every_partitioning_delivers_all_records() {
+    for partitioning in [
+        Partitioning::Shared,
+        Partitioning::Rebalance,
+        Partitioning::Rescale,
+        Partitioning::hash(|(key, _)| *key),
+    ] {
+        let mut output = run(partitioning.clone()).await;
+        output.sort_by_key(|(_, value)| *value);
+        assert_eq!(output.len(), 60, "{:?}", partitioning);
+        assert!(
+            output.iter().enumerate().all(|(i, (_, v))| *v == i as u32),
+            "{:?}",
+            partitioning
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_hash_partitioning_keeps_key_order() {
+    let output = run(Partitioning::hash(|(key, _)| *key)).await;
+    for key in 0..3 {
+        let values: Vec<u32> = output
+            .iter()
+            .filter(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+            .collect();
+        assert!(values.windows(2).all(|w| w[0] < w[1]), "key {}", key);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_hash_partitioning_keeps_key_state_in_one_instance() {
+    let input: Vec<(u64, u32)> = (0..60).map(|i| (i % 3, i as u32)).collect();
+    let stages = vec![(
+        StageOperator::per_instance(KeyCount::default),
+        Partitioning::hash(|(key, _)| *key),
+    )];
+    let output = run_stages(CollectionSource::new(input), stages).await;
+    for key in 0..3 {
+        let count = output.iter().filter(|(k, _)| *k == key).map(|(_, c)| *c);
+        assert_eq!(count.max(), Some(20), "key {}", key);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_watermark_waits_for_every_instance() {
+    for partitioning in [Partitioning::Shared, Partitioning::Rebalance] {
+        let late = Arc::new(AtomicUsize::new(0));
+        let check = LateCount {
+            watermark: i64::MIN,
+            late: late.clone(),
+        };
+        let stages = vec![
+            (StageOperator::per_instance(|| Delay), partitioning.clone()),
+            (
+                StageOperator::Shared(Arc::new(Mutex::new(check))),
+                Partitioning::Shared,
+            ),
+        ];
+        let output = run_stages(TimestampSource { next: 0, count: 80 }, stages).await;
+        assert_eq!(output.len(), 80, "{:?}", partitioning);
+        assert_eq!(late.load(Ordering::SeqCst), 0, "{:?}", partitioning);
+    }
+}
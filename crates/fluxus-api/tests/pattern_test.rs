@@ -0,0 +1,51 @@
+use fluxus_api::operators::Pattern;
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use std::time::Duration;
+
+#[test]
+fn test_detect_pattern() {
+    tokio_test::block_on(async {
+        let events = DataStream::new(CollectionSource::with_timestamps(vec![
+            (1_000, ("u1", "login")),
+            (2_000, ("u2", "login")),
+            (3_000, ("u1", "browse")),
+            (4_000, ("u1", "payment_failed")),
+            (5_000, ("u1", "payment")),
+            (6_000, ("u2", "payment_failed")),
+            (7_000, ("u2", "browse")),
+            (8_000, ("u2", "payment")),
+            (10_000, ("u3", "login")),
+            (80_000, ("u3", "payment_failed")),
+            (81_000, ("u3", "payment")),
+        ]));
+        // A retry must directly follow the failed payment, and the whole
+        // sequence happen within a minute
+        let pattern = Pattern::begin("login")
+            .r#where(|(_, event): &(&str, &str)| *event == "login")
+            .followed_by("failed")
+            .r#where(|(_, event)| *event == "payment_failed")
+            .next("retry")
+            .r#where(|(_, event)| *event == "payment")
+            .within(Duration::from_secs(60));
+
+        let sink = CollectionSink::new();
+        events
+            .key_by(|(user, _)| *user)
+            .detect(pattern)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        let matches = sink.get_data();
+        assert_eq!(matches.len(), 1);
+        let (user, sequence) = &matches[0];
+        assert_eq!(*user, "u1");
+        let steps: Vec<&str> = sequence
+            .events
+            .iter()
+            .map(|(step, _)| step.as_str())
+            .collect();
+        assert_eq!(steps, vec!["login", "failed", "retry"]);
+        assert_eq!(sequence.get("retry"), Some(&("u1", "payment")));
+    });
+}
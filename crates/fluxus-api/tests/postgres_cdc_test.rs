@@ -0,0 +1,295 @@
+use fluxus_sources::{PostgresCdcSource, Source};
+use fluxus_utils::models::StreamError;
+use fluxus_utils::postgres::{ChangeKind, Lsn, PgOutputDecoder, PostgresOptions, SslMode};
+use fluxus_utils::row::Value;
+use fluxus_utils::security::TlsConfig;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+async fn read_message(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let tag = stream.read_u8().await.unwrap();
+    let len = stream.read_u32().await.unwrap() as usize;
+    let mut body = vec![0; len - 4];
+    stream.read_exact(&mut body).await.unwrap();
+    (tag, body)
+}
+
+async fn write_message(stream: &mut TcpStream, tag: u8, body: &[u8]) {
+    let mut bytes = vec![tag];
+    bytes.extend_from_slice(&((body.len() + 4) as u32).to_be_bytes());
+    bytes.extend_from_slice(body);
+    stream.write_all(&bytes).await.unwrap();
+}
+
+async fn write_xlog(stream: &mut TcpStream, lsn: u64, data: &str) {
+    let mut body = vec![b'w'];
+    body.extend_from_slice(&lsn.to_be_bytes());
+    body.extend_from_slice(&lsn.to_be_bytes());
+    body.extend_from_slice(&0u64.to_be_bytes());
+    body.extend_from_slice(data.as_bytes());
+    write_message(stream, b'd', &body).await;
+}
+
+/// Accept a replication connection and start streaming, reporting that the
+/// slot already exists
+async fn accept(listener: &TcpListener) -> TcpStream {
+    let (mut stream, _) = listener.accept().await.unwrap();
+    let len = stream.read_u32().await.unwrap() as usize;
+    let mut startup = vec![0; len - 4];
+    stream.read_exact(&mut startup).await.unwrap();
+    assert!(startup.windows(20).any(|w| w == b"replication\0database"));
+    write_message(&mut stream, b'R', &0u32.to_be_bytes()).await;
+    write_message(&mut stream, b'Z', b"I").await;
+
+    let (tag, query) = read_message(&mut stream).await;
+    assert_eq!(tag, b'Q');
+    assert!(query.starts_with(b"CREATE_REPLICATION_SLOT \"orders\" LOGICAL wal2json"));
+    write_message(
+        &mut stream,
+        b'E',
+        b"SERROR\0C42710\0Mreplication slot exists\0\0",
+    )
+    .await;
+    write_message(&mut stream, b'Z', b"I").await;
+
+    let (tag, query) = read_message(&mut stream).await;
+    assert_eq!(tag, b'Q');
+    assert!(query.starts_with(b"START_REPLICATION SLOT \"orders\" LOGICAL 0/10 "));
+    write_message(&mut stream, b'W', &[0, 0, 0]).await;
+    stream
+}
+
+/// Read status updates until one confirms `lsn`
+async fn wait_for_flush(stream: &mut TcpStream, lsn: u64) {
+    loop {
+        let (tag, body) = read_message(stream).await;
+        assert_eq!((tag, body[0]), (b'd', b'r'));
+        let flushed = u64::from_be_bytes(body[9..17].try_into().unwrap());
+        assert!(
+            flushed <= lsn,
+            "confirmed {} before it was flushed",
+            flushed
+        );
+        if flushed == lsn {
+            return;
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_postgres_cdc_confirms_flushed_changes() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let options = PostgresOptions::new("127.0.0.1", port, "shop", "orders")
+        .with_start_lsn("0/10".parse().unwrap())
+        .with_status_interval(Duration::from_millis(20));
+    let mut source = PostgresCdcSource::new(options);
+    let acker = source.acker();
+
+    let server = tokio::spawn(async move {
+        let mut stream = accept(&listener).await;
+        write_xlog(&mut stream, 0x20, r#"{"action":"B"}"#).await;
+        write_xlog(
+            &mut stream,
+            0x21,
+            r#"{"action":"I","schema":"public","table":"orders","columns":[{"name":"id","type":"integer","value":1},{"name":"paid","type":"boolean","value":false},{"name":"total","type":"numeric","value":"9.50"}]}"#,
+        )
+        .await;
+        write_xlog(
+            &mut stream,
+            0x22,
+            r#"{"action":"U","schema":"public","table":"orders","columns":[{"name":"id","type":"integer","value":1},{"name":"paid","type":"boolean","value":true}],"identity":[{"name":"id","type":"integer","value":1}]}"#,
+        )
+        .await;
+        write_xlog(&mut stream, 0x23, r#"{"action":"C"}"#).await;
+        wait_for_flush(&mut stream, 0x21).await;
+        write_xlog(
+            &mut stream,
+            0x30,
+            r#"{"action":"D","schema":"public","table":"orders","identity":[{"name":"id","type":"integer","value":1}]}"#,
+        )
+        .await;
+        stream
+    });
+
+    source.init().await.unwrap();
+    let insert = source.next().await.unwrap().unwrap();
+    assert_eq!(insert.data.kind, ChangeKind::Insert);
+    assert_eq!(insert.data.lsn, Lsn(0x21));
+    assert_eq!(insert.data.table, "orders");
+    assert_eq!(insert.data.get("id"), Some(&Value::Int64(1)));
+    assert_eq!(insert.data.get("paid"), Some(&Value::Boolean(false)));
+    assert_eq!(insert.data.get("total"), Some(&Value::Float64(9.5)));
+
+    let update = source.next().await.unwrap().unwrap();
+    assert_eq!(update.data.kind, ChangeKind::Update);
+    assert_eq!(update.data.identity("id"), Some(&Value::Int64(1)));
+    assert_eq!(update.data.get("paid"), Some(&Value::Boolean(true)));
+
+    // Only the insert was flushed by the sink
+    acker.ack_until(insert.timestamp);
+    let delete = source.next().await.unwrap().unwrap();
+    assert_eq!(delete.data.kind, ChangeKind::Delete);
+    assert!(delete.data.columns.is_empty());
+    assert_eq!(source.flushed_lsn(), Lsn(0x21));
+    server.await.unwrap();
+}
+
+#[test]
+fn test_pgoutput_decoding() {
+    let mut decoder = PgOutputDecoder::new();
+    let mut relation = b"R".to_vec();
+    relation.extend_from_slice(&7u32.to_be_bytes());
+    relation.extend_from_slice(b"public\0orders\0d");
+    relation.extend_from_slice(&2u16.to_be_bytes());
+    for (name, type_id) in [("id", 23u32), ("note", 25)] {
+        relation.push(1);
+        relation.extend_from_slice(name.as_bytes());
+        relation.push(0);
+        relation.extend_from_slice(&type_id.to_be_bytes());
+        relation.extend_from_slice(&(-1i32).to_be_bytes());
+    }
+    assert_eq!(decoder.decode(Lsn(1), &relation).unwrap(), None);
+
+    let mut insert = b"I".to_vec();
+    insert.extend_from_slice(&7u32.to_be_bytes());
+    insert.push(b'N');
+    insert.extend_from_slice(&2u16.to_be_bytes());
+    insert.push(b't');
+    insert.extend_from_slice(&2u32.to_be_bytes());
+    insert.extend_from_slice(b"42");
+    insert.push(b'n');
+    let change = decoder.decode(Lsn(2), &insert).unwrap().unwrap();
+    assert_eq!(change.kind, ChangeKind::Insert);
+    assert_eq!(change.schema, "public");
+    assert_eq!(
+        change.columns,
+        vec![
+            ("id".to_string(), Value::Int64(42)),
+            ("note".to_string(), Value::Null)
+        ]
+    );
+
+    let mut delete = b"D".to_vec();
+    delete.extend_from_slice(&7u32.to_be_bytes());
+    delete.push(b'K');
+    delete.extend_from_slice(&2u16.to_be_bytes());
+    delete.push(b't');
+    delete.extend_from_slice(&2u32.to_be_bytes());
+    delete.extend_from_slice(b"42");
+    delete.push(b'n');
+    let change = decoder.decode(Lsn(3), &delete).unwrap().unwrap();
+    assert_eq!(change.kind, ChangeKind::Delete);
+    assert_eq!(change.identity("id"), Some(&Value::Int64(42)));
+
+    assert_eq!(Lsn(0x1_0000_00AB).to_string(), "1/AB");
+    assert_eq!("1/AB".parse::<Lsn>().unwrap(), Lsn(0x1_0000_00AB));
+}
+
+#[tokio::test]
+async fn test_postgres_scram_authentication() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let options = PostgresOptions::new("127.0.0.1", port, "shop", "orders")
+        .with_credentials("fluxus", "secret");
+    let mut source = PostgresCdcSource::new(options);
+
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let len = stream.read_u32().await.unwrap() as usize;
+        let mut startup = vec![0; len - 4];
+        stream.read_exact(&mut startup).await.unwrap();
+        let mut sasl = 10u32.to_be_bytes().to_vec();
+        sasl.extend_from_slice(b"SCRAM-SHA-256\0\0");
+        write_message(&mut stream, b'R', &sasl).await;
+
+        let (tag, body) = read_message(&mut stream).await;
+        assert_eq!(tag, b'p');
+        assert!(body.starts_with(b"SCRAM-SHA-256\0"));
+        let first = String::from_utf8(body[18..].to_vec()).unwrap();
+        assert!(first.starts_with("n,,n=,r="), "{}", first);
+        write_message(
+            &mut stream,
+            b'E',
+            b"SFATAL\0C28P01\0Mpassword authentication failed\0\0",
+        )
+        .await;
+    });
+
+    let error = source.init().await.unwrap_err();
+    assert!(error.to_string().contains("28P01"), "{}", error);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_postgres_refuses_cleartext_password_without_tls() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let options = PostgresOptions::new("127.0.0.1", port, "shop", "orders")
+        .with_ssl_mode(SslMode::Prefer)
+        .with_credentials("fluxus", "secret");
+    let mut source = PostgresCdcSource::new(options);
+
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        // The TLS request, declined
+        assert_eq!(stream.read_u32().await.unwrap(), 8);
+        assert_eq!(stream.read_u32().await.unwrap(), 80_877_103);
+        stream.write_u8(b'N').await.unwrap();
+
+        let len = stream.read_u32().await.unwrap() as usize;
+        let mut startup = vec![0; len - 4];
+        stream.read_exact(&mut startup).await.unwrap();
+        write_message(&mut stream, b'R', &3u32.to_be_bytes()).await;
+        // The password is not sent
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(!rest.windows(6).any(|w| w == b"secret"));
+    });
+
+    assert!(matches!(source.init().await, Err(StreamError::Config(_))));
+    drop(source);
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_postgres_requires_tls() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let options =
+        PostgresOptions::new("127.0.0.1", port, "shop", "orders").with_tls(TlsConfig::new());
+    let mut source = PostgresCdcSource::new(options);
+
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        assert_eq!(stream.read_u32().await.unwrap(), 8);
+        assert_eq!(stream.read_u32().await.unwrap(), 80_877_103);
+        stream.write_u8(b'N').await.unwrap();
+    });
+
+    assert!(matches!(source.init().await, Err(StreamError::Config(_))));
+    server.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_postgres_tls_handshake() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let options =
+        PostgresOptions::new("127.0.0.1", port, "shop", "orders").with_tls(TlsConfig::new());
+    let mut source = PostgresCdcSource::new(options);
+
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        assert_eq!(stream.read_u32().await.unwrap(), 8);
+        assert_eq!(stream.read_u32().await.unwrap(), 80_877_103);
+        stream.write_u8(b'S').await.unwrap();
+        // A TLS handshake starts with a handshake record instead of the
+        // startup message
+        assert_eq!(stream.read_u8().await.unwrap(), 0x16);
+    });
+
+    assert!(source.init().await.is_err());
+    server.await.unwrap();
+}
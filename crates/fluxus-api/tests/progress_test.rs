@@ -0,0 +1,58 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_core::{MetricValue, Metrics, ProgressSource};
+use fluxus_sources::{CsvSource, MmapFileSource, Progress, Source, SourceProgress};
+
+#[test]
+fn test_progress_fraction() {
+    assert_eq!(Progress::new(5, None).fraction(), None);
+    assert_eq!(Progress::new(0, Some(0)).fraction(), Some(1.0));
+    assert_eq!(Progress::new(25, Some(100)).fraction(), Some(0.25));
+}
+
+#[test]
+fn test_progress_gauges() {
+    tokio_test::block_on(async {
+        let mut metrics = Metrics::new();
+        let mut source =
+            ProgressSource::new(CollectionSource::new(1..=4), &mut metrics, "backfill");
+        let gauge = |metrics: &Metrics, name: &str| match metrics.snapshot().get(name) {
+            Some(MetricValue::Gauge(value)) => *value,
+            other => panic!("unexpected metric {}: {:?}", name, other),
+        };
+        assert_eq!(gauge(&metrics, "backfill.progress_percent"), -1);
+
+        source.init().await.unwrap();
+        source.next().await.unwrap();
+        assert_eq!(gauge(&metrics, "backfill.progress_percent"), 25);
+        assert!(source.eta().is_some());
+
+        let sink = CollectionSink::new();
+        DataStream::new(source).sink(sink.clone()).await.unwrap();
+        assert_eq!(sink.get_data(), vec![2, 3, 4]);
+        assert_eq!(gauge(&metrics, "backfill.progress_percent"), 100);
+        assert_eq!(gauge(&metrics, "backfill.eta_secs"), 0);
+    })
+}
+
+#[test]
+fn test_file_source_progress() {
+    tokio_test::block_on(async {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.csv");
+        let content = "a,1\nb,2\nc,3\n";
+        std::fs::write(&path, content).unwrap();
+        let total = content.len() as u64;
+
+        let mut csv = CsvSource::new(&path);
+        csv.init().await.unwrap();
+        while csv.next().await.unwrap().is_some() {}
+        assert_eq!(csv.progress(), Progress::new(total, Some(total)));
+
+        let mut mmap = MmapFileSource::new(&path).with_chunk_size(4);
+        mmap.init().await.unwrap();
+        mmap.next().await.unwrap();
+        assert_eq!(mmap.progress(), Progress::new(4, Some(total)));
+        while mmap.next().await.unwrap().is_some() {}
+        assert_eq!(mmap.progress(), Progress::new(total, Some(total)));
+    });
+}
@@ -0,0 +1,84 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_sources::Source;
+use fluxus_transformers::{ReplaySource, ReplaySpeed};
+use std::time::Duration;
+use tokio::time::Instant;
+
+fn events() -> CollectionSource<&'static str> {
+    CollectionSource::with_timestamps(vec![
+        (10_000, "a"),
+        (11_000, "b"),
+        (11_000, "c"),
+        // Out of order, emitted at once
+        (10_500, "d"),
+        (15_000, "e"),
+    ])
+}
+
+/// Replay a source, returning its elements with the time each was emitted
+async fn replay<S: Source<&'static str>>(mut source: S) -> Vec<(&'static str, Duration)> {
+    source.init().await.unwrap();
+    let start = Instant::now();
+    let mut emitted = Vec::new();
+    let mut timestamps = Vec::new();
+    while let Some(record) = source.next().await.unwrap() {
+        emitted.push((record.data, start.elapsed()));
+        timestamps.push(record.timestamp);
+    }
+    assert_eq!(timestamps, vec![10_000, 11_000, 11_000, 10_500, 15_000]);
+    emitted
+}
+
+fn secs(emitted: &[(&'static str, Duration)]) -> Vec<(&'static str, f64)> {
+    emitted
+        .iter()
+        .map(|(data, at)| (*data, (at.as_secs_f64() * 100.0).round() / 100.0))
+        .collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_replay_realtime() {
+    let emitted = replay(ReplaySource::new(events(), ReplaySpeed::Realtime)).await;
+    assert_eq!(
+        secs(&emitted),
+        vec![("a", 0.0), ("b", 1.0), ("c", 1.0), ("d", 1.0), ("e", 5.0)]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_replay_faster_with_max_gap() {
+    let emitted = replay(ReplaySource::new(events(), ReplaySpeed::Factor(4.0))).await;
+    assert_eq!(
+        secs(&emitted),
+        vec![
+            ("a", 0.0),
+            ("b", 0.25),
+            ("c", 0.25),
+            ("d", 0.25),
+            ("e", 1.25)
+        ]
+    );
+
+    let emitted = replay(
+        ReplaySource::new(events(), ReplaySpeed::Realtime).with_max_gap(Duration::from_secs(2)),
+    )
+    .await;
+    assert_eq!(secs(&emitted)[4], ("e", 3.0));
+
+    let emitted = replay(ReplaySource::new(events(), ReplaySpeed::Unthrottled)).await;
+    assert!(emitted.iter().all(|(_, at)| at.is_zero()));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_datastream_replay() {
+    let sink = CollectionSink::new();
+    let start = Instant::now();
+    DataStream::new(events())
+        .replay(ReplaySpeed::Factor(2.0))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+
+    assert!(start.elapsed() >= Duration::from_millis(2500));
+    assert_eq!(sink.get_data(), vec!["a", "b", "c", "d", "e"]);
+}
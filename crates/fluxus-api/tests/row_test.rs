@@ -0,0 +1,52 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_utils::row::{DataType, Field, Row, Schema, Value};
+use std::sync::Arc;
+
+fn schema() -> Arc<Schema> {
+    Arc::new(Schema::new(vec![
+        Field::new("sensor", DataType::String).required(),
+        Field::new("temperature", DataType::Float64),
+        Field::new("count", DataType::Int64),
+    ]))
+}
+
+#[test]
+fn test_row_from_csv() {
+    tokio_test::block_on(async {
+        let schema = schema();
+        let source = CollectionSource::new(vec!["s1,21.5,3", "s2,,4", "\"s,3\",19,"]);
+        let sink = CollectionSink::new();
+
+        DataStream::new(source)
+            .map(move |line| Row::from_csv_line(schema.clone(), line).unwrap())
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        let data = sink.get_data();
+        assert_eq!(data.len(), 3);
+        assert_eq!(data[0].get_str("sensor"), Some("s1"));
+        assert_eq!(data[0].get_f64("temperature"), Some(21.5));
+        assert_eq!(data[0].get(2), Some(&Value::Int64(3)));
+        assert!(data[1].is_null("temperature"));
+        assert_eq!(data[2].get_str("sensor"), Some("s,3"));
+        assert!(data[2].is_null("count"));
+    })
+}
+
+#[test]
+fn test_row_json_roundtrip() {
+    let schema = schema();
+    let json = serde_json::json!({"sensor": "s1", "temperature": 20.0, "count": 2});
+    let row = Row::from_json(schema.clone(), &json).unwrap();
+    assert_eq!(row.to_json(), json);
+    assert_eq!(serde_json::to_value(&row).unwrap(), json);
+
+    let missing = serde_json::json!({"temperature": 20.0});
+    assert!(Row::from_json(schema.clone(), &missing).is_err());
+
+    let mut row = row;
+    assert!(row.set("count", Value::String("x".into())).is_err());
+    row.set("count", Value::Null).unwrap();
+    assert!(row.is_null("count"));
+}
@@ -0,0 +1,48 @@
+use fluxus_api::{CollectionSink, DataStream};
+use fluxus_sources::{SocketSource, Source};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+
+#[tokio::test]
+async fn test_socket_source_connects() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        stream
+            .write_all(b"hello world\r\nhello stream\n\nlast")
+            .await
+            .unwrap();
+    });
+
+    let sink = CollectionSink::new();
+    DataStream::new(SocketSource::connect(addr.to_string()))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+    assert_eq!(
+        sink.get_data(),
+        vec!["hello world", "hello stream", "", "last"]
+    );
+}
+
+#[tokio::test]
+async fn test_socket_source_listens() {
+    let mut source = SocketSource::listen("127.0.0.1:0").with_delimiter(b';');
+    source.init().await.unwrap();
+    let addr = source.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        for message in [&b"a;b;"[..], b"c;"] {
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.write_all(message).await.unwrap();
+        }
+    });
+
+    // Clients are read one after another
+    let mut lines = Vec::new();
+    for _ in 0..3 {
+        lines.push(source.next().await.unwrap().unwrap().data);
+    }
+    assert_eq!(lines, vec!["a", "b", "c"]);
+}
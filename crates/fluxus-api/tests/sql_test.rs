@@ -0,0 +1,224 @@
+use async_trait::async_trait;
+use fluxus_api::{CollectionSink, DataStream};
+use fluxus_sources::{Source, SqlClient, SqlSource, SqlxClient};
+use fluxus_utils::models::StreamResult;
+use fluxus_utils::row::{DataType, Field, Row, Schema, Value};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A client answering queries with scripted results, recording the queries
+struct ScriptedClient {
+    results: VecDeque<Vec<Row>>,
+    queries: Arc<Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl SqlClient for ScriptedClient {
+    async fn connect(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn query(&mut self, query: &str) -> StreamResult<Vec<Row>> {
+        self.queries.lock().unwrap().push(query.to_string());
+        Ok(self.results.pop_front().unwrap_or_default())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
+
+fn orders(ids: &[i64]) -> Vec<Row> {
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("id", DataType::Int64),
+        Field::new("item", DataType::String),
+    ]));
+    ids.iter()
+        .map(|id| {
+            Row::new(
+                schema.clone(),
+                vec![Value::Int64(*id), Value::String(format!("item-{}", id))],
+            )
+            .unwrap()
+        })
+        .collect()
+}
+
+fn scripted(results: Vec<Vec<Row>>) -> (ScriptedClient, Arc<Mutex<Vec<String>>>) {
+    let queries = Arc::new(Mutex::new(Vec::new()));
+    let client = ScriptedClient {
+        results: results.into(),
+        queries: queries.clone(),
+    };
+    (client, queries)
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+struct Order {
+    id: i64,
+    item: String,
+}
+
+#[tokio::test]
+async fn test_sql_source_runs_query_once() {
+    let (client, queries) = scripted(vec![orders(&[1, 2])]);
+    let sink = CollectionSink::new();
+    DataStream::new(SqlSource::new(client, "SELECT id, item FROM orders").deserialize::<Order>())
+        .sink(sink.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        sink.get_data(),
+        vec![
+            Order {
+                id: 1,
+                item: "item-1".to_string()
+            },
+            Order {
+                id: 2,
+                item: "item-2".to_string()
+            },
+        ]
+    );
+    assert_eq!(
+        *queries.lock().unwrap(),
+        vec!["SELECT id, item FROM orders"]
+    );
+}
+
+#[tokio::test]
+async fn test_sql_source_polls_incrementally() {
+    let (client, queries) = scripted(vec![orders(&[1, 2]), vec![], orders(&[3])]);
+    let mut source = SqlSource::new(client, "SELECT id, item FROM orders;")
+        .incremental("id", Duration::from_millis(10))
+        .with_batch_size(2);
+    source.init().await.unwrap();
+
+    let mut ids = Vec::new();
+    for _ in 0..3 {
+        let row = source.next().await.unwrap().unwrap().data;
+        ids.push(row.get_i64("id").unwrap());
+    }
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert_eq!(source.last_value(), Some(&Value::Int64(3)));
+    assert_eq!(
+        *queries.lock().unwrap(),
+        vec![
+            "SELECT * FROM (SELECT id, item FROM orders) AS fluxus_poll ORDER BY id LIMIT 2",
+            "SELECT * FROM (SELECT id, item FROM orders) AS fluxus_poll WHERE id > 2 ORDER BY id LIMIT 2",
+            "SELECT * FROM (SELECT id, item FROM orders) AS fluxus_poll WHERE id > 2 ORDER BY id LIMIT 2",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_sql_source_resumes_after_timestamp() {
+    let (client, queries) = scripted(vec![]);
+    let mut source = SqlSource::new(client, "SELECT * FROM events")
+        .incremental("updated_at", Duration::from_millis(10))
+        .starting_after(Value::Timestamp(1_714_564_800_123));
+    source.init().await.unwrap();
+    let _ = tokio::time::timeout(Duration::from_millis(50), source.next()).await;
+
+    assert_eq!(
+        queries.lock().unwrap()[0],
+        "SELECT * FROM (SELECT * FROM events) AS fluxus_poll \
+         WHERE updated_at > '2024-05-01 12:00:00.123' ORDER BY updated_at LIMIT 1000"
+    );
+}
+
+/// A SQLite database in a temporary directory, created with the statements
+async fn sqlite(dir: &tempfile::TempDir, statements: &[&str]) -> String {
+    let url = format!("sqlite://{}?mode=rwc", dir.path().join("shop.db").display());
+    let mut client = SqlxClient::new(&url);
+    client.connect().await.unwrap();
+    for statement in statements {
+        client.query(statement).await.unwrap();
+    }
+    client.close().await.unwrap();
+    url
+}
+
+#[tokio::test]
+async fn test_sqlx_client_types_sqlite_rows() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = sqlite(
+        &dir,
+        &[
+            "CREATE TABLE orders (id INTEGER, item TEXT, price REAL, paid BOOLEAN, created_at DATETIME)",
+            "INSERT INTO orders VALUES (7, 'book', 12.5, TRUE, '2024-05-01 12:00:00.123')",
+            "INSERT INTO orders VALUES (8, NULL, 3, NULL, NULL)",
+        ],
+    )
+    .await;
+
+    let sink = CollectionSink::new();
+    DataStream::new(SqlSource::new(
+        SqlxClient::new(url),
+        "SELECT * FROM orders ORDER BY id",
+    ))
+    .sink(sink.clone())
+    .await
+    .unwrap();
+
+    let rows = sink.get_data();
+    assert_eq!(
+        rows[0].values(),
+        [
+            Value::Int64(7),
+            Value::String("book".to_string()),
+            Value::Float64(12.5),
+            Value::Boolean(true),
+            Value::Timestamp(1_714_564_800_123),
+        ]
+    );
+    assert!(rows[1].is_null("item"));
+    assert!(rows[1].is_null("paid"));
+    assert!(rows[1].is_null("created_at"));
+}
+
+#[tokio::test]
+async fn test_sqlx_client_polls_sqlite_incrementally() {
+    let dir = tempfile::tempdir().unwrap();
+    let url = sqlite(
+        &dir,
+        &[
+            "CREATE TABLE events (name TEXT, updated_at DATETIME)",
+            "INSERT INTO events VALUES ('old', '2024-05-01 12:00:00.000')",
+            "INSERT INTO events VALUES ('new', '2024-05-01 12:00:00.124')",
+        ],
+    )
+    .await;
+
+    let mut source = SqlSource::new(SqlxClient::new(url), "SELECT * FROM events")
+        .incremental("updated_at", Duration::from_millis(10))
+        .starting_after(Value::Timestamp(1_714_564_800_123));
+    source.init().await.unwrap();
+    let row = source.next().await.unwrap().unwrap().data;
+    source.close().await.unwrap();
+
+    assert_eq!(
+        row.get_by_name("name"),
+        Some(&Value::String("new".to_string()))
+    );
+    assert_eq!(
+        source.last_value(),
+        Some(&Value::Timestamp(1_714_564_800_124))
+    );
+}
+
+#[test]
+fn test_sqlx_client_escapes_mysql_backslashes() {
+    let value = Value::String(r"it's C:\".to_string());
+    assert_eq!(
+        SqlxClient::new("mysql://localhost/shop").literal(&value),
+        r"'it''s C:\\'"
+    );
+    assert_eq!(
+        SqlxClient::new("postgres://localhost/shop").literal(&value),
+        r"'it''s C:\'"
+    );
+}
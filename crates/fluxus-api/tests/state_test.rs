@@ -0,0 +1,30 @@
+use fluxus_runtime::state::KeyedStateBackend;
+use std::thread;
+
+#[test]
+fn test_keyed_state_parallel_updates() {
+    let state = KeyedStateBackend::<u64, u64>::with_shards(8);
+
+    let workers: Vec<_> = (0..4u64)
+        .map(|worker| {
+            // Clones share the same state
+            let state = state.clone();
+            thread::spawn(move || {
+                // Each worker owns a disjoint range of keys
+                for i in 0..1000 {
+                    let key = worker * 100 + i % 100;
+                    state.update_with(key, || 0, |count| *count += 1);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    assert_eq!(state.len(), 400);
+    assert!(state.entries().iter().all(|(_, count)| *count == 10));
+    assert_eq!(state.remove(&0), Some(10));
+    assert!(!state.contains_key(&0));
+    assert_eq!(state.get(&399), Some(10));
+}
@@ -0,0 +1,90 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_sinks::StdoutSink;
+use fluxus_sources::StdinSource;
+use fluxus_utils::models::Record;
+use serde::Serialize;
+use tokio::io::AsyncReadExt;
+
+#[tokio::test]
+async fn test_stdin_source_reads_lines() {
+    let input: &[u8] = b"first\r\nsecond\n\n  \nlast";
+    let sink = CollectionSink::new();
+    DataStream::new(StdinSource::from_reader(input))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+    assert_eq!(sink.get_data(), vec!["first", "second", "", "  ", "last"]);
+
+    let sink = CollectionSink::new();
+    DataStream::new(StdinSource::from_reader(input).skip_empty_lines())
+        .map(|line| line.to_uppercase())
+        .sink(sink.clone())
+        .await
+        .unwrap();
+    assert_eq!(sink.get_data(), vec!["FIRST", "SECOND", "LAST"]);
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct Click {
+    user: String,
+    page: String,
+}
+
+#[tokio::test]
+async fn test_stdout_sink_writes_lines() {
+    let (writer, mut reader) = tokio::io::duplex(1 << 16);
+    DataStream::new(CollectionSource::new(vec![1, 2, 3]))
+        .map(|n| n * 10)
+        .sink(StdoutSink::lines().to_writer(writer))
+        .await
+        .unwrap();
+
+    let mut output = String::new();
+    reader.read_to_string(&mut output).await.unwrap();
+    assert_eq!(output, "10\n20\n30\n");
+}
+
+#[tokio::test]
+async fn test_stdout_sink_writes_json() {
+    let clicks = vec![
+        Click {
+            user: "ann".to_string(),
+            page: "/home".to_string(),
+        },
+        Click {
+            user: "bob".to_string(),
+            page: "/cart".to_string(),
+        },
+    ];
+    let (writer, mut reader) = tokio::io::duplex(1 << 16);
+    DataStream::new(CollectionSource::new(clicks))
+        .sink(StdoutSink::json().to_writer(writer))
+        .await
+        .unwrap();
+
+    let mut output = String::new();
+    reader.read_to_string(&mut output).await.unwrap();
+    assert_eq!(
+        output,
+        "{\"user\":\"ann\",\"page\":\"/home\"}\n{\"user\":\"bob\",\"page\":\"/cart\"}\n"
+    );
+}
+
+#[tokio::test]
+async fn test_stdout_sink_timestamps_and_closed_output() {
+    use fluxus_sinks::Sink;
+
+    let (writer, mut reader) = tokio::io::duplex(1 << 16);
+    let mut sink = StdoutSink::json_with_timestamps().to_writer(writer);
+    sink.write(Record::with_timestamp("a", 5)).await.unwrap();
+    sink.flush().await.unwrap();
+    let mut buf = [0u8; 64];
+    let n = reader.read(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"{\"data\":\"a\",\"timestamp\":5}\n");
+
+    // The reader exits, as `head` does: later records are dropped
+    drop(reader);
+    sink.write(Record::with_timestamp("b", 6)).await.unwrap();
+    sink.flush().await.unwrap();
+    sink.close().await.unwrap();
+}
@@ -0,0 +1,114 @@
+use fluxus_sources::syslog::Severity;
+use fluxus_sources::{Source, SyslogRecord, SyslogSource};
+use tokio::net::UdpSocket;
+
+#[test]
+fn test_parse_rfc5424() {
+    let record = SyslogRecord::parse(
+        "<34>1 2003-10-11T22:14:15.003Z mymachine.example.com su - ID47 - \u{feff}'su root' failed for lonvick on /dev/pts/8",
+    );
+    assert_eq!(record.facility, 4);
+    assert_eq!(record.severity, Severity::Critical);
+    assert_eq!(record.timestamp, Some(1_065_910_455_003));
+    assert_eq!(record.hostname.as_deref(), Some("mymachine.example.com"));
+    assert_eq!(record.app_name.as_deref(), Some("su"));
+    assert_eq!(record.proc_id, None);
+    assert_eq!(record.msg_id.as_deref(), Some("ID47"));
+    assert_eq!(record.structured_data, None);
+    assert_eq!(record.message, "'su root' failed for lonvick on /dev/pts/8");
+
+    let record = SyslogRecord::parse(
+        "<165>1 2003-08-24T05:14:15.000003-07:00 192.0.2.1 myproc 8710 - - %% It's time to make the do-nuts.",
+    );
+    assert_eq!(record.facility, 20);
+    assert_eq!(record.severity, Severity::Notice);
+    assert_eq!(record.timestamp, Some(1_061_727_255_000));
+    assert_eq!(record.proc_id.as_deref(), Some("8710"));
+    assert_eq!(record.message, "%% It's time to make the do-nuts.");
+}
+
+#[test]
+fn test_parse_rfc5424_structured_data() {
+    let record = SyslogRecord::parse(
+        r#"<165>1 2003-10-11T22:14:15.003Z mymachine.example.com evntslog - ID47 [exampleSDID@32473 iut="3" eventSource="Appli]cation" eventID="1011"][examplePriority@32473 class="high"] An application event log entry..."#,
+    );
+    assert_eq!(
+        record.structured_data.as_deref(),
+        Some(
+            r#"[exampleSDID@32473 iut="3" eventSource="Appli]cation" eventID="1011"][examplePriority@32473 class="high"]"#
+        )
+    );
+    assert_eq!(record.message, "An application event log entry...");
+
+    let record = SyslogRecord::parse("<13>1 - - - - - [meta a=\"\\\"]\"]");
+    assert_eq!(record.timestamp, None);
+    assert_eq!(record.hostname, None);
+    assert_eq!(
+        record.structured_data.as_deref(),
+        Some("[meta a=\"\\\"]\"]")
+    );
+    assert_eq!(record.message, "");
+}
+
+#[test]
+fn test_parse_rfc3164() {
+    let record =
+        SyslogRecord::parse("<34>Oct 11 22:14:15 mymachine su: 'su root' failed for lonvick\n");
+    assert_eq!(record.severity, Severity::Critical);
+    assert!(record.timestamp.is_some());
+    assert_eq!(record.hostname.as_deref(), Some("mymachine"));
+    assert_eq!(record.app_name.as_deref(), Some("su"));
+    assert_eq!(record.message, "'su root' failed for lonvick");
+
+    let record = SyslogRecord::parse("<13>Feb  5 17:32:18 10.0.0.99 sshd[1234]: Accepted");
+    assert_eq!(record.hostname.as_deref(), Some("10.0.0.99"));
+    assert_eq!(record.app_name.as_deref(), Some("sshd"));
+    assert_eq!(record.proc_id.as_deref(), Some("1234"));
+    assert_eq!(record.message, "Accepted");
+
+    // Two timestamps of the same year are a day apart
+    let earlier = SyslogRecord::parse("<13>Mar  1 00:00:00 host a: x").timestamp;
+    let later = SyslogRecord::parse("<13>Mar  2 00:00:00 host a: x").timestamp;
+    assert_eq!(later.unwrap() - earlier.unwrap(), 86_400_000);
+}
+
+#[test]
+fn test_parse_without_header() {
+    let record = SyslogRecord::parse("plain message");
+    assert_eq!(record.facility, 1);
+    assert_eq!(record.severity, Severity::Notice);
+    assert_eq!(record.message, "plain message");
+
+    let record = SyslogRecord::parse("<11>no timestamp here");
+    assert_eq!(record.severity, Severity::Error);
+    assert!(record.severity.is_error());
+    assert_eq!(record.timestamp, None);
+    assert_eq!(record.message, "no timestamp here");
+}
+
+#[tokio::test]
+async fn test_syslog_source_receives_datagrams() {
+    let mut source = SyslogSource::bind("127.0.0.1:0");
+    source.init().await.unwrap();
+    let addr = source.local_addr().unwrap();
+
+    let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    client
+        .send_to(
+            b"<11>1 2003-10-11T22:14:15.003Z host app 42 - - failed latency_ms=1500",
+            addr,
+        )
+        .await
+        .unwrap();
+    client.send_to(b"<14>no header", addr).await.unwrap();
+
+    let record = source.next().await.unwrap().unwrap();
+    assert_eq!(record.timestamp, 1_065_910_455_003);
+    assert_eq!(record.data.app_name.as_deref(), Some("app"));
+    assert_eq!(record.data.peer, Some(client.local_addr().unwrap()));
+
+    let record = source.next().await.unwrap().unwrap();
+    assert_eq!(record.data.severity, Severity::Informational);
+    assert_eq!(record.data.message, "no header");
+    source.close().await.unwrap();
+}
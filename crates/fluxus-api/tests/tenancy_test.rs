@@ -0,0 +1,90 @@
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_core::MetricValue;
+use fluxus_runtime::tenancy::{TenantQuota, TenantQuotas, TenantStateBackend, Tenanted};
+use fluxus_utils::memory::{MemoryAccount, MemorySize};
+
+#[test]
+fn test_tenant_state_isolation() {
+    let state = TenantStateBackend::new();
+    state.set("acme", "count", 1);
+    state.set("globex", "count", 2);
+
+    assert_eq!(state.get("acme", &"count"), Some(1));
+    assert_eq!(state.get("globex", &"count"), Some(2));
+    assert_eq!(state.get("initech", &"count"), None);
+
+    state.clear_tenant("acme");
+    assert!(state.is_empty("acme"));
+    assert_eq!(state.len("globex"), 1);
+}
+
+#[test]
+fn test_tenant_state_memory_accounting() {
+    let account = MemoryAccount::new();
+    let state = TenantStateBackend::<u64, u64>::with_shards(8).with_memory_account(account.clone());
+    let entry = |tenant: &str| (tenant.to_string(), 0u64).memory_size() + 0u64.memory_size();
+
+    state.set("acme", 1, 10);
+    state.update_with("acme", 2, || 0, |count| *count += 1);
+    state.set("globex", 1, 20);
+    assert_eq!(state.get("acme", &2), Some(1));
+    assert_eq!(
+        account.bytes(),
+        (2 * entry("acme") + entry("globex")) as i64
+    );
+
+    // Dropping a tenant releases the memory of its keys only
+    state.clear_tenant("acme");
+    assert_eq!(account.bytes(), entry("globex") as i64);
+    assert_eq!(state.remove("globex", &1), Some(20));
+    assert_eq!(account.bytes(), 0);
+}
+
+#[test]
+fn test_enforce_record_quotas() {
+    tokio_test::block_on(async {
+        let events = vec![
+            ("acme", 1),
+            ("globex", 2),
+            ("acme", 3),
+            ("acme", 4),
+            ("globex", 5),
+        ];
+        let quotas = TenantQuotas::new(TenantQuota::unlimited())
+            .with_tenant("acme", TenantQuota::unlimited().with_max_records(2));
+        let sink = CollectionSink::new();
+
+        DataStream::new(CollectionSource::new(events))
+            .with_tenant(|(tenant, _)| tenant.to_string())
+            .enforce_quotas(quotas.clone())
+            .map(|record: Tenanted<(&str, i32)>| record.data.1)
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(sink.get_data(), vec![1, 2, 3, 5]);
+
+        let metrics = quotas.metrics();
+        assert!(matches!(
+            metrics["tenant.acme.accepted"],
+            MetricValue::Counter(2)
+        ));
+        assert!(matches!(
+            metrics["tenant.acme.rejected"],
+            MetricValue::Counter(1)
+        ));
+        assert!(matches!(
+            metrics["tenant.globex.rejected"],
+            MetricValue::Counter(0)
+        ));
+    })
+}
+
+#[test]
+fn test_rate_quota() {
+    let quotas = TenantQuotas::new(TenantQuota::unlimited().with_max_records_per_sec(2));
+    assert!(quotas.admit("acme"));
+    assert!(quotas.admit("acme"));
+    assert!(!quotas.admit("acme"));
+    assert!(quotas.admit("globex"));
+}
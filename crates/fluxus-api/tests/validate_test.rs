@@ -0,0 +1,74 @@
+use fluxus_api::operators::{Rule, RuleSet};
+use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+use fluxus_core::MetricValue;
+use fluxus_utils::row::{DataType, Field, Row, Schema, Value};
+use std::sync::Arc;
+
+#[test]
+fn test_validate_tags_violations() {
+    tokio_test::block_on(async {
+        let rules = RuleSet::new()
+            .rule(Rule::range("positive", |x: &i32| *x, 0, 100))
+            .rule(Rule::one_of("even", |x: &i32| x % 2, [0]));
+        let source = CollectionSource::new(vec![2, -4, 3, 101]);
+        let sink = CollectionSink::new();
+
+        DataStream::new(source)
+            .validate(rules.clone())
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        let data = sink.get_data();
+        assert!(data[0].is_valid());
+        assert_eq!(data[1].violations, vec!["positive"]);
+        assert_eq!(data[2].violations, vec!["even"]);
+        assert_eq!(data[3].violations, vec!["positive", "even"]);
+
+        let metrics = rules.metrics();
+        assert!(matches!(
+            metrics["violations.positive"],
+            MetricValue::Counter(2)
+        ));
+        assert!(matches!(
+            metrics["violations.even"],
+            MetricValue::Counter(2)
+        ));
+    })
+}
+
+#[test]
+fn test_validate_or_quarantine() {
+    tokio_test::block_on(async {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::String),
+            Field::new("country", DataType::String),
+        ]));
+        let row = |id: &str, country: &str| {
+            Row::from_csv_line(schema.clone(), &format!("{},{}", id, country)).unwrap()
+        };
+        let rules = vec![
+            Rule::field_not_null("id"),
+            Rule::field_regex("id", "^[a-z]+[0-9]*$").unwrap(),
+            Rule::field_in("country", [Value::String("NL".into())]),
+        ];
+        let source = CollectionSource::new(vec![row("a1", "NL"), row("", "NL"), row("b2", "US")]);
+        let sink = CollectionSink::new();
+        let quarantine = CollectionSink::new();
+
+        DataStream::new(source)
+            .validate_or_quarantine(rules, quarantine.clone())
+            .sink(sink.clone())
+            .await
+            .unwrap();
+
+        let data = sink.get_data();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].get_str("id"), Some("a1"));
+
+        let rejected = quarantine.get_data();
+        assert_eq!(rejected.len(), 2);
+        assert_eq!(rejected[0].violations, vec!["id_not_null", "id_regex"]);
+        assert_eq!(rejected[1].violations, vec!["country_in"]);
+    })
+}
@@ -0,0 +1,77 @@
+use async_trait::async_trait;
+use fluxus_api::{CollectionSink, CollectionSource};
+use fluxus_core::ParallelConfig;
+use fluxus_runtime::RuntimeContext;
+use fluxus_runtime::watchdog::{Watchdog, WatchdogAction, WatchdogConfig};
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+struct HangingOperator;
+
+#[async_trait]
+impl Operator<i32, i32> for HangingOperator {
+    fn name(&self) -> &str {
+        "hanging"
+    }
+
+    async fn process(&mut self, record: Record<i32>) -> StreamResult<Vec<Record<i32>>> {
+        if record.data == 2 {
+            std::future::pending::<()>().await;
+        }
+        Ok(vec![record])
+    }
+}
+
+#[test]
+fn test_watchdog_reports_stalled_call() {
+    tokio_test::block_on(async {
+        let watchdog = Watchdog::new(WatchdogConfig::new(Duration::from_millis(20)));
+        let busy = watchdog.track("busy");
+        let idle = watchdog.track("idle");
+
+        let guard = busy.enter();
+        drop(idle.enter());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        let stalls = watchdog.check();
+        assert_eq!(stalls.len(), 1);
+        assert_eq!(stalls[0].name, "busy");
+        assert!(stalls[0].elapsed >= Duration::from_millis(20));
+
+        drop(guard);
+        assert!(watchdog.check().is_empty());
+    })
+}
+
+#[test]
+fn test_watchdog_aborts_stuck_pipeline() {
+    tokio_test::block_on(async {
+        let config = WatchdogConfig::new(Duration::from_millis(50))
+            .with_check_interval(Duration::from_millis(10))
+            .with_action(WatchdogAction::Abort);
+        let runtime = RuntimeContext::new(ParallelConfig::new(1, 16, true)).with_watchdog(config);
+        let sink = CollectionSink::new();
+        let operators: Vec<Arc<Mutex<dyn Operator<i32, i32> + Send + Sync>>> =
+            vec![Arc::new(Mutex::new(HangingOperator))];
+
+        let job = runtime
+            .execute_pipeline(
+                CollectionSource::new(vec![1, 2, 3]),
+                operators,
+                sink.clone(),
+            )
+            .await
+            .unwrap();
+
+        let result = tokio::time::timeout(Duration::from_secs(5), job.await_completion())
+            .await
+            .expect("watchdog should abort the stuck pipeline");
+        assert!(matches!(result, Err(StreamError::Runtime(msg)) if msg.contains("aborted")));
+        assert!(runtime.is_finished());
+
+        assert_eq!(sink.get_data(), vec![1]);
+    })
+}
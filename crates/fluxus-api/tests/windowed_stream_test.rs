@@ -1,11 +1,46 @@
 #[cfg(test)]
 mod tests {
     use async_trait::async_trait;
-    use fluxus_api::operators::SortOrder;
+    use fluxus_api::operators::{
+        AggregateFunction, AssignedWindowAggregator, CountTrigger, DeltaTrigger, EventTimeTrigger,
+        FlushSignal, OrTrigger, PunctuationTrigger, PurgingTrigger, SortOrder, WindowAggregator,
+        WindowSorter,
+    };
     use fluxus_api::{CollectionSink, CollectionSource, DataStream};
+    use fluxus_sinks::{FileSink, file::FileFormat};
     use fluxus_sources::Source;
+    use fluxus_transformers::Operator;
     use fluxus_utils::models::Record;
-    use fluxus_utils::{models::StreamResult, window::WindowConfig};
+    use fluxus_utils::models::{StreamError, StreamResult};
+    use fluxus_utils::window::{Window, WindowConfig, WindowedValue};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_reduce() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::new(vec![3, 1, 4, 1, 5]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::global())
+                .reduce(|a, b| a.max(b))
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![5]);
+
+            // One result per window, once it closes
+            let source =
+                CollectionSource::with_timestamps(vec![(0, 3), (4, 1), (12, 4), (15, 1), (21, 5)]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(10)))
+                .reduce(|a, b| a + b)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![4, 5, 5]);
+        })
+    }
 
     #[test]
     fn test_any() {
@@ -19,11 +54,11 @@ mod tests {
                 .await
                 .unwrap();
             let data = sink.get_data();
-            assert_eq!(data[0], false);
-            assert_eq!(data[1], true);
-            assert_eq!(data[2], true);
-            assert_eq!(data[3], true);
-            assert_eq!(data[4], true);
+            assert!(!data[0]);
+            assert!(data[1]);
+            assert!(data[2]);
+            assert!(data[3]);
+            assert!(data[4]);
         })
     }
 
@@ -39,11 +74,11 @@ mod tests {
                 .await
                 .unwrap();
             let data = sink.get_data();
-            assert_eq!(data[0], false);
-            assert_eq!(data[1], false);
-            assert_eq!(data[2], false);
-            assert_eq!(data[3], false);
-            assert_eq!(data[4], false);
+            assert!(!data[0]);
+            assert!(!data[1]);
+            assert!(!data[2]);
+            assert!(!data[3]);
+            assert!(!data[4]);
         })
     }
 
@@ -181,6 +216,7 @@ mod tests {
             let sink = CollectionSink::new();
             DataStream::new(source)
                 .window(WindowConfig::global())
+                .emit_partial()
                 .distinct()
                 .sink(sink.clone())
                 .await
@@ -196,6 +232,7 @@ mod tests {
             let sink = CollectionSink::new();
             DataStream::new(source)
                 .window(WindowConfig::global())
+                .emit_partial()
                 .distinct_by_key(|s| s.as_bytes()[0])
                 .sink(sink.clone())
                 .await
@@ -214,6 +251,7 @@ mod tests {
             let sink = CollectionSink::new();
             DataStream::new(source)
                 .window(WindowConfig::global())
+                .emit_partial()
                 .top_k(3)
                 .sink(sink.clone())
                 .await
@@ -230,6 +268,7 @@ mod tests {
             let sink = CollectionSink::new();
             DataStream::new(source)
                 .window(WindowConfig::global())
+                .emit_partial()
                 .top_k_by_key(3, |s| s.as_bytes()[0])
                 .sink(sink.clone())
                 .await
@@ -265,4 +304,1128 @@ mod tests {
             assert_eq!(data[4], vec![3, 4, 5]);
         })
     }
+
+    #[test]
+    fn test_aggregate_map_accumulator() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::new(vec!["a", "b", "a", "c", "a"]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::global())
+                .aggregate(HashMap::new(), |mut counts, word| {
+                    *counts.entry(word).or_insert(0) += 1;
+                    counts
+                })
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            let last = sink.get_last_element().unwrap();
+            assert_eq!(last.len(), 3);
+            assert_eq!(last["a"], 3);
+            assert_eq!(last["b"], 1);
+            assert_eq!(last["c"], 1);
+        })
+    }
+
+    #[test]
+    fn test_coalesce_window_updates() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (0, 1),
+                (3, 2),
+                (12, 3),
+                (5, 4),
+                (14, 5),
+                (25, 6),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(10)))
+                .emit_partial()
+                .coalesce(std::time::Duration::from_secs(60))
+                .sum_by(|x: &i32| *x)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            // One final sum per window instead of one update per element
+            assert_eq!(sink.get_data(), vec![7, 8, 6]);
+        })
+    }
+
+    #[test]
+    fn test_aggregate_changelog() {
+        use fluxus_utils::models::{Change, ChangeKind};
+
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (1, 1),
+                (12, 2),
+                // Late for window 0, still within the allowed lateness
+                (5, 3),
+                (40, 4),
+                // Beyond the allowed lateness
+                (2, 5),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(
+                    WindowConfig::tumbling(std::time::Duration::from_millis(10))
+                        .with_lateness(std::time::Duration::from_millis(10)),
+                )
+                .aggregate_changelog(0, |sum, x| sum + x)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            assert_eq!(
+                sink.get_data(),
+                vec![
+                    Change::new(ChangeKind::Insert, 0, 1),
+                    Change::new(ChangeKind::Insert, 10, 2),
+                    Change::new(ChangeKind::UpdateBefore, 0, 1),
+                    Change::new(ChangeKind::UpdateAfter, 0, 4),
+                    Change::new(ChangeKind::Insert, 40, 4),
+                ]
+            );
+        })
+    }
+
+    #[test]
+    fn test_emit_on_window_close() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (0, 1),
+                (3, 2),
+                (12, 3),
+                // Window 0 closed when the element at 12 arrived
+                (5, 4),
+                (14, 5),
+                (25, 6),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(10)))
+                .sum_by(|x: &i32| *x)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            // Each window is emitted once, the last one when the input ends
+            assert_eq!(sink.get_data(), vec![3, 8, 6]);
+
+            // The allowed lateness keeps window 0 open for the late element
+            // after its first result
+            let source = CollectionSource::with_timestamps(vec![(0, 1), (3, 2), (12, 3), (5, 4)]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(
+                    WindowConfig::tumbling(std::time::Duration::from_millis(10))
+                        .with_lateness(std::time::Duration::from_millis(5)),
+                )
+                .count()
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![2, 3, 1]);
+        })
+    }
+
+    #[test]
+    fn test_late_records_to_side_output() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (1, "a"),
+                (12, "b"),
+                // Within the allowed lateness of window 0
+                (8, "c"),
+                (25, "d"),
+                // Window 0 can no longer change
+                (9, "e"),
+            ]);
+            let sink = CollectionSink::new();
+            let late = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(10)))
+                .allowed_lateness(std::time::Duration::from_millis(5))
+                .late_records_to(late.clone())
+                .aggregate(String::new(), |acc, s| acc + s)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            let expected: Vec<String> = ["a", "ac", "b", "d"].map(String::from).to_vec();
+            assert_eq!(sink.get_data(), expected);
+            assert_eq!(late.get_data(), vec!["e"]);
+        })
+    }
+
+    #[test]
+    fn test_trigger() {
+        tokio_test::block_on(async {
+            let elements =
+                || CollectionSource::with_timestamps(vec![(0, 1), (1, 2), (2, 3), (3, 4), (12, 5)]);
+            let tumbling = || WindowConfig::tumbling(std::time::Duration::from_millis(10));
+
+            // Early results every two elements, and the final result when the window ends
+            let sink = CollectionSink::new();
+            DataStream::new(elements())
+                .window(tumbling())
+                .trigger(OrTrigger::new(EventTimeTrigger, CountTrigger::of(2)))
+                .sum_by(|x: &i32| *x)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![3, 10, 10, 5]);
+
+            // Purging starts the window over after every firing
+            let sink = CollectionSink::new();
+            DataStream::new(elements())
+                .window(tumbling())
+                .trigger(PurgingTrigger::new(CountTrigger::of(2)))
+                .sum_by(|x: &i32| *x)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![3, 7]);
+
+            // Fire when an element moves more than 1.5 away from the last firing
+            let sink = CollectionSink::new();
+            DataStream::new(elements())
+                .window(tumbling())
+                .trigger(DeltaTrigger::new(1.5, |x: &i32| *x as f64))
+                .count()
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![3]);
+        })
+    }
+
+    #[test]
+    fn test_group_by() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (0, ("b", 1)),
+                (1, ("a", 2)),
+                (2, ("b", 3)),
+                (12, ("a", 4)),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(10)))
+                .group_by(|(key, _)| *key)
+                .sum_by(|(_, value)| *value)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            // One record per key of each window, in the order the keys appeared
+            assert_eq!(sink.get_data(), vec![("b", 4), ("a", 2), ("a", 4)]);
+        })
+    }
+
+    #[test]
+    fn test_aggregate_with_merge() {
+        tokio_test::block_on(async {
+            let elements: Vec<(i64, i64)> = (0..100).map(|i| (i, i)).collect();
+            let sink = CollectionSink::new();
+            DataStream::new(CollectionSource::with_timestamps(elements))
+                .parallel(4)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(25)))
+                .aggregate_with_merge(
+                    (0, 0),
+                    |(count, sum), x| (count + 1, sum + x),
+                    |a, b| (a.0 + b.0, a.1 + b.1),
+                )
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            // Each window is emitted once with the partials of all tasks merged
+            assert_eq!(
+                sink.get_data(),
+                vec![(25, 300), (25, 925), (25, 1550), (25, 2175)]
+            );
+        })
+    }
+
+    #[test]
+    fn test_aggregate_with_merge_partitioning() {
+        tokio_test::block_on(async {
+            let stream = || {
+                let elements: Vec<(i64, i64)> = (0..100).map(|i| (i, i)).collect();
+                DataStream::new(CollectionSource::with_timestamps(elements)).parallel(4)
+            };
+            // Each partial holds the keys its task saw, a key being four
+            // consecutive elements
+            let keys_per_task = |stream: DataStream<i64>| async move {
+                let sink = CollectionSink::new();
+                stream
+                    .window(WindowConfig::tumbling(std::time::Duration::from_millis(25)))
+                    .aggregate_with_merge(
+                        vec![std::collections::BTreeSet::new()],
+                        |mut partials, x| {
+                            partials[0].insert(x / 4 % 2);
+                            partials
+                        },
+                        |mut a, b| {
+                            a.extend(b);
+                            a
+                        },
+                    )
+                    .sink(sink.clone())
+                    .await
+                    .unwrap();
+                sink.get_data()
+            };
+
+            let windows = keys_per_task(stream().partition_by(|x| (x / 4 % 2) as u64)).await;
+            assert_eq!(windows.len(), 4);
+            assert!(windows.iter().flatten().all(|keys| keys.len() == 1));
+
+            let windows = keys_per_task(stream().rebalance()).await;
+            assert!(windows.iter().flatten().any(|keys| keys.len() == 2));
+
+            // The stream is read by one task, so rescale hands out to all tasks
+            let windows = keys_per_task(stream().rescale()).await;
+            assert!(windows.iter().all(|partials| partials.len() == 4));
+        })
+    }
+
+    #[test]
+    fn test_keyed_window() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (0, ("b", 1)),
+                (1, ("a", 2)),
+                (2, ("b", 3)),
+                (12, ("a", 4)),
+                // Window 0 ended, but its lateness keeps it open for "a"
+                (5, ("a", 5)),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .key_by(|(key, _): &(&str, i32)| *key)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(10)))
+                .allowed_lateness(std::time::Duration::from_millis(5))
+                .sum_by(|(_, value)| *value)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            assert_eq!(
+                sink.get_data(),
+                vec![("b", 4), ("a", 2), ("a", 7), ("a", 4)]
+            );
+        })
+    }
+
+    #[test]
+    fn test_sliding_window_overlap() {
+        tokio_test::block_on(async {
+            let source =
+                CollectionSource::with_timestamps(vec![(2, 2), (6, 6), (11, 11), (14, 14)]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::sliding(
+                    std::time::Duration::from_millis(10),
+                    std::time::Duration::from_millis(5),
+                ))
+                .aggregate(Vec::new(), |mut values, value| {
+                    values.push(value);
+                    values
+                })
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            // Every record is in both windows that cover it: [-5, 5), [0, 10),
+            // [5, 15) and [10, 20)
+            assert_eq!(
+                sink.get_data(),
+                vec![vec![2], vec![2, 6], vec![6, 11, 14], vec![11, 14]]
+            );
+        })
+    }
+
+    #[test]
+    fn test_sliding_window_order() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![(2, 2), (7, 7)]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(
+                    WindowConfig::sliding(
+                        std::time::Duration::from_millis(10),
+                        std::time::Duration::from_millis(5),
+                    )
+                    .with_watermark_delay(std::time::Duration::from_millis(100)),
+                )
+                .aggregate(Vec::new(), |mut values, value| {
+                    values.push(value);
+                    values
+                })
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            // The window starting before the epoch ends first
+            assert_eq!(sink.get_data(), vec![vec![2], vec![2, 7], vec![7]]);
+        })
+    }
+
+    #[test]
+    fn test_keyed_sliding_window() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (-3, ("a", 1)),
+                (4, ("b", 2)),
+                (8, ("a", 3)),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .key_by(|(key, _): &(&str, i32)| *key)
+                .window(WindowConfig::sliding(
+                    std::time::Duration::from_millis(10),
+                    std::time::Duration::from_millis(5),
+                ))
+                .sum_by(|(_, value)| *value)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            // Windows [-10, 0), [-5, 5), [0, 10) and [5, 15)
+            assert_eq!(
+                sink.get_data(),
+                vec![("a", 1), ("a", 1), ("b", 2), ("b", 2), ("a", 3), ("a", 3)]
+            );
+        })
+    }
+
+    #[test]
+    fn test_aggregate_windowed() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![(1, 1), (4, 2), (12, 3)]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(10)))
+                .aggregate_windowed(0, |sum, x| sum + x)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            assert_eq!(
+                sink.get_data(),
+                vec![
+                    WindowedValue {
+                        start: Some(0),
+                        end: Some(10),
+                        value: 3,
+                        is_final: true,
+                    },
+                    WindowedValue {
+                        start: Some(10),
+                        end: Some(20),
+                        value: 3,
+                        is_final: true,
+                    },
+                ]
+            );
+        })
+    }
+
+    #[test]
+    fn test_keyed_sessions() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (0, ("a", 1)),
+                (3, ("b", 2)),
+                (5, ("a", 3)),
+                // Both sessions were inactive for the gap
+                (30, ("a", 4)),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .key_by(|(key, _): &(&str, i32)| *key)
+                .window(WindowConfig::session(std::time::Duration::from_millis(10)))
+                .sum_by(|(_, value)| *value)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            assert_eq!(sink.get_data(), vec![("b", 2), ("a", 4), ("a", 4)]);
+        })
+    }
+
+    #[test]
+    fn test_keyed_session_limits() {
+        tokio_test::block_on(async {
+            // A key that never goes quiet
+            let bot = (0..7).map(|i| (i, ("bot", 1)));
+            let sink = CollectionSink::new();
+            DataStream::new(CollectionSource::with_timestamps(bot.clone()))
+                .key_by(|(key, _): &(&str, i32)| *key)
+                .window(WindowConfig::session(std::time::Duration::from_millis(10)))
+                .max_session_size(3)
+                .count()
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![("bot", 3), ("bot", 3), ("bot", 1)]);
+
+            let sink = CollectionSink::new();
+            DataStream::new(CollectionSource::with_timestamps(bot))
+                .key_by(|(key, _): &(&str, i32)| *key)
+                .window(WindowConfig::session(std::time::Duration::from_millis(10)))
+                .max_session_duration(std::time::Duration::from_millis(4))
+                .count()
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![("bot", 4), ("bot", 3)]);
+        })
+    }
+
+    #[test]
+    fn test_apply() {
+        tokio_test::block_on(async {
+            let source =
+                CollectionSource::with_timestamps(vec![(1, 5), (3, 1), (4, 9), (7, 3), (12, 8)]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(10)))
+                .apply(|window, records| {
+                    let mut values: Vec<i32> = records.iter().map(|r| r.data).collect();
+                    values.sort();
+                    let first = records.first().map(|r| r.timestamp);
+                    vec![(window.start, first, values[values.len() / 2])]
+                })
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            // The median of each window, with the timestamp of its first record
+            assert_eq!(
+                sink.get_data(),
+                vec![(Some(0), Some(1), 5), (Some(10), Some(12), 8)]
+            );
+        })
+    }
+
+    #[test]
+    fn test_sink_partitioned() {
+        tokio_test::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let source = CollectionSource::with_timestamps(vec![
+                (1, ("a", 1.0)),
+                (2, ("b", 2.0)),
+                (4, ("a", 3.0)),
+                (12, ("b", 4.0)),
+                (25, ("a", 5.0)),
+            ]);
+            let root = dir.path().to_path_buf();
+            DataStream::new(source)
+                .key_by(|(sensor, _): &(&str, f64)| sensor.to_string())
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(10)))
+                .aggregate_windowed(0.0, |sum, (_, value)| sum + value)
+                .sink_partitioned(
+                    |path| FileSink::new(path, FileFormat::JsonLines),
+                    move |window, sensor| {
+                        root.join(window.start.unwrap().to_string())
+                            .join(format!("{}.jsonl", sensor))
+                    },
+                )
+                .await
+                .unwrap();
+
+            let read = |window: &str, sensor: &str| {
+                let path = dir.path().join(window).join(format!("{}.jsonl", sensor));
+                std::fs::read_to_string(path).ok()
+            };
+            assert_eq!(
+                read("0", "a").as_deref(),
+                Some("{\"start\":0,\"end\":10,\"value\":[\"a\",4.0],\"is_final\":true}\n")
+            );
+            assert_eq!(
+                read("0", "b").as_deref(),
+                Some("{\"start\":0,\"end\":10,\"value\":[\"b\",2.0],\"is_final\":true}\n")
+            );
+            assert!(read("10", "b").is_some());
+            assert!(read("10", "a").is_none());
+            assert!(read("20", "a").is_some());
+        })
+    }
+
+    #[test]
+    fn test_keyed_aggregate_windowed_sessions() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (0, ("a", 1)),
+                (5, ("a", 2)),
+                (30, ("a", 3)),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .key_by(|(key, _): &(&str, i32)| *key)
+                .window(WindowConfig::session(std::time::Duration::from_millis(10)))
+                .aggregate_windowed(0, |sum, (_, value)| sum + value)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            let windows: Vec<_> = sink
+                .get_data()
+                .into_iter()
+                .map(|result| (result.start, result.end, result.value.1))
+                .collect();
+            assert_eq!(
+                windows,
+                vec![(Some(0), Some(15), 3), (Some(30), Some(40), 3)]
+            );
+        })
+    }
+
+    /// Average of the values of a window
+    struct Average;
+
+    impl AggregateFunction<(i64, i64), (i64, i64), f64> for Average {
+        fn create_accumulator(&self) -> (i64, i64) {
+            (0, 0)
+        }
+
+        fn add(&self, (count, sum): (i64, i64), (_, value): (i64, i64)) -> (i64, i64) {
+            (count + 1, sum + value)
+        }
+
+        fn merge(&self, a: (i64, i64), b: (i64, i64)) -> (i64, i64) {
+            (a.0 + b.0, a.1 + b.1)
+        }
+
+        fn get_result(&self, (count, sum): (i64, i64)) -> f64 {
+            sum as f64 / count as f64
+        }
+    }
+
+    #[test]
+    fn test_aggregate_function() {
+        tokio_test::block_on(async {
+            let elements: Vec<(i64, (i64, i64))> = (0..100).map(|i| (i, (i % 2, i))).collect();
+            for parallelism in [1, 4] {
+                let sink = CollectionSink::new();
+                DataStream::new(CollectionSource::with_timestamps(elements.clone()))
+                    .parallel(parallelism)
+                    .window(WindowConfig::tumbling(std::time::Duration::from_millis(25)))
+                    .aggregate_function(Average)
+                    .sink(sink.clone())
+                    .await
+                    .unwrap();
+                assert_eq!(sink.get_data(), vec![12.0, 37.0, 62.0, 87.0]);
+            }
+
+            let sink = CollectionSink::new();
+            DataStream::new(CollectionSource::with_timestamps(elements))
+                .key_by(|(parity, _): &(i64, i64)| *parity)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(50)))
+                .aggregate_function(Average)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(
+                sink.get_data(),
+                vec![(0, 24.0), (1, 25.0), (0, 74.0), (1, 75.0)]
+            );
+        })
+    }
+
+    /// Source that waits before each element
+    struct DelayedSource {
+        inner: CollectionSource<i32>,
+        delay: std::time::Duration,
+    }
+
+    #[async_trait]
+    impl Source<i32> for DelayedSource {
+        async fn init(&mut self) -> StreamResult<()> {
+            self.inner.init().await
+        }
+
+        async fn next(&mut self) -> StreamResult<Option<Record<i32>>> {
+            tokio::time::sleep(self.delay).await;
+            self.inner.next().await
+        }
+
+        async fn close(&mut self) -> StreamResult<()> {
+            self.inner.close().await
+        }
+    }
+
+    #[test]
+    fn test_emit_every() {
+        tokio_test::block_on(async {
+            let source = DelayedSource {
+                inner: CollectionSource::with_timestamps(vec![
+                    (0, 1),
+                    (1, 2),
+                    (2, 3),
+                    (3_600_000, 4),
+                ]),
+                delay: std::time::Duration::from_millis(60),
+            };
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::tumbling(std::time::Duration::from_secs(3600)))
+                .emit_every(std::time::Duration::from_millis(20))
+                .aggregate_windowed(0, |sum, x| sum + x)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            let results: Vec<_> = sink
+                .get_data()
+                .into_iter()
+                .map(|result| (result.start, result.value, result.is_final))
+                .collect();
+            assert_eq!(
+                results,
+                vec![
+                    (Some(0), 1, false),
+                    (Some(0), 3, false),
+                    (Some(0), 6, false),
+                    (Some(0), 6, true),
+                    (Some(3_600_000), 4, false),
+                    (Some(3_600_000), 4, true),
+                ]
+            );
+        })
+    }
+
+    #[test]
+    fn test_count_or_time_windows() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (0, 1),
+                (1, 2),
+                (2, 3),
+                (10, 4),
+                (40, 5),
+                (41, 6),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::count_or_time(
+                    3,
+                    std::time::Duration::from_millis(20),
+                ))
+                .aggregate_windowed(0, |sum, value| sum + value)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            // Full after three elements, then closed by time and end of input
+            let windows: Vec<_> = sink
+                .get_data()
+                .into_iter()
+                .map(|result| (result.start, result.end, result.value))
+                .collect();
+            assert_eq!(
+                windows,
+                vec![
+                    (Some(0), Some(20), 6),
+                    (Some(10), Some(30), 4),
+                    (Some(40), Some(60), 11)
+                ]
+            );
+
+            let source = CollectionSource::with_timestamps(vec![
+                (0, ("a", 1)),
+                (1, ("b", 2)),
+                (2, ("a", 3)),
+                (50, ("b", 4)),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .key_by(|(key, _): &(&str, i32)| *key)
+                .window(WindowConfig::count_or_time(
+                    2,
+                    std::time::Duration::from_millis(20),
+                ))
+                .aggregate(0, |sum, (_, value)| sum + value)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+
+            assert_eq!(sink.get_data(), vec![("a", 4), ("b", 2), ("b", 4)]);
+        })
+    }
+
+    #[test]
+    fn test_assign_with() {
+        tokio_test::block_on(async {
+            // Tenant "a" has 10ms windows, tenant "b" 20ms windows
+            let source = CollectionSource::with_timestamps(vec![
+                (0, "a"),
+                (5, "b"),
+                (12, "a"),
+                (15, "b"),
+                (25, "a"),
+                (30, "b"),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::global())
+                .assign_with(|tenant: &&str, timestamp| {
+                    let size = if *tenant == "a" { 10 } else { 20 };
+                    vec![Window::tumbling(
+                        timestamp,
+                        std::time::Duration::from_millis(size),
+                    )]
+                })
+                .group_by(|tenant| *tenant)
+                .count()
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(
+                sink.get_data(),
+                vec![("a", 1), ("b", 2), ("a", 1), ("a", 1), ("b", 1)]
+            );
+
+            // Trading sessions, records outside of them belong to no window
+            let source =
+                CollectionSource::with_timestamps(vec![(10, 1), (50, 2), (150, 3), (210, 4)]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::global())
+                .assign_with(|_: &i32, timestamp| {
+                    [(0, 100), (200, 300)]
+                        .into_iter()
+                        .filter(|(start, end)| (*start..*end).contains(&timestamp))
+                        .map(|(start, end)| Window {
+                            start: Some(start),
+                            end: Some(end),
+                        })
+                        .collect()
+                })
+                .aggregate_windowed(0, |sum, value| sum + value)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            let windows: Vec<_> = sink
+                .get_data()
+                .into_iter()
+                .map(|result| (result.start, result.end, result.value))
+                .collect();
+            assert_eq!(
+                windows,
+                vec![(Some(0), Some(100), 3), (Some(200), Some(300), 4)]
+            );
+        })
+    }
+
+    #[test]
+    fn test_assign_with_live_windows() {
+        tokio_test::block_on(async {
+            let assigner: std::sync::Arc<dyn fluxus_utils::window::WindowAssigner<i32>> =
+                std::sync::Arc::new(|_: &i32, timestamp| {
+                    vec![Window::tumbling(
+                        timestamp,
+                        std::time::Duration::from_millis(10),
+                    )]
+                });
+            let gauge = std::sync::Arc::new(fluxus_core::Gauge::new());
+            // Ended windows are held for their allowed lateness
+            let window_config =
+                WindowConfig::global().with_lateness(std::time::Duration::from_millis(5));
+            let mut aggregator =
+                AssignedWindowAggregator::new(window_config, assigner, 0, |sum, x| sum + x)
+                    .live_windows(gauge.clone());
+            aggregator
+                .process(Record::with_timestamp(1, 0))
+                .await
+                .unwrap();
+            aggregator
+                .process(Record::with_timestamp(2, 12))
+                .await
+                .unwrap();
+            assert_eq!(gauge.value(), 2);
+            aggregator.on_watermark(15).await.unwrap();
+            assert_eq!(gauge.value(), 1);
+            aggregator.on_end_of_input().await.unwrap();
+            assert_eq!(gauge.value(), 0);
+        })
+    }
+
+    #[test]
+    fn test_assign_with_rejects_unsupported() {
+        tokio_test::block_on(async {
+            let windowed = || {
+                DataStream::new(CollectionSource::with_timestamps(vec![
+                    (0, 1),
+                    (4, 2),
+                    (12, 3),
+                ]))
+                .window(WindowConfig::global())
+                .assign_with(|_: &i32, timestamp| {
+                    vec![Window::tumbling(
+                        timestamp,
+                        std::time::Duration::from_millis(10),
+                    )]
+                })
+            };
+
+            let result = windowed()
+                .emit_partial()
+                .aggregate(0, |sum, x| sum + x)
+                .sink(CollectionSink::new())
+                .await;
+            assert!(matches!(
+                result,
+                Err(StreamError::Config(message)) if message == "emit_partial is not supported with assign_with"
+            ));
+
+            let result = windowed()
+                .sort_by(|a, b| a.cmp(b))
+                .sink(CollectionSink::new())
+                .await;
+            assert!(matches!(
+                result,
+                Err(StreamError::Config(message)) if message == "sort_by is not supported with assign_with"
+            ));
+
+            // Aggregations built on aggregate use the assigned windows
+            let sink = CollectionSink::new();
+            windowed()
+                .reduce(|a, b| a + b)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![3, 3]);
+        })
+    }
+
+    #[test]
+    fn test_live_windows_purged_by_watermark() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![(0, 3), (5, 1), (12, 2), (25, 4)]);
+            let gauge = std::sync::Arc::new(fluxus_core::Gauge::new());
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(WindowConfig::tumbling(std::time::Duration::from_millis(10)))
+                .live_windows(gauge.clone())
+                .sort_by(|a: &i32, b: &i32| a.cmp(b))
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_last_element(), Some(vec![4]));
+            // Only the window of the last element is still held
+            assert_eq!(gauge.value(), 1);
+
+            let window_config = WindowConfig::tumbling(std::time::Duration::from_millis(10))
+                .with_lateness(std::time::Duration::from_millis(5));
+            let gauge = std::sync::Arc::new(fluxus_core::Gauge::new());
+            let mut sorter = WindowSorter::new(window_config.clone(), |a: &i32, b: &i32| a.cmp(b))
+                .live_windows(gauge.clone());
+            sorter.process(Record::with_timestamp(1, 0)).await.unwrap();
+            sorter.process(Record::with_timestamp(2, 12)).await.unwrap();
+            assert_eq!(gauge.value(), 2);
+            sorter.on_watermark(15).await.unwrap();
+            assert_eq!(gauge.value(), 1);
+
+            // Partial results keep their windows until the watermark advances
+            let gauge = std::sync::Arc::new(fluxus_core::Gauge::new());
+            let mut aggregator = WindowAggregator::new(window_config, 0, |sum, x: i32| sum + x)
+                .emit_partial()
+                .live_windows(gauge.clone());
+            for (timestamp, value) in [(0, 1), (12, 2), (31, 3)] {
+                aggregator
+                    .process(Record::with_timestamp(value, timestamp))
+                    .await
+                    .unwrap();
+            }
+            assert!(aggregator.on_watermark(31).await.unwrap().is_empty());
+            assert_eq!(gauge.value(), 1);
+        })
+    }
+
+    #[test]
+    fn test_global_window_triggers() {
+        tokio_test::block_on(async {
+            // Sum up to every flush marker, and the rest when the input ends
+            let sink = CollectionSink::new();
+            DataStream::new(CollectionSource::new(vec![1, 2, 0, 3, 4, 0, 5]))
+                .window(WindowConfig::global())
+                .trigger(PurgingTrigger::new(OrTrigger::new(
+                    PunctuationTrigger::new(|x: &i32| *x == 0),
+                    EventTimeTrigger,
+                )))
+                .sum_by(|x: &i32| *x)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![3, 7, 5]);
+
+            // Flushed from outside of the window operator
+            let signal = FlushSignal::new();
+            let flush = signal.clone();
+            let sink = CollectionSink::new();
+            DataStream::new(CollectionSource::new(vec![1, 2, 3, 4]))
+                .map(move |x| {
+                    if x == 3 {
+                        flush.flush();
+                    }
+                    x
+                })
+                .window(WindowConfig::global())
+                .trigger(PurgingTrigger::new(signal.trigger()))
+                .sum_by(|x: &i32| *x)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(sink.get_data(), vec![6]);
+        })
+    }
+
+    #[test]
+    fn test_window_offset() {
+        tokio_test::block_on(async {
+            let source =
+                CollectionSource::with_timestamps(vec![(0, 1), (2, 1), (3, 1), (12, 1), (13, 1)]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .window(
+                    WindowConfig::tumbling(std::time::Duration::from_millis(10))
+                        .with_offset(std::time::Duration::from_millis(3)),
+                )
+                .aggregate_windowed(0, |count, _: i32| count + 1)
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            let windows: Vec<_> = sink
+                .get_data()
+                .into_iter()
+                .map(|result| (result.start, result.end, result.value))
+                .collect();
+            assert_eq!(
+                windows,
+                vec![
+                    (Some(-7), Some(3), 2),
+                    (Some(3), Some(13), 2),
+                    (Some(13), Some(23), 1)
+                ]
+            );
+
+            // Offsets beyond the slide wrap around
+            let sliding = WindowConfig::sliding(
+                std::time::Duration::from_millis(10),
+                std::time::Duration::from_millis(5),
+            );
+            let keys = |offset| {
+                sliding
+                    .clone()
+                    .with_offset(std::time::Duration::from_millis(offset))
+                    .get_window_keys(8)
+            };
+            assert_eq!(keys(0), vec![0, 5]);
+            assert_eq!(keys(2), vec![2, 7]);
+            assert_eq!(keys(7), keys(2));
+
+            // Assigners shift windows by the same offset
+            let window = Window::tumbling_with_offset(
+                12,
+                std::time::Duration::from_millis(10),
+                std::time::Duration::from_millis(3),
+            );
+            assert_eq!((window.start, window.end), (Some(3), Some(13)));
+        })
+    }
+
+    #[test]
+    fn test_cogroup() {
+        tokio_test::block_on(async {
+            let impressions =
+                CollectionSource::with_timestamps(vec![(1, "ad-1"), (4, "ad-2"), (12, "ad-1")]);
+            let clicks = CollectionSource::with_timestamps(vec![(5, "ad-1"), (21, "ad-2")]);
+            let config = WindowConfig::tumbling(std::time::Duration::from_millis(10));
+            let sink = CollectionSink::new();
+            DataStream::new(impressions)
+                .window(config.clone())
+                .cogroup(
+                    DataStream::new(clicks).window(config),
+                    |window, impressions: Vec<&str>, clicks: Vec<&str>| {
+                        vec![(window.start, impressions.len(), clicks.len())]
+                    },
+                )
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            assert_eq!(
+                sink.get_data(),
+                vec![(Some(0), 2, 1), (Some(10), 1, 0), (Some(20), 0, 1)]
+            );
+        });
+    }
+
+    #[test]
+    fn test_sessionize() {
+        tokio_test::block_on(async {
+            let source = CollectionSource::with_timestamps(vec![
+                (0, ("a", 1)),
+                (5, ("b", 1)),
+                (3, ("a", 2)),
+                (40, ("a", 3)),
+            ]);
+            let sink = CollectionSink::new();
+            DataStream::new(source)
+                .sessionize(|(user, _)| *user, std::time::Duration::from_millis(10))
+                .map(|(user, session)| {
+                    let events: Vec<_> = session.events.iter().map(|(_, n)| *n).collect();
+                    (user, session.start, session.end, events)
+                })
+                .sink(sink.clone())
+                .await
+                .unwrap();
+            let mut sessions = sink.get_data();
+            sessions.sort();
+            assert_eq!(
+                sessions,
+                vec![
+                    ("a", 0, 3, vec![1, 2]),
+                    ("a", 40, 40, vec![3]),
+                    ("b", 5, 5, vec![1]),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn test_keyed_state_ttl() {
+        tokio_test::block_on(async {
+            let run = |config: WindowConfig| async move {
+                let source = CollectionSource::with_timestamps(vec![
+                    (0, ("a", 1)),
+                    (5, ("b", 1)),
+                    (12, ("b", 1)),
+                    (20, ("b", 1)),
+                    (21, ("a", 1)),
+                ]);
+                let sink = CollectionSink::new();
+                DataStream::new(source)
+                    .key_by(|(key, _): &(&str, i32)| *key)
+                    .window(config)
+                    .sum_by(|(_, n)| *n)
+                    .sink(sink.clone())
+                    .await
+                    .unwrap();
+                let mut sums = sink.get_data();
+                sums.sort();
+                sums
+            };
+
+            assert_eq!(run(WindowConfig::global()).await, vec![("a", 2), ("b", 3)]);
+            // The state of "a" is dropped once it was idle for 10ms
+            let config = WindowConfig::global().state_ttl(std::time::Duration::from_millis(10));
+            assert_eq!(run(config).await, vec![("a", 1), ("b", 3)]);
+        });
+    }
 }
@@ -0,0 +1,246 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::metrics::{MetricValue, Metrics};
+
+/// When an [`AlertRule`] fires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "condition", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// The value of the metric is above the threshold
+    Above { threshold: f64 },
+    /// The value of the metric is below the threshold
+    Below { threshold: f64 },
+    /// The metric grows by more than `per_sec` per second between two
+    /// evaluations, e.g. for the rate of failed records
+    RateAbove { per_sec: f64 },
+}
+
+/// A rule that fires an alert while a metric meets a condition.
+///
+/// Rules can be written in code or loaded from JSON with
+/// [`AlertEngine::from_json`], e.g.
+/// `{"name": "failing", "metric": "job.records_failed", "condition": "rate_above", "per_sec": 5}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertRule {
+    /// Name of the rule, which identifies its alerts
+    pub name: String,
+    /// Name of the metric in the [`Metrics`] registry
+    pub metric: String,
+    #[serde(flatten)]
+    pub condition: AlertCondition,
+}
+
+impl AlertRule {
+    /// Fire while `metric` is above `threshold`
+    pub fn above(name: impl Into<String>, metric: impl Into<String>, threshold: f64) -> Self {
+        Self::new(name, metric, AlertCondition::Above { threshold })
+    }
+
+    /// Fire while `metric` is below `threshold`
+    pub fn below(name: impl Into<String>, metric: impl Into<String>, threshold: f64) -> Self {
+        Self::new(name, metric, AlertCondition::Below { threshold })
+    }
+
+    /// Fire while `metric` grows by more than `per_sec` per second
+    pub fn rate_above(name: impl Into<String>, metric: impl Into<String>, per_sec: f64) -> Self {
+        Self::new(name, metric, AlertCondition::RateAbove { per_sec })
+    }
+
+    fn new(name: impl Into<String>, metric: impl Into<String>, condition: AlertCondition) -> Self {
+        Self {
+            name: name.into(),
+            metric: metric.into(),
+            condition,
+        }
+    }
+
+    /// The threshold of the condition
+    fn threshold(&self) -> f64 {
+        match self.condition {
+            AlertCondition::Above { threshold } | AlertCondition::Below { threshold } => threshold,
+            AlertCondition::RateAbove { per_sec } => per_sec,
+        }
+    }
+}
+
+/// Whether an alert started or stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+/// An alert emitted when a rule starts or stops firing
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    /// Name of the rule
+    pub rule: String,
+    pub metric: String,
+    pub state: AlertState,
+    /// Value of the metric, or its rate for rate rules
+    pub value: f64,
+    pub threshold: f64,
+}
+
+impl fmt::Display for Alert {
+    /// One line suitable for chat notifications, e.g.
+    /// `[FIRING] failing: job.records_failed = 12 (threshold 5)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self.state {
+            AlertState::Firing => "FIRING",
+            AlertState::Resolved => "RESOLVED",
+        };
+        write!(
+            f,
+            "[{}] {}: {} = {} (threshold {})",
+            state, self.rule, self.metric, self.value, self.threshold
+        )
+    }
+}
+
+fn metric_value(value: &MetricValue) -> f64 {
+    match value {
+        MetricValue::Counter(value) => *value as f64,
+        MetricValue::Gauge(value) => *value as f64,
+        MetricValue::Timer { avg_micros, .. } => *avg_micros as f64,
+    }
+}
+
+/// Evaluates [`AlertRule`]s against a [`Metrics`] registry.
+///
+/// Alerts are only emitted when a rule changes state: once with
+/// [`AlertState::Firing`] when its condition starts to hold, and once with
+/// [`AlertState::Resolved`] when it stops. Rules of metrics that don't
+/// exist don't fire, and rate rules need two evaluations to fire.
+pub struct AlertEngine {
+    metrics: Arc<Metrics>,
+    rules: Vec<AlertRule>,
+    previous: HashMap<String, (f64, Instant)>,
+    firing: HashSet<String>,
+}
+
+impl AlertEngine {
+    pub fn new(metrics: Arc<Metrics>, rules: Vec<AlertRule>) -> Self {
+        Self {
+            metrics,
+            rules,
+            previous: HashMap::new(),
+            firing: HashSet::new(),
+        }
+    }
+
+    /// Create an engine with the rules of a JSON array of [`AlertRule`]s
+    pub fn from_json(metrics: Arc<Metrics>, json: &str) -> StreamResult<Self> {
+        let rules = serde_json::from_str(json)
+            .map_err(|e| StreamError::Config(format!("invalid alert rules: {}", e)))?;
+        Ok(Self::new(metrics, rules))
+    }
+
+    /// The rules of the engine
+    pub fn rules(&self) -> &[AlertRule] {
+        &self.rules
+    }
+
+    /// Evaluate all rules against the current metrics, returning the alerts
+    /// of the rules that started or stopped firing
+    pub fn evaluate(&mut self) -> Vec<Alert> {
+        let snapshot = self.metrics.snapshot();
+        let now = Instant::now();
+        let mut alerts = Vec::new();
+        for rule in &self.rules {
+            let Some(value) = snapshot.get(&rule.metric).map(metric_value) else {
+                continue;
+            };
+            let value = match rule.condition {
+                AlertCondition::RateAbove { .. } => {
+                    // Keyed by rule, since rules of one metric are evaluated
+                    // independently
+                    let previous = self.previous.insert(rule.name.clone(), (value, now));
+                    let Some((last, at)) = previous else {
+                        continue;
+                    };
+                    let elapsed = now.duration_since(at).as_secs_f64();
+                    if elapsed <= 0.0 {
+                        continue;
+                    }
+                    (value - last) / elapsed
+                }
+                _ => value,
+            };
+            let holds = match rule.condition {
+                AlertCondition::Above { threshold } => value > threshold,
+                AlertCondition::Below { threshold } => value < threshold,
+                AlertCondition::RateAbove { per_sec } => value > per_sec,
+            };
+            let state = match (holds, self.firing.contains(&rule.name)) {
+                (true, false) => {
+                    self.firing.insert(rule.name.clone());
+                    AlertState::Firing
+                }
+                (false, true) => {
+                    self.firing.remove(&rule.name);
+                    AlertState::Resolved
+                }
+                _ => continue,
+            };
+            alerts.push(Alert {
+                rule: rule.name.clone(),
+                metric: rule.metric.clone(),
+                state,
+                value,
+                threshold: rule.threshold(),
+            });
+        }
+        alerts
+    }
+}
+
+/// A source of the alerts of an [`AlertEngine`], evaluating its rules every
+/// `interval`, so that a pipeline can send its own alerts to a sink, e.g. a
+/// chat webhook wrapped in [`NotifyOnce`](fluxus_sinks::NotifyOnce).
+///
+/// The source never ends; stop it by stopping its job.
+pub struct AlertSource {
+    engine: AlertEngine,
+    interval: Duration,
+    pending: VecDeque<Alert>,
+}
+
+impl AlertSource {
+    pub fn new(engine: AlertEngine, interval: Duration) -> Self {
+        Self {
+            engine,
+            interval,
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Source<Alert> for AlertSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        // The first evaluation records the starting point of rate rules
+        self.pending.extend(self.engine.evaluate());
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<Alert>>> {
+        while self.pending.is_empty() {
+            tokio::time::sleep(self.interval).await;
+            self.pending.extend(self.engine.evaluate());
+        }
+        Ok(self.pending.pop_front().map(Record::new))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+}
@@ -1,3 +1,7 @@
+/// Connector security settings, defined in fluxus-utils because the sources
+/// and sinks that take them cannot depend on fluxus-core
+pub use fluxus_utils::security::{AuthConfig, SaslMechanism, TlsConfig};
+
 /// Configuration for parallel processing
 #[derive(Debug, Clone)]
 pub struct ParallelConfig {
@@ -0,0 +1,232 @@
+use async_trait::async_trait;
+use fluxus_utils::models::StreamResult;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::sleep;
+
+use crate::error_handling::RetryStrategy;
+use crate::metrics::{Counter, Gauge, Metrics};
+
+/// Opens connections to a database or service for a [`ConnectionPool`]
+#[async_trait]
+pub trait Connector: Send + Sync {
+    type Connection: Send;
+
+    /// Open a new connection
+    async fn connect(&self) -> StreamResult<Self::Connection>;
+
+    /// Check whether an idle connection can still be used before it is
+    /// handed out again
+    async fn is_healthy(&self, _connection: &mut Self::Connection) -> bool {
+        true
+    }
+}
+
+/// Counters and gauges of a [`ConnectionPool`]
+struct ConnectionMetrics {
+    connects: Arc<Counter>,
+    failures: Arc<Counter>,
+    open: Arc<Gauge>,
+}
+
+struct Shared<C: Connector> {
+    connector: C,
+    strategy: RetryStrategy,
+    idle: Mutex<Vec<C::Connection>>,
+    permits: Arc<Semaphore>,
+    metrics: ConnectionMetrics,
+}
+
+/// A pool of connections shared by sinks, with health checks and
+/// reconnects with backoff.
+///
+/// At most `size` connections are handed out at a time. Idle connections
+/// that fail their health check and connections that were
+/// [`discard`](PooledConnection::discard)ed after an error are replaced by
+/// new ones, retrying failed connects with the retry strategy, so that
+/// pipelines survive restarts of the service they write to. Clones of the
+/// pool share its connections.
+pub struct ConnectionPool<C: Connector> {
+    shared: Arc<Shared<C>>,
+}
+
+impl<C: Connector> Clone for ConnectionPool<C> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<C: Connector> ConnectionPool<C> {
+    pub fn new(connector: C, size: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                connector,
+                strategy: RetryStrategy::NoRetry,
+                idle: Mutex::new(Vec::new()),
+                permits: Arc::new(Semaphore::new(size.max(1))),
+                metrics: ConnectionMetrics {
+                    connects: Arc::new(Counter::new()),
+                    failures: Arc::new(Counter::new()),
+                    open: Arc::new(Gauge::new()),
+                },
+            }),
+        }
+    }
+
+    fn configure(&mut self) -> &mut Shared<C> {
+        Arc::get_mut(&mut self.shared)
+            .expect("a connection pool must be configured before it is cloned")
+    }
+
+    /// Retry failed connects with the given strategy
+    pub fn with_retry(mut self, strategy: RetryStrategy) -> Self {
+        self.configure().strategy = strategy;
+        self
+    }
+
+    /// Publish the `<name>.connects`, `<name>.connect_failures` and
+    /// `<name>.connections_open` metrics of the pool
+    pub fn report_to(mut self, metrics: &mut Metrics, name: &str) -> Self {
+        self.configure().metrics = ConnectionMetrics {
+            connects: metrics.counter(&format!("{}.connects", name)),
+            failures: metrics.counter(&format!("{}.connect_failures", name)),
+            open: metrics.gauge(&format!("{}.connections_open", name)),
+        };
+        self
+    }
+
+    /// Number of connects that succeeded
+    pub fn connects(&self) -> u64 {
+        self.shared.metrics.connects.value()
+    }
+
+    /// Number of connects that failed, including retried ones
+    pub fn connect_failures(&self) -> u64 {
+        self.shared.metrics.failures.value()
+    }
+
+    /// Number of open connections, idle or handed out
+    pub fn open_connections(&self) -> i64 {
+        self.shared.metrics.open.value()
+    }
+
+    /// Get a healthy connection, waiting while all connections are in use
+    /// and connecting if there is no idle one
+    pub async fn get(&self) -> StreamResult<PooledConnection<C>> {
+        let permit = self
+            .shared
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the semaphore of a connection pool is never closed");
+
+        loop {
+            let idle = self.shared.idle.lock().unwrap().pop();
+            let Some(mut connection) = idle else {
+                break;
+            };
+            if self.shared.connector.is_healthy(&mut connection).await {
+                return Ok(self.hand_out(connection, permit));
+            }
+            tracing::warn!("Dropping unhealthy connection");
+            self.shared.closed();
+        }
+
+        let connection = self.connect().await?;
+        Ok(self.hand_out(connection, permit))
+    }
+
+    async fn connect(&self) -> StreamResult<C::Connection> {
+        let metrics = &self.shared.metrics;
+        let mut attempt = 0;
+        loop {
+            match self.shared.connector.connect().await {
+                Ok(connection) => {
+                    metrics.connects.increment();
+                    metrics.open.set(metrics.open.value() + 1);
+                    return Ok(connection);
+                }
+                Err(e) => {
+                    metrics.failures.increment();
+                    let Some(delay) = self.shared.strategy.get_delay(attempt) else {
+                        return Err(e);
+                    };
+                    tracing::warn!(
+                        "Connect failed (attempt {}): {}. Retrying after {:?}",
+                        attempt + 1,
+                        e,
+                        delay
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    fn hand_out(
+        &self,
+        connection: C::Connection,
+        permit: OwnedSemaphorePermit,
+    ) -> PooledConnection<C> {
+        PooledConnection {
+            connection: Some(connection),
+            shared: self.shared.clone(),
+            _permit: permit,
+        }
+    }
+}
+
+impl<C: Connector> Shared<C> {
+    fn closed(&self) {
+        self.metrics.open.set(self.metrics.open.value() - 1);
+    }
+}
+
+/// A connection of a [`ConnectionPool`], returned to the pool when dropped
+pub struct PooledConnection<C: Connector> {
+    connection: Option<C::Connection>,
+    shared: Arc<Shared<C>>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl<C: Connector> PooledConnection<C> {
+    /// Close the connection instead of returning it to the pool, e.g. after
+    /// a write failed, so that the next [`get`](ConnectionPool::get)
+    /// reconnects
+    pub fn discard(mut self) {
+        if self.connection.take().is_some() {
+            self.shared.closed();
+        }
+    }
+}
+
+impl<C: Connector> Deref for PooledConnection<C> {
+    type Target = C::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        self.connection
+            .as_ref()
+            .expect("a pooled connection is only taken when it is dropped")
+    }
+}
+
+impl<C: Connector> DerefMut for PooledConnection<C> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.connection
+            .as_mut()
+            .expect("a pooled connection is only taken when it is dropped")
+    }
+}
+
+impl<C: Connector> Drop for PooledConnection<C> {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            self.shared.idle.lock().unwrap().push(connection);
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use fluxus_sinks::Sink;
+use fluxus_transformers::Operator;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+use super::RetryStrategy;
+use crate::metrics::Counter;
+
+/// Wraps an operator or sink so that every call is cancelled once it exceeds a timeout.
+///
+/// Timed out calls are retried according to the retry strategy and then
+/// reported as [`StreamError::Timeout`].
+pub struct Deadline<X> {
+    inner: X,
+    timeout: Duration,
+    strategy: RetryStrategy,
+    timeouts: Arc<Counter>,
+}
+
+impl<X> Deadline<X> {
+    pub fn new(inner: X, timeout: Duration) -> Self {
+        Self {
+            inner,
+            timeout,
+            strategy: RetryStrategy::NoRetry,
+            timeouts: Arc::new(Counter::new()),
+        }
+    }
+
+    /// Retry timed out calls with the given strategy
+    pub fn with_retry(mut self, strategy: RetryStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Counter of timed out calls, shared with clones of the handle
+    pub fn timeouts(&self) -> Arc<Counter> {
+        self.timeouts.clone()
+    }
+
+    pub fn into_inner(self) -> X {
+        self.inner
+    }
+
+    /// Count a timed out call and get the delay before the next attempt, if any
+    fn retry_delay(&self, what: &str, attempt: usize) -> Option<Duration> {
+        self.timeouts.increment();
+        let delay = self.strategy.get_delay(attempt)?;
+        tracing::warn!(
+            "{} timed out after {:?} (attempt {}), retrying after {:?}",
+            what,
+            self.timeout,
+            attempt + 1,
+            delay
+        );
+        Some(delay)
+    }
+}
+
+/// Adds `with_timeout` to operators and sinks
+pub trait DeadlineExt: Sized {
+    /// Cancel calls that run longer than the timeout
+    fn with_timeout(self, timeout: Duration) -> Deadline<Self> {
+        Deadline::new(self, timeout)
+    }
+}
+
+impl<X> DeadlineExt for X {}
+
+#[async_trait]
+impl<In, Out, O> Operator<In, Out> for Deadline<O>
+where
+    In: Clone + Send + Sync + 'static,
+    Out: Send + 'static,
+    O: Operator<In, Out> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn init(&mut self) -> StreamResult<()> {
+        self.inner.init().await
+    }
+
+    async fn process(&mut self, record: Record<In>) -> StreamResult<Vec<Record<Out>>> {
+        let mut attempt = 0;
+        loop {
+            match timeout(self.timeout, self.inner.process(record.clone())).await {
+                Ok(result) => return result,
+                Err(_) => match self.retry_delay("Operator call", attempt) {
+                    Some(delay) => sleep(delay).await,
+                    None => return Err(StreamError::Timeout(self.timeout)),
+                },
+            }
+            attempt += 1;
+        }
+    }
+
+    async fn on_window_trigger(&mut self) -> StreamResult<Vec<Record<Out>>> {
+        self.inner.on_window_trigger().await
+    }
+
+    async fn on_watermark(&mut self, watermark: i64) -> StreamResult<Vec<Record<Out>>> {
+        self.inner.on_watermark(watermark).await
+    }
+
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<Out>>> {
+        self.inner.on_end_of_input().await
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.inner.close().await
+    }
+}
+
+#[async_trait]
+impl<T, K> Sink<T> for Deadline<K>
+where
+    T: Clone + Send + Sync + 'static,
+    K: Sink<T> + Send + Sync,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        self.inner.init().await
+    }
+
+    async fn write(&mut self, record: Record<T>) -> StreamResult<()> {
+        let mut attempt = 0;
+        loop {
+            match timeout(self.timeout, self.inner.write(record.clone())).await {
+                Ok(result) => return result,
+                Err(_) => match self.retry_delay("Sink write", attempt) {
+                    Some(delay) => sleep(delay).await,
+                    None => return Err(StreamError::Timeout(self.timeout)),
+                },
+            }
+            attempt += 1;
+        }
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        let mut attempt = 0;
+        loop {
+            match timeout(self.timeout, self.inner.flush()).await {
+                Ok(result) => return result,
+                Err(_) => match self.retry_delay("Sink flush", attempt) {
+                    Some(delay) => sleep(delay).await,
+                    None => return Err(StreamError::Timeout(self.timeout)),
+                },
+            }
+            attempt += 1;
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.inner.close().await
+    }
+}
@@ -0,0 +1,140 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamResult};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
+use tokio::time::sleep;
+
+use super::RetryStrategy;
+
+/// Whether a source wrapped in [`InitRetry`] is ready
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// The source has not been initialized yet, or is being retried
+    Connecting,
+    /// The source was initialized
+    Connected,
+    /// The source could not be initialized within the retry strategy
+    Failed,
+}
+
+/// The connection state of a source, shared with clones of the handle
+#[derive(Debug, Clone)]
+pub struct ConnectionStatus(Arc<AtomicU8>);
+
+impl ConnectionStatus {
+    fn new() -> Self {
+        Self(Arc::new(AtomicU8::new(ConnectionState::Connecting as u8)))
+    }
+
+    pub fn get(&self) -> ConnectionState {
+        match self.0.load(Ordering::Relaxed) {
+            s if s == ConnectionState::Connected as u8 => ConnectionState::Connected,
+            s if s == ConnectionState::Failed as u8 => ConnectionState::Failed,
+            _ => ConnectionState::Connecting,
+        }
+    }
+
+    fn set(&self, state: ConnectionState) {
+        self.0.store(state as u8, Ordering::Relaxed);
+    }
+}
+
+/// Wraps a source so that a failing `init` is retried with a retry strategy
+/// instead of aborting the pipeline, e.g. while a server answers 503 or DNS
+/// doesn't resolve yet.
+///
+/// In [`lazy`](Self::lazy) mode `init` succeeds right away and the source is
+/// connected on the first read, so that the rest of the job starts while
+/// the source is [`Connecting`](ConnectionState::Connecting).
+pub struct InitRetry<S> {
+    inner: S,
+    strategy: RetryStrategy,
+    lazy: bool,
+    connected: bool,
+    status: ConnectionStatus,
+}
+
+impl<S> InitRetry<S> {
+    pub fn new(inner: S, strategy: RetryStrategy) -> Self {
+        Self {
+            inner,
+            strategy,
+            lazy: false,
+            connected: false,
+            status: ConnectionStatus::new(),
+        }
+    }
+
+    /// Connect on the first read instead of in `init`
+    pub fn lazy(mut self) -> Self {
+        self.lazy = true;
+        self
+    }
+
+    /// Connection state of the source, shared with clones of the handle
+    pub fn status(&self) -> ConnectionStatus {
+        self.status.clone()
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    async fn connect<T>(&mut self) -> StreamResult<()>
+    where
+        S: Source<T> + Send,
+    {
+        let mut attempt = 0;
+        loop {
+            match self.inner.init().await {
+                Ok(()) => {
+                    self.connected = true;
+                    self.status.set(ConnectionState::Connected);
+                    return Ok(());
+                }
+                Err(e) => match self.strategy.get_delay(attempt) {
+                    Some(delay) => {
+                        tracing::warn!(
+                            "Source failed to initialize (attempt {}): {}. Retrying after {:?}",
+                            attempt + 1,
+                            e,
+                            delay
+                        );
+                        sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => {
+                        self.status.set(ConnectionState::Failed);
+                        return Err(e);
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T, S> Source<T> for InitRetry<S>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + Sync,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        if self.lazy {
+            return Ok(());
+        }
+        self.connect().await
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        if !self.connected {
+            self.connect().await?;
+        }
+        self.inner.next().await
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.inner.close().await
+    }
+}
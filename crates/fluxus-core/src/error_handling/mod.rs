@@ -1,8 +1,12 @@
 mod backpressure;
+mod deadline;
+mod init_retry;
 mod retry_strategy;
 
 pub use backpressure::{BackpressureController, BackpressureStrategy};
+pub use deadline::{Deadline, DeadlineExt};
 use fluxus_utils::models::StreamResult;
+pub use init_retry::{ConnectionState, ConnectionStatus, InitRetry};
 pub use retry_strategy::RetryStrategy;
 use tokio::time::sleep;
 
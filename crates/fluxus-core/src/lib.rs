@@ -2,15 +2,22 @@
 //!
 //! This module contains the core abstractions and data types for stream processing.
 
+pub mod alerting;
 pub mod config;
+pub mod connection;
 pub mod error_handling;
 pub mod metrics;
 pub mod pipeline;
+pub mod progress;
 
 // Re-export commonly used items
-pub use config::ParallelConfig;
+pub use alerting::{Alert, AlertCondition, AlertEngine, AlertRule, AlertSource, AlertState};
+pub use config::{AuthConfig, ParallelConfig, SaslMechanism, TlsConfig};
+pub use connection::{ConnectionPool, Connector, PooledConnection};
 pub use error_handling::{
-    BackpressureController, BackpressureStrategy, ErrorHandler, RetryStrategy,
+    BackpressureController, BackpressureStrategy, ConnectionState, ConnectionStatus, Deadline,
+    DeadlineExt, ErrorHandler, InitRetry, RetryStrategy,
 };
-pub use metrics::{Counter, Gauge, MetricValue, Metrics, Timer};
-pub use pipeline::Pipeline;
+pub use metrics::{Counter, Gauge, MetricValue, Metrics, Timer, write_metrics_json};
+pub use pipeline::{DryRun, Pipeline};
+pub use progress::ProgressSource;
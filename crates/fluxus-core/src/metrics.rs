@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use fluxus_utils::memory::{MemoryAccount, TrackingAllocator};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
 use std::time::{Duration, Instant};
@@ -93,7 +97,7 @@ impl Timer {
     pub fn average_duration_micros(&self) -> u64 {
         let total = self.duration_counter.value();
         let count = self.count_counter.value();
-        if count == 0 { 0 } else { total / count }
+        total.checked_div(count).unwrap_or(0)
     }
 }
 
@@ -103,6 +107,8 @@ pub struct Metrics {
     counters: HashMap<String, Arc<Counter>>,
     gauges: HashMap<String, Arc<Gauge>>,
     timers: HashMap<String, Arc<Timer>>,
+    memory: HashMap<String, MemoryAccount>,
+    track_allocations: bool,
 }
 
 impl Metrics {
@@ -131,6 +137,34 @@ impl Metrics {
             .clone()
     }
 
+    /// The memory account of a job, reported as the `<job>.memory_bytes`
+    /// gauge, which includes the memory of its operators
+    pub fn job_memory(&mut self, job: &str) -> MemoryAccount {
+        self.memory
+            .entry(format!("{}.memory_bytes", job))
+            .or_default()
+            .clone()
+    }
+
+    /// The memory account of an operator of a job, reported as the
+    /// `<job>.<operator>.memory_bytes` gauge
+    pub fn operator_memory(&mut self, job: &str, operator: &str) -> MemoryAccount {
+        let name = format!("{}.{}.memory_bytes", job, operator);
+        if let Some(account) = self.memory.get(&name) {
+            return account.clone();
+        }
+        let account = self.job_memory(job).child();
+        self.memory.insert(name, account.clone());
+        account
+    }
+
+    /// Report the bytes allocated by the process as the
+    /// `process.allocated_bytes` gauge, which requires the
+    /// [`TrackingAllocator`] to be installed
+    pub fn track_allocations(&mut self) {
+        self.track_allocations = true;
+    }
+
     pub fn snapshot(&self) -> HashMap<String, MetricValue> {
         let mut snapshot = HashMap::new();
 
@@ -142,6 +176,17 @@ impl Metrics {
             snapshot.insert(name.clone(), MetricValue::Gauge(gauge.value()));
         }
 
+        for (name, account) in &self.memory {
+            snapshot.insert(name.clone(), MetricValue::Gauge(account.bytes()));
+        }
+
+        if self.track_allocations {
+            snapshot.insert(
+                "process.allocated_bytes".to_string(),
+                MetricValue::Gauge(TrackingAllocator::allocated() as i64),
+            );
+        }
+
         for (name, timer) in &self.timers {
             snapshot.insert(
                 name.clone(),
@@ -154,9 +199,27 @@ impl Metrics {
 
         snapshot
     }
+
+    /// Write a snapshot of all metrics to a JSON file, see [`write_metrics_json`]
+    pub fn write_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        write_metrics_json(&self.snapshot(), path)
+    }
+}
+
+/// Write a metrics snapshot to a JSON file as an object keyed by metric name,
+/// e.g. `{"job.records": {"type": "counter", "value": 3}}`
+pub fn write_metrics_json(
+    snapshot: &HashMap<String, MetricValue>,
+    path: impl AsRef<Path>,
+) -> io::Result<()> {
+    // Sorted keys keep the files of different runs comparable
+    let sorted: BTreeMap<_, _> = snapshot.iter().collect();
+    let json = serde_json::to_string_pretty(&sorted).map_err(io::Error::other)?;
+    std::fs::write(path, json)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
 pub enum MetricValue {
     Counter(u64),
     Gauge(i64),
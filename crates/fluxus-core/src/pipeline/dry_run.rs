@@ -0,0 +1,11 @@
+use fluxus_utils::models::Record;
+
+/// Outcome of a dry run, which initializes every component and processes a
+/// sample of records without writing them to the sink
+#[derive(Debug, Clone)]
+pub struct DryRun<T> {
+    /// Number of records read from the source
+    pub records_read: usize,
+    /// Records that would have been written to the sink
+    pub output: Vec<Record<T>>,
+}
@@ -1,5 +1,7 @@
+mod dry_run;
 mod processor;
 mod status;
 
+pub use dry_run::DryRun;
 pub use processor::Pipeline;
 pub use status::PipelineStatus;
@@ -1,3 +1,4 @@
+use super::dry_run::DryRun;
 use super::status::PipelineStatus;
 use crate::BackpressureStrategy;
 use crate::Counter;
@@ -12,6 +13,7 @@ use fluxus_sinks::dummy_sink::DummySink;
 use fluxus_sources::Source;
 use fluxus_transformers::operator::Operator;
 use fluxus_utils::models::Record;
+use fluxus_utils::models::StreamError;
 use fluxus_utils::models::StreamResult;
 use fluxus_utils::time::current_time;
 use fluxus_utils::window::WindowConfig;
@@ -183,6 +185,55 @@ impl<T: 'static + Send + Clone> Pipeline<T> {
             .await
     }
 
+    /// Check the pipeline before running it.
+    ///
+    /// Initializes the source, operators and sink, then processes records until
+    /// `sample` records reached the end of the operator chain or the source
+    /// ends. Nothing is written to the sink, and every component is closed
+    /// again. Any error is returned immediately, without retries.
+    pub async fn validate(mut self, sample: usize) -> StreamResult<DryRun<T>> {
+        self.source.init().await?;
+        for op in &mut self.operators {
+            op.init().await?;
+        }
+        self.sink.init().await?;
+
+        let mut dry_run = DryRun {
+            records_read: 0,
+            output: Vec::new(),
+        };
+        while dry_run.output.len() < sample {
+            let record = match self.source.next().await {
+                Ok(Some(record)) => record,
+                Ok(None) | Err(StreamError::EOF) => break,
+                Err(StreamError::Wait(ms)) => {
+                    time::sleep(Duration::from_millis(ms)).await;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            dry_run.records_read += 1;
+
+            let mut records = vec![record];
+            for op in &mut self.operators {
+                let mut next = Vec::new();
+                for record in records {
+                    next.extend(op.process(record).await?);
+                }
+                records = next;
+            }
+            dry_run.output.extend(records);
+        }
+        dry_run.output.truncate(sample);
+
+        for op in &mut self.operators {
+            op.close().await?;
+        }
+        self.source.close().await?;
+        self.sink.close().await?;
+        Ok(dry_run)
+    }
+
     /// Execute the pipeline with error handling and backpressure
     pub async fn execute(mut self) -> StreamResult<()> {
         self.status = PipelineStatus::Running;
@@ -197,12 +248,12 @@ impl<T: 'static + Send + Clone> Pipeline<T> {
         let mut watermark_interval = time::interval(Duration::from_millis(100));
 
         loop {
-            if self.backpressure.should_apply_backpressure() {
-                if let Some(backoff) = self.backpressure.get_backoff() {
-                    tracing::debug!("Applying backpressure, waiting for {:?}", backoff);
-                    time::sleep(backoff).await;
-                    continue;
-                }
+            if self.backpressure.should_apply_backpressure()
+                && let Some(backoff) = self.backpressure.get_backoff()
+            {
+                tracing::debug!("Applying backpressure, waiting for {:?}", backoff);
+                time::sleep(backoff).await;
+                continue;
             }
 
             tokio::select! {
@@ -275,6 +326,20 @@ impl<T: 'static + Send + Clone> Pipeline<T> {
             }
         }
 
+        // Emit what the operators still hold, such as open windows
+        let mut records = Vec::new();
+        for op in &mut self.operators {
+            let mut flushed = Vec::new();
+            for record in records {
+                flushed.extend(op.process(record).await?);
+            }
+            flushed.extend(op.on_end_of_input().await?);
+            records = flushed;
+        }
+        for record in records {
+            self.sink.write(record).await?;
+        }
+
         self.sink.flush().await?;
         self.sink.close().await?;
         self.status = PipelineStatus::Completed;
@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use fluxus_sources::{Progress, Source, SourceProgress};
+use fluxus_utils::models::{Record, StreamResult};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::metrics::{Gauge, Metrics};
+
+/// A source that reports how far a bounded inner source is through its input.
+///
+/// The percentage and the estimated seconds remaining are published as the
+/// `<name>.progress_percent` and `<name>.eta_secs` gauges, which stay at -1
+/// while unknown, and every new percent is logged.
+pub struct ProgressSource<S> {
+    inner: S,
+    name: String,
+    percent: Arc<Gauge>,
+    eta_secs: Arc<Gauge>,
+    started: Option<Instant>,
+    eta: Option<Duration>,
+}
+
+impl<S> ProgressSource<S>
+where
+    S: SourceProgress,
+{
+    pub fn new(inner: S, metrics: &mut Metrics, name: &str) -> Self {
+        let percent = metrics.gauge(&format!("{}.progress_percent", name));
+        let eta_secs = metrics.gauge(&format!("{}.eta_secs", name));
+        percent.set(-1);
+        eta_secs.set(-1);
+        Self {
+            inner,
+            name: name.to_string(),
+            percent,
+            eta_secs,
+            started: None,
+            eta: None,
+        }
+    }
+
+    /// Estimated time until the inner source is exhausted
+    pub fn eta(&self) -> Option<Duration> {
+        self.eta
+    }
+
+    fn update(&mut self) {
+        let Some(fraction) = self.inner.progress().fraction() else {
+            return;
+        };
+        let elapsed = self.started.get_or_insert_with(Instant::now).elapsed();
+        self.eta = (fraction > 0.0).then(|| elapsed.mul_f64((1.0 - fraction) / fraction));
+
+        let percent = (fraction * 100.0).floor() as i64;
+        self.eta_secs
+            .set(self.eta.map_or(-1, |eta| eta.as_secs_f64().ceil() as i64));
+        if percent > self.percent.value() {
+            self.percent.set(percent);
+            tracing::info!("{}: {}% done, ETA {:?}", self.name, percent, self.eta);
+        }
+    }
+}
+
+impl<S> SourceProgress for ProgressSource<S>
+where
+    S: SourceProgress,
+{
+    fn progress(&self) -> Progress {
+        self.inner.progress()
+    }
+}
+
+#[async_trait]
+impl<T, S> Source<T> for ProgressSource<S>
+where
+    T: Send + 'static,
+    S: Source<T> + SourceProgress + Send + Sync,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        self.inner.init().await?;
+        self.started = Some(Instant::now());
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        let record = self.inner.next().await?;
+        self.update();
+        Ok(record)
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.inner.close().await
+    }
+}
@@ -0,0 +1,207 @@
+use fluxus_utils::models::{StreamError, StreamResult};
+use futures::stream::FuturesUnordered;
+use futures::{FutureExt, StreamExt};
+use std::any::Any;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use tokio::sync::watch;
+use tokio::task::{AbortHandle, JoinError, JoinHandle};
+
+/// How a task of a pipeline stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskExit {
+    /// The task ran to completion
+    Completed,
+    /// The task stopped with an error, such as a source or sink failure
+    Failed(String),
+    /// The task panicked, with the panic message
+    Panicked(String),
+    /// The task was aborted
+    Aborted,
+}
+
+/// A task of a running pipeline together with the name used in errors
+pub(crate) struct JobTask {
+    pub(crate) name: String,
+    pub(crate) handle: JoinHandle<StreamResult<()>>,
+    /// Set when the task stops, dropped without a value when it is aborted
+    exit: watch::Receiver<Option<TaskExit>>,
+}
+
+impl JobTask {
+    /// Spawn a task of a pipeline, recording how it stops for its stage handle
+    pub(crate) fn spawn<F>(name: String, future: F) -> Self
+    where
+        F: Future<Output = StreamResult<()>> + Send + 'static,
+    {
+        let (tx, exit) = watch::channel(None);
+        let handle = tokio::spawn(async move {
+            match AssertUnwindSafe(future).catch_unwind().await {
+                Ok(result) => {
+                    let status = match &result {
+                        Ok(()) => TaskExit::Completed,
+                        Err(e) => TaskExit::Failed(e.to_string()),
+                    };
+                    tx.send_replace(Some(status));
+                    result
+                }
+                Err(panic) => {
+                    tx.send_replace(Some(TaskExit::Panicked(panic_message(&*panic))));
+                    // Keep the panic visible to `await_completion`
+                    std::panic::resume_unwind(panic)
+                }
+            }
+        });
+        Self { name, handle, exit }
+    }
+}
+
+/// Handle to the tasks of one stage of a pipeline, the source, an operator
+/// with its parallel instances or the sink, see [`JobHandle::stages`]
+#[derive(Clone)]
+pub struct StageHandle {
+    name: String,
+    tasks: Vec<(AbortHandle, watch::Receiver<Option<TaskExit>>)>,
+}
+
+impl StageHandle {
+    /// Name of the stage, e.g. `source`, `operator[0] map` or `sink`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of tasks of the stage
+    pub fn parallelism(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Whether all tasks of the stage have stopped
+    pub fn is_finished(&self) -> bool {
+        self.tasks.iter().all(|(handle, _)| handle.is_finished())
+    }
+
+    /// Stop all tasks of the stage, leaving the other stages running
+    pub fn abort(&self) {
+        self.tasks.iter().for_each(|(handle, _)| handle.abort());
+    }
+
+    /// The message of the first task of the stage that panicked
+    pub fn panic(&self) -> Option<String> {
+        self.tasks
+            .iter()
+            .find_map(|(_, exit)| match &*exit.borrow() {
+                Some(TaskExit::Panicked(message)) => Some(message.clone()),
+                _ => None,
+            })
+    }
+
+    /// Wait until every task of the stage has stopped, returning how each
+    /// of them stopped
+    pub async fn join(&self) -> Vec<TaskExit> {
+        let mut exits = Vec::with_capacity(self.tasks.len());
+        for (_, exit) in &self.tasks {
+            let mut exit = exit.clone();
+            let status = match exit.wait_for(Option::is_some).await {
+                Ok(status) => status.clone().unwrap_or(TaskExit::Aborted),
+                // The task was dropped before it stopped on its own
+                Err(_) => TaskExit::Aborted,
+            };
+            exits.push(status);
+        }
+        exits
+    }
+}
+
+/// Handle to a pipeline started by the runtime
+pub struct JobHandle {
+    id: String,
+    tasks: Vec<JobTask>,
+}
+
+impl JobHandle {
+    pub(crate) fn new(id: String, tasks: Vec<JobTask>) -> Self {
+        Self { id, tasks }
+    }
+
+    /// Identifier of the pipeline
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Whether all tasks of the pipeline have stopped
+    pub fn is_finished(&self) -> bool {
+        self.tasks.iter().all(|task| task.handle.is_finished())
+    }
+
+    /// Handles to the stages of the pipeline, from the source to the sink
+    pub fn stages(&self) -> Vec<StageHandle> {
+        let mut stages: Vec<StageHandle> = Vec::new();
+        for task in &self.tasks {
+            let handle = (task.handle.abort_handle(), task.exit.clone());
+            match stages.last_mut() {
+                Some(stage) if stage.name == task.name => stage.tasks.push(handle),
+                _ => stages.push(StageHandle {
+                    name: task.name.clone(),
+                    tasks: vec![handle],
+                }),
+            }
+        }
+        stages
+    }
+
+    /// The stage with the given name
+    pub fn stage(&self, name: &str) -> Option<StageHandle> {
+        self.stages().into_iter().find(|stage| stage.name == name)
+    }
+
+    /// Stop all tasks of the pipeline
+    pub fn abort(&self) {
+        self.tasks.iter().for_each(|task| task.handle.abort());
+    }
+
+    /// Wait until every task of the pipeline has stopped.
+    ///
+    /// Returns the first fatal error, such as a source or sink failure, a
+    /// panic or an aborted task, in the order the tasks failed.
+    pub async fn await_completion(self) -> StreamResult<()> {
+        let mut tasks: FuturesUnordered<_> = self
+            .tasks
+            .into_iter()
+            .map(|task| async move { (task.name, task.handle.await) })
+            .collect();
+
+        let mut first_error = None;
+        while let Some((name, result)) = tasks.next().await {
+            let result = match result {
+                Ok(result) => result,
+                Err(e) => Err(join_error(&name, e)),
+            };
+            if let Err(e) = result {
+                tracing::error!("Pipeline {}: {} failed: {}", self.id, name, e);
+                first_error.get_or_insert(e);
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Describe a task that panicked or was cancelled
+fn join_error(name: &str, e: JoinError) -> StreamError {
+    if e.is_cancelled() {
+        return StreamError::Runtime(format!("{} was aborted", name));
+    }
+    let message = panic_message(&*e.into_panic());
+    StreamError::Runtime(format!("{} panicked: {}", name, message))
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic payload".to_string())
+}
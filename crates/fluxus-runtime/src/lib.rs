@@ -1,11 +1,24 @@
 //! Fluxus Runtime - Execution engine for stream processing
 //!
 //! This module implements the runtime execution environment for Fluxus pipelines.
+mod job;
+mod registry;
 mod runtime;
-pub use runtime::RuntimeContext;
+pub use job::{JobHandle, StageHandle, TaskExit};
+pub use registry::{JobRegistry, RegisteredJob};
+pub use runtime::{OperatorFactory, RuntimeContext, SharedOperator, StageOperator};
+
+/// Distribution of records to parallel operator instances
+pub mod partition;
 
 /// State management for stateful operators
 pub mod state;
 
 /// Watermark tracking and propagation
 pub mod watermark;
+
+/// Tenant isolation and quotas for shared pipelines
+pub mod tenancy;
+
+/// Detection of stuck operators and slow sinks
+pub mod watchdog;
@@ -0,0 +1,302 @@
+use fluxus_utils::memory::MemoryAccount;
+use fluxus_utils::models::Record;
+use std::mem::size_of;
+use std::sync::Arc;
+use tokio::sync::{Mutex, mpsc, watch};
+
+type HashFn<T> = Arc<dyn Fn(&T) -> u64 + Send + Sync>;
+
+/// How records are distributed to the parallel instances of an operator
+#[derive(Default)]
+pub enum Partitioning<T> {
+    /// All instances pull from one shared queue, whichever is free takes the next record
+    #[default]
+    Shared,
+    /// Every upstream task hands out records round-robin to all instances
+    Rebalance,
+    /// Every upstream task hands out records round-robin to its local subset
+    /// of instances, a single one when both stages have the same parallelism
+    Rescale,
+    /// Records with the same hash always go to the same instance
+    Hash(HashFn<T>),
+}
+
+impl<T> Partitioning<T> {
+    /// Partition records by the hash returned from the given function
+    pub fn hash<F>(f: F) -> Self
+    where
+        F: Fn(&T) -> u64 + Send + Sync + 'static,
+    {
+        Self::Hash(Arc::new(f))
+    }
+}
+
+impl<T> Clone for Partitioning<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Shared => Self::Shared,
+            Self::Rebalance => Self::Rebalance,
+            Self::Rescale => Self::Rescale,
+            Self::Hash(f) => Self::Hash(f.clone()),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Partitioning<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Shared => write!(f, "Shared"),
+            Self::Rebalance => write!(f, "Rebalance"),
+            Self::Rescale => write!(f, "Rescale"),
+            Self::Hash(_) => write!(f, "Hash"),
+        }
+    }
+}
+
+/// What flows from one stage of a pipeline to the next
+pub(crate) enum Element<T> {
+    Record(Record<T>),
+    /// The event-time watermark of the source advanced
+    Watermark(i64),
+}
+
+/// Receiving end of one parallel instance
+pub(crate) enum StageInput<T> {
+    /// The queue shared by all instances, where the instance that takes a
+    /// watermark passes it on to the others through `watermark`
+    Shared {
+        rx: Arc<Mutex<mpsc::Receiver<Element<T>>>>,
+        watermark: Arc<watch::Sender<i64>>,
+        seen: watch::Receiver<i64>,
+    },
+    Owned(mpsc::Receiver<Element<T>>),
+}
+
+impl<T> StageInput<T> {
+    /// Receive the next element, releasing it from the account of the
+    /// channels if given
+    pub(crate) async fn recv(&mut self, memory: Option<&MemoryAccount>) -> Option<Element<T>> {
+        let release = |element: &Option<Element<T>>| {
+            if let (Some(_), Some(memory)) = (element, memory) {
+                memory.release(Element::<T>::SIZE);
+            }
+        };
+        match self {
+            Self::Shared {
+                rx,
+                watermark,
+                seen,
+            } => loop {
+                tokio::select! {
+                    biased;
+                    Ok(()) = seen.changed() => {
+                        return Some(Element::Watermark(*seen.borrow_and_update()));
+                    }
+                    element = async { rx.lock().await.recv().await } => {
+                        release(&element);
+                        match element {
+                            Some(Element::Watermark(next)) => {
+                                watermark.send_if_modified(|current| {
+                                    let advanced = next > *current;
+                                    if advanced {
+                                        *current = next;
+                                    }
+                                    advanced
+                                });
+                            }
+                            element => return element,
+                        }
+                    }
+                }
+            },
+            Self::Owned(rx) => {
+                let element = rx.recv().await;
+                release(&element);
+                element
+            }
+        }
+    }
+}
+
+/// The watermarks the parallel instances of a stage have passed
+pub(crate) struct WatermarkMerge {
+    /// The watermark of every instance, and the last one forwarded
+    state: std::sync::Mutex<(Vec<i64>, i64)>,
+}
+
+impl WatermarkMerge {
+    pub(crate) fn new(instances: usize) -> Self {
+        Self {
+            state: std::sync::Mutex::new((vec![i64::MIN; instances], i64::MIN)),
+        }
+    }
+
+    /// Record that an instance passed a watermark, `i64::MAX` once its input
+    /// ended, returning the minimum watermark across the instances if it
+    /// advanced past the last one returned
+    pub(crate) fn advance(&self, instance: usize, watermark: i64) -> Option<i64> {
+        let mut state = self.state.lock().unwrap();
+        let (watermarks, forwarded) = &mut *state;
+        watermarks[instance] = watermarks[instance].max(watermark);
+        let min = watermarks.iter().copied().min().unwrap_or(i64::MAX);
+        if min <= *forwarded || min == i64::MAX {
+            return None;
+        }
+        *forwarded = min;
+        Some(min)
+    }
+}
+
+impl<T> Element<T> {
+    /// Bytes an element takes in a channel, without the heap memory of its
+    /// record, which the runtime cannot estimate for any type
+    pub(crate) const SIZE: usize = size_of::<Element<T>>();
+}
+
+/// Create the channels feeding `parallelism` instances with the given partitioning
+pub(crate) fn stage_channels<T>(
+    partitioning: &Partitioning<T>,
+    parallelism: usize,
+    buffer_size: usize,
+) -> (Vec<mpsc::Sender<Element<T>>>, Vec<StageInput<T>>) {
+    let parallelism = parallelism.max(1);
+    match partitioning {
+        Partitioning::Shared => {
+            let (tx, rx) = mpsc::channel(buffer_size);
+            let rx = Arc::new(Mutex::new(rx));
+            let (watermark, seen) = watch::channel(i64::MIN);
+            let watermark = Arc::new(watermark);
+            let inputs = (0..parallelism)
+                .map(|_| StageInput::Shared {
+                    rx: rx.clone(),
+                    watermark: watermark.clone(),
+                    seen: seen.clone(),
+                })
+                .collect();
+            (vec![tx], inputs)
+        }
+        _ => (0..parallelism)
+            .map(|_| {
+                let (tx, rx) = mpsc::channel(buffer_size);
+                (tx, StageInput::Owned(rx))
+            })
+            .unzip(),
+    }
+}
+
+/// Chooses the instance each record of one upstream task goes to
+pub struct Partitioner<T> {
+    partitioning: Partitioning<T>,
+    instances: usize,
+    /// Instances this upstream task sends to in round-robin order
+    targets: Vec<usize>,
+    next: usize,
+}
+
+impl<T> Partitioner<T> {
+    /// Create the partitioner of upstream task `upstream` out of
+    /// `upstream_count`, sending to `instances` instances
+    pub fn new(
+        partitioning: Partitioning<T>,
+        instances: usize,
+        upstream: usize,
+        upstream_count: usize,
+    ) -> Self {
+        let instances = instances.max(1);
+        let upstream_count = upstream_count.max(1);
+        let targets = match partitioning {
+            Partitioning::Rescale => {
+                let local: Vec<usize> = (0..instances)
+                    .filter(|j| j % upstream_count == upstream % upstream_count)
+                    .collect();
+                if local.is_empty() {
+                    vec![upstream % instances]
+                } else {
+                    local
+                }
+            }
+            _ => (0..instances).collect(),
+        };
+        Self {
+            partitioning,
+            instances,
+            targets,
+            // Stagger the starting point so that upstream tasks do not send in lockstep
+            next: upstream,
+        }
+    }
+
+    /// The instance the record goes to, always the first one for
+    /// [`Partitioning::Shared`], whose instances read from one queue
+    pub fn partition(&mut self, value: &T) -> usize {
+        match &self.partitioning {
+            Partitioning::Shared => 0,
+            Partitioning::Hash(f) => (f(value) % self.instances as u64) as usize,
+            Partitioning::Rebalance | Partitioning::Rescale => {
+                let index = self.targets[self.next % self.targets.len()];
+                self.next = self.next.wrapping_add(1);
+                index
+            }
+        }
+    }
+}
+
+/// Sending end of one upstream task, choosing the target instance per record
+pub(crate) struct Dispatcher<T> {
+    senders: Vec<mpsc::Sender<Element<T>>>,
+    partitioner: Partitioner<T>,
+    /// Account of the elements queued in the channels
+    memory: Option<MemoryAccount>,
+}
+
+impl<T> Dispatcher<T> {
+    /// Create the dispatcher of upstream task `upstream` out of `upstream_count`
+    pub(crate) fn new(
+        senders: Vec<mpsc::Sender<Element<T>>>,
+        partitioning: Partitioning<T>,
+        upstream: usize,
+        upstream_count: usize,
+    ) -> Self {
+        let partitioner = Partitioner::new(partitioning, senders.len(), upstream, upstream_count);
+        Self {
+            senders,
+            partitioner,
+            memory: None,
+        }
+    }
+
+    /// Account for the elements sent until they are received
+    pub(crate) fn with_memory(mut self, memory: Option<MemoryAccount>) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    /// Send an element to an instance, returning false once it is gone
+    async fn send_to(&self, index: usize, element: Element<T>) -> bool {
+        if let Some(memory) = &self.memory {
+            memory.add(Element::<T>::SIZE);
+        }
+        let sent = self.senders[index].send(element).await.is_ok();
+        if let (false, Some(memory)) = (sent, &self.memory) {
+            memory.release(Element::<T>::SIZE);
+        }
+        sent
+    }
+
+    /// Send a record, returning false once the downstream stage is gone
+    pub(crate) async fn send(&mut self, record: Record<T>) -> bool {
+        let index = self.partitioner.partition(&record.data);
+        self.send_to(index, Element::Record(record)).await
+    }
+
+    /// Send a watermark to every instance, returning false once the
+    /// downstream stage is gone
+    pub(crate) async fn send_watermark(&mut self, watermark: i64) -> bool {
+        for index in 0..self.senders.len() {
+            if !self.send_to(index, Element::Watermark(watermark)).await {
+                return false;
+            }
+        }
+        true
+    }
+}
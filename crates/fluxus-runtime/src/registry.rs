@@ -0,0 +1,150 @@
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use fluxus_core::{MetricValue, Metrics};
+use fluxus_utils::models::{StreamError, StreamResult};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::job::{JobHandle, StageHandle};
+
+struct JobEntry {
+    name: String,
+    id: String,
+    stages: Vec<StageHandle>,
+    metrics: OnceLock<Arc<Metrics>>,
+}
+
+/// A job in the [`JobRegistry`], which can be inspected and stopped from
+/// anywhere in the process
+#[derive(Clone)]
+pub struct RegisteredJob {
+    entry: Arc<JobEntry>,
+}
+
+impl RegisteredJob {
+    fn new(name: String, job: &JobHandle) -> Self {
+        Self {
+            entry: Arc::new(JobEntry {
+                name,
+                id: job.id().to_string(),
+                stages: job.stages(),
+                metrics: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Name the job was registered under
+    pub fn name(&self) -> &str {
+        &self.entry.name
+    }
+
+    /// Identifier of the pipeline, see [`JobHandle::id`]
+    pub fn id(&self) -> &str {
+        &self.entry.id
+    }
+
+    /// Handles to the stages of the job, from the source to the sink
+    pub fn stages(&self) -> Vec<StageHandle> {
+        self.entry.stages.clone()
+    }
+
+    /// Names of the stages of the job, from the source to the sink
+    pub fn topology(&self) -> Vec<String> {
+        self.entry
+            .stages
+            .iter()
+            .map(|stage| stage.name().to_string())
+            .collect()
+    }
+
+    /// Whether all tasks of the job have stopped
+    pub fn is_finished(&self) -> bool {
+        self.entry.stages.iter().all(StageHandle::is_finished)
+    }
+
+    /// Stop all tasks of the job
+    pub fn abort(&self) {
+        self.entry.stages.iter().for_each(StageHandle::abort);
+    }
+
+    /// Publish the metrics of the job with the registry. Only the first
+    /// metrics reported are kept.
+    pub fn report_metrics(&self, metrics: Arc<Metrics>) {
+        if self.entry.metrics.set(metrics).is_err() {
+            tracing::warn!("Job {} already reports metrics", self.entry.name);
+        }
+    }
+
+    /// Snapshot of the metrics of the job, if it reports any
+    pub fn metrics(&self) -> Option<HashMap<String, MetricValue>> {
+        self.entry.metrics.get().map(|metrics| metrics.snapshot())
+    }
+}
+
+/// Process-wide registry of running jobs by name, e.g. to look up a job
+/// started elsewhere with `JobRegistry::get("wordcount")`.
+///
+/// Every pipeline started by a [`RuntimeContext`](crate::RuntimeContext) is
+/// registered under its id, and can be registered under a name of its own
+/// with [`register`](Self::register). Finished jobs stay registered under
+/// their name until a new job takes the name or they are removed.
+pub struct JobRegistry;
+
+impl JobRegistry {
+    fn jobs() -> &'static DashMap<String, RegisteredJob> {
+        static JOBS: OnceLock<DashMap<String, RegisteredJob>> = OnceLock::new();
+        JOBS.get_or_init(DashMap::new)
+    }
+
+    /// Register a job under `name`, failing if a job that is still running
+    /// holds the name
+    pub fn register(name: impl Into<String>, job: &JobHandle) -> StreamResult<RegisteredJob> {
+        let name = name.into();
+        let registered = RegisteredJob::new(name.clone(), job);
+        match Self::jobs().entry(name) {
+            Entry::Occupied(mut entry) => {
+                if entry.get().id() != job.id() && !entry.get().is_finished() {
+                    return Err(StreamError::Config(format!(
+                        "a running job is already registered as {}",
+                        entry.key()
+                    )));
+                }
+                entry.insert(registered.clone());
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(registered.clone());
+            }
+        }
+        Ok(registered)
+    }
+
+    /// Register a job started by the runtime under its id, dropping the
+    /// finished jobs registered under their id
+    pub(crate) fn register_id(job: &JobHandle) -> RegisteredJob {
+        Self::jobs()
+            .retain(|name, registered| name != registered.id() || !registered.is_finished());
+        let registered = RegisteredJob::new(job.id().to_string(), job);
+        Self::jobs().insert(job.id().to_string(), registered.clone());
+        registered
+    }
+
+    /// The job registered under `name`
+    pub fn get(name: &str) -> Option<RegisteredJob> {
+        Self::jobs().get(name).map(|entry| entry.value().clone())
+    }
+
+    /// Names of all registered jobs, in order
+    pub fn names() -> Vec<String> {
+        let mut names: Vec<String> = Self::jobs()
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Remove the job registered under `name`, without stopping it
+    pub fn remove(name: &str) -> Option<RegisteredJob> {
+        Self::jobs().remove(name).map(|(_, job)| job)
+    }
+}
@@ -1,168 +1,553 @@
-use dashmap::DashMap;
 use fluxus_core::ParallelConfig;
 use fluxus_sinks::Sink;
 use fluxus_sources::Source;
 use fluxus_transformers::Operator;
-use fluxus_utils::models::{Record, StreamResult};
+use fluxus_utils::memory::MemoryAccount;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::future::Future;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::sync::{Mutex, mpsc};
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
+use crate::job::{JobHandle, JobTask};
+use crate::partition::{
+    Dispatcher, Element, Partitioning, StageInput, WatermarkMerge, stage_channels,
+};
+use crate::registry::{JobRegistry, RegisteredJob};
+use crate::watchdog::{Progress, Watchdog, WatchdogAction, WatchdogConfig};
+
+/// An operator shared by the parallel instances of a stage
+pub type SharedOperator<T> = Arc<Mutex<dyn Operator<T, T> + Send + Sync>>;
+
+/// Creates the operator of one parallel instance of a stage
+pub type OperatorFactory<T> = Arc<dyn Fn() -> SharedOperator<T> + Send + Sync>;
+
+/// The operator of a pipeline stage
+pub enum StageOperator<T> {
+    /// One operator fed by all instances of the stage. It sees a watermark
+    /// once every instance has passed it and is flushed when the last
+    /// instance runs out of input.
+    Shared(SharedOperator<T>),
+    /// An operator per instance, so that state follows the partitioning of
+    /// the stage, e.g. all records of a key with [`Partitioning::Hash`]
+    PerInstance(OperatorFactory<T>),
+}
+
+impl<T> StageOperator<T> {
+    /// Give every instance its own operator created by `factory`
+    pub fn per_instance<O, F>(factory: F) -> Self
+    where
+        O: Operator<T, T> + Send + Sync + 'static,
+        F: Fn() -> O + Send + Sync + 'static,
+    {
+        Self::PerInstance(Arc::new(move || Arc::new(Mutex::new(factory()))))
+    }
+
+    /// The operators of `instances` instances, the same one for all
+    /// instances of a shared operator
+    fn instances(&self, instances: usize) -> Vec<SharedOperator<T>> {
+        match self {
+            Self::Shared(operator) => vec![operator.clone(); instances],
+            Self::PerInstance(factory) => (0..instances).map(|_| factory()).collect(),
+        }
+    }
+}
+
 /// Runtime context for managing stream processing execution
 pub struct RuntimeContext {
     /// Task parallelism configuration
     parallel_config: ParallelConfig,
-    /// Active task handles
-    task_handles: Arc<DashMap<String, Vec<JoinHandle<()>>>>,
+    /// Pipelines started by this context, also in the [`JobRegistry`]
+    jobs: std::sync::Mutex<Vec<RegisteredJob>>,
+    /// Stuck task detection, disabled by default
+    watchdog: Option<WatchdogConfig>,
+    /// Account of the elements queued between the stages of the pipelines
+    memory: Option<MemoryAccount>,
 }
 
 impl RuntimeContext {
     pub fn new(parallel_config: ParallelConfig) -> Self {
         Self {
             parallel_config,
-            task_handles: Arc::new(DashMap::new()),
+            jobs: std::sync::Mutex::new(Vec::new()),
+            watchdog: None,
+            memory: None,
         }
     }
 
-    /// Execute a source-to-sink pipeline with operators
+    /// Monitor operator and sink calls of every pipeline for stalls
+    pub fn with_watchdog(mut self, config: WatchdogConfig) -> Self {
+        self.watchdog = Some(config);
+        self
+    }
+
+    /// Account for the elements queued in the channels between the stages
+    /// of every pipeline, e.g. with an account of
+    /// [`Metrics::job_memory`](fluxus_core::Metrics::job_memory).
+    ///
+    /// Elements are estimated by their inline size, without the heap memory
+    /// their records own, so the bytes of records with strings or buffers
+    /// are underestimated.
+    pub fn with_memory_account(mut self, account: MemoryAccount) -> Self {
+        self.memory = Some(account);
+        self
+    }
+
+    /// Start a source-to-sink pipeline with operators
     pub async fn execute_pipeline<T, S, K>(
         &self,
         source: S,
-        operators: Vec<Arc<Mutex<dyn Operator<T, T> + Send + Sync>>>,
+        operators: Vec<SharedOperator<T>>,
         sink: K,
-    ) -> StreamResult<()>
+    ) -> StreamResult<JobHandle>
+    where
+        T: Clone + Send + Sync + 'static,
+        S: Source<T> + Send + Sync + 'static,
+        K: Sink<T> + Send + Sync + 'static,
+    {
+        let stages = operators
+            .into_iter()
+            .map(|operator| (StageOperator::Shared(operator), Partitioning::Shared))
+            .collect();
+        self.execute_partitioned_pipeline(source, stages, sink)
+            .await
+    }
+
+    /// Start a pipeline where each operator declares how records are distributed
+    /// to its parallel instances.
+    ///
+    /// Each instance forwards a watermark once all instances of its stage
+    /// have passed it, so that no instance still holds earlier records.
+    pub async fn execute_partitioned_pipeline<T, S, K>(
+        &self,
+        source: S,
+        stages: Vec<(StageOperator<T>, Partitioning<T>)>,
+        sink: K,
+    ) -> StreamResult<JobHandle>
     where
         T: Clone + Send + Sync + 'static,
         S: Source<T> + Send + Sync + 'static,
         K: Sink<T> + Send + Sync + 'static,
     {
-        let (tx, rx) = mpsc::channel(self.parallel_config.buffer_size);
+        let parallelism = self.parallel_config.parallelism.max(1);
+        let buffer_size = self.parallel_config.buffer_size;
         let source = Arc::new(Mutex::new(source));
         let sink = Arc::new(Mutex::new(sink));
+        let watchdog = Watchdog::new(self.watchdog.clone().unwrap_or_default());
+
+        // Create the input channels of every operator stage and of the sink
+        let mut channels = Vec::with_capacity(stages.len() + 1);
+        for (_, partitioning) in &stages {
+            let (senders, inputs) = stage_channels(partitioning, parallelism, buffer_size);
+            channels.push((senders, partitioning.clone(), inputs));
+        }
+        let (sink_tx, sink_rx) = mpsc::channel(buffer_size);
+        channels.push((vec![sink_tx], Partitioning::Shared, Vec::new()));
 
         // Spawn source task
-        let source_handle = self.spawn_source_task(source.clone(), tx.clone());
-
-        // Create channels for operator pipeline
-        let mut curr_rx = rx;
-        let mut handles = vec![source_handle];
-
-        // Spawn operator tasks
-        for operator in operators {
-            let (new_tx, new_rx) = mpsc::channel(self.parallel_config.buffer_size);
-            let operator_handles = self.spawn_operator_tasks(operator, curr_rx, new_tx);
-            handles.extend(operator_handles);
-            curr_rx = new_rx;
+        let (senders, partitioning, _) = &channels[0];
+        let dispatcher = Dispatcher::new(senders.clone(), partitioning.clone(), 0, 1)
+            .with_memory(self.memory.clone());
+        let mut tasks = vec![JobTask::spawn(
+            "source".to_string(),
+            Self::source_task(source.clone(), dispatcher),
+        )];
+
+        // Spawn operator tasks, each stage sends to the inputs of the next one
+        let mut channels = channels.into_iter();
+        let mut current = channels.next();
+        for (index, (operator, _)) in stages.into_iter().enumerate() {
+            let (_, _, inputs) = current.take().expect("stage channels");
+            let next = channels.next().expect("downstream channels");
+            let shared = matches!(operator, StageOperator::Shared(_));
+            let operators = operator.instances(inputs.len());
+            let name = format!("operator[{}] {}", index, operators[0].lock().await.name());
+            let progress = watchdog.track(name.clone());
+            let dispatchers = (0..inputs.len())
+                .map(|i| {
+                    Dispatcher::new(next.0.clone(), next.1.clone(), i, inputs.len())
+                        .with_memory(self.memory.clone())
+                })
+                .collect();
+            let instances = self.operator_tasks(operators, shared, inputs, dispatchers, progress);
+            tasks.extend(
+                instances
+                    .into_iter()
+                    .map(|instance| JobTask::spawn(name.clone(), instance)),
+            );
+            current = Some(next);
         }
+        // Release the senders held here so that channels close when their producers finish
+        drop(current);
 
         // Spawn sink task
-        let sink_handle = self.spawn_sink_task(sink.clone(), curr_rx);
-        handles.push(sink_handle);
+        tasks.push(JobTask::spawn(
+            "sink".to_string(),
+            Self::sink_task(
+                sink.clone(),
+                sink_rx,
+                watchdog.track("sink"),
+                self.memory.clone(),
+            ),
+        ));
 
-        // Store handles
-        self.task_handles
-            .insert(Uuid::new_v4().to_string(), handles);
+        let pipeline_id = Uuid::new_v4().to_string();
+        let job = JobHandle::new(pipeline_id, tasks);
+        let registered = JobRegistry::register_id(&job);
+        self.jobs.lock().unwrap().push(registered.clone());
 
-        Ok(())
+        if self.watchdog.is_some() {
+            self.spawn_watchdog_task(watchdog, registered);
+        }
+
+        Ok(job)
     }
 
-    fn spawn_source_task<T, S>(
-        &self,
+    fn spawn_watchdog_task(&self, watchdog: Watchdog, job: RegisteredJob) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let pipeline_id = job.id();
+            let config = watchdog.config().clone();
+            loop {
+                tokio::time::sleep(config.check_interval).await;
+
+                if job.is_finished() {
+                    break;
+                }
+
+                let stalls = watchdog.check();
+                for stall in &stalls {
+                    tracing::warn!(
+                        "Pipeline {}: {} made no progress for {:?}",
+                        pipeline_id,
+                        stall.name,
+                        stall.elapsed
+                    );
+                }
+
+                if !stalls.is_empty() && config.action == WatchdogAction::Abort {
+                    tracing::error!("Pipeline {}: aborting stalled tasks", pipeline_id);
+                    job.abort();
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Whether all tasks of every pipeline have stopped
+    pub fn is_finished(&self) -> bool {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .all(RegisteredJob::is_finished)
+    }
+
+    async fn source_task<T, S>(
         source: Arc<Mutex<S>>,
-        tx: mpsc::Sender<Record<T>>,
-    ) -> JoinHandle<()>
+        mut dispatcher: Dispatcher<T>,
+    ) -> StreamResult<()>
     where
         T: Clone + Send + 'static,
         S: Source<T> + Send + 'static,
     {
-        tokio::spawn(async move {
-            loop {
-                let mut source_guard = source.lock().await;
-                match source_guard.next().await {
-                    Ok(Some(record)) => {
-                        if tx.send(record).await.is_err() {
+        let mut result = Ok(());
+        let mut watermark = None;
+        loop {
+            let mut source_guard = source.lock().await;
+            match source_guard.next().await {
+                Ok(Some(record)) => {
+                    if !dispatcher.send(record).await {
+                        break;
+                    }
+                    // Pass the watermark on behind the record that advanced it
+                    let advanced = source_guard
+                        .watermark()
+                        .filter(|&next| watermark.is_none_or(|current| next > current));
+                    if let Some(next) = advanced {
+                        watermark = Some(next);
+                        if !dispatcher.send_watermark(next).await {
                             break;
                         }
                     }
-                    _ => break,
+                }
+                Ok(None) | Err(StreamError::EOF) => break,
+                Err(StreamError::Wait(ms)) => {
+                    drop(source_guard);
+                    tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
+                }
+                Err(e) => {
+                    result = Err(stage_error(e, "source", None, StreamError::source_error));
+                    break;
                 }
             }
-            let mut source_guard = source.lock().await;
-            if let Err(e) = source_guard.close().await {
-                tracing::error!("Error closing source: {:?}", e);
-            }
-        })
+        }
+        let mut source_guard = source.lock().await;
+        if let Err(e) = source_guard.close().await {
+            tracing::error!("Error closing source: {:?}", e);
+            let e = stage_error(e, "source", None, StreamError::source_error);
+            result = result.and(Err(e));
+        }
+        result
     }
 
-    fn spawn_operator_tasks<T>(
+    fn operator_tasks<T>(
         &self,
-        operator: Arc<Mutex<dyn Operator<T, T> + Send + Sync>>,
-        rx: mpsc::Receiver<Record<T>>,
-        tx: mpsc::Sender<Record<T>>,
-    ) -> Vec<JoinHandle<()>>
+        operators: Vec<SharedOperator<T>>,
+        shared: bool,
+        inputs: Vec<StageInput<T>>,
+        dispatchers: Vec<Dispatcher<T>>,
+        progress: Progress,
+    ) -> Vec<impl Future<Output = StreamResult<()>> + Send + 'static + use<T>>
     where
         T: Clone + Send + 'static,
     {
-        let mut handles = Vec::new();
-        let rx = Arc::new(Mutex::new(rx));
-
-        for _ in 0..self.parallel_config.parallelism {
-            let operator = Arc::clone(&operator);
-            let rx = Arc::clone(&rx);
-            let tx = tx.clone();
-
-            let handle = tokio::spawn(async move {
-                loop {
-                    let record = {
-                        let mut rx = rx.lock().await;
-                        match rx.recv().await {
-                            Some(r) => r,
-                            None => break,
+        let mut instances = Vec::new();
+        // Watermarks are forwarded once every instance has passed them. A
+        // shared operator also sees them only then, and the last instance to
+        // run out of input flushes it
+        let watermarks = Arc::new(WatermarkMerge::new(inputs.len()));
+        let running = Arc::new(AtomicUsize::new(inputs.len()));
+
+        let tasks = operators.into_iter().zip(inputs).zip(dispatchers);
+        for (instance, ((operator, mut input), mut dispatcher)) in tasks.enumerate() {
+            let progress = progress.clone();
+            let watermarks = Arc::clone(&watermarks);
+            let running = Arc::clone(&running);
+            let memory = self.memory.clone();
+
+            let instance = async move {
+                let mut input_watermark = i64::MIN;
+                while let Some(element) = input.recv(memory.as_ref()).await {
+                    let record = match element {
+                        Element::Record(record) => record,
+                        Element::Watermark(next) => {
+                            if next <= input_watermark {
+                                continue;
+                            }
+                            input_watermark = next;
+                            let forwarded = Self::pass_watermark(
+                                &operator,
+                                shared,
+                                &watermarks,
+                                instance,
+                                next,
+                                &progress,
+                                &mut dispatcher,
+                            )
+                            .await;
+                            if !forwarded {
+                                return Ok(());
+                            }
+                            continue;
                         }
                     };
 
                     let mut op = operator.lock().await;
-                    if let Ok(results) = op.process(record).await {
-                        for result in results {
-                            if tx.send(result).await.is_err() {
-                                return;
-                            }
+                    let timestamp = record.timestamp;
+                    let results = {
+                        let _guard = progress.enter();
+                        op.process(record).await
+                    };
+                    // A failed record is dropped, the operator keeps processing
+                    let results = match results {
+                        Ok(results) => results,
+                        Err(e) => {
+                            let e = e.or_context(op.name(), Some(timestamp));
+                            tracing::warn!("Operator {} failed on a record: {}", op.name(), e);
+                            continue;
+                        }
+                    };
+                    drop(op);
+                    for result in results {
+                        if !dispatcher.send(result).await {
+                            return Ok(());
                         }
                     }
                 }
-            });
-            handles.push(handle);
+
+                // An instance without input no longer holds back the watermark
+                if !shared {
+                    let mut op = operator.lock().await;
+                    let results = {
+                        let _guard = progress.enter();
+                        op.on_end_of_input().await
+                    };
+                    let results = results.map_err(|e| e.or_context(op.name(), None))?;
+                    drop(op);
+                    for result in results {
+                        if !dispatcher.send(result).await {
+                            return Ok(());
+                        }
+                    }
+                }
+                let forwarded = Self::pass_watermark(
+                    &operator,
+                    shared,
+                    &watermarks,
+                    instance,
+                    i64::MAX,
+                    &progress,
+                    &mut dispatcher,
+                )
+                .await;
+                if shared && forwarded && running.fetch_sub(1, Ordering::SeqCst) == 1 {
+                    let mut op = operator.lock().await;
+                    let results = {
+                        let _guard = progress.enter();
+                        op.on_end_of_input().await
+                    };
+                    let results = results.map_err(|e| e.or_context(op.name(), None))?;
+                    drop(op);
+                    for result in results {
+                        if !dispatcher.send(result).await {
+                            break;
+                        }
+                    }
+                }
+                Ok(())
+            };
+            instances.push(instance);
         }
 
-        handles
+        instances
     }
 
-    fn spawn_sink_task<T, K>(
-        &self,
+    /// Record that an instance passed a watermark and forward the minimum
+    /// watermark of the stage if it advanced, returning false once the
+    /// downstream stage is gone.
+    ///
+    /// An operator of its own sees the watermark of the instance right away.
+    /// A shared operator sees the minimum, and keeps its lock until the
+    /// watermark is forwarded behind the records it emitted.
+    async fn pass_watermark<T>(
+        operator: &SharedOperator<T>,
+        shared: bool,
+        watermarks: &WatermarkMerge,
+        instance: usize,
+        watermark: i64,
+        progress: &Progress,
+        dispatcher: &mut Dispatcher<T>,
+    ) -> bool {
+        if !shared {
+            // The end of the input was already passed to the operator
+            if watermark != i64::MAX {
+                let mut op = operator.lock().await;
+                let results = Self::on_watermark(&mut *op, watermark, progress).await;
+                drop(op);
+                for result in results {
+                    if !dispatcher.send(result).await {
+                        return false;
+                    }
+                }
+            }
+            return match watermarks.advance(instance, watermark) {
+                Some(next) => dispatcher.send_watermark(next).await,
+                None => true,
+            };
+        }
+
+        let mut op = operator.lock().await;
+        let Some(next) = watermarks.advance(instance, watermark) else {
+            return true;
+        };
+        let results = Self::on_watermark(&mut *op, next, progress).await;
+        for result in results {
+            if !dispatcher.send(result).await {
+                return false;
+            }
+        }
+        dispatcher.send_watermark(next).await
+    }
+
+    /// Pass a watermark to an operator, logging a failure like a failed record
+    async fn on_watermark<T>(
+        op: &mut (dyn Operator<T, T> + Send + Sync),
+        watermark: i64,
+        progress: &Progress,
+    ) -> Vec<Record<T>> {
+        let results = {
+            let _guard = progress.enter();
+            op.on_watermark(watermark).await
+        };
+        results.unwrap_or_else(|e| {
+            let e = e.or_context(op.name(), Some(watermark));
+            tracing::warn!("Operator {} failed on a watermark: {}", op.name(), e);
+            Vec::new()
+        })
+    }
+
+    async fn sink_task<T, K>(
         sink: Arc<Mutex<K>>,
-        mut rx: mpsc::Receiver<Record<T>>,
-    ) -> JoinHandle<()>
+        mut rx: mpsc::Receiver<Element<T>>,
+        progress: Progress,
+        memory: Option<MemoryAccount>,
+    ) -> StreamResult<()>
     where
         T: Clone + Send + 'static,
         K: Sink<T> + Send + 'static,
     {
-        tokio::spawn(async move {
-            while let Some(record) = rx.recv().await {
-                let mut sink_guard = sink.lock().await;
-                if let Err(e) = sink_guard.write(record).await {
-                    tracing::error!("Error writing to sink: {:?}", e);
-                }
+        let mut result = Ok(());
+        while let Some(element) = rx.recv().await {
+            if let Some(memory) = &memory {
+                memory.release(Element::<T>::SIZE);
             }
-
+            let Element::Record(record) = element else {
+                continue;
+            };
             let mut sink_guard = sink.lock().await;
-            if let Err(e) = sink_guard.flush().await {
-                tracing::error!("Error flushing sink: {:?}", e);
+            let _guard = progress.enter();
+            let timestamp = record.timestamp;
+            if let Err(e) = sink_guard.write(record).await {
+                tracing::error!("Error writing to sink: {:?}", e);
+                result = Err(stage_error(
+                    e,
+                    "sink",
+                    Some(timestamp),
+                    StreamError::sink_error,
+                ));
+                break;
             }
-
-            if let Err(e) = sink_guard.close().await {
-                tracing::error!("Error closing sink: {:?}", e);
+        }
+        // Stop upstream tasks once the sink has failed
+        rx.close();
+        while rx.try_recv().is_ok() {
+            if let Some(memory) = &memory {
+                memory.release(Element::<T>::SIZE);
             }
-        })
+        }
+        drop(rx);
+
+        let mut sink_guard = sink.lock().await;
+        if result.is_ok()
+            && let Err(e) = sink_guard.flush().await
+        {
+            tracing::error!("Error flushing sink: {:?}", e);
+            result = Err(stage_error(e, "sink", None, StreamError::sink_error));
+        }
+
+        if let Err(e) = sink_guard.close().await {
+            tracing::error!("Error closing sink: {:?}", e);
+            let e = stage_error(e, "sink", None, StreamError::sink_error);
+            result = result.and(Err(e));
+        }
+        result
+    }
+}
+
+/// Attribute a failure to a stage, wrapping errors without context with `wrap`
+fn stage_error(
+    e: StreamError,
+    stage: &str,
+    timestamp: Option<i64>,
+    wrap: fn(String) -> StreamError,
+) -> StreamError {
+    if e.context().is_some() {
+        return e.or_context(stage, timestamp);
     }
+    let retryable = e.is_retryable();
+    wrap(e.to_string())
+        .with_retryable(retryable)
+        .or_context(stage, timestamp)
 }
@@ -1,32 +1,221 @@
-use parking_lot::RwLock;
-use std::collections::HashMap;
+use dashmap::DashMap;
+use fluxus_utils::memory::{MemoryAccount, MemorySize};
 use std::hash::Hash;
 use std::sync::Arc;
 
+type SizeFn<T> = Arc<dyn Fn(&T) -> usize + Send + Sync>;
+
+/// The account of the memory of a state backend and how to estimate it
+struct StateMemory<K, V> {
+    account: MemoryAccount,
+    key_size: SizeFn<K>,
+    value_size: SizeFn<V>,
+}
+
+impl<K, V> Clone for StateMemory<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            account: self.account.clone(),
+            key_size: self.key_size.clone(),
+            value_size: self.value_size.clone(),
+        }
+    }
+}
+
 /// Simple key-value state backend
-#[derive(Default)]
+///
+/// Keys are spread over shards that are locked independently, so parallel
+/// operator instances working on different keys rarely contend. Clones share
+/// the same state.
 pub struct KeyedStateBackend<K, V> {
-    state: Arc<RwLock<HashMap<K, V>>>,
+    state: Arc<DashMap<K, V>>,
+    memory: Option<StateMemory<K, V>>,
+}
+
+impl<K, V> Clone for KeyedStateBackend<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            memory: self.memory.clone(),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V> Default for KeyedStateBackend<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl<K, V> KeyedStateBackend<K, V>
 where
     K: Eq + Hash,
 {
+    /// Create a backend with a shard count based on the number of cores
     pub fn new() -> Self {
         Self {
-            state: Arc::new(RwLock::new(HashMap::new())),
+            state: Arc::new(DashMap::new()),
+            memory: None,
         }
     }
 
+    /// Create a backend with at least `shards` shards, rounded up to a power of two
+    pub fn with_shards(shards: usize) -> Self {
+        Self {
+            state: Arc::new(DashMap::with_shard_amount(
+                shards.max(2).next_power_of_two(),
+            )),
+            memory: None,
+        }
+    }
+
+    /// Account for the estimated memory of the entries. Entries present
+    /// before are not accounted for.
+    pub fn with_memory_account(self, account: MemoryAccount) -> Self
+    where
+        K: MemorySize + 'static,
+        V: MemorySize + 'static,
+    {
+        self.with_memory_estimate(account, K::memory_size, V::memory_size)
+    }
+
+    /// Account for the memory of the entries as estimated by the given
+    /// functions, for keys or values without a [`MemorySize`] estimate.
+    /// Entries present before are not accounted for.
+    pub fn with_memory_estimate<KS, VS>(
+        mut self,
+        account: MemoryAccount,
+        key_size: KS,
+        value_size: VS,
+    ) -> Self
+    where
+        K: 'static,
+        V: 'static,
+        KS: Fn(&K) -> usize + Send + Sync + 'static,
+        VS: Fn(&V) -> usize + Send + Sync + 'static,
+    {
+        self.memory = Some(StateMemory {
+            account,
+            key_size: Arc::new(key_size),
+            value_size: Arc::new(value_size),
+        });
+        self
+    }
+
     pub fn get(&self, key: &K) -> Option<V>
     where
         V: Clone,
     {
-        self.state.read().get(key).cloned()
+        self.state.get(key).map(|value| value.clone())
     }
 
     pub fn set(&self, key: K, value: V) {
-        self.state.write().insert(key, value);
+        let Some(memory) = &self.memory else {
+            self.state.insert(key, value);
+            return;
+        };
+        let key_size = (memory.key_size)(&key);
+        let added = key_size + (memory.value_size)(&value);
+        // A replaced value keeps its key, accounted for with the new one
+        let replaced = self
+            .state
+            .insert(key, value)
+            .map_or(0, |old| key_size + (memory.value_size)(&old));
+        memory.account.resize(replaced, added);
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        let (key, value) = self.state.remove(key)?;
+        if let Some(memory) = &self.memory {
+            memory
+                .account
+                .release((memory.key_size)(&key) + (memory.value_size)(&value));
+        }
+        Some(value)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.state.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.state.is_empty()
+    }
+
+    /// Number of entries for which `f` returns true
+    pub fn count<F>(&self, mut f: F) -> usize
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        self.state
+            .iter()
+            .filter(|entry| f(entry.key(), entry.value()))
+            .count()
+    }
+
+    /// Keep only the entries for which `f` returns true
+    pub fn retain<F>(&self, mut f: F)
+    where
+        F: FnMut(&K, &mut V) -> bool,
+    {
+        let Some(memory) = &self.memory else {
+            self.state.retain(f);
+            return;
+        };
+        self.state.retain(|key, value| {
+            let before = (memory.key_size)(key) + (memory.value_size)(value);
+            let keep = f(key, value);
+            let after = if keep {
+                (memory.key_size)(key) + (memory.value_size)(value)
+            } else {
+                0
+            };
+            memory.account.resize(before, after);
+            keep
+        });
+    }
+
+    /// A copy of all entries, in no particular order
+    pub fn entries(&self) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.state
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+
+    /// Update a value in place, inserting `init()` first if the key is missing.
+    ///
+    /// Only the shard of the key is locked while `f` runs, so `f` must not
+    /// access other keys of the same backend.
+    pub fn update_with<I, F, R>(&self, key: K, init: I, f: F) -> R
+    where
+        I: FnOnce() -> V,
+        F: FnOnce(&mut V) -> R,
+    {
+        let Some(memory) = &self.memory else {
+            return f(self.state.entry(key).or_insert_with(init).value_mut());
+        };
+        let mut inserted = false;
+        let mut entry = self.state.entry(key).or_insert_with(|| {
+            inserted = true;
+            init()
+        });
+        let before = if inserted {
+            0
+        } else {
+            (memory.key_size)(entry.key()) + (memory.value_size)(entry.value())
+        };
+        let result = f(entry.value_mut());
+        let after = (memory.key_size)(entry.key()) + (memory.value_size)(entry.value());
+        memory.account.resize(before, after);
+        result
     }
 }
@@ -0,0 +1,243 @@
+use fluxus_core::MetricValue;
+use fluxus_utils::memory::{MemoryAccount, MemorySize};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::state::KeyedStateBackend;
+
+/// A record payload tagged with the tenant it belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tenanted<T> {
+    pub tenant: String,
+    pub data: T,
+}
+
+impl<T> Tenanted<T> {
+    pub fn new<S: Into<String>>(tenant: S, data: T) -> Self {
+        Self {
+            tenant: tenant.into(),
+            data,
+        }
+    }
+}
+
+/// Keyed state namespaced per tenant, so tenants never see each other's keys
+///
+/// Every key is stored with its tenant as a prefix in one
+/// [`KeyedStateBackend`], so all tenants share its shards and memory account.
+/// Clones share the same state.
+pub struct TenantStateBackend<K, V> {
+    state: KeyedStateBackend<(String, K), V>,
+}
+
+impl<K: Eq + Hash, V> Default for TenantStateBackend<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> Clone for TenantStateBackend<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<K, V> TenantStateBackend<K, V>
+where
+    K: Eq + Hash,
+{
+    pub fn new() -> Self {
+        Self {
+            state: KeyedStateBackend::new(),
+        }
+    }
+
+    /// Create a backend with at least `shards` shards, see
+    /// [`KeyedStateBackend::with_shards`]
+    pub fn with_shards(shards: usize) -> Self {
+        Self {
+            state: KeyedStateBackend::with_shards(shards),
+        }
+    }
+
+    /// Account for the estimated memory of the entries of all tenants
+    pub fn with_memory_account(mut self, account: MemoryAccount) -> Self
+    where
+        K: MemorySize + 'static,
+        V: MemorySize + 'static,
+    {
+        self.state = self.state.with_memory_account(account);
+        self
+    }
+
+    pub fn get(&self, tenant: &str, key: &K) -> Option<V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.state.get(&(tenant.to_string(), key.clone()))
+    }
+
+    pub fn set(&self, tenant: &str, key: K, value: V) {
+        self.state.set((tenant.to_string(), key), value);
+    }
+
+    pub fn remove(&self, tenant: &str, key: &K) -> Option<V>
+    where
+        K: Clone,
+    {
+        self.state.remove(&(tenant.to_string(), key.clone()))
+    }
+
+    /// Update a value of a tenant in place, see [`KeyedStateBackend::update_with`]
+    pub fn update_with<I, F, R>(&self, tenant: &str, key: K, init: I, f: F) -> R
+    where
+        I: FnOnce() -> V,
+        F: FnOnce(&mut V) -> R,
+    {
+        self.state.update_with((tenant.to_string(), key), init, f)
+    }
+
+    /// Drop all state of a tenant
+    pub fn clear_tenant(&self, tenant: &str) {
+        self.state.retain(|(owner, _), _| owner != tenant);
+    }
+
+    /// Number of keys held by a tenant
+    pub fn len(&self, tenant: &str) -> usize {
+        self.state.count(|(owner, _), _| owner == tenant)
+    }
+
+    pub fn is_empty(&self, tenant: &str) -> bool {
+        self.len(tenant) == 0
+    }
+}
+
+/// Limits applied to the records of a single tenant
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TenantQuota {
+    /// Maximum number of records accepted over the lifetime of the pipeline
+    pub max_records: Option<u64>,
+    /// Maximum number of records accepted per second
+    pub max_records_per_sec: Option<u64>,
+}
+
+impl TenantQuota {
+    /// A quota without any limits
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_records(mut self, max_records: u64) -> Self {
+        self.max_records = Some(max_records);
+        self
+    }
+
+    pub fn with_max_records_per_sec(mut self, max_records_per_sec: u64) -> Self {
+        self.max_records_per_sec = Some(max_records_per_sec);
+        self
+    }
+}
+
+#[derive(Debug)]
+struct TenantUsage {
+    accepted: u64,
+    rejected: u64,
+    window_start: Instant,
+    window_count: u64,
+}
+
+impl TenantUsage {
+    fn new() -> Self {
+        Self {
+            accepted: 0,
+            rejected: 0,
+            window_start: Instant::now(),
+            window_count: 0,
+        }
+    }
+}
+
+/// Per-tenant quota enforcement with accepted/rejected counters.
+///
+/// Clones share the same usage, so a handle kept outside the pipeline can
+/// read the metrics of the running quota check.
+#[derive(Clone, Default)]
+pub struct TenantQuotas {
+    default: TenantQuota,
+    overrides: HashMap<String, TenantQuota>,
+    usage: Arc<Mutex<HashMap<String, TenantUsage>>>,
+}
+
+impl TenantQuotas {
+    /// Apply the given quota to every tenant without an override
+    pub fn new(default: TenantQuota) -> Self {
+        Self {
+            default,
+            ..Default::default()
+        }
+    }
+
+    /// Set a dedicated quota for one tenant
+    pub fn with_tenant<S: Into<String>>(mut self, tenant: S, quota: TenantQuota) -> Self {
+        self.overrides.insert(tenant.into(), quota);
+        self
+    }
+
+    /// The quota that applies to a tenant
+    pub fn quota(&self, tenant: &str) -> TenantQuota {
+        self.overrides.get(tenant).copied().unwrap_or(self.default)
+    }
+
+    /// Account for one record of the tenant, returning whether it is within quota
+    pub fn admit(&self, tenant: &str) -> bool {
+        let quota = self.quota(tenant);
+        let mut usage = self.usage.lock();
+        let usage = usage
+            .entry(tenant.to_string())
+            .or_insert_with(TenantUsage::new);
+
+        if usage.window_start.elapsed() >= Duration::from_secs(1) {
+            usage.window_start = Instant::now();
+            usage.window_count = 0;
+        }
+
+        let within_total = quota.max_records.is_none_or(|max| usage.accepted < max);
+        let within_rate = quota
+            .max_records_per_sec
+            .is_none_or(|max| usage.window_count < max);
+        if within_total && within_rate {
+            usage.accepted += 1;
+            usage.window_count += 1;
+            true
+        } else {
+            usage.rejected += 1;
+            false
+        }
+    }
+
+    /// Per-tenant counters, keyed by `tenant.<id>.accepted` and `tenant.<id>.rejected`
+    pub fn metrics(&self) -> HashMap<String, MetricValue> {
+        self.usage
+            .lock()
+            .iter()
+            .flat_map(|(tenant, usage)| {
+                [
+                    (
+                        format!("tenant.{}.accepted", tenant),
+                        MetricValue::Counter(usage.accepted),
+                    ),
+                    (
+                        format!("tenant.{}.rejected", tenant),
+                        MetricValue::Counter(usage.rejected),
+                    ),
+                ]
+            })
+            .collect()
+    }
+}
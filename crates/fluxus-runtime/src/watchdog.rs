@@ -0,0 +1,137 @@
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// What the watchdog does once a stalled task is detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WatchdogAction {
+    /// Only log a diagnostic
+    #[default]
+    Log,
+    /// Log a diagnostic and abort the tasks of the pipeline
+    Abort,
+}
+
+/// Configuration of the stuck task watchdog
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// How long a single call may run before it is reported
+    pub stall_timeout: Duration,
+    /// How often the watchdog checks for stalled calls
+    pub check_interval: Duration,
+    pub action: WatchdogAction,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            stall_timeout: Duration::from_secs(30),
+            check_interval: Duration::from_secs(1),
+            action: WatchdogAction::Log,
+        }
+    }
+}
+
+impl WatchdogConfig {
+    pub fn new(stall_timeout: Duration) -> Self {
+        Self {
+            stall_timeout,
+            check_interval: (stall_timeout / 4).max(Duration::from_millis(10)),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_check_interval(mut self, check_interval: Duration) -> Self {
+        self.check_interval = check_interval;
+        self
+    }
+
+    pub fn with_action(mut self, action: WatchdogAction) -> Self {
+        self.action = action;
+        self
+    }
+}
+
+/// A call that has been running for longer than the stall timeout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stall {
+    pub name: String,
+    pub elapsed: Duration,
+}
+
+/// Progress slot of a tracked task
+#[derive(Clone, Default)]
+pub struct Progress {
+    busy_since: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Progress {
+    /// Mark the start of a call, which lasts until the guard is dropped
+    pub fn enter(&self) -> ProgressGuard<'_> {
+        *self.busy_since.lock() = Some(Instant::now());
+        ProgressGuard { progress: self }
+    }
+
+    fn busy_for(&self) -> Option<Duration> {
+        self.busy_since.lock().map(|since| since.elapsed())
+    }
+}
+
+/// Marks the tracked task idle again when dropped
+pub struct ProgressGuard<'a> {
+    progress: &'a Progress,
+}
+
+impl Drop for ProgressGuard<'_> {
+    fn drop(&mut self) {
+        *self.progress.busy_since.lock() = None;
+    }
+}
+
+/// Detects operators and sinks whose calls stopped making progress
+#[derive(Clone)]
+pub struct Watchdog {
+    config: WatchdogConfig,
+    tracked: Arc<DashMap<String, Progress>>,
+}
+
+impl Watchdog {
+    pub fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            tracked: Arc::new(DashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> &WatchdogConfig {
+        &self.config
+    }
+
+    /// Start tracking a task under the given name
+    pub fn track<S: Into<String>>(&self, name: S) -> Progress {
+        self.tracked.entry(name.into()).or_default().clone()
+    }
+
+    /// Stop tracking a task
+    pub fn untrack(&self, name: &str) {
+        self.tracked.remove(name);
+    }
+
+    /// Tasks whose current call exceeded the stall timeout
+    pub fn check(&self) -> Vec<Stall> {
+        let mut stalls: Vec<Stall> = self
+            .tracked
+            .iter()
+            .filter_map(|entry| {
+                let elapsed = entry.value().busy_for()?;
+                (elapsed >= self.config.stall_timeout).then(|| Stall {
+                    name: entry.key().clone(),
+                    elapsed,
+                })
+            })
+            .collect();
+        stalls.sort_by(|a, b| a.name.cmp(&b.name));
+        stalls
+    }
+}
@@ -1,6 +1,6 @@
 use parking_lot::RwLock;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 /// Watermark tracker for managing event time progress
 pub struct WatermarkTracker {
@@ -31,3 +31,119 @@ impl WatermarkTracker {
         *self.current_watermark.read()
     }
 }
+
+/// How the event-time watermark follows the timestamps seen so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatermarkStrategy {
+    /// Timestamps never decrease, the watermark is the latest timestamp
+    Monotonous,
+    /// Timestamps arrive at most the given duration out of order
+    BoundedOutOfOrderness(Duration),
+}
+
+impl WatermarkStrategy {
+    pub fn monotonous() -> Self {
+        Self::Monotonous
+    }
+
+    pub fn bounded_out_of_orderness(bound: Duration) -> Self {
+        Self::BoundedOutOfOrderness(bound)
+    }
+
+    /// Watermark for the greatest timestamp seen so far, in milliseconds
+    pub fn watermark(&self, max_timestamp: i64) -> i64 {
+        match self {
+            Self::Monotonous => max_timestamp,
+            Self::BoundedOutOfOrderness(bound) => {
+                max_timestamp.saturating_sub(bound.as_millis() as i64)
+            }
+        }
+    }
+}
+
+/// Generates event-time watermarks from the timestamps of a stream
+#[derive(Debug, Clone)]
+pub struct WatermarkGenerator {
+    strategy: WatermarkStrategy,
+    max_timestamp: Option<i64>,
+}
+
+impl WatermarkGenerator {
+    pub fn new(strategy: WatermarkStrategy) -> Self {
+        Self {
+            strategy,
+            max_timestamp: None,
+        }
+    }
+
+    /// Observe an event timestamp and return the current watermark
+    pub fn on_event(&mut self, timestamp: i64) -> i64 {
+        let max = self
+            .max_timestamp
+            .map_or(timestamp, |max| max.max(timestamp));
+        self.max_timestamp = Some(max);
+        self.strategy.watermark(max)
+    }
+
+    /// Current watermark, none before the first event
+    pub fn current(&self) -> Option<i64> {
+        self.max_timestamp.map(|max| self.strategy.watermark(max))
+    }
+
+    /// Whether an event with this timestamp is behind the current watermark
+    pub fn is_late(&self, timestamp: i64) -> bool {
+        self.current()
+            .is_some_and(|watermark| timestamp < watermark)
+    }
+}
+
+/// Decides the watermark of a stream from its elements, for watermarks that
+/// don't only follow the timestamps, e.g. [`Punctuated`]. Implement it for
+/// custom watermarks.
+pub trait WatermarkPolicy<T>: Send + Sync {
+    /// Observe an element and return the current watermark
+    fn on_event(&mut self, value: &T, timestamp: i64) -> Option<i64>;
+
+    /// Current watermark, none before the first watermark
+    fn current(&self) -> Option<i64>;
+}
+
+impl<T> WatermarkPolicy<T> for WatermarkGenerator {
+    fn on_event(&mut self, _value: &T, timestamp: i64) -> Option<i64> {
+        Some(WatermarkGenerator::on_event(self, timestamp))
+    }
+
+    fn current(&self) -> Option<i64> {
+        WatermarkGenerator::current(self)
+    }
+}
+
+/// Watermarks carried by punctuation elements of the stream, such as
+/// end-of-batch markers. `f` returns the watermark an element carries, if
+/// it is a punctuation; the watermark never moves back.
+pub struct Punctuated<F> {
+    f: F,
+    watermark: Option<i64>,
+}
+
+impl<F> Punctuated<F> {
+    pub fn new(f: F) -> Self {
+        Self { f, watermark: None }
+    }
+}
+
+impl<T, F> WatermarkPolicy<T> for Punctuated<F>
+where
+    F: Fn(&T, i64) -> Option<i64> + Send + Sync,
+{
+    fn on_event(&mut self, value: &T, timestamp: i64) -> Option<i64> {
+        if let Some(watermark) = (self.f)(value, timestamp) {
+            self.watermark = Some(self.watermark.map_or(watermark, |wm| wm.max(watermark)));
+        }
+        self.watermark
+    }
+
+    fn current(&self) -> Option<i64> {
+        self.watermark
+    }
+}
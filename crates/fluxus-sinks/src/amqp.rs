@@ -0,0 +1,152 @@
+use async_trait::async_trait;
+use fluxus_utils::amqp::{AmqpClient, AmqpOptions, AmqpQueue};
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use serde::Serialize;
+
+use super::Sink;
+
+type EncodeFn<T> = Box<dyn Fn(&T) -> StreamResult<Vec<u8>> + Send + Sync>;
+type RoutingKeyFn<T> = Box<dyn Fn(&T) -> String + Send + Sync>;
+
+/// A sink that publishes records to an AMQP exchange, e.g. of RabbitMQ.
+///
+/// Writes don't wait for the broker, [`flush`](Sink::flush) waits until it
+/// confirmed every message published so far and fails if it rejected any.
+/// A publish that fails because the connection was lost is retried once on
+/// a new connection, unless messages published before it are still
+/// unconfirmed.
+pub struct AmqpSink<T> {
+    options: AmqpOptions,
+    exchange: String,
+    routing_key: RoutingKeyFn<T>,
+    queue: Option<AmqpQueue>,
+    persistent: bool,
+    encode: EncodeFn<T>,
+    client: Option<AmqpClient>,
+}
+
+impl<T: Serialize> AmqpSink<T> {
+    /// Create a new AMQP sink publishing records as JSON
+    pub fn json(
+        options: AmqpOptions,
+        exchange: impl Into<String>,
+        routing_key: impl Into<String>,
+    ) -> Self {
+        Self::new(options, exchange, routing_key, |data: &T| {
+            serde_json::to_vec(data).map_err(|e| StreamError::Serialization(e.to_string()))
+        })
+    }
+}
+
+impl<T> AmqpSink<T> {
+    /// Create a new AMQP sink publishing records encoded by the given
+    /// function. The default exchange `""` routes messages to the queue
+    /// named by their routing key.
+    pub fn new<F>(
+        options: AmqpOptions,
+        exchange: impl Into<String>,
+        routing_key: impl Into<String>,
+        encode: F,
+    ) -> Self
+    where
+        F: Fn(&T) -> StreamResult<Vec<u8>> + Send + Sync + 'static,
+    {
+        let routing_key = routing_key.into();
+        Self {
+            options,
+            exchange: exchange.into(),
+            routing_key: Box::new(move |_| routing_key.clone()),
+            queue: None,
+            persistent: true,
+            encode: Box::new(encode),
+            client: None,
+        }
+    }
+
+    /// Publish each record with the routing key computed from it
+    pub fn with_routing_key_fn<F>(mut self, routing_key: F) -> Self
+    where
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        self.routing_key = Box::new(routing_key);
+        self
+    }
+
+    /// Declare a queue when connecting
+    pub fn with_queue(mut self, queue: AmqpQueue) -> Self {
+        self.queue = Some(queue);
+        self
+    }
+
+    /// Whether the broker writes the messages to disk, on by default
+    pub fn with_persistent(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+        self
+    }
+
+    async fn connect(&self) -> StreamResult<AmqpClient> {
+        let mut client = AmqpClient::connect(&self.options).await?;
+        if let Some(queue) = &self.queue {
+            client.declare_queue(queue).await?;
+        }
+        client.confirm_select().await?;
+        Ok(client)
+    }
+
+    async fn publish(&mut self, routing_key: &str, body: &[u8]) -> StreamResult<()> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => {
+                let client = self.connect().await?;
+                self.client.insert(client)
+            }
+        };
+        client
+            .publish(&self.exchange, routing_key, body, self.persistent)
+            .await
+    }
+}
+
+#[async_trait]
+impl<T> Sink<T> for AmqpSink<T>
+where
+    T: Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        self.client = Some(self.connect().await?);
+        Ok(())
+    }
+
+    async fn write(&mut self, record: Record<T>) -> StreamResult<()> {
+        let routing_key = (self.routing_key)(&record.data);
+        let body = (self.encode)(&record.data)?;
+        if let Err(e) = self.publish(&routing_key, &body).await {
+            let unconfirmed = self.client.take().map_or(0, |client| client.unconfirmed());
+            if unconfirmed > 0 {
+                return Err(StreamError::Runtime(format!(
+                    "Lost the connection to the AMQP broker with {} unconfirmed messages: {}",
+                    unconfirmed, e
+                )));
+            }
+            tracing::warn!("Failed to publish to the AMQP broker, reconnecting: {}", e);
+            tokio::time::sleep(self.options.reconnect_delay).await;
+            self.publish(&routing_key, &body).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        match self.client.as_mut() {
+            Some(client) => client.wait_for_confirms().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        if let Some(mut client) = self.client.take() {
+            client.wait_for_confirms().await?;
+            client.close().await?;
+        }
+        Ok(())
+    }
+}
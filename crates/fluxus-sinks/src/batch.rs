@@ -0,0 +1,127 @@
+use crate::Sink;
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamResult};
+use std::time::{Duration, Instant};
+
+/// A sink that writes records in batches, e.g. with one bulk insert per
+/// batch instead of one request per record.
+///
+/// Run it with [`BatchingSink`], which collects the records of a stream
+/// into batches.
+#[async_trait]
+pub trait BatchSink<T> {
+    /// Initialize the sink
+    async fn init(&mut self) -> StreamResult<()>;
+
+    /// Write a batch of records to the sink
+    async fn write_batch(&mut self, records: Vec<Record<T>>) -> StreamResult<()>;
+
+    /// Flush any buffered data, after which the written batches are durable
+    async fn flush(&mut self) -> StreamResult<()>;
+
+    /// Close the sink and release resources
+    async fn close(&mut self) -> StreamResult<()>;
+}
+
+/// The records a flush of a [`BatchingSink`] made durable
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushAck {
+    /// Number of records written since the previous flush
+    pub records: usize,
+    /// Latest timestamp of the acknowledged records
+    pub max_timestamp: i64,
+}
+
+type FlushCallback = Box<dyn Fn(FlushAck) + Send + Sync>;
+
+/// Runs a [`BatchSink`] as a [`Sink`], writing batches of up to `batch_size`
+/// records, or fewer once `max_wait` passed since the previous batch.
+///
+/// Every batch is written and flushed together, after which the callbacks
+/// set with [`on_flush`](Self::on_flush) are called with the latest
+/// timestamp of the flushed records, e.g. to acknowledge them at the source.
+pub struct BatchingSink<T, S> {
+    inner: S,
+    batch: Vec<Record<T>>,
+    batch_size: usize,
+    max_wait: Duration,
+    last_flush: Instant,
+    callbacks: Vec<FlushCallback>,
+}
+
+impl<T, S: BatchSink<T>> BatchingSink<T, S> {
+    pub fn new(inner: S, batch_size: usize, max_wait: Duration) -> Self {
+        let batch_size = batch_size.max(1);
+        Self {
+            inner,
+            batch: Vec::with_capacity(batch_size),
+            batch_size,
+            max_wait,
+            last_flush: Instant::now(),
+            callbacks: Vec::new(),
+        }
+    }
+
+    /// Call `f` after every flush of the inner sink that wrote records
+    pub fn on_flush<F>(mut self, f: F) -> Self
+    where
+        F: Fn(FlushAck) + Send + Sync + 'static,
+    {
+        self.callbacks.push(Box::new(f));
+        self
+    }
+
+    /// Write the pending batch, flush the inner sink and acknowledge the
+    /// records of the batch
+    async fn flush_batch(&mut self) -> StreamResult<()>
+    where
+        T: Send,
+        S: Send,
+    {
+        let batch = std::mem::take(&mut self.batch);
+        let Some(max_timestamp) = batch.iter().map(|record| record.timestamp).max() else {
+            return self.inner.flush().await;
+        };
+        let ack = FlushAck {
+            records: batch.len(),
+            max_timestamp,
+        };
+        self.inner.write_batch(batch).await?;
+        self.inner.flush().await?;
+        self.last_flush = Instant::now();
+        for callback in &self.callbacks {
+            callback(ack);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T, S> Sink<T> for BatchingSink<T, S>
+where
+    T: Send,
+    S: BatchSink<T> + Send,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        self.inner.init().await
+    }
+
+    async fn write(&mut self, record: Record<T>) -> StreamResult<()> {
+        self.batch.push(record);
+        if self.batch.len() >= self.batch_size || self.last_flush.elapsed() >= self.max_wait {
+            self.flush_batch().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        self.flush_batch().await
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        if !self.batch.is_empty() {
+            self.flush_batch().await?;
+        }
+        self.inner.close().await
+    }
+}
@@ -0,0 +1,103 @@
+use async_trait::async_trait;
+use fluxus_utils::clickhouse::{ClickHouseOptions, Client, clickhouse_error};
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::batch::{BatchSink, BatchingSink};
+
+type EncodeFn<T> = Box<dyn Fn(&T) -> StreamResult<Vec<u8>> + Send + Sync>;
+
+/// A sink that inserts records into a ClickHouse table with the
+/// `clickhouse` client, one `INSERT` in the `JSONEachRow` format per batch.
+///
+/// ClickHouse writes a part per insert and favors few large inserts over
+/// many small ones, so run the sink with a [`BatchingSink`] of tens of
+/// thousands of records or more, e.g. with [`batched`](Self::batched). An
+/// insert is durable once ClickHouse answered it, so flushes do nothing.
+pub struct ClickHouseSink<T> {
+    options: ClickHouseOptions,
+    table: String,
+    encode: EncodeFn<T>,
+    client: Option<Client>,
+}
+
+impl<T: Serialize> ClickHouseSink<T> {
+    /// Create a new ClickHouse sink inserting records as JSON objects, with
+    /// a field per column
+    pub fn json(options: ClickHouseOptions, table: impl Into<String>) -> Self {
+        Self::new(options, table, |data: &T| {
+            serde_json::to_vec(data).map_err(|e| StreamError::Serialization(e.to_string()))
+        })
+    }
+}
+
+impl<T> ClickHouseSink<T> {
+    /// Create a new ClickHouse sink inserting records encoded by the given
+    /// function as a JSON object each
+    pub fn new<F>(options: ClickHouseOptions, table: impl Into<String>, encode: F) -> Self
+    where
+        F: Fn(&T) -> StreamResult<Vec<u8>> + Send + Sync + 'static,
+    {
+        Self {
+            options,
+            table: table.into(),
+            encode: Box::new(encode),
+            client: None,
+        }
+    }
+}
+
+impl<T: Send + 'static> ClickHouseSink<T> {
+    /// Run the sink inserting batches of up to `batch_size` records, or
+    /// fewer once `max_wait` passed since the previous insert
+    pub fn batched(self, batch_size: usize, max_wait: Duration) -> BatchingSink<T, Self> {
+        BatchingSink::new(self, batch_size, max_wait)
+    }
+}
+
+#[async_trait]
+impl<T> BatchSink<T> for ClickHouseSink<T>
+where
+    T: Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        let client = self.options.client().await?;
+        client
+            .query("SELECT 1")
+            .execute()
+            .await
+            .map_err(clickhouse_error)?;
+        self.client = Some(client);
+        Ok(())
+    }
+
+    async fn write_batch(&mut self, records: Vec<Record<T>>) -> StreamResult<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+        let client = self
+            .client
+            .as_ref()
+            .ok_or_else(|| StreamError::Runtime("not connected to ClickHouse".to_string()))?;
+
+        let mut body = Vec::new();
+        for record in &records {
+            body.extend((self.encode)(&record.data)?);
+            body.push(b'\n');
+        }
+        let mut insert =
+            client.insert_formatted_with(format!("INSERT INTO {} FORMAT JSONEachRow", self.table));
+        insert.send(body.into()).await.map_err(clickhouse_error)?;
+        insert.end().await.map_err(clickhouse_error)
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.client = None;
+        Ok(())
+    }
+}
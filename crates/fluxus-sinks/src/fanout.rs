@@ -0,0 +1,80 @@
+use crate::Sink;
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamResult};
+
+type BoxedSink<T> = Box<dyn Sink<T> + Send + Sync>;
+
+/// A sink that writes every record to all of its inner sinks
+pub struct FanOutSink<T> {
+    sinks: Vec<BoxedSink<T>>,
+}
+
+impl<T> Default for FanOutSink<T> {
+    fn default() -> Self {
+        Self { sinks: Vec::new() }
+    }
+}
+
+impl<T> FanOutSink<T> {
+    /// Create a new fan-out sink over the given sinks
+    pub fn new(sinks: Vec<BoxedSink<T>>) -> Self {
+        Self { sinks }
+    }
+
+    /// Add another sink
+    pub fn with_sink<S>(mut self, sink: S) -> Self
+    where
+        S: Sink<T> + Send + Sync + 'static,
+    {
+        self.sinks.push(Box::new(sink));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.sinks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sinks.is_empty()
+    }
+}
+
+#[async_trait]
+impl<T: Clone + Send + Sync + 'static> Sink<T> for FanOutSink<T> {
+    async fn init(&mut self) -> StreamResult<()> {
+        for sink in self.sinks.iter_mut() {
+            sink.init().await?;
+        }
+        Ok(())
+    }
+
+    async fn write(&mut self, record: Record<T>) -> StreamResult<()> {
+        let Some((last, rest)) = self.sinks.split_last_mut() else {
+            return Ok(());
+        };
+        for sink in rest {
+            sink.write(record.clone()).await?;
+        }
+        last.write(record).await
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        for sink in self.sinks.iter_mut() {
+            sink.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        // Close every sink even if one of them fails, reporting the first error
+        let mut result = Ok(());
+        for sink in self.sinks.iter_mut() {
+            if let Err(e) = sink.close().await
+                && result.is_ok()
+            {
+                result = Err(e);
+            }
+        }
+        result
+    }
+}
@@ -1,6 +1,7 @@
 use crate::Sink;
 use async_trait::async_trait;
 use csv;
+use fluxus_utils::compression::{CompressionCodec, EncodedWriter};
 use fluxus_utils::models::{Record, StreamResult};
 use serde::Serialize;
 use serde_json;
@@ -24,7 +25,8 @@ pub enum FileFormat {
 pub struct FileSink<T> {
     path: PathBuf,
     format: FileFormat,
-    file: Option<File>,
+    compression: CompressionCodec,
+    file: Option<EncodedWriter>,
     _phantom: PhantomData<T>,
 }
 
@@ -34,16 +36,24 @@ impl<T> FileSink<T> {
         Self {
             path: path.into(),
             format,
+            compression: CompressionCodec::None,
             file: None,
             _phantom: PhantomData,
         }
     }
+
+    /// Compress the output with the given codec
+    pub fn with_compression(mut self, codec: CompressionCodec) -> Self {
+        self.compression = codec;
+        self
+    }
 }
 
 #[async_trait]
 impl<T: Serialize + Send> Sink<T> for FileSink<T> {
     async fn init(&mut self) -> StreamResult<()> {
-        self.file = Some(File::create(&self.path).await?);
+        let file = File::create(&self.path).await?;
+        self.file = Some(self.compression.encoder(file));
         Ok(())
     }
 
@@ -79,7 +89,8 @@ impl<T: Serialize + Send> Sink<T> for FileSink<T> {
 
     async fn close(&mut self) -> StreamResult<()> {
         if let Some(mut file) = self.file.take() {
-            file.flush().await?;
+            // Shutdown also writes the trailer of compressed formats
+            file.shutdown().await?;
         }
         Ok(())
     }
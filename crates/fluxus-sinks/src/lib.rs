@@ -1,11 +1,33 @@
+#[cfg(feature = "amqp")]
+pub mod amqp;
+pub mod batch;
 pub mod buffered;
+#[cfg(feature = "clickhouse")]
+pub mod clickhouse;
 pub mod console;
 pub mod dummy_sink;
+pub mod fanout;
 pub mod file;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod notify_once;
+pub mod partitioned;
+pub mod stdout;
 
+#[cfg(feature = "amqp")]
+pub use amqp::AmqpSink;
+pub use batch::{BatchSink, BatchingSink, FlushAck};
 pub use buffered::BufferedSink;
+#[cfg(feature = "clickhouse")]
+pub use clickhouse::ClickHouseSink;
 pub use console::ConsoleSink;
+pub use fanout::FanOutSink;
 pub use file::FileSink;
+#[cfg(feature = "mqtt")]
+pub use mqtt::MqttSink;
+pub use notify_once::NotifyOnce;
+pub use partitioned::WindowPartitionedSink;
+pub use stdout::StdoutSink;
 
 use async_trait::async_trait;
 use fluxus_utils::models::{Record, StreamResult};
@@ -27,6 +49,29 @@ pub trait Sink<T> {
     async fn close(&mut self) -> StreamResult<()>;
 }
 
+#[async_trait]
+impl<T, S> Sink<T> for Box<S>
+where
+    T: Send + 'static,
+    S: Sink<T> + Send + ?Sized,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        (**self).init().await
+    }
+
+    async fn write(&mut self, record: Record<T>) -> StreamResult<()> {
+        (**self).write(record).await
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        (**self).flush().await
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        (**self).close().await
+    }
+}
+
 /// Formatter for console output
 pub trait ConsoleFormatter<T> {
     fn format(&self, record: &Record<T>) -> String;
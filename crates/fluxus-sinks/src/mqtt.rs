@@ -0,0 +1,101 @@
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use fluxus_utils::mqtt::{MqttClient, MqttMessage, MqttOptions, QoS};
+use serde::Serialize;
+
+use super::Sink;
+
+type EncodeFn<T> = Box<dyn Fn(&T) -> StreamResult<Vec<u8>> + Send + Sync>;
+type TopicFn<T> = Box<dyn Fn(&T) -> String + Send + Sync>;
+
+/// A sink that publishes records to an MQTT topic.
+///
+/// A publish interrupted by a lost connection is retried on a new
+/// connection, after the reconnect delay of the options, until the maximum
+/// reconnect attempts of the options are used up.
+pub struct MqttSink<T> {
+    options: MqttOptions,
+    topic: TopicFn<T>,
+    qos: QoS,
+    retain: bool,
+    encode: EncodeFn<T>,
+    client: Option<MqttClient>,
+}
+
+impl<T: Serialize> MqttSink<T> {
+    /// Create a new MQTT sink publishing records as JSON
+    pub fn json(options: MqttOptions, topic: impl Into<String>, qos: QoS) -> Self {
+        Self::new(options, topic, qos, |data: &T| {
+            serde_json::to_vec(data).map_err(|e| StreamError::Serialization(e.to_string()))
+        })
+    }
+}
+
+impl<T> MqttSink<T> {
+    /// Create a new MQTT sink publishing records encoded by the given function
+    pub fn new<F>(options: MqttOptions, topic: impl Into<String>, qos: QoS, encode: F) -> Self
+    where
+        F: Fn(&T) -> StreamResult<Vec<u8>> + Send + Sync + 'static,
+    {
+        let topic = topic.into();
+        Self {
+            options,
+            topic: Box::new(move |_| topic.clone()),
+            qos,
+            retain: false,
+            encode: Box::new(encode),
+            client: None,
+        }
+    }
+
+    /// Publish each record to the topic computed from it
+    pub fn with_topic_fn<F>(mut self, topic: F) -> Self
+    where
+        F: Fn(&T) -> String + Send + Sync + 'static,
+    {
+        self.topic = Box::new(topic);
+        self
+    }
+
+    /// Ask the broker to keep the last message of the topic for new
+    /// subscribers
+    pub fn with_retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+}
+
+#[async_trait]
+impl<T> Sink<T> for MqttSink<T>
+where
+    T: Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        self.client = Some(MqttClient::connect(&self.options, &[]).await?);
+        Ok(())
+    }
+
+    async fn write(&mut self, record: Record<T>) -> StreamResult<()> {
+        let message = MqttMessage::new((self.topic)(&record.data), (self.encode)(&record.data)?)
+            .with_qos(self.qos)
+            .with_retain(self.retain);
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => self
+                .client
+                .insert(MqttClient::connect(&self.options, &[]).await?),
+        };
+        client.publish(&message).await
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        if let Some(mut client) = self.client.take() {
+            client.disconnect().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use crate::Sink;
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamResult};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+/// A sink wrapper that sends every notification at most once, even when a
+/// pipeline is restarted and replays its input, e.g. for alerts sent to
+/// Telegram or a webhook.
+///
+/// Notifications are buffered until the sink is flushed, which commits
+/// them: the buffered notifications are written to the inner sink, the
+/// inner sink is flushed, and the ids of the sent notifications are
+/// appended to a ledger file. Notifications whose id is in the ledger, or
+/// already buffered, are dropped. A crash before the commit loses nothing,
+/// since the buffered notifications are sent again on replay.
+pub struct NotifyOnce<T, S, F> {
+    inner: S,
+    id: F,
+    ledger: PathBuf,
+    sent: HashSet<String>,
+    pending: Vec<(String, Record<T>)>,
+    pending_ids: HashSet<String>,
+}
+
+impl<T, S, F> NotifyOnce<T, S, F>
+where
+    F: Fn(&T) -> String,
+{
+    /// Deduplicate the notifications written to `inner` by `id`, keeping the
+    /// ids of sent notifications in the file at `ledger`
+    pub fn new<P: Into<PathBuf>>(inner: S, ledger: P, id: F) -> Self {
+        Self {
+            inner,
+            id,
+            ledger: ledger.into(),
+            sent: HashSet::new(),
+            pending: Vec::new(),
+            pending_ids: HashSet::new(),
+        }
+    }
+
+    /// Append the ids of sent notifications to the ledger
+    async fn record(&mut self, ids: Vec<String>) -> StreamResult<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.ledger)
+            .await?;
+        let mut lines = String::new();
+        for id in &ids {
+            lines.push_str(id);
+            lines.push('\n');
+        }
+        file.write_all(lines.as_bytes()).await?;
+        file.sync_data().await?;
+        self.sent.extend(ids);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T, S, F> Sink<T> for NotifyOnce<T, S, F>
+where
+    T: Send + Sync,
+    S: Sink<T> + Send + Sync,
+    F: Fn(&T) -> String + Send + Sync,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match tokio::fs::read_to_string(&self.ledger).await {
+            Ok(ledger) => self.sent = ledger.lines().map(str::to_string).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        self.inner.init().await
+    }
+
+    async fn write(&mut self, record: Record<T>) -> StreamResult<()> {
+        let id = (self.id)(&record.data);
+        if self.sent.contains(&id) || !self.pending_ids.insert(id.clone()) {
+            tracing::debug!("Dropping notification {} that was already sent", id);
+            return Ok(());
+        }
+        self.pending.push((id, record));
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        let mut sent = Vec::with_capacity(self.pending.len());
+        let mut result = Ok(());
+        self.pending_ids.clear();
+        for (id, record) in std::mem::take(&mut self.pending) {
+            if let Err(e) = self.inner.write(record).await {
+                result = Err(e);
+                break;
+            }
+            sent.push(id);
+        }
+        let flushed = self.inner.flush().await;
+        // Notifications written before a failure were sent all the same
+        self.record(sent).await?;
+        result.and(flushed)
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.flush().await?;
+        self.inner.close().await
+    }
+}
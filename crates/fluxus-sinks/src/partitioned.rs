@@ -0,0 +1,123 @@
+use crate::Sink;
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamResult};
+use fluxus_utils::window::{Window, WindowedValue};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+/// An open partition and the end of its window
+struct Partition<S> {
+    sink: S,
+    end: Option<i64>,
+}
+
+/// A sink that writes the results of each window and key to a sink of its
+/// own, e.g. one file per hour and sensor, instead of interleaving all
+/// windows in one output.
+///
+/// The partition of a result is at the path returned by `path` for its
+/// window and key, and its sink is created by `factory` when the first
+/// result of the partition arrives, creating missing parent directories.
+/// Since windows emit their results when they end, the partitions of a
+/// window are closed once a result of a window that ends later arrives, and
+/// the remaining ones when the sink is closed. A late update of a closed
+/// partition creates its sink again, so a sink that truncates its output,
+/// such as [`FileSink`](crate::FileSink), ends up with the latest result.
+pub struct WindowPartitionedSink<K, A, S, F, P> {
+    factory: F,
+    path: P,
+    partitions: HashMap<PathBuf, Partition<S>>,
+    _phantom: PhantomData<(K, A)>,
+}
+
+impl<K, A, S, F, P> WindowPartitionedSink<K, A, S, F, P>
+where
+    F: Fn(&Path) -> S,
+    P: Fn(Window, &K) -> PathBuf,
+{
+    pub fn new(factory: F, path: P) -> Self {
+        Self {
+            factory,
+            path,
+            partitions: HashMap::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Number of partitions that are open
+    pub fn open_partitions(&self) -> usize {
+        self.partitions.len()
+    }
+}
+
+#[async_trait]
+impl<K, A, S, F, P> Sink<WindowedValue<(K, A)>> for WindowPartitionedSink<K, A, S, F, P>
+where
+    K: Send + Sync,
+    A: Send + Sync,
+    S: Sink<WindowedValue<(K, A)>> + Send + Sync,
+    F: Fn(&Path) -> S + Send + Sync,
+    P: Fn(Window, &K) -> PathBuf + Send + Sync,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn write(&mut self, record: Record<WindowedValue<(K, A)>>) -> StreamResult<()> {
+        let end = record.data.end;
+        let path = (self.path)(record.data.window(), &record.data.value.0);
+
+        // Results of a later window finalize the partitions of earlier ones
+        if let Some(end) = end {
+            let finished: Vec<PathBuf> = self
+                .partitions
+                .iter()
+                .filter(|(_, partition)| partition.end.is_some_and(|e| e < end))
+                .map(|(path, _)| path.clone())
+                .collect();
+            for path in finished {
+                if let Some(mut partition) = self.partitions.remove(&path) {
+                    partition.sink.flush().await?;
+                    partition.sink.close().await?;
+                }
+            }
+        }
+
+        if !self.partitions.contains_key(&path) {
+            if let Some(parent) = path.parent()
+                && !parent.as_os_str().is_empty()
+            {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let mut sink = (self.factory)(&path);
+            sink.init().await?;
+            self.partitions
+                .insert(path.clone(), Partition { sink, end });
+        }
+        let partition = self
+            .partitions
+            .get_mut(&path)
+            .expect("the partition was just opened");
+        partition.sink.write(record).await
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        for partition in self.partitions.values_mut() {
+            partition.sink.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        let mut result = Ok(());
+        for (_, mut partition) in self.partitions.drain() {
+            let closed = match partition.sink.flush().await {
+                Ok(()) => partition.sink.close().await,
+                Err(e) => Err(e),
+            };
+            result = result.and(closed);
+        }
+        result
+    }
+}
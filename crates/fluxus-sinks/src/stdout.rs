@@ -0,0 +1,120 @@
+use crate::Sink;
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use serde::Serialize;
+use std::fmt::Display;
+use std::io::ErrorKind;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+
+type FormatFn<T> = Box<dyn Fn(&Record<T>) -> StreamResult<String> + Send + Sync>;
+type LineWriter = BufWriter<Box<dyn AsyncWrite + Send + Sync + Unpin>>;
+
+/// A sink that writes records to standard output, one line per record, for
+/// the next command of a shell pipeline, e.g. `my-app | jq .user`.
+///
+/// Unlike [`ConsoleSink`](crate::ConsoleSink), which logs records, it
+/// writes nothing but the records. Output is buffered until a flush. When
+/// the reader of the output exits, e.g. `head`, the remaining records are
+/// dropped instead of failing the pipeline.
+pub struct StdoutSink<T> {
+    format: FormatFn<T>,
+    writer: LineWriter,
+    closed: bool,
+}
+
+impl<T: Display> StdoutSink<T> {
+    /// Create a sink writing records as text
+    pub fn lines() -> Self {
+        Self::new(|record: &Record<T>| Ok(record.data.to_string()))
+    }
+}
+
+impl<T: Serialize> StdoutSink<T> {
+    /// Create a sink writing records as JSON, one object per line
+    pub fn json() -> Self {
+        Self::new(|record: &Record<T>| {
+            serde_json::to_string(&record.data)
+                .map_err(|e| StreamError::Serialization(e.to_string()))
+        })
+    }
+
+    /// Create a sink writing records as JSON objects with the timestamp of
+    /// the record and its data, e.g. `{"data":{...},"timestamp":1}`
+    pub fn json_with_timestamps() -> Self {
+        Self::new(|record: &Record<T>| {
+            serde_json::to_string(&serde_json::json!({
+                "timestamp": record.timestamp,
+                "data": record.data,
+            }))
+            .map_err(|e| StreamError::Serialization(e.to_string()))
+        })
+    }
+}
+
+impl<T> StdoutSink<T> {
+    /// Create a sink writing records formatted by the given function, which
+    /// shouldn't add a newline
+    pub fn new<F>(format: F) -> Self
+    where
+        F: Fn(&Record<T>) -> StreamResult<String> + Send + Sync + 'static,
+    {
+        Self {
+            format: Box::new(format),
+            writer: BufWriter::new(Box::new(tokio::io::stdout())),
+            closed: false,
+        }
+    }
+
+    /// Write to another writer than standard output, e.g. standard error
+    pub fn to_writer<W>(mut self, writer: W) -> Self
+    where
+        W: AsyncWrite + Send + Sync + Unpin + 'static,
+    {
+        self.writer = BufWriter::new(Box::new(writer));
+        self
+    }
+
+    /// Drop the output once its reader is gone, failing on other errors
+    fn check(&mut self, result: std::io::Result<()>) -> StreamResult<()> {
+        match result {
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                tracing::debug!("Output closed, dropping further records");
+                self.closed = true;
+                Ok(())
+            }
+            result => Ok(result?),
+        }
+    }
+}
+
+#[async_trait]
+impl<T> Sink<T> for StdoutSink<T>
+where
+    T: Send,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn write(&mut self, record: Record<T>) -> StreamResult<()> {
+        if self.closed {
+            return Ok(());
+        }
+        let mut line = (self.format)(&record)?;
+        line.push('\n');
+        let result = self.writer.write_all(line.as_bytes()).await;
+        self.check(result)
+    }
+
+    async fn flush(&mut self) -> StreamResult<()> {
+        if self.closed {
+            return Ok(());
+        }
+        let result = self.writer.flush().await;
+        self.check(result)
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.flush().await
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+
+#[derive(Default)]
+struct AckState {
+    /// Timestamps of the records read and the positions they were read at,
+    /// e.g. delivery tags, in order of reading
+    pending: VecDeque<(i64, u64)>,
+    /// Latest timestamp the downstream sink acknowledged
+    acked_until: Option<i64>,
+}
+
+#[derive(Default)]
+struct Shared {
+    state: Mutex<AckState>,
+    notify: Notify,
+}
+
+/// Acknowledges the records of a source once they were handled, e.g. from
+/// the flush callback of a `BatchingSink`:
+///
+/// ```ignore
+/// let acker = source.acker();
+/// let sink = BatchingSink::new(sink, 100, Duration::from_secs(1))
+///     .on_flush(move |ack| acker.ack_until(ack.max_timestamp));
+/// ```
+///
+/// Sources that hand out an acker timestamp their records with their time
+/// of reading, strictly increasing, so that a timestamp identifies the
+/// records to acknowledge.
+#[derive(Clone, Default)]
+pub struct Acker {
+    shared: Arc<Shared>,
+}
+
+impl Acker {
+    /// Acknowledge all records with timestamps up to `timestamp`
+    pub fn ack_until(&self, timestamp: i64) {
+        let mut state = self.state();
+        state.acked_until = Some(state.acked_until.map_or(timestamp, |t| t.max(timestamp)));
+        drop(state);
+        self.shared.notify.notify_one();
+    }
+
+    /// Track a record read at `position`
+    pub(crate) fn push(&self, timestamp: i64, position: u64) {
+        self.state().pending.push_back((timestamp, position));
+    }
+
+    /// Take the last position of the records acknowledged since the
+    /// previous call
+    pub(crate) fn take_acked(&self) -> Option<u64> {
+        let mut state = self.state();
+        let acked_until = state.acked_until?;
+        let mut last = None;
+        while let Some(&(timestamp, position)) = state.pending.front() {
+            if timestamp > acked_until {
+                break;
+            }
+            last = Some(position);
+            state.pending.pop_front();
+        }
+        last
+    }
+
+    /// Whether all records read were acknowledged
+    pub(crate) fn is_idle(&self) -> bool {
+        self.state().pending.is_empty()
+    }
+
+    /// Forget the records not acknowledged yet, e.g. because they will be
+    /// read again
+    pub(crate) fn clear(&self) {
+        self.state().pending.clear();
+    }
+
+    /// Wait until records are acknowledged
+    pub(crate) async fn notified(&self) {
+        self.shared.notify.notified().await
+    }
+
+    fn state(&self) -> MutexGuard<'_, AckState> {
+        self.shared.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The current time in milliseconds, but at least one more than `last`
+pub(crate) fn increasing_timestamp(last: &mut i64) -> i64 {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64;
+    *last = now.max(last.saturating_add(1));
+    *last
+}
@@ -0,0 +1,219 @@
+use super::Source;
+use super::acker::{Acker, increasing_timestamp};
+use async_trait::async_trait;
+use fluxus_utils::amqp::{AmqpClient, AmqpOptions, AmqpQueue, Delivery};
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use serde::de::DeserializeOwned;
+
+type DecodeFn<T> = Box<dyn Fn(&Delivery) -> StreamResult<T> + Send + Sync>;
+
+/// A source that consumes an AMQP queue, e.g. of RabbitMQ.
+///
+/// The broker delivers at most `prefetch` messages that aren't acknowledged
+/// yet, so a pipeline that falls behind slows down the deliveries. Messages
+/// are acknowledged as they are read, or with [`acker`](Self::acker) once
+/// the sink flushed their records. Messages that fail to decode are
+/// rejected without requeueing, which routes them to the dead-letter
+/// exchange of the queue if it has one.
+///
+/// The source never ends. When the connection is lost it connects again
+/// after the reconnect delay of its options, and the broker redelivers the
+/// messages that weren't acknowledged.
+pub struct AmqpSource<T> {
+    options: AmqpOptions,
+    queue: AmqpQueue,
+    prefetch: u16,
+    decode: DecodeFn<T>,
+    acker: Option<Acker>,
+    client: Option<AmqpClient>,
+    last_timestamp: i64,
+}
+
+impl AmqpSource<Delivery> {
+    /// Create a new AMQP source emitting the raw deliveries of a queue,
+    /// declaring it first
+    pub fn new(options: AmqpOptions, queue: AmqpQueue) -> Self {
+        Self {
+            options,
+            queue,
+            prefetch: 100,
+            decode: Box::new(|delivery| Ok(delivery.clone())),
+            acker: None,
+            client: None,
+            last_timestamp: i64::MIN,
+        }
+    }
+}
+
+impl<T> AmqpSource<T> {
+    /// Limit the messages delivered but not acknowledged yet
+    pub fn with_prefetch(mut self, prefetch: u16) -> Self {
+        self.prefetch = prefetch.max(1);
+        self
+    }
+
+    /// Decode the deliveries with the given function
+    pub fn with_decoder<U, F>(self, decode: F) -> AmqpSource<U>
+    where
+        F: Fn(&Delivery) -> StreamResult<U> + Send + Sync + 'static,
+    {
+        AmqpSource {
+            options: self.options,
+            queue: self.queue,
+            prefetch: self.prefetch,
+            decode: Box::new(decode),
+            acker: self.acker,
+            client: self.client,
+            last_timestamp: self.last_timestamp,
+        }
+    }
+
+    /// Decode the bodies of the deliveries as JSON
+    pub fn json<U: DeserializeOwned>(self) -> AmqpSource<U> {
+        self.with_decoder(|delivery| {
+            serde_json::from_slice(&delivery.body)
+                .map_err(|e| StreamError::Serialization(e.to_string()))
+        })
+    }
+
+    /// Acknowledge deliveries only through the returned acker, instead of as
+    /// they are read
+    pub fn acker(&mut self) -> Acker {
+        self.acker.get_or_insert_with(Acker::default).clone()
+    }
+
+    async fn connect(&self) -> StreamResult<AmqpClient> {
+        let mut client = AmqpClient::connect(&self.options).await?;
+        client.declare_queue(&self.queue).await?;
+        client.set_prefetch(self.prefetch).await?;
+        client.consume(&self.queue.name).await?;
+        Ok(client)
+    }
+
+    /// Send the acknowledgements the acker asked for
+    async fn send_acks(&mut self) -> StreamResult<()> {
+        let (Some(acker), Some(client)) = (self.acker.as_ref(), self.client.as_mut()) else {
+            return Ok(());
+        };
+        match acker.take_acked() {
+            Some(tag) => client.ack(tag, true).await,
+            None => Ok(()),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.client = None;
+        // The broker redelivers the messages of the lost channel
+        if let Some(acker) = &self.acker {
+            acker.clear();
+        }
+    }
+
+    async fn ack(&mut self, tag: u64) -> StreamResult<()> {
+        match self.client.as_mut() {
+            Some(client) => client.ack(tag, false).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn reject(&mut self, tag: u64) -> StreamResult<()> {
+        match self.client.as_mut() {
+            Some(client) => client.nack(tag, false).await,
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl<T> Source<T> for AmqpSource<T>
+where
+    T: Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        self.client = Some(self.connect().await?);
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        loop {
+            if self.client.is_none() {
+                match self.connect().await {
+                    Ok(client) => self.client = Some(client),
+                    Err(e) => {
+                        tracing::warn!("Failed to reconnect to the AMQP broker: {}", e);
+                        tokio::time::sleep(self.options.reconnect_delay).await;
+                        continue;
+                    }
+                }
+            }
+
+            let delivery = match self.send_acks().await {
+                Err(e) => Err(e),
+                Ok(()) => {
+                    let Some(client) = self.client.as_mut() else {
+                        continue;
+                    };
+                    match &self.acker {
+                        // Wake up to send acknowledgements, which may be what
+                        // the broker waits for to deliver more
+                        Some(acker) => tokio::select! {
+                            delivery = client.next_delivery() => delivery,
+                            _ = acker.notified() => continue,
+                        },
+                        None => client.next_delivery().await,
+                    }
+                }
+            };
+            let delivery = match delivery {
+                Ok(delivery) => delivery,
+                Err(e) => {
+                    tracing::warn!("Lost the connection to the AMQP broker: {}", e);
+                    self.reset();
+                    tokio::time::sleep(self.options.reconnect_delay).await;
+                    continue;
+                }
+            };
+
+            let tag = delivery.delivery_tag;
+            let result = match (self.decode)(&delivery) {
+                Ok(data) => {
+                    let timestamp = increasing_timestamp(&mut self.last_timestamp);
+                    match &self.acker {
+                        Some(acker) => {
+                            acker.push(timestamp, tag);
+                            Ok(Some(Record::with_timestamp(data, timestamp)))
+                        }
+                        None => self
+                            .ack(tag)
+                            .await
+                            .map(|()| Some(Record::with_timestamp(data, timestamp))),
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Rejecting AMQP message from {} that failed to decode: {}",
+                        delivery.routing_key,
+                        e
+                    );
+                    self.reject(tag).await.map(|()| None)
+                }
+            };
+            match result {
+                Ok(Some(record)) => return Ok(Some(record)),
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!("Lost the connection to the AMQP broker: {}", e);
+                    self.reset();
+                }
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.send_acks().await?;
+        if let Some(mut client) = self.client.take() {
+            client.close().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use apache_avro::Reader;
+use apache_avro::schema_compatibility::SchemaCompatibility;
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use serde::de::DeserializeOwned;
+use std::fs::File;
+use std::io::BufReader;
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use super::Source;
+
+pub use apache_avro::Schema;
+
+fn avro_error(e: apache_avro::Error) -> StreamError {
+    StreamError::Serialization(format!("Avro error: {}", e))
+}
+
+/// A source that reads the objects of an Avro object container file and
+/// deserializes them into `T` with serde.
+///
+/// Objects are read with the schema the file was written with, or resolved
+/// into a reader schema set with [`with_reader_schema`](Self::with_reader_schema),
+/// so that files written with older versions of a schema can be read as
+/// the current version. Aliases of renamed records and fields aren't
+/// followed.
+pub struct AvroSource<T> {
+    path: PathBuf,
+    reader_schema: Option<Schema>,
+    reader: Option<Reader<'static, BufReader<File>>>,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> AvroSource<T> {
+    /// Create a source reading the container file at a path
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            reader_schema: None,
+            reader: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Resolve the objects into a reader schema, which must be able to read
+    /// the schema of the file
+    pub fn with_reader_schema(mut self, schema: Schema) -> Self {
+        self.reader_schema = Some(schema);
+        self
+    }
+}
+
+#[async_trait]
+impl<T> Source<T> for AvroSource<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        let reader = Reader::new(BufReader::new(File::open(&self.path)?)).map_err(avro_error)?;
+        if let Some(reader_schema) = &self.reader_schema {
+            SchemaCompatibility::can_read(reader.writer_schema(), reader_schema).map_err(|e| {
+                StreamError::Config(format!(
+                    "the reader schema can't read {}: {}",
+                    self.path.display(),
+                    e
+                ))
+            })?;
+        }
+        self.reader = Some(reader);
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        let Some(reader) = &mut self.reader else {
+            return Ok(None);
+        };
+        let Some(value) = reader.next().transpose().map_err(avro_error)? else {
+            return Ok(None);
+        };
+        let value = match &self.reader_schema {
+            Some(schema) => value.resolve(schema).map_err(avro_error)?,
+            None => value,
+        };
+        let data = apache_avro::from_value(&value).map_err(avro_error)?;
+        Ok(Some(Record::new(data)))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.reader = None;
+        Ok(())
+    }
+}
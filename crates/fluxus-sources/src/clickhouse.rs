@@ -0,0 +1,159 @@
+use async_trait::async_trait;
+use fluxus_utils::clickhouse::{
+    BytesCursor, ClickHouseOptions, RowBinaryDecoder, clickhouse_error,
+};
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use fluxus_utils::row::Row;
+use serde::de::DeserializeOwned;
+use tokio::sync::Mutex;
+
+use super::Source;
+
+/// A source that runs a `SELECT` query on ClickHouse with the `clickhouse`
+/// client and emits the rows of the results.
+///
+/// The results are streamed in the `RowBinaryWithNamesAndTypes` format and
+/// decoded as they arrive, so that queries returning more rows than fit in
+/// memory can be read. The source ends with the last row.
+pub struct ClickHouseSource<T> {
+    options: ClickHouseOptions,
+    query: String,
+    decode: Box<dyn Fn(Row) -> StreamResult<T> + Send + Sync>,
+    // Only used through `&mut self`, the lock makes the source `Sync`
+    cursor: Option<Mutex<BytesCursor>>,
+    decoder: Option<RowBinaryDecoder>,
+    /// Received bytes not decoded yet
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl ClickHouseSource<Row> {
+    /// Create a source running a query, without a `FORMAT` clause
+    pub fn new(options: ClickHouseOptions, query: impl Into<String>) -> Self {
+        Self {
+            options,
+            query: query.into(),
+            decode: Box::new(Ok),
+            cursor: None,
+            decoder: None,
+            buffer: Vec::new(),
+            position: 0,
+        }
+    }
+}
+
+impl<T> ClickHouseSource<T> {
+    /// Decode the rows with the given function
+    pub fn with_decoder<U, F>(self, decode: F) -> ClickHouseSource<U>
+    where
+        F: Fn(Row) -> StreamResult<U> + Send + Sync + 'static,
+    {
+        ClickHouseSource {
+            options: self.options,
+            query: self.query,
+            decode: Box::new(decode),
+            cursor: self.cursor,
+            decoder: self.decoder,
+            buffer: self.buffer,
+            position: self.position,
+        }
+    }
+
+    /// Deserialize the rows into a type with serde, by column name
+    pub fn deserialize<U: DeserializeOwned>(self) -> ClickHouseSource<U> {
+        self.with_decoder(|row| {
+            serde_json::from_value(row.to_json())
+                .map_err(|e| StreamError::Serialization(e.to_string()))
+        })
+    }
+
+    /// The schema of the results, once the source read their header
+    pub fn schema(&self) -> Option<&std::sync::Arc<fluxus_utils::row::Schema>> {
+        self.decoder.as_ref().map(RowBinaryDecoder::schema)
+    }
+
+    /// Receive the next chunk of the results into the buffer, `false` at
+    /// their end
+    async fn receive(&mut self) -> StreamResult<bool> {
+        let Some(cursor) = &mut self.cursor else {
+            return Ok(false);
+        };
+        let chunk = cursor.get_mut().next().await.map_err(clickhouse_error)?;
+        match chunk {
+            Some(chunk) => {
+                self.buffer.drain(..self.position);
+                self.position = 0;
+                self.buffer.extend_from_slice(&chunk);
+                Ok(true)
+            }
+            None => {
+                self.cursor = None;
+                if self.position < self.buffer.len() {
+                    return Err(StreamError::Serialization(
+                        "the ClickHouse results end within a row".to_string(),
+                    ));
+                }
+                Ok(false)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T> Source<T> for ClickHouseSource<T>
+where
+    T: Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        // Sent as is, without binding `?` placeholders
+        let cursor = self
+            .options
+            .client()
+            .await?
+            .query_raw(self.query.trim().trim_end_matches(';'))
+            .fetch_bytes("RowBinaryWithNamesAndTypes")
+            .map_err(clickhouse_error)?;
+
+        self.cursor = Some(Mutex::new(cursor));
+        self.buffer.clear();
+        self.position = 0;
+        self.decoder = None;
+        loop {
+            if let Some((decoder, len)) = RowBinaryDecoder::decode_header(&self.buffer)? {
+                self.decoder = Some(decoder);
+                self.position = len;
+                return Ok(());
+            }
+            if !self.receive().await? {
+                return Err(StreamError::Serialization(
+                    "the ClickHouse results have no header".to_string(),
+                ));
+            }
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        loop {
+            let Some(decoder) = &self.decoder else {
+                return Ok(None);
+            };
+            if self.position < self.buffer.len()
+                && let Some((row, len)) = decoder.decode_row(&self.buffer[self.position..])?
+            {
+                self.position += len;
+                return Ok(Some(Record::new((self.decode)(row)?)));
+            }
+            if !self.receive().await? {
+                return Ok(None);
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.cursor = None;
+        self.decoder = None;
+        self.buffer.clear();
+        self.position = 0;
+        Ok(())
+    }
+}
@@ -1,20 +1,44 @@
 use async_trait::async_trait;
+use fluxus_utils::compression::{CompressionCodec, DecodedReader};
 use fluxus_utils::models::{Record, StreamError, StreamResult};
+use fluxus_utils::security::{AuthConfig, TlsConfig};
 use futures::TryStreamExt;
 use reqwest;
 use std::io::{self, Error};
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::fs::File;
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio_util::io::StreamReader;
+use tokio_util::io::{ReaderStream, StreamReader};
 
-use super::Source;
+use super::{Progress, Source, SourceProgress};
 
-/// A source that reads CSV files
+/// A source that reads CSV files, emitting each record as a line.
+///
+/// By default lines are emitted as they are. Once the delimiter or quoting
+/// is changed or columns are selected, records are parsed and emitted as
+/// comma separated lines, e.g. for [`Row::from_csv_line`].
+///
+/// [`Row::from_csv_line`]: fluxus_utils::row::Row::from_csv_line
 pub struct CsvSource {
     source: CsvSourceType,
-    reader: Option<Box<dyn tokio::io::AsyncBufRead + Unpin + Send + Sync>>,
+    compression: CompressionCodec,
+    tls: TlsConfig,
+    auth: AuthConfig,
+    delimiter: u8,
+    quote: Option<u8>,
+    comment: Option<u8>,
+    has_headers: bool,
+    columns: Option<Columns>,
+    headers: Option<Vec<String>>,
+    /// Positions of the selected columns, resolved on init
+    projection: Option<Vec<usize>>,
+    reader: Option<DecodedReader>,
+    /// Bytes consumed from the file or response body, before decompression
+    bytes_read: Arc<AtomicU64>,
+    total_bytes: Option<u64>,
 }
 
 enum CsvSourceType {
@@ -22,40 +46,219 @@ enum CsvSourceType {
     RemoteUrl(String),
 }
 
+enum Columns {
+    Names(Vec<String>),
+    Indices(Vec<usize>),
+}
+
 impl CsvSource {
     /// Create a new CSV source from a local file path
     pub fn new<P: Into<PathBuf>>(path: P) -> Self {
-        Self {
-            source: CsvSourceType::LocalFile(path.into()),
-            reader: None,
-        }
+        Self::with_source(CsvSourceType::LocalFile(path.into()))
     }
 
     /// Create a new CSV source from a remote URL
     pub fn from_url<S: Into<String>>(url: S) -> Self {
+        Self::with_source(CsvSourceType::RemoteUrl(url.into()))
+    }
+
+    fn with_source(source: CsvSourceType) -> Self {
         Self {
-            source: CsvSourceType::RemoteUrl(url.into()),
+            source,
+            compression: CompressionCodec::None,
+            tls: TlsConfig::default(),
+            auth: AuthConfig::None,
+            delimiter: b',',
+            quote: Some(b'"'),
+            comment: None,
+            has_headers: false,
+            columns: None,
+            headers: None,
+            projection: None,
             reader: None,
+            bytes_read: Arc::new(AtomicU64::new(0)),
+            total_bytes: None,
+        }
+    }
+
+    /// Set the field delimiter, e.g. `b'\t'` for tab separated values
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Set the quote character. Quoted fields may contain delimiters and
+    /// line breaks, and a doubled quote stands for a quote.
+    pub fn with_quote(mut self, quote: u8) -> Self {
+        self.quote = Some(quote);
+        self
+    }
+
+    /// Treat quote characters as ordinary characters
+    pub fn without_quoting(mut self) -> Self {
+        self.quote = None;
+        self
+    }
+
+    /// Skip lines starting with the given character, e.g. `b'#'`
+    pub fn with_comment(mut self, comment: u8) -> Self {
+        self.comment = Some(comment);
+        self
+    }
+
+    /// Whether the first record is a header naming the columns, which is
+    /// not emitted
+    pub fn with_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    /// Emit only the named columns, in the given order. Requires headers.
+    pub fn select_columns<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.columns = Some(Columns::Names(names.into_iter().map(Into::into).collect()));
+        self
+    }
+
+    /// Emit only the columns at the given positions, in the given order.
+    /// Missing columns are empty.
+    pub fn select_column_indices<I: IntoIterator<Item = usize>>(mut self, indices: I) -> Self {
+        self.columns = Some(Columns::Indices(indices.into_iter().collect()));
+        self
+    }
+
+    /// The column names of the header, once initialized
+    pub fn headers(&self) -> Option<&[String]> {
+        self.headers.as_deref()
+    }
+
+    /// Set the compression codec of the input
+    pub fn with_compression(mut self, codec: CompressionCodec) -> Self {
+        self.compression = codec;
+        self
+    }
+
+    /// Set the TLS options used for remote URLs
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// Set the credentials used for remote URLs
+    pub fn with_auth(mut self, auth: AuthConfig) -> Self {
+        self.auth = auth;
+        self
+    }
+}
+
+/// Build an HTTP client honouring the TLS options
+async fn http_client(tls: &TlsConfig) -> StreamResult<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .danger_accept_invalid_certs(tls.accept_invalid_certs);
+    if let Some(path) = &tls.ca_cert {
+        let pem = tokio::fs::read(path).await?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .map_err(|e| StreamError::Config(format!("invalid CA certificate: {}", e)))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    if let (Some(cert), Some(key)) = (&tls.client_cert, &tls.client_key) {
+        let cert = tokio::fs::read(cert).await?;
+        let key = tokio::fs::read(key).await?;
+        let identity = reqwest::Identity::from_pkcs8_pem(&cert, &key)
+            .map_err(|e| StreamError::Config(format!("invalid client certificate: {}", e)))?;
+        builder = builder.identity(identity);
+    }
+    builder
+        .build()
+        .map_err(|_e| StreamError::Io(io::Error::other("create http client error")))
+}
+
+impl CsvSource {
+    /// Read the next record, which spans several lines if a quoted field
+    /// contains line breaks, skipping comment lines
+    async fn read_record(&mut self) -> StreamResult<Option<String>> {
+        let Some(reader) = &mut self.reader else {
+            return Ok(None);
+        };
+        let mut record = String::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 {
+                return Ok((!record.is_empty()).then_some(record));
+            }
+            if record.is_empty()
+                && self
+                    .comment
+                    .is_some_and(|comment| line.as_bytes().first() == Some(&comment))
+            {
+                continue;
+            }
+            record.push_str(&line);
+            // A record with an odd number of quotes continues on the next line
+            let in_quotes = self
+                .quote
+                .is_some_and(|quote| record.bytes().filter(|b| *b == quote).count() % 2 == 1);
+            if !in_quotes {
+                return Ok(Some(record));
+            }
         }
     }
+
+    fn parses_records(&self) -> bool {
+        self.delimiter != b',' || self.quote != Some(b'"') || self.projection.is_some()
+    }
+
+    fn parse_fields(&self, record: &str) -> StreamResult<csv::StringRecord> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(self.delimiter)
+            .quoting(self.quote.is_some())
+            .quote(self.quote.unwrap_or(b'"'))
+            .from_reader(record.as_bytes());
+        Ok(reader.records().next().transpose()?.unwrap_or_default())
+    }
+}
+
+/// Write fields as a comma separated line
+fn write_line<'a, I: Iterator<Item = &'a str>>(fields: I) -> StreamResult<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .flexible(true)
+        .from_writer(Vec::new());
+    writer.write_record(fields)?;
+    let mut line = String::from_utf8(writer.into_inner()?)?;
+    line.truncate(line.trim_end_matches(['\r', '\n']).len());
+    Ok(line)
 }
 
 #[async_trait]
 impl Source<String> for CsvSource {
     async fn init(&mut self) -> StreamResult<()> {
+        self.bytes_read = Arc::new(AtomicU64::new(0));
+        let bytes_read = self.bytes_read.clone();
+        let count = move |bytes: &bytes::Bytes| {
+            bytes_read.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+        };
         match &self.source {
             CsvSourceType::LocalFile(path) => {
                 let file = File::open(path)
                     .await
                     .map_err(|e| StreamError::Io(Error::other(format!("{}", e))))?;
-                self.reader = Some(Box::new(BufReader::new(file)));
+                self.total_bytes = file.metadata().await.ok().map(|meta| meta.len());
+                let reader = StreamReader::new(ReaderStream::new(file).inspect_ok(count));
+                self.reader = Some(self.compression.decoder(BufReader::new(reader)).await?);
             }
             CsvSourceType::RemoteUrl(url) => {
-                let client = reqwest::Client::builder()
-                    .timeout(Duration::from_secs(30))
-                    .build()
-                    .map_err(|_e| StreamError::Io(io::Error::other("create http client error")))?;
-                let response = client.get(url).send().await.map_err(|e| {
+                let client = http_client(&self.tls).await?;
+                let mut request = client.get(url);
+                for (name, value) in self.auth.http_headers("GET", url)? {
+                    request = request.header(name, value);
+                }
+                let response = request.send().await.map_err(|e| {
                     StreamError::Io(Error::other(format!("Failed to fetch URL: {}", e)))
                 })?;
 
@@ -66,31 +269,58 @@ impl Source<String> for CsvSource {
                     ))));
                 }
 
+                self.total_bytes = response.content_length();
                 let byte_stream = response
                     .bytes_stream()
-                    .map_err(|e| Error::other(format!("{}", e)));
+                    .map_err(|e| Error::other(format!("{}", e)))
+                    .inspect_ok(count);
 
                 let reader = StreamReader::new(byte_stream);
-                self.reader = Some(Box::new(BufReader::new(reader)));
+                self.reader = Some(self.compression.decoder(BufReader::new(reader)).await?);
             }
         }
+
+        if self.has_headers {
+            let header = self.read_record().await?.unwrap_or_default();
+            let header = self.parse_fields(header.trim())?;
+            self.headers = Some(header.iter().map(str::to_string).collect());
+        }
+        self.projection = match &self.columns {
+            None => None,
+            Some(Columns::Indices(indices)) => Some(indices.clone()),
+            Some(Columns::Names(names)) => {
+                let headers = self.headers.as_ref().ok_or_else(|| {
+                    StreamError::Config("selecting CSV columns by name requires headers".into())
+                })?;
+                let indices = names
+                    .iter()
+                    .map(|name| {
+                        headers.iter().position(|h| h == name).ok_or_else(|| {
+                            StreamError::Config(format!("unknown CSV column {}", name))
+                        })
+                    })
+                    .collect::<StreamResult<_>>()?;
+                Some(indices)
+            }
+        };
         Ok(())
     }
 
     async fn next(&mut self) -> StreamResult<Option<Record<String>>> {
-        if let Some(reader) = &mut self.reader {
-            let mut line = String::new();
-            match reader.read_line(&mut line).await {
-                Ok(0) => Ok(None), // EOF
-                Ok(_) => {
-                    let line = line.trim().to_string();
-                    Ok(Some(Record::new(line)))
-                }
-                Err(e) => Err(e.into()),
-            }
-        } else {
-            Ok(None)
+        let Some(record) = self.read_record().await? else {
+            return Ok(None);
+        };
+        let record = record.trim();
+        if !self.parses_records() {
+            return Ok(Some(Record::new(record.to_string())));
         }
+
+        let fields = self.parse_fields(record)?;
+        let line = match &self.projection {
+            Some(indices) => write_line(indices.iter().map(|i| fields.get(*i).unwrap_or(""))),
+            None => write_line(fields.iter()),
+        }?;
+        Ok(Some(Record::new(line)))
     }
 
     async fn close(&mut self) -> StreamResult<()> {
@@ -98,3 +328,9 @@ impl Source<String> for CsvSource {
         Ok(())
     }
 }
+
+impl SourceProgress for CsvSource {
+    fn progress(&self) -> Progress {
+        Progress::new(self.bytes_read.load(Ordering::Relaxed), self.total_bytes)
+    }
+}
@@ -1,15 +1,146 @@
 use async_trait::async_trait;
 use fluxus_utils::models::{Record, StreamResult};
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
 use std::marker::PhantomData;
+use std::time::Duration;
+use tokio::time::Instant;
 
 use super::Source;
 
-/// A source that generates test data
+/// A distribution of random values, sampled by [`Sampler`]
+pub trait Distribution: Send + Sync {
+    fn sample(&self, rng: &mut dyn RngCore) -> f64;
+}
+
+/// Values spread evenly over `[low, high)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Uniform {
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Uniform {
+    pub fn new(low: f64, high: f64) -> Self {
+        Self { low, high }
+    }
+}
+
+impl Distribution for Uniform {
+    fn sample(&self, rng: &mut dyn RngCore) -> f64 {
+        self.low + (self.high - self.low) * rng.r#gen::<f64>()
+    }
+}
+
+/// Values of a normal (Gaussian) distribution, e.g. of latencies around a
+/// mean
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Normal {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl Normal {
+    pub fn new(mean: f64, std_dev: f64) -> Self {
+        Self { mean, std_dev }
+    }
+}
+
+impl Distribution for Normal {
+    fn sample(&self, rng: &mut dyn RngCore) -> f64 {
+        // Box-Muller transform
+        let u1 = 1.0 - rng.r#gen::<f64>();
+        let u2 = rng.r#gen::<f64>();
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+        self.mean + self.std_dev * z
+    }
+}
+
+/// Ranks `1..=n` of a Zipf distribution, where rank `k` is drawn with a
+/// probability proportional to `1 / k^exponent`, e.g. of the popularity of
+/// pages or keys, with a few hot ranks and a long tail
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zipf {
+    /// Cumulative probability of each rank
+    cumulative: Vec<f64>,
+}
+
+impl Zipf {
+    pub fn new(n: usize, exponent: f64) -> Self {
+        let mut cumulative = Vec::with_capacity(n.max(1));
+        let mut total = 0.0;
+        for k in 1..=n.max(1) {
+            total += 1.0 / (k as f64).powf(exponent);
+            cumulative.push(total);
+        }
+        for p in &mut cumulative {
+            *p /= total;
+        }
+        Self { cumulative }
+    }
+}
+
+impl Distribution for Zipf {
+    fn sample(&self, rng: &mut dyn RngCore) -> f64 {
+        let u = rng.r#gen::<f64>();
+        let rank = self.cumulative.partition_point(|p| *p <= u);
+        (rank.min(self.cumulative.len() - 1) + 1) as f64
+    }
+}
+
+/// Draws values of a [`Distribution`], for generator functions
+pub struct Sampler {
+    distribution: Box<dyn Distribution>,
+    rng: StdRng,
+}
+
+impl Sampler {
+    /// Create a sampler seeded from the operating system
+    pub fn new<D: Distribution + 'static>(distribution: D) -> Self {
+        Self {
+            distribution: Box::new(distribution),
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    /// Create a sampler drawing the same values on every run
+    pub fn seeded<D: Distribution + 'static>(distribution: D, seed: u64) -> Self {
+        Self {
+            distribution: Box::new(distribution),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn sample(&mut self) -> f64 {
+        self.distribution.sample(&mut self.rng)
+    }
+}
+
+/// Periodic bursts of a rate limited generator
+#[derive(Debug, Clone, Copy)]
+struct Burst {
+    every: Duration,
+    length: Duration,
+    multiplier: f64,
+}
+
+/// A source that generates test data.
+///
+/// By default records are generated as fast as they are read, until the
+/// generator function returns `None`. For load tests, the source can be
+/// limited to a rate, with periodic bursts above it, and to a number of
+/// records.
 pub struct GeneratorSource<T, F>
 where
     F: FnMut() -> Option<T> + Send,
 {
     generator: F,
+    rate: Option<f64>,
+    burst: Option<Burst>,
+    max_records: Option<u64>,
+    emitted: u64,
+    start: Option<Instant>,
+    next_due: Option<Instant>,
     _phantom: PhantomData<T>,
 }
 
@@ -21,6 +152,12 @@ where
     pub fn new(generator: F) -> Self {
         Self {
             generator,
+            rate: None,
+            burst: None,
+            max_records: None,
+            emitted: 0,
+            start: None,
+            next_due: None,
             _phantom: PhantomData,
         }
     }
@@ -44,6 +181,73 @@ where
             }
         })
     }
+
+    /// Generate at most `records_per_second`, evenly spaced
+    pub fn with_rate(mut self, records_per_second: f64) -> Self {
+        self.rate = (records_per_second > 0.0).then_some(records_per_second);
+        self
+    }
+
+    /// Multiply the rate by `multiplier` for `length` at the start of every
+    /// `every`, e.g. ten times the rate for 5 seconds every minute
+    pub fn with_bursts(mut self, every: Duration, length: Duration, multiplier: f64) -> Self {
+        self.burst = Some(Burst {
+            every,
+            length,
+            multiplier,
+        });
+        self
+    }
+
+    /// End the source after `max_records` records
+    pub fn with_max_records(mut self, max_records: u64) -> Self {
+        self.max_records = Some(max_records);
+        self
+    }
+
+    /// The rate at a time since the start, with bursts
+    fn rate_at(&self, elapsed: Duration) -> Option<f64> {
+        let rate = self.rate?;
+        Some(match self.burst {
+            Some(burst)
+                if !burst.every.is_zero()
+                    && elapsed.as_nanos() % burst.every.as_nanos() < burst.length.as_nanos() =>
+            {
+                rate * burst.multiplier.max(f64::MIN_POSITIVE)
+            }
+            _ => rate,
+        })
+    }
+
+    /// Wait until the next record is due
+    async fn pace(&mut self) {
+        if self.rate.is_none() {
+            return;
+        }
+        let now = Instant::now();
+        let start = *self.start.get_or_insert(now);
+        let due = self.next_due.unwrap_or(now);
+        if due > now {
+            tokio::time::sleep_until(due).await;
+        }
+        // Catch up when reads fall behind, but by at most a second of records
+        let due = match Instant::now().checked_sub(Duration::from_secs(1)) {
+            Some(behind) => due.max(behind),
+            None => due,
+        };
+        let rate = self
+            .rate_at(due.saturating_duration_since(start))
+            .unwrap_or(1.0);
+        self.next_due = Some(due + Duration::from_secs_f64(1.0 / rate));
+    }
+}
+
+impl GeneratorSource<f64, fn() -> Option<f64>> {
+    /// Create a source that generates the values of a distribution without
+    /// end, e.g. with [`with_max_records`](GeneratorSource::with_max_records)
+    pub fn sampling(mut sampler: Sampler) -> GeneratorSource<f64, impl FnMut() -> Option<f64>> {
+        GeneratorSource::new(move || Some(sampler.sample()))
+    }
 }
 
 #[async_trait]
@@ -53,11 +257,22 @@ where
     F: FnMut() -> Option<T> + Send + Sync,
 {
     async fn init(&mut self) -> StreamResult<()> {
+        self.emitted = 0;
+        self.start = None;
+        self.next_due = None;
         Ok(())
     }
 
     async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
-        Ok((self.generator)().map(Record::new))
+        if self.max_records.is_some_and(|max| self.emitted >= max) {
+            return Ok(None);
+        }
+        self.pace().await;
+        let record = (self.generator)().map(Record::new);
+        if record.is_some() {
+            self.emitted += 1;
+        }
+        Ok(record)
     }
 
     async fn close(&mut self) -> StreamResult<()> {
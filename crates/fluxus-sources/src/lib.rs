@@ -1,10 +1,48 @@
+#[cfg(any(feature = "amqp", feature = "postgres"))]
+pub mod acker;
+#[cfg(feature = "amqp")]
+pub mod amqp;
+#[cfg(feature = "avro")]
+pub mod avro;
+#[cfg(feature = "clickhouse")]
+pub mod clickhouse;
 pub mod csv;
 pub mod generator;
+pub mod mmap;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod progress;
+pub mod socket;
+pub mod sql;
+pub mod stdin;
+pub mod syslog;
 
+#[cfg(any(feature = "amqp", feature = "postgres"))]
+pub use acker::Acker;
+#[cfg(feature = "amqp")]
+pub use amqp::AmqpSource;
+#[cfg(feature = "avro")]
+pub use avro::AvroSource;
+#[cfg(feature = "clickhouse")]
+pub use clickhouse::ClickHouseSource;
 pub use csv::CsvSource;
 
 use fluxus_utils::models::{Record, StreamResult};
 pub use generator::GeneratorSource;
+pub use mmap::MmapFileSource;
+#[cfg(feature = "mqtt")]
+pub use mqtt::MqttSource;
+#[cfg(feature = "postgres")]
+pub use postgres::PostgresCdcSource;
+pub use progress::{Progress, SourceProgress};
+pub use socket::SocketSource;
+#[cfg(any(feature = "mysql", feature = "postgres", feature = "sqlite"))]
+pub use sql::SqlxClient;
+pub use sql::{SqlClient, SqlSource};
+pub use stdin::StdinSource;
+pub use syslog::{SyslogRecord, SyslogSource};
 
 use async_trait::async_trait;
 
@@ -19,4 +57,10 @@ pub trait Source<T> {
 
     /// Close the source and release resources
     async fn close(&mut self) -> StreamResult<()>;
+
+    /// The event-time watermark of the records read so far, in
+    /// milliseconds, for sources that track one
+    fn watermark(&self) -> Option<i64> {
+        None
+    }
 }
@@ -0,0 +1,123 @@
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use memmap2::Mmap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::path::PathBuf;
+
+use super::{Progress, Source, SourceProgress};
+
+const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+
+/// A source that reads the lines of a large local file through a memory map.
+///
+/// The file is scanned in chunks of roughly `chunk_size` bytes. Every chunk
+/// is extended to the next newline so that no line is split across chunks.
+pub struct MmapFileSource {
+    path: PathBuf,
+    chunk_size: usize,
+    mmap: Option<Mmap>,
+    offset: usize,
+    /// Lines of the current chunk with the file offset where each one ends
+    lines: VecDeque<(String, usize)>,
+    /// File offset after the last emitted line
+    position: usize,
+}
+
+impl MmapFileSource {
+    /// Create a new source from a local file path
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            mmap: None,
+            offset: 0,
+            lines: VecDeque::new(),
+            position: 0,
+        }
+    }
+
+    /// Set the number of bytes scanned at once
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Split the next chunk of the file into lines
+    fn fill(&mut self) -> StreamResult<()> {
+        let Some(mmap) = &self.mmap else {
+            return Ok(());
+        };
+        let data = &mmap[..];
+        let start = self.offset;
+        if start >= data.len() {
+            return Ok(());
+        }
+
+        // Align the end of the chunk to the end of a line
+        let end = (start + self.chunk_size).min(data.len());
+        let end = match memchr::memchr(b'\n', &data[end - 1..]) {
+            Some(pos) => end + pos,
+            None => data.len(),
+        };
+
+        let chunk = &data[start..end];
+        let chunk = chunk.strip_suffix(b"\n").unwrap_or(chunk);
+        let mut line_start = start;
+        for line in chunk.split(|b| *b == b'\n') {
+            let line_end = (line_start + line.len() + 1).min(end);
+            line_start = line_end;
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            let line = std::str::from_utf8(line).map_err(|e| {
+                StreamError::Serialization(format!("invalid UTF-8 in {:?}: {}", self.path, e))
+            })?;
+            self.lines.push_back((line.to_string(), line_end));
+        }
+        self.offset = end;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Source<String> for MmapFileSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        let file = File::open(&self.path)?;
+        self.offset = 0;
+        self.position = 0;
+        self.lines.clear();
+        // Mapping an empty file fails on some platforms
+        if file.metadata()?.len() == 0 {
+            self.mmap = None;
+            return Ok(());
+        }
+        // SAFETY: the file is only read, callers must not truncate it while it is mapped
+        let mmap = unsafe { Mmap::map(&file)? };
+        #[cfg(unix)]
+        mmap.advise(memmap2::Advice::Sequential)?;
+        self.mmap = Some(mmap);
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<String>>> {
+        if self.lines.is_empty() {
+            self.fill()?;
+        }
+        Ok(self.lines.pop_front().map(|(line, end)| {
+            self.position = end;
+            Record::new(line)
+        }))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.mmap = None;
+        self.lines.clear();
+        Ok(())
+    }
+}
+
+impl SourceProgress for MmapFileSource {
+    fn progress(&self) -> Progress {
+        let total = self.mmap.as_ref().map_or(0, |mmap| mmap.len());
+        Progress::new(self.position as u64, Some(total as u64))
+    }
+}
@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use fluxus_utils::mqtt::{MqttClient, MqttMessage, MqttOptions, QoS};
+use serde::de::DeserializeOwned;
+
+use super::Source;
+
+type DecodeFn<T> = Box<dyn Fn(MqttMessage) -> StreamResult<T> + Send + Sync>;
+
+/// A source that reads the messages of MQTT topic subscriptions.
+///
+/// The source never ends. When the connection is lost it connects again
+/// after the reconnect delay of its options and renews its subscriptions,
+/// failing once the maximum reconnect attempts of the options are used up.
+pub struct MqttSource<T> {
+    options: MqttOptions,
+    subscriptions: Vec<(String, QoS)>,
+    decode: DecodeFn<T>,
+    client: Option<MqttClient>,
+}
+
+impl MqttSource<MqttMessage> {
+    /// Create a new MQTT source emitting the raw messages
+    pub fn new(options: MqttOptions) -> Self {
+        Self {
+            options,
+            subscriptions: Vec::new(),
+            decode: Box::new(Ok),
+            client: None,
+        }
+    }
+}
+
+impl<T> MqttSource<T> {
+    /// Subscribe to a topic filter, which may contain the `+` and `#`
+    /// wildcards
+    pub fn subscribe(mut self, filter: impl Into<String>, qos: QoS) -> Self {
+        self.subscriptions.push((filter.into(), qos));
+        self
+    }
+
+    /// Decode the messages with the given function
+    pub fn with_decoder<U, F>(self, decode: F) -> MqttSource<U>
+    where
+        F: Fn(MqttMessage) -> StreamResult<U> + Send + Sync + 'static,
+    {
+        MqttSource {
+            options: self.options,
+            subscriptions: self.subscriptions,
+            decode: Box::new(decode),
+            client: self.client,
+        }
+    }
+
+    /// Decode the payloads of the messages as JSON
+    pub fn json<U: DeserializeOwned>(self) -> MqttSource<U> {
+        self.with_decoder(|message| {
+            serde_json::from_slice(&message.payload)
+                .map_err(|e| StreamError::Serialization(e.to_string()))
+        })
+    }
+}
+
+#[async_trait]
+impl<T> Source<T> for MqttSource<T>
+where
+    T: Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        self.client = Some(MqttClient::connect(&self.options, &self.subscriptions).await?);
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        let client = match self.client.as_mut() {
+            Some(client) => client,
+            None => self
+                .client
+                .insert(MqttClient::connect(&self.options, &self.subscriptions).await?),
+        };
+        let message = client.next_message().await?;
+        (self.decode)(message).map(|data| Some(Record::new(data)))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        if let Some(mut client) = self.client.take() {
+            client.disconnect().await?;
+        }
+        Ok(())
+    }
+}
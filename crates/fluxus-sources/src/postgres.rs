@@ -0,0 +1,184 @@
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamResult};
+use fluxus_utils::postgres::{
+    Change, Lsn, PgOutputDecoder, PostgresOptions, ReplicationClient, ReplicationMessage,
+    ReplicationPlugin, decode_wal2json,
+};
+use std::time::Instant;
+
+use super::Source;
+use super::acker::{Acker, increasing_timestamp};
+
+/// A source of the row changes of a Postgres database, streamed from a
+/// logical replication slot decoded by `wal2json` or `pgoutput`.
+///
+/// The source confirms the position of the changes it read to the server
+/// every status interval, or with [`acker`](Self::acker) the position of
+/// the changes the sink flushed. The slot keeps the log after the confirmed
+/// position, so a source that connects again, or a new one on the same
+/// slot, resumes there and may emit the changes after it once more.
+///
+/// The source never ends. When the connection is lost it connects again
+/// after the reconnect delay of its options.
+pub struct PostgresCdcSource {
+    options: PostgresOptions,
+    client: Option<ReplicationClient>,
+    decoder: PgOutputDecoder,
+    acker: Option<Acker>,
+    /// Latest position received from the server
+    received: Lsn,
+    /// Latest position confirmed to the server
+    flushed: Lsn,
+    last_status: Instant,
+    last_timestamp: i64,
+}
+
+impl PostgresCdcSource {
+    pub fn new(options: PostgresOptions) -> Self {
+        Self {
+            received: options.start_lsn,
+            flushed: options.start_lsn,
+            options,
+            client: None,
+            decoder: PgOutputDecoder::new(),
+            acker: None,
+            last_status: Instant::now(),
+            last_timestamp: i64::MIN,
+        }
+    }
+
+    /// Confirm positions only through the returned acker, instead of as
+    /// the changes are read
+    pub fn acker(&mut self) -> Acker {
+        self.acker.get_or_insert_with(Acker::default).clone()
+    }
+
+    /// Latest position confirmed to the server, to resume from with
+    /// [`PostgresOptions::with_start_lsn`]
+    pub fn flushed_lsn(&self) -> Lsn {
+        self.flushed
+    }
+
+    async fn connect(&mut self) -> StreamResult<()> {
+        let mut client = ReplicationClient::connect(&self.options).await?;
+        client.create_slot(&self.options).await?;
+        client
+            .start_replication(&self.options, self.flushed)
+            .await?;
+        self.client = Some(client);
+        self.decoder = PgOutputDecoder::new();
+        self.received = self.flushed;
+        // Changes after the confirmed position are streamed again
+        if let Some(acker) = &self.acker {
+            acker.clear();
+        }
+        Ok(())
+    }
+
+    async fn send_status(&mut self) -> StreamResult<()> {
+        match &self.acker {
+            Some(acker) => {
+                if let Some(position) = acker.take_acked() {
+                    self.flushed = self.flushed.max(Lsn(position));
+                }
+                if acker.is_idle() {
+                    self.flushed = self.flushed.max(self.received);
+                }
+            }
+            None => self.flushed = self.flushed.max(self.received),
+        }
+        self.last_status = Instant::now();
+        match self.client.as_mut() {
+            Some(client) => client.send_status(self.received, self.flushed).await,
+            None => Ok(()),
+        }
+    }
+
+    fn decode(&mut self, lsn: Lsn, data: &[u8]) -> StreamResult<Option<Change>> {
+        match self.options.plugin {
+            ReplicationPlugin::Wal2Json => decode_wal2json(lsn, data),
+            ReplicationPlugin::PgOutput { .. } => self.decoder.decode(lsn, data),
+        }
+    }
+
+    /// Read the next message, or `None` once a status update is due
+    async fn next_message(&mut self) -> Option<StreamResult<ReplicationMessage>> {
+        let wait = self
+            .options
+            .status_interval
+            .saturating_sub(self.last_status.elapsed());
+        let client = self.client.as_mut()?;
+        let acked = async {
+            match &self.acker {
+                Some(acker) => acker.notified().await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            message = client.next_message() => Some(message),
+            _ = acked => None,
+            _ = tokio::time::sleep(wait) => None,
+        }
+    }
+
+    async fn reconnect_after(&mut self, e: fluxus_utils::models::StreamError) {
+        tracing::warn!("Lost the replication connection to Postgres: {}", e);
+        self.client = None;
+        tokio::time::sleep(self.options.reconnect_delay).await;
+    }
+}
+
+#[async_trait]
+impl Source<Change> for PostgresCdcSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        self.connect().await
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<Change>>> {
+        loop {
+            if self.client.is_none()
+                && let Err(e) = self.connect().await
+            {
+                self.reconnect_after(e).await;
+                continue;
+            }
+
+            let message = match self.next_message().await {
+                Some(message) => message,
+                None => {
+                    if let Err(e) = self.send_status().await {
+                        self.reconnect_after(e).await;
+                    }
+                    continue;
+                }
+            };
+            match message {
+                Ok(ReplicationMessage::XLogData { lsn, data, .. }) => {
+                    self.received = self.received.max(lsn);
+                    if let Some(change) = self.decode(lsn, &data)? {
+                        let timestamp = increasing_timestamp(&mut self.last_timestamp);
+                        if let Some(acker) = &self.acker {
+                            acker.push(timestamp, lsn.0);
+                        }
+                        return Ok(Some(Record::with_timestamp(change, timestamp)));
+                    }
+                }
+                Ok(ReplicationMessage::Keepalive { end, reply }) => {
+                    self.received = self.received.max(end);
+                    if reply && let Err(e) = self.send_status().await {
+                        self.reconnect_after(e).await;
+                    }
+                }
+                Err(e) => self.reconnect_after(e).await,
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.send_status().await?;
+        if let Some(mut client) = self.client.take() {
+            client.close().await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+/// Position of a bounded source in its input, in bytes or records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Progress {
+    /// Amount of input consumed so far
+    pub position: u64,
+    /// Total amount of input, if known
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    pub fn new(position: u64, total: Option<u64>) -> Self {
+        Self { position, total }
+    }
+
+    /// Fraction of the input consumed, between 0 and 1
+    pub fn fraction(&self) -> Option<f64> {
+        match self.total? {
+            0 => Some(1.0),
+            total => Some((self.position as f64 / total as f64).min(1.0)),
+        }
+    }
+}
+
+/// Implemented by sources that know how far they are through their input
+pub trait SourceProgress {
+    fn progress(&self) -> Progress;
+}
@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamResult};
+use std::net::SocketAddr;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::Source;
+
+enum SocketMode {
+    Connect(String),
+    Listen(String),
+}
+
+/// A source that reads delimited text from a TCP socket, one record per
+/// line, e.g. the lines typed into `nc -lk 9999`.
+///
+/// A connecting source ends when the server closes the connection. A
+/// listening source reads the connections of clients one after another and
+/// never ends.
+pub struct SocketSource {
+    mode: SocketMode,
+    delimiter: u8,
+    listener: Option<TcpListener>,
+    reader: Option<BufReader<TcpStream>>,
+}
+
+impl SocketSource {
+    /// Create a source that connects to a server, e.g. `localhost:9999`
+    pub fn connect<S: Into<String>>(addr: S) -> Self {
+        Self::new(SocketMode::Connect(addr.into()))
+    }
+
+    /// Create a source that listens for clients on an address, e.g.
+    /// `0.0.0.0:9999`
+    pub fn listen<S: Into<String>>(addr: S) -> Self {
+        Self::new(SocketMode::Listen(addr.into()))
+    }
+
+    fn new(mode: SocketMode) -> Self {
+        Self {
+            mode,
+            delimiter: b'\n',
+            listener: None,
+            reader: None,
+        }
+    }
+
+    /// Split records on another byte than a newline
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// The address a listening source is bound to once initialized, e.g. to
+    /// find the port chosen for port 0
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener
+            .as_ref()
+            .and_then(|listener| listener.local_addr().ok())
+    }
+}
+
+#[async_trait]
+impl Source<String> for SocketSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        match &self.mode {
+            SocketMode::Connect(addr) => {
+                self.reader = Some(BufReader::new(TcpStream::connect(addr).await?));
+            }
+            SocketMode::Listen(addr) => {
+                self.listener = Some(TcpListener::bind(addr).await?);
+            }
+        }
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<String>>> {
+        loop {
+            let reader = match (&mut self.reader, &self.listener) {
+                (Some(reader), _) => reader,
+                (None, Some(listener)) => {
+                    let (stream, peer) = listener.accept().await?;
+                    tracing::debug!("Reading lines from {}", peer);
+                    self.reader.insert(BufReader::new(stream))
+                }
+                (None, None) => return Ok(None),
+            };
+
+            let mut line = Vec::new();
+            if reader.read_until(self.delimiter, &mut line).await? == 0 {
+                self.reader = None;
+                continue;
+            }
+            if line.last() == Some(&self.delimiter) {
+                line.pop();
+            }
+            if self.delimiter == b'\n' && line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            return Ok(Some(Record::new(
+                String::from_utf8_lossy(&line).into_owned(),
+            )));
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.reader = None;
+        self.listener = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamResult};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+use super::Source;
+
+type LineReader = Box<dyn tokio::io::AsyncBufRead + Send + Sync + Unpin>;
+
+/// A source that reads lines of text from standard input, one record per
+/// line, so that a pipeline can run in a shell pipeline, e.g.
+/// `tail -f access.log | my-app`.
+///
+/// The source ends when its input is closed.
+pub struct StdinSource {
+    reader: Option<LineReader>,
+    skip_empty: bool,
+}
+
+impl StdinSource {
+    /// Create a source reading standard input
+    pub fn new() -> Self {
+        Self::from_reader(tokio::io::stdin())
+    }
+
+    /// Create a source reading the lines of another reader, e.g. a file or
+    /// the output of a child process
+    pub fn from_reader<R>(reader: R) -> Self
+    where
+        R: AsyncRead + Send + Sync + Unpin + 'static,
+    {
+        Self {
+            reader: Some(Box::new(BufReader::new(reader))),
+            skip_empty: false,
+        }
+    }
+
+    /// Skip lines that are empty or only whitespace
+    pub fn skip_empty_lines(mut self) -> Self {
+        self.skip_empty = true;
+        self
+    }
+}
+
+impl Default for StdinSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Source<String> for StdinSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<String>>> {
+        let Some(reader) = &mut self.reader else {
+            return Ok(None);
+        };
+        loop {
+            let mut line = Vec::new();
+            if reader.read_until(b'\n', &mut line).await? == 0 {
+                self.reader = None;
+                return Ok(None);
+            }
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            if self.skip_empty && line.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+            return Ok(Some(Record::new(
+                String::from_utf8_lossy(&line).into_owned(),
+            )));
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.reader = None;
+        Ok(())
+    }
+}
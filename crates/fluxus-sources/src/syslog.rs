@@ -0,0 +1,343 @@
+use async_trait::async_trait;
+use fluxus_utils::models::{Record, StreamResult};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::UdpSocket;
+
+use super::Source;
+
+/// Largest datagram a UDP packet can carry
+const MAX_DATAGRAM: usize = 65_535;
+
+/// Severity of a syslog message, from most to least severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Severity {
+    Emergency = 0,
+    Alert = 1,
+    Critical = 2,
+    Error = 3,
+    Warning = 4,
+    Notice = 5,
+    Informational = 6,
+    Debug = 7,
+}
+
+impl Severity {
+    fn from_code(code: u8) -> Self {
+        match code {
+            0 => Self::Emergency,
+            1 => Self::Alert,
+            2 => Self::Critical,
+            3 => Self::Error,
+            4 => Self::Warning,
+            5 => Self::Notice,
+            6 => Self::Informational,
+            _ => Self::Debug,
+        }
+    }
+
+    /// Whether the severity is [`Error`](Self::Error) or worse
+    pub fn is_error(&self) -> bool {
+        *self <= Self::Error
+    }
+}
+
+/// A syslog message, as parsed from the RFC 5424 or the older RFC 3164
+/// (BSD) format
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyslogRecord {
+    pub facility: u8,
+    pub severity: Severity,
+    /// Time of the message in milliseconds since the unix epoch. RFC 3164
+    /// timestamps have no year or zone and are taken as UTC of the current
+    /// year.
+    pub timestamp: Option<i64>,
+    pub hostname: Option<String>,
+    /// Application, or the tag of RFC 3164 messages
+    pub app_name: Option<String>,
+    pub proc_id: Option<String>,
+    pub msg_id: Option<String>,
+    /// Structured data elements of RFC 5424 messages, as sent
+    pub structured_data: Option<String>,
+    pub message: String,
+    /// Sender of the datagram
+    pub peer: Option<SocketAddr>,
+}
+
+impl SyslogRecord {
+    /// Parse a message leniently: a message without a valid priority is
+    /// kept whole as the text of a user-level notice, as RFC 3164 suggests
+    pub fn parse(line: &str) -> Self {
+        let line = line.trim_end_matches(['\r', '\n', '\0']);
+        let Some((priority, rest)) = parse_priority(line) else {
+            return Self::new(13, line.to_string());
+        };
+        match rest.strip_prefix("1 ") {
+            Some(rest) => Self::parse_rfc5424(priority, rest),
+            None => Self::parse_rfc3164(priority, rest),
+        }
+    }
+
+    fn new(priority: u8, message: String) -> Self {
+        Self {
+            facility: priority >> 3,
+            severity: Severity::from_code(priority & 0x07),
+            timestamp: None,
+            hostname: None,
+            app_name: None,
+            proc_id: None,
+            msg_id: None,
+            structured_data: None,
+            message,
+            peer: None,
+        }
+    }
+
+    fn parse_rfc5424(priority: u8, rest: &str) -> Self {
+        let mut fields = rest.splitn(6, ' ');
+        let mut next = || fields.next().filter(|field| *field != "-");
+        let timestamp = next().and_then(parse_rfc3339);
+        let hostname = next().map(str::to_string);
+        let app_name = next().map(str::to_string);
+        let proc_id = next().map(str::to_string);
+        let msg_id = next().map(str::to_string);
+        let rest = fields.next().unwrap_or("");
+
+        let (structured_data, message) = match rest.strip_prefix('-') {
+            Some(message) => (None, message),
+            None => {
+                let end = structured_data_end(rest);
+                (Some(rest[..end].to_string()), &rest[end..])
+            }
+        };
+        let message = message.strip_prefix(' ').unwrap_or(message);
+        let message = message.strip_prefix('\u{feff}').unwrap_or(message);
+
+        Self {
+            timestamp,
+            hostname,
+            app_name,
+            proc_id,
+            msg_id,
+            structured_data,
+            ..Self::new(priority, message.to_string())
+        }
+    }
+
+    fn parse_rfc3164(priority: u8, rest: &str) -> Self {
+        let Some(timestamp) = rest.get(..15).and_then(parse_bsd_timestamp) else {
+            return Self::new(priority, rest.to_string());
+        };
+        let rest = rest[15..].trim_start();
+        let (hostname, content) = rest.split_once(' ').unwrap_or((rest, ""));
+
+        // The tag ends at the first character that isn't alphanumeric, e.g.
+        // `sshd[1234]: ` or `su: `
+        let tag_end = content
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "-_./".contains(c)))
+            .unwrap_or(content.len());
+        let (app_name, mut message) = content.split_at(tag_end);
+        let mut proc_id = None;
+        if let Some(after) = message.strip_prefix('[')
+            && let Some((pid, after)) = after.split_once(']')
+        {
+            proc_id = Some(pid.to_string());
+            message = after;
+        }
+        let message = match message.strip_prefix(':') {
+            Some(message) => message.trim_start(),
+            None if proc_id.is_none() => content,
+            None => message.trim_start(),
+        };
+        let app_name =
+            (!app_name.is_empty() && message.len() < content.len()).then(|| app_name.to_string());
+
+        Self {
+            timestamp: Some(timestamp),
+            hostname: Some(hostname.to_string()),
+            app_name,
+            proc_id,
+            ..Self::new(priority, message.to_string())
+        }
+    }
+}
+
+/// The priority in angle brackets at the start of a message and the rest
+fn parse_priority(line: &str) -> Option<(u8, &str)> {
+    let rest = line.strip_prefix('<')?;
+    let (priority, rest) = rest.split_once('>')?;
+    if priority.is_empty() || priority.len() > 3 {
+        return None;
+    }
+    let priority: u8 = priority.parse().ok()?;
+    (priority <= 191).then_some((priority, rest))
+}
+
+/// Byte offset after the structured data elements at the start of `text`,
+/// each in brackets, with `\]` and `\"` escaped inside values
+fn structured_data_end(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut pos = 0;
+    while bytes.get(pos) == Some(&b'[') {
+        let mut in_value = false;
+        pos += 1;
+        while let Some(&byte) = bytes.get(pos) {
+            pos += 1;
+            match byte {
+                b'\\' if in_value => pos += 1,
+                b'"' => in_value = !in_value,
+                b']' if !in_value => break,
+                _ => {}
+            }
+        }
+    }
+    pos.min(text.len())
+}
+
+/// Days since the unix epoch of a date of the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The year of a day since the unix epoch
+fn year_of_days(days: i64) -> i64 {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    year_of_era + era * 400 + i64::from(month_index >= 10)
+}
+
+fn time_of_day(text: &str) -> Option<i64> {
+    let mut parts = text.split(':');
+    let hours: i64 = parts.next()?.parse().ok()?;
+    let minutes: i64 = parts.next()?.parse().ok()?;
+    let seconds: i64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+    Some((hours * 3600 + minutes * 60 + seconds) * 1000)
+}
+
+/// Parse a timestamp like `2003-10-11T22:14:15.003Z` or with an offset like
+/// `+02:00` into milliseconds since the unix epoch
+fn parse_rfc3339(text: &str) -> Option<i64> {
+    let (date, time) = text.split_once(['T', 't'])?;
+    let mut date_parts = date.split('-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: i64 = date_parts.next()?.parse().ok()?;
+    let day: i64 = date_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let (time, offset) = match time.find(['Z', 'z', '+', '-']) {
+        Some(pos) => time.split_at(pos),
+        None => return None,
+    };
+    let offset_ms = match offset {
+        "Z" | "z" => 0,
+        _ => {
+            let sign = if offset.starts_with('-') { -1 } else { 1 };
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let hours: i64 = hours.parse().ok()?;
+            let minutes: i64 = minutes.parse().ok()?;
+            sign * (hours * 60 + minutes) * 60_000
+        }
+    };
+    let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+    let millis = match fraction.get(..fraction.len().min(3)) {
+        Some("") | None => 0,
+        Some(digits) => digits.parse::<i64>().ok()? * 10i64.pow(3 - digits.len() as u32),
+    };
+
+    Some(days_from_civil(year, month, day) * 86_400_000 + time_of_day(time)? + millis - offset_ms)
+}
+
+/// Parse a timestamp like `Oct 11 22:14:15` of the current year in UTC into
+/// milliseconds since the unix epoch
+fn parse_bsd_timestamp(text: &str) -> Option<i64> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let month = MONTHS
+        .iter()
+        .position(|month| text.get(..3) == Some(month))? as i64
+        + 1;
+    let day: i64 = text.get(4..6)?.trim_start().parse().ok()?;
+    if text.get(3..4) != Some(" ") || text.get(6..7) != Some(" ") || !(1..=31).contains(&day) {
+        return None;
+    }
+    let now_days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+        / 86_400;
+    let year = year_of_days(now_days);
+    Some(days_from_civil(year, month, day) * 86_400_000 + time_of_day(text.get(7..)?)?)
+}
+
+/// A source that receives syslog messages over UDP, one per datagram, and
+/// parses them into [`SyslogRecord`]s timestamped with the time of the
+/// message, or of arrival if it has none.
+///
+/// The source never ends.
+pub struct SyslogSource {
+    addr: String,
+    socket: Option<UdpSocket>,
+    buffer: Vec<u8>,
+}
+
+impl SyslogSource {
+    /// Create a source that listens on an address, e.g. `0.0.0.0:514`
+    pub fn bind<S: Into<String>>(addr: S) -> Self {
+        Self {
+            addr: addr.into(),
+            socket: None,
+            buffer: vec![0; MAX_DATAGRAM],
+        }
+    }
+
+    /// The address the source is bound to once initialized, e.g. to find
+    /// the port chosen for port 0
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.socket
+            .as_ref()
+            .and_then(|socket| socket.local_addr().ok())
+    }
+}
+
+#[async_trait]
+impl Source<SyslogRecord> for SyslogSource {
+    async fn init(&mut self) -> StreamResult<()> {
+        self.socket = Some(UdpSocket::bind(&self.addr).await?);
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<SyslogRecord>>> {
+        let Some(socket) = &self.socket else {
+            return Ok(None);
+        };
+        let (len, peer) = socket.recv_from(&mut self.buffer).await?;
+        let mut record = SyslogRecord::parse(&String::from_utf8_lossy(&self.buffer[..len]));
+        record.peer = Some(peer);
+        Ok(Some(match record.timestamp {
+            Some(timestamp) => Record::with_timestamp(record, timestamp),
+            None => Record::new(record),
+        }))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.socket = None;
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamResult};
+use futures::{Stream, StreamExt};
+use std::future::Future;
+use std::marker::PhantomData;
+use tokio::sync::mpsc;
+
+use crate::MergeOrder;
+use crate::reader::spawn_reader;
+
+/// A source that keeps the records of an inner source for which an async
+/// predicate (e.g. a lookup in a remote blocklist) returns true.
+///
+/// Up to `concurrency` predicates are evaluated at the same time.
+pub struct AsyncFilterSource<T, S, F> {
+    inner: Option<S>,
+    f: Option<F>,
+    concurrency: usize,
+    order: MergeOrder,
+    rx: Option<mpsc::Receiver<StreamResult<Record<T>>>>,
+    _phantom: PhantomData<T>,
+}
+
+impl<T, S, F, Fut> AsyncFilterSource<T, S, F>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + 'static,
+    F: Fn(&T) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = bool> + Send + 'static,
+{
+    pub fn new(inner: S, concurrency: usize, order: MergeOrder, f: F) -> Self {
+        Self {
+            inner: Some(inner),
+            f: Some(f),
+            concurrency: concurrency.max(1),
+            order,
+            rx: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    fn start(&mut self, inner: S, f: F) -> mpsc::Receiver<StreamResult<Record<T>>> {
+        let input = spawn_reader(inner, self.concurrency);
+        let (tx, rx) = mpsc::channel(self.concurrency);
+        let concurrency = self.concurrency;
+        let order = self.order;
+
+        tokio::spawn(async move {
+            let records = futures::stream::unfold(input, |mut input| async move {
+                input.recv().await.map(|item| (item, input))
+            });
+            let checks = records.map(move |item| {
+                let check = item.as_ref().ok().map(|record| f(&record.data));
+                async move {
+                    match (item, check) {
+                        (Ok(record), Some(check)) => check.await.then_some(Ok(record)),
+                        (item, _) => Some(item),
+                    }
+                }
+            });
+            match order {
+                MergeOrder::Ordered => forward(checks.buffered(concurrency), tx).await,
+                MergeOrder::Unordered => forward(checks.buffer_unordered(concurrency), tx).await,
+            }
+        });
+
+        rx
+    }
+}
+
+/// Send the kept records downstream until the consumer goes away
+async fn forward<T, St>(results: St, tx: mpsc::Sender<StreamResult<Record<T>>>)
+where
+    St: Stream<Item = Option<StreamResult<Record<T>>>>,
+{
+    let mut results = std::pin::pin!(results);
+    while let Some(result) = results.next().await {
+        if let Some(item) = result
+            && tx.send(item).await.is_err()
+        {
+            break;
+        }
+    }
+}
+
+#[async_trait]
+impl<T, S, F, Fut> Source<T> for AsyncFilterSource<T, S, F>
+where
+    T: Send + Sync + 'static,
+    S: Source<T> + Send + Sync + 'static,
+    F: Fn(&T) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = bool> + Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.init().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        if let (Some(inner), Some(f)) = (self.inner.take(), self.f.take()) {
+            self.rx = Some(self.start(inner, f));
+        }
+        let Some(rx) = self.rx.as_mut() else {
+            return Ok(None);
+        };
+        rx.recv().await.transpose()
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.rx = None;
+        match self.inner.take() {
+            Some(mut inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, timeout_at};
+
+use crate::reader::spawn_reader_task;
+
+/// A source that groups the records of an inner source into batches.
+///
+/// A batch is emitted once it holds `max_size` records or `max_wait` has passed
+/// since its first record arrived, whichever comes first. The inner source is
+/// read on a separate task so that a slow source cannot hold back a pending batch.
+/// The task is aborted when the batch source is closed or dropped.
+pub struct BatchSource<T, S> {
+    inner: Option<S>,
+    rx: Option<mpsc::Receiver<StreamResult<Record<T>>>>,
+    reader: Option<JoinHandle<()>>,
+    max_size: usize,
+    max_wait: Duration,
+    pending_error: Option<StreamError>,
+}
+
+impl<T, S> BatchSource<T, S>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + 'static,
+{
+    pub fn new(inner: S, max_size: usize, max_wait: Duration) -> Self {
+        Self {
+            inner: Some(inner),
+            rx: None,
+            reader: None,
+            max_size: max_size.max(1),
+            max_wait,
+            pending_error: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, S> Source<Vec<T>> for BatchSource<T, S>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.init().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<Vec<T>>>> {
+        if let Some(e) = self.pending_error.take() {
+            return Err(e);
+        }
+        if let Some(inner) = self.inner.take() {
+            let (rx, reader) = spawn_reader_task(inner, self.max_size);
+            self.rx = Some(rx);
+            self.reader = Some(reader);
+        }
+        let Some(rx) = self.rx.as_mut() else {
+            return Ok(None);
+        };
+
+        // Block until the first record of the batch arrives
+        let first = match rx.recv().await {
+            Some(Ok(record)) => record,
+            Some(Err(e)) => return Err(e),
+            None => return Ok(None),
+        };
+        let timestamp = first.timestamp;
+        let mut batch = vec![first.data];
+
+        let deadline = Instant::now() + self.max_wait;
+        while batch.len() < self.max_size {
+            match timeout_at(deadline, rx.recv()).await {
+                Ok(Some(Ok(record))) => batch.push(record.data),
+                Ok(Some(Err(e))) => {
+                    self.pending_error = Some(e);
+                    break;
+                }
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        Ok(Some(Record::with_timestamp(batch, timestamp)))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.rx = None;
+        if let Some(reader) = self.reader.take() {
+            reader.abort();
+        }
+        match self.inner.take() {
+            Some(mut inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T, S> Drop for BatchSource<T, S> {
+    fn drop(&mut self) {
+        if let Some(reader) = self.reader.take() {
+            reader.abort();
+        }
+    }
+}
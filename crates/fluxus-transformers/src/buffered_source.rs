@@ -0,0 +1,131 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::memory::{MemoryAccount, MemorySize};
+use fluxus_utils::models::{Record, StreamResult};
+use std::marker::PhantomData;
+use tokio::sync::mpsc;
+
+use crate::reader::spawn_reader;
+
+type SizeFn<T> = fn(&Record<T>) -> usize;
+
+/// A source that reads ahead of its consumer.
+///
+/// The inner source, including any IO and parsing it performs, runs on a
+/// dedicated task that keeps up to `capacity` records in a bounded queue, so
+/// reading overlaps with downstream processing instead of alternating with it.
+pub struct BufferedSource<T, S> {
+    inner: Option<S>,
+    rx: Option<mpsc::Receiver<StreamResult<Record<T>>>>,
+    capacity: usize,
+    memory: Option<(MemoryAccount, SizeFn<T>)>,
+}
+
+impl<T, S> BufferedSource<T, S>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + 'static,
+{
+    pub fn new(inner: S, capacity: usize) -> Self {
+        Self {
+            inner: Some(inner),
+            rx: None,
+            capacity: capacity.max(1),
+            memory: None,
+        }
+    }
+
+    /// Account for the estimated memory of the records in the queue
+    pub fn with_memory_account(mut self, account: MemoryAccount) -> Self
+    where
+        T: MemorySize,
+    {
+        self.memory = Some((account, Record::<T>::memory_size));
+        self
+    }
+}
+
+#[async_trait]
+impl<T, S> Source<T> for BufferedSource<T, S>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.init().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        if let Some(inner) = self.inner.take() {
+            self.rx = Some(match self.memory.clone() {
+                Some((account, size)) => spawn_reader(
+                    Accounted {
+                        inner,
+                        account,
+                        size,
+                        _phantom: PhantomData,
+                    },
+                    self.capacity,
+                ),
+                None => spawn_reader(inner, self.capacity),
+            });
+        }
+        let Some(rx) = self.rx.as_mut() else {
+            return Ok(None);
+        };
+        let record = rx.recv().await.transpose()?;
+        if let (Some(record), Some((account, size))) = (&record, &self.memory) {
+            account.release(size(record));
+        }
+        Ok(record)
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        if let (Some(rx), Some((account, size))) = (self.rx.as_mut(), &self.memory) {
+            while let Ok(Ok(record)) = rx.try_recv() {
+                account.release(size(&record));
+            }
+        }
+        // Dropping the receiver stops the reader task, which closes the inner source
+        self.rx = None;
+        match self.inner.take() {
+            Some(mut inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+}
+
+/// Accounts for the records read by the inner source until the consumer
+/// receives them
+struct Accounted<T, S> {
+    inner: S,
+    account: MemoryAccount,
+    size: SizeFn<T>,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+#[async_trait]
+impl<T, S> Source<T> for Accounted<T, S>
+where
+    T: Send + 'static,
+    S: Source<T> + Send,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        self.inner.init().await
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        let record = self.inner.next().await?;
+        if let Some(record) = &record {
+            self.account.add((self.size)(record));
+        }
+        Ok(record)
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.inner.close().await
+    }
+}
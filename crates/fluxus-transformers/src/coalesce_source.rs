@@ -0,0 +1,122 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamResult};
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+
+use crate::reader::spawn_reader;
+
+/// A source that keeps only the latest record per key of an inner source and
+/// emits the retained records once per flush interval, e.g. to cut the sink
+/// writes of windows that update on every record.
+///
+/// Retained records are emitted in the order their keys first appeared and
+/// are all flushed when the inner source ends.
+pub struct CoalesceSource<T, K, S, F> {
+    inner: Option<S>,
+    key_fn: F,
+    flush_interval: Duration,
+    rx: Option<mpsc::Receiver<StreamResult<Record<T>>>>,
+    ticker: Option<Interval>,
+    pending: HashMap<K, Record<T>>,
+    order: Vec<K>,
+    ready: VecDeque<Record<T>>,
+}
+
+impl<T, K, S, F> CoalesceSource<T, K, S, F>
+where
+    T: Send + 'static,
+    K: Eq + Hash + Clone,
+    S: Source<T> + Send + 'static,
+    F: Fn(&T) -> K,
+{
+    pub fn new(inner: S, key_fn: F, flush_interval: Duration) -> Self {
+        Self {
+            inner: Some(inner),
+            key_fn,
+            flush_interval,
+            rx: None,
+            ticker: None,
+            pending: HashMap::new(),
+            order: Vec::new(),
+            ready: VecDeque::new(),
+        }
+    }
+
+    fn retain(&mut self, record: Record<T>) {
+        let key = (self.key_fn)(&record.data);
+        if self.pending.insert(key.clone(), record).is_none() {
+            self.order.push(key);
+        }
+    }
+
+    fn flush(&mut self) {
+        for key in self.order.drain(..) {
+            if let Some(record) = self.pending.remove(&key) {
+                self.ready.push_back(record);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T, K, S, F> Source<T> for CoalesceSource<T, K, S, F>
+where
+    T: Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    S: Source<T> + Send + Sync + 'static,
+    F: Fn(&T) -> K + Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.init().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        if let Some(inner) = self.inner.take() {
+            self.rx = Some(spawn_reader(inner, 1));
+            let period = self.flush_interval.max(Duration::from_millis(1));
+            let mut ticker = tokio::time::interval_at(Instant::now() + period, period);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            self.ticker = Some(ticker);
+        }
+
+        loop {
+            if let Some(record) = self.ready.pop_front() {
+                return Ok(Some(record));
+            }
+            let (Some(rx), Some(ticker)) = (self.rx.as_mut(), self.ticker.as_mut()) else {
+                return Ok(None);
+            };
+
+            tokio::select! {
+                item = rx.recv() => match item {
+                    Some(Ok(record)) => self.retain(record),
+                    Some(Err(e)) => return Err(e),
+                    None => {
+                        self.rx = None;
+                        self.flush();
+                    }
+                },
+                _ = ticker.tick() => self.flush(),
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.rx = None;
+        self.ticker = None;
+        self.pending.clear();
+        self.order.clear();
+        self.ready.clear();
+        match self.inner.take() {
+            Some(mut inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::InnerSource;
+
+/// Where a record read from the shared source is delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    /// Deliver to a single output
+    To(usize),
+    /// Deliver a copy to every output
+    All,
+    /// Drop the record
+    Discard,
+}
+
+type RouteFn<T> = Box<dyn Fn(&T) -> Route + Send + Sync>;
+
+struct Shared<T> {
+    inner: Box<InnerSource<T>>,
+    route: RouteFn<T>,
+    queues: Vec<VecDeque<Record<T>>>,
+    initialized: bool,
+    exhausted: bool,
+    /// Outputs that have not been closed yet
+    active: Vec<bool>,
+}
+
+/// One output of a source whose records are dispatched to several consumers.
+///
+/// All outputs pull from the same inner source. Records routed to an output
+/// that is not being read are buffered until it is, so the outputs should be
+/// consumed concurrently to keep memory bounded.
+pub struct DispatchSource<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+    index: usize,
+    closed: bool,
+}
+
+impl<T> DispatchSource<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    /// Create `outputs` sources fed from `inner` according to `route`
+    pub fn new<S, F>(inner: S, outputs: usize, route: F) -> Vec<Self>
+    where
+        S: Source<T> + Send + Sync + 'static,
+        F: Fn(&T) -> Route + Send + Sync + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            inner: Box::new(inner),
+            route: Box::new(route),
+            queues: (0..outputs).map(|_| VecDeque::new()).collect(),
+            initialized: false,
+            exhausted: false,
+            active: vec![true; outputs],
+        }));
+        (0..outputs)
+            .map(|index| Self {
+                shared: shared.clone(),
+                index,
+                closed: false,
+            })
+            .collect()
+    }
+
+    /// Create `outputs` sources that each receive every record of `inner`
+    pub fn broadcast<S>(inner: S, outputs: usize) -> Vec<Self>
+    where
+        S: Source<T> + Send + Sync + 'static,
+    {
+        Self::new(inner, outputs, |_| Route::All)
+    }
+}
+
+#[async_trait]
+impl<T> Source<T> for DispatchSource<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        let mut shared = self.shared.lock().await;
+        if !shared.initialized {
+            shared.initialized = true;
+            shared.inner.init().await?;
+        }
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        let mut shared = self.shared.lock().await;
+        loop {
+            if let Some(record) = shared.queues[self.index].pop_front() {
+                return Ok(Some(record));
+            }
+            if shared.exhausted {
+                return Ok(None);
+            }
+
+            let record = match shared.inner.next().await {
+                Ok(Some(record)) => record,
+                Ok(None) | Err(StreamError::EOF) => {
+                    shared.exhausted = true;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let Shared {
+                route,
+                queues,
+                active,
+                ..
+            } = &mut *shared;
+            match route(&record.data) {
+                Route::To(index) => {
+                    if active.get(index).copied().unwrap_or(false) {
+                        queues[index].push_back(record);
+                    }
+                }
+                Route::All => {
+                    for (queue, _) in queues.iter_mut().zip(active.iter()).filter(|(_, a)| **a) {
+                        queue.push_back(record.clone());
+                    }
+                }
+                Route::Discard => {}
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+
+        let mut shared = self.shared.lock().await;
+        shared.queues[self.index].clear();
+        shared.active[self.index] = false;
+        // The inner source is closed together with the last output
+        if !shared.active.contains(&true) {
+            shared.inner.close().await?;
+        }
+        Ok(())
+    }
+}
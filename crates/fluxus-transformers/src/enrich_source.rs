@@ -0,0 +1,271 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamResult};
+use futures::{Stream, StreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::reader::spawn_reader;
+
+type Enriched<T, V> = mpsc::Receiver<StreamResult<Record<(T, Option<V>)>>>;
+
+/// What to do with a record whose lookup found nothing
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum MissPolicy<V> {
+    /// Drop the record
+    Drop,
+    /// Emit the record without a value
+    #[default]
+    PassThrough,
+    /// Emit the record with the given value
+    Default(V),
+}
+
+/// Caching and concurrency settings of an enrichment
+#[derive(Debug, Clone)]
+pub struct CachePolicy<V> {
+    capacity: usize,
+    ttl: Duration,
+    concurrency: usize,
+    on_miss: MissPolicy<V>,
+}
+
+impl<V> CachePolicy<V> {
+    /// Cache up to `capacity` lookups for `ttl` each, evicting the least
+    /// recently used entry when full
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            concurrency: 1,
+            on_miss: MissPolicy::PassThrough,
+        }
+    }
+
+    /// Do not cache lookups
+    pub fn no_cache() -> Self {
+        Self::new(0, Duration::ZERO)
+    }
+
+    /// Run up to `concurrency` lookups at the same time
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Set what to do with records whose lookup found nothing
+    pub fn on_miss(mut self, on_miss: MissPolicy<V>) -> Self {
+        self.on_miss = on_miss;
+        self
+    }
+}
+
+/// An LRU cache whose entries expire after a fixed time
+struct LookupCache<K, V> {
+    capacity: usize,
+    ttl: Duration,
+    entries: HashMap<K, (Option<V>, Instant, u64)>,
+    recency: BTreeMap<u64, K>,
+    tick: u64,
+}
+
+impl<K, V> LookupCache<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+        }
+    }
+
+    /// The cached result of a lookup, `None` if it is not cached or has expired
+    fn get(&mut self, key: &K) -> Option<Option<V>> {
+        let (value, inserted, used) = self.entries.get_mut(key)?;
+        if inserted.elapsed() >= self.ttl {
+            self.recency.remove(used);
+            self.entries.remove(key);
+            return None;
+        }
+        self.recency.remove(used);
+        self.tick += 1;
+        *used = self.tick;
+        self.recency.insert(self.tick, key.clone());
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: K, value: Option<V>) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some((_, _, used)) = self.entries.remove(&key) {
+            self.recency.remove(&used);
+        }
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+        self.tick += 1;
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(key, (value, Instant::now(), self.tick));
+    }
+}
+
+/// A source that pairs the records of an inner source with the result of an
+/// async lookup in an external store, e.g. Redis, Postgres or an HTTP service.
+///
+/// Lookups are cached by key and up to `concurrency` lookups run at the same
+/// time. The output keeps the order of the input.
+pub struct EnrichSource<T, V, S, KF, L> {
+    inner: Option<S>,
+    key_fn: Option<KF>,
+    lookup: Option<L>,
+    policy: CachePolicy<V>,
+    rx: Option<Enriched<T, V>>,
+}
+
+impl<T, K, V, S, KF, L, Fut> EnrichSource<T, V, S, KF, L>
+where
+    T: Send + 'static,
+    K: Eq + Hash + Clone + Send + 'static,
+    V: Clone + Send + 'static,
+    S: Source<T> + Send + 'static,
+    KF: Fn(&T) -> K + Send + Sync + 'static,
+    L: Fn(K) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = StreamResult<Option<V>>> + Send + 'static,
+{
+    pub fn new(inner: S, key_fn: KF, lookup: L, policy: CachePolicy<V>) -> Self {
+        Self {
+            inner: Some(inner),
+            key_fn: Some(key_fn),
+            lookup: Some(lookup),
+            policy,
+            rx: None,
+        }
+    }
+
+    fn start(&self, inner: S, key_fn: KF, lookup: L) -> Enriched<T, V> {
+        let concurrency = self.policy.concurrency;
+        let on_miss = self.policy.on_miss.clone();
+        let cache = Arc::new(Mutex::new(LookupCache::new(
+            self.policy.capacity,
+            self.policy.ttl,
+        )));
+        let input = spawn_reader(inner, concurrency);
+        let (tx, rx) = mpsc::channel(concurrency);
+
+        tokio::spawn(async move {
+            let records = futures::stream::unfold(input, |mut input| async move {
+                input.recv().await.map(|item| (item, input))
+            });
+            let lookups = records.map(move |item| {
+                let pending = item.map(|record| {
+                    let key = key_fn(&record.data);
+                    let cached = cache.lock().unwrap().get(&key);
+                    let fetch = match cached {
+                        Some(_) => None,
+                        None => Some(lookup(key.clone())),
+                    };
+                    (record, key, cached, fetch)
+                });
+                let cache = cache.clone();
+                let on_miss = on_miss.clone();
+                async move {
+                    let (record, key, cached, fetch) = match pending {
+                        Ok(pending) => pending,
+                        Err(e) => return Some(Err(e)),
+                    };
+                    let value = match (cached, fetch) {
+                        (Some(value), _) => value,
+                        (None, Some(fetch)) => match fetch.await {
+                            Ok(value) => {
+                                cache.lock().unwrap().insert(key, value.clone());
+                                value
+                            }
+                            Err(e) => return Some(Err(e)),
+                        },
+                        (None, None) => None,
+                    };
+                    let value = match (value, on_miss) {
+                        (Some(value), _) => Some(value),
+                        (None, MissPolicy::Drop) => return None,
+                        (None, MissPolicy::PassThrough) => None,
+                        (None, MissPolicy::Default(value)) => Some(value),
+                    };
+                    Some(Ok(Record::with_timestamp(
+                        (record.data, value),
+                        record.timestamp,
+                    )))
+                }
+            });
+            forward(lookups.buffered(concurrency), tx).await;
+        });
+
+        rx
+    }
+}
+
+/// Send the enriched records downstream until the consumer goes away
+async fn forward<T, St>(results: St, tx: mpsc::Sender<StreamResult<Record<T>>>)
+where
+    St: Stream<Item = Option<StreamResult<Record<T>>>>,
+{
+    let mut results = std::pin::pin!(results);
+    while let Some(result) = results.next().await {
+        if let Some(item) = result
+            && tx.send(item).await.is_err()
+        {
+            break;
+        }
+    }
+}
+
+#[async_trait]
+impl<T, K, V, S, KF, L, Fut> Source<(T, Option<V>)> for EnrichSource<T, V, S, KF, L>
+where
+    T: Send + Sync + 'static,
+    K: Eq + Hash + Clone + Send + 'static,
+    V: Clone + Send + Sync + 'static,
+    S: Source<T> + Send + Sync + 'static,
+    KF: Fn(&T) -> K + Send + Sync + 'static,
+    L: Fn(K) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = StreamResult<Option<V>>> + Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.init().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<(T, Option<V>)>>> {
+        if let (Some(inner), Some(key_fn), Some(lookup)) =
+            (self.inner.take(), self.key_fn.take(), self.lookup.take())
+        {
+            self.rx = Some(self.start(inner, key_fn, lookup));
+        }
+        let Some(rx) = self.rx.as_mut() else {
+            return Ok(None);
+        };
+        rx.recv().await.transpose()
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.rx = None;
+        match self.inner.take() {
+            Some(mut inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+}
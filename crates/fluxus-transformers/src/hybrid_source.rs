@@ -0,0 +1,149 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamResult};
+
+use crate::InnerSource;
+
+type ConnectLive<T> = Box<dyn FnOnce(Option<i64>) -> Box<InnerSource<T>> + Send + Sync>;
+
+/// A source that drains a bounded historical source, e.g. archive files,
+/// and then switches to a live source, e.g. a message queue, so that one
+/// pipeline backfills and keeps up.
+///
+/// The live source is created and initialized once the historical source
+/// ends, from the handover position: the latest timestamp read from the
+/// historical source. With [`skip_overlap`](Self::skip_overlap) live records
+/// at or before it are dropped. The watermark never goes back across the
+/// switch.
+pub struct HybridSource<T> {
+    historical: Option<Box<InnerSource<T>>>,
+    connect_live: Option<ConnectLive<T>>,
+    live: Option<Box<InnerSource<T>>>,
+    handover: Option<i64>,
+    skip_overlap: bool,
+    watermark: Option<i64>,
+}
+
+impl<T> HybridSource<T> {
+    /// Create a source reading `live` after `historical`
+    pub fn new<H, L>(historical: H, live: L) -> Self
+    where
+        H: Source<T> + Send + Sync + 'static,
+        L: Source<T> + Send + Sync + 'static,
+    {
+        Self::with_handover(historical, move |_| live)
+    }
+
+    /// Create a source reading `historical`, then the live source created by
+    /// `connect_live` from the handover position, e.g. to start consuming
+    /// a queue after the last archived event
+    pub fn with_handover<H, L, F>(historical: H, connect_live: F) -> Self
+    where
+        H: Source<T> + Send + Sync + 'static,
+        L: Source<T> + Send + Sync + 'static,
+        F: FnOnce(Option<i64>) -> L + Send + Sync + 'static,
+    {
+        Self {
+            historical: Some(Box::new(historical)),
+            connect_live: Some(Box::new(move |handover| {
+                Box::new(connect_live(handover)) as Box<InnerSource<T>>
+            })),
+            live: None,
+            handover: None,
+            skip_overlap: false,
+            watermark: None,
+        }
+    }
+
+    /// Drop live records with a timestamp at or before the handover
+    /// position, which the historical source already delivered
+    pub fn skip_overlap(mut self) -> Self {
+        self.skip_overlap = true;
+        self
+    }
+
+    /// Whether the source switched to the live source
+    pub fn is_live(&self) -> bool {
+        self.historical.is_none()
+    }
+
+    /// The latest timestamp read from the historical source
+    pub fn handover(&self) -> Option<i64> {
+        self.handover
+    }
+
+    fn advance_watermark(&mut self, watermark: Option<i64>) {
+        if let Some(watermark) = watermark {
+            self.watermark = Some(self.watermark.map_or(watermark, |w| w.max(watermark)));
+        }
+    }
+}
+
+#[async_trait]
+impl<T> Source<T> for HybridSource<T>
+where
+    T: Send + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match self.historical.as_mut() {
+            Some(historical) => historical.init().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        if let Some(historical) = self.historical.as_mut() {
+            if let Some(record) = historical.next().await? {
+                let watermark = historical.watermark();
+                self.handover = Some(
+                    self.handover
+                        .map_or(record.timestamp, |h| h.max(record.timestamp)),
+                );
+                self.advance_watermark(watermark);
+                return Ok(Some(record));
+            }
+
+            if let Some(mut historical) = self.historical.take() {
+                historical.close().await?;
+            }
+            // Without a watermark of its own, the historical source is
+            // complete up to the handover position
+            self.advance_watermark(self.handover);
+            if let Some(connect_live) = self.connect_live.take() {
+                let mut live = connect_live(self.handover);
+                live.init().await?;
+                tracing::info!("Switching to the live source at {:?}", self.handover);
+                self.live = Some(live);
+            }
+        }
+
+        loop {
+            let Some(live) = self.live.as_mut() else {
+                return Ok(None);
+            };
+            let Some(record) = live.next().await? else {
+                return Ok(None);
+            };
+            let watermark = live.watermark();
+            self.advance_watermark(watermark);
+            if self.skip_overlap && self.handover.is_some_and(|h| record.timestamp <= h) {
+                continue;
+            }
+            return Ok(Some(record));
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        if let Some(mut historical) = self.historical.take() {
+            historical.close().await?;
+        }
+        if let Some(mut live) = self.live.take() {
+            live.close().await?;
+        }
+        Ok(())
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        self.watermark
+    }
+}
@@ -1,9 +1,33 @@
+mod async_filter_source;
+mod batch_source;
+mod buffered_source;
+mod coalesce_source;
+mod dispatch_source;
+mod enrich_source;
+mod hybrid_source;
+mod merge_sorted;
 pub mod operator;
+mod parallel_map_source;
+mod reader;
+mod replay_source;
+mod timeout_source;
 mod transform_base;
 mod transform_source;
 mod transform_source_with_operator;
 
+pub use async_filter_source::AsyncFilterSource;
+pub use batch_source::BatchSource;
+pub use buffered_source::BufferedSource;
+pub use coalesce_source::CoalesceSource;
+pub use dispatch_source::{DispatchSource, Route};
+pub use enrich_source::{CachePolicy, EnrichSource, MissPolicy};
+pub use hybrid_source::HybridSource;
+pub use merge_sorted::MergeSorted;
 pub use operator::{Operator, OperatorBuilder};
+pub use parallel_map_source::{MergeOrder, ParallelMapSource};
+pub use reader::{spawn_reader, spawn_reader_task};
+pub use replay_source::{ReplaySource, ReplaySpeed};
+pub use timeout_source::{TimeoutEvent, TimeoutSource};
 pub use transform_base::TransformBase;
 pub use transform_source::TransformSource;
 pub use transform_source_with_operator::TransformSourceWithOperator;
@@ -0,0 +1,117 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::time::Duration;
+
+use crate::InnerSource;
+
+/// A source that merges timestamp-ordered sources into one stream ordered by
+/// timestamp, e.g. one archive file per hour.
+///
+/// Only the next record of every input is held at a time, in a k-way heap.
+/// Ties are emitted in the order the inputs were added.
+pub struct MergeSorted<T> {
+    sources: Vec<Box<InnerSource<T>>>,
+    heads: Vec<Option<Record<T>>>,
+    heap: BinaryHeap<Reverse<(i64, usize)>>,
+    started: bool,
+}
+
+impl<T> Default for MergeSorted<T> {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl<T> MergeSorted<T> {
+    /// Create a merge over the given sources
+    pub fn new(sources: Vec<Box<InnerSource<T>>>) -> Self {
+        Self {
+            sources,
+            heads: Vec::new(),
+            heap: BinaryHeap::new(),
+            started: false,
+        }
+    }
+
+    /// Add another source
+    pub fn with_source<S>(mut self, source: S) -> Self
+    where
+        S: Source<T> + Send + Sync + 'static,
+    {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.sources.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sources.is_empty()
+    }
+
+    /// Lower bound of the timestamps still to come, none once every input
+    /// is exhausted or before the first read
+    pub fn watermark(&self) -> Option<i64> {
+        self.heap.peek().map(|Reverse((ts, _))| *ts)
+    }
+
+    /// Read the next record of an input into the heap
+    async fn advance(&mut self, index: usize) -> StreamResult<()> {
+        loop {
+            match self.sources[index].next().await {
+                Ok(Some(record)) => {
+                    self.heap.push(Reverse((record.timestamp, index)));
+                    self.heads[index] = Some(record);
+                    return Ok(());
+                }
+                Ok(None) | Err(StreamError::EOF) => return Ok(()),
+                // The merge cannot move on without the next record of every input
+                Err(StreamError::Wait(ms)) => tokio::time::sleep(Duration::from_millis(ms)).await,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<T> Source<T> for MergeSorted<T>
+where
+    T: Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        for source in self.sources.iter_mut() {
+            source.init().await?;
+        }
+        Ok(())
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        if !self.started {
+            self.started = true;
+            self.heads = (0..self.sources.len()).map(|_| None).collect();
+            for index in 0..self.sources.len() {
+                self.advance(index).await?;
+            }
+        }
+
+        let Some(Reverse((_, index))) = self.heap.pop() else {
+            return Ok(None);
+        };
+        let record = self.heads[index].take();
+        self.advance(index).await?;
+        Ok(record)
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.heap.clear();
+        self.heads.clear();
+        for source in self.sources.iter_mut() {
+            source.close().await?;
+        }
+        Ok(())
+    }
+}
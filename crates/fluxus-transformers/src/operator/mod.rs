@@ -16,6 +16,11 @@ pub use window_reduce::WindowReduceOperator;
 /// Operator trait defines the interface for stream processing operators
 #[async_trait]
 pub trait Operator<In, Out>: Send {
+    /// Name of the operator used in diagnostics
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
     /// Initialize the operator
     async fn init(&mut self) -> StreamResult<()> {
         Ok(())
@@ -29,6 +34,19 @@ pub trait Operator<In, Out>: Send {
         Ok(Vec::new())
     }
 
+    /// Called when the event-time watermark advances to `watermark`, so that
+    /// the operator can emit the windows that ended and drop the state of
+    /// windows that can no longer change
+    async fn on_watermark(&mut self, _watermark: i64) -> StreamResult<Vec<Record<Out>>> {
+        Ok(Vec::new())
+    }
+
+    /// Emit the records the operator still holds, such as open windows, once
+    /// its input is exhausted
+    async fn on_end_of_input(&mut self) -> StreamResult<Vec<Record<Out>>> {
+        Ok(Vec::new())
+    }
+
     /// Close the operator and release resources
     async fn close(&mut self) -> StreamResult<()> {
         Ok(())
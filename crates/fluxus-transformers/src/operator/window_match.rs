@@ -30,7 +30,7 @@ where
     }
 
     fn get_affected_windows(&self, timestamp: i64) -> Vec<i64> {
-        self.window.window_type.get_affected_windows(timestamp)
+        self.window.get_affected_windows(timestamp)
     }
 
     fn process_window(&self, records: &[Record<T>]) -> Option<Record<bool>> {
@@ -91,7 +91,7 @@ where
     }
 
     fn get_affected_windows(&self, timestamp: i64) -> Vec<i64> {
-        self.window.window_type.get_affected_windows(timestamp)
+        self.window.get_affected_windows(timestamp)
     }
 
     fn process_window(&self, records: &[Record<T>]) -> Option<Record<bool>> {
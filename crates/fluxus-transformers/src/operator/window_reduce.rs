@@ -32,7 +32,7 @@ where
     }
 
     fn get_affected_windows(&self, timestamp: i64) -> Vec<i64> {
-        self.window.window_type.get_affected_windows(timestamp)
+        self.window.get_affected_windows(timestamp)
     }
 
     fn process_window(&self, records: &[Record<T>]) -> Option<Record<T>> {
@@ -97,7 +97,8 @@ where
                     key + gap.as_millis() as i64 + self.window.allow_lateness.as_millis() as i64
                         <= now
                 }
-                WindowType::Global => {
+                // Count-or-time windows are closed by the DataStream API
+                WindowType::Global | WindowType::CountOrTime(_, _) => {
                     // Global window doesn't expire based on time, so it's never considered expired here
                     false
                 }
@@ -106,10 +107,10 @@ where
             .collect();
 
         for key in expired_keys {
-            if let Some(records) = self.buffer.remove(&key) {
-                if let Some(result) = self.process_window(&records) {
-                    results.push(result);
-                }
+            if let Some(records) = self.buffer.remove(&key)
+                && let Some(result) = self.process_window(&records)
+            {
+                results.push(result);
             }
         }
 
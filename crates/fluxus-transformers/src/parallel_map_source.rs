@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamResult};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::{Mutex, mpsc};
+
+use crate::reader::spawn_reader;
+
+/// Order in which the results of parallel workers are merged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergeOrder {
+    /// Emit results in the order of the input records
+    #[default]
+    Ordered,
+    /// Emit results as soon as any worker finishes
+    Unordered,
+}
+
+type Sequenced<T> = (u64, StreamResult<Record<T>>);
+
+/// A source that applies a function to the records of an inner source on
+/// several worker tasks, e.g. to parse raw lines in parallel.
+pub struct ParallelMapSource<T, R, S, F> {
+    inner: Option<S>,
+    f: Arc<F>,
+    workers: usize,
+    order: MergeOrder,
+    rx: Option<mpsc::Receiver<Sequenced<R>>>,
+    pending: BTreeMap<u64, StreamResult<Record<R>>>,
+    next_seq: u64,
+    _phantom: std::marker::PhantomData<T>,
+}
+
+impl<T, R, S, F> ParallelMapSource<T, R, S, F>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    S: Source<T> + Send + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    pub fn new(inner: S, workers: usize, order: MergeOrder, f: F) -> Self {
+        Self {
+            inner: Some(inner),
+            f: Arc::new(f),
+            workers: workers.max(1),
+            order,
+            rx: None,
+            pending: BTreeMap::new(),
+            next_seq: 0,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    fn start(&mut self, inner: S) -> mpsc::Receiver<Sequenced<R>> {
+        let mut input = spawn_reader(inner, self.workers * 2);
+        let (work_tx, work_rx) = mpsc::channel::<(u64, Record<T>)>(self.workers * 2);
+        let (out_tx, out_rx) = mpsc::channel(self.workers * 2);
+        let work_rx = Arc::new(Mutex::new(work_rx));
+
+        for _ in 0..self.workers {
+            let work_rx = work_rx.clone();
+            let out_tx = out_tx.clone();
+            let f = self.f.clone();
+            tokio::spawn(async move {
+                loop {
+                    let Some((seq, record)) = work_rx.lock().await.recv().await else {
+                        break;
+                    };
+                    let data = f(record.data);
+                    let record = Record::with_timestamp(data, record.timestamp);
+                    if out_tx.send((seq, Ok(record))).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        // Number the input records and hand them out to the workers
+        tokio::spawn(async move {
+            let mut seq = 0;
+            while let Some(item) = input.recv().await {
+                let sent = match item {
+                    Ok(record) => work_tx.send((seq, record)).await.is_ok(),
+                    Err(e) => {
+                        let _ = out_tx.send((seq, Err(e))).await;
+                        false
+                    }
+                };
+                if !sent {
+                    break;
+                }
+                seq += 1;
+            }
+        });
+
+        out_rx
+    }
+}
+
+#[async_trait]
+impl<T, R, S, F> Source<R> for ParallelMapSource<T, R, S, F>
+where
+    T: Send + 'static,
+    R: Send + Sync + 'static,
+    S: Source<T> + Send + Sync + 'static,
+    F: Fn(T) -> R + Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.init().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<R>>> {
+        if let Some(inner) = self.inner.take() {
+            self.rx = Some(self.start(inner));
+        }
+        let Some(rx) = self.rx.as_mut() else {
+            return Ok(None);
+        };
+
+        if self.order == MergeOrder::Unordered {
+            return match rx.recv().await {
+                Some((_, result)) => result.map(Some),
+                None => Ok(None),
+            };
+        }
+
+        loop {
+            if let Some(result) = self.pending.remove(&self.next_seq) {
+                self.next_seq += 1;
+                return result.map(Some);
+            }
+            match rx.recv().await {
+                Some((seq, result)) => {
+                    self.pending.insert(seq, result);
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.rx = None;
+        self.pending.clear();
+        match self.inner.take() {
+            Some(mut inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamError, StreamResult};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Drive a source on a separate task, forwarding its records over a bounded channel.
+///
+/// The channel closes when the source is exhausted. A fatal source error is
+/// forwarded as the last item, `Wait` requests are honoured on the reader task.
+pub fn spawn_reader<T, S>(inner: S, capacity: usize) -> mpsc::Receiver<StreamResult<Record<T>>>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + 'static,
+{
+    spawn_reader_task(inner, capacity).0
+}
+
+/// Same as [`spawn_reader`], also returning the handle of the reader task so
+/// that it can be aborted while the source is still being read
+pub fn spawn_reader_task<T, S>(
+    mut inner: S,
+    capacity: usize,
+) -> (mpsc::Receiver<StreamResult<Record<T>>>, JoinHandle<()>)
+where
+    T: Send + 'static,
+    S: Source<T> + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel(capacity.max(1));
+    let handle = tokio::spawn(async move {
+        loop {
+            match inner.next().await {
+                Ok(Some(record)) => {
+                    if tx.send(Ok(record)).await.is_err() {
+                        break;
+                    }
+                }
+                Ok(None) | Err(StreamError::EOF) => break,
+                Err(StreamError::Wait(ms)) => {
+                    tokio::time::sleep(Duration::from_millis(ms)).await;
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    break;
+                }
+            }
+        }
+        if let Err(e) = inner.close().await {
+            tracing::error!("Error closing source: {:?}", e);
+        }
+    });
+    (rx, handle)
+}
@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamResult};
+use std::marker::PhantomData;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Pace of a [`ReplaySource`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// Replay at the pace the records were produced
+    Realtime,
+    /// Replay this many times faster than the records were produced
+    Factor(f64),
+    /// Replay without waiting between records
+    Unthrottled,
+}
+
+impl ReplaySpeed {
+    fn factor(&self) -> Option<f64> {
+        match self {
+            Self::Realtime => Some(1.0),
+            Self::Factor(factor) if *factor > 0.0 && factor.is_finite() => Some(*factor),
+            _ => None,
+        }
+    }
+}
+
+/// A source that replays the records of a bounded source at the pace of
+/// their timestamps, e.g. a day of recorded events in an hour, so that a
+/// historical dataset flows through time windows as it did live.
+///
+/// The first record is emitted at once and every later record after the
+/// time between its timestamp and the latest timestamp before it, divided
+/// by the speed. Records whose timestamp is before that latest timestamp
+/// are emitted without waiting. Timestamps are kept as they are.
+pub struct ReplaySource<T, S> {
+    inner: S,
+    speed: ReplaySpeed,
+    max_gap: Option<Duration>,
+    /// Latest timestamp emitted and when it was due
+    last: Option<(i64, Instant)>,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T, S> ReplaySource<T, S> {
+    pub fn new(inner: S, speed: ReplaySpeed) -> Self {
+        Self {
+            inner,
+            speed,
+            max_gap: None,
+            last: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Wait at most `max_gap` between records, e.g. to skip the nights of
+    /// a dataset of office hours
+    pub fn with_max_gap(mut self, max_gap: Duration) -> Self {
+        self.max_gap = Some(max_gap);
+        self
+    }
+}
+
+#[async_trait]
+impl<T, S> Source<T> for ReplaySource<T, S>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + Sync,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        self.last = None;
+        self.inner.init().await
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
+        let Some(record) = self.inner.next().await? else {
+            return Ok(None);
+        };
+        let Some(factor) = self.speed.factor() else {
+            return Ok(Some(record));
+        };
+
+        self.last = Some(match self.last {
+            None => (record.timestamp, Instant::now()),
+            Some((last_timestamp, last_due)) if record.timestamp > last_timestamp => {
+                let gap_ms = (record.timestamp - last_timestamp) as f64 / factor;
+                let mut gap = Duration::from_secs_f64(gap_ms / 1000.0);
+                if let Some(max_gap) = self.max_gap {
+                    gap = gap.min(max_gap);
+                }
+                let due = last_due + gap;
+                tokio::time::sleep_until(due).await;
+                (record.timestamp, due)
+            }
+            Some(last) => last,
+        });
+        Ok(Some(record))
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.inner.close().await
+    }
+
+    fn watermark(&self) -> Option<i64> {
+        self.inner.watermark()
+    }
+}
@@ -0,0 +1,92 @@
+use async_trait::async_trait;
+use fluxus_sources::Source;
+use fluxus_utils::models::{Record, StreamResult};
+use fluxus_utils::time::current_time;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+use crate::reader::spawn_reader;
+
+/// Output of a [`TimeoutSource`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimeoutEvent<T> {
+    /// A record that arrived in time
+    Record(T),
+    /// No record arrived for `idle`, emitted once per elapsed timeout period
+    Timeout { idle: Duration },
+}
+
+/// A source that emits a timeout marker whenever the inner source stays silent
+/// for longer than the configured duration
+pub struct TimeoutSource<T, S> {
+    inner: Option<S>,
+    rx: Option<mpsc::Receiver<StreamResult<Record<T>>>>,
+    timeout: Duration,
+    idle: Duration,
+}
+
+impl<T, S> TimeoutSource<T, S>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + 'static,
+{
+    pub fn new(inner: S, timeout: Duration) -> Self {
+        Self {
+            inner: Some(inner),
+            rx: None,
+            timeout,
+            idle: Duration::ZERO,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, S> Source<TimeoutEvent<T>> for TimeoutSource<T, S>
+where
+    T: Send + 'static,
+    S: Source<T> + Send + Sync + 'static,
+{
+    async fn init(&mut self) -> StreamResult<()> {
+        match self.inner.as_mut() {
+            Some(inner) => inner.init().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn next(&mut self) -> StreamResult<Option<Record<TimeoutEvent<T>>>> {
+        if let Some(inner) = self.inner.take() {
+            self.rx = Some(spawn_reader(inner, 1));
+        }
+        let Some(rx) = self.rx.as_mut() else {
+            return Ok(None);
+        };
+
+        match timeout(self.timeout, rx.recv()).await {
+            Ok(Some(Ok(record))) => {
+                self.idle = Duration::ZERO;
+                Ok(Some(Record::with_timestamp(
+                    TimeoutEvent::Record(record.data),
+                    record.timestamp,
+                )))
+            }
+            Ok(Some(Err(e))) => Err(e),
+            Ok(None) => Ok(None),
+            Err(_) => {
+                self.idle += self.timeout;
+                Ok(Some(Record::with_timestamp(
+                    TimeoutEvent::Timeout { idle: self.idle },
+                    current_time() as i64,
+                )))
+            }
+        }
+    }
+
+    async fn close(&mut self) -> StreamResult<()> {
+        self.rx = None;
+        match self.inner.take() {
+            Some(mut inner) => inner.close().await,
+            None => Ok(()),
+        }
+    }
+}
@@ -1,41 +1,34 @@
 use fluxus_utils::models::{Record, StreamResult};
-use std::sync::Arc;
 
 use crate::{InnerOperator, InnerSource};
 
-#[derive(Clone)]
-pub struct TransformBase<T: Clone> {
-    inner: Arc<InnerSource<T>>,
-    operators: Vec<Arc<InnerOperator<T, T>>>,
+pub struct TransformBase<T> {
+    inner: Box<InnerSource<T>>,
+    operators: Vec<Box<InnerOperator<T, T>>>,
+    watermark: Option<i64>,
 }
 
-impl<T: Clone + Send + Sync + 'static> TransformBase<T> {
-    pub fn new(inner: Arc<InnerSource<T>>) -> Self {
+impl<T: Send + Sync + 'static> TransformBase<T> {
+    pub fn new(inner: Box<InnerSource<T>>) -> Self {
         Self {
             inner,
             operators: Vec::new(),
+            watermark: None,
         }
     }
 
-    pub fn set_operators(&mut self, operators: Vec<Arc<InnerOperator<T, T>>>) {
+    pub fn set_operators(&mut self, operators: Vec<Box<InnerOperator<T, T>>>) {
         self.operators = operators;
     }
 
     pub async fn process_operators(&mut self, record: Record<T>) -> StreamResult<Vec<Record<T>>> {
         let mut records = vec![record];
 
-        for op in &self.operators {
+        for op in &mut self.operators {
             let mut processed = Vec::new();
 
             for rec in records {
-                let operator = Arc::clone(op);
-                let results = unsafe {
-                    // Safe because we have exclusive access through &mut self
-                    let op = &mut *(Arc::as_ptr(&operator) as *mut InnerOperator<T, T>);
-                    op.process(rec).await?
-                };
-
-                processed.extend(results);
+                processed.extend(op.process(rec).await?);
             }
 
             if processed.is_empty() {
@@ -48,21 +41,80 @@ impl<T: Clone + Send + Sync + 'static> TransformBase<T> {
         Ok(records)
     }
 
+    /// Collect what the operators still hold once the input is exhausted,
+    /// passing the records each operator flushes through the operators after it
+    pub async fn flush_operators(&mut self) -> StreamResult<Vec<Record<T>>> {
+        let mut records = Vec::new();
+
+        for op in &mut self.operators {
+            let mut flushed = Vec::new();
+            for rec in records {
+                flushed.extend(op.process(rec).await?);
+            }
+            flushed.extend(op.on_end_of_input().await?);
+            records = flushed;
+        }
+
+        Ok(records)
+    }
+
+    /// The watermark of the inner source if it advanced since the last call
+    pub fn advance_watermark(&mut self) -> Option<i64> {
+        let watermark = self.inner.watermark()?;
+        if self.watermark.is_some_and(|current| current >= watermark) {
+            return None;
+        }
+        self.watermark = Some(watermark);
+        Some(watermark)
+    }
+
+    /// The latest watermark passed through the operators
+    pub fn watermark(&self) -> Option<i64> {
+        self.watermark
+    }
+
+    /// Pass a watermark through the operators, passing the records each
+    /// operator emits through the operators after it
+    pub async fn watermark_operators(&mut self, watermark: i64) -> StreamResult<Vec<Record<T>>> {
+        let mut records = Vec::new();
+
+        for op in &mut self.operators {
+            let mut emitted = Vec::new();
+            for rec in records {
+                emitted.extend(op.process(rec).await?);
+            }
+            emitted.extend(op.on_watermark(watermark).await?);
+            records = emitted;
+        }
+
+        Ok(records)
+    }
+
     pub async fn get_next_record(&mut self) -> StreamResult<Option<Record<T>>> {
-        let inner = Arc::clone(&self.inner);
-        unsafe {
-            // Safe because we have exclusive access through &mut self
-            let source = &mut *(Arc::as_ptr(&inner) as *mut InnerSource<T>);
-            source.next().await
+        self.inner.next().await
+    }
+
+    pub async fn init_inner(&mut self) -> StreamResult<()> {
+        self.inner.init().await
+    }
+
+    /// Initialize the chained operators, in pipeline order
+    pub async fn init_operators(&mut self) -> StreamResult<()> {
+        for op in &mut self.operators {
+            op.init().await?;
         }
+        Ok(())
     }
 
-    pub async fn close_inner(&mut self) -> StreamResult<()> {
-        let inner = Arc::clone(&self.inner);
-        unsafe {
-            // Safe because we have exclusive access through &mut self
-            let source = &mut *(Arc::as_ptr(&inner) as *mut InnerSource<T>);
-            source.close().await
+    /// Close the chained operators, downstream first
+    pub async fn close_operators(&mut self) -> StreamResult<()> {
+        for op in self.operators.iter_mut().rev() {
+            op.close().await?;
         }
+        Ok(())
+    }
+
+    pub async fn close_inner(&mut self) -> StreamResult<()> {
+        self.inner.close().await
     }
 }
@@ -1,61 +1,76 @@
 use async_trait::async_trait;
 use fluxus_sources::Source;
 use fluxus_utils::models::{Record, StreamResult};
-use std::sync::Arc;
 
 use crate::{InnerOperator, InnerSource, TransformBase};
 
-#[derive(Clone)]
-pub struct TransformSource<T: Clone> {
+pub struct TransformSource<T> {
     base: TransformBase<T>,
     buffer: Vec<Record<T>>,
+    finished: bool,
+    watermark: Option<i64>,
 }
 
-impl<T: Clone + Send + Sync + 'static> TransformSource<T> {
-    pub fn new(inner: Arc<InnerSource<T>>) -> Self {
+impl<T: Send + Sync + 'static> TransformSource<T> {
+    pub fn new(inner: Box<InnerSource<T>>) -> Self {
         Self {
             base: TransformBase::new(inner),
             buffer: Vec::new(),
+            finished: false,
+            watermark: None,
         }
     }
 
-    pub fn set_operators(&mut self, operators: Vec<Arc<InnerOperator<T, T>>>) {
+    pub fn set_operators(&mut self, operators: Vec<Box<InnerOperator<T, T>>>) {
         self.base.set_operators(operators);
     }
 }
 
 #[async_trait]
-impl<T: Clone + Send + Sync + 'static> Source<T> for TransformSource<T> {
+impl<T: Send + Sync + 'static> Source<T> for TransformSource<T> {
     async fn init(&mut self) -> StreamResult<()> {
-        Ok(())
+        self.base.init_inner().await?;
+        self.base.init_operators().await
     }
 
     async fn next(&mut self) -> StreamResult<Option<Record<T>>> {
-        // If we have records in the buffer, return one
-        if !self.buffer.is_empty() {
-            return Ok(self.buffer.pop());
-        }
-
-        let record = self.base.get_next_record().await?;
-
-        // If there's no next record, return None
-        let Some(record) = record else {
-            return Ok(None);
-        };
-
-        let records = self.base.process_operators(record).await?;
+        // Refill the buffer until the operators produce at least one record
+        while self.buffer.is_empty() {
+            if self.finished {
+                return Ok(None);
+            }
 
-        if records.is_empty() {
-            return self.next().await;
+            // Once the input is exhausted, emit what the operators still hold
+            self.buffer = match self.base.get_next_record().await? {
+                Some(record) => {
+                    let mut records = self.base.process_operators(record).await?;
+                    if let Some(watermark) = self.base.advance_watermark() {
+                        records.extend(self.base.watermark_operators(watermark).await?);
+                    }
+                    records
+                }
+                None => {
+                    self.finished = true;
+                    self.base.flush_operators().await?
+                }
+            };
+            self.buffer.reverse();
         }
 
-        self.buffer = records;
-        self.buffer.reverse();
-
-        Ok(self.buffer.pop())
+        let record = self.buffer.pop();
+        // The watermark holds back until the records before it are read
+        if self.buffer.is_empty() {
+            self.watermark = self.base.watermark();
+        }
+        Ok(record)
     }
 
     async fn close(&mut self) -> StreamResult<()> {
+        self.base.close_operators().await?;
         self.base.close_inner().await
     }
+
+    fn watermark(&self) -> Option<i64> {
+        self.watermark
+    }
 }
@@ -1,31 +1,27 @@
 use async_trait::async_trait;
 use fluxus_sources::Source;
 use fluxus_utils::models::{Record, StreamResult};
-use std::sync::Arc;
 
 use crate::{InnerOperator, InnerSource, Operator, TransformBase};
 
 /// A source that applies a single operator transformation
-#[derive(Clone)]
-pub struct TransformSourceWithOperator<T, R>
-where
-    T: Clone,
-    R: Clone,
-{
+pub struct TransformSourceWithOperator<T, R> {
     base: TransformBase<T>,
-    operator: Arc<InnerOperator<T, R>>,
+    operator: Box<InnerOperator<T, R>>,
     buffer: Vec<Record<R>>,
+    finished: bool,
+    watermark: Option<i64>,
 }
 
 impl<T, R> TransformSourceWithOperator<T, R>
 where
-    T: Clone + Send + Sync + 'static,
-    R: Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
 {
     pub fn new<O>(
-        inner: Arc<InnerSource<T>>,
+        inner: Box<InnerSource<T>>,
         operator: O,
-        operators: Vec<Arc<InnerOperator<T, T>>>,
+        operators: Vec<Box<InnerOperator<T, T>>>,
     ) -> Self
     where
         O: Operator<T, R> + Send + Sync + 'static,
@@ -34,8 +30,10 @@ where
         base.set_operators(operators);
         Self {
             base,
-            operator: Arc::new(operator),
+            operator: Box::new(operator),
             buffer: Vec::new(),
+            finished: false,
+            watermark: None,
         }
     }
 }
@@ -43,44 +41,67 @@ where
 #[async_trait]
 impl<T, R> Source<R> for TransformSourceWithOperator<T, R>
 where
-    T: Clone + Send + Sync + 'static,
-    R: Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    R: Send + Sync + 'static,
 {
     async fn init(&mut self) -> StreamResult<()> {
-        Ok(())
+        self.base.init_inner().await?;
+        self.base.init_operators().await?;
+        self.operator.init().await
     }
 
     async fn next(&mut self) -> StreamResult<Option<Record<R>>> {
-        if !self.buffer.is_empty() {
-            return Ok(self.buffer.pop());
-        }
-        let record = self.base.get_next_record().await?;
-
-        // If there's no next record, return None
-        let Some(record) = record else {
-            return Ok(None);
-        };
+        while self.buffer.is_empty() {
+            if self.finished {
+                return Ok(None);
+            }
 
-        let records = self.base.process_operators(record).await?;
+            // Once the input is exhausted, emit what the operators still hold
+            let mut watermark = None;
+            let records = match self.base.get_next_record().await? {
+                Some(record) => {
+                    let mut records = self.base.process_operators(record).await?;
+                    watermark = self.base.advance_watermark();
+                    if let Some(watermark) = watermark {
+                        records.extend(self.base.watermark_operators(watermark).await?);
+                    }
+                    records
+                }
+                None => {
+                    self.finished = true;
+                    self.base.flush_operators().await?
+                }
+            };
 
-        if records.is_empty() {
-            return self.next().await;
+            let mut final_results = Vec::new();
+            for rec in records {
+                final_results.extend(self.operator.process(rec).await?);
+            }
+            if let Some(watermark) = watermark {
+                final_results.extend(self.operator.on_watermark(watermark).await?);
+            }
+            if self.finished {
+                final_results.extend(self.operator.on_end_of_input().await?);
+            }
+            self.buffer = final_results;
+            self.buffer.reverse();
         }
 
-        let mut final_results = Vec::new();
-        for rec in records {
-            final_results.extend(unsafe {
-                let op = &mut *(Arc::as_ptr(&self.operator) as *mut InnerOperator<T, R>);
-                op.process(rec).await?
-            });
+        let record = self.buffer.pop();
+        // The watermark holds back until the records before it are read
+        if self.buffer.is_empty() {
+            self.watermark = self.base.watermark();
         }
-        self.buffer = final_results;
-        self.buffer.reverse();
-
-        Ok(self.buffer.pop())
+        Ok(record)
     }
 
     async fn close(&mut self) -> StreamResult<()> {
+        self.operator.close().await?;
+        self.base.close_operators().await?;
         self.base.close_inner().await
     }
+
+    fn watermark(&self) -> Option<i64> {
+        self.watermark
+    }
 }
@@ -0,0 +1,372 @@
+//! An AMQP 0-9-1 client shared by the AMQP source and sink, built on `lapin`
+
+use futures::StreamExt;
+use lapin::options::{
+    BasicAckOptions, BasicConsumeOptions, BasicNackOptions, BasicPublishOptions, BasicQosOptions,
+    ConfirmSelectOptions, QueueDeclareOptions,
+};
+use lapin::tcp::{OwnedIdentity, OwnedTLSConfig};
+use lapin::types::{AMQPValue, FieldTable};
+use lapin::uri::{AMQPAuthority, AMQPScheme, AMQPUri, AMQPUserInfo};
+use lapin::{
+    BasicProperties, Channel, Connection, ConnectionProperties, Consumer, PublisherConfirm,
+};
+use std::time::Duration;
+
+use crate::models::{StreamError, StreamResult};
+use crate::security::{AuthConfig, TlsConfig};
+
+/// Connection options of an AMQP client
+#[derive(Debug, Clone)]
+pub struct AmqpOptions {
+    pub host: String,
+    pub port: u16,
+    pub virtual_host: String,
+    /// Username and password, as [`AuthConfig::Basic`]. Without credentials
+    /// the default `guest` user of RabbitMQ is used.
+    pub auth: AuthConfig,
+    /// Connect over TLS (`amqps`) with the given options instead of plain TCP
+    pub tls: Option<TlsConfig>,
+    /// Time to wait before connecting again after the connection was lost
+    pub reconnect_delay: Duration,
+}
+
+impl AmqpOptions {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            virtual_host: "/".to_string(),
+            auth: AuthConfig::None,
+            tls: None,
+            reconnect_delay: Duration::from_secs(1),
+        }
+    }
+
+    pub fn with_virtual_host(mut self, virtual_host: impl Into<String>) -> Self {
+        self.virtual_host = virtual_host.into();
+        self
+    }
+
+    /// Authenticate with a username and password
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.auth = AuthConfig::Basic {
+            username: username.into(),
+            password: password.into(),
+        };
+        self
+    }
+
+    /// Connect over TLS, usually on port 5671, verifying the broker against
+    /// the root certificates of the platform and those of the options
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub fn with_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+}
+
+/// The TLS settings of a connection as `lapin` takes them
+async fn tls_config(tls: &TlsConfig) -> StreamResult<OwnedTLSConfig> {
+    if tls.accept_invalid_certs {
+        return Err(StreamError::Config(
+            "AMQP does not support accepting invalid certificates".to_string(),
+        ));
+    }
+    let pem = tls.read_pem().await?;
+    let cert_chain = pem
+        .ca_cert
+        .map(String::from_utf8)
+        .transpose()
+        .map_err(|e| StreamError::Config(format!("invalid CA certificate: {}", e)))?;
+    Ok(OwnedTLSConfig {
+        identity: pem
+            .identity
+            .map(|(pem, key)| OwnedIdentity::PKCS8 { pem, key }),
+        cert_chain,
+    })
+}
+
+/// A queue to declare, created by the broker unless it already exists with
+/// the same settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AmqpQueue {
+    pub name: String,
+    /// Whether the queue survives a restart of the broker
+    pub durable: bool,
+    /// Whether the queue is deleted once its last consumer is gone
+    pub auto_delete: bool,
+    /// Exchange that rejected messages are routed to
+    pub dead_letter_exchange: Option<String>,
+    /// Routing key of dead-lettered messages, instead of their own
+    pub dead_letter_routing_key: Option<String>,
+}
+
+impl AmqpQueue {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            durable: true,
+            auto_delete: false,
+            dead_letter_exchange: None,
+            dead_letter_routing_key: None,
+        }
+    }
+
+    pub fn with_durable(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    pub fn with_auto_delete(mut self, auto_delete: bool) -> Self {
+        self.auto_delete = auto_delete;
+        self
+    }
+
+    /// Route rejected messages to an exchange, optionally with a different
+    /// routing key
+    pub fn with_dead_letter(
+        mut self,
+        exchange: impl Into<String>,
+        routing_key: Option<String>,
+    ) -> Self {
+        self.dead_letter_exchange = Some(exchange.into());
+        self.dead_letter_routing_key = routing_key;
+        self
+    }
+}
+
+/// A message delivered to a consumer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delivery {
+    /// Identifies the delivery when acknowledging it
+    pub delivery_tag: u64,
+    /// Whether the message was delivered before without being acknowledged
+    pub redelivered: bool,
+    pub exchange: String,
+    pub routing_key: String,
+    pub body: Vec<u8>,
+}
+
+fn amqp_error(e: lapin::Error) -> StreamError {
+    StreamError::Runtime(format!("AMQP error: {}", e))
+}
+
+/// A connection to an AMQP broker with one open channel
+pub struct AmqpClient {
+    connection: Connection,
+    channel: Channel,
+    consumer: Option<Consumer>,
+    /// Confirmations of the messages published since the last wait
+    confirms: Vec<PublisherConfirm>,
+}
+
+impl AmqpClient {
+    /// Connect to the broker of the options and open a channel
+    pub async fn connect(options: &AmqpOptions) -> StreamResult<Self> {
+        let userinfo = match &options.auth {
+            AuthConfig::None => AMQPUserInfo::default(),
+            AuthConfig::Basic { username, password } => AMQPUserInfo {
+                username: username.clone(),
+                password: password.clone(),
+            },
+            _ => {
+                return Err(StreamError::Config(
+                    "AMQP only supports username and password authentication".to_string(),
+                ));
+            }
+        };
+        let (scheme, tls) = match &options.tls {
+            Some(tls) => (AMQPScheme::AMQPS, tls_config(tls).await?),
+            None => (AMQPScheme::AMQP, OwnedTLSConfig::default()),
+        };
+        let uri = AMQPUri {
+            scheme,
+            authority: AMQPAuthority {
+                userinfo,
+                host: options.host.clone(),
+                port: options.port,
+            },
+            vhost: options.virtual_host.clone(),
+            ..Default::default()
+        };
+
+        let runtime = lapin::runtime::default_runtime().map_err(amqp_error)?;
+        let connection =
+            Connection::connect_uri_with_config(uri, ConnectionProperties::default(), tls, runtime)
+                .await
+                .map_err(amqp_error)?;
+        let channel = connection.create_channel().await.map_err(amqp_error)?;
+        Ok(Self {
+            connection,
+            channel,
+            consumer: None,
+            confirms: Vec::new(),
+        })
+    }
+
+    /// Declare a queue
+    pub async fn declare_queue(&mut self, queue: &AmqpQueue) -> StreamResult<()> {
+        let mut arguments = FieldTable::default();
+        if let Some(exchange) = &queue.dead_letter_exchange {
+            arguments.insert(
+                "x-dead-letter-exchange".into(),
+                AMQPValue::LongString(exchange.as_str().into()),
+            );
+        }
+        if let Some(routing_key) = &queue.dead_letter_routing_key {
+            arguments.insert(
+                "x-dead-letter-routing-key".into(),
+                AMQPValue::LongString(routing_key.as_str().into()),
+            );
+        }
+        let options = QueueDeclareOptions {
+            durable: queue.durable,
+            auto_delete: queue.auto_delete,
+            ..Default::default()
+        };
+        self.channel
+            .queue_declare(queue.name.as_str().into(), options, arguments)
+            .await
+            .map_err(amqp_error)?;
+        Ok(())
+    }
+
+    /// Limit the deliveries not acknowledged yet to `prefetch` messages
+    pub async fn set_prefetch(&mut self, prefetch: u16) -> StreamResult<()> {
+        self.channel
+            .basic_qos(prefetch, BasicQosOptions::default())
+            .await
+            .map_err(amqp_error)
+    }
+
+    /// Start consuming a queue, with every delivery to be acknowledged
+    pub async fn consume(&mut self, queue: &str) -> StreamResult<()> {
+        let consumer = self
+            .channel
+            .basic_consume(
+                queue.into(),
+                "".into(),
+                BasicConsumeOptions::default(),
+                FieldTable::default(),
+            )
+            .await
+            .map_err(amqp_error)?;
+        self.consumer = Some(consumer);
+        Ok(())
+    }
+
+    /// Wait for the next delivery of the consumer. Cancelling the wait
+    /// loses no deliveries.
+    pub async fn next_delivery(&mut self) -> StreamResult<Delivery> {
+        let consumer = self
+            .consumer
+            .as_mut()
+            .ok_or_else(|| StreamError::Runtime("AMQP client isn't consuming".to_string()))?;
+        match consumer.next().await {
+            Some(Ok(delivery)) => Ok(Delivery {
+                delivery_tag: delivery.delivery_tag,
+                redelivered: delivery.redelivered,
+                exchange: delivery.exchange.to_string(),
+                routing_key: delivery.routing_key.to_string(),
+                body: delivery.data,
+            }),
+            Some(Err(e)) => Err(amqp_error(e)),
+            None => Err(StreamError::Runtime(
+                "AMQP consumer was cancelled".to_string(),
+            )),
+        }
+    }
+
+    /// Acknowledge a delivery, or with `multiple` all deliveries up to it
+    pub async fn ack(&mut self, delivery_tag: u64, multiple: bool) -> StreamResult<()> {
+        self.channel
+            .basic_ack(delivery_tag, BasicAckOptions { multiple })
+            .await
+            .map_err(amqp_error)
+    }
+
+    /// Reject a delivery, requeueing it or dead-lettering it
+    pub async fn nack(&mut self, delivery_tag: u64, requeue: bool) -> StreamResult<()> {
+        let options = BasicNackOptions {
+            multiple: false,
+            requeue,
+        };
+        self.channel
+            .basic_nack(delivery_tag, options)
+            .await
+            .map_err(amqp_error)
+    }
+
+    /// Have the broker confirm every message published from now on
+    pub async fn confirm_select(&mut self) -> StreamResult<()> {
+        self.channel
+            .confirm_select(ConfirmSelectOptions::default())
+            .await
+            .map_err(amqp_error)
+    }
+
+    /// Publish a message to an exchange, the default one being `""`
+    pub async fn publish(
+        &mut self,
+        exchange: &str,
+        routing_key: &str,
+        body: &[u8],
+        persistent: bool,
+    ) -> StreamResult<()> {
+        let properties =
+            BasicProperties::default().with_delivery_mode(if persistent { 2 } else { 1 });
+        let confirm = self
+            .channel
+            .basic_publish(
+                exchange.into(),
+                routing_key.into(),
+                BasicPublishOptions::default(),
+                body,
+                properties,
+            )
+            .await
+            .map_err(amqp_error)?;
+        self.confirms.push(confirm);
+        Ok(())
+    }
+
+    /// Number of published messages the broker didn't confirm yet
+    pub fn unconfirmed(&self) -> usize {
+        self.confirms.len()
+    }
+
+    /// Wait until the broker confirmed every published message, failing if
+    /// it rejected any of them since the previous wait
+    pub async fn wait_for_confirms(&mut self) -> StreamResult<()> {
+        let mut nacked = 0;
+        for confirm in self.confirms.drain(..) {
+            if confirm.await.map_err(amqp_error)?.is_nack() {
+                nacked += 1;
+            }
+        }
+        if nacked > 0 {
+            return Err(StreamError::Runtime(format!(
+                "AMQP broker rejected {} messages",
+                nacked
+            )));
+        }
+        Ok(())
+    }
+
+    /// Close the connection
+    pub async fn close(&mut self) -> StreamResult<()> {
+        self.connection
+            .close(200, "OK".into())
+            .await
+            .map_err(amqp_error)
+    }
+}
@@ -0,0 +1,388 @@
+//! Options of a ClickHouse client and a decoder of query results in the
+//! `RowBinaryWithNamesAndTypes` format, typed with `clickhouse-types`
+
+use clickhouse_types::data_types::{DataTypeNode, DecimalType, EnumType};
+use hyper_tls::HttpsConnector;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::models::{StreamError, StreamResult};
+use crate::row::{DataType, Field, Row, Schema, Value};
+use crate::security::{AuthConfig, TlsConfig};
+
+pub use clickhouse::Client;
+pub use clickhouse::query::BytesCursor;
+
+/// Options of a connection to the HTTP interface of a ClickHouse server
+#[derive(Debug, Clone)]
+pub struct ClickHouseOptions {
+    /// Address of the HTTP interface, e.g. `http://localhost:8123`
+    pub url: String,
+    pub database: String,
+    /// Username and password, as [`AuthConfig::Basic`]
+    pub auth: AuthConfig,
+    /// Settings of every query, e.g. `max_execution_time`
+    pub settings: Vec<(String, String)>,
+    /// TLS options of `https` addresses, which are otherwise verified
+    /// against the root certificates of the platform only
+    pub tls: Option<TlsConfig>,
+}
+
+/// Keep-alive interval and idle timeout of pooled connections, as in the
+/// default client of `clickhouse`, below the 3s keep-alive of the server
+const TCP_KEEPALIVE: Duration = Duration::from_secs(60);
+const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+impl ClickHouseOptions {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            database: "default".to_string(),
+            auth: AuthConfig::None,
+            settings: Vec::new(),
+            tls: None,
+        }
+    }
+
+    pub fn with_database(mut self, database: impl Into<String>) -> Self {
+        self.database = database.into();
+        self
+    }
+
+    /// Authenticate with a username and password
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.auth = AuthConfig::Basic {
+            username: username.into(),
+            password: password.into(),
+        };
+        self
+    }
+
+    /// Set a setting of every query
+    pub fn with_setting(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.settings.push((name.into(), value.into()));
+        self
+    }
+
+    /// Trust a private CA or authenticate with a client certificate on an
+    /// `https` address
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// A client of the HTTP interface with the options
+    pub async fn client(&self) -> StreamResult<Client> {
+        let client = match &self.tls {
+            Some(tls) => {
+                let mut http = HttpConnector::new();
+                http.set_keepalive(Some(TCP_KEEPALIVE));
+                http.enforce_http(false);
+                let tls = tokio_native_tls::TlsConnector::from(tls.native_tls_connector().await?);
+                Client::with_http_client(
+                    hyper_util::client::legacy::Client::builder(TokioExecutor::new())
+                        .pool_idle_timeout(POOL_IDLE_TIMEOUT)
+                        .build(HttpsConnector::from((http, tls))),
+                )
+            }
+            None => Client::default(),
+        };
+        let mut client = client.with_url(&self.url).with_database(&self.database);
+        match &self.auth {
+            AuthConfig::None => {}
+            AuthConfig::Basic { username, password } => {
+                client = client.with_user(username).with_password(password);
+            }
+            _ => {
+                return Err(StreamError::Config(
+                    "ClickHouse only supports username and password authentication".to_string(),
+                ));
+            }
+        }
+        for (name, value) in &self.settings {
+            client = client.with_setting(name, value);
+        }
+        Ok(client)
+    }
+}
+
+/// An error of the ClickHouse client
+pub fn clickhouse_error(e: clickhouse::error::Error) -> StreamError {
+    StreamError::Runtime(format!("ClickHouse error: {}", e))
+}
+
+/// Why decoding stopped: the input ended within a value, or is invalid
+enum Stop {
+    Incomplete,
+    Invalid(StreamError),
+}
+
+type Decoded<T> = Result<T, Stop>;
+
+fn invalid<T>(message: impl Into<String>) -> Decoded<T> {
+    Err(Stop::Invalid(StreamError::Serialization(format!(
+        "invalid ClickHouse RowBinary data: {}",
+        message.into()
+    ))))
+}
+
+struct Cursor<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, n: usize) -> Decoded<&'a [u8]> {
+        let bytes = self
+            .input
+            .get(self.pos..self.pos + n)
+            .ok_or(Stop::Incomplete)?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Decoded<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("N bytes"))
+    }
+
+    fn varint(&mut self) -> Decoded<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        invalid("a length is too long")
+    }
+
+    fn string(&mut self) -> Decoded<&'a [u8]> {
+        let len = self.varint()? as usize;
+        self.take(len)
+    }
+}
+
+/// Type of a column in rows, failing on types the decoder doesn't read
+fn data_type(node: &DataTypeNode) -> StreamResult<DataType> {
+    Ok(match node {
+        DataTypeNode::Bool => DataType::Boolean,
+        DataTypeNode::UInt8
+        | DataTypeNode::UInt16
+        | DataTypeNode::UInt32
+        | DataTypeNode::UInt64
+        | DataTypeNode::Int8
+        | DataTypeNode::Int16
+        | DataTypeNode::Int32
+        | DataTypeNode::Int64 => DataType::Int64,
+        DataTypeNode::Float32 | DataTypeNode::Float64 | DataTypeNode::Decimal(..) => {
+            DataType::Float64
+        }
+        DataTypeNode::Date
+        | DataTypeNode::Date32
+        | DataTypeNode::DateTime(_)
+        | DataTypeNode::DateTime64(..) => DataType::Timestamp,
+        DataTypeNode::String
+        | DataTypeNode::FixedString(_)
+        | DataTypeNode::UUID
+        | DataTypeNode::Enum(..) => DataType::String,
+        DataTypeNode::Array(items) => {
+            data_type(items)?;
+            DataType::String
+        }
+        DataTypeNode::Nullable(inner) | DataTypeNode::LowCardinality(inner) => data_type(inner)?,
+        _ => {
+            return Err(StreamError::Config(format!(
+                "unsupported ClickHouse type {}",
+                node
+            )));
+        }
+    })
+}
+
+/// Decode a value of a column in the `RowBinary` format
+fn decode(node: &DataTypeNode, cursor: &mut Cursor<'_>) -> Decoded<Value> {
+    Ok(match node {
+        DataTypeNode::UInt64 => {
+            let value = u64::from_le_bytes(cursor.array()?);
+            match i64::try_from(value) {
+                Ok(value) => Value::Int64(value),
+                Err(_) => return invalid(format!("UInt64 {} is out of range", value)),
+            }
+        }
+        DataTypeNode::UInt8 => Value::Int64(cursor.take(1)?[0].into()),
+        DataTypeNode::UInt16 => Value::Int64(u16::from_le_bytes(cursor.array()?).into()),
+        DataTypeNode::UInt32 => Value::Int64(u32::from_le_bytes(cursor.array()?).into()),
+        DataTypeNode::Int8 => Value::Int64(signed(cursor.take(1)?) as i64),
+        DataTypeNode::Int16 => Value::Int64(signed(cursor.take(2)?) as i64),
+        DataTypeNode::Int32 => Value::Int64(signed(cursor.take(4)?) as i64),
+        DataTypeNode::Int64 => Value::Int64(i64::from_le_bytes(cursor.array()?)),
+        DataTypeNode::Float32 => Value::Float64(f32::from_le_bytes(cursor.array()?) as f64),
+        DataTypeNode::Float64 => Value::Float64(f64::from_le_bytes(cursor.array()?)),
+        DataTypeNode::Bool => Value::Boolean(cursor.take(1)?[0] != 0),
+        DataTypeNode::String => {
+            Value::String(String::from_utf8_lossy(cursor.string()?).into_owned())
+        }
+        DataTypeNode::FixedString(len) => {
+            let bytes = cursor.take(*len)?;
+            let end = bytes.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+            Value::String(String::from_utf8_lossy(&bytes[..end]).into_owned())
+        }
+        DataTypeNode::Date => {
+            Value::Timestamp(i64::from(u16::from_le_bytes(cursor.array()?)) * DAY)
+        }
+        DataTypeNode::Date32 => {
+            Value::Timestamp(i64::from(i32::from_le_bytes(cursor.array()?)) * DAY)
+        }
+        DataTypeNode::DateTime(_) => {
+            Value::Timestamp(i64::from(u32::from_le_bytes(cursor.array()?)) * 1000)
+        }
+        DataTypeNode::DateTime64(precision, _) => {
+            let ticks = i64::from_le_bytes(cursor.array()?);
+            let precision: u32 = precision.to_string().parse().expect("a digit");
+            Value::Timestamp(match precision {
+                0..=3 => ticks * 10i64.pow(3 - precision),
+                _ => ticks.div_euclid(10i64.pow(precision - 3)),
+            })
+        }
+        DataTypeNode::UUID => {
+            let high = u64::from_le_bytes(cursor.array()?);
+            let low = u64::from_le_bytes(cursor.array()?);
+            let hex = format!("{:016x}{:016x}", high, low);
+            Value::String(format!(
+                "{}-{}-{}-{}-{}",
+                &hex[..8],
+                &hex[8..12],
+                &hex[12..16],
+                &hex[16..20],
+                &hex[20..]
+            ))
+        }
+        DataTypeNode::Enum(enum_type, names) => {
+            let bytes = match enum_type {
+                EnumType::Enum8 => 1,
+                EnumType::Enum16 => 2,
+            };
+            let code = signed(cursor.take(bytes)?) as i16;
+            match names.get(&code) {
+                Some(name) => Value::String(name.clone()),
+                None => return invalid(format!("enum value {} is unknown", code)),
+            }
+        }
+        DataTypeNode::Decimal(_, scale, size) => {
+            let bytes = match size {
+                DecimalType::Decimal32 => 4,
+                DecimalType::Decimal64 => 8,
+                DecimalType::Decimal128 => 16,
+                DecimalType::Decimal256 => return invalid("Decimal256 is unsupported"),
+            };
+            Value::Float64(signed(cursor.take(bytes)?) as f64 / 10f64.powi(i32::from(*scale)))
+        }
+        DataTypeNode::Nullable(inner) => {
+            if cursor.take(1)?[0] != 0 {
+                Value::Null
+            } else {
+                decode(inner, cursor)?
+            }
+        }
+        // Low cardinality columns are sent as their values
+        DataTypeNode::LowCardinality(inner) => decode(inner, cursor)?,
+        DataTypeNode::Array(items) => {
+            let len = cursor.varint()? as usize;
+            let mut values = Vec::with_capacity(len.min(1024));
+            for _ in 0..len {
+                values.push(serde_json::to_value(decode(items, cursor)?).unwrap_or_default());
+            }
+            Value::String(serde_json::Value::Array(values).to_string())
+        }
+        other => return invalid(format!("unsupported type {}", other)),
+    })
+}
+
+const DAY: i64 = 86_400_000;
+
+/// A little endian signed integer of up to 16 bytes
+fn signed(bytes: &[u8]) -> i128 {
+    let mut buf = [0u8; 16];
+    buf[..bytes.len()].copy_from_slice(bytes);
+    let shift = (16 - bytes.len()) * 8;
+    (i128::from_le_bytes(buf) << shift) >> shift
+}
+
+/// Decodes query results in the `RowBinaryWithNamesAndTypes` format into
+/// [`Row`]s. Integers are read as `Int64`, decimals as `Float64`, dates and
+/// times as timestamps, and UUIDs, enums and arrays, as JSON, as strings.
+#[derive(Debug, Clone)]
+pub struct RowBinaryDecoder {
+    schema: Arc<Schema>,
+    types: Vec<DataTypeNode>,
+}
+
+impl RowBinaryDecoder {
+    /// Decode the header naming and typing the columns, `None` if the input
+    /// ends before it, or the decoder and the length of the header
+    pub fn decode_header(input: &[u8]) -> StreamResult<Option<(Self, usize)>> {
+        let mut cursor = Cursor { input, pos: 0 };
+        let header = (|| {
+            let count = cursor.varint()? as usize;
+            let mut names = Vec::with_capacity(count);
+            for _ in 0..count {
+                names.push(String::from_utf8_lossy(cursor.string()?).into_owned());
+            }
+            let mut types = Vec::with_capacity(count);
+            for _ in 0..count {
+                types.push(String::from_utf8_lossy(cursor.string()?).into_owned());
+            }
+            Ok((names, types))
+        })();
+        let (names, types) = match header {
+            Ok(header) => header,
+            Err(Stop::Incomplete) => return Ok(None),
+            Err(Stop::Invalid(e)) => return Err(e),
+        };
+
+        let types = types
+            .iter()
+            .map(|name| {
+                DataTypeNode::new(name).map_err(|e| {
+                    StreamError::Config(format!("unsupported ClickHouse type {}: {}", name, e))
+                })
+            })
+            .collect::<StreamResult<Vec<_>>>()?;
+        let fields = names
+            .into_iter()
+            .zip(&types)
+            .map(|(name, node)| Ok(Field::new(name, data_type(node)?)))
+            .collect::<StreamResult<_>>()?;
+        let decoder = Self {
+            schema: Arc::new(Schema::new(fields)),
+            types,
+        };
+        Ok(Some((decoder, cursor.pos)))
+    }
+
+    pub fn schema(&self) -> &Arc<Schema> {
+        &self.schema
+    }
+
+    /// Decode the next row, `None` if the input ends before it, or the row
+    /// and its length
+    pub fn decode_row(&self, input: &[u8]) -> StreamResult<Option<(Row, usize)>> {
+        let mut cursor = Cursor { input, pos: 0 };
+        let mut values = Vec::with_capacity(self.types.len());
+        for node in &self.types {
+            match decode(node, &mut cursor) {
+                Ok(value) => values.push(value),
+                Err(Stop::Incomplete) => return Ok(None),
+                Err(Stop::Invalid(e)) => return Err(e),
+            }
+        }
+        Ok(Some((Row::new(self.schema.clone(), values)?, cursor.pos)))
+    }
+}
@@ -0,0 +1,106 @@
+use async_compression::tokio::bufread::{BzDecoder, GzipDecoder, XzDecoder, ZstdDecoder};
+use async_compression::tokio::write::{BzEncoder, GzipEncoder, XzEncoder, ZstdEncoder};
+use std::path::Path;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, BufReader};
+
+/// Boxed reader returned by [`CompressionCodec::decoder`]
+pub type DecodedReader = Box<dyn AsyncBufRead + Unpin + Send + Sync>;
+
+/// Boxed writer returned by [`CompressionCodec::encoder`]
+pub type EncodedWriter = Box<dyn AsyncWrite + Unpin + Send + Sync>;
+
+/// Compression codec shared by file, object and HTTP connectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CompressionCodec {
+    /// Uncompressed data
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+    /// Detect the codec from the leading magic bytes of the stream
+    Auto,
+}
+
+impl CompressionCodec {
+    /// Guess the codec from a file name or URL extension
+    pub fn from_extension<P: AsRef<Path>>(path: P) -> Self {
+        let ext = path
+            .as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("gz") | Some("gzip") => Self::Gzip,
+            Some("zst") | Some("zstd") => Self::Zstd,
+            Some("bz2") => Self::Bzip2,
+            Some("xz") => Self::Xz,
+            _ => Self::None,
+        }
+    }
+
+    /// Identify the codec from the first bytes of a stream
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Self::Gzip
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Self::Zstd
+        } else if bytes.starts_with(b"BZh") {
+            Self::Bzip2
+        } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Self::Xz
+        } else {
+            Self::None
+        }
+    }
+
+    /// File extension (without dot) conventionally used for this codec
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::Gzip => Some("gz"),
+            Self::Zstd => Some("zst"),
+            Self::Bzip2 => Some("bz2"),
+            Self::Xz => Some("xz"),
+            Self::None | Self::Auto => None,
+        }
+    }
+
+    /// Wrap a reader so that it yields decompressed bytes
+    pub async fn decoder<R>(self, mut reader: R) -> std::io::Result<DecodedReader>
+    where
+        R: AsyncBufRead + Unpin + Send + Sync + 'static,
+    {
+        let codec = match self {
+            // Peek at the buffered bytes without consuming them
+            Self::Auto => Self::from_magic(reader.fill_buf().await?),
+            codec => codec,
+        };
+        Ok(match codec {
+            Self::None | Self::Auto => Box::new(reader),
+            Self::Gzip => {
+                let mut decoder = GzipDecoder::new(reader);
+                decoder.multiple_members(true);
+                Box::new(BufReader::new(decoder))
+            }
+            Self::Zstd => Box::new(BufReader::new(ZstdDecoder::new(reader))),
+            Self::Bzip2 => Box::new(BufReader::new(BzDecoder::new(reader))),
+            Self::Xz => Box::new(BufReader::new(XzDecoder::new(reader))),
+        })
+    }
+
+    /// Wrap a writer so that written bytes are compressed.
+    /// `Auto` cannot be detected on write and behaves like `None`.
+    pub fn encoder<W>(self, writer: W) -> EncodedWriter
+    where
+        W: AsyncWrite + Unpin + Send + Sync + 'static,
+    {
+        match self {
+            Self::None | Self::Auto => Box::new(writer),
+            Self::Gzip => Box::new(GzipEncoder::new(writer)),
+            Self::Zstd => Box::new(ZstdEncoder::new(writer)),
+            Self::Bzip2 => Box::new(BzEncoder::new(writer)),
+            Self::Xz => Box::new(XzEncoder::new(writer)),
+        }
+    }
+}
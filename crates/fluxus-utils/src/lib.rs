@@ -1,4 +1,17 @@
+#[cfg(feature = "amqp")]
+pub mod amqp;
+#[cfg(feature = "clickhouse")]
+pub mod clickhouse;
+pub mod compression;
 pub mod error_converters;
+pub mod memory;
 pub mod models;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+pub mod row;
+pub mod security;
+pub mod stats;
 pub mod time;
 pub mod window;
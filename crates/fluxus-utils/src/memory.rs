@@ -0,0 +1,243 @@
+//! Memory accounting: estimated sizes of values, shared byte counters per
+//! job and operator, and an optional allocator counting the bytes of the
+//! whole process
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::mem::size_of;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+
+use crate::models::Record;
+
+/// An estimate of the memory a value holds, to account for buffered records
+/// and state.
+///
+/// The estimate counts the value itself and the heap memory it owns, by
+/// capacity, but not memory shared with other values, e.g. behind an `Arc`.
+pub trait MemorySize {
+    /// Bytes of the value and of the heap memory it owns
+    fn memory_size(&self) -> usize {
+        size_of_val(self) + self.heap_size()
+    }
+
+    /// Bytes of the heap memory the value owns
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+macro_rules! impl_memory_size {
+    ($($t:ty),*) => {
+        $(impl MemorySize for $t {})*
+    };
+}
+
+impl_memory_size!(
+    (),
+    bool,
+    char,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    f32,
+    f64,
+    &str
+);
+
+impl MemorySize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: MemorySize> MemorySize for Box<T> {
+    fn heap_size(&self) -> usize {
+        (**self).memory_size()
+    }
+}
+
+impl<T: MemorySize> MemorySize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, MemorySize::heap_size)
+    }
+}
+
+impl<T: MemorySize> MemorySize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(MemorySize::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: MemorySize> MemorySize for VecDeque<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(MemorySize::heap_size).sum::<usize>()
+    }
+}
+
+impl<K: MemorySize, V: MemorySize, S> MemorySize for HashMap<K, V, S> {
+    fn heap_size(&self) -> usize {
+        // A control byte per bucket besides the entry
+        self.capacity() * (size_of::<(K, V)>() + 1)
+            + self
+                .iter()
+                .map(|(k, v)| k.heap_size() + v.heap_size())
+                .sum::<usize>()
+    }
+}
+
+impl<K: MemorySize, V: MemorySize> MemorySize for BTreeMap<K, V> {
+    fn heap_size(&self) -> usize {
+        self.iter()
+            .map(|(k, v)| k.memory_size() + v.memory_size())
+            .sum()
+    }
+}
+
+impl<A: MemorySize, B: MemorySize> MemorySize for (A, B) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size()
+    }
+}
+
+impl<A: MemorySize, B: MemorySize, C: MemorySize> MemorySize for (A, B, C) {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size() + self.1.heap_size() + self.2.heap_size()
+    }
+}
+
+impl<T: MemorySize> MemorySize for Record<T> {
+    fn heap_size(&self) -> usize {
+        self.data.heap_size()
+    }
+}
+
+#[derive(Debug, Default)]
+struct AccountInner {
+    bytes: AtomicI64,
+    peak: AtomicI64,
+    parent: Option<MemoryAccount>,
+}
+
+/// A counter of the bytes held by a job or an operator, e.g. by its window
+/// buffers, channels and state.
+///
+/// Clones share the counter. The bytes of a [`child`](Self::child) account,
+/// e.g. of an operator, also count towards its parent, e.g. its job.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryAccount {
+    inner: Arc<AccountInner>,
+}
+
+impl MemoryAccount {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An account whose bytes also count towards this one
+    pub fn child(&self) -> Self {
+        Self {
+            inner: Arc::new(AccountInner {
+                parent: Some(self.clone()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Account for `bytes` more
+    pub fn add(&self, bytes: usize) {
+        self.change(bytes as i64);
+    }
+
+    /// Account for `bytes` less, e.g. of a record that left a buffer
+    pub fn release(&self, bytes: usize) {
+        self.change(-(bytes as i64));
+    }
+
+    /// Account for a value that grew or shrank from `before` to `after` bytes
+    pub fn resize(&self, before: usize, after: usize) {
+        self.change(after as i64 - before as i64);
+    }
+
+    fn change(&self, delta: i64) {
+        if delta == 0 {
+            return;
+        }
+        let bytes = self.inner.bytes.fetch_add(delta, Ordering::Relaxed) + delta;
+        self.inner.peak.fetch_max(bytes, Ordering::Relaxed);
+        if let Some(parent) = &self.inner.parent {
+            parent.change(delta);
+        }
+    }
+
+    /// Bytes currently accounted for
+    pub fn bytes(&self) -> i64 {
+        self.inner.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Most bytes accounted for at any time
+    pub fn peak(&self) -> i64 {
+        self.inner.peak.load(Ordering::Relaxed)
+    }
+}
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator that counts the bytes allocated by the process, to
+/// compare the accounted memory of pipelines with the total.
+///
+/// Install it in the binary of an application:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+/// ```
+pub struct TrackingAllocator;
+
+impl TrackingAllocator {
+    /// Bytes currently allocated, zero unless the allocator is installed
+    pub fn allocated() -> usize {
+        ALLOCATED.load(Ordering::Relaxed)
+    }
+}
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
@@ -1,3 +1,4 @@
+use std::fmt;
 use thiserror::Error;
 
 use crate::time::current_time;
@@ -24,6 +25,60 @@ impl<T> Record<T> {
     }
 }
 
+/// Kind of change a changelog entry makes to a previously emitted result
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    /// A result for a key that had none before
+    Insert,
+    /// Retracts the previous result for the key, followed by an `UpdateAfter`
+    UpdateBefore,
+    /// The new result for the key
+    UpdateAfter,
+}
+
+/// An entry of a changelog, e.g. to upsert window results into a database
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<K, T> {
+    pub kind: ChangeKind,
+    /// The key of the result, such as the window it belongs to
+    pub key: K,
+    pub value: T,
+}
+
+impl<K, T> Change<K, T> {
+    pub fn new(kind: ChangeKind, key: K, value: T) -> Self {
+        Self { kind, key, value }
+    }
+
+    /// Whether the entry withdraws a previously emitted result
+    pub fn is_retraction(&self) -> bool {
+        self.kind == ChangeKind::UpdateBefore
+    }
+}
+
+/// Where an error happened and whether retrying may help
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// Name of the operator, source or sink that failed
+    pub operator: Option<String>,
+    /// Timestamp of the record being processed
+    pub timestamp: Option<i64>,
+    /// Whether the failed call may succeed when retried
+    pub retryable: bool,
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(operator) = &self.operator {
+            write!(f, " in {}", operator)?;
+        }
+        if let Some(timestamp) = self.timestamp {
+            write!(f, " at {}", timestamp)?;
+        }
+        Ok(())
+    }
+}
+
 /// Error types that can occur during stream processing
 #[derive(Error, Debug)]
 pub enum StreamError {
@@ -44,6 +99,161 @@ pub enum StreamError {
 
     #[error("Wait for {0} milliseconds")]
     Wait(u64),
+
+    #[error("Timed out after {0:?}")]
+    Timeout(std::time::Duration),
+
+    #[error("Source error{context}: {message}")]
+    Source {
+        message: String,
+        context: ErrorContext,
+    },
+
+    #[error("Sink error{context}: {message}")]
+    Sink {
+        message: String,
+        context: ErrorContext,
+    },
+
+    #[error("Operator error{context}: {message}")]
+    Operator {
+        message: String,
+        context: ErrorContext,
+    },
+
+    #[error("Backpressure{context}: {message}")]
+    Backpressure {
+        message: String,
+        context: ErrorContext,
+    },
+
+    #[error("Checkpoint error{context}: {message}")]
+    Checkpoint {
+        message: String,
+        context: ErrorContext,
+    },
+}
+
+impl StreamError {
+    /// A failure reading from a source
+    pub fn source_error<S: Into<String>>(message: S) -> Self {
+        Self::Source {
+            message: message.into(),
+            context: ErrorContext::default(),
+        }
+    }
+
+    /// A failure writing to a sink
+    pub fn sink_error<S: Into<String>>(message: S) -> Self {
+        Self::Sink {
+            message: message.into(),
+            context: ErrorContext::default(),
+        }
+    }
+
+    /// A failure processing a record in an operator
+    pub fn operator_error<S: Into<String>>(message: S) -> Self {
+        Self::Operator {
+            message: message.into(),
+            context: ErrorContext::default(),
+        }
+    }
+
+    /// A record rejected because a downstream stage is overloaded, retryable by default
+    pub fn backpressure<S: Into<String>>(message: S) -> Self {
+        Self::Backpressure {
+            message: message.into(),
+            context: ErrorContext {
+                retryable: true,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// A failure taking or restoring a checkpoint
+    pub fn checkpoint<S: Into<String>>(message: S) -> Self {
+        Self::Checkpoint {
+            message: message.into(),
+            context: ErrorContext::default(),
+        }
+    }
+
+    /// Context of the error, for the variants that carry one
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::Source { context, .. }
+            | Self::Sink { context, .. }
+            | Self::Operator { context, .. }
+            | Self::Backpressure { context, .. }
+            | Self::Checkpoint { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    fn context_mut(&mut self) -> Option<&mut ErrorContext> {
+        match self {
+            Self::Source { context, .. }
+            | Self::Sink { context, .. }
+            | Self::Operator { context, .. }
+            | Self::Backpressure { context, .. }
+            | Self::Checkpoint { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    /// Set the name of the failed stage, ignored by variants without context
+    pub fn with_operator<S: Into<String>>(mut self, operator: S) -> Self {
+        if let Some(context) = self.context_mut() {
+            context.operator = Some(operator.into());
+        }
+        self
+    }
+
+    /// Set the timestamp of the failed record, ignored by variants without context
+    pub fn with_timestamp(mut self, timestamp: i64) -> Self {
+        if let Some(context) = self.context_mut() {
+            context.timestamp = Some(timestamp);
+        }
+        self
+    }
+
+    /// Mark the error as retryable or not, ignored by variants without context
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        if let Some(context) = self.context_mut() {
+            context.retryable = retryable;
+        }
+        self
+    }
+
+    /// Fill in the stage and record of an error with context where they are not set yet
+    pub fn or_context(mut self, operator: &str, timestamp: Option<i64>) -> Self {
+        if let Some(context) = self.context_mut() {
+            context.operator.get_or_insert_with(|| operator.to_string());
+            if context.timestamp.is_none() {
+                context.timestamp = timestamp;
+            }
+        }
+        self
+    }
+
+    /// Whether the failed call may succeed when retried
+    pub fn is_retryable(&self) -> bool {
+        use std::io::ErrorKind;
+        match self {
+            Self::Io(e) => matches!(
+                e.kind(),
+                ErrorKind::Interrupted
+                    | ErrorKind::TimedOut
+                    | ErrorKind::WouldBlock
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::ConnectionRefused
+                    | ErrorKind::BrokenPipe
+            ),
+            Self::Timeout(_) | Self::Wait(_) => true,
+            _ => self.context().is_some_and(|context| context.retryable),
+        }
+    }
 }
 
 /// A Result type specialized for stream processing operations
@@ -0,0 +1,378 @@
+//! An MQTT client shared by the MQTT source and sink, built on `rumqttc`
+
+use rumqttc::{
+    AsyncClient, ConnectionError, Event, EventLoop, Outgoing, Packet, SubscribeFilter,
+    SubscribeReasonCode, TlsConfiguration, Transport,
+};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::models::{StreamError, StreamResult};
+use crate::security::{AuthConfig, TlsConfig};
+
+pub use rumqttc::QoS;
+
+/// Capacity of the request channel between the client and its event loop
+const REQUEST_CAPACITY: usize = 64;
+
+/// Connection options of an MQTT client
+#[derive(Debug, Clone)]
+pub struct MqttOptions {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    /// Interval of keep-alive pings while the connection is idle
+    pub keep_alive: Duration,
+    /// Start without the subscriptions and messages of a previous session
+    pub clean_session: bool,
+    /// Username and password, as [`AuthConfig::Basic`]
+    pub auth: AuthConfig,
+    /// Connect over TLS with the given options instead of plain TCP
+    pub tls: Option<TlsConfig>,
+    /// Largest packet to send or receive, in bytes
+    pub max_packet_size: usize,
+    /// Time to wait before connecting again after the connection was lost
+    pub reconnect_delay: Duration,
+    /// Number of attempts to connect again after the connection was lost
+    /// before failing, or unlimited if unset
+    pub max_reconnect_attempts: Option<u32>,
+}
+
+impl MqttOptions {
+    pub fn new(client_id: impl Into<String>, host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            client_id: client_id.into(),
+            keep_alive: Duration::from_secs(30),
+            clean_session: true,
+            auth: AuthConfig::None,
+            tls: None,
+            max_packet_size: 256 * 1024,
+            reconnect_delay: Duration::from_secs(1),
+            max_reconnect_attempts: None,
+        }
+    }
+
+    /// Ping the broker after the given idle time, which must be zero to turn
+    /// pings off or at least a second
+    pub fn with_keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    pub fn with_clean_session(mut self, clean_session: bool) -> Self {
+        self.clean_session = clean_session;
+        self
+    }
+
+    /// Authenticate with a username and password
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.auth = AuthConfig::Basic {
+            username: username.into(),
+            password: password.into(),
+        };
+        self
+    }
+
+    /// Connect over TLS, verifying the broker against the root certificates
+    /// of the platform and those of the options
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    pub fn with_max_packet_size(mut self, max_packet_size: usize) -> Self {
+        self.max_packet_size = max_packet_size;
+        self
+    }
+
+    pub fn with_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    pub fn with_max_reconnect_attempts(mut self, attempts: u32) -> Self {
+        self.max_reconnect_attempts = Some(attempts);
+        self
+    }
+
+    async fn to_rumqttc(&self) -> StreamResult<rumqttc::MqttOptions> {
+        if !self.keep_alive.is_zero() && self.keep_alive < Duration::from_secs(1) {
+            return Err(StreamError::Config(
+                "MQTT keep alive must be zero or at least a second".to_string(),
+            ));
+        }
+        let mut options = rumqttc::MqttOptions::new(&self.client_id, &self.host, self.port);
+        options
+            .set_keep_alive(self.keep_alive)
+            .set_clean_session(self.clean_session)
+            .set_max_packet_size(self.max_packet_size, self.max_packet_size);
+        match &self.auth {
+            AuthConfig::None => {}
+            AuthConfig::Basic { username, password } => {
+                options.set_credentials(username, password);
+            }
+            _ => {
+                return Err(StreamError::Config(
+                    "MQTT only supports username and password authentication".to_string(),
+                ));
+            }
+        }
+        if let Some(tls) = &self.tls {
+            let connector = tls.native_tls_connector().await?;
+            options.set_transport(Transport::Tls(TlsConfiguration::NativeConnector(connector)));
+        }
+        Ok(options)
+    }
+}
+
+/// A message published to a topic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MqttMessage {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    pub qos: QoS,
+    pub retain: bool,
+}
+
+impl MqttMessage {
+    pub fn new(topic: impl Into<String>, payload: impl Into<Vec<u8>>) -> Self {
+        Self {
+            topic: topic.into(),
+            payload: payload.into(),
+            qos: QoS::AtMostOnce,
+            retain: false,
+        }
+    }
+
+    pub fn with_qos(mut self, qos: QoS) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    pub fn with_retain(mut self, retain: bool) -> Self {
+        self.retain = retain;
+        self
+    }
+}
+
+/// Whether a topic matches a subscription filter, with `+` matching one
+/// level and a trailing `#` any number of levels
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    // Wildcards don't match topics of the broker starting with `$`
+    if topic.starts_with('$') && (filter.starts_with('+') || filter.starts_with('#')) {
+        return false;
+    }
+    let mut levels = topic.split('/');
+    for part in filter.split('/') {
+        match (part, levels.next()) {
+            ("#", _) => return true,
+            ("+", Some(_)) => {}
+            (part, Some(level)) if part == level => {}
+            _ => return false,
+        }
+    }
+    levels.next().is_none()
+}
+
+fn connection_error(e: ConnectionError) -> StreamError {
+    StreamError::Runtime(format!("MQTT connection error: {}", e))
+}
+
+fn client_error(e: rumqttc::ClientError) -> StreamError {
+    StreamError::Runtime(format!("MQTT client error: {}", e))
+}
+
+/// A session with an MQTT broker.
+///
+/// The client connects again when the connection is lost, waiting the
+/// reconnect delay of its options between attempts, and renews its
+/// subscriptions unless the broker kept the session.
+pub struct MqttClient {
+    client: AsyncClient,
+    // Only used through `&mut self`, the lock makes the client `Sync`
+    eventloop: Mutex<EventLoop>,
+    subscriptions: Vec<(String, QoS)>,
+    reconnect_delay: Duration,
+    max_reconnect_attempts: Option<u32>,
+    failures: u32,
+}
+
+impl MqttClient {
+    /// Connect to the broker of the options and subscribe to the topic
+    /// filters, failing if the broker can't be reached or rejects any of them
+    pub async fn connect(
+        options: &MqttOptions,
+        subscriptions: &[(String, QoS)],
+    ) -> StreamResult<Self> {
+        let (client, eventloop) = AsyncClient::new(options.to_rumqttc().await?, REQUEST_CAPACITY);
+        let mut client = Self {
+            client,
+            eventloop: Mutex::new(eventloop),
+            subscriptions: subscriptions.to_vec(),
+            reconnect_delay: options.reconnect_delay,
+            max_reconnect_attempts: options.max_reconnect_attempts,
+            failures: 0,
+        };
+
+        // The first connection fails instead of being retried
+        loop {
+            let event = client
+                .eventloop
+                .get_mut()
+                .poll()
+                .await
+                .map_err(connection_error)?;
+            if client.on_event(&event).await? {
+                break;
+            }
+        }
+        if !client.subscriptions.is_empty() {
+            while !matches!(client.poll().await?, Event::Incoming(Packet::SubAck(_))) {}
+        }
+        Ok(client)
+    }
+
+    /// Publish a message, waiting for the broker to acknowledge it as its
+    /// QoS requires
+    pub async fn publish(&mut self, message: &MqttMessage) -> StreamResult<()> {
+        self.send(message).await?;
+        let mut id = None;
+        loop {
+            let event = self.poll().await?;
+            match (&event, id) {
+                (Event::Outgoing(Outgoing::Publish(pkid)), None) => {
+                    if message.qos == QoS::AtMostOnce {
+                        return Ok(());
+                    }
+                    id = Some(*pkid);
+                }
+                (Event::Incoming(Packet::PubAck(ack)), Some(id))
+                    if message.qos == QoS::AtLeastOnce && ack.pkid == id =>
+                {
+                    return Ok(());
+                }
+                (Event::Incoming(Packet::PubComp(comp)), Some(id)) if comp.pkid == id => {
+                    return Ok(());
+                }
+                // A new session drops the messages in flight, so publish again
+                (Event::Incoming(Packet::ConnAck(ack)), _) if !ack.session_present => {
+                    self.send(message).await?;
+                    id = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Wait for the next message of the subscriptions, which the event loop
+    /// acknowledges as its QoS requires
+    pub async fn next_message(&mut self) -> StreamResult<MqttMessage> {
+        loop {
+            if let Event::Incoming(Packet::Publish(publish)) = self.poll().await? {
+                return Ok(MqttMessage {
+                    topic: publish.topic,
+                    payload: publish.payload.to_vec(),
+                    qos: publish.qos,
+                    retain: publish.retain,
+                });
+            }
+        }
+    }
+
+    /// Close the session
+    pub async fn disconnect(&mut self) -> StreamResult<()> {
+        self.client.disconnect().await.map_err(client_error)?;
+        loop {
+            match self.eventloop.get_mut().poll().await {
+                Ok(Event::Outgoing(Outgoing::Disconnect)) => return Ok(()),
+                Ok(_) => {}
+                // The connection is gone either way
+                Err(e) => {
+                    tracing::debug!("MQTT connection closed while disconnecting: {}", e);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    async fn send(&self, message: &MqttMessage) -> StreamResult<()> {
+        self.client
+            .publish(
+                message.topic.as_str(),
+                message.qos,
+                message.retain,
+                message.payload.clone(),
+            )
+            .await
+            .map_err(client_error)
+    }
+
+    /// Poll the event loop, connecting again after the reconnect delay when
+    /// the connection is lost
+    async fn poll(&mut self) -> StreamResult<Event> {
+        loop {
+            match self.eventloop.get_mut().poll().await {
+                Ok(event) => {
+                    self.on_event(&event).await?;
+                    return Ok(event);
+                }
+                Err(e) => {
+                    self.failures += 1;
+                    if self
+                        .max_reconnect_attempts
+                        .is_some_and(|max| self.failures > max)
+                    {
+                        return Err(connection_error(e));
+                    }
+                    tracing::warn!(
+                        "Lost the connection to the MQTT broker, reconnecting: {}",
+                        e
+                    );
+                    tokio::time::sleep(self.reconnect_delay).await;
+                }
+            }
+        }
+    }
+
+    /// Renew the subscriptions on a new session and fail on rejected ones,
+    /// returning whether the event is the acknowledgement of a connection
+    async fn on_event(&mut self, event: &Event) -> StreamResult<bool> {
+        match event {
+            Event::Incoming(Packet::ConnAck(ack)) => {
+                self.failures = 0;
+                if !ack.session_present && !self.subscriptions.is_empty() {
+                    let filters = self
+                        .subscriptions
+                        .iter()
+                        .map(|(filter, qos)| SubscribeFilter::new(filter.clone(), *qos));
+                    self.client
+                        .subscribe_many(filters)
+                        .await
+                        .map_err(client_error)?;
+                }
+                Ok(true)
+            }
+            Event::Incoming(Packet::SubAck(suback)) => {
+                let rejected = suback
+                    .return_codes
+                    .iter()
+                    .zip(&self.subscriptions)
+                    .find(|(code, _)| **code == SubscribeReasonCode::Failure);
+                match rejected {
+                    Some((_, (filter, _))) => Err(StreamError::Runtime(format!(
+                        "MQTT broker rejected the subscription to {}",
+                        filter
+                    ))),
+                    None => Ok(false),
+                }
+            }
+            _ => Ok(false),
+        }
+    }
+}
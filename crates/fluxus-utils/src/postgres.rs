@@ -0,0 +1,881 @@
+//! A Postgres client for simple queries and logical replication, on the
+//! messages and SCRAM authentication of `postgres-protocol`, and decoders of
+//! the changes of the `wal2json` and `pgoutput` plugins
+
+use bytes::BytesMut;
+use fallible_iterator::FallibleIterator;
+use postgres_protocol::authentication::md5_hash;
+use postgres_protocol::authentication::sasl::{ChannelBinding, SCRAM_SHA_256, ScramSha256};
+use postgres_protocol::message::backend::{ErrorResponseBody, Header, Message};
+use postgres_protocol::message::frontend;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::models::{StreamError, StreamResult};
+use crate::row::Value;
+use crate::security::{AuthConfig, TlsConfig};
+
+/// Microseconds from the unix epoch to the Postgres epoch, 2000-01-01
+const PG_EPOCH_MICROS: i64 = 946_684_800_000_000;
+
+/// SQLSTATE of creating an object that already exists
+const DUPLICATE_OBJECT: &str = "42710";
+
+/// A position in the write-ahead log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lsn(pub u64);
+
+impl fmt::Display for Lsn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:X}/{:X}", self.0 >> 32, self.0 & 0xffff_ffff)
+    }
+}
+
+impl FromStr for Lsn {
+    type Err = StreamError;
+
+    /// Parse the `X/Y` notation of Postgres
+    fn from_str(s: &str) -> StreamResult<Self> {
+        let invalid = || StreamError::Config(format!("invalid LSN '{}'", s));
+        let (high, low) = s.split_once('/').ok_or_else(invalid)?;
+        let high = u64::from_str_radix(high, 16).map_err(|_| invalid())?;
+        let low = u64::from_str_radix(low, 16).map_err(|_| invalid())?;
+        if high > u32::MAX as u64 || low > u32::MAX as u64 {
+            return Err(invalid());
+        }
+        Ok(Lsn((high << 32) | low))
+    }
+}
+
+/// Output plugin that decodes the changes of a replication slot
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicationPlugin {
+    /// The `wal2json` extension, in format version 2
+    Wal2Json,
+    /// The built-in `pgoutput` plugin, streaming the tables of a
+    /// publication created with `CREATE PUBLICATION`
+    PgOutput { publication: String },
+}
+
+impl ReplicationPlugin {
+    fn name(&self) -> &str {
+        match self {
+            Self::Wal2Json => "wal2json",
+            Self::PgOutput { .. } => "pgoutput",
+        }
+    }
+
+    fn options(&self) -> String {
+        match self {
+            Self::Wal2Json => "(\"format-version\" '2', \"include-types\" 'true')".to_string(),
+            Self::PgOutput { publication } => format!(
+                "(proto_version '1', publication_names '{}')",
+                publication.replace('\'', "''")
+            ),
+        }
+    }
+}
+
+/// Whether to encrypt the connection, as the `sslmode` of libpq
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SslMode {
+    /// Connect without TLS
+    #[default]
+    Disable,
+    /// Use TLS if the server supports it
+    Prefer,
+    /// Fail unless the server supports TLS
+    Require,
+}
+
+/// Connection options of a logical replication client
+#[derive(Debug, Clone)]
+pub struct PostgresOptions {
+    pub host: String,
+    pub port: u16,
+    pub database: String,
+    /// Username and password, as [`AuthConfig::Basic`]
+    pub auth: AuthConfig,
+    pub ssl_mode: SslMode,
+    /// TLS options of the connection unless `ssl_mode` disables TLS. The
+    /// server is verified against the root certificates of the platform and
+    /// those of the options.
+    pub tls: TlsConfig,
+    /// Replication slot to stream, created if it doesn't exist
+    pub slot: String,
+    pub plugin: ReplicationPlugin,
+    /// Position to stream from when the slot has not confirmed a later one
+    pub start_lsn: Lsn,
+    /// Interval of the status updates that confirm positions to the server
+    pub status_interval: Duration,
+    /// Time to wait before connecting again after the connection was lost
+    pub reconnect_delay: Duration,
+}
+
+impl PostgresOptions {
+    pub fn new(
+        host: impl Into<String>,
+        port: u16,
+        database: impl Into<String>,
+        slot: impl Into<String>,
+    ) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            database: database.into(),
+            auth: AuthConfig::None,
+            ssl_mode: SslMode::Disable,
+            tls: TlsConfig::default(),
+            slot: slot.into(),
+            plugin: ReplicationPlugin::Wal2Json,
+            start_lsn: Lsn::default(),
+            status_interval: Duration::from_secs(10),
+            reconnect_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Authenticate with a username and password
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.auth = AuthConfig::Basic {
+            username: username.into(),
+            password: password.into(),
+        };
+        self
+    }
+
+    pub fn with_ssl_mode(mut self, ssl_mode: SslMode) -> Self {
+        self.ssl_mode = ssl_mode;
+        self
+    }
+
+    /// Require TLS with the given options
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.ssl_mode = SslMode::Require;
+        self.tls = tls;
+        self
+    }
+
+    pub fn with_plugin(mut self, plugin: ReplicationPlugin) -> Self {
+        self.plugin = plugin;
+        self
+    }
+
+    pub fn with_start_lsn(mut self, lsn: Lsn) -> Self {
+        self.start_lsn = lsn;
+        self
+    }
+
+    pub fn with_status_interval(mut self, interval: Duration) -> Self {
+        self.status_interval = interval;
+        self
+    }
+
+    pub fn with_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+}
+
+/// Kind of a row change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A change of a row of a table
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// Position of the change in the write-ahead log
+    pub lsn: Lsn,
+    pub kind: ChangeKind,
+    pub schema: String,
+    pub table: String,
+    /// New values of the row, empty for deletes. Unchanged values stored
+    /// out of line are left out of updates.
+    pub columns: Vec<(String, Value)>,
+    /// Replica identity of the row before an update or delete, usually its
+    /// primary key, if the server sent it
+    pub identity: Vec<(String, Value)>,
+}
+
+impl Change {
+    /// The new value of a column
+    pub fn get(&self, column: &str) -> Option<&Value> {
+        find(&self.columns, column)
+    }
+
+    /// The value of a column of the replica identity
+    pub fn identity(&self, column: &str) -> Option<&Value> {
+        find(&self.identity, column)
+    }
+}
+
+fn find<'a>(values: &'a [(String, Value)], column: &str) -> Option<&'a Value> {
+    values
+        .iter()
+        .find(|(name, _)| name == column)
+        .map(|(_, value)| value)
+}
+
+fn protocol_error(message: impl fmt::Display) -> StreamError {
+    StreamError::Runtime(format!("Postgres protocol error: {}", message))
+}
+
+/// Type a textual value by the name of its Postgres type, keeping values
+/// without a matching type as strings
+fn typed_text(type_name: &str, text: &str) -> Value {
+    let parsed = match type_name {
+        "boolean" | "bool" => match text {
+            "t" | "true" => Some(Value::Boolean(true)),
+            "f" | "false" => Some(Value::Boolean(false)),
+            _ => None,
+        },
+        "smallint" | "integer" | "bigint" | "int2" | "int4" | "int8" => {
+            text.parse().ok().map(Value::Int64)
+        }
+        "real" | "double precision" | "numeric" | "float4" | "float8" => {
+            text.parse().ok().map(Value::Float64)
+        }
+        _ => None,
+    };
+    parsed.unwrap_or_else(|| Value::String(text.to_string()))
+}
+
+fn typed_json(type_name: &str, json: &serde_json::Value) -> Value {
+    use serde_json::Value as Json;
+    match json {
+        Json::Null => Value::Null,
+        Json::Bool(v) => Value::Boolean(*v),
+        Json::Number(n) => match n.as_i64() {
+            Some(v) if !matches!(type_name, "real" | "double precision" | "numeric") => {
+                Value::Int64(v)
+            }
+            _ => n.as_f64().map_or(Value::Null, Value::Float64),
+        },
+        Json::String(s) => typed_text(type_name, s),
+        other => Value::String(other.to_string()),
+    }
+}
+
+/// Decode a message of `wal2json` in format version 2, `None` for messages
+/// other than row changes
+pub fn decode_wal2json(lsn: Lsn, data: &[u8]) -> StreamResult<Option<Change>> {
+    let json: serde_json::Value =
+        serde_json::from_slice(data).map_err(|e| StreamError::Serialization(e.to_string()))?;
+    let kind = match json.get("action").and_then(|action| action.as_str()) {
+        Some("I") => ChangeKind::Insert,
+        Some("U") => ChangeKind::Update,
+        Some("D") => ChangeKind::Delete,
+        _ => return Ok(None),
+    };
+    let text = |field: &str| {
+        json.get(field)
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+            .ok_or_else(|| StreamError::Serialization(format!("wal2json change has no {}", field)))
+    };
+    let values = |field: &str| {
+        json.get(field)
+            .and_then(|columns| columns.as_array())
+            .map(|columns| {
+                columns
+                    .iter()
+                    .filter_map(|column| {
+                        let name = column.get("name")?.as_str()?;
+                        let type_name = column.get("type").and_then(|t| t.as_str()).unwrap_or("");
+                        let value = column.get("value").unwrap_or(&serde_json::Value::Null);
+                        Some((name.to_string(), typed_json(type_name, value)))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    Ok(Some(Change {
+        lsn,
+        kind,
+        schema: text("schema")?,
+        table: text("table")?,
+        columns: values("columns"),
+        identity: values("identity"),
+    }))
+}
+
+/// A table as described by a `pgoutput` relation message
+#[derive(Debug, Clone)]
+struct Relation {
+    schema: String,
+    table: String,
+    /// Names and type ids of the columns
+    columns: Vec<(String, u32)>,
+}
+
+/// Decodes the messages of `pgoutput`, which describes each table once
+/// before its first change
+#[derive(Debug, Default)]
+pub struct PgOutputDecoder {
+    relations: HashMap<u32, Relation>,
+}
+
+impl PgOutputDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decode a message, `None` for messages other than row changes
+    pub fn decode(&mut self, lsn: Lsn, data: &[u8]) -> StreamResult<Option<Change>> {
+        let mut reader = Reader::new(data);
+        let tag = reader.u8()?;
+        let kind = match tag {
+            b'R' => {
+                let id = reader.u32()?;
+                let schema = reader.cstr()?;
+                let table = reader.cstr()?;
+                let _replica_identity = reader.u8()?;
+                let count = reader.u16()?;
+                let mut columns = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    let _flags = reader.u8()?;
+                    let name = reader.cstr()?;
+                    let type_id = reader.u32()?;
+                    let _modifier = reader.u32()?;
+                    columns.push((name, type_id));
+                }
+                self.relations.insert(
+                    id,
+                    Relation {
+                        schema,
+                        table,
+                        columns,
+                    },
+                );
+                return Ok(None);
+            }
+            b'I' => ChangeKind::Insert,
+            b'U' => ChangeKind::Update,
+            b'D' => ChangeKind::Delete,
+            _ => return Ok(None),
+        };
+
+        let id = reader.u32()?;
+        let relation = self
+            .relations
+            .get(&id)
+            .ok_or_else(|| protocol_error(format!("change of unknown relation {}", id)))?;
+        let mut columns = Vec::new();
+        let mut identity = Vec::new();
+        loop {
+            match reader.u8()? {
+                b'N' => {
+                    columns = Self::tuple(relation, &mut reader)?;
+                    break;
+                }
+                b'K' | b'O' => {
+                    identity = Self::tuple(relation, &mut reader)?;
+                    if kind == ChangeKind::Delete {
+                        break;
+                    }
+                }
+                other => {
+                    return Err(protocol_error(format!("unexpected tuple type {}", other)));
+                }
+            }
+        }
+        Ok(Some(Change {
+            lsn,
+            kind,
+            schema: relation.schema.clone(),
+            table: relation.table.clone(),
+            columns,
+            identity,
+        }))
+    }
+
+    fn tuple(relation: &Relation, reader: &mut Reader<'_>) -> StreamResult<Vec<(String, Value)>> {
+        let count = reader.u16()? as usize;
+        let mut values = Vec::with_capacity(count);
+        for index in 0..count {
+            let (name, type_id) = relation
+                .columns
+                .get(index)
+                .ok_or_else(|| protocol_error("more values than columns"))?;
+            let value = match reader.u8()? {
+                b'n' => Value::Null,
+                // Unchanged values stored out of line aren't sent
+                b'u' => continue,
+                b't' => {
+                    let len = reader.u32()? as usize;
+                    let text = String::from_utf8_lossy(reader.take(len)?).into_owned();
+                    typed_text(type_name(*type_id), &text)
+                }
+                other => {
+                    return Err(protocol_error(format!("unsupported value type {}", other)));
+                }
+            };
+            values.push((name.clone(), value));
+        }
+        Ok(values)
+    }
+}
+
+/// Name of a built-in type by its id
+fn type_name(type_id: u32) -> &'static str {
+    match type_id {
+        16 => "bool",
+        20 => "int8",
+        21 => "int2",
+        23 => "int4",
+        700 => "float4",
+        701 => "float8",
+        1700 => "numeric",
+        _ => "",
+    }
+}
+
+/// Reads big-endian fields of a message
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn take(&mut self, n: usize) -> StreamResult<&'a [u8]> {
+        let bytes = self
+            .bytes
+            .get(self.pos..self.pos + n)
+            .ok_or_else(|| protocol_error("truncated message"))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> StreamResult<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> StreamResult<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> StreamResult<u32> {
+        let b = self.take(4)?;
+        Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> StreamResult<u64> {
+        let b = self.take(8)?;
+        Ok(u64::from_be_bytes([
+            b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7],
+        ]))
+    }
+
+    fn cstr(&mut self) -> StreamResult<String> {
+        let rest = &self.bytes[self.pos.min(self.bytes.len())..];
+        let end = rest
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or_else(|| protocol_error("unterminated string"))?;
+        let value = String::from_utf8_lossy(&rest[..end]).into_owned();
+        self.pos += end + 1;
+        Ok(value)
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.pos.min(self.bytes.len())..]
+    }
+}
+
+/// A message of the replication stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplicationMessage {
+    /// Output of the plugin for the change at `lsn`
+    XLogData { lsn: Lsn, end: Lsn, data: Vec<u8> },
+    /// The end of the log on the server, which asks for a status update
+    /// when `reply` is set
+    Keepalive { end: Lsn, reply: bool },
+}
+
+/// A replication connection to a Postgres server
+pub struct ReplicationClient {
+    client: PostgresClient,
+}
+
+impl ReplicationClient {
+    /// Connect to the server of the options, authenticating with a password,
+    /// MD5 or SCRAM-SHA-256. A password is only sent in cleartext over TLS.
+    pub async fn connect(options: &PostgresOptions) -> StreamResult<Self> {
+        let client = PostgresClient::connect(options, &[("replication", "database")]).await?;
+        Ok(Self { client })
+    }
+
+    /// Create the logical replication slot of the options, unless it exists
+    pub async fn create_slot(&mut self, options: &PostgresOptions) -> StreamResult<()> {
+        let query = format!(
+            "CREATE_REPLICATION_SLOT {} LOGICAL {}",
+            quote_identifier(&options.slot),
+            options.plugin.name()
+        );
+        match self.client.execute(&query).await {
+            Err(StreamError::Runtime(message)) if message.contains(DUPLICATE_OBJECT) => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Start streaming the slot of the options from `lsn`, or from the
+    /// position the slot confirmed if that is later
+    pub async fn start_replication(
+        &mut self,
+        options: &PostgresOptions,
+        lsn: Lsn,
+    ) -> StreamResult<()> {
+        let query = format!(
+            "START_REPLICATION SLOT {} LOGICAL {} {}",
+            quote_identifier(&options.slot),
+            lsn,
+            options.plugin.options()
+        );
+        self.client.send_query(&query).await?;
+        loop {
+            match self.client.read().await? {
+                Backend::CopyBoth => return Ok(()),
+                Backend::Message(Message::ErrorResponse(body)) => return Err(server_error(&body)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Wait for the next message of the replication stream. Cancelling the
+    /// wait loses no messages.
+    pub async fn next_message(&mut self) -> StreamResult<ReplicationMessage> {
+        loop {
+            match self.client.read().await? {
+                Backend::Message(Message::CopyData(body)) => {
+                    let mut reader = Reader::new(body.data());
+                    match reader.u8()? {
+                        b'w' => {
+                            let lsn = Lsn(reader.u64()?);
+                            let end = Lsn(reader.u64()?);
+                            let _sent_at = reader.u64()?;
+                            return Ok(ReplicationMessage::XLogData {
+                                lsn,
+                                end,
+                                data: reader.rest().to_vec(),
+                            });
+                        }
+                        b'k' => {
+                            let end = Lsn(reader.u64()?);
+                            let _sent_at = reader.u64()?;
+                            let reply = reader.u8()? == 1;
+                            return Ok(ReplicationMessage::Keepalive { end, reply });
+                        }
+                        _ => {}
+                    }
+                }
+                Backend::Message(Message::ErrorResponse(body)) => return Err(server_error(&body)),
+                Backend::Message(Message::CopyDone) => {
+                    return Err(StreamError::Runtime(
+                        "Postgres ended the replication stream".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Confirm to the server that changes up to `flushed` were handled, so
+    /// it can release the log before it
+    pub async fn send_status(&mut self, received: Lsn, flushed: Lsn) -> StreamResult<()> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as i64
+            - PG_EPOCH_MICROS;
+        let mut body = vec![b'r'];
+        body.extend_from_slice(&received.0.to_be_bytes());
+        body.extend_from_slice(&flushed.0.to_be_bytes());
+        body.extend_from_slice(&flushed.0.to_be_bytes());
+        body.extend_from_slice(&now.to_be_bytes());
+        body.push(0);
+        let mut buf = BytesMut::new();
+        frontend::CopyData::new(&body[..])?.write(&mut buf);
+        self.client.write(&buf).await
+    }
+
+    /// Close the connection
+    pub async fn close(&mut self) -> StreamResult<()> {
+        self.client.close().await
+    }
+}
+
+/// A message of the server. `postgres-protocol` doesn't know the response
+/// that starts the replication stream, so it's told apart here.
+enum Backend {
+    Message(Message),
+    CopyBoth,
+}
+
+/// The byte stream of a connection, encrypted or not
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Connection for S {}
+
+/// A connection to a Postgres server running simple queries
+struct PostgresClient {
+    stream: Box<dyn Connection>,
+    /// Whether the connection is encrypted
+    tls: bool,
+    buffer: BytesMut,
+}
+
+impl PostgresClient {
+    /// Connect to the database of the options with extra startup
+    /// parameters, authenticating with a password, MD5 or SCRAM-SHA-256
+    async fn connect(options: &PostgresOptions, parameters: &[(&str, &str)]) -> StreamResult<Self> {
+        let (username, password) = match &options.auth {
+            AuthConfig::None => ("postgres", None),
+            AuthConfig::Basic { username, password } => {
+                (username.as_str(), Some(password.as_str()))
+            }
+            _ => {
+                return Err(StreamError::Config(
+                    "Postgres only supports username and password authentication".to_string(),
+                ));
+            }
+        };
+
+        let mut client = Self::open(options).await?;
+
+        let mut buf = BytesMut::new();
+        let startup = [("user", username), ("database", options.database.as_str())]
+            .into_iter()
+            .chain(parameters.iter().copied());
+        frontend::startup_message(startup, &mut buf)?;
+        client.write(&buf).await?;
+
+        client.authenticate(username, password).await?;
+        client.wait_ready().await?;
+        Ok(client)
+    }
+
+    /// Open the connection, asking the server for TLS unless the options
+    /// disable it
+    async fn open(options: &PostgresOptions) -> StreamResult<Self> {
+        let mut stream = TcpStream::connect((options.host.as_str(), options.port)).await?;
+        if options.ssl_mode != SslMode::Disable {
+            let mut buf = BytesMut::new();
+            frontend::ssl_request(&mut buf);
+            stream.write_all(&buf).await?;
+            match stream.read_u8().await? {
+                b'S' => {
+                    let connector = tokio_native_tls::TlsConnector::from(
+                        options.tls.native_tls_connector().await?,
+                    );
+                    let stream = connector
+                        .connect(&options.host, stream)
+                        .await
+                        .map_err(|e| {
+                            StreamError::Runtime(format!("Postgres TLS handshake failed: {}", e))
+                        })?;
+                    return Ok(Self {
+                        stream: Box::new(stream),
+                        tls: true,
+                        buffer: BytesMut::new(),
+                    });
+                }
+                b'N' if options.ssl_mode == SslMode::Prefer => {}
+                b'N' => {
+                    return Err(StreamError::Config(
+                        "the Postgres server does not support TLS".to_string(),
+                    ));
+                }
+                other => {
+                    return Err(protocol_error(format!(
+                        "unexpected response {:?} to the TLS request",
+                        other as char
+                    )));
+                }
+            }
+        }
+        Ok(Self {
+            stream: Box::new(stream),
+            tls: false,
+            buffer: BytesMut::new(),
+        })
+    }
+
+    async fn authenticate(&mut self, username: &str, password: Option<&str>) -> StreamResult<()> {
+        let password_required =
+            || StreamError::Config("the Postgres server requires a password".to_string());
+        let mut scram: Option<ScramSha256> = None;
+        loop {
+            let mut buf = BytesMut::new();
+            match self.read_message().await? {
+                Message::AuthenticationOk => return Ok(()),
+                Message::AuthenticationCleartextPassword => {
+                    if !self.tls {
+                        return Err(StreamError::Config(
+                            "refusing to send the Postgres password in cleartext without TLS"
+                                .to_string(),
+                        ));
+                    }
+                    let password = password.ok_or_else(password_required)?;
+                    frontend::password_message(password.as_bytes(), &mut buf)?;
+                }
+                Message::AuthenticationMd5Password(body) => {
+                    let password = password.ok_or_else(password_required)?;
+                    let hash = md5_hash(username.as_bytes(), password.as_bytes(), body.salt());
+                    frontend::password_message(hash.as_bytes(), &mut buf)?;
+                }
+                Message::AuthenticationSasl(body) => {
+                    let mechanisms: Vec<&str> =
+                        body.mechanisms().collect().map_err(protocol_error)?;
+                    if !mechanisms.contains(&SCRAM_SHA_256) {
+                        return Err(StreamError::Config(format!(
+                            "unsupported Postgres SASL mechanisms {:?}",
+                            mechanisms
+                        )));
+                    }
+                    let password = password.ok_or_else(password_required)?;
+                    let state =
+                        ScramSha256::new(password.as_bytes(), ChannelBinding::unsupported());
+                    frontend::sasl_initial_response(SCRAM_SHA_256, state.message(), &mut buf)?;
+                    scram = Some(state);
+                }
+                Message::AuthenticationSaslContinue(body) => {
+                    let state = scram
+                        .as_mut()
+                        .ok_or_else(|| protocol_error("SASL continue before start"))?;
+                    state.update(body.data()).map_err(protocol_error)?;
+                    frontend::sasl_response(state.message(), &mut buf)?;
+                }
+                Message::AuthenticationSaslFinal(body) => {
+                    let state = scram
+                        .as_mut()
+                        .ok_or_else(|| protocol_error("SASL final before start"))?;
+                    // Checks the signature of the server, proving it knows
+                    // the password
+                    state.finish(body.data()).map_err(|_| {
+                        StreamError::Config(
+                            "the Postgres server failed SCRAM authentication".to_string(),
+                        )
+                    })?;
+                }
+                Message::ErrorResponse(body) => return Err(server_error(&body)),
+                _ => {
+                    return Err(StreamError::Config(
+                        "unsupported Postgres authentication method".to_string(),
+                    ));
+                }
+            }
+            if !buf.is_empty() {
+                self.write(&buf).await?;
+            }
+        }
+    }
+
+    /// Close the connection
+    pub async fn close(&mut self) -> StreamResult<()> {
+        let mut buf = BytesMut::new();
+        frontend::terminate(&mut buf);
+        self.write(&buf).await?;
+        self.stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// Run a command, failing with the error of the server
+    pub async fn execute(&mut self, query: &str) -> StreamResult<()> {
+        self.send_query(query).await?;
+        let mut error = None;
+        loop {
+            match self.read().await? {
+                Backend::Message(Message::ErrorResponse(body)) => error = Some(server_error(&body)),
+                Backend::Message(Message::ReadyForQuery(_)) => return error.map_or(Ok(()), Err),
+                _ => {}
+            }
+        }
+    }
+
+    async fn send_query(&mut self, query: &str) -> StreamResult<()> {
+        let mut buf = BytesMut::new();
+        frontend::query(query, &mut buf)?;
+        self.write(&buf).await
+    }
+
+    async fn wait_ready(&mut self) -> StreamResult<()> {
+        loop {
+            match self.read_message().await? {
+                Message::ReadyForQuery(_) => return Ok(()),
+                Message::ErrorResponse(body) => return Err(server_error(&body)),
+                _ => {}
+            }
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> StreamResult<()> {
+        self.stream.write_all(bytes).await?;
+        Ok(())
+    }
+
+    /// Read the next message, failing on the start of a replication stream
+    async fn read_message(&mut self) -> StreamResult<Message> {
+        match self.read().await? {
+            Backend::Message(message) => Ok(message),
+            Backend::CopyBoth => Err(protocol_error("unexpected replication stream")),
+        }
+    }
+
+    async fn read(&mut self) -> StreamResult<Backend> {
+        loop {
+            let header = Header::parse(&self.buffer).map_err(protocol_error)?;
+            if let Some(header) = header
+                && header.tag() == b'W'
+            {
+                let len = header.len() as usize + 1;
+                if self.buffer.len() >= len {
+                    let _ = self.buffer.split_to(len);
+                    return Ok(Backend::CopyBoth);
+                }
+            } else if let Some(message) =
+                Message::parse(&mut self.buffer).map_err(protocol_error)?
+            {
+                return Ok(Backend::Message(message));
+            }
+            if self.stream.read_buf(&mut self.buffer).await? == 0 {
+                return Err(StreamError::Runtime(
+                    "Postgres connection closed".to_string(),
+                ));
+            }
+        }
+    }
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The message and SQLSTATE of an error response
+fn server_error(body: &ErrorResponseBody) -> StreamError {
+    let (mut code, mut message) = (String::new(), String::new());
+    let mut fields = body.fields();
+    while let Ok(Some(field)) = fields.next() {
+        match field.type_() {
+            b'C' => code = String::from_utf8_lossy(field.value_bytes()).into_owned(),
+            b'M' => message = String::from_utf8_lossy(field.value_bytes()).into_owned(),
+            _ => {}
+        }
+    }
+    StreamError::Runtime(format!("Postgres error {}: {}", code, message))
+}
@@ -0,0 +1,375 @@
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::models::{StreamError, StreamResult};
+
+/// Data type of a column in a schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    Boolean,
+    Int64,
+    Float64,
+    String,
+    /// Milliseconds since the unix epoch
+    Timestamp,
+}
+
+/// A single dynamically typed value
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Boolean(bool),
+    Int64(i64),
+    Float64(f64),
+    String(String),
+    Timestamp(i64),
+}
+
+impl Value {
+    /// Get the data type of this value, `None` for nulls
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            Value::Null => None,
+            Value::Boolean(_) => Some(DataType::Boolean),
+            Value::Int64(_) => Some(DataType::Int64),
+            Value::Float64(_) => Some(DataType::Float64),
+            Value::String(_) => Some(DataType::String),
+            Value::Timestamp(_) => Some(DataType::Timestamp),
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Value::Int64(v) | Value::Timestamp(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Get the value as a float, widening integers
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Float64(v) => Some(*v),
+            Value::Int64(v) => Some(*v as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Parse a textual value (e.g. a CSV cell) into the given type.
+    /// Empty strings are treated as null.
+    pub fn parse(text: &str, data_type: DataType) -> StreamResult<Self> {
+        if text.is_empty() {
+            return Ok(Value::Null);
+        }
+        let invalid =
+            || StreamError::Serialization(format!("cannot parse '{}' as {:?}", text, data_type));
+        Ok(match data_type {
+            DataType::Boolean => Value::Boolean(text.parse().map_err(|_| invalid())?),
+            DataType::Int64 => Value::Int64(text.parse().map_err(|_| invalid())?),
+            DataType::Float64 => Value::Float64(text.parse().map_err(|_| invalid())?),
+            DataType::String => Value::String(text.to_string()),
+            DataType::Timestamp => Value::Timestamp(text.parse().map_err(|_| invalid())?),
+        })
+    }
+
+    /// Convert a JSON value into the given type
+    pub fn from_json(json: &serde_json::Value, data_type: DataType) -> StreamResult<Self> {
+        use serde_json::Value as Json;
+        let value = match (json, data_type) {
+            (Json::Null, _) => Some(Value::Null),
+            (Json::Bool(v), DataType::Boolean) => Some(Value::Boolean(*v)),
+            (Json::Number(n), DataType::Int64) => n.as_i64().map(Value::Int64),
+            (Json::Number(n), DataType::Float64) => n.as_f64().map(Value::Float64),
+            (Json::Number(n), DataType::Timestamp) => n.as_i64().map(Value::Timestamp),
+            (Json::String(s), DataType::String) => Some(Value::String(s.clone())),
+            (Json::String(s), _) => Some(Value::parse(s, data_type)?),
+            _ => None,
+        };
+        value.ok_or_else(|| {
+            StreamError::Serialization(format!("cannot convert {} to {:?}", json, data_type))
+        })
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Boolean(v) => (*v).into(),
+            Value::Int64(v) | Value::Timestamp(v) => (*v).into(),
+            Value::Float64(v) => (*v).into(),
+            Value::String(v) => v.clone().into(),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Boolean(v) => write!(f, "{}", v),
+            Value::Int64(v) | Value::Timestamp(v) => write!(f, "{}", v),
+            Value::Float64(v) => write!(f, "{}", v),
+            Value::String(v) => write!(f, "{}", v),
+        }
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_none(),
+            Value::Boolean(v) => serializer.serialize_bool(*v),
+            Value::Int64(v) | Value::Timestamp(v) => serializer.serialize_i64(*v),
+            Value::Float64(v) => serializer.serialize_f64(*v),
+            Value::String(v) => serializer.serialize_str(v),
+        }
+    }
+}
+
+/// A named, typed column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub data_type: DataType,
+    pub nullable: bool,
+}
+
+impl Field {
+    /// Create a new nullable field
+    pub fn new<S: Into<String>>(name: S, data_type: DataType) -> Self {
+        Self {
+            name: name.into(),
+            data_type,
+            nullable: true,
+        }
+    }
+
+    /// Mark the field as required (non-nullable)
+    pub fn required(mut self) -> Self {
+        self.nullable = false;
+        self
+    }
+
+    /// Check that a value can be stored in this field
+    pub fn check(&self, value: &Value) -> StreamResult<()> {
+        match value.data_type() {
+            None if !self.nullable => Err(StreamError::Serialization(format!(
+                "field '{}' is not nullable",
+                self.name
+            ))),
+            Some(t) if t != self.data_type => Err(StreamError::Serialization(format!(
+                "field '{}' expects {:?}, got {:?}",
+                self.name, self.data_type, t
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Ordered set of fields describing the layout of a row
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    fields: Vec<Field>,
+    index: HashMap<String, usize>,
+}
+
+impl Schema {
+    /// Create a new schema from a list of fields
+    pub fn new(fields: Vec<Field>) -> Self {
+        let index = fields
+            .iter()
+            .enumerate()
+            .map(|(i, f)| (f.name.clone(), i))
+            .collect();
+        Self { fields, index }
+    }
+
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Get the position of a field by name
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.index.get(name).copied()
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.index_of(name).map(|i| &self.fields[i])
+    }
+
+    /// Check that the values match the field types and nullability
+    pub fn validate(&self, values: &[Value]) -> StreamResult<()> {
+        if values.len() != self.fields.len() {
+            return Err(StreamError::Serialization(format!(
+                "expected {} values, got {}",
+                self.fields.len(),
+                values.len()
+            )));
+        }
+        self.fields
+            .iter()
+            .zip(values)
+            .try_for_each(|(field, value)| field.check(value))
+    }
+}
+
+/// A structured record whose layout is described by a shared schema
+#[derive(Debug, Clone)]
+pub struct Row {
+    schema: Arc<Schema>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    /// Create a new row, validating the values against the schema
+    pub fn new(schema: Arc<Schema>, values: Vec<Value>) -> StreamResult<Self> {
+        schema.validate(&values)?;
+        Ok(Self { schema, values })
+    }
+
+    /// Parse a row from CSV cells in schema order
+    pub fn from_csv_record(schema: Arc<Schema>, record: &csv::StringRecord) -> StreamResult<Self> {
+        let values = schema
+            .fields()
+            .iter()
+            .enumerate()
+            .map(|(i, field)| Value::parse(record.get(i).unwrap_or(""), field.data_type))
+            .collect::<StreamResult<Vec<_>>>()?;
+        Self::new(schema, values)
+    }
+
+    /// Parse a row from a single CSV line
+    pub fn from_csv_line(schema: Arc<Schema>, line: &str) -> StreamResult<Self> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(line.as_bytes());
+        let record = reader.records().next().transpose()?.unwrap_or_default();
+        Self::from_csv_record(schema, &record)
+    }
+
+    /// Build a row from a JSON object, missing fields become null
+    pub fn from_json(schema: Arc<Schema>, json: &serde_json::Value) -> StreamResult<Self> {
+        let object = json.as_object().ok_or_else(|| {
+            StreamError::Serialization(format!("expected JSON object, got {}", json))
+        })?;
+        let values = schema
+            .fields()
+            .iter()
+            .map(|field| match object.get(&field.name) {
+                Some(v) => Value::from_json(v, field.data_type),
+                None => Ok(Value::Null),
+            })
+            .collect::<StreamResult<Vec<_>>>()?;
+        Self::new(schema, values)
+    }
+
+    /// Convert the row into a JSON object keyed by field name
+    pub fn to_json(&self) -> serde_json::Value {
+        let object = self
+            .schema
+            .fields()
+            .iter()
+            .zip(&self.values)
+            .map(|(field, value)| (field.name.clone(), value.to_json()))
+            .collect();
+        serde_json::Value::Object(object)
+    }
+
+    pub fn schema(&self) -> &Arc<Schema> {
+        &self.schema
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Get a value by column index
+    pub fn get(&self, index: usize) -> Option<&Value> {
+        self.values.get(index)
+    }
+
+    /// Get a value by field name
+    pub fn get_by_name(&self, name: &str) -> Option<&Value> {
+        self.schema.index_of(name).map(|i| &self.values[i])
+    }
+
+    /// Whether the named field is null or missing from the schema
+    pub fn is_null(&self, name: &str) -> bool {
+        self.get_by_name(name).is_none_or(Value::is_null)
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        self.get_by_name(name).and_then(Value::as_bool)
+    }
+
+    pub fn get_i64(&self, name: &str) -> Option<i64> {
+        self.get_by_name(name).and_then(Value::as_i64)
+    }
+
+    pub fn get_f64(&self, name: &str) -> Option<f64> {
+        self.get_by_name(name).and_then(Value::as_f64)
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.get_by_name(name).and_then(Value::as_str)
+    }
+
+    /// Replace the value of a field, checking it against the schema
+    pub fn set(&mut self, name: &str, value: Value) -> StreamResult<()> {
+        let index = self
+            .schema
+            .index_of(name)
+            .ok_or_else(|| StreamError::Serialization(format!("unknown field '{}'", name)))?;
+        self.schema.fields()[index].check(&value)?;
+        self.values[index] = value;
+        Ok(())
+    }
+}
+
+impl PartialEq for Row {
+    fn eq(&self, other: &Self) -> bool {
+        self.schema.fields() == other.schema.fields() && self.values == other.values
+    }
+}
+
+impl Serialize for Row {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.values.len()))?;
+        for (field, value) in self.schema.fields().iter().zip(&self.values) {
+            map.serialize_entry(&field.name, value)?;
+        }
+        map.end()
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_json())
+    }
+}
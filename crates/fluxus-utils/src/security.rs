@@ -0,0 +1,395 @@
+//! TLS and credential settings shared by network connectors
+//!
+//! They are defined here rather than in fluxus-core because the connectors
+//! in fluxus-sources and fluxus-sinks take them, and fluxus-core depends on
+//! those crates. fluxus-core re-exports them from its config module.
+
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use percent_encoding::percent_decode_str;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::SystemTime;
+use url::Url;
+
+use crate::models::{StreamError, StreamResult};
+
+/// TLS options shared by network connectors
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    /// PEM file with additional trusted root certificates
+    pub ca_cert: Option<PathBuf>,
+    /// PEM file with the client certificate chain
+    pub client_cert: Option<PathBuf>,
+    /// PEM file with the PKCS#8 private key of the client certificate
+    pub client_key: Option<PathBuf>,
+    /// Accept invalid server certificates, for testing only
+    pub accept_invalid_certs: bool,
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust the root certificates in the given PEM file
+    pub fn with_ca_cert<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.ca_cert = Some(path.into());
+        self
+    }
+
+    /// Authenticate with a client certificate and its private key
+    pub fn with_client_cert<P: Into<PathBuf>>(mut self, cert: P, key: P) -> Self {
+        self.client_cert = Some(cert.into());
+        self.client_key = Some(key.into());
+        self
+    }
+
+    /// Skip verification of the server certificate
+    pub fn with_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+
+    /// Read the PEM files of the trusted root certificates and of the client
+    /// identity
+    #[cfg(any(feature = "amqp", feature = "native-tls"))]
+    pub(crate) async fn read_pem(&self) -> StreamResult<TlsPem> {
+        let ca_cert = match &self.ca_cert {
+            Some(path) => Some(tokio::fs::read(path).await?),
+            None => None,
+        };
+        let identity = match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => {
+                Some((tokio::fs::read(cert).await?, tokio::fs::read(key).await?))
+            }
+            _ => None,
+        };
+        Ok(TlsPem { ca_cert, identity })
+    }
+
+    /// Build a native TLS connector honouring the options
+    #[cfg(feature = "native-tls")]
+    pub(crate) async fn native_tls_connector(&self) -> StreamResult<native_tls::TlsConnector> {
+        let pem = self.read_pem().await?;
+        let mut builder = native_tls::TlsConnector::builder();
+        builder.danger_accept_invalid_certs(self.accept_invalid_certs);
+        if let Some(ca_cert) = &pem.ca_cert {
+            let cert = native_tls::Certificate::from_pem(ca_cert)
+                .map_err(|e| StreamError::Config(format!("invalid CA certificate: {}", e)))?;
+            builder.add_root_certificate(cert);
+        }
+        if let Some((cert, key)) = &pem.identity {
+            let identity = native_tls::Identity::from_pkcs8(cert, key)
+                .map_err(|e| StreamError::Config(format!("invalid client certificate: {}", e)))?;
+            builder.identity(identity);
+        }
+        builder
+            .build()
+            .map_err(|e| StreamError::Config(format!("invalid TLS configuration: {}", e)))
+    }
+}
+
+/// The contents of the PEM files of a [`TlsConfig`]
+#[cfg(any(feature = "amqp", feature = "native-tls"))]
+pub(crate) struct TlsPem {
+    pub ca_cert: Option<Vec<u8>>,
+    /// The client certificate chain and its private key
+    pub identity: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// SASL mechanisms supported by message-queue connectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaslMechanism {
+    Plain,
+    ScramSha256,
+    ScramSha512,
+}
+
+/// Credentials shared by network connectors
+///
+/// The `Debug` output redacts passwords, tokens and secret keys.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub enum AuthConfig {
+    #[default]
+    None,
+    /// HTTP basic authentication
+    Basic { username: String, password: String },
+    /// Bearer token sent in the `Authorization` header
+    Bearer(String),
+    /// SASL username/password authentication
+    Sasl {
+        mechanism: SaslMechanism,
+        username: String,
+        password: String,
+    },
+    /// AWS Signature Version 4 request signing
+    AwsSigV4 {
+        access_key: String,
+        secret_key: String,
+        session_token: Option<String>,
+        region: String,
+        service: String,
+    },
+}
+
+const REDACTED: &str = "<redacted>";
+
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "None"),
+            Self::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .field("password", &REDACTED)
+                .finish(),
+            Self::Bearer(_) => f.debug_tuple("Bearer").field(&REDACTED).finish(),
+            Self::Sasl {
+                mechanism,
+                username,
+                ..
+            } => f
+                .debug_struct("Sasl")
+                .field("mechanism", mechanism)
+                .field("username", username)
+                .field("password", &REDACTED)
+                .finish(),
+            Self::AwsSigV4 {
+                access_key,
+                session_token,
+                region,
+                service,
+                ..
+            } => f
+                .debug_struct("AwsSigV4")
+                .field("access_key", access_key)
+                .field("secret_key", &REDACTED)
+                .field("session_token", &session_token.as_ref().map(|_| REDACTED))
+                .field("region", region)
+                .field("service", service)
+                .finish(),
+        }
+    }
+}
+
+impl AuthConfig {
+    pub fn basic<S: Into<String>>(username: S, password: S) -> Self {
+        Self::Basic {
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    pub fn bearer<S: Into<String>>(token: S) -> Self {
+        Self::Bearer(token.into())
+    }
+
+    pub fn sasl<S: Into<String>>(mechanism: SaslMechanism, username: S, password: S) -> Self {
+        Self::Sasl {
+            mechanism,
+            username: username.into(),
+            password: password.into(),
+        }
+    }
+
+    pub fn aws_sigv4<S: Into<String>>(access_key: S, secret_key: S, region: S, service: S) -> Self {
+        Self::AwsSigV4 {
+            access_key: access_key.into(),
+            secret_key: secret_key.into(),
+            session_token: None,
+            region: region.into(),
+            service: service.into(),
+        }
+    }
+
+    /// Headers to attach to an HTTP request with an empty body
+    pub fn http_headers(&self, method: &str, url: &str) -> StreamResult<Vec<(String, String)>> {
+        self.http_headers_at(method, url, SystemTime::now())
+    }
+
+    /// Same as [`AuthConfig::http_headers`], signing with the given request time
+    pub fn http_headers_at(
+        &self,
+        method: &str,
+        url: &str,
+        time: SystemTime,
+    ) -> StreamResult<Vec<(String, String)>> {
+        match self {
+            Self::None => Ok(vec![]),
+            Self::Basic { username, password } => {
+                let credentials = base64::engine::general_purpose::STANDARD
+                    .encode(format!("{}:{}", username, password));
+                Ok(vec![(
+                    "authorization".to_string(),
+                    format!("Basic {}", credentials),
+                )])
+            }
+            Self::Bearer(token) => Ok(vec![(
+                "authorization".to_string(),
+                format!("Bearer {}", token),
+            )]),
+            Self::Sasl { .. } => Err(StreamError::Config(
+                "SASL authentication is not supported over HTTP".to_string(),
+            )),
+            Self::AwsSigV4 {
+                access_key,
+                secret_key,
+                session_token,
+                region,
+                service,
+            } => {
+                let url = Url::parse(url).map_err(|e| StreamError::Config(e.to_string()))?;
+                let signer = SigV4 {
+                    access_key,
+                    secret_key,
+                    session_token: session_token.as_deref(),
+                    region,
+                    service,
+                };
+                signer.sign(method, &url, time.into())
+            }
+        }
+    }
+}
+
+struct SigV4<'a> {
+    access_key: &'a str,
+    secret_key: &'a str,
+    session_token: Option<&'a str>,
+    region: &'a str,
+    service: &'a str,
+}
+
+impl SigV4<'_> {
+    /// The URI-encoded path of the URL. S3 encodes the decoded segments
+    /// once, other services encode the segments as sent, which are already
+    /// encoded, again and remove empty segments.
+    fn canonical_uri(&self, url: &Url) -> String {
+        let s3 = self.service == "s3";
+        let path = url.path();
+        let segments: Vec<String> = path
+            .split('/')
+            .skip(1)
+            .filter(|segment| s3 || !segment.is_empty())
+            .map(|segment| {
+                if s3 {
+                    uri_encode(&percent_decode_str(segment).collect::<Vec<u8>>())
+                } else {
+                    uri_encode(segment.as_bytes())
+                }
+            })
+            .collect();
+
+        let mut uri = format!("/{}", segments.join("/"));
+        if !s3 && path.ends_with('/') && !segments.is_empty() {
+            uri.push('/');
+        }
+        uri
+    }
+
+    fn sign(
+        &self,
+        method: &str,
+        url: &Url,
+        time: DateTime<Utc>,
+    ) -> StreamResult<Vec<(String, String)>> {
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(StreamError::Config(format!("URL has no host: {}", url))),
+        };
+        let amz_date = time.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = time.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(Sha256::digest(b""));
+
+        // Canonical headers must be sorted by name
+        let mut headers = vec![
+            ("host".to_string(), host),
+            ("x-amz-date".to_string(), amz_date.clone()),
+        ];
+        if self.service == "s3" {
+            headers.push(("x-amz-content-sha256".to_string(), payload_hash.clone()));
+        }
+        if let Some(token) = self.session_token {
+            headers.push(("x-amz-security-token".to_string(), token.to_string()));
+        }
+        headers.sort();
+
+        let canonical_headers: String = headers
+            .iter()
+            .map(|(k, v)| format!("{}:{}\n", k, v.trim()))
+            .collect();
+        let signed_headers = headers
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let mut query: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(k, v)| (uri_encode(k.as_bytes()), uri_encode(v.as_bytes())))
+            .collect();
+        query.sort();
+        let canonical_query = query
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\n{}",
+            method.to_ascii_uppercase(),
+            self.canonical_uri(url),
+            canonical_query,
+            canonical_headers,
+            signed_headers,
+            payload_hash
+        );
+
+        let scope = format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        let key = [date.as_str(), self.region, self.service, "aws4_request"]
+            .iter()
+            .fold(
+                format!("AWS4{}", self.secret_key).into_bytes(),
+                |key, part| hmac_sha256(&key, part.as_bytes()),
+            );
+        let signature = hex::encode(hmac_sha256(&key, string_to_sign.as_bytes()));
+
+        headers.retain(|(k, _)| k != "host");
+        headers.push((
+            "authorization".to_string(),
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                self.access_key, scope, signed_headers, signature
+            ),
+        ));
+        Ok(headers)
+    }
+}
+
+pub(crate) fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn uri_encode(input: &[u8]) -> String {
+    input
+        .iter()
+        .map(|&b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
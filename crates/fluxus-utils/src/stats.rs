@@ -0,0 +1,301 @@
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use serde::Serialize;
+
+/// Count, sum, extremes, mean and variance of a series of numbers, updated
+/// incrementally as numbers are added.
+///
+/// The mean and variance are updated with Welford's algorithm, which stays
+/// accurate for long series of large numbers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Stats {
+    count: u64,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+    mean: f64,
+    /// Sum of the squared differences from the mean
+    m2: f64,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a number to the series
+    pub fn add(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    /// Combine with the statistics of another series, e.g. of another task
+    pub fn merge(self, other: Stats) -> Stats {
+        if self.count == 0 {
+            return other;
+        }
+        if other.count == 0 {
+            return self;
+        }
+        let count = self.count + other.count;
+        let delta = other.mean - self.mean;
+        Stats {
+            count,
+            sum: self.sum + other.sum,
+            min: self.min.zip(other.min).map(|(a, b)| a.min(b)),
+            max: self.max.zip(other.max).map(|(a, b)| a.max(b)),
+            mean: self.mean + delta * other.count as f64 / count as f64,
+            m2: self.m2
+                + other.m2
+                + delta * delta * self.count as f64 * other.count as f64 / count as f64,
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn sum(&self) -> f64 {
+        self.sum
+    }
+
+    /// Smallest number, `None` for an empty series
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    /// Largest number, `None` for an empty series
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+
+    /// Arithmetic mean, `None` for an empty series
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.mean)
+    }
+
+    /// Population variance, `None` for an empty series
+    pub fn variance(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.m2 / self.count as f64)
+    }
+
+    /// Population standard deviation, `None` for an empty series
+    pub fn stddev(&self) -> Option<f64> {
+        self.variance().map(f64::sqrt)
+    }
+}
+
+impl FromIterator<f64> for Stats {
+    fn from_iter<I: IntoIterator<Item = f64>>(iter: I) -> Self {
+        let mut stats = Stats::new();
+        for value in iter {
+            stats.add(value);
+        }
+        stats
+    }
+}
+
+/// Approximate quantiles of a series of numbers in bounded memory, using
+/// the DDSketch algorithm.
+///
+/// Numbers are counted in buckets whose bounds grow exponentially, so that
+/// every quantile is within the relative accuracy of the exact value, and
+/// the number of buckets only grows with the logarithm of the range.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantileSketch {
+    gamma: f64,
+    ln_gamma: f64,
+    positive: BTreeMap<i32, u64>,
+    negative: BTreeMap<i32, u64>,
+    zeros: u64,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        Self::new(0.01)
+    }
+}
+
+impl QuantileSketch {
+    /// Numbers closer to zero than this are counted as zero
+    const MIN_INDEXABLE: f64 = 1e-9;
+
+    /// A sketch whose quantiles are within `relative_accuracy` of the exact
+    /// value, e.g. 0.01 for 1%
+    pub fn new(relative_accuracy: f64) -> Self {
+        let accuracy = relative_accuracy.clamp(1e-6, 0.5);
+        let gamma = (1.0 + accuracy) / (1.0 - accuracy);
+        Self {
+            gamma,
+            ln_gamma: gamma.ln(),
+            positive: BTreeMap::new(),
+            negative: BTreeMap::new(),
+            zeros: 0,
+            count: 0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    fn index(&self, value: f64) -> i32 {
+        (value.ln() / self.ln_gamma).ceil() as i32
+    }
+
+    /// Estimate of the numbers in the bucket with the given index
+    fn value(&self, index: i32) -> f64 {
+        2.0 * self.gamma.powi(index) / (self.gamma + 1.0)
+    }
+
+    /// Add a number to the series, ignoring NaN
+    pub fn add(&mut self, value: f64) {
+        if value.is_nan() {
+            return;
+        }
+        if value > Self::MIN_INDEXABLE {
+            *self.positive.entry(self.index(value)).or_default() += 1;
+        } else if value < -Self::MIN_INDEXABLE {
+            *self.negative.entry(self.index(-value)).or_default() += 1;
+        } else {
+            self.zeros += 1;
+        }
+        self.count += 1;
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    /// Combine with the sketch of another series with the same accuracy
+    pub fn merge(mut self, other: QuantileSketch) -> QuantileSketch {
+        for (index, count) in other.positive {
+            *self.positive.entry(index).or_default() += count;
+        }
+        for (index, count) in other.negative {
+            *self.negative.entry(index).or_default() += count;
+        }
+        self.zeros += other.zeros;
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Approximate `q`-quantile for `q` between 0 and 1, `None` for an empty
+    /// series
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (q.clamp(0.0, 1.0) * (self.count - 1) as f64).floor() as u64;
+        let mut seen = 0;
+        // Negative numbers from the largest magnitude, zeros, then positive numbers
+        let buckets = self
+            .negative
+            .iter()
+            .rev()
+            .map(|(index, count)| (-self.value(*index), *count))
+            .chain(std::iter::once((0.0, self.zeros)))
+            .chain(
+                self.positive
+                    .iter()
+                    .map(|(index, count)| (self.value(*index), *count)),
+            );
+        for (value, count) in buckets {
+            seen += count;
+            if seen > rank {
+                return Some(value.clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+/// Approximate number of distinct values in a series, using the
+/// HyperLogLog algorithm.
+///
+/// Only `2^precision` one-byte registers are kept however many values are
+/// added, and the estimate is typically within `1.04 / sqrt(2^precision)`
+/// of the exact count, about 1.6% for the default precision of 12.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HyperLogLog {
+    precision: u32,
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        Self::new(12)
+    }
+}
+
+impl HyperLogLog {
+    /// A sketch with `2^precision` registers, for a precision between 4 and 18
+    pub fn new(precision: u32) -> Self {
+        let precision = precision.clamp(4, 18);
+        Self {
+            precision,
+            registers: vec![0; 1 << precision],
+        }
+    }
+
+    /// Add a value to the series
+    pub fn add<T: Hash + ?Sized>(&mut self, value: &T) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        self.add_hash(hasher.finish());
+    }
+
+    /// Add a value by its 64-bit hash
+    pub fn add_hash(&mut self, hash: u64) {
+        let index = (hash >> (64 - self.precision)) as usize;
+        // Position of the first set bit after the index bits, with a stop
+        // bit so that it is at most 64 - precision + 1
+        let rest = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// Combine with the sketch of another series with the same precision
+    pub fn merge(mut self, other: HyperLogLog) -> HyperLogLog {
+        for (register, other) in self.registers.iter_mut().zip(other.registers) {
+            *register = (*register).max(other);
+        }
+        self
+    }
+
+    /// Estimated number of distinct values
+    pub fn count(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|register| 2f64.powi(-(*register as i32)))
+            .sum();
+        let estimate = alpha * m * m / sum;
+
+        // Linear counting is more accurate while many registers are empty
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use serde::Serialize;
 use std::time::Duration;
 
 /// Window type for stream processing
@@ -11,6 +12,9 @@ pub enum WindowType {
     Session(Duration),
     /// Global window, no window boundaries
     Global,
+    /// Window of up to a number of elements that also closes after a
+    /// duration since its first element, whichever comes first
+    CountOrTime(usize, Duration),
 }
 
 /// Configuration for windowed operations
@@ -22,6 +26,12 @@ pub struct WindowConfig {
     pub allow_lateness: Duration,
     /// Watermark strategy (time to wait before processing)
     pub watermark_delay: Duration,
+    /// How long the window state of a key is kept after its latest element
+    /// in event time, for keyed windows
+    pub state_ttl: Option<Duration>,
+    /// How far the boundaries of tumbling and sliding windows are shifted
+    /// from multiples of the size or slide
+    pub offset: Duration,
 }
 
 impl WindowConfig {
@@ -31,6 +41,8 @@ impl WindowConfig {
             window_type: WindowType::Tumbling(size),
             allow_lateness: Duration::from_secs(0),
             watermark_delay: Duration::from_secs(0),
+            state_ttl: None,
+            offset: Duration::ZERO,
         }
     }
 
@@ -40,6 +52,8 @@ impl WindowConfig {
             window_type: WindowType::Sliding(size, slide),
             allow_lateness: Duration::from_secs(0),
             watermark_delay: Duration::from_secs(0),
+            state_ttl: None,
+            offset: Duration::ZERO,
         }
     }
 
@@ -49,15 +63,33 @@ impl WindowConfig {
             window_type: WindowType::Session(gap),
             allow_lateness: Duration::from_secs(0),
             watermark_delay: Duration::from_secs(0),
+            state_ttl: None,
+            offset: Duration::ZERO,
         }
     }
 
-    /// Create a new global window configuration
+    /// Create a new global window configuration, whose single window emits
+    /// when the input ends, or whenever the trigger of the windowed stream
+    /// fires, e.g. at a count or a flush marker
     pub fn global() -> Self {
         Self {
             window_type: WindowType::Global,
             allow_lateness: Duration::from_secs(0),
             watermark_delay: Duration::from_secs(0),
+            state_ttl: None,
+            offset: Duration::ZERO,
+        }
+    }
+
+    /// Create a new window configuration that closes a window after `count`
+    /// elements or `duration` since its first element, whichever comes first
+    pub fn count_or_time(count: usize, duration: Duration) -> Self {
+        Self {
+            window_type: WindowType::CountOrTime(count, duration),
+            allow_lateness: Duration::from_secs(0),
+            watermark_delay: Duration::from_secs(0),
+            state_ttl: None,
+            offset: Duration::ZERO,
         }
     }
 
@@ -67,11 +99,160 @@ impl WindowConfig {
         self
     }
 
+    /// Shift the boundaries of tumbling and sliding windows by `offset`, e.g.
+    /// to start hourly windows at half past or daily windows at local
+    /// midnight. Days in UTC+8 start at 16:00 UTC, so they take an offset of
+    /// 16 hours, and days in UTC-5 one of 5 hours.
+    pub fn with_offset(mut self, offset: Duration) -> Self {
+        self.offset = offset;
+        self
+    }
+
     /// Set the watermark delay for this window
     pub fn with_watermark_delay(mut self, delay: Duration) -> Self {
         self.watermark_delay = delay;
         self
     }
+
+    /// Drop the window state of keys that received no elements for `ttl` of
+    /// event time, without emitting it, to bound the state of keys with
+    /// many distinct values such as IP addresses. Applies to keyed windows,
+    /// e.g. global windows whose keys would otherwise be kept forever.
+    pub fn state_ttl(mut self, ttl: Duration) -> Self {
+        self.state_ttl = Some(ttl);
+        self
+    }
+
+    /// Starts of the windows containing `timestamp`, shifted by the offset
+    pub fn get_affected_windows(&self, timestamp: i64) -> Vec<i64> {
+        let offset_ms = match self.window_type {
+            WindowType::Tumbling(_) | WindowType::Sliding(_, _) => self.offset.as_millis() as i64,
+            _ => 0,
+        };
+        self.window_type
+            .get_affected_windows(timestamp - offset_ms)
+            .into_iter()
+            .map(|start| start + offset_ms)
+            .collect()
+    }
+
+    /// Keys of the windows containing `timestamp`, shifted by the offset
+    pub fn get_window_keys(&self, timestamp: i64) -> Vec<u64> {
+        self.get_affected_windows(timestamp)
+            .iter()
+            .map(|&ts| ts as u64)
+            .collect()
+    }
+}
+
+/// The bounds of a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct Window {
+    /// Start of the window, `None` for the global window
+    pub start: Option<i64>,
+    /// Exclusive end of the window, `None` for the global window
+    pub end: Option<i64>,
+}
+
+impl Window {
+    /// The window with the given key
+    pub fn new(window_type: &WindowType, key: u64) -> Self {
+        Self {
+            start: window_type.window_start(key as i64),
+            end: window_type.window_end(key as i64),
+        }
+    }
+
+    /// The tumbling window of the given size that contains `timestamp`
+    pub fn tumbling(timestamp: i64, size: Duration) -> Self {
+        Self::tumbling_with_offset(timestamp, size, Duration::ZERO)
+    }
+
+    /// The tumbling window of the given size that contains `timestamp`,
+    /// with its boundaries shifted by `offset`, see [`WindowConfig::with_offset`]
+    pub fn tumbling_with_offset(timestamp: i64, size: Duration, offset: Duration) -> Self {
+        let size_ms = size.as_millis() as i64;
+        let offset_ms = offset.as_millis() as i64;
+        let start = (timestamp - offset_ms).div_euclid(size_ms) * size_ms + offset_ms;
+        Self {
+            start: Some(start),
+            end: Some(start + size_ms),
+        }
+    }
+}
+
+/// Assigns records to windows derived from their content rather than from a
+/// [`WindowType`], e.g. window sizes per tenant or the sessions of a trading
+/// calendar.
+///
+/// Closures of a record and its timestamp returning its windows are
+/// assigners.
+pub trait WindowAssigner<T>: Send + Sync {
+    /// The windows of a record with the given timestamp
+    fn assign(&self, value: &T, timestamp: i64) -> Vec<Window>;
+}
+
+impl<T, F> WindowAssigner<T> for F
+where
+    F: Fn(&T, i64) -> Vec<Window> + Send + Sync,
+{
+    fn assign(&self, value: &T, timestamp: i64) -> Vec<Window> {
+        self(value, timestamp)
+    }
+}
+
+/// The result of a window together with the bounds of the window, so that
+/// results can be told apart by window
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowedValue<A> {
+    /// Start of the window, `None` for the global window
+    pub start: Option<i64>,
+    /// Exclusive end of the window, `None` for the global window
+    pub end: Option<i64>,
+    pub value: A,
+    /// Whether the value is the result of the window when it ended, rather
+    /// than an early result of a window that is still open
+    pub is_final: bool,
+}
+
+impl<A> WindowedValue<A> {
+    /// The final value of the window with the given key
+    pub fn new(window_type: &WindowType, key: u64, value: A) -> Self {
+        let window = Window::new(window_type, key);
+        Self {
+            start: window.start,
+            end: window.end,
+            value,
+            is_final: true,
+        }
+    }
+
+    /// An early value of the window with the given key, while it is open
+    pub fn early(window_type: &WindowType, key: u64, value: A) -> Self {
+        Self {
+            is_final: false,
+            ..Self::new(window_type, key, value)
+        }
+    }
+
+    /// The bounds of the window
+    pub fn window(&self) -> Window {
+        Window {
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+/// A closed session of elements, see `DataStream::sessionize`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Session<T> {
+    /// Timestamp of the first element
+    pub start: i64,
+    /// Timestamp of the last element
+    pub end: i64,
+    /// The elements of the session in timestamp order
+    pub events: Vec<T>,
 }
 
 impl WindowType {
@@ -79,24 +260,28 @@ impl WindowType {
         match self {
             WindowType::Tumbling(duration) => {
                 let duration_ms = duration.as_millis() as i64;
-                vec![(timestamp / duration_ms) * duration_ms]
+                vec![timestamp.div_euclid(duration_ms) * duration_ms]
             }
+            // Every window whose start lies in (timestamp - size, timestamp],
+            // in order of their start
             WindowType::Sliding(size, slide) => {
                 let slide_ms = slide.as_millis() as i64;
                 let size_ms = size.as_millis() as i64;
-                let earliest_window = ((timestamp - size_ms) / slide_ms) * slide_ms;
-                let latest_window = (timestamp / slide_ms) * slide_ms;
-
-                (earliest_window..=latest_window)
-                    .step_by(slide.as_millis() as usize)
-                    .filter(|&start| timestamp - start < size_ms)
-                    .collect()
+                let latest_window = timestamp.div_euclid(slide_ms) * slide_ms;
+                let mut windows: Vec<i64> = (0..)
+                    .map(|i| latest_window - i * slide_ms)
+                    .take_while(|&start| timestamp - start < size_ms)
+                    .collect();
+                windows.reverse();
+                windows
             }
             WindowType::Session(gap) => {
                 let gap_ms = gap.as_millis() as i64;
                 vec![timestamp / gap_ms]
             }
-            WindowType::Global => {
+            // Count-or-time windows are opened by their first element rather
+            // than by its timestamp alone
+            WindowType::Global | WindowType::CountOrTime(_, _) => {
                 vec![0]
             }
         }
@@ -106,6 +291,28 @@ impl WindowType {
         self.get_common_windows(timestamp)
     }
 
+    /// Start of the window with the given key, `None` for the global window
+    pub fn window_start(&self, key: i64) -> Option<i64> {
+        match self {
+            WindowType::Tumbling(..) | WindowType::Sliding(..) => Some(key),
+            WindowType::Session(gap) => Some(key * gap.as_millis() as i64),
+            WindowType::Global | WindowType::CountOrTime(_, _) => None,
+        }
+    }
+
+    /// Exclusive end of the window with the given key, `None` for the global
+    /// window, which never ends
+    pub fn window_end(&self, key: i64) -> Option<i64> {
+        match self {
+            WindowType::Tumbling(size) | WindowType::Sliding(size, _) => {
+                Some(key + size.as_millis() as i64)
+            }
+            // Session windows are keyed by their index rather than their start
+            WindowType::Session(gap) => Some((key + 1) * gap.as_millis() as i64),
+            WindowType::Global | WindowType::CountOrTime(_, _) => None,
+        }
+    }
+
     pub fn get_window_keys(&self, timestamp: i64) -> Vec<u64> {
         self.get_common_windows(timestamp)
             .iter()
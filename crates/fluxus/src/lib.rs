@@ -106,6 +106,9 @@ pub mod transformers {
     pub use fluxus_transformers::*;
 }
 
+#[cfg(all(feature = "fluxus-api", feature = "fluxus-utils"))]
+pub mod presets;
+
 #[cfg(feature = "fluxus-utils")]
 pub mod utils {
     pub use fluxus_utils::*;
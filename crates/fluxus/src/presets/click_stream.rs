@@ -0,0 +1,99 @@
+//! Page-view sessions of users
+
+use fluxus_api::DataStream;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct ClickEvent {
+    pub user_id: String,
+    pub page_id: String,
+    pub event_type: String,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserSession {
+    pub user_id: String,
+    pub page_views: Vec<String>,
+    pub start_time: SystemTime,
+    pub duration_secs: u64,
+    pub total_events: usize,
+}
+
+/// Collects the page views of each user per session, emitting a session
+/// once its user was inactive for the gap
+#[derive(Debug, Clone)]
+pub struct ClickSessions {
+    gap: Duration,
+    event_type: String,
+}
+
+impl Default for ClickSessions {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+impl ClickSessions {
+    pub fn new(gap: Duration) -> Self {
+        Self {
+            gap,
+            event_type: "page_view".to_string(),
+        }
+    }
+
+    /// Track events of the given type instead of page views
+    pub fn event_type(mut self, event_type: impl Into<String>) -> Self {
+        self.event_type = event_type.into();
+        self
+    }
+
+    pub fn build(self, events: DataStream<ClickEvent>) -> DataStream<UserSession> {
+        let event_type = self.event_type;
+        events
+            .filter(move |event| event.event_type == event_type)
+            .sessionize(|event| event.user_id.clone(), self.gap)
+            .map(|(user_id, session)| {
+                let start_time = session.events[0].timestamp;
+                let end_time = session.events[session.events.len() - 1].timestamp;
+                UserSession {
+                    user_id,
+                    start_time,
+                    duration_secs: end_time
+                        .duration_since(start_time)
+                        .unwrap_or_default()
+                        .as_secs(),
+                    total_events: session.events.len(),
+                    page_views: session.events.into_iter().map(|e| e.page_id).collect(),
+                }
+            })
+    }
+}
+
+/// Three users visiting the same pages, each page view followed by a click
+pub fn sample_clicks() -> Vec<ClickEvent> {
+    let start_time = SystemTime::now();
+    let mut events = Vec::new();
+    let pages = ["home", "products", "cart", "checkout"];
+    let users = ["user1", "user2", "user3"];
+
+    for (user_idx, user_id) in users.iter().enumerate() {
+        let user_start = start_time + Duration::from_secs(user_idx as u64 * 5);
+        for (i, &page) in pages.iter().enumerate() {
+            events.push(ClickEvent {
+                user_id: user_id.to_string(),
+                page_id: page.to_string(),
+                event_type: "page_view".to_string(),
+                timestamp: user_start + Duration::from_secs(i as u64 * 10),
+            });
+            events.push(ClickEvent {
+                user_id: user_id.to_string(),
+                page_id: page.to_string(),
+                event_type: "click".to_string(),
+                timestamp: user_start + Duration::from_secs(i as u64 * 10 + 2),
+            });
+        }
+    }
+
+    events
+}
@@ -0,0 +1,57 @@
+//! Occurrences of each event per timestamp, for records with event timestamps
+
+use fluxus_api::DataStream;
+use fluxus_utils::models::Record;
+use fluxus_utils::time::current_time;
+use fluxus_utils::window::WindowConfig;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Number of occurrences of each event and timestamp in a window
+pub type EventCount = HashMap<(String, i64), usize>;
+
+/// Counts the events with the same name and timestamp per tumbling window
+#[derive(Debug, Clone)]
+pub struct EventCounter {
+    window: Duration,
+}
+
+impl Default for EventCounter {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(1))
+    }
+}
+
+impl EventCounter {
+    pub fn new(window: Duration) -> Self {
+        Self { window }
+    }
+
+    pub fn build(self, events: DataStream<Record<String>>) -> DataStream<EventCount> {
+        events
+            .window(WindowConfig::tumbling(self.window))
+            .aggregate(HashMap::new(), |mut counts: EventCount, event| {
+                *counts.entry((event.data, event.timestamp)).or_insert(0) += 1;
+                counts
+            })
+    }
+}
+
+/// Logins, clicks and a purchase within 600 ms from now
+pub fn sample_events() -> Vec<Record<String>> {
+    let now = current_time() as i64;
+    [
+        ("login", 0),
+        ("click", 100),
+        ("click", 100),
+        ("click", 100),
+        ("login", 200),
+        ("purchase", 300),
+        ("click", 400),
+        ("click", 400),
+        ("click", 600),
+    ]
+    .into_iter()
+    .map(|(event, offset_ms)| Record::with_timestamp(event.to_string(), now + offset_ms))
+    .collect()
+}
@@ -0,0 +1,172 @@
+//! Per-device status statistics per sliding window
+
+use fluxus_api::DataStream;
+use fluxus_utils::window::WindowConfig;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct IoTData {
+    pub device_id: String,
+    pub device_type: String,
+    pub value: f64,
+    pub battery_level: u8,
+    pub signal_strength: i32,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct DeviceStats {
+    pub device_id: String,
+    pub device_type: String,
+    pub avg_value: f64,
+    pub min_battery: u8,
+    pub avg_signal: i32,
+    pub alert_count: u32,
+    pub last_update: SystemTime,
+}
+
+/// Statistics of each device in a window, by device id
+pub type WindowStats = HashMap<String, DeviceStats>;
+
+/// Aggregates the readings of each device per sliding window, counting
+/// readings with a low battery or a weak signal as alerts
+#[derive(Debug, Clone)]
+pub struct DeviceMonitor {
+    size: Duration,
+    slide: Duration,
+    min_battery: u8,
+    min_signal: i32,
+}
+
+impl Default for DeviceMonitor {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(120), Duration::from_secs(30))
+    }
+}
+
+impl DeviceMonitor {
+    pub fn new(size: Duration, slide: Duration) -> Self {
+        Self {
+            size,
+            slide,
+            min_battery: 20,
+            min_signal: -90,
+        }
+    }
+
+    /// Raise an alert for readings below the battery level, in percent, or
+    /// the signal strength, in dBm
+    pub fn alert_below(mut self, battery_level: u8, signal_strength: i32) -> Self {
+        self.min_battery = battery_level;
+        self.min_signal = signal_strength;
+        self
+    }
+
+    pub fn build(self, data: DataStream<IoTData>) -> DataStream<WindowStats> {
+        let (min_battery, min_signal) = (self.min_battery, self.min_signal);
+        data.window(WindowConfig::sliding(self.size, self.slide))
+            .aggregate(HashMap::new(), move |mut stats: WindowStats, data| {
+                let entry = stats
+                    .entry(data.device_id.clone())
+                    .or_insert_with(|| DeviceStats {
+                        device_id: data.device_id.clone(),
+                        device_type: data.device_type.clone(),
+                        avg_value: 0.0,
+                        min_battery: data.battery_level,
+                        avg_signal: 0,
+                        alert_count: 0,
+                        last_update: data.timestamp,
+                    });
+
+                entry.avg_value = (entry.avg_value + data.value) / 2.0;
+                entry.min_battery = entry.min_battery.min(data.battery_level);
+                entry.avg_signal = (entry.avg_signal + data.signal_strength) / 2;
+                entry.last_update = data.timestamp;
+                if data.battery_level < min_battery || data.signal_strength < min_signal {
+                    entry.alert_count += 1;
+                }
+                stats
+            })
+    }
+}
+
+/// Decode a reading published over MQTT as a JSON object with the fields of
+/// [`IoTData`] except the timestamp, which is the time of arrival
+#[cfg(feature = "mqtt")]
+pub fn decode_reading(
+    message: fluxus_utils::mqtt::MqttMessage,
+) -> fluxus_utils::models::StreamResult<IoTData> {
+    use fluxus_utils::models::StreamError;
+
+    let value: serde_json::Value = serde_json::from_slice(&message.payload)
+        .map_err(|e| StreamError::Serialization(e.to_string()))?;
+    let field = |name: &str| {
+        value.get(name).ok_or_else(|| {
+            StreamError::Serialization(format!("reading on {} has no {}", message.topic, name))
+        })
+    };
+    let invalid = |name: &str| {
+        StreamError::Serialization(format!(
+            "reading on {} has an invalid {}",
+            message.topic, name
+        ))
+    };
+    Ok(IoTData {
+        device_id: field("device_id")?
+            .as_str()
+            .ok_or_else(|| invalid("device_id"))?
+            .to_string(),
+        device_type: field("device_type")?
+            .as_str()
+            .ok_or_else(|| invalid("device_type"))?
+            .to_string(),
+        value: field("value")?.as_f64().ok_or_else(|| invalid("value"))?,
+        battery_level: field("battery_level")?
+            .as_u64()
+            .and_then(|level| u8::try_from(level).ok())
+            .ok_or_else(|| invalid("battery_level"))?,
+        signal_strength: field("signal_strength")?
+            .as_i64()
+            .and_then(|signal| i32::try_from(signal).ok())
+            .ok_or_else(|| invalid("signal_strength"))?,
+        timestamp: SystemTime::now(),
+    })
+}
+
+/// Readings of five devices every 15 seconds, with draining batteries and a
+/// fluctuating signal
+pub fn sample_data() -> Vec<IoTData> {
+    let device_types = [
+        "Temperature Sensor",
+        "Humidity Sensor",
+        "Pressure Sensor",
+        "Light Sensor",
+    ];
+    let mut data = Vec::new();
+    let start_time = SystemTime::now();
+
+    for i in 0..100 {
+        for j in 1..=5 {
+            let device_type = device_types[j % device_types.len()];
+            let base_value = match device_type {
+                "Temperature Sensor" => 25.0,
+                "Humidity Sensor" => 60.0,
+                "Pressure Sensor" => 1013.0,
+                "Light Sensor" => 500.0,
+                _ => 0.0,
+            };
+
+            data.push(IoTData {
+                device_id: format!("DEV_{j:03}"),
+                device_type: device_type.to_string(),
+                value: base_value + (i as f64 * 0.1).sin() * 5.0,
+                battery_level: 100 - (i / 20) as u8,
+                signal_strength: -70 - (i % 30),
+                timestamp: start_time + Duration::from_secs(i as u64 * 15),
+            });
+        }
+    }
+
+    data
+}
@@ -0,0 +1,194 @@
+//! Per-service error rates and latencies of log events per sliding window
+
+use fluxus_api::DataStream;
+use fluxus_utils::window::WindowConfig;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct LogEvent {
+    pub service: String,
+    pub level: String,
+    pub message: String,
+    pub latency_ms: u64,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnomalyStats {
+    pub service: String,
+    pub error_rate: f64,
+    pub avg_latency: f64,
+    pub error_count: u32,
+    pub high_latency_count: u32,
+    pub total_events: u32,
+}
+
+/// Statistics of each service in a window, by service name
+pub type WindowStats = HashMap<String, AnomalyStats>;
+
+/// Aggregates the log events of each service per sliding window, counting
+/// `ERROR` events and events slower than a latency threshold
+#[derive(Debug, Clone)]
+pub struct LogAnomalies {
+    size: Duration,
+    slide: Duration,
+    high_latency_ms: u64,
+}
+
+impl Default for LogAnomalies {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(60), Duration::from_secs(10))
+    }
+}
+
+impl LogAnomalies {
+    pub fn new(size: Duration, slide: Duration) -> Self {
+        Self {
+            size,
+            slide,
+            high_latency_ms: 1000,
+        }
+    }
+
+    /// Count events slower than `latency_ms` as high latency events
+    pub fn high_latency_above(mut self, latency_ms: u64) -> Self {
+        self.high_latency_ms = latency_ms;
+        self
+    }
+
+    pub fn build(self, events: DataStream<LogEvent>) -> DataStream<WindowStats> {
+        let high_latency_ms = self.high_latency_ms;
+        events
+            .window(WindowConfig::sliding(self.size, self.slide))
+            .aggregate(HashMap::new(), move |mut stats: WindowStats, event| {
+                let entry = stats
+                    .entry(event.service.clone())
+                    .or_insert_with(|| AnomalyStats {
+                        service: event.service.clone(),
+                        error_rate: 0.0,
+                        avg_latency: 0.0,
+                        error_count: 0,
+                        high_latency_count: 0,
+                        total_events: 0,
+                    });
+
+                entry.total_events += 1;
+                entry.avg_latency = (entry.avg_latency * (entry.total_events - 1) as f64
+                    + event.latency_ms as f64)
+                    / entry.total_events as f64;
+                if event.level == "ERROR" {
+                    entry.error_count += 1;
+                }
+                if event.latency_ms > high_latency_ms {
+                    entry.high_latency_count += 1;
+                }
+                entry.error_rate = entry.error_count as f64 / entry.total_events as f64;
+                stats
+            })
+    }
+}
+
+/// A log event of a syslog message, from the service named by its
+/// application or host. Messages of severity error or worse are `ERROR`
+/// events and warnings `WARN` events; a latency is taken from a
+/// `latency_ms=120` or `latency=120ms` field in the message.
+#[cfg(feature = "fluxus-sources")]
+impl From<fluxus_sources::SyslogRecord> for LogEvent {
+    fn from(record: fluxus_sources::SyslogRecord) -> Self {
+        use fluxus_sources::syslog::Severity;
+
+        let level = match record.severity {
+            severity if severity.is_error() => "ERROR",
+            Severity::Warning => "WARN",
+            _ => "INFO",
+        };
+        let latency_ms = record
+            .message
+            .split_whitespace()
+            .find_map(|field| {
+                let field = field.trim_end_matches([',', ';']);
+                match field.strip_prefix("latency_ms=") {
+                    Some(value) => value.parse().ok(),
+                    None => field
+                        .strip_prefix("latency=")?
+                        .strip_suffix("ms")?
+                        .parse()
+                        .ok(),
+                }
+            })
+            .unwrap_or(0);
+        let timestamp = match record.timestamp.and_then(|ms| u64::try_from(ms).ok()) {
+            Some(ms) => SystemTime::UNIX_EPOCH + Duration::from_millis(ms),
+            None => SystemTime::now(),
+        };
+        Self {
+            service: record
+                .app_name
+                .or(record.hostname)
+                .unwrap_or_else(|| "unknown".to_string()),
+            level: level.to_string(),
+            message: record.message,
+            latency_ms,
+            timestamp,
+        }
+    }
+}
+
+/// Log events of four services every half second, with service-specific
+/// error probabilities and latencies
+pub fn sample_events() -> Vec<LogEvent> {
+    let services = [
+        "api-gateway",
+        "user-service",
+        "order-service",
+        "payment-service",
+    ];
+    let mut events = Vec::new();
+    let start_time = SystemTime::now();
+
+    for i in 0..200 {
+        for service in &services {
+            let error_prob = match *service {
+                "api-gateway" => 0.05,
+                "user-service" => 0.02,
+                "order-service" => 0.08,
+                "payment-service" => 0.03,
+                _ => 0.01,
+            };
+            let level = if rand_float() < error_prob {
+                "ERROR"
+            } else if rand_float() < 0.15 {
+                "WARN"
+            } else {
+                "INFO"
+            };
+            let base_latency = match *service {
+                "api-gateway" => 50,
+                "user-service" => 100,
+                "order-service" => 150,
+                "payment-service" => 200,
+                _ => 100,
+            };
+
+            events.push(LogEvent {
+                service: service.to_string(),
+                level: level.to_string(),
+                message: format!("Processing request #{i}"),
+                latency_ms: base_latency + (rand_float() * 1000.0) as u64,
+                timestamp: start_time + Duration::from_secs(i as u64 / 2),
+            });
+        }
+    }
+
+    events
+}
+
+/// A float between 0 and 1 taken from the clock
+fn rand_float() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .expect("System time cannot be earlier than UNIX epoch")
+        .subsec_nanos() as f64;
+    (nanos % 1000.0) / 1000.0
+}
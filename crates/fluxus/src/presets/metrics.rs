@@ -0,0 +1,36 @@
+//! Metrics of example runs written to JSON, for `--metrics-out`
+
+use fluxus_api::DataStream;
+use fluxus_core::{MetricValue, write_metrics_json};
+use fluxus_sinks::Sink;
+use fluxus_utils::models::StreamResult;
+use std::path::Path;
+use std::time::Instant;
+
+/// Write the stream to the sink like [`DataStream::sink`], then write the
+/// record counters of its stages and the run time of the job, as the
+/// `job.duration_ms` gauge, to `metrics_out` if given
+pub async fn sink_with_metrics<T, K>(
+    stream: DataStream<T>,
+    sink: K,
+    metrics_out: Option<&Path>,
+) -> StreamResult<()>
+where
+    T: Send + Sync + 'static,
+    K: Sink<T> + Send + Sync + 'static,
+{
+    let plan = stream.plan();
+    let started = Instant::now();
+    stream.sink(sink).await?;
+
+    if let Some(path) = metrics_out {
+        let mut metrics = plan.metrics();
+        metrics.insert(
+            "job.duration_ms".to_string(),
+            MetricValue::Gauge(started.elapsed().as_millis() as i64),
+        );
+        write_metrics_json(&metrics, path)?;
+        println!("Metrics written to {}", path.display());
+    }
+    Ok(())
+}
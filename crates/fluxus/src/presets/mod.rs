@@ -0,0 +1,33 @@
+//! Reference pipelines behind the examples.
+//!
+//! Each preset builds its pipeline onto any [`DataStream`](fluxus_api::DataStream)
+//! of its input type, so the same logic runs against a collection in a test
+//! or a real source, and comes with the sample data the examples use.
+//!
+//! ```rust
+//! use fluxus::api::{CollectionSink, CollectionSource, DataStream};
+//! use fluxus::presets::word_count::{self, WordCount};
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let source = CollectionSource::new(word_count::sample_lines());
+//!     let sink = CollectionSink::new();
+//!     WordCount::default()
+//!         .build(DataStream::new(source))
+//!         .sink(sink.clone())
+//!         .await
+//!         .unwrap();
+//!     assert_eq!(sink.get_data()[0]["hello"], 3);
+//! }
+//! ```
+
+pub mod click_stream;
+pub mod event_count;
+pub mod iot_devices;
+pub mod log_anomaly;
+#[cfg(all(feature = "fluxus-core", feature = "fluxus-sinks"))]
+pub mod metrics;
+pub mod network_log;
+pub mod stock_market;
+pub mod temperature;
+pub mod word_count;
@@ -0,0 +1,109 @@
+//! Per-path request statistics of HTTP access logs per sliding window
+
+use fluxus_api::DataStream;
+use fluxus_utils::window::WindowConfig;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub ip: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub bytes: u64,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct PathStats {
+    pub path: String,
+    pub total_requests: usize,
+    pub error_count: usize,
+    pub total_bytes: u64,
+    pub avg_response_size: f64,
+}
+
+impl PathStats {
+    /// Share of requests that failed, in percent
+    pub fn error_rate(&self) -> f64 {
+        self.error_count as f64 / self.total_requests as f64 * 100.0
+    }
+}
+
+/// Statistics of each path in a window, by path
+pub type WindowStats = HashMap<String, PathStats>;
+
+/// Aggregates the requests of each path per sliding window
+#[derive(Debug, Clone)]
+pub struct PathTraffic {
+    size: Duration,
+    slide: Duration,
+}
+
+impl Default for PathTraffic {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(60), Duration::from_secs(10))
+    }
+}
+
+impl PathTraffic {
+    pub fn new(size: Duration, slide: Duration) -> Self {
+        Self { size, slide }
+    }
+
+    pub fn build(self, logs: DataStream<LogEntry>) -> DataStream<WindowStats> {
+        logs.window(WindowConfig::sliding(self.size, self.slide))
+            .aggregate(HashMap::new(), |mut stats: WindowStats, log| {
+                let entry = stats.entry(log.path.clone()).or_insert_with(|| PathStats {
+                    path: log.path,
+                    total_requests: 0,
+                    error_count: 0,
+                    total_bytes: 0,
+                    avg_response_size: 0.0,
+                });
+
+                entry.total_requests += 1;
+                if log.status >= 400 {
+                    entry.error_count += 1;
+                }
+                entry.total_bytes += log.bytes;
+                entry.avg_response_size = entry.total_bytes as f64 / entry.total_requests as f64;
+                stats
+            })
+    }
+}
+
+/// Requests to four paths, four per second, with occasional 404 and 500 responses
+pub fn sample_logs() -> Vec<LogEntry> {
+    let start_time = SystemTime::now();
+    let mut logs = Vec::new();
+    let paths = ["/api/users", "/api/products", "/api/orders", "/health"];
+    let methods = ["GET", "POST", "PUT", "DELETE"];
+
+    for i in 0..200 {
+        let status = if i % 10 == 0 {
+            500
+        } else if i % 7 == 0 {
+            404
+        } else {
+            200
+        };
+        let bytes = if status == 200 {
+            1000 + (i % 5) * 500
+        } else {
+            100 + (i % 3) * 50
+        } as u64;
+
+        logs.push(LogEntry {
+            ip: format!("192.168.1.{}", i % 256),
+            method: methods[i % methods.len()].to_string(),
+            path: paths[i % paths.len()].to_string(),
+            status,
+            bytes,
+            timestamp: start_time + Duration::from_secs(i as u64 / 4),
+        });
+    }
+
+    logs
+}
@@ -0,0 +1,101 @@
+//! Per-symbol trading statistics per sliding window
+
+use fluxus_api::DataStream;
+use fluxus_utils::window::WindowConfig;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct StockTrade {
+    pub symbol: String,
+    pub price: f64,
+    pub volume: u64,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct StockStats {
+    pub symbol: String,
+    /// Volume weighted average price
+    pub vwap: f64,
+    pub total_volume: u64,
+    pub price_change: f64,
+    pub high: f64,
+    pub low: f64,
+}
+
+/// Statistics of each symbol in a window, by symbol
+pub type WindowStats = HashMap<String, StockStats>;
+
+/// Aggregates the trades of each symbol per sliding window
+#[derive(Debug, Clone)]
+pub struct TradeStats {
+    size: Duration,
+    slide: Duration,
+}
+
+impl Default for TradeStats {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(300), Duration::from_secs(60))
+    }
+}
+
+impl TradeStats {
+    pub fn new(size: Duration, slide: Duration) -> Self {
+        Self { size, slide }
+    }
+
+    pub fn build(self, trades: DataStream<StockTrade>) -> DataStream<WindowStats> {
+        trades
+            .window(WindowConfig::sliding(self.size, self.slide))
+            .aggregate(HashMap::new(), |mut stats: WindowStats, trade| {
+                let entry = stats
+                    .entry(trade.symbol.clone())
+                    .or_insert_with(|| StockStats {
+                        symbol: trade.symbol.clone(),
+                        vwap: 0.0,
+                        total_volume: 0,
+                        price_change: 0.0,
+                        high: trade.price,
+                        low: trade.price,
+                    });
+
+                let volume_price =
+                    (entry.vwap * entry.total_volume as f64) + (trade.price * trade.volume as f64);
+                entry.total_volume += trade.volume;
+                entry.vwap = volume_price / entry.total_volume as f64;
+                entry.high = entry.high.max(trade.price);
+                entry.low = entry.low.min(trade.price);
+                entry.price_change = entry.high - entry.low;
+                stats
+            })
+    }
+}
+
+/// Trades of four symbols every 30 seconds with fluctuating prices
+pub fn sample_trades() -> Vec<StockTrade> {
+    let symbols = ["AAPL", "GOOGL", "MSFT", "AMZN"];
+    let mut trades = Vec::new();
+    let start_time = SystemTime::now();
+
+    for i in 0..100 {
+        for symbol in &symbols {
+            let base_price = match *symbol {
+                "AAPL" => 150.0,
+                "GOOGL" => 2800.0,
+                "MSFT" => 300.0,
+                "AMZN" => 3300.0,
+                _ => 100.0,
+            };
+
+            trades.push(StockTrade {
+                symbol: symbol.to_string(),
+                price: base_price + (i as f64 * 0.1).sin() * 5.0,
+                volume: 100 + (i as u64 % 900),
+                timestamp: start_time + Duration::from_secs(i as u64 * 30),
+            });
+        }
+    }
+
+    trades
+}
@@ -0,0 +1,112 @@
+//! Per-sensor temperature and humidity statistics per tumbling window
+
+use fluxus_api::DataStream;
+use fluxus_utils::stats::Stats;
+use fluxus_utils::window::WindowConfig;
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone)]
+pub struct SensorReading {
+    pub sensor_id: String,
+    pub temperature: f64,
+    pub humidity: f64,
+    pub timestamp: SystemTime,
+}
+
+#[derive(Debug, Clone)]
+pub struct SensorStats {
+    pub sensor_id: String,
+    pub avg_temperature: f64,
+    pub avg_humidity: f64,
+    pub min_temperature: f64,
+    pub max_temperature: f64,
+    pub reading_count: usize,
+}
+
+impl SensorStats {
+    fn new(sensor_id: String, temperature: &Stats, humidity: &Stats) -> Self {
+        Self {
+            sensor_id,
+            avg_temperature: temperature.mean().unwrap_or_default(),
+            avg_humidity: humidity.mean().unwrap_or_default(),
+            min_temperature: temperature.min().unwrap_or_default(),
+            max_temperature: temperature.max().unwrap_or_default(),
+            reading_count: temperature.count() as usize,
+        }
+    }
+}
+
+/// Statistics of each sensor in a window, by sensor id
+pub type WindowStats = HashMap<String, SensorStats>;
+
+/// Temperature and humidity statistics of each sensor, by sensor id
+type Readings = HashMap<String, (Stats, Stats)>;
+
+/// Aggregates the readings of each sensor per tumbling window
+#[derive(Debug, Clone)]
+pub struct TemperatureStats {
+    window: Duration,
+}
+
+impl Default for TemperatureStats {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(10))
+    }
+}
+
+impl TemperatureStats {
+    pub fn new(window: Duration) -> Self {
+        Self { window }
+    }
+
+    pub fn build(self, readings: DataStream<SensorReading>) -> DataStream<WindowStats> {
+        readings
+            .window(WindowConfig::tumbling(self.window))
+            .aggregate(HashMap::new(), |mut readings: Readings, reading| {
+                let (temperature, humidity) = readings.entry(reading.sensor_id).or_default();
+                temperature.add(reading.temperature);
+                humidity.add(reading.humidity);
+                readings
+            })
+            .map(|readings| {
+                readings
+                    .into_iter()
+                    .map(|(id, (temperature, humidity))| {
+                        let stats = SensorStats::new(id.clone(), &temperature, &humidity);
+                        (id, stats)
+                    })
+                    .collect()
+            })
+    }
+}
+
+/// Readings of three sensors: one varying, one warming up and one fluctuating
+pub fn sample_readings() -> Vec<SensorReading> {
+    let start_time = SystemTime::now();
+    let mut readings = Vec::new();
+
+    for i in 0..100 {
+        let timestamp = start_time + Duration::from_secs(i as u64 / 10);
+        readings.push(SensorReading {
+            sensor_id: "sensor1".to_string(),
+            temperature: 20.0 + (i as f64 / 10.0).sin() * 2.0,
+            humidity: 50.0 + (i as f64 / 10.0).cos() * 5.0,
+            timestamp,
+        });
+        readings.push(SensorReading {
+            sensor_id: "sensor2".to_string(),
+            temperature: 22.0 + i as f64 * 0.1,
+            humidity: 55.0 + i as f64 * 0.2,
+            timestamp,
+        });
+        readings.push(SensorReading {
+            sensor_id: "sensor3".to_string(),
+            temperature: 25.0 + (i as f64 * 0.7).cos() * 3.0,
+            humidity: 60.0 + (i as f64 * 0.5).sin() * 4.0,
+            timestamp,
+        });
+    }
+
+    readings
+}
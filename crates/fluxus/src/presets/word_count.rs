@@ -0,0 +1,72 @@
+//! Word frequencies of lines of text per tumbling window
+
+use fluxus_api::DataStream;
+use fluxus_utils::window::WindowConfig;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Number of occurrences of each word in a window
+pub type WordCounts = HashMap<String, usize>;
+
+/// Counts the lowercased words of each line per tumbling window
+#[derive(Debug, Clone)]
+pub struct WordCount {
+    window: Duration,
+    prefix: Option<String>,
+}
+
+impl Default for WordCount {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1))
+    }
+}
+
+impl WordCount {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            prefix: None,
+        }
+    }
+
+    /// Only count the lines that start with `prefix`
+    pub fn lines_starting_with(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn build<L>(self, lines: DataStream<L>) -> DataStream<WordCounts>
+    where
+        L: AsRef<str> + Clone + Send + Sync + 'static,
+    {
+        let prefix = self.prefix;
+        lines
+            .filter(move |line| {
+                prefix
+                    .as_deref()
+                    .is_none_or(|prefix| line.as_ref().starts_with(prefix))
+            })
+            .map(|line| {
+                line.as_ref()
+                    .split_whitespace()
+                    .map(|s| s.to_lowercase())
+                    .collect::<Vec<_>>()
+            })
+            .window(WindowConfig::tumbling(self.window))
+            .aggregate(HashMap::new(), |mut counts, words| {
+                for word in words {
+                    *counts.entry(word).or_insert(0) += 1;
+                }
+                counts
+            })
+    }
+}
+
+pub fn sample_lines() -> Vec<&'static str> {
+    vec![
+        "hello world",
+        "hello stream processing",
+        "world of streaming",
+        "hello streaming world",
+    ]
+}
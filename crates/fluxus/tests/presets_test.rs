@@ -0,0 +1,126 @@
+#![cfg(all(feature = "fluxus-api", feature = "fluxus-utils"))]
+
+use fluxus::api::{CollectionSink, CollectionSource, DataStream};
+use fluxus::presets::{click_stream, event_count, network_log, temperature};
+
+#[tokio::test]
+async fn test_temperature_preset() {
+    let sink = CollectionSink::new();
+    temperature::TemperatureStats::default()
+        .build(DataStream::new(CollectionSource::new(
+            temperature::sample_readings(),
+        )))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+
+    let stats = sink.get_data();
+    let readings: usize = stats
+        .iter()
+        .flat_map(|window| window.values())
+        .map(|stats| stats.reading_count)
+        .sum();
+    assert_eq!(readings, 300);
+}
+
+#[tokio::test]
+async fn test_click_stream_preset_tracks_event_type() {
+    let sink = CollectionSink::new();
+    click_stream::ClickSessions::default()
+        .event_type("click")
+        .build(DataStream::new(CollectionSource::new(
+            click_stream::sample_clicks(),
+        )))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+
+    let sessions = sink.get_data();
+    assert_eq!(sessions.len(), 3);
+    let user1 = sessions
+        .iter()
+        .find(|session| session.user_id == "user1")
+        .unwrap();
+    assert_eq!(
+        user1.page_views,
+        vec!["home", "products", "cart", "checkout"]
+    );
+    assert_eq!(user1.total_events, 4);
+    assert_eq!(user1.duration_secs, 30);
+}
+
+#[tokio::test]
+async fn test_network_log_preset() {
+    let sink = CollectionSink::new();
+    network_log::PathTraffic::default()
+        .build(DataStream::new(CollectionSource::new(
+            network_log::sample_logs(),
+        )))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+
+    let stats = sink.get_last_element().unwrap();
+    let health = &stats["/health"];
+    assert_eq!(health.total_requests, 50);
+    assert!(health.error_rate() > 0.0);
+}
+
+#[tokio::test]
+async fn test_event_count_preset() {
+    let sink = CollectionSink::new();
+    event_count::EventCounter::new(std::time::Duration::from_secs(60))
+        .build(DataStream::new(CollectionSource::new(
+            event_count::sample_events(),
+        )))
+        .sink(sink.clone())
+        .await
+        .unwrap();
+
+    let counts: usize = sink.get_data().iter().flat_map(|c| c.values()).sum();
+    assert_eq!(counts, 9);
+}
+
+#[test]
+fn test_log_event_from_syslog_record() {
+    use fluxus::presets::log_anomaly::LogEvent;
+    use fluxus::sources::SyslogRecord;
+    use std::time::{Duration, SystemTime};
+
+    let event = LogEvent::from(SyslogRecord::parse(
+        "<11>1 2003-10-11T22:14:15.003Z host order-service - - - declined latency_ms=1250",
+    ));
+    assert_eq!(event.service, "order-service");
+    assert_eq!(event.level, "ERROR");
+    assert_eq!(event.latency_ms, 1250);
+    assert_eq!(
+        event.timestamp,
+        SystemTime::UNIX_EPOCH + Duration::from_millis(1_065_910_455_003)
+    );
+
+    let event = LogEvent::from(SyslogRecord::parse(
+        "<12>Oct 11 22:14:15 web1 gateway: slow latency=300ms",
+    ));
+    assert_eq!(event.service, "gateway");
+    assert_eq!(event.level, "WARN");
+    assert_eq!(event.latency_ms, 300);
+}
+
+#[cfg(all(feature = "fluxus-core", feature = "fluxus-sinks"))]
+#[tokio::test]
+async fn test_sink_with_metrics() {
+    let path = std::env::temp_dir().join(format!("fluxus-metrics-{}.json", std::process::id()));
+    let stream = DataStream::new(CollectionSource::new(vec![1, 2, 3])).name("numbers");
+    fluxus::presets::metrics::sink_with_metrics(stream, CollectionSink::new(), Some(&path))
+        .await
+        .unwrap();
+
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(
+        json["operator.numbers.records_out"],
+        serde_json::json!({"type": "counter", "value": 3})
+    );
+    assert_eq!(json["job.duration_ms"]["type"], "gauge");
+}
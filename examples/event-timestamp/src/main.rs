@@ -1,47 +1,31 @@
 use anyhow::Result;
-use fluxus::utils::{models::Record, window::WindowConfig};
-use fluxus::{
-    api::{
-        DataStream,
-        io::{CollectionSink, CollectionSource},
-    },
-    utils::time::current_time,
+use clap::Parser;
+use fluxus::api::{
+    DataStream,
+    io::{CollectionSink, CollectionSource},
 };
-use std::{collections::HashMap, time::Duration};
+use fluxus::presets::event_count::{self, EventCount, EventCounter};
+use fluxus::presets::metrics::sink_with_metrics;
+use std::path::PathBuf;
 
-pub type EventCount = HashMap<(String, i64), usize>;
+#[derive(Parser)]
+struct Args {
+    /// Write the metrics of the pipeline to this JSON file when it finishes
+    #[arg(long)]
+    metrics_out: Option<PathBuf>,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Create timestamped event data
-    let events = vec![
-        Record::with_timestamp("login".to_string(), get_timestamp(0)),
-        Record::with_timestamp("click".to_string(), get_timestamp(100)),
-        Record::with_timestamp("click".to_string(), get_timestamp(100)),
-        Record::with_timestamp("click".to_string(), get_timestamp(100)),
-        Record::with_timestamp("login".to_string(), get_timestamp(200)),
-        Record::with_timestamp("purchase".to_string(), get_timestamp(300)),
-        Record::with_timestamp("click".to_string(), get_timestamp(400)),
-        Record::with_timestamp("click".to_string(), get_timestamp(400)),
-        Record::with_timestamp("click".to_string(), get_timestamp(600)),
-    ];
+    let args = Args::parse();
 
-    // Create data source and sink
-    let source = CollectionSource::new(events);
+    // Create data source and sink from timestamped sample events
+    let source = CollectionSource::new(event_count::sample_events());
     let sink: CollectionSink<EventCount> = CollectionSink::new();
 
-    // Build and execute stream processing pipeline
-    DataStream::new(source)
-        // Create tumbling windows of 1 milliseconds
-        .window(WindowConfig::tumbling(Duration::from_millis(1)))
-        // Count events in each time window
-        .aggregate(HashMap::new(), |mut counts, event| {
-            *counts.entry((event.data, event.timestamp)).or_insert(0) += 1;
-            counts
-        })
-        // Write results to sink
-        .sink(sink.clone())
-        .await?;
+    // Count events in tumbling windows of 1 millisecond
+    let stream = EventCounter::default().build(DataStream::new(source));
+    sink_with_metrics(stream, sink.clone(), args.metrics_out.as_deref()).await?;
 
     // Print results
     println!("\nEvent counts by timestamp:");
@@ -56,9 +40,3 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
-
-// Helper function: Generate timestamp relative to current time
-fn get_timestamp(offset_ms: u64) -> i64 {
-    let now = current_time() as i64;
-    now + offset_ms as i64
-}
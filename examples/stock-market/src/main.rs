@@ -1,75 +1,31 @@
 use anyhow::Result;
+use clap::Parser;
 use fluxus::api::{
     DataStream,
     io::{CollectionSink, CollectionSource},
 };
-use fluxus::utils::window::WindowConfig;
-use std::{
-    collections::HashMap,
-    time::{Duration, SystemTime},
-};
-
-#[derive(Clone)]
-#[allow(dead_code)]
-pub struct StockTrade {
-    symbol: String,
-    price: f64,
-    volume: u64,
-    timestamp: SystemTime,
-}
-
-#[derive(Clone)]
-#[allow(dead_code)]
-pub struct StockStats {
-    symbol: String,
-    vwap: f64, // Volume Weighted Average Price
-    total_volume: u64,
-    price_change: f64,
-    high: f64,
-    low: f64,
+use fluxus::presets::metrics::sink_with_metrics;
+use fluxus::presets::stock_market::{self, TradeStats};
+use std::path::PathBuf;
+
+#[derive(Parser)]
+struct Args {
+    /// Write the metrics of the pipeline to this JSON file when it finishes
+    #[arg(long)]
+    metrics_out: Option<PathBuf>,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args = Args::parse();
+
     // Generate sample stock trading data
-    let trades = generate_sample_trades();
-    let source = CollectionSource::new(trades);
+    let source = CollectionSource::new(stock_market::sample_trades());
     let sink = CollectionSink::new();
 
-    // Build and execute stream processing pipeline
-    DataStream::new(source)
-        // Group by stock symbol
-        .map(|trade| (trade.symbol.clone(), trade))
-        // Create 5-minute sliding window with 1-minute slide
-        .window(WindowConfig::sliding(
-            Duration::from_secs(300), // 5 minutes
-            Duration::from_secs(60),  // 1 minute
-        ))
-        // Aggregate stock statistics within each window
-        .aggregate(HashMap::new(), |mut stats, (symbol, trade)| {
-            let entry = stats.entry(symbol.clone()).or_insert_with(|| StockStats {
-                symbol,
-                vwap: 0.0,
-                total_volume: 0,
-                price_change: 0.0,
-                high: trade.price,
-                low: trade.price,
-            });
-
-            // Update statistics
-            let volume_price =
-                (entry.vwap * entry.total_volume as f64) + (trade.price * trade.volume as f64);
-            entry.total_volume += trade.volume;
-            entry.vwap = volume_price / entry.total_volume as f64;
-            entry.high = entry.high.max(trade.price);
-            entry.low = entry.low.min(trade.price);
-            entry.price_change = entry.high - entry.low;
-
-            stats
-        })
-        // Output results to sink
-        .sink(sink.clone())
-        .await?;
+    // Aggregate trade statistics in 5-minute windows sliding every minute
+    let stream = TradeStats::default().build(DataStream::new(source));
+    sink_with_metrics(stream, sink.clone(), args.metrics_out.as_deref()).await?;
 
     // Print results
     println!("\nStock Market Statistics:");
@@ -84,34 +40,3 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
-
-// Generate sample trading data
-fn generate_sample_trades() -> Vec<StockTrade> {
-    let symbols = vec!["AAPL", "GOOGL", "MSFT", "AMZN"];
-    let mut trades = Vec::new();
-    let start_time = SystemTime::now();
-
-    for i in 0..100 {
-        for symbol in &symbols {
-            let base_price = match *symbol {
-                "AAPL" => 150.0,
-                "GOOGL" => 2800.0,
-                "MSFT" => 300.0,
-                "AMZN" => 3300.0,
-                _ => 100.0,
-            };
-
-            // Simulate price fluctuation
-            let price_variation = (i as f64 * 0.1).sin() * 5.0;
-            let trade = StockTrade {
-                symbol: symbol.to_string(),
-                price: base_price + price_variation,
-                volume: 100 + (i as u64 % 900),
-                timestamp: start_time + Duration::from_secs(i as u64 * 30), // Data point every 30 seconds
-            };
-            trades.push(trade);
-        }
-    }
-
-    trades
-}
@@ -1,55 +1,47 @@
 use anyhow::Result;
+use clap::Parser;
 use fluxus::api::{
     DataStream,
     io::{CollectionSink, CollectionSource},
 };
-use fluxus::utils::window::WindowConfig;
-use std::collections::HashMap;
-use std::time::Duration;
+use fluxus::presets::metrics::sink_with_metrics;
+use fluxus::presets::word_count::{self, WordCount, WordCounts};
+use fluxus::sources::SocketSource;
+use std::path::PathBuf;
 
-pub type WordCount = HashMap<String, usize>;
+#[derive(Parser)]
+struct Args {
+    /// Write the metrics of the pipeline to this JSON file when it finishes
+    #[arg(long)]
+    metrics_out: Option<PathBuf>,
+
+    /// Read the lines from this TCP server, as `host:port`, until it closes
+    /// the connection, instead of the sample text
+    #[arg(long)]
+    socket: Option<String>,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Sample input text
-    let text = vec![
-        "hello world",
-        "hello stream processing",
-        "world of streaming",
-        "hello streaming world",
-    ];
+    let args = Args::parse();
 
-    // Create a source from the text collection
-    let source = CollectionSource::new(text);
-    let sink: CollectionSink<WordCount> = CollectionSink::new();
+    let sink: CollectionSink<WordCounts> = CollectionSink::new();
 
-    // Build and execute the streaming pipeline
-    DataStream::new(source)
-        .filter(|line| line.starts_with("hello"))
-        // Split text into words
-        .map(|line| {
-            line.split_whitespace()
-                .map(|s| s.to_lowercase())
-                .collect::<Vec<_>>()
-        })
-        // Parallelize the processing
-        .parallel(2)
-        // Create tumbling windows of 1 second
-        .window(WindowConfig::tumbling(Duration::from_millis(1000)))
-        // Count words in each window
-        .aggregate(HashMap::new(), |mut counts, words| {
-            for word in words {
-                *counts.entry(word).or_insert(0) += 1;
-            }
-            counts
-        })
-        // Write results to sink
-        .sink(sink.clone())
-        .await?;
+    // Count the words of lines starting with "hello" in windows of 1 second,
+    // from the socket or the sample text
+    let word_count = WordCount::default().lines_starting_with("hello");
+    let stream = match args.socket {
+        Some(addr) => word_count.build(DataStream::new(SocketSource::connect(addr))),
+        None => {
+            let source = CollectionSource::new(word_count::sample_lines());
+            word_count.build(DataStream::new(source).parallel(2))
+        }
+    };
+    sink_with_metrics(stream, sink.clone(), args.metrics_out.as_deref()).await?;
 
     // Print the results
     println!("\nWord count last result:");
-    let last_result = sink.get_last_element().unwrap();
+    let last_result = sink.get_last_element().unwrap_or_default();
     let mut words: Vec<_> = last_result.iter().collect();
     words.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
     for (word, count) in words {